@@ -254,7 +254,7 @@ async fn test_concurrent_execution(registry: &SecurityToolRegistry) -> usize {
     let mut join_set = JoinSet::new();
     
     // Get ALL tools and test them concurrently
-    let all_tool_ids: Vec<String> = registry.tools().map(|t| t.id.clone()).collect();
+    let all_tool_ids: Vec<String> = registry.tools().into_iter().map(|t| t.id).collect();
     
     for tool_id in all_tool_ids {
         let registry = registry.clone();
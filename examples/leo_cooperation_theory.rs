@@ -17,7 +17,7 @@
 use spai::{
     AgentBuilder, AgentId, AgentMemory, BackgroundExecutor, CheckpointManager, LlmClient,
     MemoryBlock, MemoryConfig, OpenRouterClient, ReActConfig, ReasoningFormat, SharedMemoryManager,
-    SleepTimeAgent, SleepTimeConfig,
+    Role, SleepTimeAgent, SleepTimeConfig,
 };
 use std::sync::Arc;
 use uuid::Uuid;
@@ -333,21 +333,24 @@ Possible solutions:
         game_theorist_memory.agent_id,
         game_theorist_memory.clone(),
         SleepTimeConfig::default(),
-    );
+    )
+    .with_client(client.clone(), "tngtech/deepseek-r1t2-chimera:free");
     game_theorist_sleeptime.start().await?;
 
     let engineer_sleeptime = SleepTimeAgent::new(
         engineer_memory.agent_id,
         engineer_memory.clone(),
         SleepTimeConfig::default(),
-    );
+    )
+    .with_client(client.clone(), "tngtech/deepseek-r1t2-chimera:free");
     engineer_sleeptime.start().await?;
 
     let policy_sleeptime = SleepTimeAgent::new(
         policy_memory.agent_id,
         policy_memory.clone(),
         SleepTimeConfig::default(),
-    );
+    )
+    .with_client(client.clone(), "tngtech/deepseek-r1t2-chimera:free");
     policy_sleeptime.start().await?;
 
     // Create background executor for async execution
@@ -364,32 +367,33 @@ Possible solutions:
     // Execute agents in parallel using background executor
     println!("Starting parallel agent execution...\n");
 
-    let run_1 = executor
-        .execute_async(game_theorist.clone(), question_1.to_string())
-        .await?;
-    let run_2 = executor
-        .execute_async(engineer.clone(), question_1.to_string())
-        .await?;
-    let run_3 = executor
-        .execute_async(policy_analyst.clone(), question_1.to_string())
+    let runs = executor
+        .execute_batch(vec![
+            (game_theorist.clone(), question_1.to_string()),
+            (engineer.clone(), question_1.to_string()),
+            (policy_analyst.clone(), question_1.to_string()),
+        ])
         .await?;
 
     // Wait for all to complete
     println!("⏳ Waiting for agents to complete analysis...\n");
 
-    let result_1 = executor.wait_for_completion(run_1).await?;
-    let result_2 = executor.wait_for_completion(run_2).await?;
-    let result_3 = executor.wait_for_completion(run_3).await?;
+    let results = executor
+        .wait_for_all(runs)
+        .await
+        .into_iter()
+        .collect::<spai::Result<Vec<_>>>()?;
+    let (result_1, result_2, result_3) = (&results[0], &results[1], &results[2]);
 
     // Record responses in memory
     game_theorist_memory
-        .add_message("assistant".to_string(), result_1.content.clone())
+        .add_message(Role::Assistant, result_1.content.clone())
         .await;
     engineer_memory
-        .add_message("assistant".to_string(), result_2.content.clone())
+        .add_message(Role::Assistant, result_2.content.clone())
         .await;
     policy_memory
-        .add_message("assistant".to_string(), result_3.content.clone())
+        .add_message(Role::Assistant, result_3.content.clone())
         .await;
 
     println!("🎓 Dr. Chen (Game Theorist):");
@@ -423,28 +427,29 @@ Possible solutions:
                       that could incentivize cooperation. Consider both technical and \
                       regulatory approaches.";
 
-    let run_4 = executor
-        .execute_async(game_theorist.clone(), question_2.to_string())
-        .await?;
-    let run_5 = executor
-        .execute_async(engineer.clone(), question_2.to_string())
-        .await?;
-    let run_6 = executor
-        .execute_async(policy_analyst.clone(), question_2.to_string())
+    let runs = executor
+        .execute_batch(vec![
+            (game_theorist.clone(), question_2.to_string()),
+            (engineer.clone(), question_2.to_string()),
+            (policy_analyst.clone(), question_2.to_string()),
+        ])
         .await?;
 
-    let result_4 = executor.wait_for_completion(run_4).await?;
-    let result_5 = executor.wait_for_completion(run_5).await?;
-    let result_6 = executor.wait_for_completion(run_6).await?;
+    let results = executor
+        .wait_for_all(runs)
+        .await
+        .into_iter()
+        .collect::<spai::Result<Vec<_>>>()?;
+    let (result_4, result_5, result_6) = (&results[0], &results[1], &results[2]);
 
     game_theorist_memory
-        .add_message("assistant".to_string(), result_4.content.clone())
+        .add_message(Role::Assistant, result_4.content.clone())
         .await;
     engineer_memory
-        .add_message("assistant".to_string(), result_5.content.clone())
+        .add_message(Role::Assistant, result_5.content.clone())
         .await;
     policy_memory
-        .add_message("assistant".to_string(), result_6.content.clone())
+        .add_message(Role::Assistant, result_6.content.clone())
         .await;
 
     println!("🎓 Dr. Chen (Game Theorist):");
@@ -463,28 +468,29 @@ Possible solutions:
     let question_3 = "Synthesize the proposed solutions into a unified framework. \
                       What are the essential elements that must be included?";
 
-    let run_7 = executor
-        .execute_async(game_theorist.clone(), question_3.to_string())
-        .await?;
-    let run_8 = executor
-        .execute_async(engineer.clone(), question_3.to_string())
-        .await?;
-    let run_9 = executor
-        .execute_async(policy_analyst.clone(), question_3.to_string())
+    let runs = executor
+        .execute_batch(vec![
+            (game_theorist.clone(), question_3.to_string()),
+            (engineer.clone(), question_3.to_string()),
+            (policy_analyst.clone(), question_3.to_string()),
+        ])
         .await?;
 
-    let result_7 = executor.wait_for_completion(run_7).await?;
-    let result_8 = executor.wait_for_completion(run_8).await?;
-    let result_9 = executor.wait_for_completion(run_9).await?;
+    let results = executor
+        .wait_for_all(runs)
+        .await
+        .into_iter()
+        .collect::<spai::Result<Vec<_>>>()?;
+    let (result_7, result_8, result_9) = (&results[0], &results[1], &results[2]);
 
     game_theorist_memory
-        .add_message("assistant".to_string(), result_7.content.clone())
+        .add_message(Role::Assistant, result_7.content.clone())
         .await;
     engineer_memory
-        .add_message("assistant".to_string(), result_8.content.clone())
+        .add_message(Role::Assistant, result_8.content.clone())
         .await;
     policy_memory
-        .add_message("assistant".to_string(), result_9.content.clone())
+        .add_message(Role::Assistant, result_9.content.clone())
         .await;
 
     println!("🎓 Dr. Chen (Game Theorist):");
@@ -536,16 +542,16 @@ Possible solutions:
         &game_theorist_memory,
         "openrouter".to_string(),
         None,
-    )?;
+    ).await?;
 
-    checkpoint_manager.checkpoint(&engineer, &engineer_memory, "openrouter".to_string(), None)?;
+    checkpoint_manager.checkpoint(&engineer, &engineer_memory, "openrouter".to_string(), None).await?;
 
     checkpoint_manager.checkpoint(
         &policy_analyst,
         &policy_memory,
         "openrouter".to_string(),
         None,
-    )?;
+    ).await?;
 
     println!("✅ All agents checkpointed!");
 
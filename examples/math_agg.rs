@@ -11,11 +11,14 @@
 //! Run the scraper first: ./tools/mathoverflow_scraper --limit 5
 
 use spai::prelude::*;
+use spai::orchestrator::CritiqueTopology;
+use spai::LeanVerifyTool;
 use std::fs;
 use std::path::Path;
 use std::sync::Arc;
+use futures::future::join_all;
+use tokio::sync::Semaphore;
 use std::time::Instant;
-use std::process::Command;
 use serde::{Deserialize, Serialize};
 use chrono::Utc;
 
@@ -48,6 +51,8 @@ struct SolvedQuestion {
     lean_verified: bool,
     lean_errors: Option<String>,
     verification_attempts: u32,
+    #[serde(default)]
+    critique_topology: String,
     solved_at: String,
 }
 
@@ -221,6 +226,7 @@ fn build_prover_agent(
         .max_loops(3)
         .temperature(0.7)
         .client(client)
+        .tool(Arc::new(LeanVerifyTool::new()))
         .react_config(ReActConfig {
             enable_reasoning_traces: true,
             reasoning_format: ReasoningFormat::ThoughtAction,
@@ -296,82 +302,29 @@ struct LeanVerificationResult {
     output: String,
 }
 
-/// Check if Lean4 is available on the system
-fn check_lean_available() -> bool {
-    Command::new("lean")
-        .arg("--version")
-        .output()
-        .map(|o| o.status.success())
-        .unwrap_or(false)
-}
-
-/// Verify a Lean4 proof by writing to temp file and running lean
-fn verify_lean_proof(proof_code: &str, question_id: &str) -> LeanVerificationResult {
-    // Create temp directory for lean files
-    let temp_dir = std::env::temp_dir().join("mathoverflow_proofs");
-    if let Err(e) = fs::create_dir_all(&temp_dir) {
-        return LeanVerificationResult {
-            success: false,
-            errors: Some(format!("Failed to create temp dir: {}", e)),
-            output: String::new(),
-        };
+/// Verify a Lean4 proof with the same tool the provers call
+async fn verify_lean_proof(verifier: &LeanVerifyTool, proof_code: &str) -> LeanVerificationResult {
+    println!("🔍 Verifying Lean4 proof");
+    let result = verifier.verify(proof_code).await;
+    if result.success {
+        println!("   ✅ Lean4 verification PASSED");
+    } else {
+        let errors = result.error.clone().unwrap_or_default();
+        println!("   ❌ Lean4 verification FAILED");
+        println!("   Errors: {}", errors.lines().take(5).collect::<Vec<_>>().join("\n   "));
     }
-    
-    // Write proof to temp file
-    let proof_file = temp_dir.join(format!("{}.lean", question_id));
-    if let Err(e) = fs::write(&proof_file, proof_code) {
-        return LeanVerificationResult {
-            success: false,
-            errors: Some(format!("Failed to write proof file: {}", e)),
-            output: String::new(),
-        };
-    }
-    
-    println!("🔍 Verifying Lean4 proof: {:?}", proof_file);
-    
-    // Run lean4 on the file
-    let output = Command::new("lean")
-        .arg(&proof_file)
-        .output();
-    
-    match output {
-        Ok(result) => {
-            let stdout = String::from_utf8_lossy(&result.stdout).to_string();
-            let stderr = String::from_utf8_lossy(&result.stderr).to_string();
-            let combined_output = format!("{}\n{}", stdout, stderr);
-            
-            if result.status.success() {
-                println!("   ✅ Lean4 verification PASSED");
-                LeanVerificationResult {
-                    success: true,
-                    errors: None,
-                    output: combined_output,
-                }
+
+    LeanVerificationResult {
+        success: result.success,
+        // The fix round needs the full compiler output, not just the summary
+        errors: (!result.success).then(|| {
+            if result.content.is_empty() {
+                result.error.clone().unwrap_or_default()
             } else {
-                // Lean4 outputs errors to stdout, not stderr
-                let error_text = if !stdout.trim().is_empty() {
-                    stdout.trim().to_string()
-                } else {
-                    stderr.trim().to_string()
-                };
-                println!("   ❌ Lean4 verification FAILED");
-                println!("   Errors: {}", error_text.lines().take(5).collect::<Vec<_>>().join("\n   "));
-                LeanVerificationResult {
-                    success: false,
-                    errors: Some(error_text),
-                    output: combined_output,
-                }
+                result.content.clone()
             }
-        }
-        Err(e) => {
-            let error_msg = format!("Failed to run lean: {}", e);
-            println!("   ⚠️  {}", error_msg);
-            LeanVerificationResult {
-                success: false,
-                errors: Some(error_msg),
-                output: String::new(),
-            }
-        }
+        }),
+        output: result.content,
     }
 }
 
@@ -391,6 +344,7 @@ async fn prove_question(
     provers: &[Agent],
     proctor: &Agent,
     debate_rounds: usize,
+    topology: CritiqueTopology,
 ) -> anyhow::Result<SolvedQuestion> {
     let start = Instant::now();
     
@@ -458,26 +412,33 @@ Provide a complete Lean4 proof with:
         println!("⚔️  DEBATE ROUND {}\n", round);
         
         for (i, prover) in provers.iter().enumerate() {
-            // Each prover critiques the previous prover's work
-            let prev_idx = (i + provers.len() - 1) % provers.len();
-            let prev_output = &prover_outputs[prev_idx];
+            // The topology decides whose work this prover critiques
+            let targets = topology.targets(i, provers.len());
+            if targets.is_empty() {
+                continue;
+            }
+            let others = targets
+                .iter()
+                .map(|&t| prover_outputs[t].as_str())
+                .collect::<Vec<_>>()
+                .join("\n\n---\n\n");
             
             let critique_prompt = format!(
-                r#"Review and critique this proof attempt, then provide an improved proof:
+                r#"Review and critique these proof attempts, then provide an improved proof:
 
 {}
 
 Your original approach:
 {}
 
-Critique the other proof for:
+Critique the other proofs for:
 1. Correctness issues
 2. Missing cases
 3. Style improvements
 4. Better Lean4 idioms
 
 Then provide your improved proof addressing any issues you found."#,
-                prev_output, prover_outputs[i]
+                others, prover_outputs[i]
             );
             
             println!("🎓 {} (critique & improvement):\n", prover.name);
@@ -537,7 +498,8 @@ Format your response exactly as:
         parse_synthesis(&synthesis.content);
     
     // Step 5: Verify the Lean4 proof
-    let lean_available = check_lean_available();
+    let verifier = LeanVerifyTool::new().with_temp_dir(std::env::temp_dir().join("mathoverflow_proofs"));
+    let lean_available = verifier.is_available().await;
     let mut verification_result = LeanVerificationResult {
         success: false,
         errors: None,
@@ -545,6 +507,7 @@ Format your response exactly as:
     };
     let mut verification_attempts = 0u32;
     const MAX_VERIFICATION_ATTEMPTS: u32 = 3;
+    const MAX_CONCURRENT_FIXES: usize = 3;
     
     if lean_available && !lean_proof.is_empty() {
         println!("{}", "═".repeat(80));
@@ -553,7 +516,7 @@ Format your response exactly as:
         // Verification loop with retry
         while verification_attempts < MAX_VERIFICATION_ATTEMPTS {
             verification_attempts += 1;
-            verification_result = verify_lean_proof(&lean_proof, &question.id);
+            verification_result = verify_lean_proof(&verifier, &lean_proof).await;
             
             if verification_result.success {
                 break;
@@ -588,19 +551,38 @@ Provide the corrected Lean4 code."#,
                         errors, lean_proof
                     );
                     
+                    // Ask every prover concurrently; a failing prover is reported
+                    // but does not abort the rest of the batch
+                    let permits = Arc::new(Semaphore::new(MAX_CONCURRENT_FIXES));
+                    let fix_results = join_all(provers.iter().map(|prover| {
+                        let permits = permits.clone();
+                        let fix_request = &fix_request;
+                        async move {
+                            let _permit = permits.acquire().await.expect("semaphore closed");
+                            (prover, prover.react_loop(fix_request).await)
+                        }
+                    }))
+                    .await;
+
                     let mut fix_proposals = Vec::new();
-                    for prover in provers {
-                        print!("   ❓ Asking {}... ", prover.name);
-                        // In a real implementation we would run these in parallel
-                        // but sequential is fine for this example
-                        match prover.react_loop(&fix_request).await {
+                    let mut fix_errors = Vec::new();
+                    for (prover, result) in fix_results {
+                        match result {
                             Ok(resp) => {
-                                println!("✓");
+                                println!("   ✓ {} proposed a fix", prover.name);
                                 fix_proposals.push(format!("## {} Proposed Fix\n{}", prover.name, resp.content));
                             }
-                            Err(e) => println!("✗ (Error: {})", e),
+                            Err(e) => {
+                                println!("   ✗ {} failed: {}", prover.name, e);
+                                fix_errors.push(format!("{}: {}", prover.name, e));
+                            }
                         }
                     }
+
+                    if fix_proposals.is_empty() {
+                        println!("   ⚠️  No fix proposals received ({} errors), skipping consensus", fix_errors.len());
+                        continue;
+                    }
                     
                     println!("   🔮 Proctor synthesizing consensus fix...");
                     let consensus_prompt = format!(
@@ -678,6 +660,7 @@ Output ONLY the corrected Lean4 code in a code block."#,
         lean_verified: verification_result.success,
         lean_errors: verification_result.errors,
         verification_attempts,
+        critique_topology: topology.to_string(),
         solved_at: Utc::now().to_rfc3339(),
     })
 }
@@ -785,6 +768,12 @@ async fn main() -> anyhow::Result<()> {
         .and_then(|i| args.get(i + 1))
         .and_then(|s| s.parse().ok())
         .unwrap_or(2);
+    let topology: CritiqueTopology = args.iter()
+        .position(|a| a == "--topology")
+        .and_then(|i| args.get(i + 1))
+        .map(|s| s.parse())
+        .transpose()?
+        .unwrap_or_default();
     
     // Initialize OpenRouter client
     let client: Arc<dyn LlmClient> = match OpenRouterClient::from_env() {
@@ -834,6 +823,7 @@ async fn main() -> anyhow::Result<()> {
     
     println!("⚙️  Configuration:");
     println!("   • Debate rounds: {}", debate_rounds);
+    println!("   • Critique topology: {}", topology);
     println!("   • Debug mode: {}", debug);
     
     // Process each question
@@ -842,7 +832,7 @@ async fn main() -> anyhow::Result<()> {
     let mut failed_count = 0;
     
     for question in &questions_to_process {
-        match prove_question(question, &provers, &proctor, debate_rounds).await {
+        match prove_question(question, &provers, &proctor, debate_rounds, topology).await {
             Ok(solved) => {
                 save_solved(&solved, solved_dir)?;
                 if solved.lean_verified {
@@ -876,6 +866,7 @@ async fn main() -> anyhow::Result<()> {
                     lean_verified: false,
                     lean_errors: Some(e.to_string()),
                     verification_attempts: 0,
+                    critique_topology: topology.to_string(),
                     solved_at: Utc::now().to_rfc3339(),
                 };
                 save_solved(&partial, solved_dir)?;
@@ -209,21 +209,21 @@ async fn test_hierarchical_pattern(client: &Arc<dyn LlmClient>, registry: &Arc<S
     println!("✓ Loaded config: {:?}", config.pattern);
 
     // Build agents from config with tools
-    let (lead_agent, subagents) = match &config.pattern_config {
-        PatternSpecificConfig::Hierarchical { lead_agent, subagents } => {
+    let (lead_agent, subagents, aggregation) = match &config.pattern_config {
+        PatternSpecificConfig::Hierarchical { lead_agent, subagents, aggregation } => {
             let lead = build_agent_with_tools(lead_agent, client.clone(), registry)?;
             let subs: Vec<_> = subagents.generate_agents()
                 .iter()
                 .map(|cfg| build_agent_with_tools(cfg, client.clone(), registry))
                 .collect::<std::result::Result<Vec<_>, _>>()?;
-            (lead, subs)
+            (lead, subs, aggregation.clone().unwrap_or_default())
         }
         _ => return Err(anyhow::anyhow!("Expected Hierarchical config")),
     };
     
     println!("✓ Built lead agent + {} subagents from template", subagents.len());
 
-    let orchestrator = HierarchicalOrchestrator::new(lead_agent, subagents);
+    let orchestrator = HierarchicalOrchestrator::new(lead_agent, subagents).with_aggregation(aggregation);
     let result = orchestrator.execute(HIERARCHICAL_QUESTION).await?;
     
     println!("\nResult ({} agents, {} handoffs, {}ms):\n", 
@@ -283,23 +283,30 @@ async fn test_router_pattern(client: &Arc<dyn LlmClient>, registry: &Arc<Securit
     println!("✓ Loaded config: {:?}", config.pattern);
 
     // Build agents from config with tools
-    let (router_agent, specialists) = match &config.pattern_config {
-        PatternSpecificConfig::Router { router_agent, specialists } => {
+    let (router_agent, specialists, default_agent) = match &config.pattern_config {
+        PatternSpecificConfig::Router { router_agent, specialists, default_agent } => {
             let router = build_agent_with_tools(router_agent, client.clone(), registry)?;
             let specs: std::collections::HashMap<String, _> = specialists.iter()
                 .map(|(k, v)| Ok((k.clone(), build_agent_with_tools(v, client.clone(), registry)?)))
                 .collect::<anyhow::Result<_>>()?;
-            (router, specs)
+            let default = default_agent
+                .as_ref()
+                .map(|cfg| build_agent_with_tools(cfg, client.clone(), registry))
+                .transpose()?;
+            (router, specs, default)
         }
         _ => return Err(anyhow::anyhow!("Expected Router config")),
     };
     
     println!("✓ Built router + {} specialists from template", specialists.len());
 
-    let orchestrator = RouterOrchestrator::new(router_agent).with_specialists(specialists);
+    let mut orchestrator = RouterOrchestrator::new(router_agent).with_specialists(specialists);
+    if let Some(default_agent) = default_agent {
+        orchestrator = orchestrator.with_default_agent(default_agent);
+    }
     let result = orchestrator.execute(ROUTER_QUESTION).await?;
     
-    let routed_to = result.metadata.extra.get("routed_to")
+    let routed_to = result.metadata.extra.get("route")
         .and_then(|v| v.as_str())
         .unwrap_or("unknown");
     
@@ -322,19 +329,23 @@ async fn test_consensus_pattern(client: &Arc<dyn LlmClient>, registry: &Arc<Secu
     println!("✓ Loaded config: {:?}", config.pattern);
 
     // Build agents from config with tools
-    let (agents, threshold) = match &config.pattern_config {
-        PatternSpecificConfig::Consensus { agents, threshold } => {
+    let (agents, threshold, weights, tie_break) = match &config.pattern_config {
+        PatternSpecificConfig::Consensus { agents, threshold, tie_break, .. } => {
             let built: Vec<_> = agents.iter()
                 .map(|cfg| build_agent_with_tools(cfg, client.clone(), registry))
                 .collect::<std::result::Result<Vec<_>, _>>()?;
-            (built, *threshold)
+            let weights = agents.iter().map(|cfg| cfg.vote_weight.unwrap_or(1.0) as f64).collect();
+            (built, *threshold, weights, *tie_break)
         }
         _ => return Err(anyhow::anyhow!("Expected Consensus config")),
     };
     
     println!("✓ Built {} voters (threshold: {:.0}%)", agents.len(), threshold * 100.0);
 
-    let orchestrator = ConsensusOrchestrator::new(agents).with_threshold(threshold);
+    let orchestrator = ConsensusOrchestrator::new(agents)
+        .with_threshold(threshold)
+        .with_weights(weights)
+        .with_tie_break(tie_break);
     let result = orchestrator.execute(CONSENSUS_QUESTION).await?;
     
     let consensus_reached = result.metadata.extra.get("consensus_reached")
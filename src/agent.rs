@@ -1,16 +1,49 @@
 //! Agent implementation with ReAct loop
 
-use crate::config::ModelConfig;
+use crate::background::{RunEvent, RunEventType, SeqId};
+use crate::config::{ModelConfig, ProviderPreferences};
 use crate::error::{Error, Result};
-use crate::guardrails::{GuardrailContext, InputGuardrail, OutputGuardrail};
+use crate::guardrails::{BudgetGuardrail, GuardrailContext, GuardrailResult, InputGuardrail, InputObservationGuardrail, OutputGuardrail, SecretRedactor};
+use crate::handoffs::{HandoffContext, HandoffTool, HANDOFF_TOOL_ID};
+use crate::hitl::{
+    ActionType, ApprovalContext, ApprovalDecision, ApprovalHandler, ApprovalRequest, Priority, TimeoutDecision,
+};
 use crate::llm_client::LlmClient;
-use crate::openrouter::{CompletionRequest, Message};
-use crate::react::{Action, Observation, ReActConfig, ReActTrace, Thought};
-use crate::tools::{Tool, ToolContext};
-use crate::types::{AgentId, TokenUsage};
+use crate::memory::{AgentMemory, HistoryWindow, MemoryConfig, StorageBackend, TokenCounter, Tokenizer};
+use crate::metrics::Metrics;
+use crate::openrouter::{
+    Choice, CompletionRequest, CompletionResponse, ContentPart, Message, RateLimitInfo, ResponseFormat, Role,
+    ToolChoice, Usage,
+};
+use crate::output_transform::{apply_all, ExtractJsonObject, OutputTransform};
+use crate::prompt_log::{PromptLog, PromptRecord};
+use crate::react::{
+    split_reasoning, Action, Observation, ReActConfig, ReActTrace, ReasoningFormat, ReasoningTags, Thought,
+};
+use crate::tool_cache::{execute_cached, ToolCache};
+use crate::tool_protocol::{ParsedToolCall, ToolProtocol};
+use crate::tools::{compile_input_schema, validate_params, validate_params_with, ProgressSender, Tool, ToolContext, ToolOutput};
+use crate::types::{AgentId, TokenUsage, TraceId};
 use parking_lot::RwLock;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::field::{display, Empty};
+use tracing::Instrument;
+
+/// Default cap on concurrently running tool calls within one turn
+pub const DEFAULT_MAX_CONCURRENT_TOOLS: usize = 4;
+
+/// Default number of times a reply that fails the response schema is retried
+pub const DEFAULT_SCHEMA_RETRIES: u32 = 2;
+
+/// Longest an agent waits for an exhausted rate limit to reset before calling anyway
+pub const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60);
 
 /// Agent structure
 pub struct Agent<TContext = ()> {
@@ -26,22 +59,78 @@ pub struct Agent<TContext = ()> {
     pub tools: Vec<Arc<dyn Tool>>,
     /// Agents this agent can hand off to
     pub handoff_targets: Vec<AgentId>,
+    /// Agents reachable through the built-in handoff tool
+    pub handoff_agents: Vec<Arc<Agent>>,
     /// Input guardrails (run before processing)
     pub input_guardrails: Vec<Arc<dyn InputGuardrail>>,
     /// Output guardrails (run on final output)
     pub output_guardrails: Vec<Arc<dyn OutputGuardrail>>,
+    /// Observation guardrails (run on tool output before the model sees it)
+    pub observation_guardrails: Vec<Arc<dyn InputObservationGuardrail>>,
+    /// Transforms applied in order to the final answer
+    pub post_processors: Vec<Box<dyn OutputTransform>>,
+    /// Conversation memory whose history is replayed into each prompt
+    pub memory: Option<AgentMemory>,
+    /// How much of the memory history is included in each prompt
+    pub history_window: HistoryWindow,
+    /// Counts tokens for history windows, plans and turns whose response reports no usage
+    pub tokenizer: Arc<dyn Tokenizer>,
     /// Maximum reasoning loops before forcing completion
     pub max_loops: u32,
+    /// Token / cost ceiling checked before each model call
+    pub budget: Option<BudgetGuardrail>,
     /// Temperature for LLM sampling
     pub temperature: f32,
     /// ReAct configuration for this agent
     pub react_config: ReActConfig,
+    /// Reasoning delimiters stripped from responses into [`AgentOutput::reasoning`]
+    pub reasoning_tags: ReasoningTags,
+    /// How tool calls and results are exchanged with the model
+    pub tool_protocol: ToolProtocol,
+    /// Tool choice sent with native tool definitions (None = provider default)
+    pub tool_choice: Option<ToolChoice>,
+    /// Run all tool calls from one turn concurrently
+    pub parallel_tool_calls: bool,
+    /// Maximum tool calls running at once when `parallel_tool_calls` is set
+    pub max_concurrent_tools: usize,
+    /// JSON schema the final answer must match (sent as `response_format`)
+    ///
+    /// Answers are checked with the validator compiled from this schema at build time.
+    pub response_schema: Option<serde_json::Value>,
+    /// Corrective retries for final answers that fail the response schema
+    pub schema_retries: u32,
+    /// Limit on each model call; a timed-out turn is retried while loops remain
+    pub turn_timeout: Option<Duration>,
+    /// Limit on each tool call; a timed-out call becomes an error observation
+    pub tool_timeout: Option<Duration>,
     /// Shared context accessible across agent runs
     pub context: Arc<RwLock<TContext>>,
     /// Agent lifecycle hooks
     pub hooks: AgentHooks,
     /// LLM client (OpenRouter, vLLM, etc.)
     client: Arc<dyn LlmClient>,
+    /// Operational metrics sink
+    metrics: Arc<dyn Metrics>,
+    /// Capability tags advertised for routing and handoffs (lowercase)
+    capabilities: Vec<String>,
+    /// Audit sink for the exact requests sent to the model
+    prompt_log: Option<Arc<dyn PromptLog>>,
+    /// Redactor applied to prompt log records before they are persisted
+    prompt_log_redactor: Option<SecretRedactor>,
+    /// Memoized results of cacheable tools
+    tool_cache: Option<Arc<dyn ToolCache>>,
+    /// Where approval for gated tool calls is requested
+    approval_handler: Option<Arc<dyn ApprovalHandler>>,
+    /// Tool IDs or names whose calls need human approval
+    approval_tools: Vec<String>,
+    /// How long to wait for an approval decision, and what to do after
+    approval_timeout: Option<(Duration, TimeoutDecision)>,
+    /// Rate-limit state reported by the most recent model response
+    rate_limit: RwLock<Option<RateLimitInfo>>,
+    /// `response_schema` compiled when the agent was built
+    response_validator: Option<jsonschema::Validator>,
+    /// Input schema of each tool present at build time, by tool ID; `None` if it did not compile
+    tool_validators: HashMap<String, Option<jsonschema::Validator>>,
 }
 
 impl Agent<()> {
@@ -51,6 +140,35 @@ impl Agent<()> {
     }
 }
 
+impl<TContext> Agent<TContext> {
+    /// Capability tags this agent advertises
+    pub fn capabilities(&self) -> &[String] {
+        &self.capabilities
+    }
+
+    /// Whether this agent advertises a capability (case-insensitive)
+    pub fn has_capability(&self, tag: &str) -> bool {
+        self.capabilities.iter().any(|c| c.eq_ignore_ascii_case(tag))
+    }
+
+    /// Rate-limit state reported by the most recent model response
+    pub fn rate_limit(&self) -> Option<RateLimitInfo> {
+        self.rate_limit.read().clone()
+    }
+
+    /// How long to hold off the next model call, capped at [`MAX_RATE_LIMIT_WAIT`]
+    ///
+    /// `None` unless the last reported rate limit is exhausted and not yet reset.
+    pub fn rate_limit_backoff(&self) -> Option<Duration> {
+        self.rate_limit
+            .read()
+            .as_ref()
+            .and_then(RateLimitInfo::backoff)
+            .filter(|wait| !wait.is_zero())
+            .map(|wait| wait.min(MAX_RATE_LIMIT_WAIT))
+    }
+}
+
 impl<TContext> Agent<TContext>
 where
     TContext: Send + Sync + 'static,
@@ -63,71 +181,464 @@ where
         AgentBuilder::new()
     }
 
+    /// Metrics sink this agent reports to
+    pub fn metrics(&self) -> &Arc<dyn Metrics> {
+        &self.metrics
+    }
+
     /// Execute the ReAct loop for the given input
     pub async fn react_loop(&self, input: &str) -> Result<AgentOutput> {
+        self.run_with_metrics(input, &[], HandoffContext::new(input), None, None, None).await
+    }
+
+    /// Execute the ReAct loop with images attached to the input, for vision models
+    ///
+    /// Build the parts with [`ContentPart::image_url`] or
+    /// [`ContentPart::image_base64`], e.g. from a screenshot tool's output.
+    pub async fn react_loop_with_images(&self, input: &str, images: Vec<ContentPart>) -> Result<AgentOutput> {
+        self.run_with_metrics(input, &images, HandoffContext::new(input), None, None, None).await
+    }
+
+    /// Execute the ReAct loop until it finishes or `token` is cancelled
+    ///
+    /// Cancellation is checked between turns and aborts an in-flight model
+    /// call; the run then fails with [`Error::Cancelled`] carrying the output
+    /// produced so far. Agents this one hands off to observe the same token.
+    pub async fn react_loop_with_cancel(&self, input: &str, token: CancellationToken) -> Result<AgentOutput> {
+        self.run_with_metrics(input, &[], HandoffContext::new(input), None, None, Some(&token)).await
+    }
+
+    /// Execute the ReAct loop, forwarding tool progress updates to `progress`
+    pub async fn react_loop_with_progress(&self, input: &str, progress: ProgressSender) -> Result<AgentOutput> {
+        self.run_with_metrics(input, &[], HandoffContext::new(input), Some(&progress), None, None).await
+    }
+
+    /// Execute the ReAct loop, sending events to `tx` as they happen
+    ///
+    /// Emits a `Thought` event per turn, `ToolCall` / `ToolResult` events
+    /// around each tool execution, and a final `Output` event. When the client
+    /// supports [`LlmClient::stream`], assistant text is also sent as it is
+    /// generated, as `Thought` events carrying a `delta`; otherwise each thought
+    /// arrives whole. Turns that send native tool definitions are not streamed.
+    pub async fn react_loop_streaming(&self, input: &str, tx: mpsc::Sender<RunEvent>) -> Result<AgentOutput> {
+        let events = EventSink::new(tx);
+        let output = self
+            .run_with_metrics(input, &[], HandoffContext::new(input), None, Some(&events), None)
+            .await?;
+
+        let tool_calls = output
+            .trace
+            .actions
+            .iter()
+            .filter(|action| matches!(action, Action::ToolCall { .. }))
+            .count();
+        events
+            .emit(
+                RunEventType::Output,
+                serde_json::json!({ "content": output.content, "tool_calls": tool_calls }),
+            )
+            .await;
+        Ok(output)
+    }
+
+    /// Execute the ReAct loop synchronously on a fresh current-thread runtime
+    ///
+    /// Must not be called from within an existing Tokio runtime; see
+    /// [`crate::blocking`].
+    pub fn react_loop_blocking(&self, input: &str) -> Result<AgentOutput> {
+        crate::blocking::block_on(self.react_loop(input))?
+    }
+
+    /// Execute the ReAct loop synchronously on a caller-provided runtime
+    pub fn react_loop_blocking_on(&self, runtime: &tokio::runtime::Runtime, input: &str) -> Result<AgentOutput> {
+        crate::blocking::block_on_with(runtime, self.react_loop(input))?
+    }
+
+    /// Continue a task handed off by another agent
+    ///
+    /// Fails if this agent already appears in the context's handoff chain.
+    pub async fn receive_handoff(&self, ctx: HandoffContext) -> Result<AgentOutput> {
+        self.receive(ctx, None).await
+    }
+
+    /// Continue a handed-off task, observing the caller's cancellation token
+    async fn receive(&self, ctx: HandoffContext, cancel: Option<&CancellationToken>) -> Result<AgentOutput> {
+        if ctx.chain.contains(&self.id) {
+            return Err(Error::handoff(format!(
+                "Handoff cycle detected: '{}' has already handled this task",
+                self.name
+            )));
+        }
+
+        let input = ctx.to_prompt();
+        self.run_with_metrics(&input, &[], ctx, None, None, cancel).await
+    }
+
+    async fn run_with_metrics(
+        &self,
+        input: &str,
+        images: &[ContentPart],
+        inbound: HandoffContext,
+        progress: Option<&ProgressSender>,
+        events: Option<&EventSink>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<AgentOutput> {
+        let start = Instant::now();
+        let span = tracing::info_span!(
+            "react_loop",
+            agent = %self.name,
+            agent_id = %self.id,
+            model = %self.model.model,
+            run_id = Empty,
+            loops = Empty,
+            tokens.prompt = Empty,
+            tokens.completion = Empty,
+            success = Empty,
+        );
+        let result = self
+            .run_react_loop(input, images, inbound, progress, events, cancel)
+            .instrument(span.clone())
+            .await;
+        let loops = match &result {
+            Ok(output) => output.trace.iteration_count(),
+            Err(Error::MaxLoopsExceeded(max)) => *max as usize,
+            Err(Error::BudgetExceeded { partial, .. }) | Err(Error::Cancelled { partial }) => {
+                partial.trace.iteration_count()
+            }
+            Err(_) => 0,
+        };
+        span.record("loops", loops);
+        span.record("success", result.is_ok());
+        if let Ok(output) = &result {
+            span.record("tokens.prompt", output.trace.total_tokens.prompt_tokens);
+            span.record("tokens.completion", output.trace.total_tokens.completion_tokens);
+        }
+        self.metrics
+            .record_agent_run(&self.name, loops, start.elapsed(), result.is_ok());
+        result
+    }
+
+    async fn run_react_loop(
+        &self,
+        input: &str,
+        images: &[ContentPart],
+        inbound: HandoffContext,
+        progress: Option<&ProgressSender>,
+        events: Option<&EventSink>,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<AgentOutput> {
         // Check input guardrails
-        let guardrail_ctx = GuardrailContext::new(self.id);
+        let guardrail_ctx = GuardrailContext::new(self.id)
+            .with_data(crate::guardrails::INPUT_KEY, serde_json::json!(input));
+        let mut guarded_input = None;
+        let mut guardrail_results = Vec::new();
         for guardrail in &self.input_guardrails {
-            let result = guardrail.check(input, &guardrail_ctx).await?;
+            let mut result = guardrail
+                .check(guarded_input.as_deref().unwrap_or(input), &guardrail_ctx)
+                .await?;
             if !result.passed {
+                self.metrics.record_guardrail_block(guardrail.id());
                 return Err(Error::guardrail_violation(
                     guardrail.id(),
                     result.reasoning,
                 ));
             }
+            if let Some(modified) = result.suggested_modification.clone() {
+                guarded_input = Some(modified);
+            }
+            result.guardrail = guardrail.id().to_string();
+            guardrail_results.push(result);
         }
+        let input = guarded_input.as_deref().unwrap_or(input);
 
         let mut trace = ReActTrace::new();
-        let mut messages = vec![
-            Message::system(&self.system_prompt),
-            Message::user(input),
-        ];
+        if let Some(memory) = &self.memory {
+            if memory.needs_compaction().await {
+                memory.compact(self.client.as_ref(), &self.model.model).await?;
+            }
+        }
+        let (mut messages, history) = self.initial_messages(input, images).await;
+
+        let run_id = TraceId::new();
+        tracing::Span::current().record("run_id", display(run_id));
+        // Native reasoning marks a reasoning model, so Auto strips tags for models ignoring the flag
+        let reasoning_tags = match (&self.reasoning_tags, self.react_config.reasoning_format) {
+            (ReasoningTags::Auto, ReasoningFormat::NativeReasoning) => {
+                ReasoningTags::DEFAULT_TAGS.iter().map(|t| t.to_string()).collect()
+            }
+            (tags, _) => tags.tags_for(&self.model.model),
+        };
+        let mut reasoning = Vec::new();
+        let mut completed_calls: HashMap<String, Observation> = HashMap::new();
+        let mut schema_failures = 0;
+        for iteration in 0..self.max_loops {
+            let turn_span = tracing::info_span!(
+                "turn",
+                turn = iteration,
+                model = Empty,
+                tokens.prompt = Empty,
+                tokens.completion = Empty,
+            );
+
+            // Stop between turns once cancelled
+            if cancel.is_some_and(CancellationToken::is_cancelled) {
+                return Err(self.cancelled(trace, &reasoning));
+            }
+
+            // Stop before a call that could take the run over budget
+            if let Some(reason) = self.budget.as_ref().and_then(|budget| {
+                let next = TokenUsage::new(
+                    self.tokenizer.count_messages(&messages) as u64,
+                    self.react_config.max_reasoning_tokens as u64,
+                );
+                budget.check_next(&trace.total_tokens, &next, &self.model)
+            }) {
+                let mut partial = self.partial_output(trace, &reasoning);
+                partial.truncated_by_budget = true;
+                self.metrics.record_guardrail_block("budget");
+                return Err(Error::BudgetExceeded {
+                    reason,
+                    partial: Box::new(partial),
+                });
+            }
 
-        for _iteration in 0..self.max_loops {
             // THOUGHT: Generate reasoning about current state
-            let thought = self.generate_thought(&messages).await?;
+            let thought = self
+                .generate_thought(&messages, run_id, iteration, events)
+                .instrument(turn_span.clone());
+            let thought = match cancel {
+                Some(token) => tokio::select! {
+                    result = thought => result,
+                    _ = token.cancelled() => return Err(self.cancelled(trace, &reasoning)),
+                },
+                None => thought.await,
+            };
+            let (mut thought, mut response) = match thought {
+                // Record the timeout and retry the turn while loops remain
+                Err(Error::Timeout(message)) if iteration + 1 < self.max_loops => {
+                    trace.add_observation(Observation::error(format!("Timeout: {}", message)));
+                    continue;
+                }
+                result => result?,
+            };
+
+            // Keep reasoning-model chain-of-thought out of the answer and later prompts
+            if let Some(native) = response.reasoning.take() {
+                thought.reasoning = Some(native.clone());
+                reasoning.push(native);
+            }
+            if let (content, Some(stripped)) = split_reasoning(&response.content, &reasoning_tags) {
+                reasoning.push(stripped.clone());
+                thought.reasoning = Some(match thought.reasoning.take() {
+                    Some(native) => format!("{}\n\n{}", native, stripped),
+                    None => stripped,
+                });
+                thought.content = content.clone();
+                response.content = content;
+            }
             trace.add_thought(thought.clone());
+            emit(
+                events,
+                RunEventType::Thought,
+                serde_json::json!({ "turn": iteration, "content": thought.content }),
+            )
+            .await;
 
             // Parse the thought to determine the next action
-            let action = self.decide_action(&thought, &messages).await?;
+            let action = intercept_handoff(self.decide_action(&thought, &response).await?);
+
+            // An unparseable tool call is not an answer; ask for valid JSON instead
+            if matches!(action, Action::FinalAnswer { .. }) {
+                let malformed = self
+                    .tool_protocol
+                    .malformed_tool_calls(&response)
+                    .into_iter()
+                    .find(|call| self.tools.iter().any(|t| t.id() == call.tool_id));
+                if let Some(call) = malformed {
+                    let observation = Observation::error(format!(
+                        "Could not parse the arguments for '{}' as JSON ({}). \
+                         Retry the call with valid JSON arguments.",
+                        call.tool_id, call.error
+                    ));
+                    emit_result(events, &call.tool_id, &observation).await;
+                    trace.add_observation(observation.clone());
+                    let call = ParsedToolCall {
+                        id: call.id,
+                        tool_id: call.tool_id,
+                        params: serde_json::json!({}),
+                    };
+                    messages.push(self.tool_protocol.calls_message(&response, std::slice::from_ref(&call)));
+                    messages.push(self.tool_protocol.call_result_message(&call, &observation));
+                    continue;
+                }
+            }
+
+            // Run every call from this turn concurrently when enabled
+            if self.parallel_tool_calls && matches!(action, Action::ToolCall { .. }) {
+                let calls: Vec<ParsedToolCall> = self
+                    .tool_protocol
+                    .parse_tool_calls(&response)
+                    .into_iter()
+                    .filter(|call| {
+                        call.tool_id != HANDOFF_TOOL_ID && self.tools.iter().any(|t| t.id() == call.tool_id)
+                    })
+                    .collect();
+                if calls.len() > 1 {
+                    for call in &calls {
+                        trace.add_action(Action::tool_call(&call.tool_id, call.params.clone()));
+                        emit(
+                            events,
+                            RunEventType::ToolCall,
+                            serde_json::json!({ "tool_id": call.tool_id, "params": call.params }),
+                        )
+                        .await;
+                    }
+                    let observations = self
+                        .execute_tool_calls(&calls, progress, &mut completed_calls)
+                        .instrument(turn_span.clone())
+                        .await;
+
+                    // Echo only the calls answered below; skipped ones would be left without a reply
+                    messages.push(self.tool_protocol.calls_message(&response, &calls));
+                    for (call, observation) in calls.iter().zip(observations) {
+                        let observation = self.guard_observation(observation, &guardrail_ctx).await?;
+                        emit_result(events, &call.tool_id, &observation).await;
+                        trace.add_observation(observation.clone());
+                        messages.push(self.tool_protocol.call_result_message(call, &observation));
+                    }
+                    continue;
+                }
+            }
             trace.add_action(action.clone());
 
             match action {
                 Action::ToolCall { tool_id, params, .. } => {
-                    // Execute tool and capture observation
-                    let observation = self.execute_tool(&tool_id, params).await?;
+                    emit(
+                        events,
+                        RunEventType::ToolCall,
+                        serde_json::json!({ "tool_id": tool_id, "params": params }),
+                    )
+                    .await;
+
+                    // The call decide_action picked, so its ID (not the first one's) gets the reply
+                    let executed = self
+                        .tool_protocol
+                        .parse_tool_calls(&response)
+                        .into_iter()
+                        .find(|call| call.tool_id == tool_id && call.params == params);
+
+                    // Side-effecting calls that already succeeded this run are not repeated
+                    let key = self.idempotency_key(&tool_id, &params);
+                    let observation = match key.as_ref().and_then(|key| completed_calls.get(key)) {
+                        Some(previous) => replayed(previous),
+                        None => {
+                            let observation = self
+                                .execute_tool(&tool_id, params, progress)
+                                .instrument(turn_span.clone())
+                                .await?;
+                            if let Some(key) = key.filter(|_| !observation.is_error) {
+                                completed_calls.insert(key, observation.clone());
+                            }
+                            observation
+                        }
+                    };
+                    let observation = self.guard_observation(observation, &guardrail_ctx).await?;
+                    emit_result(events, &tool_id, &observation).await;
                     trace.add_observation(observation.clone());
 
-                    // Add tool result to messages
-                    messages.push(Message::assistant(&thought.content));
-                    messages.push(Message::user(&observation.content));
+                    // Add tool call and result to messages, echoing only the call that ran
+                    match &executed {
+                        Some(call) => {
+                            messages.push(self.tool_protocol.calls_message(&response, std::slice::from_ref(call)));
+                            messages.push(self.tool_protocol.call_result_message(call, &observation));
+                        }
+                        None => {
+                            messages.push(self.tool_protocol.call_message(&response));
+                            messages.push(self.tool_protocol.result_message(&response, &tool_id, &observation));
+                        }
+                    }
                 }
                 Action::Handoff { target_agent, reason, .. } => {
-                    // TODO: Implement handoff to another agent
-                    return Err(Error::handoff(format!(
-                        "Handoff to agent {} not yet implemented: {}",
-                        target_agent, reason
-                    )));
+                    let mut ctx = inbound
+                        .with_handler(self.id)
+                        .with_metadata("reason", serde_json::json!(reason));
+                    ctx.observations.extend(trace.observations.iter().cloned());
+                    let mut output = self
+                        .perform_handoff(&target_agent, &reason, ctx, trace, cancel)
+                        .instrument(turn_span.clone())
+                        .await?;
+                    guardrail_results.append(&mut output.guardrail_results);
+                    output.guardrail_results = guardrail_results;
+                    return Ok(output);
                 }
                 Action::FinalAnswer { answer, .. } => {
+                    // Structured answers must match the schema; ask again with the errors
+                    let answer = match &self.response_validator {
+                        Some(validator) => match check_structured(validator, &answer) {
+                            Ok(json) => json,
+                            Err(message) if schema_failures < self.schema_retries => {
+                                schema_failures += 1;
+                                messages.push(Message::assistant(&response.content));
+                                messages.push(Message::user(format!(
+                                    "That reply does not match the required JSON schema ({}). \
+                                     Reply with only the corrected JSON.",
+                                    message
+                                )));
+                                continue;
+                            }
+                            Err(message) => return Err(Error::SchemaValidation { message, raw: answer }),
+                        },
+                        None => answer,
+                    };
+
                     // Complete the loop with final output
                     trace.complete();
-                    let output = AgentOutput {
+                    let metadata = match history {
+                        Some(summary) => serde_json::json!({ "history_window": summary }),
+                        None => serde_json::json!({}),
+                    };
+                    let mut output = AgentOutput {
                         agent_id: self.id,
-                        content: answer,
+                        content: apply_all(&self.post_processors, &answer),
+                        raw_content: answer,
+                        reasoning: self.exposed_reasoning(&reasoning),
+                        truncated_by_budget: false,
+                        cancelled: false,
                         trace,
-                        metadata: serde_json::json!({}),
+                        metadata,
+                        guardrail_results,
                     };
 
                     // Check output guardrails
                     for guardrail in &self.output_guardrails {
-                        let result = guardrail.check(&output, &guardrail_ctx).await?;
+                        let mut result = guardrail.check(&output, &guardrail_ctx).await?;
                         if !result.passed {
+                            self.metrics.record_guardrail_block(guardrail.id());
                             return Err(Error::guardrail_violation(
                                 guardrail.id(),
                                 result.reasoning,
                             ));
                         }
+                        if result.redactions > 0 {
+                            tracing::warn!(
+                                guardrail = guardrail.id(),
+                                redactions = result.redactions,
+                                "Redacted agent output"
+                            );
+                            output.metadata["redactions"][guardrail.id()] = result.redactions.into();
+                        }
+                        if let Some(content) = result.suggested_modification.clone() {
+                            output.content = content;
+                        }
+                        result.guardrail = guardrail.id().to_string();
+                        output.guardrail_results.push(result);
+                    }
+
+                    if let Some(memory) = &self.memory {
+                        memory.add_message(Role::User, input.to_string()).await;
+                        memory
+                            .add_message(Role::Assistant, output.content.clone())
+                            .await;
                     }
 
                     return Ok(output);
@@ -140,30 +651,273 @@ where
         Err(Error::MaxLoopsExceeded(self.max_loops))
     }
 
+    /// First-turn prompt: system prompt with tool instructions, history window, then input
+    ///
+    /// Also returns the history window summary when the agent has memory.
+    async fn initial_messages(&self, input: &str, images: &[ContentPart]) -> (Vec<Message>, Option<serde_json::Value>) {
+        let system_prompt = match self.tool_protocol.instructions(&self.tools) {
+            Some(instructions) => format!("{}\n\n{}", self.system_prompt, instructions),
+            None => self.system_prompt.clone(),
+        };
+        let mut messages = vec![Message::system(system_prompt)];
+        let history = match &self.memory {
+            Some(memory) => {
+                let selection = memory
+                    .select_history(self.history_window, self.tokenizer.as_ref())
+                    .await;
+                messages.extend(selection.messages.iter().map(|entry| match entry.role {
+                    Role::Assistant => Message::assistant(&entry.content),
+                    Role::System => Message::system(&entry.content),
+                    _ => Message::user(&entry.content),
+                }));
+                Some(selection.summary())
+            }
+            None => None,
+        };
+        let mut message = Message::user(input);
+        message.parts.extend_from_slice(images);
+        messages.push(message);
+        (messages, history)
+    }
+
+    /// Estimate the cost of running `input` without calling the model
+    ///
+    /// Counts the first turn's prompt (system prompt, tool definitions,
+    /// history window and input) with the agent's [`Tokenizer`] and assumes every
+    /// turn uses the full completion allowance. Later turns also carry tool
+    /// results, so treat the per-turn figure as a lower bound. Memory that
+    /// is due for compaction is counted uncompacted.
+    pub async fn plan(&self, input: &str) -> PlanEstimate {
+        let counter = self.tokenizer.as_ref();
+        let (messages, _) = self.initial_messages(input, &[]).await;
+        let mut prompt_tokens = counter.count_messages(&messages);
+        if let Some(tools) = self.tool_protocol.tool_definitions(&self.tools) {
+            prompt_tokens += counter.count_tokens(&serde_json::to_string(&tools).unwrap_or_default());
+        }
+        if let Some(schema) = &self.response_schema {
+            prompt_tokens += counter.count_tokens(&schema.to_string());
+        }
+
+        let turn = TokenUsage::new(
+            prompt_tokens as u64,
+            self.react_config.max_reasoning_tokens as u64,
+        );
+        let cost_per_turn_usd = self.model.cost_usd(&turn);
+        PlanEstimate {
+            agent_name: self.name.clone(),
+            model: self.model.model.clone(),
+            prompt_tokens: turn.prompt_tokens,
+            max_completion_tokens: turn.completion_tokens,
+            max_loops: self.max_loops,
+            cost_per_turn_usd,
+            estimated_cost_usd: cost_per_turn_usd.map(|cost| cost * self.max_loops as f64),
+        }
+    }
+
+    /// Output of a run stopped early, ending at the latest thought
+    fn partial_output(&self, mut trace: ReActTrace, reasoning: &[String]) -> AgentOutput {
+        trace.complete();
+        let content = trace.thoughts.last().map(|t| t.content.clone()).unwrap_or_default();
+        let mut partial = AgentOutput::new(self.id, content, trace);
+        partial.reasoning = self.exposed_reasoning(reasoning);
+        partial
+    }
+
+    /// Reasoning to surface in [`AgentOutput::reasoning`], if exposed
+    fn exposed_reasoning(&self, reasoning: &[String]) -> Option<String> {
+        (self.react_config.expose_reasoning && !reasoning.is_empty()).then(|| reasoning.join("\n\n"))
+    }
+
+    /// Error for a run stopped by its cancellation token
+    fn cancelled(&self, trace: ReActTrace, reasoning: &[String]) -> Error {
+        let mut partial = self.partial_output(trace, reasoning);
+        partial.cancelled = true;
+        Error::Cancelled {
+            partial: Box::new(partial),
+        }
+    }
+
     /// Generate a thought based on the current state
-    async fn generate_thought(&self, messages: &[Message]) -> Result<Thought> {
-        let request = CompletionRequest::new(&self.model.model, messages.to_vec())
+    async fn generate_thought(
+        &self,
+        messages: &[Message],
+        run_id: TraceId,
+        turn: u32,
+        events: Option<&EventSink>,
+    ) -> Result<(Thought, Message)> {
+        let mut request = CompletionRequest::new(&self.model.model, messages.to_vec())
             .with_temperature(self.temperature)
-            .with_max_tokens(self.react_config.max_reasoning_tokens);
+            .with_max_tokens(self.react_config.max_reasoning_tokens)
+            .with_fallback_models(self.model.fallback_models.clone());
+        if let Some(preferences) = &self.model.provider_preferences {
+            request = request.with_provider(preferences.clone());
+        }
+        if matches!(self.react_config.reasoning_format, ReasoningFormat::NativeReasoning) {
+            request = request.with_include_reasoning(true);
+        }
+        if let Some(schema) = &self.response_schema {
+            request = request.with_response_format(ResponseFormat::json_schema("response", schema.clone()));
+        }
+        if let Some(tools) = self.tool_protocol.tool_definitions(&self.tools) {
+            request = request.with_tools(tools);
+            match &self.tool_choice {
+                Some(ToolChoice::Required | ToolChoice::Named(_)) if turn > 0 => {}
+                Some(choice) => request = request.with_tool_choice(choice.clone()),
+                None => {}
+            }
+        }
 
-        let response = self.client.complete(request).await?;
+        // Wait out an exhausted rate limit rather than spend the call on a 429
+        if let Some(wait) = self.rate_limit_backoff() {
+            tracing::debug!(agent = %self.name, ?wait, "Rate limit exhausted, waiting for reset");
+            tokio::time::sleep(wait).await;
+        }
+
+        let start = Instant::now();
+        let audit_request = self.prompt_log.as_ref().map(|_| request.clone());
+        let response = async {
+            match events {
+                Some(events) if request.tools.is_none() => self.stream_completion(request, turn, events).await,
+                _ => self.client.complete(request).await,
+            }
+        };
+        let response = match self.turn_timeout {
+            Some(limit) => tokio::time::timeout(limit, response).await.unwrap_or_else(|_| {
+                Err(Error::Timeout(format!("model call on turn {} exceeded {:?}", turn, limit)))
+            }),
+            None => response.await,
+        };
+        self.metrics
+            .record_llm_request(&self.model.model, start.elapsed(), response.is_ok());
+
+        if let (Some(log), Some(request)) = (&self.prompt_log, audit_request) {
+            let mut record = PromptRecord {
+                run_id,
+                turn,
+                agent_id: self.id,
+                agent_name: self.name.clone(),
+                timestamp: chrono::Utc::now(),
+                request,
+                usage: response.as_ref().ok().map(|r| TokenUsage::from(r.usage.clone())),
+                error: response.as_ref().err().map(|e| e.to_string()),
+            };
+            if let Some(redactor) = &self.prompt_log_redactor {
+                record.redact(redactor);
+            }
+            log.record(&record).await?;
+        }
+        let response = response?;
+        if let Some(limit) = &response.rate_limit {
+            *self.rate_limit.write() = Some(limit.clone());
+        }
 
-        let content = response
+        let message = response
             .choices
             .first()
-            .map(|choice| choice.message.content.clone())
-            .unwrap_or_default();
+            .map(|choice| choice.message.clone())
+            .unwrap_or_else(|| Message::assistant(""));
+
+        let mut tokens = TokenUsage::from(response.usage);
+        // Some servers omit usage; estimate it so budgets and metrics still see the turn
+        if tokens.total_tokens == 0 {
+            tokens = TokenUsage::new(
+                self.tokenizer.count_messages(messages) as u64,
+                self.tokenizer.count_tokens(&message.content) as u64,
+            );
+        }
+        // Attribute usage to the model that answered, which may be a fallback
+        self.metrics.record_tokens(&response.model, &tokens);
+        let span = tracing::Span::current();
+        span.record("model", response.model.as_str());
+        span.record("tokens.prompt", tokens.prompt_tokens);
+        span.record("tokens.completion", tokens.completion_tokens);
+
+        Ok((Thought::new(&message.content).with_tokens(tokens), message))
+    }
+
+    /// Stream a completion, forwarding text deltas as `Thought` events
+    ///
+    /// Falls back to a plain completion when the client cannot stream.
+    async fn stream_completion(
+        &self,
+        request: CompletionRequest,
+        turn: u32,
+        events: &EventSink,
+    ) -> Result<CompletionResponse> {
+        let mut stream = match self.client.stream(request.clone()).await {
+            Ok(stream) => stream,
+            Err(_) => return self.client.complete(request).await,
+        };
 
-        let tokens = TokenUsage::from(response.usage);
+        let mut response = CompletionResponse {
+            id: String::new(),
+            model: request.model,
+            choices: Vec::new(),
+            usage: Usage::default(),
+            retries: 0,
+            rate_limit: None,
+        };
+        let mut content = String::new();
+        let mut reasoning = String::new();
+        let mut finish_reason = None;
+        while let Some(chunk) = stream.next_chunk().await {
+            let chunk = chunk?;
+            response.id = chunk.id;
+            response.model = chunk.model;
+            if let Some(usage) = chunk.usage {
+                response.usage = usage;
+            }
+            for choice in chunk.choices.into_iter().filter(|choice| choice.index == 0) {
+                if let Some(delta) = choice.delta.content.filter(|delta| !delta.is_empty()) {
+                    events
+                        .emit(RunEventType::Thought, serde_json::json!({ "turn": turn, "delta": delta }))
+                        .await;
+                    content.push_str(&delta);
+                }
+                if let Some(delta) = choice.delta.reasoning {
+                    reasoning.push_str(&delta);
+                }
+                finish_reason = choice.finish_reason.or(finish_reason);
+            }
+        }
 
-        Ok(Thought::new(content).with_tokens(tokens))
+        let mut message = Message::assistant(content);
+        message.reasoning = (!reasoning.trim().is_empty()).then_some(reasoning);
+        response.choices.push(Choice {
+            index: 0,
+            message,
+            finish_reason,
+        });
+        Ok(response)
     }
 
     /// Decide the next action based on the thought
-    async fn decide_action(&self, thought: &Thought, _messages: &[Message]) -> Result<Action> {
+    async fn decide_action(&self, thought: &Thought, response: &Message) -> Result<Action> {
         // Simple parsing logic - in production, this would be more sophisticated
         let content = thought.content.to_lowercase();
 
+        // Check for a handoff(target_role, reason) call
+        if !self.handoff_agents.is_empty() {
+            if let Some(action) = parse_handoff_call(&thought.content) {
+                return Ok(action);
+            }
+        }
+
+        // Check for a tool call in this agent's protocol (known tools only)
+        if let Some(call) = self
+            .tool_protocol
+            .parse_tool_calls(response)
+            .into_iter()
+            .find(|call| self.tools.iter().any(|t| t.id() == call.tool_id))
+        {
+            return Ok(Action::tool_call(call.tool_id, call.params));
+        }
+
+        // Structured replies are the answer as a whole
+        if self.response_schema.is_some() {
+            return Ok(Action::final_answer(&thought.content));
+        }
+
         // Check for final answer
         if content.contains("final answer:") || content.contains("answer:") {
             // Extract the answer after "final answer:" or "answer:"
@@ -178,34 +932,89 @@ where
             return Ok(Action::final_answer(answer));
         }
 
-        // Check for tool calls
-        if content.contains("action:") {
-            // Simple parsing - in production, would use function calling
-            if let Some(tool) = self.tools.first() {
-                return Ok(Action::tool_call(
-                    tool.id(),
-                    serde_json::json!({ "message": "test" }),
-                ));
-            }
-        }
-
         // Default to final answer if no action detected
         Ok(Action::final_answer(&thought.content))
     }
 
     /// Execute a tool with the given parameters
-    async fn execute_tool(&self, tool_id: &str, params: serde_json::Value) -> Result<Observation> {
+    async fn execute_tool(
+        &self,
+        tool_id: &str,
+        params: serde_json::Value,
+        progress: Option<&ProgressSender>,
+    ) -> Result<Observation> {
+        let span = tracing::info_span!("tool_call", tool = tool_id, success = Empty, from_cache = Empty);
+        let observation = self.run_tool(tool_id, params, progress).instrument(span.clone()).await;
+        span.record("success", matches!(&observation, Ok(o) if !o.is_error));
+        if let Ok(observation) = &observation {
+            span.record("from_cache", observation.from_cache);
+        }
+        observation
+    }
+
+    /// Look up, gate and run a tool inside the span opened by `execute_tool`
+    async fn run_tool(
+        &self,
+        tool_id: &str,
+        mut params: serde_json::Value,
+        progress: Option<&ProgressSender>,
+    ) -> Result<Observation> {
         let tool = self
             .tools
             .iter()
             .find(|t| t.id() == tool_id)
             .ok_or_else(|| Error::tool_execution(tool_id, "Tool not found"))?;
 
-        let ctx = ToolContext::new(self.id);
-        let output = tool.execute(params, &ctx).await?;
+        let mut ctx = ToolContext::new(self.id);
+        if let Some(progress) = progress {
+            ctx = ctx.with_progress(tool_id, progress.clone());
+        }
+        if let Some(cache) = &self.tool_cache {
+            ctx = ctx.with_cache(cache.clone());
+        }
+        if let Some(memory) = &self.memory {
+            ctx = ctx.with_memory(memory.clone());
+        }
+        if let Some(on_tool_start) = &self.hooks.on_tool_start {
+            if let Err(e) = on_tool_start(&ctx, tool_id, &mut params) {
+                return Ok(Observation::error(format!("Call to {} aborted by hook: {}", tool_id, e)));
+            }
+        }
+        // Bad arguments come back to the model as a failed call, not a tool error
+        let validation = match self.tool_validators.get(tool_id) {
+            Some(validator) => validate_params_with(tool.as_ref(), validator.as_ref(), &params),
+            // Added to `tools` after the agent was built
+            None => validate_params(tool.as_ref(), &params),
+        };
+        if validation.is_ok() {
+            if let Some(denied) = self.request_tool_approval(tool.as_ref(), &params).await? {
+                return Ok(denied);
+            }
+        }
+
+        let start = Instant::now();
+        let output = match validation {
+            Ok(()) => match self.tool_timeout {
+                Some(limit) => tokio::time::timeout(limit, execute_cached(tool.as_ref(), params, &ctx))
+                    .await
+                    .unwrap_or_else(|_| Ok(ToolOutput::failure(format!("Timed out after {:?}", limit)))),
+                None => execute_cached(tool.as_ref(), params, &ctx).await,
+            },
+            Err(invalid) => Ok(invalid),
+        };
+        let success = matches!(&output, Ok(o) if o.success);
+        self.metrics.record_tool_call(tool_id, start.elapsed(), success);
+        let output = output?;
+        if let Some(on_tool_end) = &self.hooks.on_tool_end {
+            if let Err(e) = on_tool_end(&ctx, tool_id, &output) {
+                tracing::warn!(tool_id, "on_tool_end hook failed: {}", e);
+            }
+        }
 
         if output.success {
-            Ok(Observation::new(&output.content))
+            let mut observation = Observation::new(&output.content);
+            observation.from_cache = output.from_cache;
+            Ok(observation)
         } else {
             Ok(Observation::error(
                 output.error.unwrap_or_else(|| "Unknown error".to_string()),
@@ -213,13 +1022,327 @@ where
         }
     }
 
+    /// Ask for approval of a gated tool call, returning the observation to record if it was not approved
+    ///
+    /// A handler that fails counts as a denial, so the tool is not run and the
+    /// model sees why instead of the run aborting.
+    async fn request_tool_approval(
+        &self,
+        tool: &dyn Tool,
+        params: &serde_json::Value,
+    ) -> Result<Option<Observation>> {
+        let gated = self
+            .approval_tools
+            .iter()
+            .any(|name| name == tool.id() || name == tool.name());
+        let handler = match &self.approval_handler {
+            Some(handler) if gated => handler,
+            _ => return Ok(None),
+        };
+
+        let request = ApprovalRequest {
+            id: crate::types::ApprovalId::new(),
+            agent_id: self.id,
+            action_type: ActionType::ToolExecution,
+            description: format!("{} wants to run {} with {}", self.name, tool.id(), params),
+            context: ApprovalContext {
+                data: HashMap::from([
+                    ("tool".to_string(), serde_json::json!(tool.id())),
+                    ("arguments".to_string(), params.clone()),
+                ]),
+            },
+            priority: Priority::High,
+            deadline: None,
+            suggested_approvers: Vec::new(),
+            timeout: self.approval_timeout.map(|(timeout, _)| timeout),
+        };
+
+        let id = request.id;
+        let decision = match self.approval_timeout {
+            Some((timeout, on_timeout)) => match tokio::time::timeout(timeout, handler.request_approval(request)).await {
+                Ok(decision) => decision,
+                Err(_) => {
+                    if let Err(e) = handler.cancel(id).await {
+                        tracing::warn!("Failed to cancel approval request {}: {}", id, e);
+                    }
+                    let outcome = match on_timeout {
+                        TimeoutDecision::Reject => "auto-rejected",
+                        TimeoutDecision::Defer => "deferred",
+                    };
+                    return Ok(Some(Observation::error(format!(
+                        "Call to {} {} due to timeout: no approval decision within {:?}; the tool was not run",
+                        tool.id(),
+                        outcome,
+                        timeout
+                    ))));
+                }
+            },
+            None => handler.request_approval(request).await,
+        };
+        let decision = match decision {
+            Ok(decision) => decision,
+            Err(e) => {
+                tracing::warn!("Approval request {} for {} failed: {}", id, tool.id(), e);
+                return Ok(Some(Observation::error(format!(
+                    "Call to {} not run: approval request failed: {}",
+                    tool.id(),
+                    e
+                ))));
+            }
+        };
+        let reason = match decision {
+            ApprovalDecision::Approved { .. } | ApprovalDecision::AutoApproved { .. } => return Ok(None),
+            ApprovalDecision::Rejected { approver, reason } => format!("rejected by {}: {}", approver, reason),
+            ApprovalDecision::ModificationRequired { approver, instructions } => {
+                format!("{} asked for changes: {}", approver, instructions)
+            }
+            ApprovalDecision::Escalated { target, reason } => {
+                format!("escalated to {} ({}) and not yet approved", target, reason)
+            }
+        };
+        Ok(Some(Observation::error(format!("Call to {} denied: {}", tool.id(), reason))))
+    }
+
+    /// Execute one turn's tool calls concurrently, at most `max_concurrent_tools` at a time
+    ///
+    /// Observations come back in call order. A failing call becomes an error
+    /// observation instead of aborting its siblings.
+    async fn execute_tool_calls(
+        &self,
+        calls: &[ParsedToolCall],
+        progress: Option<&ProgressSender>,
+        completed_calls: &mut HashMap<String, Observation>,
+    ) -> Vec<Observation> {
+        let keys: Vec<Option<String>> = calls
+            .iter()
+            .map(|call| self.idempotency_key(&call.tool_id, &call.params))
+            .collect();
+        // Identical side-effecting calls within the batch also run only once
+        let duplicate_of: Vec<Option<usize>> = keys
+            .iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let key = key.as_ref()?;
+                keys[..i].iter().position(|earlier| earlier.as_ref() == Some(key))
+            })
+            .collect();
+
+        let completed = &*completed_calls;
+        let limit = tokio::sync::Semaphore::new(self.max_concurrent_tools.max(1));
+        let mut pending = Vec::with_capacity(calls.len());
+        for ((call, key), duplicate) in calls.iter().zip(&keys).zip(&duplicate_of) {
+            let limit = &limit;
+            pending.push(async move {
+                if duplicate.is_some() {
+                    return None;
+                }
+                if let Some(previous) = key.as_ref().and_then(|key| completed.get(key)) {
+                    return Some(replayed(previous));
+                }
+                let _permit = limit.acquire().await.ok();
+                Some(
+                    self.execute_tool(&call.tool_id, call.params.clone(), progress)
+                        .await
+                        .unwrap_or_else(|e| Observation::error(e.to_string())),
+                )
+            });
+        }
+        let mut observations = futures::future::join_all(pending).await;
+
+        for (i, duplicate) in duplicate_of.iter().enumerate() {
+            if let Some(first) = *duplicate {
+                observations[i] = observations[first].as_ref().map(replayed);
+            }
+        }
+        let observations: Vec<Observation> = observations.into_iter().flatten().collect();
+
+        for (key, observation) in keys.into_iter().zip(&observations) {
+            if let Some(key) = key.filter(|_| !observation.is_error) {
+                completed_calls.entry(key).or_insert_with(|| observation.clone());
+            }
+        }
+        observations
+    }
+
+    /// Idempotency key of a call to a side-effecting tool
+    fn idempotency_key(&self, tool_id: &str, params: &serde_json::Value) -> Option<String> {
+        self.tools
+            .iter()
+            .find(|t| t.id() == tool_id)
+            .and_then(|t| t.idempotency_key(params))
+    }
+
+    /// Run observation guardrails, redacting or withholding content as they direct
+    async fn guard_observation(
+        &self,
+        mut observation: Observation,
+        ctx: &GuardrailContext,
+    ) -> Result<Observation> {
+        for guardrail in &self.observation_guardrails {
+            let result = guardrail.check(&observation, ctx).await?;
+            if result.passed {
+                continue;
+            }
+
+            self.metrics.record_guardrail_block(guardrail.id());
+            if result.tripwire_triggered {
+                return Err(Error::guardrail_violation(guardrail.id(), result.reasoning));
+            }
+            observation.content = result.suggested_modification.unwrap_or_else(|| {
+                format!("[observation withheld by {}: {}]", guardrail.id(), result.reasoning)
+            });
+        }
+
+        Ok(observation)
+    }
+
     /// Perform a handoff to another agent
-    async fn perform_handoff(&self, _target_agent: AgentId, _trace: &ReActTrace) -> Result<AgentOutput> {
-        // TODO: Implement handoff logic
-        Err(Error::handoff("Handoff not yet implemented"))
+    async fn perform_handoff(
+        &self,
+        target: &str,
+        reason: &str,
+        ctx: HandoffContext,
+        mut trace: ReActTrace,
+        cancel: Option<&CancellationToken>,
+    ) -> Result<AgentOutput> {
+        let target = target.trim();
+        let target_agent = self
+            .handoff_agents
+            .iter()
+            .find(|a| a.name.eq_ignore_ascii_case(target) || a.id.to_string() == target)
+            .ok_or_else(|| Error::handoff(format!("Unknown handoff target '{}'", target)))?;
+
+        self.metrics.record_handoff(&self.name, &target_agent.name);
+        trace.add_observation(Observation::new(format!(
+            "Handed off to {}: {}",
+            target_agent.name, reason
+        )));
+
+        let ctx = ctx.with_trace(trace.clone());
+        let output = Box::pin(target_agent.receive(ctx, cancel)).await?;
+
+        // Continue the caller's trace with the target's steps
+        trace.thoughts.extend(output.trace.thoughts);
+        trace.actions.extend(output.trace.actions);
+        trace.observations.extend(output.trace.observations);
+        trace.total_tokens.add(output.trace.total_tokens);
+        trace.complete();
+
+        let mut handoffs = vec![serde_json::json!({
+            "from": self.name,
+            "to": target_agent.name,
+            "reason": reason,
+        })];
+        if let Some(serde_json::Value::Array(nested)) = output.metadata.get("handoffs") {
+            handoffs.extend(nested.iter().cloned());
+        }
+
+        Ok(AgentOutput {
+            agent_id: output.agent_id,
+            content: output.content,
+            raw_content: output.raw_content,
+            reasoning: output.reasoning,
+            truncated_by_budget: output.truncated_by_budget,
+            cancelled: output.cancelled,
+            trace,
+            metadata: serde_json::json!({ "handoffs": handoffs }),
+            guardrail_results: output.guardrail_results,
+        })
+    }
+}
+
+/// Parse `content` as JSON accepted by `validator`, returning the JSON text
+fn check_structured(validator: &jsonschema::Validator, content: &str) -> std::result::Result<String, String> {
+    // Tolerate code fences or prose around the object
+    let json: serde_json::Value = serde_json::from_str(content.trim())
+        .or_else(|e| serde_json::from_str(&ExtractJsonObject.apply(content)).map_err(|_| e))
+        .map_err(|e| format!("invalid JSON: {}", e))?;
+    if let Err(errors) = validator.validate(&json) {
+        return Err(errors.map(|e| e.to_string()).collect::<Vec<_>>().join("; "));
+    }
+    Ok(json.to_string())
+}
+
+/// Numbers and sends the events of a streaming run
+struct EventSink {
+    tx: mpsc::Sender<RunEvent>,
+    next_seq: AtomicU64,
+}
+
+impl EventSink {
+    fn new(tx: mpsc::Sender<RunEvent>) -> Self {
+        Self {
+            tx,
+            next_seq: AtomicU64::new(0),
+        }
+    }
+
+    async fn emit(&self, event_type: RunEventType, data: serde_json::Value) {
+        let event = RunEvent {
+            seq_id: SeqId::new(self.next_seq.fetch_add(1, Ordering::Relaxed)),
+            timestamp: chrono::Utc::now(),
+            event_type,
+            data,
+        };
+        // A dropped receiver only means nobody is watching any more
+        let _ = self.tx.send(event).await;
     }
 }
 
+async fn emit(events: Option<&EventSink>, event_type: RunEventType, data: serde_json::Value) {
+    if let Some(events) = events {
+        events.emit(event_type, data).await;
+    }
+}
+
+async fn emit_result(events: Option<&EventSink>, tool_id: &str, observation: &Observation) {
+    let data = serde_json::json!({
+        "tool_id": tool_id,
+        "content": observation.content,
+        "is_error": observation.is_error,
+    });
+    emit(events, RunEventType::ToolResult, data).await;
+}
+
+/// Observation standing in for a side-effecting call that already ran
+fn replayed(previous: &Observation) -> Observation {
+    Observation::new(format!(
+        "[already executed earlier in this run; not repeated] {}",
+        previous.content
+    ))
+}
+
+/// Turn a call to the built-in handoff tool into a handoff action
+fn intercept_handoff(action: Action) -> Action {
+    match action {
+        Action::ToolCall { tool_id, params, .. } if tool_id == HANDOFF_TOOL_ID => {
+            let field = |key: &str| {
+                params
+                    .get(key)
+                    .and_then(|v| v.as_str())
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            Action::handoff(field("target_role"), field("reason"))
+        }
+        other => other,
+    }
+}
+
+/// Parse `handoff(target_role, reason)` out of free-form model output
+fn parse_handoff_call(content: &str) -> Option<Action> {
+    static HANDOFF_RE: std::sync::OnceLock<regex::Regex> = std::sync::OnceLock::new();
+    let re = HANDOFF_RE.get_or_init(|| {
+        regex::Regex::new(r#"(?i)\bhandoff\(\s*["']?([^"',)]+?)["']?\s*(?:,\s*["']?([^"')]*)["']?\s*)?\)"#)
+            .unwrap()
+    });
+
+    let caps = re.captures(content)?;
+    let target = caps.get(1)?.as_str().trim();
+    let reason = caps.get(2).map(|m| m.as_str().trim()).unwrap_or_default();
+    Some(Action::handoff(target, reason))
+}
+
 /// Agent builder
 pub struct AgentBuilder<TContext = ()> {
     name: Option<String>,
@@ -227,14 +1350,44 @@ pub struct AgentBuilder<TContext = ()> {
     model: Option<String>,
     tools: Vec<Arc<dyn Tool>>,
     handoff_targets: Vec<AgentId>,
+    handoff_agents: Vec<Arc<Agent>>,
     input_guardrails: Vec<Arc<dyn InputGuardrail>>,
     output_guardrails: Vec<Arc<dyn OutputGuardrail>>,
+    observation_guardrails: Vec<Arc<dyn InputObservationGuardrail>>,
+    post_processors: Vec<Box<dyn OutputTransform>>,
+    memory: Option<AgentMemory>,
+    history_window: Option<HistoryWindow>,
+    tokenizer: Option<Arc<dyn Tokenizer>>,
     max_loops: u32,
+    budget: Option<BudgetGuardrail>,
+    pricing: Option<(f64, f64)>,
+    fallback_models: Vec<String>,
+    provider_preferences: Option<ProviderPreferences>,
     temperature: f32,
     react_config: Option<ReActConfig>,
+    reasoning_tags: ReasoningTags,
+    tool_protocol: ToolProtocol,
+    tool_choice: Option<ToolChoice>,
+    parallel_tool_calls: bool,
+    max_concurrent_tools: usize,
+    response_schema: Option<serde_json::Value>,
+    schema_retries: u32,
+    turn_timeout: Option<Duration>,
+    tool_timeout: Option<Duration>,
     context: Option<Arc<RwLock<TContext>>>,
     hooks: AgentHooks,
     client: Option<Arc<dyn LlmClient>>,
+    metrics: Option<Arc<dyn Metrics>>,
+    capabilities: Vec<String>,
+    prompt_log: Option<Arc<dyn PromptLog>>,
+    prompt_log_redactor: Option<SecretRedactor>,
+    tool_cache: Option<Arc<dyn ToolCache>>,
+    approval_handler: Option<Arc<dyn ApprovalHandler>>,
+    approval_tools: Vec<String>,
+    approval_timeout: Option<(Duration, TimeoutDecision)>,
+    validate_model: bool,
+    #[cfg(feature = "storage")]
+    recall_storage: Option<Arc<dyn crate::storage::MemoryStorage>>,
 }
 
 impl<TContext> AgentBuilder<TContext>
@@ -249,14 +1402,44 @@ where
             model: None,
             tools: Vec::new(),
             handoff_targets: Vec::new(),
+            handoff_agents: Vec::new(),
             input_guardrails: Vec::new(),
             output_guardrails: Vec::new(),
+            observation_guardrails: Vec::new(),
+            post_processors: Vec::new(),
+            memory: None,
+            history_window: None,
+            tokenizer: None,
             max_loops: 10,
+            budget: None,
+            pricing: None,
+            fallback_models: Vec::new(),
+            provider_preferences: None,
             temperature: 0.7,
             react_config: None,
+            reasoning_tags: ReasoningTags::default(),
+            tool_protocol: ToolProtocol::default(),
+            tool_choice: None,
+            parallel_tool_calls: false,
+            max_concurrent_tools: DEFAULT_MAX_CONCURRENT_TOOLS,
+            response_schema: None,
+            schema_retries: DEFAULT_SCHEMA_RETRIES,
+            turn_timeout: None,
+            tool_timeout: None,
             context: None,
             hooks: AgentHooks::default(),
             client: None,
+            metrics: None,
+            capabilities: Vec::new(),
+            prompt_log: None,
+            prompt_log_redactor: None,
+            tool_cache: None,
+            approval_handler: None,
+            approval_tools: Vec::new(),
+            approval_timeout: None,
+            validate_model: false,
+            #[cfg(feature = "storage")]
+            recall_storage: None,
         }
     }
 
@@ -278,6 +1461,20 @@ where
         self
     }
 
+    /// Set models the provider falls back to, in order, if the primary is unavailable
+    pub fn fallback_models(mut self, models: Vec<String>) -> Self {
+        self.fallback_models = models;
+        self
+    }
+
+    /// Set how OpenRouter picks providers for this agent's requests
+    ///
+    /// Overrides the client's default preferences.
+    pub fn provider_preferences(mut self, preferences: ProviderPreferences) -> Self {
+        self.provider_preferences = Some(preferences);
+        self
+    }
+
     /// Add a tool
     pub fn tool(mut self, tool: Arc<dyn Tool>) -> Self {
         self.tools.push(tool);
@@ -296,57 +1493,299 @@ where
         self
     }
 
-    /// Add an input guardrail
-    pub fn input_guardrail(mut self, guardrail: Arc<dyn InputGuardrail>) -> Self {
+    /// Allow handing off to an agent via the built-in handoff tool
+    pub fn handoff(mut self, agent: Arc<Agent>) -> Self {
+        self.handoff_targets.push(agent.id);
+        self.handoff_agents.push(agent);
+        self
+    }
+
+    /// Add an input guardrail (same as [`add_input_guardrail`](Self::add_input_guardrail))
+    pub fn input_guardrail(self, guardrail: Arc<dyn InputGuardrail>) -> Self {
+        self.add_input_guardrail(guardrail)
+    }
+
+    /// Add an output guardrail (same as [`add_output_guardrail`](Self::add_output_guardrail))
+    pub fn output_guardrail(self, guardrail: Arc<dyn OutputGuardrail>) -> Self {
+        self.add_output_guardrail(guardrail)
+    }
+
+    /// Add an input guardrail, run after those already added
+    ///
+    /// Each guardrail sees the input as rewritten by the ones before it; the
+    /// first failing result stops the run and skips the rest. Results are
+    /// kept in [`AgentOutput::guardrail_results`].
+    pub fn add_input_guardrail(mut self, guardrail: Arc<dyn InputGuardrail>) -> Self {
         self.input_guardrails.push(guardrail);
         self
     }
 
-    /// Add an output guardrail
-    pub fn output_guardrail(mut self, guardrail: Arc<dyn OutputGuardrail>) -> Self {
+    /// Add an output guardrail, run after those already added
+    ///
+    /// Each guardrail sees the output as rewritten by the ones before it; the
+    /// first failing result stops the run and skips the rest. Results are
+    /// kept in [`AgentOutput::guardrail_results`].
+    pub fn add_output_guardrail(mut self, guardrail: Arc<dyn OutputGuardrail>) -> Self {
         self.output_guardrails.push(guardrail);
         self
     }
 
-    /// Set the maximum loops
-    pub fn max_loops(mut self, max_loops: u32) -> Self {
-        self.max_loops = max_loops;
+    /// Add an observation guardrail
+    pub fn observation_guardrail(mut self, guardrail: Arc<dyn InputObservationGuardrail>) -> Self {
+        self.observation_guardrails.push(guardrail);
         self
     }
 
-    /// Set the temperature
-    pub fn temperature(mut self, temperature: f32) -> Self {
-        self.temperature = temperature;
+    /// Add transforms applied in order to the final answer
+    ///
+    /// The cleaned answer becomes [`AgentOutput::content`]; the original is
+    /// kept in [`AgentOutput::raw_content`].
+    pub fn post_process(mut self, transforms: Vec<Box<dyn OutputTransform>>) -> Self {
+        self.post_processors.extend(transforms);
         self
     }
 
-    /// Set the ReAct configuration
-    pub fn react_config(mut self, config: ReActConfig) -> Self {
-        self.react_config = Some(config);
+    /// Set which reasoning tags are stripped from model responses
+    ///
+    /// Defaults to [`ReasoningTags::Auto`]; stripped text is kept in
+    /// [`AgentOutput::reasoning`].
+    pub fn reasoning_tags(mut self, tags: ReasoningTags) -> Self {
+        self.reasoning_tags = tags;
         self
     }
 
-    /// Set the context
-    pub fn context(mut self, context: Arc<RwLock<TContext>>) -> Self {
-        self.context = Some(context);
+    /// Set the conversation memory replayed into each prompt
+    pub fn memory(mut self, memory: AgentMemory) -> Self {
+        self.memory = Some(memory);
         self
     }
 
-    /// Set the hooks
-    pub fn hooks(mut self, hooks: AgentHooks) -> Self {
-        self.hooks = hooks;
+    /// Let the model edit its own memory blocks with `core_memory_append` and `core_memory_replace`
+    ///
+    /// The tools act on the agent's [`memory`](Self::memory) and fail when it
+    /// has none or its config disables agentic control.
+    pub fn core_memory_tools(mut self) -> Self {
+        self.tools.extend(crate::memory_tools::core_memory_tools());
         self
     }
 
-    /// Set the LLM client (OpenRouter, vLLM, etc.)
-    pub fn client(mut self, client: Arc<dyn LlmClient>) -> Self {
-        self.client = Some(client);
+    /// Set how much message history is included in each prompt
+    ///
+    /// Overrides the memory's [`window_strategy`](MemoryConfig::window_strategy).
+    /// Without an explicit [`memory`](Self::memory), an in-process memory is created.
+    pub fn history_window(mut self, window: HistoryWindow) -> Self {
+        self.history_window = Some(window);
         self
     }
 
-    /// Build the agent
-    pub fn build(self) -> Result<Agent<TContext>> {
+    /// Estimate tokens with `tokenizer` instead of the chars/4 [`TokenCounter`]
+    pub fn tokenizer(mut self, tokenizer: Arc<dyn Tokenizer>) -> Self {
+        self.tokenizer = Some(tokenizer);
+        self
+    }
+
+    /// Set the maximum loops
+    pub fn max_loops(mut self, max_loops: u32) -> Self {
+        self.max_loops = max_loops;
+        self
+    }
+
+    /// Stop the loop before a model call that could break a token or cost cap
+    pub fn budget(mut self, budget: BudgetGuardrail) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Set the model's price in USD per million prompt and completion tokens
+    pub fn pricing(mut self, prompt_per_mtok: f64, completion_per_mtok: f64) -> Self {
+        self.pricing = Some((prompt_per_mtok, completion_per_mtok));
+        self
+    }
+
+    /// Set the temperature
+    pub fn temperature(mut self, temperature: f32) -> Self {
+        self.temperature = temperature;
+        self
+    }
+
+    /// Set the ReAct configuration
+    pub fn react_config(mut self, config: ReActConfig) -> Self {
+        self.react_config = Some(config);
+        self
+    }
+
+    /// Set how tool calls and results are exchanged with the model
+    pub fn tool_protocol(mut self, protocol: ToolProtocol) -> Self {
+        self.tool_protocol = protocol;
+        self
+    }
+
+    /// Set the tool choice sent with native (`OpenAiJson`) tool definitions
+    ///
+    /// `Required` and `Named` apply to the first turn only, so the agent can
+    /// still give a final answer once it has acted; `None` and `Auto` apply
+    /// to every turn.
+    pub fn tool_choice(mut self, choice: ToolChoice) -> Self {
+        self.tool_choice = Some(choice);
+        self
+    }
+
+    /// Run all tool calls from one model response concurrently
+    ///
+    /// Observations are recorded in call order, and a failing call becomes an
+    /// error observation without aborting the others.
+    pub fn parallel_tool_calls(mut self, parallel: bool) -> Self {
+        self.parallel_tool_calls = parallel;
+        self
+    }
+
+    /// Cap concurrently running tool calls (default [`DEFAULT_MAX_CONCURRENT_TOOLS`])
+    pub fn max_concurrent_tools(mut self, max: usize) -> Self {
+        self.max_concurrent_tools = max;
+        self
+    }
+
+    /// Require the final answer to be JSON matching `schema`
+    ///
+    /// The schema is sent as the request's `response_format`, and answers are
+    /// validated locally too; see [`AgentOutput::parse_structured`].
+    pub fn response_schema(mut self, schema: serde_json::Value) -> Self {
+        self.response_schema = Some(schema);
+        self
+    }
+
+    /// Set how many times an answer that fails the response schema is retried
+    pub fn schema_retries(mut self, retries: u32) -> Self {
+        self.schema_retries = retries;
+        self
+    }
+
+    /// Limit each model call; expiry is recorded and the turn retried
+    ///
+    /// The run fails with [`Error::Timeout`] if the last allowed turn times out.
+    pub fn turn_timeout(mut self, timeout: Duration) -> Self {
+        self.turn_timeout = Some(timeout);
+        self
+    }
+
+    /// Limit each tool call; expiry is reported to the model as a failed call
+    pub fn tool_timeout(mut self, timeout: Duration) -> Self {
+        self.tool_timeout = Some(timeout);
+        self
+    }
+
+    /// Check the model ID against the client's model list when building
+    ///
+    /// Unknown IDs fail with the nearest matches as suggestions; a match that
+    /// differs only in case is normalized to the listed ID. The list is
+    /// fetched from the client, so use [`AgentBuilder::build_async`] inside
+    /// an async runtime.
+    pub fn validate_model(mut self, validate: bool) -> Self {
+        self.validate_model = validate;
+        self
+    }
+
+    /// Set the context
+    pub fn context(mut self, context: Arc<RwLock<TContext>>) -> Self {
+        self.context = Some(context);
+        self
+    }
+
+    /// Set the hooks
+    pub fn hooks(mut self, hooks: AgentHooks) -> Self {
+        self.hooks = hooks;
+        self
+    }
+
+    /// Set the LLM client (OpenRouter, vLLM, etc.)
+    pub fn client(mut self, client: Arc<dyn LlmClient>) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Set the metrics sink (defaults to a no-op)
+    pub fn metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Record every request sent to the model in an audit log
+    pub fn prompt_log(mut self, log: Arc<dyn PromptLog>) -> Self {
+        self.prompt_log = Some(log);
+        self
+    }
+
+    /// Redact secrets from prompt log records before they are persisted
+    pub fn redact_prompt_log(mut self, redactor: SecretRedactor) -> Self {
+        self.prompt_log_redactor = Some(redactor);
+        self
+    }
+
+    /// Ask `handler` before running tools listed in [`require_approval_for`](Self::require_approval_for)
+    pub fn approval_handler(mut self, handler: Arc<dyn ApprovalHandler>) -> Self {
+        self.approval_handler = Some(handler);
+        self
+    }
+
+    /// Require human approval before calling these tools (by ID or name)
+    ///
+    /// Each matching call sends an [`ApprovalRequest`] with the tool and its
+    /// arguments to the [`approval_handler`](Self::approval_handler) and waits
+    /// for the decision; anything but approval becomes an error observation
+    /// and the tool does not run. Other tools are not gated.
+    pub fn require_approval_for(mut self, tools: Vec<String>) -> Self {
+        self.approval_tools.extend(tools);
+        self
+    }
+
+    /// Stop waiting for an approval decision after `timeout`
+    ///
+    /// The request is cancelled and `on_timeout` decides the outcome; either
+    /// way the tool does not run and the observation says why.
+    pub fn approval_timeout(mut self, timeout: Duration, on_timeout: TimeoutDecision) -> Self {
+        self.approval_timeout = Some((timeout, on_timeout));
+        self
+    }
+
+    /// Memoize results of [cacheable](Tool::cacheable) tools
+    ///
+    /// Share one cache between agents to reuse results across them.
+    pub fn tool_cache(mut self, cache: Arc<dyn ToolCache>) -> Self {
+        self.tool_cache = Some(cache);
+        self
+    }
+
+    /// Give the agent a `recall` tool that searches persisted message history
+    ///
+    /// Messages are looked up under the agent memory's ID when memory is set,
+    /// so a long-lived assistant can recall sessions from earlier processes.
+    #[cfg(feature = "storage")]
+    pub fn recall(mut self, storage: Arc<dyn crate::storage::MemoryStorage>) -> Self {
+        self.recall_storage = Some(storage);
+        self
+    }
+
+    /// Advertise capability tags (e.g. `network`, `pcap`) for routing and handoffs
+    pub fn capabilities(mut self, capabilities: &[&str]) -> Self {
+        for capability in capabilities {
+            let capability = capability.trim().to_lowercase();
+            if !capability.is_empty() && !self.capabilities.contains(&capability) {
+                self.capabilities.push(capability);
+            }
+        }
+        self
+    }
+
+    /// Build the agent
+    pub fn build(self) -> Result<Agent<TContext>> {
+        if self.validate_model {
+            return crate::blocking::block_on(self.build_async())?;
+        }
+
         let name = self.name.ok_or_else(|| Error::config("Agent name is required"))?;
+        if !self.approval_tools.is_empty() && self.approval_handler.is_none() {
+            return Err(Error::config("require_approval_for needs an approval_handler"));
+        }
         let system_prompt = self
             .system_prompt
             .ok_or_else(|| Error::config("System prompt is required"))?;
@@ -362,23 +1801,117 @@ where
             })
             .ok_or_else(|| Error::config("LLM client not configured (set OPENROUTER_API_KEY or VLLM_BASE_URL)"))?;
 
+        let mut tools = self.tools;
+        if !self.handoff_agents.is_empty() {
+            let names: Vec<&str> = self.handoff_agents.iter().map(|a| a.name.as_str()).collect();
+            tools.push(Arc::new(HandoffTool::new(&names)));
+        }
+
+        let id = AgentId::new();
+        let memory = self.memory.or_else(|| {
+            self.history_window.map(|_| {
+                AgentMemory::new(
+                    id,
+                    MemoryConfig {
+                        storage_backend: StorageBackend::Memory,
+                        ..MemoryConfig::default()
+                    },
+                )
+            })
+        });
+
+        let history_window = self
+            .history_window
+            .or_else(|| memory.as_ref().map(|m| m.config.window_strategy))
+            .unwrap_or_default();
+
+        #[cfg(feature = "storage")]
+        if let Some(storage) = self.recall_storage {
+            let owner = memory.as_ref().map(|m| m.agent_id).unwrap_or(id);
+            tools.push(Arc::new(crate::memory_tools::RecallTool::new(storage, owner)));
+        }
+
+        let response_validator = self
+            .response_schema
+            .as_ref()
+            .map(|schema| {
+                jsonschema::validator_for(schema)
+                    .map_err(|e| Error::config(format!("Invalid response schema: {}", e)))
+            })
+            .transpose()?;
+        let tool_validators = tools
+            .iter()
+            .map(|tool| {
+                let validator = compile_input_schema(tool.as_ref())
+                    .map_err(|e| tracing::warn!(agent = %name, "{}; only Tool::validate is checked", e))
+                    .ok();
+                (tool.id().to_string(), validator)
+            })
+            .collect();
+
+        let mut model = ModelConfig::new(model_name).with_fallback_models(self.fallback_models);
+        if let Some(preferences) = self.provider_preferences {
+            model = model.with_provider_preferences(preferences);
+        }
+        if let Some((prompt, completion)) = self.pricing {
+            model = model.with_pricing(prompt, completion);
+        }
+
         Ok(Agent {
-            id: AgentId::new(),
+            id,
             name,
             system_prompt,
-            model: ModelConfig::new(model_name),
-            tools: self.tools,
+            model,
+            tools,
             handoff_targets: self.handoff_targets,
+            handoff_agents: self.handoff_agents,
             input_guardrails: self.input_guardrails,
             output_guardrails: self.output_guardrails,
+            observation_guardrails: self.observation_guardrails,
+            post_processors: self.post_processors,
+            memory,
+            history_window,
+            tokenizer: self.tokenizer.unwrap_or_else(|| Arc::new(TokenCounter::default())),
             max_loops: self.max_loops,
+            budget: self.budget,
             temperature: self.temperature,
             react_config: self.react_config.unwrap_or_default(),
+            reasoning_tags: self.reasoning_tags,
+            tool_protocol: self.tool_protocol,
+            tool_choice: self.tool_choice,
+            parallel_tool_calls: self.parallel_tool_calls,
+            max_concurrent_tools: self.max_concurrent_tools.max(1),
+            response_schema: self.response_schema,
+            schema_retries: self.schema_retries,
+            turn_timeout: self.turn_timeout,
+            tool_timeout: self.tool_timeout,
             context: self.context.unwrap_or_else(|| Arc::new(RwLock::new(TContext::default()))),
             hooks: self.hooks,
             client,
+            metrics: self.metrics.unwrap_or_else(crate::metrics::noop),
+            capabilities: self.capabilities,
+            prompt_log: self.prompt_log,
+            prompt_log_redactor: self.prompt_log_redactor,
+            tool_cache: self.tool_cache,
+            approval_handler: self.approval_handler,
+            approval_tools: self.approval_tools,
+            approval_timeout: self.approval_timeout,
+            rate_limit: RwLock::new(None),
+            response_validator,
+            tool_validators,
         })
     }
+
+    /// Build the agent, validating the model ID if requested
+    pub async fn build_async(mut self) -> Result<Agent<TContext>> {
+        let validate = std::mem::take(&mut self.validate_model);
+        let mut agent = self.build()?;
+        if validate {
+            let available = agent.client.model_ids().await?;
+            agent.model.model = crate::llm_client::resolve_model_id(&agent.model.model, &available)?;
+        }
+        Ok(agent)
+    }
 }
 
 impl<TContext> Default for AgentBuilder<TContext>
@@ -390,27 +1923,67 @@ where
     }
 }
 
+/// Cost estimate for one agent run, produced by [`Agent::plan`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanEstimate {
+    /// Agent name
+    pub agent_name: String,
+    /// Model the agent calls
+    pub model: String,
+    /// Estimated tokens in the first turn's prompt
+    pub prompt_tokens: u64,
+    /// Completion tokens allowed per turn
+    pub max_completion_tokens: u64,
+    /// Maximum turns in a run
+    pub max_loops: u32,
+    /// Estimated USD per turn (None without model pricing)
+    pub cost_per_turn_usd: Option<f64>,
+    /// Estimated USD for a run of `max_loops` turns (None without model pricing)
+    pub estimated_cost_usd: Option<f64>,
+}
+
 /// Agent output
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentOutput {
     /// Agent that produced this output
     pub agent_id: AgentId,
-    /// Output content
+    /// Output content, after post-processing
     pub content: String,
+    /// Final answer before post-processing
+    #[serde(default)]
+    pub raw_content: String,
+    /// Chain-of-thought stripped from reasoning-model responses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
+    /// Whether the run was cut short by its token / cost budget
+    #[serde(default)]
+    pub truncated_by_budget: bool,
+    /// Whether the run was cut short by cancellation
+    #[serde(default)]
+    pub cancelled: bool,
     /// ReAct trace
     pub trace: ReActTrace,
     /// Additional metadata
     pub metadata: serde_json::Value,
+    /// Input then output guardrail results, in the order the guardrails ran
+    #[serde(default)]
+    pub guardrail_results: Vec<GuardrailResult>,
 }
 
 impl AgentOutput {
     /// Create a new agent output
     pub fn new(agent_id: AgentId, content: impl Into<String>, trace: ReActTrace) -> Self {
+        let content = content.into();
         Self {
             agent_id,
-            content: content.into(),
+            raw_content: content.clone(),
+            content,
+            reasoning: None,
+            truncated_by_budget: false,
+            cancelled: false,
             trace,
             metadata: serde_json::json!({}),
+            guardrail_results: Vec::new(),
         }
     }
 
@@ -419,6 +1992,14 @@ impl AgentOutput {
         self.metadata = metadata;
         self
     }
+
+    /// Deserialize the content of a structured (JSON) answer
+    pub fn parse_structured<T: DeserializeOwned>(&self) -> Result<T> {
+        serde_json::from_str(&self.content).map_err(|e| Error::SchemaValidation {
+            message: e.to_string(),
+            raw: self.content.clone(),
+        })
+    }
 }
 
 /// Agent lifecycle hooks
@@ -430,14 +2011,1554 @@ pub struct AgentHooks {
     pub on_complete: Option<Arc<dyn Fn(&AgentOutput) -> Result<()> + Send + Sync>>,
     /// Hook called on agent error
     pub on_error: Option<Arc<dyn Fn(&Error) -> Result<()> + Send + Sync>>,
+    /// Hook called before each tool call; may rewrite the arguments, and an error aborts the call
+    pub on_tool_start: Option<ToolStartHook>,
+    /// Hook called with each tool call's output
+    pub on_tool_end: Option<ToolEndHook>,
 }
 
+/// Hook run before a tool call with its context, tool ID and mutable arguments
+pub type ToolStartHook = Arc<dyn Fn(&ToolContext, &str, &mut serde_json::Value) -> Result<()> + Send + Sync>;
+
+/// Hook run after a tool call with its context, tool ID and output
+pub type ToolEndHook = Arc<dyn Fn(&ToolContext, &str, &ToolOutput) -> Result<()> + Send + Sync>;
+
 impl std::fmt::Debug for AgentHooks {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("AgentHooks")
             .field("on_start", &self.on_start.is_some())
             .field("on_complete", &self.on_complete.is_some())
             .field("on_error", &self.on_error.is_some())
+            .field("on_tool_start", &self.on_tool_start.is_some())
+            .field("on_tool_end", &self.on_tool_end.is_some())
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openrouter::{Choice, CompletionResponse, Usage};
+    use crate::testing::{MockLlmClient, MockResponse};
+    use async_trait::async_trait;
+
+    /// Response with `text` and no usage
+    fn reply(text: &str) -> CompletionResponse {
+        CompletionResponse {
+            id: "test".to_string(),
+            model: "test".to_string(),
+            choices: vec![Choice {
+                index: 0,
+                message: Message::assistant(text),
+                finish_reason: Some("stop".to_string()),
+            }],
+            usage: Usage {
+                prompt_tokens: 0,
+                completion_tokens: 0,
+                total_tokens: 0,
+            },
+            retries: 0,
+            rate_limit: None,
+        }
+    }
+
+    /// Client that always answers with the same text and cannot stream
+    fn fixed(reply: &str) -> Arc<MockLlmClient> {
+        Arc::new(
+            MockLlmClient::new()
+                .with_fallback_reply(reply)
+                .without_streaming()
+                .with_model_ids(["anthropic/claude-sonnet-4.5", "openai/gpt-4o"]),
+        )
+    }
+
+    /// Client that answers with `replies` in order
+    fn scripted(replies: &[&str]) -> Arc<MockLlmClient> {
+        Arc::new(replies.iter().fold(MockLlmClient::new(), |client, r| client.with_reply(*r)))
+    }
+
+    fn agent(name: &str, reply: &str) -> AgentBuilder {
+        Agent::builder()
+            .name(name)
+            .system_prompt("You are a test agent.")
+            .client(fixed(reply))
+    }
+
+    #[tokio::test]
+    async fn test_agent_initiated_handoff() {
+        let specialist = Arc::new(agent("Specialist", "Final answer: port 22 is fine").build().unwrap());
+        let coordinator = agent(
+            "Coordinator",
+            "Thought: this needs an expert. handoff(\"specialist\", \"needs network expertise\")",
+        )
+        .handoff(specialist.clone())
+        .build()
+        .unwrap();
+
+        assert!(coordinator.tools.iter().any(|t| t.id() == HANDOFF_TOOL_ID));
+
+        let output = coordinator.react_loop("Check port 22").await.unwrap();
+        assert_eq!(output.agent_id, specialist.id);
+        assert_eq!(output.content, "port 22 is fine");
+        assert!(matches!(
+            &output.trace.actions[0],
+            Action::Handoff { target_agent, reason, .. }
+                if target_agent == "specialist" && reason == "needs network expertise"
+        ));
+        assert_eq!(output.metadata["handoffs"][0]["to"], "Specialist");
+    }
+
+    #[tokio::test]
+    async fn test_post_process_keeps_raw_content() {
+        use crate::output_transform::{ExtractJsonObject, StripThinkTags};
+
+        let agent = agent(
+            "Extractor",
+            "Final answer: <think>the user wants JSON</think> Here it is: {\"ok\": true}",
+        )
+        .post_process(vec![Box::new(StripThinkTags), Box::new(ExtractJsonObject)])
+        .build()
+        .unwrap();
+
+        let output = agent.react_loop("Give me JSON").await.unwrap();
+        assert_eq!(output.content, "{\"ok\": true}");
+        assert!(output.raw_content.starts_with("<think>"));
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_tags_stripped() {
+        let reply = "<think>port 22 looks open</think>\nFinal answer: <reasoning>double-check</reasoning>port 22 is open";
+
+        let output = agent("Reasoner", reply)
+            .reasoning_tags(ReasoningTags::Custom(vec!["think".to_string(), "reasoning".to_string()]))
+            .build()
+            .unwrap()
+            .react_loop("Is port 22 open?")
+            .await
+            .unwrap();
+        assert_eq!(output.content, "port 22 is open");
+        assert_eq!(output.reasoning.as_deref(), Some("port 22 looks open\n\ndouble-check"));
+
+        let mut deepseek = agent("Reasoner", reply).build().unwrap();
+        deepseek.model.model = "deepseek/deepseek-r1".to_string();
+        let output = deepseek.react_loop("Is port 22 open?").await.unwrap();
+        assert_eq!(output.content, "port 22 is open");
+
+        let output = agent("Plain", reply).build().unwrap().react_loop("Is port 22 open?").await.unwrap();
+        assert!(output.reasoning.is_none());
+        assert!(output.content.contains("<reasoning>"));
+    }
+
+    /// Client that answers once with native reasoning tokens
+    fn native_reasoning() -> Arc<MockLlmClient> {
+        let mut response = reply("Final answer: port 22 is open");
+        response.choices[0].message.reasoning = Some("the scan shows 22/tcp open".to_string());
+        Arc::new(MockLlmClient::new().with_response(MockResponse::Response(response)))
+    }
+
+    #[tokio::test]
+    async fn test_native_reasoning_captured() {
+        let native = ReActConfig {
+            reasoning_format: ReasoningFormat::NativeReasoning,
+            ..ReActConfig::default()
+        };
+        let builder = |client: Arc<MockLlmClient>| {
+            Agent::builder()
+                .name("Native")
+                .system_prompt("You are a test agent.")
+                .client(client)
+        };
+
+        let client = native_reasoning();
+        let output = builder(client.clone())
+            .react_config(native.clone())
+            .build()
+            .unwrap()
+            .react_loop("Is port 22 open?")
+            .await
+            .unwrap();
+        assert_eq!(output.content, "port 22 is open");
+        assert_eq!(output.reasoning.as_deref(), Some("the scan shows 22/tcp open"));
+        assert_eq!(output.trace.thoughts[0].reasoning.as_deref(), Some("the scan shows 22/tcp open"));
+        assert_eq!(client.last_request().unwrap().include_reasoning, Some(true));
+
+        // Other formats do not ask for reasoning tokens
+        let client = native_reasoning();
+        builder(client.clone()).build().unwrap().react_loop("Is port 22 open?").await.unwrap();
+        assert_ne!(client.last_request().unwrap().include_reasoning, Some(true));
+
+        // Hidden from the output but still kept in the trace
+        let hidden = ReActConfig {
+            expose_reasoning: false,
+            ..native.clone()
+        };
+        let output = builder(native_reasoning())
+            .react_config(hidden)
+            .build()
+            .unwrap()
+            .react_loop("Is port 22 open?")
+            .await
+            .unwrap();
+        assert!(output.reasoning.is_none());
+        assert!(output.trace.thoughts[0].reasoning.is_some());
+
+        // Models that ignore the flag fall back to reasoning tags
+        let output = agent("Tagged", "<think>looks open</think>Final answer: port 22 is open")
+            .react_config(native)
+            .build()
+            .unwrap()
+            .react_loop("Is port 22 open?")
+            .await
+            .unwrap();
+        assert_eq!(output.content, "port 22 is open");
+        assert_eq!(output.reasoning.as_deref(), Some("looks open"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_model() {
+        let checked = agent("Checked", "Final answer: ok")
+            .model("OpenAI/GPT-4o")
+            .validate_model(true)
+            .build_async()
+            .await
+            .unwrap();
+        assert_eq!(checked.model.model, "openai/gpt-4o");
+
+        let err = agent("Checked", "Final answer: ok")
+            .model("anthropic/claude-sonnet-4")
+            .validate_model(true)
+            .build_async()
+            .await
+            .err()
+            .unwrap();
+        assert!(err.to_string().contains("did you mean anthropic/claude-sonnet-4.5"));
+    }
+
+    #[tokio::test]
+    async fn test_handoff_cycle_rejected() {
+        let specialist = agent("Specialist", "Final answer: done").build().unwrap();
+        let ctx = HandoffContext::new("task").with_handler(specialist.id);
+        let err = specialist.receive_handoff(ctx).await.unwrap_err();
+        assert!(matches!(err, Error::Handoff(_)));
+    }
+
+    #[tokio::test]
+    async fn test_unknown_handoff_target() {
+        let specialist = Arc::new(agent("Specialist", "Final answer: done").build().unwrap());
+        let coordinator = agent("Coordinator", "handoff(nobody, no reason)")
+            .handoff(specialist)
+            .build()
+            .unwrap();
+        assert!(coordinator.react_loop("task").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tool_protocol_xml_round_trip() {
+        let client = scripted(&[
+            "<tool_use><name>echo</name><input>{\"message\": \"ping\"}</input></tool_use>",
+            "Final answer: pong",
+        ]);
+        let agent = Agent::builder()
+            .name("Xml")
+            .system_prompt("You are a test agent.")
+            .tool(Arc::new(crate::tools::EchoTool))
+            .tool_protocol(ToolProtocol::Anthropic)
+            .client(client.clone())
+            .build()
+            .unwrap();
+
+        let output = agent.react_loop("ping?").await.unwrap();
+        assert_eq!(output.content, "pong");
+        assert!(matches!(&output.trace.actions[0], Action::ToolCall { tool_id, .. } if tool_id == "echo"));
+
+        let request = client.last_request().unwrap();
+        assert!(request.tools.is_none());
+        assert!(request.messages[0].content.contains("<tool_use>"));
+        assert!(request.messages.last().unwrap().content.starts_with("<tool_result name=\"echo\">"));
+    }
+
+    #[tokio::test]
+    async fn test_core_memory_tools_edit_agent_memory() {
+        let memory = AgentMemory::new(
+            AgentId::new(),
+            MemoryConfig {
+                storage_backend: StorageBackend::Memory,
+                ..MemoryConfig::default()
+            },
+        );
+        let mut persona = crate::memory::MemoryBlock::new("persona", "I am terse.");
+        persona.max_size = Some(40);
+        memory.add_block(persona).await.unwrap();
+
+        let client = scripted(&[
+            "Action: core_memory_append\nAction Input: {\"label\": \"persona\", \"content\": \"User is Ada.\"}",
+            "Action: core_memory_replace\nAction Input: {\"label\": \"persona\", \"old\": \"terse\", \"new\": \"brief\"}",
+            "Action: core_memory_append\nAction Input: {\"label\": \"persona\", \"content\": \"This line does not fit in the block.\"}",
+            "Action: core_memory_replace\nAction Input: {\"label\": \"scratchpad\", \"old\": \"a\", \"new\": \"b\"}",
+            "Final answer: noted",
+        ]);
+        let agent = Agent::builder()
+            .name("Curator")
+            .system_prompt("You are a test agent.")
+            .memory(memory.clone())
+            .core_memory_tools()
+            .client(client)
+            .build()
+            .unwrap();
+
+        let output = agent.react_loop("Remember my name is Ada").await.unwrap();
+        assert_eq!(output.content, "noted");
+
+        let observations = &output.trace.observations;
+        assert!(!observations[0].is_error);
+        assert!(observations[0].content.contains("I am terse.\nUser is Ada."));
+        assert!(observations[1].content.contains("I am brief.\nUser is Ada."));
+        assert!(observations[2].is_error);
+        assert!(observations[2].content.contains("max size 40"));
+        assert!(observations[3].is_error);
+        assert!(observations[3].content.contains("not found"));
+
+        let persona = memory.get_block_by_label("persona").await.unwrap();
+        assert_eq!(persona.value, "I am brief.\nUser is Ada.");
+    }
+
+    #[tokio::test]
+    async fn test_core_memory_tools_respect_agentic_control() {
+        let memory = AgentMemory::new(
+            AgentId::new(),
+            MemoryConfig {
+                storage_backend: StorageBackend::Memory,
+                enable_agentic_control: false,
+                ..MemoryConfig::default()
+            },
+        );
+        memory.add_block(crate::memory::MemoryBlock::new("persona", "I am terse.")).await.unwrap();
+
+        let client = scripted(&[
+            "Action: core_memory_append\nAction Input: {\"label\": \"persona\", \"content\": \"I am verbose.\"}",
+            "Final answer: done",
+        ]);
+        let agent = Agent::builder()
+            .name("Locked")
+            .system_prompt("You are a test agent.")
+            .memory(memory.clone())
+            .core_memory_tools()
+            .client(client)
+            .build()
+            .unwrap();
+
+        let output = agent.react_loop("Change your persona").await.unwrap();
+        assert!(output.trace.observations[0].is_error);
+        assert!(output.trace.observations[0].content.contains("disabled"));
+        assert_eq!(memory.get_block_by_label("persona").await.unwrap().value, "I am terse.");
+    }
+
+    #[tokio::test]
+    async fn test_malformed_tool_arguments_retried() {
+        let client = scripted(&[
+            "Action: echo\nAction Input: {\"message\": ping}",
+            "Action: echo\nAction Input: ```json\n{'message': 'ping',}\n```\nWaiting for the result.",
+            "Final answer: pong",
+        ]);
+        let agent = Agent::builder()
+            .name("Sloppy")
+            .system_prompt("You are a test agent.")
+            .tool(Arc::new(crate::tools::EchoTool))
+            .client(client.clone())
+            .build()
+            .unwrap();
+
+        let output = agent.react_loop("ping?").await.unwrap();
+        assert_eq!(output.content, "pong");
+        assert!(output.trace.observations[0].is_error);
+        assert!(output.trace.observations[0].content.contains("valid JSON"));
+        assert!(matches!(
+            &output.trace.actions[0],
+            Action::ToolCall { tool_id, params, .. } if tool_id == "echo" && params["message"] == "ping"
+        ));
+        assert!(!output.trace.observations[1].is_error);
+    }
+
+    #[tokio::test]
+    async fn test_react_loop_streaming_events() {
+        // Each reply arrives in two deltas
+        let client = MockLlmClient::new()
+            .with_reply("Action: echo\nAction Input: {\"message\": \"ping\"}")
+            .with_reply("Final answer: pong")
+            .with_stream_deltas(2);
+        let agent = Agent::builder()
+            .name("Streamer")
+            .system_prompt("You are a test agent.")
+            .tool(Arc::new(crate::tools::EchoTool))
+            .client(Arc::new(client))
+            .build()
+            .unwrap();
+
+        let (tx, mut rx) = mpsc::channel(64);
+        let output = agent.react_loop_streaming("ping?", tx).await.unwrap();
+        assert_eq!(output.content, "pong");
+
+        let mut events = Vec::new();
+        while let Some(event) = rx.recv().await {
+            events.push(event);
+        }
+        assert!(events.windows(2).all(|pair| pair[0].seq_id < pair[1].seq_id));
+
+        let deltas: String = events
+            .iter()
+            .filter_map(|e| e.data.get("delta").and_then(|d| d.as_str()))
+            .collect();
+        assert_eq!(deltas, "Action: echo\nAction Input: {\"message\": \"ping\"}Final answer: pong");
+
+        let kinds: Vec<&RunEventType> = events
+            .iter()
+            .filter(|e| e.data.get("delta").is_none())
+            .map(|e| &e.event_type)
+            .collect();
+        assert_eq!(
+            kinds,
+            [
+                &RunEventType::Thought,
+                &RunEventType::ToolCall,
+                &RunEventType::ToolResult,
+                &RunEventType::Thought,
+                &RunEventType::Output,
+            ]
+        );
+        assert_eq!(events.last().unwrap().data["content"], "pong");
+    }
+
+    #[tokio::test]
+    async fn test_react_loop_streaming_falls_back_to_complete() {
+        let agent = agent("Fixed", "Final answer: done").build().unwrap();
+        let (tx, mut rx) = mpsc::channel(8);
+        agent.react_loop_streaming("go", tx).await.unwrap();
+
+        let thought = rx.recv().await.unwrap();
+        assert_eq!(thought.event_type, RunEventType::Thought);
+        assert_eq!(thought.data["content"], "Final answer: done");
+        assert_eq!(rx.recv().await.unwrap().event_type, RunEventType::Output);
+        assert!(rx.recv().await.is_none());
+    }
+
+    /// Client that keeps calling a tool and bills 1000 tokens per turn
+    fn metered() -> Arc<MockLlmClient> {
+        Arc::new(
+            MockLlmClient::new()
+                .with_fallback_reply("Action: echo\nAction Input: {\"message\": \"again\"}")
+                .with_usage(600, 400),
+        )
+    }
+
+    /// Rate-limit state with no requests left until `reset_in` from now
+    fn exhausted(reset_in: Duration) -> RateLimitInfo {
+        RateLimitInfo {
+            remaining_requests: Some(0),
+            reset_at: Some(chrono::Utc::now() + chrono::Duration::from_std(reset_in).unwrap()),
+            ..RateLimitInfo::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_rate_limit_delays_next_call() {
+        let client = MockLlmClient::new()
+            .with_reply("Action: echo\nAction Input: {\"message\": \"ping\"}")
+            .with_reply("Final answer: pong")
+            .with_rate_limit(exhausted(Duration::from_millis(300)));
+        let agent = Agent::builder()
+            .name("Limited")
+            .system_prompt("You are a test agent.")
+            .tool(Arc::new(crate::tools::EchoTool))
+            .client(Arc::new(client))
+            .build()
+            .unwrap();
+        assert_eq!(agent.rate_limit_backoff(), None);
+
+        let start = Instant::now();
+        let output = agent.react_loop("ping").await.unwrap();
+        assert_eq!(output.content, "pong");
+        assert!(start.elapsed() >= Duration::from_millis(250), "{:?}", start.elapsed());
+        assert!(agent.rate_limit().unwrap().is_exhausted());
+        // Waits are capped, and a reset in the past means no wait at all
+        *agent.rate_limit.write() = Some(exhausted(Duration::from_secs(3600)));
+        assert_eq!(agent.rate_limit_backoff(), Some(MAX_RATE_LIMIT_WAIT));
+        *agent.rate_limit.write() = Some(RateLimitInfo {
+            reset_at: Some(chrono::Utc::now() - chrono::Duration::seconds(1)),
+            ..exhausted(Duration::ZERO)
+        });
+        assert_eq!(agent.rate_limit_backoff(), None);
+    }
+
+    #[tokio::test]
+    async fn test_budget_stops_before_overspending() {
+        let budgeted = |budget: BudgetGuardrail| {
+            Agent::builder()
+                .name("Budgeted")
+                .system_prompt("You are a test agent.")
+                .tool(Arc::new(crate::tools::EchoTool))
+                .client(metered())
+                .pricing(1.0, 2.0)
+                .budget(budget)
+                .build()
+                .unwrap()
+        };
+
+        let err = budgeted(BudgetGuardrail::new().with_max_tokens(2500))
+            .react_loop("loop forever")
+            .await
+            .err()
+            .unwrap();
+        let Error::BudgetExceeded { reason, partial } = err else {
+            panic!("expected a budget error");
+        };
+        // A third turn could add the prompt plus up to 1000 completion tokens
+        assert!(reason.contains("on top of 2000 used"), "{}", reason);
+        assert!(partial.truncated_by_budget);
+        assert_eq!(partial.trace.thoughts.len(), 2);
+        assert_eq!(partial.trace.observations.len(), 2);
+        assert!(partial.trace.total_tokens.total_tokens <= 2500);
+
+        // $0.0014 per turn at $1 / $2 per million tokens, and up to $0.002 for the next completion
+        let err = budgeted(BudgetGuardrail::new().with_max_cost_usd(0.004))
+            .react_loop("loop forever")
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, Error::BudgetExceeded { partial, .. } if partial.trace.thoughts.len() == 2));
+
+        // A single call that could overrun the budget is never made
+        let err = budgeted(BudgetGuardrail::new().with_max_tokens(500))
+            .react_loop("loop forever")
+            .await
+            .err()
+            .unwrap();
+        assert!(matches!(err, Error::BudgetExceeded { partial, .. } if partial.trace.thoughts.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn test_budget_uses_tokenizer_when_usage_missing() {
+        // The fixed client reports zero usage, so every turn is estimated at 100 tokens per text
+        let agent = agent("Estimated", "Action: echo\nAction Input: {\"message\": \"again\"}")
+            .tool(Arc::new(crate::tools::EchoTool))
+            .tokenizer(Arc::new(crate::memory::FnTokenizer::new(|_| 100).with_per_message_overhead(0)))
+            .react_config(ReActConfig {
+                max_reasoning_tokens: 100,
+                ..ReActConfig::default()
+            })
+            .budget(BudgetGuardrail::new().with_max_tokens(1000))
+            .build()
+            .unwrap();
+
+        let err = agent.react_loop("loop forever").await.err().unwrap();
+        let Error::BudgetExceeded { partial, .. } = err else {
+            panic!("expected a budget error");
+        };
+        assert_eq!(partial.trace.thoughts.len(), 2);
+        // System prompt and input, then the reply
+        assert_eq!(partial.trace.thoughts[0].tokens.prompt_tokens, 200);
+        assert_eq!(partial.trace.thoughts[0].tokens.completion_tokens, 100);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_aborts_in_flight_call() {
+        let token = CancellationToken::new();
+        // Calls a tool twice, then never answers; the agent has to abandon the call
+        let again = "Action: echo\nAction Input: {\"message\": \"again\"}";
+        let client = Arc::new(
+            MockLlmClient::new()
+                .with_reply(again)
+                .with_reply(again)
+                .with_response(MockResponse::Stall),
+        );
+        let agent = Agent::builder()
+            .name("Cancellable")
+            .system_prompt("You are a test agent.")
+            .tool(Arc::new(crate::tools::EchoTool))
+            .client(client.clone())
+            .max_loops(10)
+            .build()
+            .unwrap();
+
+        let canceller = tokio::spawn({
+            let token = token.clone();
+            async move {
+                while client.request_count() < 3 {
+                    tokio::time::sleep(Duration::from_millis(1)).await;
+                }
+                token.cancel();
+            }
+        });
+        let err = agent.react_loop_with_cancel("loop forever", token.clone()).await.err().unwrap();
+        canceller.await.unwrap();
+        let Error::Cancelled { partial } = err else {
+            panic!("expected a cancellation error");
+        };
+        assert!(partial.cancelled);
+        assert_eq!(partial.trace.observations.len(), 2);
+
+        // An already-cancelled token stops the run before the first turn
+        let err = agent.react_loop_with_cancel("again", token).await.err().unwrap();
+        assert!(matches!(err, Error::Cancelled { partial } if partial.trace.thoughts.is_empty()));
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Answer {
+        answer: String,
+        confidence: f64,
+    }
+
+    fn answer_schema() -> serde_json::Value {
+        serde_json::json!({
+            "type": "object",
+            "properties": {
+                "answer": { "type": "string" },
+                "confidence": { "type": "number" },
+            },
+            "required": ["answer", "confidence"],
+            "additionalProperties": false,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_response_schema_round_trip() {
+        let client = scripted(&[
+            "```json\n{\"answer\": \"Paris\"}\n```",
+            "{\"answer\": \"Paris\", \"confidence\": 0.9}",
+        ]);
+        let agent = Agent::builder()
+            .name("Structured")
+            .system_prompt("You are a test agent.")
+            .response_schema(answer_schema())
+            .client(client.clone())
+            .build()
+            .unwrap();
+
+        let output = agent.react_loop("Capital of France?").await.unwrap();
+        let answer: Answer = output.parse_structured().unwrap();
+        assert_eq!(answer.answer, "Paris");
+        assert_eq!(answer.confidence, 0.9);
+
+        // The second request carried the schema error back to the model
+        let request = client.last_request().unwrap();
+        assert!(request.messages.last().unwrap().content.contains("confidence"));
+        let wire = serde_json::to_value(&request).unwrap();
+        assert_eq!(wire["response_format"]["type"], "json_schema");
+        assert_eq!(wire["response_format"]["json_schema"]["schema"], answer_schema());
+    }
+
+    #[tokio::test]
+    async fn test_response_schema_exhausted() {
+        let agent = agent("Structured", "The answer is Paris.")
+            .response_schema(answer_schema())
+            .schema_retries(0)
+            .build()
+            .unwrap();
+        let err = agent.react_loop("Capital of France?").await.err().unwrap();
+        assert!(matches!(err, Error::SchemaValidation { raw, .. } if raw == "The answer is Paris."));
+
+        let invalid = Agent::builder()
+            .name("Invalid")
+            .system_prompt("You are a test agent.")
+            .response_schema(serde_json::json!({ "type": 12 }))
+            .client(fixed("{}"))
+            .build();
+        assert!(matches!(invalid, Err(Error::Config(_))));
+    }
+
+    #[tokio::test]
+    async fn test_tool_arguments_validated_before_execute() {
+        let agent = agent("Validator", "Final answer: done")
+            .tool(Arc::new(crate::tools::EchoTool))
+            .build()
+            .unwrap();
+
+        let observation = agent.execute_tool("echo", serde_json::json!({}), None).await.unwrap();
+        assert!(observation.is_error);
+        assert!(observation.content.contains("Invalid parameters for echo"));
+        assert!(observation.content.contains("\"message\" is a required property"));
+
+        let invalid = crate::tools::validate_params(&crate::tools::EchoTool, &serde_json::json!({ "message": 5 }))
+            .unwrap_err();
+        assert!(!invalid.success);
+        assert_eq!(invalid.data.unwrap()["validation_errors"][0]["path"], "/message");
+
+        let observation = agent
+            .execute_tool("echo", serde_json::json!({ "message": "hi" }), None)
+            .await
+            .unwrap();
+        assert!(!observation.is_error);
+        assert!(agent.tool_validators["echo"].is_some());
+    }
+
+    /// Tool whose input schema does not compile
+    struct BrokenSchemaTool;
+
+    #[async_trait]
+    impl Tool for BrokenSchemaTool {
+        fn id(&self) -> &str {
+            "broken"
+        }
+
+        fn name(&self) -> &str {
+            "Broken"
+        }
+
+        fn description(&self) -> &str {
+            "Tool with an invalid input schema"
+        }
+
+        fn input_schema(&self) -> crate::tools::JsonSchema {
+            crate::tools::JsonSchema {
+                schema_type: "no-such-type".to_string(),
+                ..crate::tools::JsonSchema::empty()
+            }
+        }
+
+        fn validate(&self, params: &serde_json::Value) -> Result<()> {
+            if params.get("target").is_none() {
+                return Err(Error::InvalidInput("target is required".to_string()));
+            }
+            Ok(())
+        }
+
+        async fn execute(&self, _params: serde_json::Value, _ctx: &ToolContext) -> Result<crate::tools::ToolOutput> {
+            Ok(crate::tools::ToolOutput::success("ran"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_uncompilable_tool_schema_falls_back_to_validate() {
+        assert!(crate::tools::compile_input_schema(&BrokenSchemaTool).is_err());
+
+        let agent = agent("Broken", "Final answer: done")
+            .tool(Arc::new(BrokenSchemaTool))
+            .build()
+            .unwrap();
+        assert!(agent.tool_validators["broken"].is_none());
+
+        let observation = agent.execute_tool("broken", serde_json::json!({}), None).await.unwrap();
+        assert!(observation.is_error);
+        assert!(observation.content.contains("target is required"));
+
+        let observation = agent
+            .execute_tool("broken", serde_json::json!({ "target": "host" }), None)
+            .await
+            .unwrap();
+        assert!(!observation.is_error);
+    }
+
+    #[tokio::test]
+    async fn test_memory_compacted_before_run() {
+        let memory = AgentMemory::new(
+            AgentId::new(),
+            MemoryConfig {
+                max_context_size: 10,
+                compaction_ratio: Some(1.0),
+                compaction_keep_recent: 1,
+                storage_backend: StorageBackend::Memory,
+                ..MemoryConfig::default()
+            },
+        );
+        memory.add_message(Role::User, "scan the host".to_string()).await;
+        memory.add_message(Role::Assistant, "port 22 is open".to_string()).await;
+
+        let agent = agent("Compacting", "Final answer: done").memory(memory.clone()).build().unwrap();
+        agent.react_loop("anything else?").await.unwrap();
+
+        let history = memory.get_recent_messages(10).await;
+        assert!(history[0].is_summary());
+        assert_eq!(history[1].content, "port 22 is open");
+        assert_eq!(history.len(), 4);
+    }
+
+    /// Side-effecting tool that counts how often it actually runs
+    #[derive(Default)]
+    struct KillTool(std::sync::atomic::AtomicUsize);
+
+    #[async_trait]
+    impl Tool for KillTool {
+        fn id(&self) -> &str {
+            "kill_process"
+        }
+
+        fn name(&self) -> &str {
+            "Kill process"
+        }
+
+        fn description(&self) -> &str {
+            "Kill a process by PID"
+        }
+
+        fn input_schema(&self) -> crate::tools::JsonSchema {
+            crate::tools::JsonSchema::empty()
+        }
+
+        async fn execute(&self, params: serde_json::Value, _ctx: &ToolContext) -> Result<crate::tools::ToolOutput> {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(crate::tools::ToolOutput::success(format!("killed {}", params["pid"])))
+        }
+
+        fn idempotency_key(&self, params: &serde_json::Value) -> Option<String> {
+            Some(crate::tools::idempotency_key(self.id(), params))
+        }
+    }
+
+    /// Tool that never returns
+    struct StallTool;
+
+    #[async_trait]
+    impl Tool for StallTool {
+        fn id(&self) -> &str {
+            "stall"
+        }
+
+        fn name(&self) -> &str {
+            "Stall"
+        }
+
+        fn description(&self) -> &str {
+            "Never finishes"
+        }
+
+        fn input_schema(&self) -> crate::tools::JsonSchema {
+            crate::tools::JsonSchema::empty()
+        }
+
+        async fn execute(&self, _params: serde_json::Value, _ctx: &ToolContext) -> Result<ToolOutput> {
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_turn_and_tool_timeouts() {
+        let build = |max_loops| {
+            Agent::builder()
+                .name("Impatient")
+                .system_prompt("You are a test agent.")
+                .tool(Arc::new(StallTool))
+                // Never answers its first call, then calls `stall` and finishes
+                .client(Arc::new(
+                    MockLlmClient::new()
+                        .with_response(MockResponse::Stall)
+                        .with_reply("Action: stall\nAction Input: {}")
+                        .with_reply("Final Answer: done"),
+                ))
+                .turn_timeout(Duration::from_millis(50))
+                .tool_timeout(Duration::from_millis(50))
+                .max_loops(max_loops)
+                .build()
+                .unwrap()
+        };
+
+        let output = build(5).react_loop("go").await.unwrap();
+        assert_eq!(output.content, "done");
+        let observations = &output.trace.observations;
+        assert!(observations[0].is_error && observations[0].content.contains("Timeout"));
+        assert!(observations[1].is_error && observations[1].content.contains("Timed out"));
+
+        // A timeout on the last allowed turn fails the run
+        let err = build(1).react_loop("go").await.err().unwrap();
+        assert!(matches!(err, Error::Timeout(_)));
+    }
+
+    #[tokio::test]
+    async fn test_plan_estimates_without_calling_model() {
+        let plain = agent("Planner", "Final answer: unused").max_loops(3).build().unwrap();
+        let estimate = plain.plan("Summarize the quarterly report").await;
+        assert!(estimate.prompt_tokens > 0);
+        assert_eq!(estimate.max_loops, 3);
+        assert!(estimate.estimated_cost_usd.is_none());
+
+        let priced = agent("Planner", "Final answer: unused")
+            .tool(Arc::new(crate::tools::EchoTool))
+            .pricing(3.0, 15.0)
+            .max_loops(3)
+            .build()
+            .unwrap();
+        let with_tools = priced.plan("Summarize the quarterly report").await;
+        assert!(with_tools.prompt_tokens > estimate.prompt_tokens);
+
+        let per_turn = (with_tools.prompt_tokens as f64 * 3.0
+            + with_tools.max_completion_tokens as f64 * 15.0)
+            / 1_000_000.0;
+        assert!((with_tools.cost_per_turn_usd.unwrap() - per_turn).abs() < 1e-12);
+        assert!((with_tools.estimated_cost_usd.unwrap() - per_turn * 3.0).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn test_side_effecting_call_runs_once() {
+        let client = scripted(&[
+            "Action: kill_process\nAction Input: {\"pid\": 42, \"signal\": \"TERM\"}",
+            "Action: kill_process\nAction Input: {\"signal\": \"TERM\", \"pid\": 42}",
+            "Action: kill_process\nAction Input: {\"pid\": 43, \"signal\": \"TERM\"}",
+            "Final answer: done",
+        ]);
+        let tool = Arc::new(KillTool::default());
+        let agent = Agent::builder()
+            .name("Reaper")
+            .system_prompt("You are a test agent.")
+            .tool(tool.clone())
+            .client(client)
+            .build()
+            .unwrap();
+
+        let output = agent.react_loop("Stop 42 and 43").await.unwrap();
+        assert_eq!(tool.0.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert!(output.trace.observations[1].content.contains("not repeated"));
+        assert_eq!(output.trace.observations[2].content, "killed 43");
+    }
+
+    /// Approval handler that rejects `pid` 1 and approves everything else
+    #[derive(Default)]
+    struct PidOneGuard(parking_lot::Mutex<Vec<ApprovalRequest>>);
+
+    #[async_trait]
+    impl ApprovalHandler for PidOneGuard {
+        async fn request_approval(&self, request: ApprovalRequest) -> Result<ApprovalDecision> {
+            let pid = request.context.data["arguments"]["pid"].as_u64();
+            self.0.lock().push(request);
+            let approver = crate::types::UserId::new("oncall");
+            Ok(match pid {
+                Some(1) => ApprovalDecision::Rejected {
+                    approver,
+                    reason: "never kill init".to_string(),
+                },
+                _ => ApprovalDecision::Approved { approver, notes: None },
+            })
+        }
+
+        async fn check_status(&self, _id: crate::types::ApprovalId) -> Result<crate::hitl::ApprovalStatus> {
+            Ok(crate::hitl::ApprovalStatus::Approved)
+        }
+
+        async fn cancel(&self, _id: crate::types::ApprovalId) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_approval_gate_for_listed_tools() {
+        let client = scripted(&[
+            "Action: kill_process\nAction Input: {\"pid\": 1, \"signal\": \"KILL\"}",
+            "Action: echo\nAction Input: {\"message\": \"checking\"}",
+            "Action: kill_process\nAction Input: {\"pid\": 42, \"signal\": \"TERM\"}",
+            "Final answer: done",
+        ]);
+        let tool = Arc::new(KillTool::default());
+        let handler = Arc::new(PidOneGuard::default());
+        let reaper = Agent::builder()
+            .name("Reaper")
+            .system_prompt("You are a test agent.")
+            .tool(tool.clone())
+            .tool(Arc::new(crate::tools::EchoTool))
+            .approval_handler(handler.clone())
+            .require_approval_for(vec!["kill_process".to_string()])
+            .client(client)
+            .build()
+            .unwrap();
+
+        let output = reaper.react_loop("Stop the stuck job").await.unwrap();
+        let observations = &output.trace.observations;
+        assert!(observations[0].is_error);
+        assert_eq!(
+            observations[0].content,
+            "Call to kill_process denied: rejected by oncall: never kill init"
+        );
+        assert_eq!(observations[2].content, "killed 42");
+        assert_eq!(tool.0.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // Only the gated tool asked for approval
+        let requests = handler.0.lock();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[0].context.data["tool"], "kill_process");
+        assert_eq!(requests[1].context.data["arguments"]["pid"], 42);
+        drop(requests);
+
+        let unhandled = agent("Reaper", "Final answer: done")
+            .require_approval_for(vec!["kill_process".to_string()])
+            .build();
+        assert!(unhandled.is_err());
+    }
+
+    /// Approval handler whose human never answers
+    #[derive(Default)]
+    struct SilentApprover(AtomicU64);
+
+    #[async_trait]
+    impl ApprovalHandler for SilentApprover {
+        async fn request_approval(&self, _request: ApprovalRequest) -> Result<ApprovalDecision> {
+            std::future::pending().await
+        }
+
+        async fn check_status(&self, _id: crate::types::ApprovalId) -> Result<crate::hitl::ApprovalStatus> {
+            Ok(crate::hitl::ApprovalStatus::Pending)
+        }
+
+        async fn cancel(&self, _id: crate::types::ApprovalId) -> Result<()> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_approval_timeout_rejects() {
+        let client = scripted(&[
+            "Action: kill_process\nAction Input: {\"pid\": 42, \"signal\": \"TERM\"}",
+            "Final answer: waiting on approval",
+        ]);
+        let tool = Arc::new(KillTool::default());
+        let handler = Arc::new(SilentApprover::default());
+        let reaper = Agent::builder()
+            .name("Reaper")
+            .system_prompt("You are a test agent.")
+            .tool(tool.clone())
+            .approval_handler(handler.clone())
+            .require_approval_for(vec!["kill_process".to_string()])
+            .approval_timeout(Duration::from_millis(50), TimeoutDecision::Reject)
+            .client(client)
+            .build()
+            .unwrap();
+
+        let output = reaper.react_loop("Stop 42").await.unwrap();
+        let observation = &output.trace.observations[0];
+        assert!(observation.is_error);
+        assert!(observation.content.contains("auto-rejected due to timeout"));
+        assert_eq!(tool.0.load(Ordering::SeqCst), 0);
+        assert_eq!(handler.0.load(Ordering::SeqCst), 1);
+    }
+
+    /// Approval handler whose backend is down
+    struct BrokenApprover;
+
+    #[async_trait]
+    impl ApprovalHandler for BrokenApprover {
+        async fn request_approval(&self, _request: ApprovalRequest) -> Result<ApprovalDecision> {
+            Err(Error::Other("approval service unreachable".to_string()))
+        }
+
+        async fn check_status(&self, _id: crate::types::ApprovalId) -> Result<crate::hitl::ApprovalStatus> {
+            Ok(crate::hitl::ApprovalStatus::Pending)
+        }
+
+        async fn cancel(&self, _id: crate::types::ApprovalId) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_approval_handler_error_becomes_observation() {
+        let client = scripted(&[
+            "Action: kill_process\nAction Input: {\"pid\": 42, \"signal\": \"TERM\"}",
+            "Final answer: could not get approval",
+        ]);
+        let tool = Arc::new(KillTool::default());
+        let reaper = Agent::builder()
+            .name("Reaper")
+            .system_prompt("You are a test agent.")
+            .tool(tool.clone())
+            .approval_handler(Arc::new(BrokenApprover))
+            .require_approval_for(vec!["kill_process".to_string()])
+            .client(client)
+            .build()
+            .unwrap();
+
+        let output = reaper.react_loop("Stop 42").await.unwrap();
+        assert_eq!(output.content, "could not get approval");
+        let observation = &output.trace.observations[0];
+        assert!(observation.is_error);
+        assert!(observation.content.starts_with("Call to kill_process not run: approval request failed"));
+        assert!(observation.content.contains("approval service unreachable"));
+        assert_eq!(tool.0.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn test_tool_hooks_rewrite_and_abort_calls() {
+        let client = scripted(&[
+            "Action: kill_process\nAction Input: {\"pid\": 1}",
+            "Action: kill_process\nAction Input: {\"pid\": \"42\"}",
+            "Final answer: done",
+        ]);
+        let ended = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let hooks = AgentHooks {
+            on_tool_start: Some(Arc::new(|_ctx: &ToolContext, _tool: &str, args: &mut serde_json::Value| {
+                if args["pid"] == 1 {
+                    return Err(Error::InvalidInput("refusing to touch init".to_string()));
+                }
+                // Models sometimes quote numbers
+                if let Some(pid) = args["pid"].as_str().and_then(|pid| pid.parse::<u64>().ok()) {
+                    args["pid"] = serde_json::json!(pid);
+                }
+                Ok(())
+            })),
+            on_tool_end: Some(Arc::new({
+                let ended = ended.clone();
+                move |_ctx: &ToolContext, tool: &str, output: &ToolOutput| {
+                    ended.lock().push(format!("{}: {}", tool, output.content));
+                    Ok(())
+                }
+            })),
+            ..Default::default()
+        };
+        let tool = Arc::new(KillTool::default());
+        let reaper = Agent::builder()
+            .name("Reaper")
+            .system_prompt("You are a test agent.")
+            .tool(tool.clone())
+            .hooks(hooks)
+            .client(client)
+            .build()
+            .unwrap();
+
+        let output = reaper.react_loop("Stop the stuck job").await.unwrap();
+        let observations = &output.trace.observations;
+        assert!(observations[0].is_error);
+        assert!(observations[0].content.contains("refusing to touch init"));
+        assert_eq!(observations[1].content, "killed 42");
+        assert_eq!(tool.0.load(Ordering::SeqCst), 1);
+        assert_eq!(*ended.lock(), vec!["kill_process: killed 42".to_string()]);
+    }
+
+    /// Records each span as `name < parent`
+    #[derive(Clone, Default)]
+    struct SpanTree(Arc<parking_lot::Mutex<Vec<String>>>);
+
+    impl<S> tracing_subscriber::Layer<S> for SpanTree
+    where
+        S: tracing::Subscriber + for<'a> tracing_subscriber::registry::LookupSpan<'a>,
+    {
+        fn on_new_span(
+            &self,
+            _attrs: &tracing::span::Attributes<'_>,
+            id: &tracing::span::Id,
+            ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let span = ctx.span(id).unwrap();
+            let parent = span.parent().map(|p| p.name()).unwrap_or("-");
+            self.0.lock().push(format!("{} < {}", span.name(), parent));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spans_nest_turns_and_tool_calls() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let spans = SpanTree::default();
+        let _guard = tracing::subscriber::set_default(tracing_subscriber::registry().with(spans.clone()));
+        let client = scripted(&[
+            "Action: kill_process\nAction Input: {\"pid\": 42}",
+            "Final answer: done",
+        ]);
+        let reaper = Agent::builder()
+            .name("Reaper")
+            .system_prompt("You are a test agent.")
+            .tool(Arc::new(KillTool::default()))
+            .client(client)
+            .build()
+            .unwrap();
+        reaper.react_loop("Stop 42").await.unwrap();
+
+        assert_eq!(
+            *spans.0.lock(),
+            vec!["react_loop < -", "turn < react_loop", "tool_call < turn", "turn < react_loop"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_images_attached_to_input() {
+        let client = scripted(&["Final answer: a login screen"]);
+        let viewer = Agent::builder()
+            .name("Viewer")
+            .system_prompt("You are a test agent.")
+            .client(client.clone())
+            .build()
+            .unwrap();
+
+        let screenshot = ContentPart::image_base64("image/png", "iVBORw0KGgo=");
+        viewer
+            .react_loop_with_images("What is on screen?", vec![screenshot.clone()])
+            .await
+            .unwrap();
+        let request = client.last_request().unwrap();
+        let input = request.messages.last().unwrap();
+        assert_eq!(input.content, "What is on screen?");
+        assert_eq!(input.parts, vec![screenshot]);
+    }
+
+    #[tokio::test]
+    async fn test_tool_choice_forces_first_turn_only() {
+        let client = scripted(&["thinking", "Final answer: done"]);
+        let agent = Agent::builder()
+            .name("Forced")
+            .system_prompt("You are a test agent.")
+            .tool(Arc::new(crate::tools::EchoTool))
+            .tool_protocol(ToolProtocol::OpenAiJson)
+            .tool_choice(ToolChoice::named("echo"))
+            .client(client.clone())
+            .build()
+            .unwrap();
+
+        let messages = [Message::user("ping")];
+        let run_id = TraceId::new();
+        agent.generate_thought(&messages, run_id, 0, None).await.unwrap();
+        let request = client.last_request().unwrap();
+        assert_eq!(request.tool_choice, Some(ToolChoice::named("echo")));
+
+        agent.generate_thought(&messages, run_id, 1, None).await.unwrap();
+        let request = client.last_request().unwrap();
+        assert!(request.tools.is_some());
+        assert_eq!(request.tool_choice, None);
+    }
+
+    #[tokio::test]
+    async fn test_provider_preferences_sent_with_requests() {
+        let client = Arc::new(MockLlmClient::new().with_reply("Final answer: done"));
+        let preferences = ProviderPreferences::new()
+            .with_only(vec!["anthropic".to_string()])
+            .with_data_collection(crate::config::DataCollection::Deny);
+        let agent = Agent::builder()
+            .name("Compliant")
+            .system_prompt("You are a test agent.")
+            .provider_preferences(preferences.clone())
+            .client(client.clone())
+            .build()
+            .unwrap();
+
+        agent.react_loop("hi").await.unwrap();
+        assert_eq!(client.last_request().unwrap().provider, Some(preferences));
+    }
+
+    /// Tool that sleeps, tracks peak concurrency and fails on request
+    #[derive(Default)]
+    struct SlowTool {
+        running: std::sync::atomic::AtomicUsize,
+        peak: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Tool for SlowTool {
+        fn id(&self) -> &str {
+            "slow"
+        }
+
+        fn name(&self) -> &str {
+            "Slow"
+        }
+
+        fn description(&self) -> &str {
+            "Sleeps, then echoes"
+        }
+
+        fn input_schema(&self) -> crate::tools::JsonSchema {
+            crate::tools::JsonSchema::empty()
+        }
+
+        async fn execute(&self, params: serde_json::Value, _ctx: &ToolContext) -> Result<crate::tools::ToolOutput> {
+            use std::sync::atomic::Ordering;
+
+            let running = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(running, Ordering::SeqCst);
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            self.running.fetch_sub(1, Ordering::SeqCst);
+
+            match params["name"].as_str() {
+                Some("bad") => Err(Error::tool_execution("slow", "boom")),
+                name => Ok(crate::tools::ToolOutput::success(format!("done {}", name.unwrap_or("?")))),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parallel_tool_calls() {
+        let client = scripted(&[
+            "Action: slow\nAction Input: {\"name\": \"a\"}\n\
+             Action: slow\nAction Input: {\"name\": \"bad\"}\n\
+             Action: slow\nAction Input: {\"name\": \"c\"}",
+            "Final answer: done",
+        ]);
+        let tool = Arc::new(SlowTool::default());
+        let agent = Agent::builder()
+            .name("Parallel")
+            .system_prompt("You are a test agent.")
+            .tool(tool.clone())
+            .parallel_tool_calls(true)
+            .max_concurrent_tools(2)
+            .client(client.clone())
+            .build()
+            .unwrap();
+
+        let output = agent.react_loop("go").await.unwrap();
+        let tool_calls = output
+            .trace
+            .actions
+            .iter()
+            .filter(|a| matches!(a, Action::ToolCall { .. }))
+            .count();
+        assert_eq!(tool_calls, 3);
+
+        let observations: Vec<&str> = output.trace.observations.iter().map(|o| o.content.as_str()).collect();
+        assert_eq!(observations[0], "done a");
+        assert!(output.trace.observations[1].is_error);
+        assert_eq!(observations[2], "done c");
+        assert_eq!(tool.peak.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+        let request = client.last_request().unwrap();
+        let results = request.messages.iter().filter(|m| m.content.starts_with("Observation:")).count();
+        assert_eq!(results, 3);
+    }
+
+    /// Response carrying native tool calls `(id, tool, arguments)`
+    fn native_calls(calls: &[(&str, &str, &str)]) -> CompletionResponse {
+        let mut response = reply("");
+        response.choices[0].message.tool_calls = Some(
+            calls
+                .iter()
+                .map(|(id, name, arguments)| crate::openrouter::ToolCall {
+                    id: id.to_string(),
+                    tool_type: "function".to_string(),
+                    function: crate::openrouter::FunctionCall {
+                        name: name.to_string(),
+                        arguments: arguments.to_string(),
+                    },
+                })
+                .collect(),
+        );
+        response
+    }
+
+    /// IDs echoed in assistant messages and IDs answered by tool messages
+    fn echoed_and_answered(request: &CompletionRequest) -> (Vec<String>, Vec<String>) {
+        let echoed = request
+            .messages
+            .iter()
+            .flat_map(|m| m.tool_calls.iter().flatten())
+            .map(|call| call.id.clone())
+            .collect();
+        let answered = request.messages.iter().filter_map(|m| m.tool_call_id.clone()).collect();
+        (echoed, answered)
+    }
+
+    #[tokio::test]
+    async fn test_parallel_native_calls_answer_every_echoed_id() {
+        let client = Arc::new(
+            MockLlmClient::new()
+                .with_response(MockResponse::Response(native_calls(&[
+                    ("call_a", "echo", r#"{"message": "a"}"#),
+                    ("call_x", "no_such_tool", "{}"),
+                    ("call_b", "echo", r#"{"message": "b"}"#),
+                ])))
+                .with_reply("Final answer: done"),
+        );
+        let agent = Agent::builder()
+            .name("Parallel")
+            .system_prompt("You are a test agent.")
+            .tool(Arc::new(crate::tools::EchoTool))
+            .tool_protocol(ToolProtocol::OpenAiJson)
+            .parallel_tool_calls(true)
+            .client(client.clone())
+            .build()
+            .unwrap();
+
+        agent.react_loop("go").await.unwrap();
+        let (echoed, answered) = echoed_and_answered(&client.last_request().unwrap());
+        assert_eq!(echoed, ["call_a", "call_b"]);
+        assert_eq!(answered, ["call_a", "call_b"]);
+    }
+
+    #[tokio::test]
+    async fn test_single_native_call_replies_to_executed_id() {
+        let agent = |client: Arc<MockLlmClient>| {
+            Agent::builder()
+                .name("Native")
+                .system_prompt("You are a test agent.")
+                .tool(Arc::new(crate::tools::EchoTool))
+                .tool_protocol(ToolProtocol::OpenAiJson)
+                .client(client)
+                .build()
+                .unwrap()
+        };
+
+        // decide_action skips the unknown tool and runs the second call
+        let client = Arc::new(
+            MockLlmClient::new()
+                .with_response(MockResponse::Response(native_calls(&[
+                    ("call_1", "no_such_tool", "{}"),
+                    ("call_2", "echo", r#"{"message": "hi"}"#),
+                ])))
+                .with_reply("Final answer: done"),
+        );
+        agent(client.clone()).react_loop("go").await.unwrap();
+        let (echoed, answered) = echoed_and_answered(&client.last_request().unwrap());
+        assert_eq!(echoed, ["call_2"]);
+        assert_eq!(answered, ["call_2"]);
+
+        // Malformed arguments are answered for that call alone
+        let client = Arc::new(
+            MockLlmClient::new()
+                .with_response(MockResponse::Response(native_calls(&[
+                    ("call_1", "echo", r#"{"message": hi"#),
+                    ("call_2", "no_such_tool", "{}"),
+                ])))
+                .with_reply("Final answer: done"),
+        );
+        let output = agent(client.clone()).react_loop("go").await.unwrap();
+        assert!(output.trace.observations[0].is_error);
+        let (echoed, answered) = echoed_and_answered(&client.last_request().unwrap());
+        assert_eq!(echoed, ["call_1"]);
+        assert_eq!(answered, ["call_1"]);
+    }
+
+    #[tokio::test]
+    async fn test_prompt_log_records_each_turn() {
+        use crate::prompt_log::InMemoryPromptLog;
+
+        let log = Arc::new(InMemoryPromptLog::new());
+        let client = scripted(&[
+            "Action: echo\nAction Input: {\"message\": \"token=abc123\"}",
+            "Final answer: done",
+        ]);
+        let agent = Agent::builder()
+            .name("Audited")
+            .system_prompt("You are a test agent.")
+            .tool(Arc::new(crate::tools::EchoTool))
+            .client(client)
+            .prompt_log(log.clone())
+            .redact_prompt_log(SecretRedactor::new())
+            .build()
+            .unwrap();
+
+        agent.react_loop("go").await.unwrap();
+
+        let records = log.records();
+        assert_eq!(records.len(), 2);
+        let run = log.run(records[0].run_id);
+        assert_eq!(run.iter().map(|r| r.turn).collect::<Vec<_>>(), vec![0, 1]);
+        let last = run[1].request.messages.last().unwrap();
+        assert!(last.content.contains("[REDACTED]"));
+        assert!(!last.content.contains("abc123"));
+        assert!(run[1].usage.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_memory_window_strategy_used_without_builder_window() {
+        let memory = AgentMemory::new(
+            AgentId::new(),
+            MemoryConfig {
+                storage_backend: StorageBackend::Memory,
+                window_strategy: HistoryWindow::LastN(1),
+                ..MemoryConfig::default()
+            },
+        );
+        memory.add_message(Role::User, "first".to_string()).await;
+        memory.add_message(Role::Assistant, "second".to_string()).await;
+
+        let configured = agent("Windowed", "Final answer: ok").memory(memory.clone()).build().unwrap();
+        assert_eq!(configured.history_window, HistoryWindow::LastN(1));
+        let (messages, _) = configured.initial_messages("third", &[]).await;
+        let contents: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents[1..], ["second", "third"]);
+
+        let overridden = agent("Windowed", "Final answer: ok")
+            .memory(memory)
+            .history_window(HistoryWindow::All)
+            .build()
+            .unwrap();
+        assert_eq!(overridden.history_window, HistoryWindow::All);
+    }
+
+    #[tokio::test]
+    async fn test_history_window_in_metadata() {
+        let agent = agent("Chatty", "Final answer: noted")
+            .history_window(HistoryWindow::LastN(2))
+            .build()
+            .unwrap();
+
+        let first = agent.react_loop("first").await.unwrap();
+        assert_eq!(first.metadata["history_window"]["selected"], 0);
+
+        agent.react_loop("second").await.unwrap();
+        let third = agent.react_loop("third").await.unwrap();
+        assert_eq!(third.metadata["history_window"]["window"], "last_n(2)");
+        assert_eq!(third.metadata["history_window"]["selected"], 2);
+        assert_eq!(third.metadata["history_window"]["available"], 4);
+    }
+
+    #[test]
+    fn test_react_loop_blocking() {
+        let agent = agent("Scripted", "Final answer: 42").build().unwrap();
+        let output = agent.react_loop_blocking("What is the answer?").unwrap();
+        assert_eq!(output.content, "42");
+    }
+
+    #[tokio::test]
+    async fn test_observation_guardrail_redacts_before_trace() {
+        use crate::guardrails::SecretRedactionGuardrail;
+
+        let agent = agent("Inspector", "Final answer: done")
+            .observation_guardrail(Arc::new(SecretRedactionGuardrail::new()))
+            .build()
+            .unwrap();
+        let ctx = GuardrailContext::new(agent.id);
+        let observation = Observation::new("AWS_SECRET_ACCESS_KEY=abc123xyz\nLANG=C");
+
+        let guarded = agent.guard_observation(observation, &ctx).await.unwrap();
+        assert!(!guarded.content.contains("abc123xyz"));
+        assert!(guarded.content.contains("LANG=C"));
+    }
+
+    struct MaxLength(usize);
+
+    #[async_trait]
+    impl OutputGuardrail for MaxLength {
+        fn id(&self) -> &str {
+            "max_length"
+        }
+
+        async fn check(&self, output: &AgentOutput, _ctx: &GuardrailContext) -> Result<GuardrailResult> {
+            if output.content.len() > self.0 {
+                return Ok(GuardrailResult::fail(format!("{} characters", output.content.len())));
+            }
+            Ok(GuardrailResult::pass(format!("{} characters", output.content.len())))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_guardrails_run_in_order() {
+        use crate::guardrails::RedactionGuardrail;
+
+        let guarded = |max| {
+            agent("Reporter", "Final answer: contact admin@example.com")
+                .add_output_guardrail(Arc::new(RedactionGuardrail::new()))
+                .add_output_guardrail(Arc::new(MaxLength(max)))
+                .build()
+                .unwrap()
+        };
+
+        // The length check sees the redacted text
+        let output = guarded(40).react_loop("Who owns the host?").await.unwrap();
+        let ran: Vec<(&str, bool)> = output
+            .guardrail_results
+            .iter()
+            .map(|r| (r.guardrail.as_str(), r.passed))
+            .collect();
+        assert_eq!(ran, [("redaction", true), ("max_length", true)]);
+        assert_eq!(output.guardrail_results[1].reasoning, "24 characters");
+
+        let err = guarded(10).react_loop("Who owns the host?").await.unwrap_err();
+        assert!(matches!(err, Error::GuardrailViolation { guardrail, .. } if guardrail == "max_length"));
+    }
+
+    #[tokio::test]
+    async fn test_output_guardrail_redacts_response() {
+        use crate::guardrails::RedactionGuardrail;
+
+        let agent = agent("Reporter", "Final answer: backdoor beacons to 192.168.4.20")
+            .output_guardrail(Arc::new(RedactionGuardrail::new()))
+            .build()
+            .unwrap();
+
+        let output = agent.react_loop("Summarize").await.unwrap();
+        assert_eq!(output.content, "backdoor beacons to [REDACTED:private_ip]");
+        assert!(output.raw_content.contains("192.168.4.20"));
+        assert_eq!(output.metadata["redactions"]["redaction"], 1);
+    }
+}
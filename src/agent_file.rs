@@ -8,7 +8,8 @@
 
 use crate::agent::Agent;
 use crate::error::{Error, Result};
-use crate::memory::{AgentMemory, MemoryBlock, MemoryConfig, MessageEntry};
+use crate::memory::{AgentMemory, MemoryBlock, MemoryBlockId, MemoryConfig, MessageEntry};
+use crate::orchestrator::OrchestratorSnapshot;
 use crate::react::ReActConfig;
 use crate::types::AgentId;
 use chrono::{DateTime, Utc};
@@ -19,11 +20,22 @@ use std::io::{Read, Write};
 use std::path::Path;
 
 /// Agent File format version
-pub const AGENT_FILE_VERSION: &str = "1.0.0";
+pub const AGENT_FILE_VERSION: &str = "1.1.0";
+
+/// Schema version written to, and required of, every agent file
+///
+/// Bump it whenever a change to the format would make older files
+/// deserialize into the wrong shape. Files written before the field existed
+/// read as version 0.
+pub const AGENT_FILE_SCHEMA_VERSION: u32 = 1;
 
 /// Complete serializable agent state
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentFile {
+    /// Schema version, checked before the rest of the file is read
+    #[serde(default)]
+    pub schema_version: u32,
+
     /// File format version
     pub version: String,
 
@@ -110,16 +122,20 @@ pub struct MemoryState {
 }
 
 impl AgentFile {
-    /// Create a new agent file from an agent
-    pub fn from_agent(
+    /// Create a new agent file from an agent and its memory blocks and messages
+    pub async fn from_agent(
         agent: &Agent,
         memory: &AgentMemory,
         client_type: String,
         client_endpoint: Option<String>,
     ) -> Self {
         let now = Utc::now();
+        let mut blocks = memory.in_context_blocks().await;
+        blocks.extend(memory.out_of_context_blocks().await);
+        blocks.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.label.cmp(&b.label)));
 
         Self {
+            schema_version: AGENT_FILE_SCHEMA_VERSION,
             version: AGENT_FILE_VERSION.to_string(),
             metadata: AgentMetadata {
                 agent_id: agent.id.to_string(),
@@ -142,14 +158,34 @@ impl AgentFile {
             },
             memory: MemoryState {
                 config: memory.config.clone(),
-                blocks: Vec::new(), // Would be populated from memory
-                shared_block_ids: Vec::new(),
+                blocks,
+                shared_block_ids: memory
+                    .shared_block_ids()
+                    .await
+                    .iter()
+                    .map(ToString::to_string)
+                    .collect(),
             },
-            messages: Vec::new(), // Would be populated from memory.message_history
+            messages: memory.messages().await,
             custom_data: HashMap::new(),
         }
     }
 
+    /// Rebuild the agent's memory: blocks, shared block references and messages
+    pub async fn to_memory(&self) -> Result<AgentMemory> {
+        let memory = AgentMemory::new(self.agent_id()?, self.memory.config.clone());
+        for block in &self.memory.blocks {
+            memory.add_block(block.clone()).await?;
+        }
+        for id in &self.memory.shared_block_ids {
+            let uuid = uuid::Uuid::parse_str(id)
+                .map_err(|e| Error::config(format!("Invalid shared block ID '{}': {}", id, e)))?;
+            memory.attach_shared_block(MemoryBlockId::from_uuid(uuid)).await;
+        }
+        memory.replace_messages(self.messages.clone()).await;
+        Ok(memory)
+    }
+
     /// Save agent file to disk
     pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
         let serialized = serde_json::to_string_pretty(self)?;
@@ -161,19 +197,9 @@ impl AgentFile {
     /// Load agent file from disk
     pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
         let mut file = File::open(path)?;
-        let mut contents = String::new();
-        file.read_to_string(&mut contents)?;
-        let agent_file: AgentFile = serde_json::from_str(&contents)?;
-
-        // Verify version compatibility
-        if agent_file.version != AGENT_FILE_VERSION {
-            return Err(Error::config(format!(
-                "Incompatible agent file version: expected {}, got {}",
-                AGENT_FILE_VERSION, agent_file.version
-            )));
-        }
-
-        Ok(agent_file)
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+        Self::from_bytes(&contents)
     }
 
     /// Serialize to bytes (for network transfer)
@@ -181,9 +207,21 @@ impl AgentFile {
         Ok(serde_json::to_vec(self)?)
     }
 
-    /// Deserialize from bytes
+    /// Deserialize from bytes, rejecting other schema versions
+    ///
+    /// The schema version is checked on the raw JSON first, so a file in
+    /// another format fails with a version error rather than a confusing
+    /// error about whichever field happens not to match.
     pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
-        Ok(serde_json::from_slice(bytes)?)
+        let value: serde_json::Value = serde_json::from_slice(bytes)?;
+        let schema_version = value.get("schema_version").and_then(|v| v.as_u64()).unwrap_or(0);
+        if schema_version != u64::from(AGENT_FILE_SCHEMA_VERSION) {
+            return Err(Error::config(format!(
+                "Incompatible agent file schema version: expected {}, got {}",
+                AGENT_FILE_SCHEMA_VERSION, schema_version
+            )));
+        }
+        Ok(serde_json::from_value(value)?)
     }
 
     /// Get agent ID
@@ -195,6 +233,7 @@ impl AgentFile {
 }
 
 /// Agent checkpoint manager
+#[derive(Debug, Clone)]
 pub struct CheckpointManager {
     /// Base directory for checkpoints
     checkpoint_dir: String,
@@ -209,14 +248,14 @@ impl CheckpointManager {
     }
 
     /// Create a checkpoint for an agent
-    pub fn checkpoint(
+    pub async fn checkpoint(
         &self,
         agent: &Agent,
         memory: &AgentMemory,
         client_type: String,
         client_endpoint: Option<String>,
     ) -> Result<String> {
-        let agent_file = AgentFile::from_agent(agent, memory, client_type, client_endpoint);
+        let agent_file = AgentFile::from_agent(agent, memory, client_type, client_endpoint).await;
 
         // Create checkpoint filename with timestamp
         let timestamp = Utc::now().format("%Y%m%d_%H%M%S");
@@ -266,12 +305,59 @@ impl CheckpointManager {
         AgentFile::load(path)
     }
 
+    /// Restore an agent's configuration and memory from a checkpoint
+    ///
+    /// `path` is relative to the checkpoint directory unless absolute. Fails
+    /// if the file's schema version differs from [`AGENT_FILE_SCHEMA_VERSION`].
+    pub async fn restore(&self, path: impl AsRef<Path>) -> Result<(AgentConfig, AgentMemory)> {
+        let agent_file = AgentFile::load(Path::new(&self.checkpoint_dir).join(path))?;
+        let memory = agent_file.to_memory().await?;
+        Ok((agent_file.config, memory))
+    }
+
     /// Delete a checkpoint
     pub fn delete_checkpoint(&self, filename: &str) -> Result<()> {
         let path = Path::new(&self.checkpoint_dir).join(filename);
         std::fs::remove_file(path)?;
         Ok(())
     }
+
+    /// Save an orchestrator snapshot as JSON
+    pub fn save_snapshot(&self, snapshot: &OrchestratorSnapshot) -> Result<String> {
+        let filename = format!(
+            "{}_{}.snapshot.json",
+            snapshot.pattern_type,
+            snapshot.created_at.format("%Y%m%d_%H%M%S_%3f")
+        );
+        std::fs::create_dir_all(&self.checkpoint_dir)?;
+        let path = Path::new(&self.checkpoint_dir).join(&filename);
+        std::fs::write(path, serde_json::to_string_pretty(snapshot)?)?;
+        Ok(filename)
+    }
+
+    /// Load an orchestrator snapshot
+    pub fn load_snapshot(&self, filename: &str) -> Result<OrchestratorSnapshot> {
+        let path = Path::new(&self.checkpoint_dir).join(filename);
+        Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+    }
+
+    /// Most recent snapshot saved for a pattern, if any
+    pub fn latest_snapshot(&self, pattern_type: &str) -> Result<Option<OrchestratorSnapshot>> {
+        let dir = Path::new(&self.checkpoint_dir);
+        if !dir.exists() {
+            return Ok(None);
+        }
+
+        let prefix = format!("{}_", pattern_type);
+        let mut latest: Option<String> = None;
+        for entry in std::fs::read_dir(dir)? {
+            let filename = entry?.file_name().to_string_lossy().to_string();
+            if filename.starts_with(&prefix) && filename.ends_with(".snapshot.json") {
+                latest = latest.max(Some(filename));
+            }
+        }
+        latest.map(|filename| self.load_snapshot(&filename)).transpose()
+    }
 }
 
 #[cfg(test)]
@@ -279,6 +365,7 @@ mod tests {
     use super::*;
     use crate::agent::AgentBuilder;
     use crate::types::AgentId;
+    use std::sync::Arc;
     use tempfile::tempdir;
 
     #[test]
@@ -286,6 +373,7 @@ mod tests {
         let now = Utc::now();
 
         let agent_file = AgentFile {
+            schema_version: AGENT_FILE_SCHEMA_VERSION,
             version: AGENT_FILE_VERSION.to_string(),
             metadata: AgentMetadata {
                 agent_id: AgentId::new().to_string(),
@@ -323,6 +411,65 @@ mod tests {
         assert_eq!(agent_file.metadata.name, deserialized.metadata.name);
     }
 
+    #[tokio::test]
+    async fn test_checkpoint_restore_roundtrip() {
+        let temp_dir = tempdir().unwrap();
+        let manager = CheckpointManager::new(temp_dir.path().to_str().unwrap());
+        let client = crate::vllm::VllmClient::new(crate::vllm::VllmConfig::new("http://localhost:8000")).unwrap();
+        let agent = AgentBuilder::new()
+            .name("Research Assistant")
+            .model("test-model")
+            .system_prompt("You are a research assistant.")
+            .temperature(0.3)
+            .client(Arc::new(client))
+            .build()
+            .unwrap();
+
+        let memory = AgentMemory::new(agent.id, MemoryConfig::default());
+        memory
+            .add_block(MemoryBlock::with_description("persona", "Who I am", "A careful researcher"))
+            .await
+            .unwrap();
+        let archived = memory.add_block(MemoryBlock::new("scratch", "old notes")).await.unwrap();
+        memory.move_out_of_context(archived).await.unwrap();
+        memory.attach_shared_block(MemoryBlockId::new()).await;
+        memory.add_message(crate::openrouter::Role::User, "Find papers on CRDTs".to_string()).await;
+        memory.add_message(crate::openrouter::Role::Assistant, "Here are three.".to_string()).await;
+
+        let filename = manager
+            .checkpoint(&agent, &memory, "vllm".to_string(), None)
+            .await
+            .unwrap();
+        let (config, restored) = manager.restore(&filename).await.unwrap();
+
+        assert_eq!(config.system_prompt, agent.system_prompt);
+        assert_eq!(config.model, "test-model");
+        assert_eq!(config.temperature, 0.3);
+        assert_eq!(restored.agent_id, agent.id);
+        let sorted = |mut blocks: Vec<MemoryBlock>| {
+            blocks.sort_by(|a, b| a.label.cmp(&b.label));
+            blocks
+        };
+        assert_eq!(sorted(restored.in_context_blocks().await), sorted(memory.in_context_blocks().await));
+        assert_eq!(restored.out_of_context_blocks().await, memory.out_of_context_blocks().await);
+        assert_eq!(restored.shared_block_ids().await, memory.shared_block_ids().await);
+        assert_eq!(restored.messages().await, memory.messages().await);
+    }
+
+    #[tokio::test]
+    async fn test_restore_rejects_other_schema_versions() {
+        let temp_dir = tempdir().unwrap();
+        let manager = CheckpointManager::new(temp_dir.path().to_str().unwrap());
+
+        std::fs::write(temp_dir.path().join("legacy.af"), r#"{"version": "1.0.0", "metadata": {}}"#).unwrap();
+        let err = manager.restore("legacy.af").await.unwrap_err().to_string();
+        assert!(err.contains("schema version: expected 1, got 0"), "{}", err);
+
+        std::fs::write(temp_dir.path().join("future.af"), r#"{"schema_version": 7}"#).unwrap();
+        let err = manager.restore("future.af").await.unwrap_err().to_string();
+        assert!(err.contains("expected 1, got 7"), "{}", err);
+    }
+
     #[test]
     fn test_checkpoint_manager() {
         let temp_dir = tempdir().unwrap();
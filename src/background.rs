@@ -3,19 +3,28 @@
 //! This module provides:
 //! - Asynchronous agent execution with run IDs
 //! - Resumable streaming with sequence IDs
+//! - Push subscriptions that replay history, then follow live events
 //! - Cursor-based pagination for results
 //! - Connection recovery and state management
 //! - Background job tracking
+//! - Batched fan-out/fan-in with an optional worker limit
+//! - Optional persistence so runs survive a process restart
 
 use crate::agent::{Agent, AgentOutput};
 use crate::error::{Error, Result};
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use futures::stream::Stream;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio::sync::{RwLock, Semaphore};
 use uuid::Uuid;
 
+/// Live events buffered per run for subscribers that fall behind
+const SUBSCRIBER_BUFFER: usize = 256;
+
 /// Unique identifier for a background run
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct RunId(Uuid);
@@ -25,6 +34,17 @@ impl RunId {
     pub fn new() -> Self {
         Self(Uuid::new_v4())
     }
+
+    /// Get the underlying UUID
+    pub fn as_uuid(&self) -> Uuid {
+        self.0
+    }
+}
+
+impl From<Uuid> for RunId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
 }
 
 impl Default for RunId {
@@ -39,6 +59,16 @@ impl std::fmt::Display for RunId {
     }
 }
 
+impl std::str::FromStr for RunId {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Uuid::parse_str(s)
+            .map(Self)
+            .map_err(|e| Error::config(format!("Invalid run ID '{}': {}", s, e)))
+    }
+}
+
 /// Sequence ID for ordering events within a run
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct SeqId(u64);
@@ -132,6 +162,13 @@ pub enum RunEventType {
     Progress,
 }
 
+impl RunEventType {
+    /// Whether this event ends the run (no events follow it)
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Completed | Self::Failed)
+    }
+}
+
 /// Metadata about a background run
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RunMetadata {
@@ -166,6 +203,28 @@ pub struct RunMetadata {
     pub metadata: HashMap<String, String>,
 }
 
+/// Persistent store for background runs and their events
+///
+/// Implemented by the SQL backends in [`crate::storage`] (with the `storage`
+/// feature), so runs written by one process can be recovered by the next.
+#[async_trait]
+pub trait RunStorage: Send + Sync {
+    /// Save or update a run's metadata
+    async fn save_run(&self, metadata: &RunMetadata) -> Result<()>;
+
+    /// Save an event of a run
+    async fn save_run_event(&self, run_id: RunId, event: &RunEvent) -> Result<()>;
+
+    /// Load the metadata of every stored run
+    async fn load_runs(&self) -> Result<Vec<RunMetadata>>;
+
+    /// Load a run's events in sequence order
+    async fn load_run_events(&self, run_id: RunId) -> Result<Vec<RunEvent>>;
+
+    /// Delete a run and its events
+    async fn delete_run(&self, run_id: RunId) -> Result<()>;
+}
+
 /// A background run with all its state
 struct BackgroundRun {
     /// Run metadata
@@ -176,12 +235,64 @@ struct BackgroundRun {
 
     /// Optional handle to the background task
     task_handle: Option<tokio::task::JoinHandle<Result<AgentOutput>>>,
+
+    /// Live feed of new events for subscribers
+    live: broadcast::Sender<RunEvent>,
+}
+
+impl BackgroundRun {
+    /// Create a run with no events yet
+    fn new(metadata: RunMetadata, events: Vec<RunEvent>) -> Self {
+        Self {
+            metadata,
+            events,
+            task_handle: None,
+            live: broadcast::channel(SUBSCRIBER_BUFFER).0,
+        }
+    }
+
+    /// Append an event, advancing the run's sequence counter
+    fn push_event(&mut self, event_type: RunEventType, data: serde_json::Value) -> RunEvent {
+        let event = RunEvent {
+            seq_id: self.metadata.last_seq_id,
+            timestamp: Utc::now(),
+            event_type,
+            data,
+        };
+        self.events.push(event.clone());
+        self.metadata.last_seq_id = self.metadata.last_seq_id.next();
+        self.metadata.total_events += 1;
+        // No subscribers is not an error
+        let _ = self.live.send(event.clone());
+        event
+    }
+}
+
+/// Mirror a run's metadata and new events to storage, logging failures
+async fn persist(storage: Option<&dyn RunStorage>, metadata: &RunMetadata, events: &[RunEvent]) {
+    let Some(storage) = storage else {
+        return;
+    };
+    for event in events {
+        if let Err(e) = storage.save_run_event(metadata.run_id, event).await {
+            tracing::warn!("Failed to persist event {} of run {}: {}", event.seq_id, metadata.run_id, e);
+        }
+    }
+    if let Err(e) = storage.save_run(metadata).await {
+        tracing::warn!("Failed to persist run {}: {}", metadata.run_id, e);
+    }
 }
 
 /// Manager for background runs
 pub struct BackgroundExecutor {
     /// All active and completed runs
     runs: Arc<RwLock<HashMap<RunId, BackgroundRun>>>,
+
+    /// Limits how many runs execute at once (None = unlimited)
+    workers: Option<Arc<Semaphore>>,
+
+    /// Persistent store runs are mirrored to, if any
+    storage: Option<Arc<dyn RunStorage>>,
 }
 
 impl BackgroundExecutor {
@@ -189,7 +300,84 @@ impl BackgroundExecutor {
     pub fn new() -> Self {
         Self {
             runs: Arc::new(RwLock::new(HashMap::new())),
+            workers: None,
+            storage: None,
+        }
+    }
+
+    /// Run at most `max_workers` agents at once; further runs stay queued
+    pub fn with_max_workers(self, max_workers: usize) -> Self {
+        self.with_concurrency_limit(Arc::new(Semaphore::new(max_workers.max(1))))
+    }
+
+    /// Same as [`with_max_workers`](Self::with_max_workers), named to match
+    /// [`ConcurrentOrchestrator::with_max_concurrency`](crate::orchestrator::ConcurrentOrchestrator::with_max_concurrency)
+    pub fn with_max_concurrency(self, max: usize) -> Self {
+        self.with_max_workers(max)
+    }
+
+    /// Hold a permit from `limit` for the whole of each run
+    ///
+    /// Pass the semaphore given to other executors or orchestrators to share
+    /// one cap on in-flight model calls between them.
+    pub fn with_concurrency_limit(mut self, limit: Arc<Semaphore>) -> Self {
+        self.workers = Some(limit);
+        self
+    }
+
+    /// Write run metadata and events to `storage` as they are produced
+    ///
+    /// Call [`recover`](Self::recover) on startup to reload stored runs.
+    pub fn with_storage(mut self, storage: Arc<dyn RunStorage>) -> Self {
+        self.storage = Some(storage);
+        self
+    }
+
+    /// Reload stored runs after a restart
+    ///
+    /// Runs that were queued or running when the previous process stopped
+    /// have lost their task, so they are marked failed. Returns the IDs of
+    /// those interrupted runs.
+    pub async fn recover(&self) -> Result<Vec<RunId>> {
+        let storage = self
+            .storage
+            .as_deref()
+            .ok_or_else(|| Error::config("No run storage configured"))?;
+
+        // Load without holding the lock so running tasks are not blocked on storage
+        let mut stored = Vec::new();
+        for metadata in storage.load_runs().await? {
+            let events = storage.load_run_events(metadata.run_id).await?;
+            stored.push(BackgroundRun::new(metadata, events));
+        }
+
+        let mut failed = Vec::new();
+        {
+            let mut runs = self.runs.write().await;
+            for mut run in stored {
+                let run_id = run.metadata.run_id;
+                if runs.contains_key(&run_id) {
+                    continue;
+                }
+
+                if matches!(run.metadata.status, RunStatus::Queued | RunStatus::Running) {
+                    let error = "Interrupted by process restart".to_string();
+                    run.metadata.status = RunStatus::Failed { error: error.clone() };
+                    run.metadata.completed_at = Some(Utc::now());
+                    let event = run.push_event(RunEventType::Failed, serde_json::json!({ "error": error }));
+                    failed.push((run.metadata.clone(), event));
+                }
+                runs.insert(run_id, run);
+            }
+        }
+
+        let mut interrupted = Vec::with_capacity(failed.len());
+        for (metadata, event) in failed {
+            persist(Some(storage), &metadata, &[event]).await;
+            interrupted.push(metadata.run_id);
         }
+
+        Ok(interrupted)
     }
 
     /// Start an agent execution in the background
@@ -213,42 +401,76 @@ impl BackgroundExecutor {
             metadata: HashMap::new(),
         };
 
+        persist(self.storage.as_deref(), &metadata, &[]).await;
+
+        // Register the run before its task starts so no event is dropped
+        self.runs.write().await.insert(run_id, BackgroundRun::new(metadata, Vec::new()));
+
         // Spawn background task
         let runs = self.runs.clone();
+        let workers = self.workers.clone();
+        let storage = self.storage.clone();
         let handle = tokio::spawn(async move {
+            // Stay queued while the agent's provider reports an exhausted rate limit
+            if let Some(wait) = agent.rate_limit_backoff() {
+                tokio::time::sleep(wait).await;
+            }
+
+            // Stay queued until a worker slot frees up
+            let _permit = match workers {
+                Some(workers) => workers.acquire_owned().await.ok(),
+                None => None,
+            };
+
             // Update status to Running
-            {
-                let mut runs_lock = runs.write().await;
-                if let Some(run) = runs_lock.get_mut(&run_id) {
-                    run.metadata.status = RunStatus::Running;
-                    run.metadata.started_at = Some(Utc::now());
-
-                    // Add started event
-                    let event = RunEvent {
-                        seq_id: run.metadata.last_seq_id,
-                        timestamp: Utc::now(),
-                        event_type: RunEventType::Started,
-                        data: serde_json::json!({
-                            "agent": agent.name,
-                            "input": input
-                        }),
-                    };
-                    run.events.push(event);
-                    run.metadata.last_seq_id = run.metadata.last_seq_id.next();
-                    run.metadata.total_events += 1;
-                }
+            let started = runs.write().await.get_mut(&run_id).map(|run| {
+                run.metadata.status = RunStatus::Running;
+                run.metadata.started_at = Some(Utc::now());
+
+                // Add started event
+                let event = run.push_event(
+                    RunEventType::Started,
+                    serde_json::json!({
+                        "agent": agent.name,
+                        "input": input
+                    }),
+                );
+                (run.metadata.clone(), event)
+            });
+            // Persist after releasing the lock so storage IO never blocks readers
+            if let Some((metadata, event)) = started {
+                persist(storage.as_deref(), &metadata, &[event]).await;
             }
 
-            // Execute the agent
-            let result = agent.react_loop(&input).await;
+            // Execute the agent, recording tool progress as it arrives
+            let (progress_tx, mut progress_rx) = tokio::sync::mpsc::unbounded_channel();
+            let progress_runs = runs.clone();
+            let progress_storage = storage.clone();
+            let progress_task = tokio::spawn(async move {
+                while let Some(update) = progress_rx.recv().await {
+                    let progress = progress_runs.write().await.get_mut(&run_id).map(|run| {
+                        let event = run.push_event(
+                            RunEventType::Progress,
+                            serde_json::to_value(&update).unwrap_or_default(),
+                        );
+                        (run.metadata.clone(), event)
+                    });
+                    if let Some((metadata, event)) = progress {
+                        persist(progress_storage.as_deref(), &metadata, &[event]).await;
+                    }
+                }
+            });
+            let result = agent.react_loop_with_progress(&input, progress_tx).await;
+            // The sender is gone once the loop returns; drain so progress precedes the output
+            let _ = progress_task.await;
 
             // Update status based on result
-            {
+            let finished = {
                 let mut runs_lock = runs.write().await;
                 if let Some(run) = runs_lock.get_mut(&run_id) {
                     run.metadata.completed_at = Some(Utc::now());
 
-                    match &result {
+                    let events = match &result {
                         Ok(output) => {
                             run.metadata.status = RunStatus::Completed;
 
@@ -261,30 +483,17 @@ impl BackgroundExecutor {
                                 })
                                 .count();
 
-                            // Add output event
-                            let event = RunEvent {
-                                seq_id: run.metadata.last_seq_id,
-                                timestamp: Utc::now(),
-                                event_type: RunEventType::Output,
-                                data: serde_json::json!({
-                                    "content": output.content,
-                                    "tool_calls": tool_calls
-                                }),
-                            };
-                            run.events.push(event);
-                            run.metadata.last_seq_id = run.metadata.last_seq_id.next();
-                            run.metadata.total_events += 1;
-
-                            // Add completed event
-                            let event = RunEvent {
-                                seq_id: run.metadata.last_seq_id,
-                                timestamp: Utc::now(),
-                                event_type: RunEventType::Completed,
-                                data: serde_json::json!({}),
-                            };
-                            run.events.push(event);
-                            run.metadata.last_seq_id = run.metadata.last_seq_id.next();
-                            run.metadata.total_events += 1;
+                            // Add output and completed events
+                            vec![
+                                run.push_event(
+                                    RunEventType::Output,
+                                    serde_json::json!({
+                                        "content": output.content,
+                                        "tool_calls": tool_calls
+                                    }),
+                                ),
+                                run.push_event(RunEventType::Completed, serde_json::json!({})),
+                            ]
                         }
                         Err(e) => {
                             run.metadata.status = RunStatus::Failed {
@@ -292,37 +501,47 @@ impl BackgroundExecutor {
                             };
 
                             // Add failed event
-                            let event = RunEvent {
-                                seq_id: run.metadata.last_seq_id,
-                                timestamp: Utc::now(),
-                                event_type: RunEventType::Failed,
-                                data: serde_json::json!({
+                            vec![run.push_event(
+                                RunEventType::Failed,
+                                serde_json::json!({
                                     "error": e.to_string()
                                 }),
-                            };
-                            run.events.push(event);
-                            run.metadata.last_seq_id = run.metadata.last_seq_id.next();
-                            run.metadata.total_events += 1;
+                            )]
                         }
-                    }
+                    };
+                    agent.metrics().record_background_run(
+                        &agent.name,
+                        run.metadata.total_events,
+                        result.is_ok(),
+                    );
+                    Some((run.metadata.clone(), events))
+                } else {
+                    None
                 }
+            };
+            if let Some((metadata, events)) = finished {
+                persist(storage.as_deref(), &metadata, &events).await;
             }
 
             result
         });
 
-        let run = BackgroundRun {
-            metadata,
-            events: Vec::new(),
-            task_handle: Some(handle),
-        };
-
-        let mut runs = self.runs.write().await;
-        runs.insert(run_id, run);
+        if let Some(run) = self.runs.write().await.get_mut(&run_id) {
+            run.task_handle = Some(handle);
+        }
 
         Ok(run_id)
     }
 
+    /// Start several agent executions, returning their run IDs in input order
+    pub async fn execute_batch(&self, batch: Vec<(Arc<Agent>, String)>) -> Result<Vec<RunId>> {
+        let mut run_ids = Vec::with_capacity(batch.len());
+        for (agent, input) in batch {
+            run_ids.push(self.execute_async(agent, input).await?);
+        }
+        Ok(run_ids)
+    }
+
     /// Get metadata for a run
     pub async fn get_run_metadata(&self, run_id: RunId) -> Result<RunMetadata> {
         let runs = self.runs.read().await;
@@ -356,6 +575,50 @@ impl BackgroundExecutor {
         Ok(events)
     }
 
+    /// Subscribe to a run's events as they are produced
+    ///
+    /// Events recorded so far are replayed first, then live events follow
+    /// with no gap in between. The stream ends after the run's terminal
+    /// `Completed` or `Failed` event, or when the run is cleaned up. A
+    /// subscriber that falls more than a buffer's worth of events behind
+    /// skips the events it missed; use [`stream_events`](Self::stream_events)
+    /// to fetch them.
+    pub async fn subscribe(&self, run_id: RunId) -> Result<impl Stream<Item = RunEvent> + Send + 'static> {
+        // Snapshot and subscribe under one lock so no event falls in between
+        let (history, mut live) = {
+            let runs = self.runs.read().await;
+            let run = runs
+                .get(&run_id)
+                .ok_or_else(|| Error::config(format!("Run {} not found", run_id)))?;
+            (run.events.clone(), run.live.subscribe())
+        };
+        let finished = history.last().is_some_and(|e| e.event_type.is_terminal());
+
+        Ok(async_stream::stream! {
+            for event in history {
+                yield event;
+            }
+            if finished {
+                return;
+            }
+            loop {
+                match live.recv().await {
+                    Ok(event) => {
+                        let terminal = event.event_type.is_terminal();
+                        yield event;
+                        if terminal {
+                            break;
+                        }
+                    }
+                    Err(RecvError::Lagged(missed)) => {
+                        tracing::warn!("Subscriber to run {} skipped {} events", run_id, missed);
+                    }
+                    Err(RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
     /// Get events with cursor-based pagination
     pub async fn get_events_paginated(
         &self,
@@ -394,51 +657,74 @@ impl BackgroundExecutor {
 
     /// Wait for a run to complete
     pub async fn wait_for_completion(&self, run_id: RunId) -> Result<AgentOutput> {
-        // Get the task handle
-        let handle = {
-            let mut runs = self.runs.write().await;
-            let run = runs
-                .get_mut(&run_id)
-                .ok_or_else(|| Error::config(format!("Run {} not found", run_id)))?;
-
-            run.task_handle
-                .take()
-                .ok_or_else(|| Error::config("Run already completed or handle taken".to_string()))?
-        };
+        let handle = self.take_handle(run_id).await?;
+        Self::join(handle).await
+    }
 
-        // Wait for completion
-        let result = handle
-            .await
-            .map_err(|e| Error::config(format!("Failed to join task: {}", e)))??;
+    /// Wait for several runs, returning their results in the order given
+    ///
+    /// All runs are awaited concurrently; a failed, cancelled or unknown run
+    /// yields an error in its slot without affecting the others.
+    pub async fn wait_for_all(&self, run_ids: Vec<RunId>) -> Vec<Result<AgentOutput>> {
+        let mut handles = Vec::with_capacity(run_ids.len());
+        for run_id in run_ids {
+            handles.push(self.take_handle(run_id).await);
+        }
 
-        Ok(result)
+        futures::future::join_all(handles.into_iter().map(|handle| async move {
+            match handle {
+                Ok(handle) => Self::join(handle).await,
+                Err(e) => Err(e),
+            }
+        }))
+        .await
     }
 
-    /// Cancel a running execution
-    pub async fn cancel_run(&self, run_id: RunId) -> Result<()> {
+    /// Take ownership of a run's task handle
+    async fn take_handle(&self, run_id: RunId) -> Result<tokio::task::JoinHandle<Result<AgentOutput>>> {
         let mut runs = self.runs.write().await;
-
         let run = runs
             .get_mut(&run_id)
             .ok_or_else(|| Error::config(format!("Run {} not found", run_id)))?;
 
-        if let Some(handle) = run.task_handle.take() {
-            handle.abort();
-            run.metadata.status = RunStatus::Cancelled;
-            run.metadata.completed_at = Some(Utc::now());
-
-            // Add cancelled event
-            let event = RunEvent {
-                seq_id: run.metadata.last_seq_id,
-                timestamp: Utc::now(),
-                event_type: RunEventType::Failed,
-                data: serde_json::json!({
-                    "error": "Cancelled by user"
-                }),
-            };
-            run.events.push(event);
-            run.metadata.last_seq_id = run.metadata.last_seq_id.next();
-            run.metadata.total_events += 1;
+        run.task_handle
+            .take()
+            .ok_or_else(|| Error::config("Run already completed or handle taken".to_string()))
+    }
+
+    /// Wait for a task handle to finish
+    async fn join(handle: tokio::task::JoinHandle<Result<AgentOutput>>) -> Result<AgentOutput> {
+        handle
+            .await
+            .map_err(|e| Error::config(format!("Failed to join task: {}", e)))?
+    }
+
+    /// Cancel a running execution
+    pub async fn cancel_run(&self, run_id: RunId) -> Result<()> {
+        let cancelled = {
+            let mut runs = self.runs.write().await;
+
+            let run = runs
+                .get_mut(&run_id)
+                .ok_or_else(|| Error::config(format!("Run {} not found", run_id)))?;
+
+            run.task_handle.take().map(|handle| {
+                handle.abort();
+                run.metadata.status = RunStatus::Cancelled;
+                run.metadata.completed_at = Some(Utc::now());
+
+                // Add cancelled event
+                let event = run.push_event(
+                    RunEventType::Failed,
+                    serde_json::json!({
+                        "error": "Cancelled by user"
+                    }),
+                );
+                (run.metadata.clone(), event)
+            })
+        };
+        if let Some((metadata, event)) = cancelled {
+            persist(self.storage.as_deref(), &metadata, &[event]).await;
         }
 
         Ok(())
@@ -452,8 +738,8 @@ impl BackgroundExecutor {
 
     /// Clean up completed runs older than the specified duration
     pub async fn cleanup_old_runs(&self, older_than: chrono::Duration) -> usize {
-        let mut runs = self.runs.write().await;
         let cutoff = Utc::now() - older_than;
+        let mut runs = self.runs.write().await;
 
         let to_remove: Vec<RunId> = runs
             .iter()
@@ -470,12 +756,21 @@ impl BackgroundExecutor {
             .map(|(id, _)| *id)
             .collect();
 
-        let count = to_remove.len();
-        for id in to_remove {
-            runs.remove(&id);
+        for id in &to_remove {
+            runs.remove(id);
         }
+        // Delete from storage after releasing the lock so storage IO never blocks readers
+        drop(runs);
 
-        count
+        if let Some(storage) = &self.storage {
+            for id in &to_remove {
+                if let Err(e) = storage.delete_run(*id).await {
+                    tracing::warn!("Failed to delete stored run {}: {}", id, e);
+                }
+            }
+        }
+
+        to_remove.len()
     }
 }
 
@@ -505,50 +800,21 @@ pub struct PaginatedEvents {
 mod tests {
     use super::*;
     use crate::agent::AgentBuilder;
-    use crate::llm_client::LlmClient;
-    use crate::openrouter::CompletionRequest;
+    use crate::testing::MockLlmClient;
     use crate::types::AgentId;
     use async_trait::async_trait;
 
-    // Mock client for testing
-    struct MockClient;
-
-    #[async_trait]
-    impl LlmClient for MockClient {
-        async fn complete(
-            &self,
-            _request: CompletionRequest,
-        ) -> Result<crate::openrouter::CompletionResponse> {
-            Ok(crate::openrouter::CompletionResponse {
-                id: "test".to_string(),
-                model: "test".to_string(),
-                choices: vec![crate::openrouter::Choice {
-                    message: crate::openrouter::Message {
-                        role: "assistant".to_string(),
-                        content: Some("Test response".to_string()),
-                        tool_calls: None,
-                    },
-                    finish_reason: Some("stop".to_string()),
-                    index: 0,
-                }],
-                usage: None,
-            })
-        }
-
-        async fn stream(
-            &self,
-            _request: CompletionRequest,
-        ) -> Result<crate::openrouter::CompletionStream> {
-            Err(Error::config("Streaming not supported in mock".to_string()))
-        }
-
-        fn client_type(&self) -> &str {
-            "mock"
-        }
+    fn mock_client() -> Arc<MockLlmClient> {
+        Arc::new(MockLlmClient::new().with_fallback_reply("Test response"))
+    }
 
-        fn endpoint(&self) -> &str {
-            "http://localhost"
-        }
+    /// Client that calls the `slow` tool once, then answers
+    fn tool_then_answer() -> Arc<MockLlmClient> {
+        Arc::new(
+            MockLlmClient::new()
+                .with_reply("Action: slow\nAction Input: {}")
+                .with_reply("Final answer: done"),
+        )
     }
 
     #[tokio::test]
@@ -558,8 +824,9 @@ mod tests {
         let agent = Arc::new(
             AgentBuilder::new()
                 .name("Test Agent")
+                .system_prompt("You are a test agent.")
                 .model("test")
-                .client(Arc::new(MockClient))
+                .client(mock_client())
                 .build()
                 .unwrap(),
         );
@@ -583,8 +850,9 @@ mod tests {
         let agent = Arc::new(
             AgentBuilder::new()
                 .name("Test Agent")
+                .system_prompt("You are a test agent.")
                 .model("test")
-                .client(Arc::new(MockClient))
+                .client(mock_client())
                 .build()
                 .unwrap(),
         );
@@ -608,8 +876,9 @@ mod tests {
         let agent = Arc::new(
             AgentBuilder::new()
                 .name("Test Agent")
+                .system_prompt("You are a test agent.")
                 .model("test")
-                .client(Arc::new(MockClient))
+                .client(mock_client())
                 .build()
                 .unwrap(),
         );
@@ -628,4 +897,325 @@ mod tests {
 
         assert!(page1.events.len() <= 2);
     }
+
+    #[tokio::test]
+    async fn test_batch_execution() {
+        let executor = BackgroundExecutor::new().with_max_workers(2);
+
+        let agent = |name: &str| {
+            Arc::new(
+                AgentBuilder::new()
+                    .name(name)
+                    .system_prompt("You are a test agent.")
+                    .model("test")
+                    .client(mock_client())
+                    .build()
+                    .unwrap(),
+            )
+        };
+
+        let batch = ["A", "B", "C"]
+            .iter()
+            .map(|name| (agent(name), format!("Input for {}", name)))
+            .collect();
+        let run_ids = executor.execute_batch(batch).await.unwrap();
+        assert_eq!(run_ids.len(), 3);
+
+        let mut run_ids_with_unknown = run_ids.clone();
+        run_ids_with_unknown.insert(1, RunId::new());
+
+        let results = executor.wait_for_all(run_ids_with_unknown).await;
+        assert_eq!(results.len(), 4);
+        assert!(results[1].is_err());
+        assert_eq!(results.iter().filter(|r| r.is_ok()).count(), 3);
+
+        for (run_id, name) in run_ids.iter().zip(["A", "B", "C"]) {
+            let metadata = executor.get_run_metadata(*run_id).await.unwrap();
+            assert_eq!(metadata.agent_name, name);
+            assert_eq!(metadata.status, RunStatus::Completed);
+        }
+    }
+
+    struct SlowTool;
+
+    #[async_trait]
+    impl crate::tools::Tool for SlowTool {
+        fn id(&self) -> &str {
+            "slow"
+        }
+
+        fn name(&self) -> &str {
+            "Slow"
+        }
+
+        fn description(&self) -> &str {
+            "Takes a while"
+        }
+
+        fn input_schema(&self) -> crate::tools::JsonSchema {
+            crate::tools::JsonSchema::empty()
+        }
+
+        async fn execute(
+            &self,
+            _params: serde_json::Value,
+            ctx: &crate::tools::ToolContext,
+        ) -> Result<crate::tools::ToolOutput> {
+            ctx.progress(50.0, "capturing 30s/60s");
+            ctx.progress(100.0, "capturing 60s/60s");
+            Ok(crate::tools::ToolOutput::success("captured"))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exhausted_rate_limit_holds_run_in_queue() {
+        use crate::openrouter::RateLimitInfo;
+
+        let limit = RateLimitInfo {
+            remaining_requests: Some(0),
+            reset_at: Some(Utc::now() + chrono::Duration::milliseconds(400)),
+            ..RateLimitInfo::default()
+        };
+        let agent = Arc::new(
+            AgentBuilder::new()
+                .name("Limited")
+                .system_prompt("You are a test agent.")
+                .model("test")
+                .client(Arc::new(
+                    MockLlmClient::new().with_fallback_reply("Final answer: done").with_rate_limit(limit),
+                ))
+                .build()
+                .unwrap(),
+        );
+        // The first call reports the exhausted limit
+        agent.react_loop("warm up").await.unwrap();
+
+        let executor = BackgroundExecutor::new();
+        let run_id = executor.execute_async(agent, "go".to_string()).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        let metadata = executor.get_run_metadata(run_id).await.unwrap();
+        assert_eq!(metadata.status, RunStatus::Queued);
+
+        assert_eq!(executor.wait_for_completion(run_id).await.unwrap().content, "done");
+    }
+
+    #[tokio::test]
+    async fn test_tool_progress_events() {
+        let executor = BackgroundExecutor::new();
+
+        let agent = Arc::new(
+            AgentBuilder::new()
+                .name("Capturer")
+                .system_prompt("You are a test agent.")
+                .model("test")
+                .tool(Arc::new(SlowTool))
+                .client(tool_then_answer())
+                .build()
+                .unwrap(),
+        );
+
+        let run_id = executor.execute_async(agent, "Capture".to_string()).await.unwrap();
+        executor.wait_for_completion(run_id).await.unwrap();
+
+        let events = executor.stream_events(run_id, None).await.unwrap();
+        let kinds: Vec<&RunEventType> = events.iter().map(|e| &e.event_type).collect();
+        assert_eq!(
+            kinds,
+            [
+                &RunEventType::Started,
+                &RunEventType::Progress,
+                &RunEventType::Progress,
+                &RunEventType::Output,
+                &RunEventType::Completed,
+            ]
+        );
+        assert_eq!(events[1].data["tool_id"], "slow");
+        assert_eq!(events[1].data["message"], "capturing 30s/60s");
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_replays_then_follows() {
+        use futures::StreamExt;
+
+        let executor = BackgroundExecutor::new();
+        let agent = Arc::new(
+            AgentBuilder::new()
+                .name("Capturer")
+                .system_prompt("You are a test agent.")
+                .model("test")
+                .tool(Arc::new(SlowTool))
+                .client(tool_then_answer())
+                .build()
+                .unwrap(),
+        );
+
+        let run_id = executor.execute_async(agent, "Capture".to_string()).await.unwrap();
+        let live: Vec<RunEvent> = executor.subscribe(run_id).await.unwrap().collect().await;
+        let seqs: Vec<u64> = live.iter().map(|e| e.seq_id.value()).collect();
+        assert_eq!(seqs, [0, 1, 2, 3, 4]);
+        assert_eq!(live.last().unwrap().event_type, RunEventType::Completed);
+
+        // Subscribing after the run finished replays the history and ends
+        let replayed: Vec<RunEvent> = executor.subscribe(run_id).await.unwrap().collect().await;
+        assert_eq!(replayed.len(), 5);
+        assert!(executor.subscribe(RunId::new()).await.is_err());
+    }
+
+    /// Run storage kept in a map, shared between executors like a database would be
+    #[derive(Default)]
+    struct MapStorage(parking_lot::Mutex<HashMap<RunId, (RunMetadata, Vec<RunEvent>)>>);
+
+    #[async_trait]
+    impl RunStorage for MapStorage {
+        async fn save_run(&self, metadata: &RunMetadata) -> Result<()> {
+            let mut runs = self.0.lock();
+            runs.entry(metadata.run_id).or_insert_with(|| (metadata.clone(), Vec::new())).0 = metadata.clone();
+            Ok(())
+        }
+
+        async fn save_run_event(&self, run_id: RunId, event: &RunEvent) -> Result<()> {
+            match self.0.lock().get_mut(&run_id) {
+                Some((_, events)) => events.push(event.clone()),
+                None => return Err(Error::config("event for unknown run")),
+            }
+            Ok(())
+        }
+
+        async fn load_runs(&self) -> Result<Vec<RunMetadata>> {
+            Ok(self.0.lock().values().map(|(metadata, _)| metadata.clone()).collect())
+        }
+
+        async fn load_run_events(&self, run_id: RunId) -> Result<Vec<RunEvent>> {
+            Ok(self.0.lock().get(&run_id).map(|(_, events)| events.clone()).unwrap_or_default())
+        }
+
+        async fn delete_run(&self, run_id: RunId) -> Result<()> {
+            self.0.lock().remove(&run_id);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_runs_persist_and_recover() {
+        let storage = Arc::new(MapStorage::default());
+        let executor = BackgroundExecutor::new().with_storage(storage.clone());
+
+        let agent = Arc::new(
+            AgentBuilder::new()
+                .name("Test Agent")
+                .system_prompt("You are a test agent.")
+                .model("test")
+                .client(mock_client())
+                .build()
+                .unwrap(),
+        );
+        let completed = executor.execute_async(agent, "Test".to_string()).await.unwrap();
+        executor.wait_for_completion(completed).await.unwrap();
+
+        let stored = storage.load_runs().await.unwrap();
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].status, RunStatus::Completed);
+        assert_eq!(storage.load_run_events(completed).await.unwrap().len(), 3);
+
+        // A run that was mid-flight when the process died
+        let mut crashed = stored[0].clone();
+        crashed.run_id = RunId::new();
+        crashed.status = RunStatus::Running;
+        crashed.completed_at = None;
+        storage.save_run(&crashed).await.unwrap();
+
+        let restarted = BackgroundExecutor::new().with_storage(storage.clone());
+        assert_eq!(restarted.recover().await.unwrap(), vec![crashed.run_id]);
+
+        let metadata = restarted.get_run_metadata(crashed.run_id).await.unwrap();
+        assert!(matches!(metadata.status, RunStatus::Failed { .. }));
+        let events = restarted.stream_events(crashed.run_id, None).await.unwrap();
+        assert_eq!(events.last().unwrap().event_type, RunEventType::Failed);
+        assert_eq!(restarted.stream_events(completed, None).await.unwrap().len(), 3);
+        assert!(BackgroundExecutor::new().recover().await.is_err());
+    }
+
+    /// [`MapStorage`] whose event writes and deletes wait for a permit, like a stalled database
+    struct StalledStorage {
+        inner: MapStorage,
+        permits: tokio::sync::Semaphore,
+    }
+
+    #[async_trait]
+    impl RunStorage for StalledStorage {
+        async fn save_run(&self, metadata: &RunMetadata) -> Result<()> {
+            self.inner.save_run(metadata).await
+        }
+
+        async fn save_run_event(&self, run_id: RunId, event: &RunEvent) -> Result<()> {
+            self.permits.acquire().await.unwrap().forget();
+            self.inner.save_run_event(run_id, event).await
+        }
+
+        async fn load_runs(&self) -> Result<Vec<RunMetadata>> {
+            self.inner.load_runs().await
+        }
+
+        async fn load_run_events(&self, run_id: RunId) -> Result<Vec<RunEvent>> {
+            self.inner.load_run_events(run_id).await
+        }
+
+        async fn delete_run(&self, run_id: RunId) -> Result<()> {
+            self.permits.acquire().await.unwrap().forget();
+            self.inner.delete_run(run_id).await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_storage_does_not_block_readers() {
+        let storage = Arc::new(StalledStorage {
+            inner: MapStorage::default(),
+            permits: tokio::sync::Semaphore::new(0),
+        });
+        let executor = BackgroundExecutor::new().with_storage(storage.clone());
+        let agent = Arc::new(
+            AgentBuilder::new()
+                .name("Test Agent")
+                .system_prompt("You are a test agent.")
+                .model("test")
+                .client(mock_client())
+                .build()
+                .unwrap(),
+        );
+        let run_id = executor.execute_async(agent, "Test".to_string()).await.unwrap();
+
+        // The started event is stuck in storage, but the run is already readable
+        loop {
+            let metadata = tokio::time::timeout(std::time::Duration::from_secs(1), executor.get_run_metadata(run_id))
+                .await
+                .expect("reader blocked behind storage")
+                .unwrap();
+            if metadata.status == RunStatus::Running {
+                break;
+            }
+            tokio::task::yield_now().await;
+        }
+        assert_eq!(executor.list_runs().await.len(), 1);
+
+        // One permit per event: started, output and completed
+        storage.permits.add_permits(3);
+        executor.wait_for_completion(run_id).await.unwrap();
+        assert_eq!(storage.load_run_events(run_id).await.unwrap().len(), 3);
+
+        // Cleanup forgets the run at once, even while its delete is stuck in storage
+        let (removed, ()) = tokio::join!(executor.cleanup_old_runs(chrono::Duration::seconds(-1)), async {
+            loop {
+                let runs = tokio::time::timeout(std::time::Duration::from_secs(1), executor.list_runs())
+                    .await
+                    .expect("reader blocked behind storage");
+                if runs.is_empty() {
+                    break;
+                }
+                tokio::task::yield_now().await;
+            }
+            storage.permits.add_permits(1);
+        });
+        assert_eq!(removed, 1);
+        assert!(storage.load_runs().await.unwrap().is_empty());
+    }
 }
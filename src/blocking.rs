@@ -0,0 +1,57 @@
+//! Blocking helpers for callers without an async runtime
+//!
+//! These wrappers let CLIs, scripts and FFI boundaries drive agents and
+//! orchestrators synchronously.
+//!
+//! # Caveat
+//!
+//! Blocking calls must not be made from inside an existing Tokio runtime
+//! (e.g. from an `async fn` or a `#[tokio::main]` body). Doing so would
+//! deadlock or panic, so they return an error instead. Use the async API
+//! there, or move the call onto a dedicated thread.
+
+use crate::error::{Error, Result};
+use std::future::Future;
+use tokio::runtime::{Builder, Runtime};
+
+/// Run a future to completion on a fresh current-thread runtime
+pub fn block_on<F: Future>(future: F) -> Result<F::Output> {
+    ensure_outside_runtime()?;
+    let runtime = Builder::new_current_thread().enable_all().build()?;
+    Ok(runtime.block_on(future))
+}
+
+/// Run a future to completion on a caller-provided runtime
+///
+/// Reusing one runtime across calls avoids rebuilding it each time.
+pub fn block_on_with<F: Future>(runtime: &Runtime, future: F) -> Result<F::Output> {
+    ensure_outside_runtime()?;
+    Ok(runtime.block_on(future))
+}
+
+fn ensure_outside_runtime() -> Result<()> {
+    if tokio::runtime::Handle::try_current().is_ok() {
+        return Err(Error::config(
+            "Blocking API called from within an async runtime; use the async API instead",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_on() {
+        assert_eq!(block_on(async { 1 + 1 }).unwrap(), 2);
+
+        let runtime = Builder::new_current_thread().enable_all().build().unwrap();
+        assert_eq!(block_on_with(&runtime, async { "ok" }).unwrap(), "ok");
+    }
+
+    #[tokio::test]
+    async fn test_block_on_rejects_nested_runtime() {
+        assert!(matches!(block_on(async {}), Err(Error::Config(_))));
+    }
+}
@@ -1,6 +1,8 @@
 //! Configuration types for the ATHPTTGH framework
 
 use crate::error::{Error, Result};
+use crate::guardrails::SecretRedactor;
+use crate::types::TokenUsage;
 use secrecy::{ExposeSecret, SecretString};
 use serde::{Deserialize, Serialize};
 use dotenvy::dotenv;
@@ -22,6 +24,18 @@ pub struct ModelConfig {
     pub frequency_penalty: Option<f32>,
     /// Presence penalty
     pub presence_penalty: Option<f32>,
+    /// Price in USD per million prompt tokens, for budget estimation
+    #[serde(default)]
+    pub prompt_price_per_mtok: Option<f64>,
+    /// Price in USD per million completion tokens, for budget estimation
+    #[serde(default)]
+    pub completion_price_per_mtok: Option<f64>,
+    /// Models to fall back to, in order, if the primary is unavailable
+    #[serde(default)]
+    pub fallback_models: Vec<String>,
+    /// Provider routing for this model's requests, overriding the client's
+    #[serde(default)]
+    pub provider_preferences: Option<ProviderPreferences>,
 }
 
 impl ModelConfig {
@@ -34,6 +48,10 @@ impl ModelConfig {
             top_p: None,
             frequency_penalty: None,
             presence_penalty: None,
+            prompt_price_per_mtok: None,
+            completion_price_per_mtok: None,
+            fallback_models: Vec::new(),
+            provider_preferences: None,
         }
     }
 
@@ -54,41 +72,218 @@ impl ModelConfig {
         self.top_p = Some(top_p);
         self
     }
+
+    /// Set the price in USD per million prompt and completion tokens
+    pub fn with_pricing(mut self, prompt_per_mtok: f64, completion_per_mtok: f64) -> Self {
+        self.prompt_price_per_mtok = Some(prompt_per_mtok);
+        self.completion_price_per_mtok = Some(completion_per_mtok);
+        self
+    }
+
+    /// Set the models to fall back to, in order
+    pub fn with_fallback_models(mut self, models: Vec<String>) -> Self {
+        self.fallback_models = models;
+        self
+    }
+
+    /// Set the provider routing preferences
+    pub fn with_provider_preferences(mut self, preferences: ProviderPreferences) -> Self {
+        self.provider_preferences = Some(preferences);
+        self
+    }
+
+    /// Estimated cost in USD of the given usage, if pricing is known
+    pub fn cost_usd(&self, usage: &TokenUsage) -> Option<f64> {
+        let prompt = self.prompt_price_per_mtok?;
+        let completion = self.completion_price_per_mtok?;
+        Some((usage.prompt_tokens as f64 * prompt + usage.completion_tokens as f64 * completion) / 1_000_000.0)
+    }
 }
 
 /// Provider preferences for OpenRouter routing
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Serializes to OpenRouter's `provider` request object. Empty lists and
+/// unset options are left out so OpenRouter applies its own defaults.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct ProviderPreferences {
     /// Preferred providers in priority order
+    #[serde(rename = "order", alias = "preferred", default, skip_serializing_if = "Vec::is_empty")]
     pub preferred: Vec<String>,
+    /// Only route to these providers, when non-empty
+    #[serde(rename = "only", default, skip_serializing_if = "Vec::is_empty")]
+    pub allowed: Vec<String>,
     /// Providers to exclude
+    #[serde(rename = "ignore", alias = "excluded", default, skip_serializing_if = "Vec::is_empty")]
     pub excluded: Vec<String>,
+    /// Whether providers outside `preferred` may serve the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allow_fallbacks: Option<bool>,
+    /// Only use providers that support every parameter in the request
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub require_parameters: Option<bool>,
+    /// Whether providers that store or train on prompts may be used
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_collection: Option<DataCollection>,
+    /// Accepted model quantizations (e.g. "fp8", "bf16"), when non-empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub quantizations: Vec<String>,
     /// Optimization target
+    #[serde(rename = "sort", alias = "optimization", default, skip_serializing_if = "OptimizationTarget::is_balanced")]
     pub optimization: OptimizationTarget,
 }
 
-impl Default for ProviderPreferences {
-    fn default() -> Self {
-        Self {
-            preferred: vec![],
-            excluded: vec![],
-            optimization: OptimizationTarget::Balanced,
-        }
+impl ProviderPreferences {
+    /// Create preferences that leave routing to OpenRouter
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Try these providers first, in order
+    pub fn with_order(mut self, providers: Vec<String>) -> Self {
+        self.preferred = providers;
+        self
+    }
+
+    /// Restrict routing to these providers
+    pub fn with_only(mut self, providers: Vec<String>) -> Self {
+        self.allowed = providers;
+        self
+    }
+
+    /// Never route to these providers
+    pub fn with_ignore(mut self, providers: Vec<String>) -> Self {
+        self.excluded = providers;
+        self
+    }
+
+    /// Allow or forbid falling back beyond the preferred providers
+    pub fn with_allow_fallbacks(mut self, allow: bool) -> Self {
+        self.allow_fallbacks = Some(allow);
+        self
+    }
+
+    /// Require providers to support every request parameter
+    pub fn with_require_parameters(mut self, require: bool) -> Self {
+        self.require_parameters = Some(require);
+        self
+    }
+
+    /// Set the data collection policy
+    pub fn with_data_collection(mut self, policy: DataCollection) -> Self {
+        self.data_collection = Some(policy);
+        self
+    }
+
+    /// Accept only these quantizations
+    pub fn with_quantizations(mut self, quantizations: Vec<String>) -> Self {
+        self.quantizations = quantizations;
+        self
+    }
+
+    /// Set the optimization target
+    pub fn with_optimization(mut self, optimization: OptimizationTarget) -> Self {
+        self.optimization = optimization;
+        self
+    }
+
+    /// Whether these preferences leave routing entirely to OpenRouter
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
     }
 }
 
-/// Optimization target for provider selection
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+/// Whether providers that retain or train on request data may be used
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
+pub enum DataCollection {
+    /// Any provider may be used
+    Allow,
+    /// Skip providers that store or train on prompts
+    Deny,
+}
+
+/// Optimization target for provider selection
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub enum OptimizationTarget {
     /// Optimize for lower cost
+    #[serde(rename = "price", alias = "lower_cost")]
     LowerCost,
     /// Balanced cost and performance
+    #[default]
+    #[serde(rename = "balanced")]
     Balanced,
-    /// Optimize for performance
+    /// Optimize for throughput
+    #[serde(rename = "throughput", alias = "performance")]
     Performance,
+    /// Optimize for time to first token
+    #[serde(rename = "latency")]
+    Latency,
+}
+
+impl OptimizationTarget {
+    /// Balanced leaves the provider order to OpenRouter, so it is not sent
+    fn is_balanced(&self) -> bool {
+        *self == Self::Balanced
+    }
+}
+
+/// Retry policy for transient request failures
+///
+/// Network errors and 429/500/502/503/504 responses are retried with
+/// exponential backoff; other errors fail immediately. A `Retry-After`
+/// header, when present, overrides the computed delay.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetryConfig {
+    /// Retries after the first attempt (0 disables retrying)
+    pub max_retries: u32,
+    /// Delay before the first retry
+    pub initial_backoff_ms: u64,
+    /// Upper bound on any single delay
+    pub max_backoff_ms: u64,
+    /// Randomize each delay between half and the full backoff
+    pub jitter: bool,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff_ms: 500,
+            max_backoff_ms: 30_000,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryConfig {
+    /// Never retry
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Delay before retry number `attempt` (starting at 0)
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self
+            .initial_backoff_ms
+            .saturating_mul(1u64 << attempt.min(32))
+            .min(self.max_backoff_ms);
+        let delay = if self.jitter && exponential > 1 {
+            use rand_core::RngCore;
+            let half = exponential / 2;
+            half + rand_core::OsRng.next_u64() % (exponential - half + 1)
+        } else {
+            exponential
+        };
+        Duration::from_millis(delay)
+    }
 }
 
+/// Default OpenRouter API endpoint
+pub const DEFAULT_BASE_URL: &str = "https://openrouter.ai/api/v1";
+
 /// OpenRouter client configuration
 #[derive(Clone)]
 pub struct OpenRouterConfig {
@@ -102,14 +297,23 @@ pub struct OpenRouterConfig {
     pub provider_preferences: ProviderPreferences,
     /// Fallback models if primary unavailable
     pub fallback_models: Vec<String>,
-    /// Maximum retries on failure
-    pub max_retries: u32,
+    /// Retry policy for transient failures
+    pub retry: RetryConfig,
     /// Request timeout
     pub timeout: Duration,
     /// App name for OpenRouter tracking
     pub app_name: String,
+    /// Log request and response bodies at debug level under the `spai::wire` target
+    pub log_bodies: bool,
+    /// Bytes of each logged body kept before it is truncated
+    pub log_body_limit: usize,
+    /// Applied to logged bodies before they are written
+    pub log_redactor: Option<SecretRedactor>,
 }
 
+/// Default cap on each logged request or response body
+pub const DEFAULT_LOG_BODY_LIMIT: usize = 16 * 1024;
+
 impl OpenRouterConfig {
     /// Create a new OpenRouter configuration from environment
     pub fn from_env() -> Result<Self> {
@@ -119,19 +323,28 @@ impl OpenRouterConfig {
         let api_key = std::env::var("OPENROUTER_API_KEY")
             .map_err(|_| Error::config("OPENROUTER_API_KEY environment variable not set"))?;
 
+        // Allow pointing at a gateway or self-hosted OpenAI-compatible server
+        let base_url = match std::env::var("OPENROUTER_BASE_URL") {
+            Ok(url) if !url.trim().is_empty() => Url::parse(url.trim())
+                .map_err(|e| Error::config(format!("Invalid OPENROUTER_BASE_URL: {}", e)))?,
+            _ => Url::parse(DEFAULT_BASE_URL).expect("valid OpenRouter URL"),
+        };
+
         Ok(Self {
             api_key: SecretString::from(api_key),
-            base_url: Url::parse("https://openrouter.ai/api/v1")
-                .expect("valid OpenRouter URL"),
+            base_url,
             default_model: presets::BALANCED.to_string(),
             provider_preferences: ProviderPreferences::default(),
             fallback_models: vec![
                 presets::FAST.to_string(),
                 presets::FREE_TIER.to_string(),
             ],
-            max_retries: 3,
+            retry: RetryConfig::default(),
             timeout: Duration::from_secs(120),
             app_name: "ATHPTTGH Agent Harness".to_string(),
+            log_bodies: false,
+            log_body_limit: DEFAULT_LOG_BODY_LIMIT,
+            log_redactor: None,
         })
     }
 
@@ -139,17 +352,19 @@ impl OpenRouterConfig {
     pub fn new(api_key: impl Into<String>) -> Self {
         Self {
             api_key: SecretString::from(api_key.into()),
-            base_url: Url::parse("https://openrouter.ai/api/v1")
-                .expect("valid OpenRouter URL"),
+            base_url: Url::parse(DEFAULT_BASE_URL).expect("valid OpenRouter URL"),
             default_model: presets::BALANCED.to_string(),
             provider_preferences: ProviderPreferences::default(),
             fallback_models: vec![
                 presets::FAST.to_string(),
                 presets::FREE_TIER.to_string(),
             ],
-            max_retries: 3,
+            retry: RetryConfig::default(),
             timeout: Duration::from_secs(120),
             app_name: "ATHPTTGH Agent Harness".to_string(),
+            log_bodies: false,
+            log_body_limit: DEFAULT_LOG_BODY_LIMIT,
+            log_redactor: None,
         }
     }
 
@@ -177,16 +392,52 @@ impl OpenRouterConfig {
         self
     }
 
+    /// Set the retry policy
+    pub fn with_retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = retry;
+        self
+    }
+
     /// Set the app name
     pub fn with_app_name(mut self, app_name: impl Into<String>) -> Self {
         self.app_name = app_name.into();
         self
     }
 
+    /// Log the body of every request and response at debug level
+    ///
+    /// The `Authorization` header is never logged, but prompts and replies
+    /// are logged verbatim unless a [redactor](Self::with_log_redactor) is set.
+    pub fn with_log_bodies(mut self, log_bodies: bool) -> Self {
+        self.log_bodies = log_bodies;
+        self
+    }
+
+    /// Truncate each logged body after `limit` bytes
+    pub fn with_log_body_limit(mut self, limit: usize) -> Self {
+        self.log_body_limit = limit;
+        self
+    }
+
+    /// Redact logged bodies with `redactor`
+    pub fn with_log_redactor(mut self, redactor: SecretRedactor) -> Self {
+        self.log_redactor = Some(redactor);
+        self
+    }
+
     /// Get the API key as a string
     pub fn api_key(&self) -> &str {
         self.api_key.expose_secret()
     }
+
+    /// Build a full endpoint URL for the given API path (e.g. "chat/completions")
+    pub fn endpoint_url(&self, path: &str) -> String {
+        format!(
+            "{}/{}",
+            self.base_url.as_str().trim_end_matches('/'),
+            path.trim_start_matches('/')
+        )
+    }
 }
 
 impl std::fmt::Debug for OpenRouterConfig {
@@ -197,9 +448,12 @@ impl std::fmt::Debug for OpenRouterConfig {
             .field("default_model", &self.default_model)
             .field("provider_preferences", &self.provider_preferences)
             .field("fallback_models", &self.fallback_models)
-            .field("max_retries", &self.max_retries)
+            .field("retry", &self.retry)
             .field("timeout", &self.timeout)
             .field("app_name", &self.app_name)
+            .field("log_bodies", &self.log_bodies)
+            .field("log_body_limit", &self.log_body_limit)
+            .field("log_redactor", &self.log_redactor.is_some())
             .finish()
     }
 }
@@ -0,0 +1,90 @@
+//! Text embeddings for semantic recall
+//!
+//! An [`Embedder`] turns text into vectors so messages can be recalled by
+//! meaning rather than keywords (see
+//! [`SemanticMemoryStorage`](crate::storage::SemanticMemoryStorage)).
+//! [`OpenRouterEmbedder`] uses the OpenAI-compatible `/embeddings` endpoint;
+//! implement the trait yourself to use a local model.
+
+use crate::error::{Error, Result};
+use crate::openrouter::OpenRouterClient;
+use async_trait::async_trait;
+use std::sync::Arc;
+
+/// Produces embedding vectors for text
+#[async_trait]
+pub trait Embedder: Send + Sync {
+    /// Embed each text, returning one vector per input in the same order
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+
+    /// Embed a single text
+    async fn embed_one(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed(&[text.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| Error::config("Embedder returned no vectors"))
+    }
+}
+
+/// Embedder backed by an OpenRouter (or other OpenAI-compatible) endpoint
+#[derive(Clone)]
+pub struct OpenRouterEmbedder {
+    client: Arc<OpenRouterClient>,
+    model: String,
+}
+
+impl OpenRouterEmbedder {
+    /// Embed with `model` (e.g. `openai/text-embedding-3-small`)
+    pub fn new(client: Arc<OpenRouterClient>, model: impl Into<String>) -> Self {
+        Self {
+            client,
+            model: model.into(),
+        }
+    }
+
+    /// Embedding model name
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+}
+
+#[async_trait]
+impl Embedder for OpenRouterEmbedder {
+    async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.client.embed(&self.model, texts).await
+    }
+}
+
+/// Cosine similarity of two vectors (0.0 if either is zero or their lengths differ)
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a.sqrt() * norm_b.sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 3.0]).abs() < 1e-6);
+        assert!((cosine_similarity(&[1.0, 1.0], &[-1.0, -1.0]) + 1.0).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 0.0]), 0.0);
+    }
+}
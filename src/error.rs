@@ -56,6 +56,22 @@ pub enum Error {
     #[error("Maximum loops exceeded: {0}")]
     MaxLoopsExceeded(u32),
 
+    /// Token or cost budget exhausted; carries the output produced so far
+    #[error("Budget exceeded: {reason}")]
+    BudgetExceeded {
+        /// Which cap was hit and by how much
+        reason: String,
+        /// Output produced before the loop stopped
+        partial: Box<crate::agent::AgentOutput>,
+    },
+
+    /// Run cancelled through its cancellation token; carries the output produced so far
+    #[error("Run cancelled")]
+    Cancelled {
+        /// Output produced before the loop stopped
+        partial: Box<crate::agent::AgentOutput>,
+    },
+
     /// Session not found
     #[error("Session not found: {0}")]
     SessionNotFound(String),
@@ -88,6 +104,15 @@ pub enum Error {
     #[error("JSON Schema validation error: {0}")]
     JsonSchema(String),
 
+    /// Structured output did not match the response schema
+    #[error("Schema validation failed: {message}")]
+    SchemaValidation {
+        /// Why the output was rejected
+        message: String,
+        /// The model's raw output
+        raw: String,
+    },
+
     /// Generic error
     #[error("{0}")]
     Other(String),
@@ -1,9 +1,18 @@
 //! Handoff protocol and inter-agent delegation
 
+use crate::agent::Agent;
+use crate::error::{Error, Result};
+use crate::llm_client::LlmClient;
+use crate::openrouter::{CompletionRequest, Message};
 use crate::react::{Observation, ReActTrace};
+use crate::tools::{JsonSchema, Tool, ToolContext, ToolOutput};
 use crate::types::AgentId;
+use async_trait::async_trait;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Handoff request from one agent to another
@@ -56,6 +65,15 @@ pub struct HandoffContext {
     pub trace: ReActTrace,
     /// Custom metadata for the handoff
     pub metadata: HashMap<String, serde_json::Value>,
+    /// Agents that have handled this context so far, oldest first
+    #[serde(default)]
+    pub chain: Vec<AgentId>,
+    /// Compact observations into a rolling summary once there are more than this many
+    #[serde(default)]
+    pub compaction_threshold: Option<usize>,
+    /// Number of observations folded into the rolling summary so far
+    #[serde(default)]
+    pub compacted: usize,
 }
 
 impl HandoffContext {
@@ -66,9 +84,18 @@ impl HandoffContext {
             observations: Vec::new(),
             trace: ReActTrace::new(),
             metadata: HashMap::new(),
+            chain: Vec::new(),
+            compaction_threshold: None,
+            compacted: 0,
         }
     }
 
+    /// Keep at most `threshold` observations, summarizing older ones
+    pub fn with_compaction_threshold(mut self, threshold: usize) -> Self {
+        self.compaction_threshold = Some(threshold.max(2));
+        self
+    }
+
     /// Add an observation
     pub fn with_observation(mut self, observation: Observation) -> Self {
         self.observations.push(observation);
@@ -86,6 +113,194 @@ impl HandoffContext {
         self.metadata.insert(key.into(), value);
         self
     }
+
+    /// Record an observation, skipping duplicates and compacting if over the threshold
+    ///
+    /// Returns `false` if an observation with the same content was already present.
+    pub async fn record(
+        &mut self,
+        observation: Observation,
+        summarizer: &dyn ObservationSummarizer,
+    ) -> Result<bool> {
+        let content = observation.content.trim();
+        if self.observations.iter().any(|o| o.content.trim() == content) {
+            return Ok(false);
+        }
+
+        self.observations.push(observation);
+        if self.needs_compaction() {
+            self.compact(summarizer).await?;
+        }
+        Ok(true)
+    }
+
+    /// Whether there are more observations than the compaction threshold allows
+    pub fn needs_compaction(&self) -> bool {
+        self.compaction_threshold
+            .is_some_and(|threshold| self.observations.len() > threshold)
+    }
+
+    /// Fold every observation but the latest into a single summary observation
+    pub async fn compact(&mut self, summarizer: &dyn ObservationSummarizer) -> Result<()> {
+        if self.observations.len() < 2 {
+            return Ok(());
+        }
+
+        let latest = self.observations.pop().expect("at least two observations");
+        let older = std::mem::take(&mut self.observations);
+        let summary = summarizer.summarize(&self.original_query, &older).await?;
+
+        // A previous summary is folded again but was already counted
+        let resummarized = older
+            .first()
+            .is_some_and(|o| o.content.starts_with(SUMMARY_PREFIX));
+        self.compacted += older.len() - usize::from(resummarized);
+        self.observations.push(Observation::new(format!(
+            "{} {}",
+            SUMMARY_PREFIX,
+            summary.trim()
+        )));
+        self.observations.push(latest);
+        Ok(())
+    }
+
+    /// Record that an agent has handled this context
+    pub fn with_handler(mut self, agent: AgentId) -> Self {
+        self.chain.push(agent);
+        self
+    }
+
+    /// Most recent agent to handle this context
+    pub fn last_handler(&self) -> Option<AgentId> {
+        self.chain.last().copied()
+    }
+
+    /// Render the context as the input for the receiving agent
+    pub fn to_prompt(&self) -> String {
+        let mut prompt = String::new();
+        if let Some(reason) = self.metadata.get("reason").and_then(|v| v.as_str()) {
+            prompt.push_str(&format!("This task was handed off to you: {}\n\n", reason));
+        }
+        prompt.push_str(&format!("Original request:\n{}", self.original_query));
+
+        if !self.observations.is_empty() {
+            prompt.push_str("\n\nFindings so far:");
+            for observation in &self.observations {
+                prompt.push_str(&format!("\n- {}", observation.content));
+            }
+        }
+
+        prompt
+    }
+}
+
+/// Prefix marking the rolling summary observation
+pub const SUMMARY_PREFIX: &str = "[summary]";
+
+/// Condenses older handoff observations into a single summary
+#[async_trait]
+pub trait ObservationSummarizer: Send + Sync {
+    /// Summarize `observations` gathered while working on `query`
+    async fn summarize(&self, query: &str, observations: &[Observation]) -> Result<String>;
+}
+
+/// Summarizer that keeps a bounded prefix of each observation, without a model call
+#[derive(Debug, Clone)]
+pub struct TruncatingSummarizer {
+    /// Maximum characters kept from each observation
+    pub max_chars_per_observation: usize,
+    /// Maximum characters in the whole summary
+    pub max_chars: usize,
+}
+
+impl Default for TruncatingSummarizer {
+    fn default() -> Self {
+        Self {
+            max_chars_per_observation: 200,
+            max_chars: 2000,
+        }
+    }
+}
+
+#[async_trait]
+impl ObservationSummarizer for TruncatingSummarizer {
+    async fn summarize(&self, _query: &str, observations: &[Observation]) -> Result<String> {
+        let mut summary = String::new();
+        for observation in observations {
+            let content = observation
+                .content
+                .strip_prefix(SUMMARY_PREFIX)
+                .unwrap_or(&observation.content)
+                .trim();
+            let line: String = content.chars().take(self.max_chars_per_observation).collect();
+            if !summary.is_empty() {
+                summary.push_str("; ");
+            }
+            summary.push_str(&line);
+        }
+
+        // Keep the newest material when the rolling summary overflows
+        let len = summary.chars().count();
+        if len > self.max_chars {
+            summary = summary.chars().skip(len - self.max_chars).collect();
+        }
+        Ok(summary)
+    }
+}
+
+/// Summarizer that asks a (typically cheap) model to condense the observations
+pub struct LlmSummarizer {
+    client: Arc<dyn LlmClient>,
+    model: String,
+    max_tokens: u32,
+}
+
+impl LlmSummarizer {
+    /// Summarize with the given client and model
+    pub fn new(client: Arc<dyn LlmClient>, model: impl Into<String>) -> Self {
+        Self {
+            client,
+            model: model.into(),
+            max_tokens: 300,
+        }
+    }
+
+    /// Cap the length of the generated summary
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+}
+
+#[async_trait]
+impl ObservationSummarizer for LlmSummarizer {
+    async fn summarize(&self, query: &str, observations: &[Observation]) -> Result<String> {
+        let findings = observations
+            .iter()
+            .map(|o| format!("- {}", o.content))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let request = CompletionRequest::new(
+            self.model.clone(),
+            vec![
+                Message::system(
+                    "Condense the findings below into a short summary. Keep concrete \
+                     identifiers (PIDs, ports, IPs, paths) and drop repetition.",
+                ),
+                Message::user(format!("Task:\n{}\n\nFindings:\n{}", query, findings)),
+            ],
+        )
+        .with_temperature(0.0)
+        .with_max_tokens(self.max_tokens);
+
+        let response = self.client.complete(request).await?;
+        response
+            .choices
+            .first()
+            .map(|choice| choice.message.content.clone())
+            .filter(|content| !content.trim().is_empty())
+            .ok_or_else(|| Error::handoff("Summarizer returned an empty response"))
+    }
 }
 
 /// Handoff strategy
@@ -106,6 +321,183 @@ pub enum HandoffStrategy {
         /// Maximum delegation depth
         max_depth: u32,
     },
+    /// Rotate through agents, picking the one after the last handler
+    RoundRobin,
+    /// Pick the agent that has handled this context the fewest times
+    LeastBusy,
+    /// Pick the first agent that advertises the capability tag
+    ByCapability {
+        /// Capability tag to match (case-insensitive)
+        tag: String,
+    },
+    /// Pick the agent named by the LLM, via `next_agent` metadata or the latest observation
+    ByLlmDecision,
+    /// Always pick the given agent
+    Explicit {
+        /// Target agent
+        agent: AgentId,
+    },
+    /// Pick the agent of the first rule whose predicate matches the context
+    Conditional {
+        /// Predicates and their targets, checked in order
+        rules: Vec<(Predicate, AgentId)>,
+        /// Target when no rule matches
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        default: Option<AgentId>,
+    },
+}
+
+/// Condition on a [`HandoffContext`] used by [`HandoffStrategy::Conditional`]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Predicate {
+    /// Any observation contains any of the keywords (case-insensitive)
+    Keywords {
+        /// Keywords to look for
+        keywords: Vec<String>,
+    },
+    /// The latest observation matches the pattern
+    Regex {
+        /// Pattern to match
+        #[serde(with = "regex_serde")]
+        pattern: Regex,
+    },
+    /// Arbitrary check on the context (not serializable)
+    #[serde(skip)]
+    Custom(Arc<dyn Fn(&HandoffContext) -> bool + Send + Sync>),
+}
+
+impl Predicate {
+    /// Match observations containing any of `keywords`
+    pub fn keywords<S: AsRef<str>>(keywords: &[S]) -> Self {
+        Self::Keywords {
+            keywords: keywords.iter().map(|k| k.as_ref().to_string()).collect(),
+        }
+    }
+
+    /// Match a latest observation against `pattern`
+    pub fn regex(pattern: &str) -> Result<Self> {
+        let pattern = Regex::new(pattern)
+            .map_err(|e| Error::config(format!("Invalid handoff predicate pattern: {}", e)))?;
+        Ok(Self::Regex { pattern })
+    }
+
+    /// Match with a closure
+    pub fn custom(check: impl Fn(&HandoffContext) -> bool + Send + Sync + 'static) -> Self {
+        Self::Custom(Arc::new(check))
+    }
+
+    /// Whether the context satisfies this predicate
+    pub fn matches(&self, ctx: &HandoffContext) -> bool {
+        match self {
+            Self::Keywords { keywords } => ctx.observations.iter().any(|o| {
+                let content = o.content.to_lowercase();
+                keywords.iter().any(|k| content.contains(&k.to_lowercase()))
+            }),
+            Self::Regex { pattern } => ctx
+                .observations
+                .last()
+                .is_some_and(|o| pattern.is_match(&o.content)),
+            Self::Custom(check) => check(ctx),
+        }
+    }
+}
+
+impl std::fmt::Debug for Predicate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Keywords { keywords } => f.debug_struct("Keywords").field("keywords", keywords).finish(),
+            Self::Regex { pattern } => f.debug_struct("Regex").field("pattern", &pattern.as_str()).finish(),
+            Self::Custom(_) => f.write_str("Custom(..)"),
+        }
+    }
+}
+
+mod regex_serde {
+    use regex::Regex;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(pattern: &Regex, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(pattern.as_str())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+        let pattern = String::deserialize(deserializer)?;
+        Regex::new(&pattern).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Metadata key read by [`HandoffStrategy::ByLlmDecision`]
+pub const NEXT_AGENT_KEY: &str = "next_agent";
+
+impl HandoffStrategy {
+    /// Select the next agent for a handoff.
+    ///
+    /// Returns `None` when no agent matches, when `agents` is empty, or for
+    /// the mode-only strategies (`Direct`, `Collaborative`, `Supervised`,
+    /// `Cascading`) which describe how control transfers rather than who
+    /// receives it.
+    pub fn select<T>(&self, ctx: &HandoffContext, agents: &[Agent<T>]) -> Option<AgentId> {
+        if agents.is_empty() {
+            return None;
+        }
+
+        match self {
+            Self::RoundRobin => {
+                let next = ctx
+                    .last_handler()
+                    .and_then(|last| agents.iter().position(|a| a.id == last))
+                    .map(|idx| (idx + 1) % agents.len())
+                    .unwrap_or(0);
+                Some(agents[next].id)
+            }
+            Self::LeastBusy => agents
+                .iter()
+                .enumerate()
+                .min_by_key(|(idx, a)| {
+                    let load = ctx.chain.iter().filter(|id| **id == a.id).count();
+                    (load, *idx)
+                })
+                .map(|(_, a)| a.id),
+            Self::ByCapability { tag } => agents
+                .iter()
+                .find(|a| a.has_capability(tag))
+                .map(|a| a.id),
+            Self::ByLlmDecision => {
+                if let Some(name) = ctx.metadata.get(NEXT_AGENT_KEY).and_then(|v| v.as_str()) {
+                    let name = name.trim();
+                    if let Some(agent) = agents.iter().find(|a| {
+                        a.name.eq_ignore_ascii_case(name) || a.id.to_string() == name
+                    }) {
+                        return Some(agent.id);
+                    }
+                }
+
+                let latest = ctx.observations.last()?.content.to_lowercase();
+                agents
+                    .iter()
+                    .find(|a| latest.contains(&a.name.to_lowercase()))
+                    .map(|a| a.id)
+            }
+            Self::Explicit { agent } => agents.iter().find(|a| a.id == *agent).map(|a| a.id),
+            Self::Conditional { rules, default } => rules
+                .iter()
+                .filter(|(predicate, _)| predicate.matches(ctx))
+                .map(|(_, agent)| *agent)
+                .chain(*default)
+                .find(|id| agents.iter().any(|a| a.id == *id)),
+            Self::Direct
+            | Self::Collaborative
+            | Self::Supervised { .. }
+            | Self::Cascading { .. } => None,
+        }
+    }
+
+    /// Select the next agent by position rather than by id
+    pub fn select_index<T>(&self, ctx: &HandoffContext, agents: &[Agent<T>]) -> Option<usize> {
+        let id = self.select(ctx, agents)?;
+        agents.iter().position(|a| a.id == id)
+    }
 }
 
 impl Default for HandoffStrategy {
@@ -113,3 +505,259 @@ impl Default for HandoffStrategy {
         Self::Direct
     }
 }
+
+/// Identifier of the built-in handoff tool
+pub const HANDOFF_TOOL_ID: &str = "handoff";
+
+/// Built-in tool that lets an agent delegate to another agent
+///
+/// The ReAct loop intercepts calls to this tool and turns them into an
+/// [`Action::Handoff`](crate::react::Action::Handoff); `execute` is only
+/// reached when the tool is invoked outside an agent loop.
+pub struct HandoffTool {
+    description: String,
+}
+
+impl HandoffTool {
+    /// Create a handoff tool advertising the given target roles
+    pub fn new<S: AsRef<str>>(targets: &[S]) -> Self {
+        let roles = targets.iter().map(|t| t.as_ref()).collect::<Vec<_>>().join(", ");
+        Self {
+            description: format!(
+                "Hand the task off to another agent when it is better suited to continue. \
+                 Available agents: {}",
+                roles
+            ),
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for HandoffTool {
+    fn id(&self) -> &str {
+        HANDOFF_TOOL_ID
+    }
+
+    fn name(&self) -> &str {
+        "Handoff"
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn input_schema(&self) -> JsonSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "target_role".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "description": "Name of the agent to hand off to"
+            }),
+        );
+        properties.insert(
+            "reason".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "description": "Why the other agent should take over"
+            }),
+        );
+
+        JsonSchema::object(properties)
+            .with_required(vec!["target_role".to_string(), "reason".to_string()])
+    }
+
+    async fn execute(&self, params: Value, _ctx: &ToolContext) -> Result<ToolOutput> {
+        let target = params
+            .get("target_role")
+            .and_then(|v| v.as_str())
+            .unwrap_or("(unspecified)");
+
+        Ok(ToolOutput::failure(format!(
+            "Handoff to '{}' can only be performed inside an agent loop",
+            target
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_client::LlmClient;
+    use crate::openrouter::OpenRouterClient;
+    use std::sync::Arc;
+
+    fn test_client() -> Arc<dyn LlmClient> {
+        Arc::new(
+            OpenRouterClient::builder()
+                .api_key("test-key")
+                .build()
+                .unwrap(),
+        )
+    }
+
+    fn agents(names: &[&str]) -> Vec<Agent> {
+        let client = test_client();
+        names
+            .iter()
+            .map(|name| {
+                Agent::builder()
+                    .name(*name)
+                    .system_prompt(format!("You are the {} agent.", name))
+                    .client(client.clone())
+                    .build()
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_round_robin_wraps() {
+        let agents = agents(&["a", "b", "c"]);
+        let ctx = HandoffContext::new("q");
+        assert_eq!(HandoffStrategy::RoundRobin.select(&ctx, &agents), Some(agents[0].id));
+
+        let ctx = ctx.with_handler(agents[2].id);
+        assert_eq!(HandoffStrategy::RoundRobin.select(&ctx, &agents), Some(agents[0].id));
+
+        let ctx = ctx.with_handler(agents[0].id);
+        assert_eq!(HandoffStrategy::RoundRobin.select(&ctx, &agents), Some(agents[1].id));
+    }
+
+    #[test]
+    fn test_least_busy() {
+        let agents = agents(&["a", "b"]);
+        let ctx = HandoffContext::new("q")
+            .with_handler(agents[0].id)
+            .with_handler(agents[0].id)
+            .with_handler(agents[1].id);
+        assert_eq!(HandoffStrategy::LeastBusy.select(&ctx, &agents), Some(agents[1].id));
+    }
+
+    #[test]
+    fn test_by_capability_and_llm_decision() {
+        let agents = agents(&["Network Monitor", "Process Analyzer"]);
+        let ctx = HandoffContext::new("q");
+        let strategy = HandoffStrategy::ByCapability { tag: "process".into() };
+        // Names alone do not count as capabilities
+        assert_eq!(strategy.select(&ctx, &agents), None);
+
+        let client = test_client();
+        let capable = vec![
+            Agent::builder()
+                .name("Network Monitor")
+                .system_prompt("You watch the network.")
+                .capabilities(&["network", "pcap"])
+                .client(client.clone())
+                .build()
+                .unwrap(),
+            Agent::builder()
+                .name("Analyzer")
+                .system_prompt("You analyze.")
+                .capabilities(&["Process"])
+                .client(client)
+                .build()
+                .unwrap(),
+        ];
+        assert_eq!(capable[1].capabilities(), ["process".to_string()]);
+        assert_eq!(strategy.select(&ctx, &capable), Some(capable[1].id));
+        let strategy = HandoffStrategy::ByCapability { tag: "PCAP".into() };
+        assert_eq!(strategy.select(&ctx, &capable), Some(capable[0].id));
+
+        let ctx = ctx.with_metadata(NEXT_AGENT_KEY, serde_json::json!("network monitor"));
+        assert_eq!(HandoffStrategy::ByLlmDecision.select(&ctx, &agents), Some(agents[0].id));
+
+        let ctx = HandoffContext::new("q")
+            .with_observation(Observation::new("Hand this to Process Analyzer"));
+        assert_eq!(HandoffStrategy::ByLlmDecision.select(&ctx, &agents), Some(agents[1].id));
+    }
+
+    #[test]
+    fn test_explicit_and_mode_only() {
+        let agents = agents(&["a", "b"]);
+        let ctx = HandoffContext::new("q");
+        let strategy = HandoffStrategy::Explicit { agent: agents[1].id };
+        assert_eq!(strategy.select(&ctx, &agents), Some(agents[1].id));
+        assert_eq!(
+            HandoffStrategy::Explicit { agent: AgentId::new() }.select(&ctx, &agents),
+            None
+        );
+        assert_eq!(HandoffStrategy::Direct.select(&ctx, &agents), None);
+    }
+
+    #[test]
+    fn test_conditional() {
+        let agents = agents(&["triage", "incident response", "reporter"]);
+        let strategy = HandoffStrategy::Conditional {
+            rules: vec![
+                (Predicate::regex(r"SEVERITY:\s*CRITICAL").unwrap(), agents[1].id),
+                (Predicate::keywords(&["rootkit", "backdoor"]), agents[0].id),
+                (Predicate::custom(|ctx| ctx.observations.len() > 2), agents[2].id),
+            ],
+            default: Some(agents[2].id),
+        };
+
+        let ctx = HandoffContext::new("q").with_observation(Observation::new("SEVERITY: LOW - clean"));
+        assert_eq!(strategy.select(&ctx, &agents), Some(agents[2].id));
+
+        // Keywords search every observation; the regex only the latest
+        let ctx = HandoffContext::new("q")
+            .with_observation(Observation::new("Possible ROOTKIT in /dev"))
+            .with_observation(Observation::new("SEVERITY: CRITICAL - confirmed"));
+        assert_eq!(strategy.select(&ctx, &agents), Some(agents[1].id));
+        let ctx = ctx.with_observation(Observation::new("SEVERITY: MEDIUM"));
+        assert_eq!(strategy.select(&ctx, &agents), Some(agents[0].id));
+
+        // Targets missing from the candidates are skipped, default included
+        let strategy = HandoffStrategy::Conditional {
+            rules: vec![(Predicate::keywords(&["medium"]), AgentId::new())],
+            default: None,
+        };
+        assert_eq!(strategy.select(&ctx, &agents), None);
+
+        let predicate: Predicate =
+            serde_json::from_str(r#"{"type": "regex", "pattern": "CRITICAL"}"#).unwrap();
+        assert!(!predicate.matches(&ctx));
+        assert!(serde_json::from_str::<Predicate>(r#"{"type": "regex", "pattern": "("}"#).is_err());
+        assert!(Predicate::regex("(").is_err());
+    }
+
+    #[test]
+    fn test_strategy_serde() {
+        let strategy: HandoffStrategy =
+            serde_json::from_str(r#"{"type": "by_capability", "tag": "network"}"#).unwrap();
+        assert!(matches!(strategy, HandoffStrategy::ByCapability { ref tag } if tag == "network"));
+        let strategy: HandoffStrategy =
+            serde_json::from_str(r#"{"type": "round_robin"}"#).unwrap();
+        assert!(matches!(strategy, HandoffStrategy::RoundRobin));
+    }
+
+    #[tokio::test]
+    async fn test_record_dedups_and_compacts() {
+        let summarizer = TruncatingSummarizer::default();
+        let mut ctx = HandoffContext::new("q").with_compaction_threshold(3);
+
+        for content in ["[a] one", "[b] two", "[b] two", "[c] three"] {
+            ctx.record(Observation::new(content), &summarizer).await.unwrap();
+        }
+        assert_eq!(ctx.observations.len(), 3);
+        assert_eq!(ctx.compacted, 0);
+
+        ctx.record(Observation::new("[d] four"), &summarizer).await.unwrap();
+        assert_eq!(ctx.observations.len(), 2);
+        assert_eq!(ctx.compacted, 3);
+        assert_eq!(
+            ctx.observations[0].content,
+            "[summary] [a] one; [b] two; [c] three"
+        );
+        assert_eq!(ctx.observations[1].content, "[d] four");
+
+        // Later compactions roll the previous summary forward
+        ctx.record(Observation::new("[e] five"), &summarizer).await.unwrap();
+        ctx.record(Observation::new("[f] six"), &summarizer).await.unwrap();
+        assert_eq!(ctx.observations.len(), 2);
+        assert_eq!(ctx.compacted, 5);
+        assert!(ctx.observations[0].content.starts_with("[summary] [a] one"));
+        assert!(ctx.observations[0].content.ends_with("[e] five"));
+    }
+}
@@ -5,6 +5,12 @@ use async_trait::async_trait;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::time::Duration;
+
+#[cfg(feature = "websocket-approval")]
+mod websocket;
+#[cfg(feature = "websocket-approval")]
+pub use websocket::WebSocketApprovalHandler;
 
 /// Approval request
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +31,20 @@ pub struct ApprovalRequest {
     pub deadline: Option<DateTime<Utc>>,
     /// Suggested approvers
     pub suggested_approvers: Vec<UserId>,
+    /// How long the requester waits for a decision (None = indefinitely)
+    #[serde(default)]
+    pub timeout: Option<Duration>,
+}
+
+/// What happens to an action whose approval request times out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeoutDecision {
+    /// Treat the action as rejected
+    #[default]
+    Reject,
+    /// Skip the action for now, leaving the decision open
+    Defer,
 }
 
 /// Type of action requiring approval
@@ -101,6 +121,13 @@ pub enum ApprovalDecision {
     },
 }
 
+impl ApprovalDecision {
+    /// Whether the action may go ahead
+    pub fn is_approved(&self) -> bool {
+        matches!(self, Self::Approved { .. } | Self::AutoApproved { .. })
+    }
+}
+
 /// Approval status
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
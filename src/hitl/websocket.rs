@@ -0,0 +1,758 @@
+//! Approval requests answered from a browser over a WebSocket
+//!
+//! Each request is pushed to the dashboard as a text frame:
+//!
+//! ```json
+//! {"type": "approval_request", "tool": "kill_process", "arguments": {"pid": 42},
+//!  "request": { ...ApprovalRequest... }}
+//! ```
+//!
+//! and answered with a decision correlated by the request ID:
+//!
+//! ```json
+//! {"id": "<approval id>", "decision": {"status": "approved", "approver": "alice", "notes": null}}
+//! ```
+//!
+//! Withdrawn requests are announced with `{"type": "approval_cancelled", "id": ...}`.
+//! Requests still awaiting a decision are sent again after every reconnect.
+//!
+//! Anyone who can reach the dashboard endpoint can approve tool calls, so use
+//! `wss://` and a bearer token for anything but a local dashboard. This module
+//! is behind the `websocket-approval` feature, which `full` does not enable.
+
+use super::{ApprovalDecision, ApprovalHandler, ApprovalRequest, ApprovalStatus};
+use crate::error::{Error, Result};
+use crate::types::ApprovalId;
+use async_trait::async_trait;
+use base64::Engine;
+use parking_lot::Mutex;
+use rand_core::{OsRng, RngCore};
+use serde::Deserialize;
+use serde_json::json;
+use sha1::{Digest, Sha1};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::{self, ClientConfig, RootCertStore};
+use tokio_rustls::TlsConnector;
+
+/// GUID appended to the handshake key (RFC 6455, section 1.3)
+const HANDSHAKE_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// Largest message accepted from the dashboard
+const MAX_MESSAGE_BYTES: usize = 1 << 20;
+
+/// Time allowed to connect and complete the opening handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest handshake response line accepted from the dashboard
+const MAX_HEADER_LINE_BYTES: usize = 8 * 1024;
+
+/// Most handshake response headers accepted from the dashboard
+const MAX_HEADERS: usize = 64;
+
+/// Outcomes kept for `check_status`; the oldest are forgotten first
+const MAX_RESOLVED: usize = 1024;
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(250);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+const OP_CONTINUATION: u8 = 0x0;
+const OP_TEXT: u8 = 0x1;
+const OP_CLOSE: u8 = 0x8;
+const OP_PING: u8 = 0x9;
+const OP_PONG: u8 = 0xA;
+
+/// A request awaiting its decision
+struct Pending {
+    payload: String,
+    reply: oneshot::Sender<ApprovalDecision>,
+}
+
+#[derive(Default)]
+struct State {
+    pending: HashMap<ApprovalId, Pending>,
+    resolved: HashMap<ApprovalId, ApprovalStatus>,
+    /// Resolution order, so `resolved` stays within `MAX_RESOLVED`
+    resolved_order: VecDeque<ApprovalId>,
+}
+
+impl State {
+    /// Record the outcome of a request, forgetting the oldest beyond the cap
+    fn resolve(&mut self, id: ApprovalId, status: ApprovalStatus) {
+        if self.resolved.insert(id, status).is_none() {
+            self.resolved_order.push_back(id);
+        }
+        while self.resolved_order.len() > MAX_RESOLVED {
+            if let Some(oldest) = self.resolved_order.pop_front() {
+                self.resolved.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Work for the connection task
+enum Outbound {
+    Request(ApprovalId),
+    Cancel(ApprovalId),
+}
+
+/// Decision sent back by the dashboard
+#[derive(Deserialize)]
+struct Reply {
+    id: ApprovalId,
+    decision: ApprovalDecision,
+}
+
+/// [`ApprovalHandler`] that asks a web dashboard over a WebSocket
+///
+/// Requests may be in flight concurrently. The connection is re-established
+/// with exponential backoff when it drops. `wss://` URLs are verified against
+/// the Mozilla root certificates.
+pub struct WebSocketApprovalHandler {
+    state: Arc<Mutex<State>>,
+    outbound: mpsc::UnboundedSender<Outbound>,
+    driver: JoinHandle<()>,
+}
+
+impl WebSocketApprovalHandler {
+    /// Connect to the dashboard at `url` (e.g. `wss://soc.example.com/approvals`)
+    ///
+    /// Must be called from within a Tokio runtime. The first connection is
+    /// made in the background, so an unreachable dashboard is not an error here.
+    pub fn new(url: &str) -> Result<Self> {
+        Self::connect(Endpoint::parse(url)?)
+    }
+
+    /// Like [`new`](Self::new), sending `Authorization: Bearer <token>` in the handshake
+    pub fn with_bearer_token(url: &str, token: impl Into<String>) -> Result<Self> {
+        let mut endpoint = Endpoint::parse(url)?;
+        endpoint.bearer_token = Some(token.into());
+        Self::connect(endpoint)
+    }
+
+    fn connect(endpoint: Endpoint) -> Result<Self> {
+        let state = Arc::new(Mutex::new(State::default()));
+        let (outbound, rx) = mpsc::unbounded_channel();
+        let driver = tokio::spawn(drive(endpoint, state.clone(), rx));
+        Ok(Self { state, outbound, driver })
+    }
+
+    /// Number of requests awaiting a decision
+    pub fn pending(&self) -> usize {
+        self.state.lock().pending.len()
+    }
+}
+
+impl Drop for WebSocketApprovalHandler {
+    fn drop(&mut self) {
+        self.driver.abort();
+    }
+}
+
+#[async_trait]
+impl ApprovalHandler for WebSocketApprovalHandler {
+    async fn request_approval(&self, request: ApprovalRequest) -> Result<ApprovalDecision> {
+        let id = request.id;
+        let payload = json!({
+            "type": "approval_request",
+            "tool": request.context.data.get("tool"),
+            "arguments": request.context.data.get("arguments"),
+            "request": request,
+        })
+        .to_string();
+
+        let (reply, decision) = oneshot::channel();
+        self.state.lock().pending.insert(id, Pending { payload, reply });
+        let _ = self.outbound.send(Outbound::Request(id));
+
+        decision
+            .await
+            .map_err(|_| Error::ApprovalDenied(format!("approval request {} was cancelled", id)))
+    }
+
+    async fn check_status(&self, id: ApprovalId) -> Result<ApprovalStatus> {
+        let state = self.state.lock();
+        if state.pending.contains_key(&id) {
+            return Ok(ApprovalStatus::Pending);
+        }
+        state
+            .resolved
+            .get(&id)
+            .copied()
+            .ok_or_else(|| Error::InvalidInput(format!("unknown approval request {}", id)))
+    }
+
+    async fn cancel(&self, id: ApprovalId) -> Result<()> {
+        let mut state = self.state.lock();
+        if state.pending.remove(&id).is_some() {
+            state.resolve(id, ApprovalStatus::Expired);
+            let _ = self.outbound.send(Outbound::Cancel(id));
+        }
+        Ok(())
+    }
+}
+
+fn status_of(decision: &ApprovalDecision) -> ApprovalStatus {
+    match decision {
+        ApprovalDecision::Approved { .. } | ApprovalDecision::AutoApproved { .. } => ApprovalStatus::Approved,
+        ApprovalDecision::Rejected { .. } | ApprovalDecision::ModificationRequired { .. } => {
+            ApprovalStatus::Rejected
+        }
+        ApprovalDecision::Escalated { .. } => ApprovalStatus::Escalated,
+    }
+}
+
+/// Keep a connection open for as long as the handler lives
+async fn drive(endpoint: Endpoint, state: Arc<Mutex<State>>, mut outbound: mpsc::UnboundedReceiver<Outbound>) {
+    let mut backoff = INITIAL_BACKOFF;
+    loop {
+        match endpoint.connect().await {
+            Ok((reader, writer)) => {
+                backoff = INITIAL_BACKOFF;
+                match serve(reader, writer, &state, &mut outbound).await {
+                    Ok(()) => tracing::warn!("Approval dashboard closed the connection"),
+                    Err(e) => tracing::warn!("Approval dashboard connection lost: {}", e),
+                }
+            }
+            Err(e) => tracing::warn!("Failed to connect to approval dashboard: {}", e),
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+}
+
+/// Relay requests and decisions over one connection until it drops
+async fn serve<R, W>(
+    reader: R,
+    mut writer: W,
+    state: &Mutex<State>,
+    outbound: &mut mpsc::UnboundedReceiver<Outbound>,
+) -> Result<()>
+where
+    R: AsyncRead + Unpin + Send + 'static,
+    W: AsyncWrite + Unpin,
+{
+    // Frame reads are not cancel-safe, so they get their own task
+    let (frames_tx, mut frames) = mpsc::channel(16);
+    let reader_task = tokio::spawn(async move {
+        let mut reader = BufReader::new(reader);
+        loop {
+            let frame = read_message(&mut reader).await;
+            let done = frame.is_err();
+            if frames_tx.send(frame).await.is_err() || done {
+                break;
+            }
+        }
+    });
+
+    let result = async {
+        // Everything still pending is new to this connection
+        let mut sent = HashSet::new();
+        let backlog: Vec<(ApprovalId, String)> = state
+            .lock()
+            .pending
+            .iter()
+            .map(|(id, pending)| (*id, pending.payload.clone()))
+            .collect();
+        for (id, payload) in backlog {
+            write_frame(&mut writer, OP_TEXT, payload.as_bytes(), true).await?;
+            sent.insert(id);
+        }
+
+        loop {
+            tokio::select! {
+                message = outbound.recv() => match message {
+                    Some(Outbound::Request(id)) => {
+                        let payload = state.lock().pending.get(&id).map(|p| p.payload.clone());
+                        if let Some(payload) = payload {
+                            if sent.insert(id) {
+                                write_frame(&mut writer, OP_TEXT, payload.as_bytes(), true).await?;
+                            }
+                        }
+                    }
+                    Some(Outbound::Cancel(id)) => {
+                        let notice = json!({"type": "approval_cancelled", "id": id}).to_string();
+                        write_frame(&mut writer, OP_TEXT, notice.as_bytes(), true).await?;
+                    }
+                    None => return Ok(()),
+                },
+                frame = frames.recv() => match frame {
+                    Some(Ok((OP_TEXT, payload))) => resolve(state, &payload),
+                    Some(Ok((OP_PING, payload))) => write_frame(&mut writer, OP_PONG, &payload, true).await?,
+                    Some(Ok((OP_CLOSE, _))) | None => {
+                        let _ = write_frame(&mut writer, OP_CLOSE, &[], true).await;
+                        return Ok(());
+                    }
+                    Some(Ok(_)) => {}
+                    Some(Err(e)) => return Err(e),
+                },
+            }
+        }
+    }
+    .await;
+
+    reader_task.abort();
+    result
+}
+
+/// Hand a decision from the dashboard to its waiting request
+fn resolve(state: &Mutex<State>, payload: &[u8]) {
+    let reply: Reply = match serde_json::from_slice(payload) {
+        Ok(reply) => reply,
+        Err(e) => {
+            tracing::warn!("Ignoring malformed approval reply: {}", e);
+            return;
+        }
+    };
+    let mut state = state.lock();
+    match state.pending.remove(&reply.id) {
+        Some(pending) => {
+            state.resolve(reply.id, status_of(&reply.decision));
+            let _ = pending.reply.send(reply.decision);
+        }
+        None => tracing::debug!("Ignoring reply for unknown approval request {}", reply.id),
+    }
+}
+
+type Reader = Box<dyn AsyncRead + Unpin + Send>;
+type Writer = Box<dyn AsyncWrite + Unpin + Send>;
+
+/// Where the dashboard listens
+#[derive(Debug, Clone)]
+struct Endpoint {
+    host: url::Host<String>,
+    port: u16,
+    resource: String,
+    tls: bool,
+    bearer_token: Option<String>,
+    handshake_timeout: Duration,
+}
+
+impl Endpoint {
+    fn parse(url: &str) -> Result<Self> {
+        let parsed = url::Url::parse(url).map_err(|e| Error::config(format!("invalid dashboard URL {}: {}", url, e)))?;
+        let tls = match parsed.scheme() {
+            "ws" => false,
+            "wss" => true,
+            scheme => {
+                return Err(Error::config(format!(
+                    "unsupported dashboard URL scheme '{}': use ws:// or wss://",
+                    scheme
+                )))
+            }
+        };
+        let host = parsed
+            .host()
+            .ok_or_else(|| Error::config(format!("dashboard URL {} has no host", url)))?
+            .to_owned();
+        let mut resource = parsed.path().to_string();
+        if let Some(query) = parsed.query() {
+            resource.push('?');
+            resource.push_str(query);
+        }
+        Ok(Self {
+            host,
+            port: parsed.port().unwrap_or(if tls { 443 } else { 80 }),
+            resource,
+            tls,
+            bearer_token: None,
+            handshake_timeout: HANDSHAKE_TIMEOUT,
+        })
+    }
+
+    /// Host as written in a `Host` header, with IPv6 addresses bracketed
+    fn authority(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    /// Connect and handshake, giving up after the handshake timeout
+    ///
+    /// A dashboard that accepts the connection but never answers would
+    /// otherwise stall the reconnect loop, and every approval with it.
+    async fn connect(&self) -> Result<(Reader, Writer)> {
+        tokio::time::timeout(self.handshake_timeout, self.open())
+            .await
+            .map_err(|_| Error::Timeout(format!("dashboard handshake exceeded {:?}", self.handshake_timeout)))?
+    }
+
+    /// Open a TCP (and, for `wss://`, TLS) connection and perform the opening handshake
+    async fn open(&self) -> Result<(Reader, Writer)> {
+        let tcp = match &self.host {
+            url::Host::Domain(domain) => TcpStream::connect((domain.as_str(), self.port)).await?,
+            url::Host::Ipv4(addr) => TcpStream::connect((*addr, self.port)).await?,
+            url::Host::Ipv6(addr) => TcpStream::connect((*addr, self.port)).await?,
+        };
+        if !self.tls {
+            return self.handshake(tcp).await;
+        }
+
+        let server_name = match &self.host {
+            url::Host::Domain(domain) => ServerName::try_from(domain.clone())
+                .map_err(|e| Error::config(format!("invalid dashboard host {}: {}", domain, e)))?,
+            url::Host::Ipv4(addr) => ServerName::IpAddress((*addr).into()),
+            url::Host::Ipv6(addr) => ServerName::IpAddress((*addr).into()),
+        };
+        let stream = tls_connector()?.connect(server_name, tcp).await?;
+        self.handshake(stream).await
+    }
+
+    /// Perform the opening handshake on an established stream
+    async fn handshake<S>(&self, mut stream: S) -> Result<(Reader, Writer)>
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    {
+        let mut nonce = [0u8; 16];
+        OsRng.fill_bytes(&mut nonce);
+        let key = base64::engine::general_purpose::STANDARD.encode(nonce);
+        let authorization = self
+            .bearer_token
+            .as_ref()
+            .map(|token| format!("Authorization: Bearer {}\r\n", token))
+            .unwrap_or_default();
+        let handshake = format!(
+            "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Key: {}\r\nSec-WebSocket-Version: 13\r\n{}\r\n",
+            self.resource,
+            self.authority(),
+            key,
+            authorization
+        );
+        stream.write_all(handshake.as_bytes()).await?;
+        stream.flush().await?;
+
+        // Read the response byte by byte so no frame data is buffered away
+        let mut reader = BufReader::with_capacity(1, &mut stream);
+        let status = read_header_line(&mut reader).await?;
+        if status.split_whitespace().nth(1) != Some("101") {
+            return Err(Error::agent(format!("dashboard refused WebSocket upgrade: {}", status.trim())));
+        }
+        let mut accept = None;
+        for headers in 0.. {
+            let line = read_header_line(&mut reader).await?;
+            let line = line.trim_end();
+            if line.is_empty() {
+                break;
+            }
+            if headers == MAX_HEADERS {
+                return Err(Error::agent(format!("dashboard sent more than {} handshake headers", MAX_HEADERS)));
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                if name.trim().eq_ignore_ascii_case("sec-websocket-accept") {
+                    accept = Some(value.trim().to_string());
+                }
+            }
+        }
+        if accept.as_deref() != Some(accept_key(&key).as_str()) {
+            return Err(Error::agent("dashboard sent an invalid Sec-WebSocket-Accept header"));
+        }
+        drop(reader);
+
+        let (reader, writer) = tokio::io::split(stream);
+        Ok((Box::new(reader), Box::new(writer)))
+    }
+}
+
+/// Read one handshake response line, refusing overlong lines and early EOF
+async fn read_header_line<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<String> {
+    let mut line = String::new();
+    let read = reader.take(MAX_HEADER_LINE_BYTES as u64 + 1).read_line(&mut line).await?;
+    if read == 0 {
+        return Err(Error::agent("dashboard closed the connection during the handshake"));
+    }
+    if read > MAX_HEADER_LINE_BYTES {
+        return Err(Error::agent(format!(
+            "dashboard sent a handshake line longer than {} bytes",
+            MAX_HEADER_LINE_BYTES
+        )));
+    }
+    Ok(line)
+}
+
+/// TLS client trusting the Mozilla root certificates
+fn tls_connector() -> Result<TlsConnector> {
+    let roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    let config = ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .map_err(|e| Error::config(format!("TLS setup failed: {}", e)))?
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+/// Expected `Sec-WebSocket-Accept` value for a handshake key
+fn accept_key(key: &str) -> String {
+    let digest = Sha1::new()
+        .chain_update(key.as_bytes())
+        .chain_update(HANDSHAKE_GUID.as_bytes())
+        .finalize();
+    base64::engine::general_purpose::STANDARD.encode(digest)
+}
+
+/// Write one unfragmented frame, masked as clients must
+async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, opcode: u8, payload: &[u8], mask: bool) -> Result<()> {
+    let mut frame = Vec::with_capacity(payload.len() + 14);
+    frame.push(0x80 | opcode);
+    let mask_bit = if mask { 0x80 } else { 0 };
+    match payload.len() {
+        len if len < 126 => frame.push(mask_bit | len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(mask_bit | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(mask_bit | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    if mask {
+        let mut key = [0u8; 4];
+        OsRng.fill_bytes(&mut key);
+        frame.extend_from_slice(&key);
+        frame.extend(payload.iter().enumerate().map(|(i, byte)| byte ^ key[i % 4]));
+    } else {
+        frame.extend_from_slice(payload);
+    }
+    writer.write_all(&frame).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Read the next complete message, joining fragments; control frames are returned as-is
+async fn read_message<R: AsyncRead + Unpin>(reader: &mut R) -> Result<(u8, Vec<u8>)> {
+    let mut message: Option<(u8, Vec<u8>)> = None;
+    loop {
+        let mut header = [0u8; 2];
+        reader.read_exact(&mut header).await?;
+        let fin = header[0] & 0x80 != 0;
+        let opcode = header[0] & 0x0F;
+        let masked = header[1] & 0x80 != 0;
+        let len = match header[1] & 0x7F {
+            126 => reader.read_u16().await? as usize,
+            127 => reader.read_u64().await? as usize,
+            len => len as usize,
+        };
+        let buffered = message.as_ref().map_or(0, |(_, data)| data.len());
+        if len.saturating_add(buffered) > MAX_MESSAGE_BYTES {
+            return Err(Error::agent(format!("approval dashboard message exceeds {} bytes", MAX_MESSAGE_BYTES)));
+        }
+        let mut key = [0u8; 4];
+        if masked {
+            reader.read_exact(&mut key).await?;
+        }
+        let mut payload = vec![0u8; len];
+        reader.read_exact(&mut payload).await?;
+        if masked {
+            payload.iter_mut().enumerate().for_each(|(i, byte)| *byte ^= key[i % 4]);
+        }
+
+        if opcode >= OP_CLOSE {
+            return Ok((opcode, payload));
+        }
+        match (&mut message, opcode) {
+            (None, OP_CONTINUATION) => return Err(Error::agent("unexpected WebSocket continuation frame")),
+            (None, _) => message = Some((opcode, payload)),
+            (Some((_, data)), OP_CONTINUATION) => data.extend_from_slice(&payload),
+            (Some(_), _) => return Err(Error::agent("WebSocket frame interleaved with a fragmented message")),
+        }
+        if fin {
+            return Ok(message.take().expect("message started above"));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hitl::{ActionType, ApprovalContext, Priority};
+    use crate::types::{AgentId, UserId};
+    use tokio::net::TcpListener;
+
+    fn request(tool: &str) -> ApprovalRequest {
+        let mut data = HashMap::new();
+        data.insert("tool".to_string(), json!(tool));
+        data.insert("arguments".to_string(), json!({"pid": 42}));
+        ApprovalRequest {
+            id: ApprovalId::new(),
+            agent_id: AgentId::new(),
+            action_type: ActionType::ToolExecution,
+            description: format!("Call tool '{}'", tool),
+            context: ApprovalContext { data },
+            priority: Priority::High,
+            deadline: None,
+            suggested_approvers: Vec::new(),
+            timeout: None,
+        }
+    }
+
+    /// Accept one dashboard connection, completing the server side of the handshake
+    async fn accept(listener: &TcpListener) -> BufReader<TcpStream> {
+        accept_with_headers(listener).await.0
+    }
+
+    /// Like `accept`, also returning the handshake request's header lines
+    async fn accept_with_headers(listener: &TcpListener) -> (BufReader<TcpStream>, Vec<String>) {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut stream = BufReader::new(stream);
+        let mut key = String::new();
+        let mut headers = Vec::new();
+        loop {
+            let mut line = String::new();
+            stream.read_line(&mut line).await.unwrap();
+            if line.trim().is_empty() {
+                break;
+            }
+            if let Some(value) = line.strip_prefix("Sec-WebSocket-Key:") {
+                key = value.trim().to_string();
+            }
+            headers.push(line.trim_end().to_string());
+        }
+        let response = format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(&key)
+        );
+        stream.get_mut().write_all(response.as_bytes()).await.unwrap();
+        (stream, headers)
+    }
+
+    async fn next_request(stream: &mut BufReader<TcpStream>) -> serde_json::Value {
+        let (opcode, payload) = read_message(stream).await.unwrap();
+        assert_eq!(opcode, OP_TEXT);
+        serde_json::from_slice(&payload).unwrap()
+    }
+
+    async fn decide(stream: &mut BufReader<TcpStream>, id: &serde_json::Value, approver: &str) {
+        let reply = json!({"id": id, "decision": {"status": "approved", "approver": approver, "notes": null}});
+        write_frame(stream.get_mut(), OP_TEXT, reply.to_string().as_bytes(), false)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_websocket_approvals_survive_reconnect() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/approvals", listener.local_addr().unwrap());
+        let handler = Arc::new(WebSocketApprovalHandler::new(&url).unwrap());
+
+        let kill = tokio::spawn({
+            let handler = handler.clone();
+            async move { handler.request_approval(request("kill_process")).await }
+        });
+        let block = tokio::spawn({
+            let handler = handler.clone();
+            async move { handler.request_approval(request("block_ip")).await }
+        });
+
+        // The first dashboard sees both requests, then drops without answering
+        let mut first = accept(&listener).await;
+        let seen = [next_request(&mut first).await, next_request(&mut first).await];
+        assert!(seen.iter().any(|r| r["tool"] == "kill_process" && r["arguments"]["pid"] == 42));
+        drop(first);
+
+        // Both are requeued on the next connection and answered out of order
+        let mut second = accept(&listener).await;
+        let mut requeued = [next_request(&mut second).await, next_request(&mut second).await];
+        requeued.sort_by_key(|r| r["tool"].as_str().unwrap().to_string());
+        assert_eq!(requeued[0]["tool"], "block_ip");
+        decide(&mut second, &requeued[1]["request"]["id"], "alice").await;
+        decide(&mut second, &requeued[0]["request"]["id"], "bob").await;
+
+        let kill = kill.await.unwrap().unwrap();
+        assert!(matches!(kill, ApprovalDecision::Approved { ref approver, .. } if *approver == UserId::new("alice")));
+        let block = block.await.unwrap().unwrap();
+        assert!(matches!(block, ApprovalDecision::Approved { ref approver, .. } if *approver == UserId::new("bob")));
+        assert_eq!(handler.pending(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_bearer_token_over_ipv6() {
+        let Ok(listener) = TcpListener::bind("[::1]:0").await else {
+            return; // No IPv6 loopback here
+        };
+        let url = format!("ws://[::1]:{}/approvals", listener.local_addr().unwrap().port());
+        let handler = Arc::new(WebSocketApprovalHandler::with_bearer_token(&url, "s3cret").unwrap());
+        let pending = tokio::spawn({
+            let handler = handler.clone();
+            async move { handler.request_approval(request("kill_process")).await }
+        });
+
+        let (mut stream, headers) = accept_with_headers(&listener).await;
+        assert!(headers.iter().any(|h| h == "Authorization: Bearer s3cret"), "{:?}", headers);
+        let host = format!("Host: [::1]:{}", listener.local_addr().unwrap().port());
+        assert!(headers.contains(&host), "{:?}", headers);
+
+        let sent = next_request(&mut stream).await;
+        decide(&mut stream, &sent["request"]["id"], "alice").await;
+        assert!(pending.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_handshake_is_bounded() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut endpoint = Endpoint::parse(&format!("ws://{}/", listener.local_addr().unwrap())).unwrap();
+        endpoint.handshake_timeout = Duration::from_millis(200);
+
+        // A dashboard that sends `response` after reading the request, then stalls
+        let serve = |response: Vec<u8>| {
+            let listener = &listener;
+            async move {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = [0u8; 1024];
+                let _ = stream.read(&mut request).await.unwrap();
+                let _ = stream.write_all(&response).await;
+                stream
+            }
+        };
+        let status = b"HTTP/1.1 101 Switching Protocols\r\n".to_vec();
+
+        let (result, _stream) = tokio::join!(endpoint.connect(), serve(Vec::new()));
+        assert!(matches!(result, Err(Error::Timeout(_))));
+
+        let long_line = [status.clone(), vec![b'x'; MAX_HEADER_LINE_BYTES + 1]].concat();
+        let (result, _stream) = tokio::join!(endpoint.connect(), serve(long_line));
+        let err = result.err().unwrap().to_string();
+        assert!(err.contains("longer than"), "{}", err);
+
+        let many_headers = [status, b"X-Filler: 1\r\n".repeat(MAX_HEADERS + 1)].concat();
+        let (result, _stream) = tokio::join!(endpoint.connect(), serve(many_headers));
+        let err = result.err().unwrap().to_string();
+        assert!(err.contains("handshake headers"), "{}", err);
+    }
+
+    #[test]
+    fn test_resolved_outcomes_are_bounded() {
+        let mut state = State::default();
+        let first = ApprovalId::new();
+        state.resolve(first, ApprovalStatus::Approved);
+        for _ in 0..MAX_RESOLVED {
+            state.resolve(ApprovalId::new(), ApprovalStatus::Rejected);
+        }
+        assert_eq!(state.resolved.len(), MAX_RESOLVED);
+        assert!(!state.resolved.contains_key(&first));
+    }
+
+    #[test]
+    fn test_rejects_unsupported_urls() {
+        assert!(Endpoint::parse("http://dashboard.example.com/approvals").is_err());
+        assert!(Endpoint::parse("not a url").is_err());
+        let endpoint = Endpoint::parse("ws://localhost:8080/approvals?team=soc").unwrap();
+        assert_eq!(endpoint.port, 8080);
+        assert_eq!(endpoint.resource, "/approvals?team=soc");
+        let secure = Endpoint::parse("wss://dashboard.example.com/approvals").unwrap();
+        assert!(secure.tls);
+        assert_eq!(secure.port, 443);
+        let v6 = Endpoint::parse("ws://[::1]:9000/").unwrap();
+        assert_eq!(v6.host, url::Host::<String>::Ipv6(std::net::Ipv6Addr::LOCALHOST));
+        assert_eq!(v6.authority(), "[::1]:9000");
+        // Example from RFC 6455, section 1.3
+        assert_eq!(accept_key("dGhlIHNhbXBsZSBub25jZQ=="), "s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+    }
+}
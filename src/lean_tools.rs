@@ -0,0 +1,270 @@
+//! Lean 4 proof verification tool
+//!
+//! [`LeanVerifyTool`] writes a proof to a scratch file, checks it with the
+//! `lean` binary and reports the compiler output along with the location of
+//! every error, so prover agents can verify and repair proofs inside their
+//! own ReAct loop.
+
+use crate::error::{Error, Result};
+use crate::tools::{JsonSchema, Tool, ToolContext, ToolOutput};
+use async_trait::async_trait;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::process::Command;
+
+/// A diagnostic reported by Lean
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeanMessage {
+    /// Line in the proof (1-based)
+    pub line: usize,
+    /// Column in the line (0-based, as Lean reports it)
+    pub column: usize,
+    /// `error`, `warning` or `info`
+    pub severity: String,
+    /// Message text, including any continuation lines
+    pub message: String,
+}
+
+/// Parse `file:line:col: severity: message` diagnostics from Lean output
+///
+/// Lines that do not start a diagnostic are appended to the previous one.
+pub fn parse_lean_messages(output: &str) -> Vec<LeanMessage> {
+    static DIAGNOSTIC: OnceLock<Regex> = OnceLock::new();
+    let diagnostic = DIAGNOSTIC.get_or_init(|| {
+        Regex::new(r"^(?:.*?):(\d+):(\d+):\s*(error|warning|info)(?:\([^)]*\))?:\s?(.*)$").unwrap()
+    });
+
+    let mut messages: Vec<LeanMessage> = Vec::new();
+    for line in output.lines() {
+        if let Some(caps) = diagnostic.captures(line) {
+            messages.push(LeanMessage {
+                line: caps[1].parse().unwrap_or(0),
+                column: caps[2].parse().unwrap_or(0),
+                severity: caps[3].to_string(),
+                message: caps[4].to_string(),
+            });
+        } else if let Some(last) = messages.last_mut() {
+            if !line.trim().is_empty() {
+                last.message.push('\n');
+                last.message.push_str(line);
+            }
+        }
+    }
+    messages
+}
+
+/// Tool that checks Lean 4 code with the `lean` compiler
+///
+/// Takes `{ "code": "..." }`. The call succeeds only when Lean exits cleanly
+/// with no errors; `data` carries the full output and the parsed diagnostics.
+/// A missing `lean` binary or a timeout is reported as a failed call.
+#[derive(Debug, Clone)]
+pub struct LeanVerifyTool {
+    lean_path: PathBuf,
+    temp_dir: PathBuf,
+    timeout: Duration,
+}
+
+impl Default for LeanVerifyTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LeanVerifyTool {
+    /// Run `lean` from `PATH` with a 60 second timeout, in the system temp directory
+    pub fn new() -> Self {
+        Self {
+            lean_path: PathBuf::from("lean"),
+            temp_dir: std::env::temp_dir().join("spai_lean"),
+            timeout: Duration::from_secs(60),
+        }
+    }
+
+    /// Set the `lean` executable to run
+    pub fn with_lean_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.lean_path = path.into();
+        self
+    }
+
+    /// Set the directory proofs are written to before checking
+    pub fn with_temp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.temp_dir = dir.into();
+        self
+    }
+
+    /// Set how long Lean may run before the check is abandoned
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Whether the configured `lean` binary can be run
+    pub async fn is_available(&self) -> bool {
+        Command::new(&self.lean_path)
+            .arg("--version")
+            .output()
+            .await
+            .map(|o| o.status.success())
+            .unwrap_or(false)
+    }
+
+    /// Check `code` and describe the outcome
+    pub async fn verify(&self, code: &str) -> ToolOutput {
+        if let Err(e) = std::fs::create_dir_all(&self.temp_dir) {
+            return ToolOutput::failure(format!("Failed to create {}: {}", self.temp_dir.display(), e));
+        }
+        let file = self.temp_dir.join(format!("proof_{}.lean", uuid::Uuid::new_v4().simple()));
+        if let Err(e) = std::fs::write(&file, code) {
+            return ToolOutput::failure(format!("Failed to write {}: {}", file.display(), e));
+        }
+
+        let mut cmd = Command::new(&self.lean_path);
+        cmd.arg(&file).kill_on_drop(true);
+        let result = tokio::time::timeout(self.timeout, cmd.output()).await;
+        let _ = std::fs::remove_file(&file);
+
+        let output = match result {
+            Err(_) => return ToolOutput::failure(format!("Lean timed out after {:?}", self.timeout)),
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::NotFound => {
+                return ToolOutput::failure(format!(
+                    "Lean is not installed ({} not found); proofs cannot be verified",
+                    self.lean_path.display()
+                ))
+            }
+            Ok(Err(e)) => return ToolOutput::failure(format!("Failed to run lean: {}", e)),
+            Ok(Ok(output)) => output,
+        };
+
+        // Lean reports diagnostics on stdout; stderr is kept for crashes
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let combined = [stdout.trim(), stderr.trim()]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let messages = parse_lean_messages(&combined);
+        let errors = messages.iter().filter(|m| m.severity == "error").count();
+        let success = output.status.success() && errors == 0;
+
+        let data = serde_json::json!({
+            "success": success,
+            "output": combined,
+            "errors": messages.iter().filter(|m| m.severity == "error").collect::<Vec<_>>(),
+            "messages": messages,
+        });
+        let content = if combined.is_empty() {
+            "Lean accepted the proof with no messages".to_string()
+        } else {
+            combined.clone()
+        };
+
+        if success {
+            ToolOutput::success_with_data(content, data)
+        } else {
+            let error = if errors > 0 {
+                let first = messages.iter().find(|m| m.severity == "error").unwrap();
+                format!(
+                    "Lean reported {} error(s); first at line {}, column {}: {}",
+                    errors, first.line, first.column, first.message
+                )
+            } else {
+                format!("Lean exited with status: {}", output.status)
+            };
+            ToolOutput {
+                data: Some(data),
+                ..ToolOutput::failure_with_content(content, error)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Tool for LeanVerifyTool {
+    fn id(&self) -> &str {
+        "lean_verify"
+    }
+
+    fn name(&self) -> &str {
+        "Lean Verify"
+    }
+
+    fn description(&self) -> &str {
+        "Checks Lean 4 code with the Lean compiler and reports any errors with their line and column"
+    }
+
+    fn input_schema(&self) -> JsonSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "code".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "description": "Complete Lean 4 source to check"
+            }),
+        );
+
+        JsonSchema::object(properties).with_required(vec!["code".to_string()])
+    }
+
+    async fn execute(&self, params: Value, _ctx: &ToolContext) -> Result<ToolOutput> {
+        let code = params
+            .get("code")
+            .and_then(|v| v.as_str())
+            .ok_or_else(|| Error::InvalidInput("Missing 'code'".to_string()))?;
+        Ok(self.verify(code).await)
+    }
+
+    fn estimated_duration(&self) -> Duration {
+        Duration::from_secs(10)
+    }
+
+    fn cacheable(&self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lean_messages() {
+        let output = "/tmp/spai_lean/proof.lean:3:8: error: unknown identifier 'foo'\n\
+                      /tmp/spai_lean/proof.lean:5:0: warning: declaration uses 'sorry'\n\
+                      /tmp/spai_lean/proof.lean:7:2: error: type mismatch\n  h\nhas type\n  P : Prop";
+        let messages = parse_lean_messages(output);
+
+        assert_eq!(messages.len(), 3);
+        assert_eq!((messages[0].line, messages[0].column), (3, 8));
+        assert_eq!(messages[0].severity, "error");
+        assert_eq!(messages[0].message, "unknown identifier 'foo'");
+        assert_eq!(messages[1].severity, "warning");
+        assert_eq!(messages[2].message, "type mismatch\n  h\nhas type\n  P : Prop");
+    }
+
+    #[tokio::test]
+    async fn test_missing_lean_is_a_failed_call() {
+        let dir = tempfile::tempdir().unwrap();
+        let tool = LeanVerifyTool::new()
+            .with_lean_path("/nonexistent/lean")
+            .with_temp_dir(dir.path());
+        assert!(!tool.is_available().await);
+
+        let output = tool
+            .execute(
+                serde_json::json!({ "code": "theorem t : True := trivial" }),
+                &ToolContext::new(crate::types::AgentId::new()),
+            )
+            .await
+            .unwrap();
+        assert!(!output.success);
+        assert!(output.error.unwrap().contains("not installed"));
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+}
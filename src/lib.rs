@@ -46,17 +46,27 @@
 pub mod agent;
 pub mod agent_file;
 pub mod background;
+pub mod blocking;
 pub mod config;
+pub mod embedding;
 pub mod error;
 pub mod filesystem;
 pub mod guardrails;
 pub mod handoffs;
 pub mod hitl;
+pub mod lean_tools;
 pub mod llm_client;
 pub mod memory;
 pub mod memory_tools;
+pub mod metrics;
+#[cfg(feature = "ollama")]
+pub mod ollama;
+pub mod openai_compat;
 pub mod openrouter;
+pub mod output_transform;
 pub mod patterns;
+pub mod port_tools;
+pub mod prompt_log;
 pub mod orchestrator;
 pub mod react;
 pub mod sleeptime;
@@ -64,8 +74,14 @@ pub mod sleeptime;
 pub mod storage;
 pub mod tools;
 pub mod security_tools;
+pub mod swarm;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod tool_cache;
+pub mod tool_protocol;
 pub mod tracing_ext;
 pub mod turns;
+pub mod typed_tool;
 pub mod types;
 pub mod vllm;
 
@@ -74,36 +90,74 @@ pub mod vllm;
 pub mod solid;
 
 // Re-exports for convenience
-pub use agent::{Agent, AgentBuilder, AgentHooks, AgentOutput};
+pub use agent::{Agent, AgentBuilder, AgentHooks, AgentOutput, PlanEstimate, ToolEndHook, ToolStartHook};
 pub use agent_file::{AgentFile, CheckpointManager};
-pub use background::{BackgroundExecutor, RunId, SeqId, RunStatus, RunEvent, RunEventType, PaginatedEvents};
-pub use config::{ModelConfig, OpenRouterConfig};
+pub use background::{BackgroundExecutor, RunId, SeqId, RunStatus, RunEvent, RunEventType, RunStorage, PaginatedEvents};
+pub use config::{DataCollection, ModelConfig, OpenRouterConfig, ProviderPreferences, RetryConfig};
+pub use embedding::{Embedder, OpenRouterEmbedder};
 pub use error::{Error, Result};
 pub use filesystem::{FilesystemManager, AttachedFolder};
-pub use guardrails::{GuardrailContext, GuardrailResult, InputGuardrail, OutputGuardrail};
-pub use handoffs::{Handoff, HandoffContext, HandoffStrategy};
-pub use hitl::{ApprovalDecision, ApprovalHandler, ApprovalRequest};
-pub use llm_client::LlmClient;
-pub use memory::{AgentMemory, MemoryBlock, MemoryConfig, SharedMemoryManager};
-pub use openrouter::{OpenRouterClient, CompletionRequest, StreamChunk};
+pub use guardrails::{
+    BudgetGuardrail, GroundingGuardrail, GuardrailContext, GuardrailResult, InjectionAction, InjectionGuardrail,
+    InputGuardrail, InputObservationGuardrail, OutputGuardrail, RedactionGuardrail, SecretRedactionGuardrail,
+    SecretRedactor,
+};
+pub use handoffs::{
+    Handoff, HandoffContext, HandoffStrategy, LlmSummarizer, ObservationSummarizer, Predicate,
+    TruncatingSummarizer,
+};
+pub use hitl::{ApprovalDecision, ApprovalHandler, ApprovalRequest, TimeoutDecision};
+#[cfg(feature = "websocket-approval")]
+pub use hitl::WebSocketApprovalHandler;
+pub use lean_tools::LeanVerifyTool;
+pub use llm_client::{ClientRegistry, LlmClient};
+pub use memory::{
+    AgentMemory, FnTokenizer, HistoryWindow, MemoryBlock, MemoryConfig, SharedMemoryManager, TokenCounter, Tokenizer,
+};
+pub use metrics::{Metrics, NoopMetrics};
+pub use openai_compat::GenericOpenAiClient;
+#[cfg(feature = "ollama")]
+pub use ollama::{OllamaClient, OllamaConfig};
+#[cfg(feature = "prometheus")]
+pub use metrics::{spawn_prometheus_exporter, PrometheusMetrics};
+pub use openrouter::{
+    CompletionRequest, ContentPart, ImageUrl, KeyInfo, OpenRouterClient, OpenRouterModel, RateLimitInfo, ResponseFormat, Role, StreamChunk, ToolChoice,
+};
+pub use output_transform::{
+    ExtractCodeBlock, ExtractJsonObject, OutputTransform, StripThinkTags, TrimWhitespace,
+};
 pub use sleeptime::{SleepTimeAgent, SleepTimeConfig};
 #[cfg(feature = "storage")]
-pub use storage::{MemoryStorage, PostgresStorage, SqliteStorage};
+pub use storage::{MemoryStorage, PostgresStorage, SemanticMemoryStorage, SqliteStorage};
 pub use patterns::{PatternConfig, WorkflowPattern};
+pub use port_tools::PortListTool;
+pub use prompt_log::{InMemoryPromptLog, JsonlPromptLog, PromptLog, PromptRecord};
 pub use orchestrator::{
     OrchestratorConfig, OrchestratorPattern, OrchestratorResult,
     PatternType, AgentConfig, SubagentConfig,
     SequentialOrchestrator, ConcurrentOrchestrator, HierarchicalOrchestrator,
-    DebateOrchestrator, RouterOrchestrator, ConsensusOrchestrator,
+    DebateOrchestrator, RouterOrchestrator, ConsensusOrchestrator, PipelineOrchestrator, OrchestratorSnapshot,
 };
-pub use react::{ReActConfig, ReActTrace, ReasoningFormat};
-pub use tools::{Tool, ToolContext, ToolOutput};
+pub use react::{ReActConfig, ReActTrace, ReasoningFormat, ReasoningTags};
+pub use tool_cache::{DiskToolCache, InMemoryToolCache, ToolCache};
+pub use tool_protocol::ToolProtocol;
+pub use tools::{ProgressSender, ShellTool, Tool, ToolContext, ToolOutput, ToolProgress};
 #[cfg(feature = "mcp-tools")]
-pub use tools::McpSubprocessTool;
-pub use security_tools::{SecurityToolRegistry, SecurityTool, SecurityCategory, ListSecurityTools, RunSecurityTool, TaggedSecurityTools};
+pub use tools::{McpRemoteTool, McpSubprocessTool, McpToolClient};
+pub use swarm::{run_security_swarm, SecurityFindings, SwarmOptions};
+#[cfg(feature = "testing")]
+pub use testing::{MockLlmClient, MockResponse};
+pub use security_tools::{ReloadSummary, SecurityToolRegistry, SecurityTool, SecurityCategory, ListSecurityTools, MatchMode, RunSecurityTool, TagFilter, TaggedSecurityTools};
 pub use turns::{Session, Turn, TurnManager};
+pub use typed_tool::{SchemaType, ToolParams, TypedTool};
 pub use types::{AgentId, SessionId, SpanId, TraceId, TurnId};
 pub use vllm::{VllmClient, VllmConfig};
+pub use tokio_util::sync::CancellationToken;
+
+#[doc(hidden)]
+pub mod __private {
+    pub use serde_json;
+}
 
 /// Prelude module for common imports
 pub mod prelude {
@@ -1,8 +1,10 @@
 //! Unified LLM client trait for both remote (OpenRouter) and local (vLLM/SGLang) models
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::openrouter::{CompletionRequest, CompletionResponse, CompletionStream};
 use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Arc;
 
 /// Unified trait for LLM clients (both remote and local)
 #[async_trait]
@@ -18,4 +20,181 @@ pub trait LlmClient: Send + Sync {
 
     /// Get the base URL (for local models) or endpoint (for remote)
     fn endpoint(&self) -> &str;
+
+    /// Model IDs this client can serve, for validating configuration
+    ///
+    /// Clients that cannot list their models return an error (the default).
+    async fn model_ids(&self) -> Result<Vec<String>> {
+        Err(Error::config(format!("{} client cannot list models", self.client_type())))
+    }
+}
+
+/// Check a model ID against the available IDs, returning its canonical form
+///
+/// Matching ignores case and surrounding whitespace. Unknown IDs fail with
+/// the nearest available IDs as suggestions.
+pub fn resolve_model_id(model: &str, available: &[String]) -> Result<String> {
+    let wanted = model.trim().to_lowercase();
+    if let Some(id) = available.iter().find(|id| id.to_lowercase() == wanted) {
+        return Ok(id.clone());
+    }
+
+    let max_distance = (wanted.len() / 4).max(2);
+    let mut nearest: Vec<(usize, &String)> = available
+        .iter()
+        .map(|id| (edit_distance(&wanted, &id.to_lowercase()), id))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+    nearest.sort();
+
+    let hint = if nearest.is_empty() {
+        format!("{} models available", available.len())
+    } else {
+        let names: Vec<&str> = nearest.iter().take(3).map(|(_, id)| id.as_str()).collect();
+        format!("did you mean {}?", names.join(", "))
+    };
+    Err(Error::config(format!("Unknown model '{}' ({})", model, hint)))
+}
+
+/// Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Registry mapping logical client names to LLM clients
+///
+/// Lets declarative configs refer to clients by name (e.g. `client: local`)
+/// so one orchestration can mix providers.
+#[derive(Clone, Default)]
+pub struct ClientRegistry {
+    clients: HashMap<String, Arc<dyn LlmClient>>,
+    default: Option<String>,
+}
+
+impl ClientRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a client under a name
+    pub fn with_client(mut self, name: impl Into<String>, client: Arc<dyn LlmClient>) -> Self {
+        self.register(name, client);
+        self
+    }
+
+    /// Set the client used when a config does not name one
+    pub fn with_default(mut self, name: impl Into<String>) -> Self {
+        self.default = Some(name.into());
+        self
+    }
+
+    /// Register a client under a name, replacing any previous one
+    pub fn register(&mut self, name: impl Into<String>, client: Arc<dyn LlmClient>) {
+        self.clients.insert(name.into(), client);
+    }
+
+    /// Look up a client by name
+    pub fn get(&self, name: &str) -> Option<Arc<dyn LlmClient>> {
+        self.clients.get(name).cloned()
+    }
+
+    /// Registered client names, sorted
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.clients.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Resolve a client by optional name
+    ///
+    /// Without a name, falls back to the default client, or to the only
+    /// registered client if there is exactly one.
+    pub fn resolve(&self, name: Option<&str>) -> Result<Arc<dyn LlmClient>> {
+        let name = match name.or(self.default.as_deref()) {
+            Some(name) => name,
+            None if self.clients.len() == 1 => {
+                return Ok(self.clients.values().next().cloned().expect("one client registered"));
+            }
+            None => {
+                return Err(Error::config(format!(
+                    "No client specified and no default set (registered: {:?})",
+                    self.names()
+                )))
+            }
+        };
+
+        self.get(name).ok_or_else(|| {
+            Error::config(format!(
+                "Unknown LLM client '{}' (registered: {:?})",
+                name,
+                self.names()
+            ))
+        })
+    }
+}
+
+impl std::fmt::Debug for ClientRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientRegistry")
+            .field("clients", &self.names())
+            .field("default", &self.default)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vllm::{VllmClient, VllmConfig};
+
+    fn local(url: &str) -> Arc<dyn LlmClient> {
+        Arc::new(VllmClient::new(VllmConfig::new(url)).unwrap())
+    }
+
+    #[test]
+    fn test_client_registry_resolution() {
+        let registry = ClientRegistry::new()
+            .with_client("local", local("http://localhost:8000"))
+            .with_client("gpu", local("http://gpu:8000"));
+
+        assert_eq!(registry.names(), vec!["gpu", "local"]);
+        assert_eq!(registry.resolve(Some("gpu")).unwrap().endpoint(), "http://gpu:8000");
+        assert!(registry.resolve(Some("missing")).is_err());
+        assert!(registry.resolve(None).is_err());
+
+        let registry = registry.with_default("local");
+        assert_eq!(registry.resolve(None).unwrap().endpoint(), "http://localhost:8000");
+
+        let single = ClientRegistry::new().with_client("only", local("http://only:8000"));
+        assert!(single.resolve(None).is_ok());
+    }
+
+    #[test]
+    fn test_resolve_model_id() {
+        let available = vec![
+            "anthropic/claude-sonnet-4.5".to_string(),
+            "anthropic/claude-opus-4.1".to_string(),
+            "openai/gpt-4o".to_string(),
+        ];
+
+        assert_eq!(resolve_model_id(" OpenAI/GPT-4o ", &available).unwrap(), "openai/gpt-4o");
+
+        let err = resolve_model_id("anthropic/claude-sonet-4.5", &available).unwrap_err();
+        assert!(err.to_string().contains("did you mean anthropic/claude-sonnet-4.5"));
+
+        let err = resolve_model_id("mistral/large", &available).unwrap_err();
+        assert!(err.to_string().contains("3 models available"));
+    }
 }
@@ -8,12 +8,14 @@
 //! - Perpetual message history with Agent File (.af) format
 
 use crate::error::{Error, Result};
+use crate::llm_client::LlmClient;
+use crate::openrouter::{CompletionRequest, ContentPart, Message, Role};
 use crate::types::AgentId;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{Mutex, RwLock};
 use uuid::Uuid;
 
 #[cfg(feature = "storage")]
@@ -28,6 +30,11 @@ impl MemoryBlockId {
     pub fn new() -> Self {
         Self(Uuid::new_v4())
     }
+
+    /// Create a memory block ID from a UUID
+    pub fn from_uuid(uuid: Uuid) -> Self {
+        Self(uuid)
+    }
 }
 
 impl Default for MemoryBlockId {
@@ -43,7 +50,7 @@ impl std::fmt::Display for MemoryBlockId {
 }
 
 /// Memory block - a persistent, editable chunk of agent memory
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MemoryBlock {
     /// Unique identifier
     pub id: MemoryBlockId,
@@ -154,6 +161,26 @@ pub struct MemoryConfig {
 
     /// Storage backend configuration
     pub storage_backend: StorageBackend,
+
+    /// Compact message history once blocks plus history exceed this fraction
+    /// of `max_context_size` (None disables automatic compaction)
+    #[serde(default)]
+    pub compaction_ratio: Option<f32>,
+
+    /// Most recent messages kept verbatim when compacting
+    #[serde(default = "default_compaction_keep_recent")]
+    pub compaction_keep_recent: usize,
+
+    /// How much message history is replayed into each prompt
+    ///
+    /// Used by agents whose builder does not set a
+    /// [`history_window`](crate::agent::AgentBuilder::history_window).
+    #[serde(default)]
+    pub window_strategy: HistoryWindow,
+}
+
+fn default_compaction_keep_recent() -> usize {
+    10
 }
 
 impl Default for MemoryConfig {
@@ -165,6 +192,9 @@ impl Default for MemoryConfig {
             storage_backend: StorageBackend::Sqlite {
                 path: "spai_memory.db".to_string(),
             },
+            compaction_ratio: None,
+            compaction_keep_recent: default_compaction_keep_recent(),
+            window_strategy: HistoryWindow::default(),
         }
     }
 }
@@ -182,6 +212,202 @@ pub enum StorageBackend {
     Memory,
 }
 
+/// Counts how many tokens text will take up in a prompt
+///
+/// Implement this with a model's real tokenizer for exact budgets, or wrap
+/// one in a [`FnTokenizer`]; the default [`TokenCounter`] is a
+/// characters-per-token heuristic.
+pub trait Tokenizer: Send + Sync {
+    /// Count tokens in a piece of text
+    fn count_tokens(&self, text: &str) -> usize;
+
+    /// Tokens added per message for role and framing
+    fn per_message_overhead(&self) -> usize {
+        4
+    }
+
+    /// Count tokens for chat messages, including tool calls and overhead
+    ///
+    /// Image parts are not counted; providers bill them separately.
+    fn count_messages(&self, messages: &[Message]) -> usize {
+        messages
+            .iter()
+            .map(|message| {
+                let text: usize = message
+                    .parts
+                    .iter()
+                    .map(|part| match part {
+                        ContentPart::Text { text } => self.count_tokens(text),
+                        ContentPart::ImageUrl { .. } => 0,
+                    })
+                    .sum();
+                let tool_calls: usize = message
+                    .tool_calls
+                    .iter()
+                    .flatten()
+                    .map(|call| self.count_tokens(&call.function.name) + self.count_tokens(&call.function.arguments))
+                    .sum();
+                self.count_tokens(&message.content) + text + tool_calls + self.per_message_overhead()
+            })
+            .sum()
+    }
+
+    /// Count tokens for a history message, including overhead
+    fn count_message(&self, message: &MessageEntry) -> usize {
+        self.count_tokens(&message.content) + self.per_message_overhead()
+    }
+}
+
+/// [`Tokenizer`] backed by a counting function
+///
+/// Plugs in any model tokenizer without a wrapper type, e.g. a
+/// `tiktoken-rs` encoding:
+///
+/// ```ignore
+/// let bpe = tiktoken_rs::o200k_base()?;
+/// let tokenizer = FnTokenizer::new(move |text| bpe.encode_with_special_tokens(text).len());
+/// ```
+pub struct FnTokenizer {
+    count: Box<dyn Fn(&str) -> usize + Send + Sync>,
+    per_message_overhead: usize,
+}
+
+impl FnTokenizer {
+    /// Count tokens with `count`, adding 4 tokens per message
+    pub fn new(count: impl Fn(&str) -> usize + Send + Sync + 'static) -> Self {
+        Self {
+            count: Box::new(count),
+            per_message_overhead: 4,
+        }
+    }
+
+    /// Set the tokens added per message for role and framing
+    pub fn with_per_message_overhead(mut self, tokens: usize) -> Self {
+        self.per_message_overhead = tokens;
+        self
+    }
+}
+
+impl std::fmt::Debug for FnTokenizer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FnTokenizer")
+            .field("per_message_overhead", &self.per_message_overhead)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Tokenizer for FnTokenizer {
+    fn count_tokens(&self, text: &str) -> usize {
+        (self.count)(text)
+    }
+
+    fn per_message_overhead(&self) -> usize {
+        self.per_message_overhead
+    }
+}
+
+/// Approximate token counter used for context budgeting
+///
+/// Uses a characters-per-token heuristic plus a fixed per-message overhead,
+/// which is close enough for budgeting without a model-specific tokenizer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenCounter {
+    /// Average characters per token
+    pub chars_per_token: usize,
+    /// Tokens added per message for role and framing
+    pub per_message_overhead: usize,
+}
+
+impl TokenCounter {
+    /// Create a counter with the default heuristic (4 chars/token, 4 tokens/message)
+    pub fn new() -> Self {
+        Self {
+            chars_per_token: 4,
+            per_message_overhead: 4,
+        }
+    }
+
+    /// Estimate tokens in a piece of text
+    pub fn count(&self, text: &str) -> usize {
+        text.chars().count().div_ceil(self.chars_per_token.max(1))
+    }
+
+    /// Estimate tokens for a history message, including overhead
+    pub fn count_message(&self, message: &MessageEntry) -> usize {
+        self.count(&message.content) + self.per_message_overhead
+    }
+}
+
+impl Default for TokenCounter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tokenizer for TokenCounter {
+    fn count_tokens(&self, text: &str) -> usize {
+        self.count(text)
+    }
+
+    fn per_message_overhead(&self) -> usize {
+        self.per_message_overhead
+    }
+}
+
+/// How much message history is included in each prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryWindow {
+    /// Include the entire history
+    #[default]
+    All,
+    /// Include the most recent N messages
+    LastN(usize),
+    /// Include the most recent messages that fit in a token budget
+    TokenBudget(usize),
+    /// Include every compaction summary, then the most recent `keep` messages
+    SummaryPlusRecent {
+        /// Recent messages included verbatim
+        keep: usize,
+    },
+}
+
+impl std::fmt::Display for HistoryWindow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HistoryWindow::All => write!(f, "all"),
+            HistoryWindow::LastN(n) => write!(f, "last_n({})", n),
+            HistoryWindow::TokenBudget(tokens) => write!(f, "token_budget({})", tokens),
+            HistoryWindow::SummaryPlusRecent { keep } => write!(f, "summary_plus_recent({})", keep),
+        }
+    }
+}
+
+/// Messages chosen for a prompt by a [`HistoryWindow`]
+#[derive(Debug, Clone)]
+pub struct HistorySelection {
+    /// Window that produced this selection
+    pub window: HistoryWindow,
+    /// Selected messages, oldest first
+    pub messages: Vec<MessageEntry>,
+    /// Messages available in the full history
+    pub available: usize,
+    /// Estimated tokens in the selected messages
+    pub estimated_tokens: usize,
+}
+
+impl HistorySelection {
+    /// Summary suitable for output metadata
+    pub fn summary(&self) -> serde_json::Value {
+        serde_json::json!({
+            "window": self.window.to_string(),
+            "selected": self.messages.len(),
+            "available": self.available,
+            "estimated_tokens": self.estimated_tokens,
+        })
+    }
+}
+
 /// Agent memory manager - handles all memory blocks for an agent
 #[derive(Debug, Clone)]
 pub struct AgentMemory {
@@ -202,7 +428,7 @@ pub struct AgentMemory {
 }
 
 /// A single message in the agent's perpetual history
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct MessageEntry {
     /// Unique message ID
     pub id: Uuid,
@@ -211,7 +437,7 @@ pub struct MessageEntry {
     pub timestamp: DateTime<Utc>,
 
     /// Role (user, assistant, system, tool)
-    pub role: String,
+    pub role: Role,
 
     /// Message content
     pub content: String,
@@ -223,6 +449,16 @@ pub struct MessageEntry {
     pub metadata: HashMap<String, String>,
 }
 
+/// Metadata key marking a message as a compaction summary
+pub const SUMMARY_METADATA_KEY: &str = "summary";
+
+impl MessageEntry {
+    /// Whether this message is a compaction summary (never re-summarized)
+    pub fn is_summary(&self) -> bool {
+        self.metadata.get(SUMMARY_METADATA_KEY).is_some_and(|v| v == "true")
+    }
+}
+
 impl AgentMemory {
     /// Create a new agent memory manager
     pub fn new(agent_id: AgentId, config: MemoryConfig) -> Self {
@@ -261,6 +497,32 @@ impl AgentMemory {
         }
     }
 
+    /// Get a memory block by label
+    pub async fn get_block_by_label(&self, label: &str) -> Option<MemoryBlock> {
+        let blocks = self.blocks.read().await;
+        blocks.values().find(|b| b.label == label).cloned()
+    }
+
+    /// Edit the block with `label` in place, returning the updated block
+    ///
+    /// `update` receives the current value and returns the new one, which
+    /// must fit the block's `max_size`; otherwise the block is left unchanged.
+    pub async fn edit_block_by_label(
+        &self,
+        label: &str,
+        update: impl FnOnce(&str) -> Result<String>,
+    ) -> Result<MemoryBlock> {
+        let mut blocks = self.blocks.write().await;
+        let block = blocks
+            .values_mut()
+            .find(|b| b.label == label)
+            .ok_or_else(|| Error::config(format!("Memory block '{}' not found", label)))?;
+
+        let new_value = update(&block.value)?;
+        block.update_value(new_value)?;
+        Ok(block.clone())
+    }
+
     /// Delete a memory block
     pub async fn delete_block(&self, id: MemoryBlockId) -> Result<()> {
         let mut blocks = self.blocks.write().await;
@@ -273,6 +535,11 @@ impl AgentMemory {
     }
 
     /// Attach a shared memory block (by ID)
+    ///
+    /// Only the ID is stored; the block itself stays in the
+    /// [`SharedMemoryManager`], so every agent that attaches it shares the
+    /// same per-block lock and sees writes made through
+    /// [`SharedMemoryManager::update_block`] atomically.
     pub async fn attach_shared_block(&self, block_id: MemoryBlockId) {
         let mut shared = self.shared_blocks.write().await;
         if !shared.contains(&block_id) {
@@ -280,6 +547,11 @@ impl AgentMemory {
         }
     }
 
+    /// IDs of the shared blocks attached to this agent
+    pub async fn shared_block_ids(&self) -> Vec<MemoryBlockId> {
+        self.shared_blocks.read().await.clone()
+    }
+
     /// Get all in-context memory blocks
     pub async fn in_context_blocks(&self) -> Vec<MemoryBlock> {
         let blocks = self.blocks.read().await;
@@ -306,6 +578,85 @@ impl AgentMemory {
         blocks.iter().map(|b| b.size()).sum()
     }
 
+    /// Total characters in the message history
+    pub async fn history_size(&self) -> usize {
+        let history = self.message_history.read().await;
+        history.iter().map(|m| m.content.len()).sum()
+    }
+
+    /// Whether blocks plus history exceed the configured compaction threshold
+    pub async fn needs_compaction(&self) -> bool {
+        let Some(ratio) = self.config.compaction_ratio else {
+            return false;
+        };
+        let threshold = (self.config.max_context_size as f64 * ratio as f64) as usize;
+        self.context_size().await + self.history_size().await > threshold
+    }
+
+    /// Summarize older history into a single summary message
+    ///
+    /// Every message except the most recent `compaction_keep_recent` is
+    /// condensed by `model` into one `system` message tagged with
+    /// [`SUMMARY_METADATA_KEY`], which takes their place. Earlier summaries
+    /// are kept as they are rather than summarized again, and memory blocks
+    /// (including shared ones) are never touched. Returns how many messages
+    /// were folded into the summary.
+    pub async fn compact(&self, client: &dyn LlmClient, model: &str) -> Result<usize> {
+        let older: Vec<MessageEntry> = {
+            let history = self.message_history.read().await;
+            let end = history.len().saturating_sub(self.config.compaction_keep_recent);
+            history[..end].iter().filter(|m| !m.is_summary()).cloned().collect()
+        };
+        if older.is_empty() {
+            return Ok(0);
+        }
+
+        let transcript = older
+            .iter()
+            .map(|m| format!("{}: {}", m.role, m.content))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let request = CompletionRequest::new(
+            model,
+            vec![
+                Message::system(
+                    "Summarize the conversation below so it can replace the original messages. \
+                     Keep decisions, facts, open questions and concrete identifiers; drop small talk.",
+                ),
+                Message::user(transcript),
+            ],
+        )
+        .with_temperature(0.0);
+        let response = client.complete(request).await?;
+        let summary = response
+            .choices
+            .first()
+            .map(|choice| choice.message.content.trim().to_string())
+            .filter(|content| !content.is_empty())
+            .ok_or_else(|| Error::agent("Compaction summarizer returned an empty response"))?;
+
+        let mut metadata = HashMap::new();
+        metadata.insert(SUMMARY_METADATA_KEY.to_string(), "true".to_string());
+        metadata.insert("summarized_messages".to_string(), older.len().to_string());
+        let entry = MessageEntry {
+            id: Uuid::new_v4(),
+            timestamp: older.last().map(|m| m.timestamp).unwrap_or_else(Utc::now),
+            role: Role::System,
+            content: format!("Summary of earlier conversation: {}", summary),
+            tool_calls: None,
+            metadata,
+        };
+
+        // Messages may have been added while the model was summarizing; only
+        // summaries precede the first summarized message, so `position` stays valid
+        let ids: HashSet<Uuid> = older.iter().map(|m| m.id).collect();
+        let mut history = self.message_history.write().await;
+        let position = history.iter().position(|m| ids.contains(&m.id)).unwrap_or(0);
+        history.retain(|m| !ids.contains(&m.id));
+        history.insert(position, entry);
+        Ok(older.len())
+    }
+
     /// Move a block out of context (to save context window space)
     pub async fn move_out_of_context(&self, id: MemoryBlockId) -> Result<()> {
         let mut blocks = self.blocks.write().await;
@@ -350,7 +701,7 @@ impl AgentMemory {
     }
 
     /// Add a message to the perpetual history
-    pub async fn add_message(&self, role: String, content: String) -> Uuid {
+    pub async fn add_message(&self, role: Role, content: String) -> Uuid {
         let message = MessageEntry {
             id: Uuid::new_v4(),
             timestamp: Utc::now(),
@@ -366,6 +717,16 @@ impl AgentMemory {
         id
     }
 
+    /// Get the full message history, oldest first
+    pub async fn messages(&self) -> Vec<MessageEntry> {
+        self.message_history.read().await.clone()
+    }
+
+    /// Replace the message history, e.g. when restoring a checkpoint
+    pub async fn replace_messages(&self, messages: Vec<MessageEntry>) {
+        *self.message_history.write().await = messages;
+    }
+
     /// Get message history (last N messages)
     pub async fn get_recent_messages(&self, limit: usize) -> Vec<MessageEntry> {
         let history = self.message_history.read().await;
@@ -373,6 +734,44 @@ impl AgentMemory {
         history[start..].to_vec()
     }
 
+    /// Select history messages for a prompt according to a window
+    pub async fn select_history(&self, window: HistoryWindow, counter: &dyn Tokenizer) -> HistorySelection {
+        let history = self.message_history.read().await;
+        let start = match window {
+            HistoryWindow::All => 0,
+            HistoryWindow::LastN(n) => history.len().saturating_sub(n),
+            HistoryWindow::SummaryPlusRecent { keep } => history.len().saturating_sub(keep),
+            HistoryWindow::TokenBudget(budget) => {
+                let mut used = 0;
+                let mut start = history.len();
+                for message in history.iter().rev() {
+                    let tokens = counter.count_message(message);
+                    if used + tokens > budget {
+                        break;
+                    }
+                    used += tokens;
+                    start -= 1;
+                }
+                start
+            }
+        };
+
+        let mut messages: Vec<MessageEntry> = match window {
+            HistoryWindow::SummaryPlusRecent { .. } => {
+                history[..start].iter().filter(|m| m.is_summary()).cloned().collect()
+            }
+            _ => Vec::new(),
+        };
+        messages.extend_from_slice(&history[start..]);
+        let estimated_tokens = messages.iter().map(|m| counter.count_message(m)).sum();
+        HistorySelection {
+            window,
+            messages,
+            available: history.len(),
+            estimated_tokens,
+        }
+    }
+
     /// Search message history by content
     pub async fn search_messages(&self, query: &str) -> Vec<MessageEntry> {
         let history = self.message_history.read().await;
@@ -457,10 +856,14 @@ impl AgentMemory {
 }
 
 /// Shared memory manager - manages blocks shared across multiple agents
+///
+/// Each block sits behind its own async lock, so a read-modify-write through
+/// [`update_block`](Self::update_block) cannot lose a concurrent writer's
+/// update, and writers to different blocks do not wait on each other.
 #[derive(Debug, Clone)]
 pub struct SharedMemoryManager {
     /// All shared memory blocks
-    blocks: Arc<RwLock<HashMap<MemoryBlockId, MemoryBlock>>>,
+    blocks: Arc<RwLock<HashMap<MemoryBlockId, Arc<Mutex<MemoryBlock>>>>>,
 }
 
 impl SharedMemoryManager {
@@ -482,26 +885,82 @@ impl SharedMemoryManager {
         let id = block.id;
 
         let mut blocks = self.blocks.write().await;
-        blocks.insert(id, block);
+        blocks.insert(id, Arc::new(Mutex::new(block)));
         id
     }
 
+    /// Lock guarding a shared block
+    async fn lock_for(&self, id: MemoryBlockId) -> Result<Arc<Mutex<MemoryBlock>>> {
+        let blocks = self.blocks.read().await;
+        blocks
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| Error::config(format!("Shared block {} not found", id)))
+    }
+
     /// Get a shared block by ID
     pub async fn get_block(&self, id: MemoryBlockId) -> Option<MemoryBlock> {
-        let blocks = self.blocks.read().await;
-        blocks.get(&id).cloned()
+        let lock = self.lock_for(id).await.ok()?;
+        let block = lock.lock().await;
+        Some(block.clone())
     }
 
-    /// Update a shared block
-    pub async fn update_block(&self, id: MemoryBlockId, new_value: String) -> Result<()> {
-        let mut blocks = self.blocks.write().await;
+    /// Atomically read, modify and write a shared block
+    ///
+    /// `update` runs while holding the block's lock, so concurrent updates
+    /// are applied one after another. The change is discarded if the new
+    /// value exceeds the block's `max_size`. Returns the updated block.
+    pub async fn update_block(
+        &self,
+        id: MemoryBlockId,
+        update: impl FnOnce(&mut MemoryBlock),
+    ) -> Result<MemoryBlock> {
+        let lock = self.lock_for(id).await?;
+        let mut block = lock.lock().await;
+
+        let mut updated = block.clone();
+        update(&mut updated);
+        let previous = block.updated_at;
+        let value = std::mem::take(&mut updated.value);
+        updated.update_value(value)?;
+        // Keep `updated_at` strictly increasing so compare_and_swap sees every write
+        if updated.updated_at <= previous {
+            updated.updated_at = previous + chrono::Duration::nanoseconds(1);
+        }
 
-        if let Some(block) = blocks.get_mut(&id) {
-            block.update_value(new_value)?;
-            Ok(())
-        } else {
-            Err(Error::config(format!("Shared block {} not found", id)))
+        *block = updated.clone();
+        Ok(updated)
+    }
+
+    /// Replace a shared block's value
+    pub async fn set_block_value(&self, id: MemoryBlockId, new_value: String) -> Result<()> {
+        self.update_block(id, |block| block.value = new_value).await.map(|_| ())
+    }
+
+    /// Replace a shared block's value only if nobody wrote it since `expected_updated_at`
+    ///
+    /// For optimistic writers that read the block, work without holding the
+    /// lock, then write back. Returns `false`, leaving the block unchanged,
+    /// if the block's `updated_at` no longer matches.
+    pub async fn compare_and_swap(
+        &self,
+        id: MemoryBlockId,
+        expected_updated_at: DateTime<Utc>,
+        new_value: String,
+    ) -> Result<bool> {
+        let lock = self.lock_for(id).await?;
+        let mut block = lock.lock().await;
+        if block.updated_at != expected_updated_at {
+            return Ok(false);
+        }
+
+        let mut updated = block.clone();
+        updated.update_value(new_value)?;
+        if updated.updated_at <= expected_updated_at {
+            updated.updated_at = expected_updated_at + chrono::Duration::nanoseconds(1);
         }
+        *block = updated;
+        Ok(true)
     }
 }
 
@@ -514,6 +973,7 @@ impl Default for SharedMemoryManager {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::testing::MockLlmClient;
 
     #[tokio::test]
     async fn test_memory_block_creation() {
@@ -523,6 +983,112 @@ mod tests {
         assert!(block.in_context);
     }
 
+    #[tokio::test]
+    async fn test_history_window_selection() {
+        let memory = AgentMemory::new(AgentId::new(), MemoryConfig::default());
+        for i in 0..5 {
+            memory.add_message(Role::User, format!("message {} {}", i, "x".repeat(36))).await;
+        }
+        let counter = TokenCounter::new();
+
+        let all = memory.select_history(HistoryWindow::All, &counter).await;
+        assert_eq!(all.messages.len(), 5);
+
+        let last = memory.select_history(HistoryWindow::LastN(2), &counter).await;
+        assert_eq!(last.messages.len(), 2);
+        assert!(last.messages[1].content.starts_with("message 4"));
+
+        // Each message is 46 chars -> 12 tokens + 4 overhead = 16 tokens
+        let budget = memory.select_history(HistoryWindow::TokenBudget(40), &counter).await;
+        assert_eq!(budget.messages.len(), 2);
+        assert_eq!(budget.estimated_tokens, 32);
+        assert_eq!(budget.summary()["window"], "token_budget(40)");
+    }
+
+    /// Counts whitespace-separated words, with no per-message overhead
+    struct WordTokenizer;
+
+    impl Tokenizer for WordTokenizer {
+        fn count_tokens(&self, text: &str) -> usize {
+            text.split_whitespace().count()
+        }
+
+        fn per_message_overhead(&self) -> usize {
+            0
+        }
+    }
+
+    #[test]
+    fn test_tokenizer_counts_messages() {
+        let mut call = Message::assistant("");
+        call.tool_calls = Some(vec![crate::openrouter::ToolCall {
+            id: "call_1".to_string(),
+            tool_type: "function".to_string(),
+            function: crate::openrouter::FunctionCall {
+                name: "echo".to_string(),
+                arguments: "{\"message\": \"hi\"}".to_string(),
+            },
+        }]);
+        let messages = vec![Message::system("You are terse."), Message::user("What is open?"), call];
+
+        // "You are terse." is 14 chars -> 4, "What is open?" 13 -> 4, "echo" -> 1,
+        // the 17-char arguments -> 5, plus 4 overhead per message
+        assert_eq!(TokenCounter::new().count_messages(&messages), 4 + 4 + 1 + 5 + 12);
+
+        let words = FnTokenizer::new(|text| text.split_whitespace().count()).with_per_message_overhead(1);
+        assert_eq!(words.count_tokens("three word text"), 3);
+        assert_eq!(words.count_messages(&messages), 3 + 3 + 1 + 2 + 3);
+    }
+
+    #[tokio::test]
+    async fn test_history_window_strategies() {
+        let memory = AgentMemory::new(AgentId::new(), MemoryConfig::default());
+        let mut summary = MessageEntry {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            role: Role::System,
+            content: "Summary of earlier conversation: port 22 is open".to_string(),
+            tool_calls: None,
+            metadata: HashMap::new(),
+        };
+        summary.metadata.insert(SUMMARY_METADATA_KEY.to_string(), "true".to_string());
+        let mut history = vec![summary];
+        for content in ["scan the host", "checking ports now", "any web servers", "nginx on 443"] {
+            history.push(MessageEntry {
+                id: Uuid::new_v4(),
+                content: content.to_string(),
+                role: Role::User,
+                metadata: HashMap::new(),
+                ..history[0].clone()
+            });
+        }
+        memory.replace_messages(history).await;
+
+        let recent = memory.select_history(HistoryWindow::LastN(2), &TokenCounter::new()).await;
+        let contents: Vec<&str> = recent.messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["any web servers", "nginx on 443"]);
+
+        // 3 + 3 words fit in 7; the next message back would need 3 more
+        let budget = memory.select_history(HistoryWindow::TokenBudget(7), &WordTokenizer).await;
+        let contents: Vec<&str> = budget.messages.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(contents, ["any web servers", "nginx on 443"]);
+        assert_eq!(budget.estimated_tokens, 6);
+
+        let summarized = memory
+            .select_history(HistoryWindow::SummaryPlusRecent { keep: 1 }, &TokenCounter::new())
+            .await;
+        assert_eq!(summarized.messages.len(), 2);
+        assert!(summarized.messages[0].is_summary());
+        assert_eq!(summarized.messages[1].content, "nginx on 443");
+        assert_eq!(summarized.summary()["window"], "summary_plus_recent(1)");
+
+        // A summary inside the recent window is not repeated
+        let everything = memory
+            .select_history(HistoryWindow::SummaryPlusRecent { keep: 10 }, &TokenCounter::new())
+            .await;
+        assert_eq!(everything.messages.len(), 5);
+    }
+
     #[tokio::test]
     async fn test_memory_block_update() {
         let mut block = MemoryBlock::new("test", "original");
@@ -574,4 +1140,111 @@ mod tests {
         let block = shared_manager.get_block(block_id).await.unwrap();
         assert_eq!(block.value, "Acme Corp");
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_shared_block_updates_are_not_lost() {
+        let shared_manager = SharedMemoryManager::new();
+        let block_id = shared_manager.create_block("findings", "Shared findings", "").await;
+
+        let writers: Vec<_> = (0..64)
+            .map(|i| {
+                let manager = shared_manager.clone();
+                tokio::spawn(async move {
+                    manager
+                        .update_block(block_id, |block| {
+                            std::thread::yield_now();
+                            block.value.push_str(&format!("finding {}\n", i));
+                        })
+                        .await
+                        .unwrap();
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.await.unwrap();
+        }
+
+        let block = shared_manager.get_block(block_id).await.unwrap();
+        let mut lines: Vec<&str> = block.value.lines().collect();
+        lines.sort();
+        lines.dedup();
+        assert_eq!(lines.len(), 64);
+    }
+
+    #[tokio::test]
+    async fn test_shared_block_compare_and_swap() {
+        let shared_manager = SharedMemoryManager::new();
+        let block_id = shared_manager.create_block("plan", "Shared plan", "v1").await;
+
+        let read = shared_manager.get_block(block_id).await.unwrap();
+        assert!(shared_manager
+            .compare_and_swap(block_id, read.updated_at, "v2".to_string())
+            .await
+            .unwrap());
+        assert!(!shared_manager
+            .compare_and_swap(block_id, read.updated_at, "v2 from a stale reader".to_string())
+            .await
+            .unwrap());
+
+        shared_manager.set_block_value(block_id, "v3".to_string()).await.unwrap();
+        let block = shared_manager.get_block(block_id).await.unwrap();
+        assert_eq!(block.value, "v3");
+        assert!(block.updated_at > read.updated_at);
+
+        let mut bounded = shared_manager.get_block(block_id).await.unwrap();
+        bounded.max_size = Some(4);
+        shared_manager.update_block(block_id, |block| *block = bounded).await.unwrap();
+        assert!(shared_manager
+            .update_block(block_id, |block| block.value = "too long".to_string())
+            .await
+            .is_err());
+        assert_eq!(shared_manager.get_block(block_id).await.unwrap().value, "v3");
+    }
+
+    #[tokio::test]
+    async fn test_compaction_keeps_recent_and_summaries() {
+        let config = MemoryConfig {
+            max_context_size: 100,
+            compaction_ratio: Some(0.5),
+            compaction_keep_recent: 2,
+            ..MemoryConfig::default()
+        };
+        let memory = AgentMemory::new(AgentId::new(), config);
+        let block = memory.add_block(MemoryBlock::new("persona", "analyst")).await.unwrap();
+        memory.attach_shared_block(MemoryBlockId::new()).await;
+        assert!(!memory.needs_compaction().await);
+
+        for i in 0..5 {
+            memory.add_message(Role::User, format!("message {}", i)).await;
+        }
+        assert!(memory.needs_compaction().await);
+
+        let client = MockLlmClient::new().with_reply("summary 1").with_reply("summary 2");
+        assert_eq!(memory.compact(&client, "cheap-model").await.unwrap(), 3);
+        let history = memory.get_recent_messages(10).await;
+        let contents: Vec<&str> = history.iter().map(|m| m.content.as_str()).collect();
+        assert_eq!(
+            contents,
+            ["Summary of earlier conversation: summary 1", "message 3", "message 4"]
+        );
+        assert!(history[0].is_summary());
+        assert_eq!(history[0].metadata["summarized_messages"], "3");
+
+        for i in 5..8 {
+            memory.add_message(Role::User, format!("message {}", i)).await;
+        }
+        assert_eq!(memory.compact(&client, "cheap-model").await.unwrap(), 3);
+        let history = memory.get_recent_messages(10).await;
+        assert_eq!(history.len(), 4);
+        assert!(history[0].is_summary() && history[1].is_summary());
+        assert_eq!(history[3].content, "message 7");
+
+        assert!(memory.get_block(block).await.is_some());
+        assert_eq!(memory.shared_blocks.read().await.len(), 1);
+
+        // The first summary was not fed back into the second
+        let transcripts = client.prompts();
+        assert!(!transcripts[1].contains("summary 1"));
+        assert!(transcripts[1].contains("message 3"));
+    }
 }
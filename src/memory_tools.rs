@@ -4,11 +4,21 @@
 //! - Edit their own memory blocks
 //! - Move blocks in/out of context
 //! - Search their memory
+//! - Recall past sessions from persistent storage
 //! - Manage the context window
+//!
+//! The `core_memory_*` tools need no setup: they edit whichever agent's
+//! memory arrives in the [`ToolContext`].
 
 use crate::error::Result;
 use crate::memory::{AgentMemory, MemoryBlockId};
+#[cfg(feature = "storage")]
+use crate::memory::MessageEntry;
+#[cfg(feature = "storage")]
+use crate::storage::MemoryStorage;
 use crate::tools::{JsonSchema, Tool, ToolContext, ToolOutput};
+#[cfg(feature = "storage")]
+use crate::types::AgentId;
 use async_trait::async_trait;
 use serde_json::{json, Value};
 use std::collections::HashMap;
@@ -363,6 +373,349 @@ impl Tool for SearchMessagesTool {
     }
 }
 
+/// Identifier of the built-in recall tool
+pub const RECALL_TOOL_ID: &str = "recall";
+
+/// Tool for recalling past sessions from persisted message history
+///
+/// Searches the storage backend rather than the in-process history, so it
+/// finds messages from earlier runs that are no longer in the context window.
+#[cfg(feature = "storage")]
+pub struct RecallTool {
+    storage: Arc<dyn MemoryStorage>,
+    agent_id: AgentId,
+}
+
+#[cfg(feature = "storage")]
+impl RecallTool {
+    /// Default number of matches returned
+    pub const DEFAULT_K: usize = 5;
+    /// Maximum number of matches returned
+    pub const MAX_K: usize = 25;
+
+    /// Recall messages stored for the given agent
+    pub fn new(storage: Arc<dyn MemoryStorage>, agent_id: AgentId) -> Self {
+        Self { storage, agent_id }
+    }
+
+    /// Find the top `k` messages for a query, newest first
+    ///
+    /// Whatever the storage's search matches for the whole query wins (every
+    /// word with full-text search, the exact phrase otherwise); if nothing
+    /// matches, messages are ranked by how many of the query's words they contain.
+    pub async fn recall(&self, query: &str, k: usize) -> Result<Vec<MessageEntry>> {
+        let mut matches = self.storage.search_messages(self.agent_id, query).await?;
+        if matches.is_empty() {
+            let mut hits: HashMap<uuid::Uuid, (MessageEntry, usize)> = HashMap::new();
+            let mut terms: Vec<String> = query
+                .split(|c: char| !c.is_alphanumeric())
+                .filter(|term| term.len() >= 3)
+                .map(str::to_lowercase)
+                .collect();
+            terms.sort();
+            terms.dedup();
+
+            for term in &terms {
+                for message in self.storage.search_messages(self.agent_id, term).await? {
+                    hits.entry(message.id).or_insert((message, 0)).1 += 1;
+                }
+            }
+
+            let mut ranked: Vec<(MessageEntry, usize)> = hits.into_values().collect();
+            ranked.sort_by(|(a, a_hits), (b, b_hits)| {
+                b_hits.cmp(a_hits).then(b.timestamp.cmp(&a.timestamp))
+            });
+            matches = ranked.into_iter().map(|(message, _)| message).collect();
+        } else {
+            matches.sort_by_key(|m| std::cmp::Reverse(m.timestamp));
+        }
+
+        matches.truncate(k);
+        Ok(matches)
+    }
+}
+
+#[cfg(feature = "storage")]
+#[async_trait]
+impl Tool for RecallTool {
+    fn id(&self) -> &str {
+        RECALL_TOOL_ID
+    }
+
+    fn description(&self) -> &str {
+        "Recall messages from past sessions stored in long-term memory. Use this to \
+         answer questions about earlier conversations or decisions that are no longer \
+         in your context."
+    }
+
+    fn name(&self) -> &str {
+        self.id()
+    }
+
+    fn input_schema(&self) -> JsonSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "query".to_string(),
+            json!({
+                "type": "string",
+                "description": "Words or phrase to search for in past messages"
+            }),
+        );
+        properties.insert(
+            "k".to_string(),
+            json!({
+                "type": "integer",
+                "description": format!(
+                    "Number of matches to return (default {}, max {})",
+                    Self::DEFAULT_K,
+                    Self::MAX_K
+                )
+            }),
+        );
+
+        JsonSchema::object(properties).with_required(vec!["query".to_string()])
+    }
+
+    async fn execute(&self, params: Value, _ctx: &ToolContext) -> Result<ToolOutput> {
+        let query = params["query"]
+            .as_str()
+            .ok_or_else(|| crate::error::Error::tool_execution(RECALL_TOOL_ID, "Missing query"))?;
+        let k = params["k"]
+            .as_u64()
+            .map(|k| (k as usize).clamp(1, Self::MAX_K))
+            .unwrap_or(Self::DEFAULT_K);
+
+        let results = self.recall(query, k).await?;
+        if results.is_empty() {
+            return Ok(ToolOutput::success(format!(
+                "No past messages found matching '{}'",
+                query
+            )));
+        }
+
+        // The model only sees `content`, so the matches go there
+        let mut content = format!("Found {} past messages matching '{}':", results.len(), query);
+        for message in &results {
+            content.push_str(&format!(
+                "\n[{}] {}: {}",
+                message.timestamp.format("%Y-%m-%d %H:%M"),
+                message.role,
+                message.content
+            ));
+        }
+
+        let messages: Vec<Value> = results
+            .iter()
+            .map(|m| {
+                json!({
+                    "id": m.id.to_string(),
+                    "timestamp": m.timestamp.to_rfc3339(),
+                    "role": m.role,
+                    "content": m.content,
+                })
+            })
+            .collect();
+
+        Ok(ToolOutput::success_with_data(
+            content,
+            json!({
+                "query": query,
+                "results": messages,
+                "count": results.len()
+            }),
+        ))
+    }
+}
+/// ID of [`CoreMemoryAppendTool`]
+pub const CORE_MEMORY_APPEND_TOOL_ID: &str = "core_memory_append";
+
+/// ID of [`CoreMemoryReplaceTool`]
+pub const CORE_MEMORY_REPLACE_TOOL_ID: &str = "core_memory_replace";
+
+/// Memory of the calling agent, or why the model may not edit it
+fn editable_memory(ctx: &ToolContext) -> std::result::Result<&AgentMemory, ToolOutput> {
+    match ctx.memory() {
+        None => Err(ToolOutput::failure("This agent has no memory to edit")),
+        Some(memory) if !memory.config.enable_agentic_control => {
+            Err(ToolOutput::failure("Memory editing is disabled for this agent"))
+        }
+        Some(memory) => Ok(memory),
+    }
+}
+
+/// Read a required string parameter
+fn string_param<'a>(params: &'a Value, name: &str) -> std::result::Result<&'a str, ToolOutput> {
+    params[name]
+        .as_str()
+        .ok_or_else(|| ToolOutput::failure(format!("Missing '{}'", name)))
+}
+
+/// Apply an edit to the block with `label` and show the model the result
+async fn edit_core_memory(
+    memory: &AgentMemory,
+    label: &str,
+    update: impl FnOnce(&str) -> Result<String>,
+) -> ToolOutput {
+    match memory.edit_block_by_label(label, update).await {
+        Ok(block) => ToolOutput::success_with_data(
+            format!("Memory block '{}' now reads:\n{}", block.label, block.value),
+            json!({
+                "label": block.label,
+                "value": block.value,
+                "size": block.size(),
+                "max_size": block.max_size,
+            }),
+        ),
+        Err(e) => ToolOutput::failure(e.to_string()),
+    }
+}
+
+/// Tool for appending to one of the calling agent's memory blocks by label
+#[derive(Debug, Default)]
+pub struct CoreMemoryAppendTool;
+
+#[async_trait]
+impl Tool for CoreMemoryAppendTool {
+    fn id(&self) -> &str {
+        CORE_MEMORY_APPEND_TOOL_ID
+    }
+
+    fn description(&self) -> &str {
+        "Append a line to one of your memory blocks (e.g. persona, scratchpad). \
+         Returns the block's new contents."
+    }
+
+    fn name(&self) -> &str {
+        self.id()
+    }
+
+    fn input_schema(&self) -> JsonSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "label".to_string(),
+            json!({
+                "type": "string",
+                "description": "Label of the memory block to append to"
+            }),
+        );
+        properties.insert(
+            "content".to_string(),
+            json!({
+                "type": "string",
+                "description": "Text to add on a new line at the end of the block"
+            }),
+        );
+
+        JsonSchema::object(properties).with_required(vec!["label".to_string(), "content".to_string()])
+    }
+
+    async fn execute(&self, params: Value, ctx: &ToolContext) -> Result<ToolOutput> {
+        let memory = match editable_memory(ctx) {
+            Ok(memory) => memory,
+            Err(failure) => return Ok(failure),
+        };
+        let (label, content) = match (string_param(&params, "label"), string_param(&params, "content")) {
+            (Ok(label), Ok(content)) => (label, content),
+            (Err(failure), _) | (_, Err(failure)) => return Ok(failure),
+        };
+
+        Ok(edit_core_memory(memory, label, |value| {
+            Ok(if value.is_empty() {
+                content.to_string()
+            } else {
+                format!("{}\n{}", value, content)
+            })
+        })
+        .await)
+    }
+}
+
+/// Tool for replacing text in one of the calling agent's memory blocks by label
+#[derive(Debug, Default)]
+pub struct CoreMemoryReplaceTool;
+
+#[async_trait]
+impl Tool for CoreMemoryReplaceTool {
+    fn id(&self) -> &str {
+        CORE_MEMORY_REPLACE_TOOL_ID
+    }
+
+    fn description(&self) -> &str {
+        "Replace exact text in one of your memory blocks. Use an empty 'new' to delete it. \
+         Returns the block's new contents."
+    }
+
+    fn name(&self) -> &str {
+        self.id()
+    }
+
+    fn input_schema(&self) -> JsonSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "label".to_string(),
+            json!({
+                "type": "string",
+                "description": "Label of the memory block to edit"
+            }),
+        );
+        properties.insert(
+            "old".to_string(),
+            json!({
+                "type": "string",
+                "description": "Exact text currently in the block"
+            }),
+        );
+        properties.insert(
+            "new".to_string(),
+            json!({
+                "type": "string",
+                "description": "Text to put in its place"
+            }),
+        );
+
+        JsonSchema::object(properties).with_required(vec![
+            "label".to_string(),
+            "old".to_string(),
+            "new".to_string(),
+        ])
+    }
+
+    async fn execute(&self, params: Value, ctx: &ToolContext) -> Result<ToolOutput> {
+        let memory = match editable_memory(ctx) {
+            Ok(memory) => memory,
+            Err(failure) => return Ok(failure),
+        };
+        let (label, old, new) = match (
+            string_param(&params, "label"),
+            string_param(&params, "old"),
+            string_param(&params, "new"),
+        ) {
+            (Ok(label), Ok(old), Ok(new)) => (label, old, new),
+            (Err(failure), _, _) | (_, Err(failure), _) | (_, _, Err(failure)) => return Ok(failure),
+        };
+        if old.is_empty() {
+            return Ok(ToolOutput::failure("'old' must not be empty; use core_memory_append to add text"));
+        }
+
+        Ok(edit_core_memory(memory, label, |value| {
+            if value.contains(old) {
+                Ok(value.replacen(old, new, 1))
+            } else {
+                Err(crate::error::Error::InvalidInput(format!(
+                    "'{}' does not appear in memory block '{}'",
+                    old, label
+                )))
+            }
+        })
+        .await)
+    }
+}
+
+/// Create the tools that let a model edit its own memory blocks by label
+pub fn core_memory_tools() -> Vec<Arc<dyn Tool>> {
+    vec![Arc::new(CoreMemoryAppendTool), Arc::new(CoreMemoryReplaceTool)]
+}
+
 /// Create all standard memory tools for an agent
 pub fn create_memory_tools(memory: Arc<AgentMemory>) -> Vec<Arc<dyn Tool>> {
     vec![
@@ -373,3 +726,55 @@ pub fn create_memory_tools(memory: Arc<AgentMemory>) -> Vec<Arc<dyn Tool>> {
         Arc::new(SearchMessagesTool::new(memory)),
     ]
 }
+
+#[cfg(test)]
+#[cfg(feature = "storage")]
+mod tests {
+    use super::*;
+    use crate::storage::SqliteStorage;
+    use chrono::{Duration, Utc};
+
+    fn entry(content: &str, days_ago: i64) -> MessageEntry {
+        MessageEntry {
+            id: uuid::Uuid::new_v4(),
+            timestamp: Utc::now() - Duration::days(days_ago),
+            role: crate::openrouter::Role::User,
+            content: content.to_string(),
+            tool_calls: None,
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_recall_ranks_matches() {
+        let storage = Arc::new(SqliteStorage::new("sqlite::memory:").await.unwrap());
+        let agent_id = AgentId::new();
+        for message in [
+            entry("We decided to rotate the API keys monthly", 7),
+            entry("Keys for staging were rotated", 3),
+            entry("Lunch is at noon", 1),
+        ] {
+            storage.save_message(agent_id, &message).await.unwrap();
+        }
+        storage
+            .save_message(AgentId::new(), &entry("rotate keys elsewhere", 0))
+            .await
+            .unwrap();
+
+        let tool = RecallTool::new(storage, agent_id);
+        let phrase = tool.recall("rotate the API keys", 5).await.unwrap();
+        assert_eq!(phrase.len(), 1);
+
+        // No exact phrase: rank by matching words, then recency
+        let words = tool.recall("what did we decide about rotating keys", 5).await.unwrap();
+        assert_eq!(words.len(), 2);
+        assert!(words[0].content.starts_with("We decided"));
+
+        let output = tool
+            .execute(json!({"query": "keys", "k": 1}), &ToolContext::new(agent_id))
+            .await
+            .unwrap();
+        assert!(output.content.contains("Keys for staging"));
+        assert_eq!(output.data.unwrap()["count"], 1);
+    }
+}
@@ -0,0 +1,500 @@
+//! Operational metrics hooks
+//!
+//! Agents, background runs, and orchestrators report what they do through the
+//! [`Metrics`] trait. The default sink is [`NoopMetrics`]; enable the
+//! `prometheus` feature for [`PrometheusMetrics`], which keeps an in-process
+//! registry that can be rendered in the Prometheus text exposition format
+//! and served for scraping with [`spawn_prometheus_exporter`].
+
+use crate::types::TokenUsage;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Sink for operational metrics
+///
+/// Every method has a no-op default so implementations only need to
+/// override what they care about.
+pub trait Metrics: Send + Sync {
+    /// An LLM completion request finished
+    fn record_llm_request(&self, _model: &str, _latency: Duration, _success: bool) {}
+
+    /// Tokens consumed by an LLM completion
+    fn record_tokens(&self, _model: &str, _usage: &TokenUsage) {}
+
+    /// A tool call finished
+    fn record_tool_call(&self, _tool_id: &str, _latency: Duration, _success: bool) {}
+
+    /// A guardrail blocked input or output
+    fn record_guardrail_block(&self, _guardrail_id: &str) {}
+
+    /// Control was handed from one agent to another
+    fn record_handoff(&self, _from: &str, _to: &str) {}
+
+    /// An agent finished a ReAct loop
+    fn record_agent_run(&self, _agent: &str, _loops: usize, _latency: Duration, _success: bool) {}
+
+    /// A background run reached a terminal state
+    fn record_background_run(&self, _agent: &str, _total_events: usize, _success: bool) {}
+
+    /// An orchestrator pattern finished
+    fn record_orchestration(
+        &self,
+        _pattern: &str,
+        _agent_count: usize,
+        _handoff_count: usize,
+        _latency: Duration,
+        _success: bool,
+    ) {
+    }
+}
+
+/// Metrics sink that discards everything
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopMetrics;
+
+impl Metrics for NoopMetrics {}
+
+/// Shared no-op metrics sink
+pub fn noop() -> Arc<dyn Metrics> {
+    Arc::new(NoopMetrics)
+}
+
+#[cfg(feature = "prometheus")]
+pub use prometheus::{spawn_prometheus_exporter, PrometheusMetrics};
+
+#[cfg(feature = "prometheus")]
+mod prometheus {
+    use super::Metrics;
+    use crate::error::Result;
+    use crate::types::TokenUsage;
+    use parking_lot::Mutex;
+    use std::collections::BTreeMap;
+    use std::fmt::Write;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use std::time::Duration;
+    use bytes::Bytes;
+    use http_body_util::Full;
+    use hyper::body::Incoming;
+    use hyper::header::CONTENT_TYPE;
+    use hyper::server::conn::http1;
+    use hyper::service::service_fn;
+    use hyper::{Method, Request, Response, StatusCode};
+    use hyper_util::rt::{TokioIo, TokioTimer};
+    use tokio::net::{TcpListener, ToSocketAddrs};
+    use tokio::task::JoinHandle;
+
+    /// Default latency buckets in seconds
+    const DEFAULT_BUCKETS: &[f64] = &[0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0];
+
+    type Labels = Vec<(&'static str, String)>;
+
+    struct Family {
+        help: &'static str,
+        kind: &'static str,
+        series: BTreeMap<Labels, Series>,
+    }
+
+    enum Series {
+        Counter(f64),
+        Histogram {
+            buckets: Vec<u64>,
+            sum: f64,
+            count: u64,
+        },
+    }
+
+    /// In-process Prometheus registry
+    ///
+    /// Call [`PrometheusMetrics::render`] from your `/metrics` handler.
+    pub struct PrometheusMetrics {
+        namespace: String,
+        buckets: Vec<f64>,
+        families: Mutex<BTreeMap<&'static str, Family>>,
+    }
+
+    impl PrometheusMetrics {
+        /// Create a registry with the `spai` namespace
+        pub fn new() -> Self {
+            Self::with_namespace("spai")
+        }
+
+        /// Create a registry with a custom metric name prefix
+        pub fn with_namespace(namespace: impl Into<String>) -> Self {
+            Self {
+                namespace: namespace.into(),
+                buckets: DEFAULT_BUCKETS.to_vec(),
+                families: Mutex::new(BTreeMap::new()),
+            }
+        }
+
+        /// Override the latency histogram buckets (seconds, ascending)
+        pub fn with_buckets(mut self, buckets: Vec<f64>) -> Self {
+            self.buckets = buckets;
+            self
+        }
+
+        fn inc(&self, name: &'static str, help: &'static str, labels: Labels, by: f64) {
+            let mut families = self.families.lock();
+            let family = families.entry(name).or_insert_with(|| Family {
+                help,
+                kind: "counter",
+                series: BTreeMap::new(),
+            });
+            if let Series::Counter(value) =
+                family.series.entry(labels).or_insert(Series::Counter(0.0))
+            {
+                *value += by;
+            }
+        }
+
+        fn observe(&self, name: &'static str, help: &'static str, labels: Labels, value: f64) {
+            let mut families = self.families.lock();
+            let family = families.entry(name).or_insert_with(|| Family {
+                help,
+                kind: "histogram",
+                series: BTreeMap::new(),
+            });
+            let series = family.series.entry(labels).or_insert_with(|| Series::Histogram {
+                buckets: vec![0; self.buckets.len()],
+                sum: 0.0,
+                count: 0,
+            });
+            if let Series::Histogram { buckets, sum, count } = series {
+                for (slot, bound) in buckets.iter_mut().zip(&self.buckets) {
+                    if value <= *bound {
+                        *slot += 1;
+                    }
+                }
+                *sum += value;
+                *count += 1;
+            }
+        }
+
+        /// Current value of a counter, mainly for tests
+        pub fn counter_value(&self, name: &str, labels: &[(&str, &str)]) -> Option<f64> {
+            let families = self.families.lock();
+            let family = families.get(name)?;
+            family.series.iter().find_map(|(series_labels, series)| {
+                let matches = series_labels.len() == labels.len()
+                    && series_labels
+                        .iter()
+                        .zip(labels)
+                        .all(|((k1, v1), (k2, v2))| k1 == k2 && v1 == v2);
+                match series {
+                    Series::Counter(value) if matches => Some(*value),
+                    _ => None,
+                }
+            })
+        }
+
+        /// Render all metrics in the Prometheus text exposition format
+        pub fn render(&self) -> String {
+            let families = self.families.lock();
+            let mut out = String::new();
+
+            for (name, family) in families.iter() {
+                let full = format!("{}_{}", self.namespace, name);
+                let _ = writeln!(out, "# HELP {} {}", full, family.help);
+                let _ = writeln!(out, "# TYPE {} {}", full, family.kind);
+
+                for (labels, series) in &family.series {
+                    match series {
+                        Series::Counter(value) => {
+                            let _ = writeln!(out, "{}{} {}", full, format_labels(labels, None), value);
+                        }
+                        Series::Histogram { buckets, sum, count } => {
+                            for (bound, hits) in self.buckets.iter().zip(buckets) {
+                                let _ = writeln!(
+                                    out,
+                                    "{}_bucket{} {}",
+                                    full,
+                                    format_labels(labels, Some(&bound.to_string())),
+                                    hits
+                                );
+                            }
+                            let _ = writeln!(
+                                out,
+                                "{}_bucket{} {}",
+                                full,
+                                format_labels(labels, Some("+Inf")),
+                                count
+                            );
+                            let _ = writeln!(out, "{}_sum{} {}", full, format_labels(labels, None), sum);
+                            let _ = writeln!(out, "{}_count{} {}", full, format_labels(labels, None), count);
+                        }
+                    }
+                }
+            }
+
+            out
+        }
+    }
+
+    impl Default for PrometheusMetrics {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl std::fmt::Debug for PrometheusMetrics {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("PrometheusMetrics")
+                .field("namespace", &self.namespace)
+                .field("families", &self.families.lock().len())
+                .finish()
+        }
+    }
+
+    /// Largest request head the exporter reads before answering
+    const MAX_REQUEST_BYTES: usize = 8 * 1024;
+
+    /// How long a client may take to send its request head
+    const HEADER_READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Longest a scrape connection may stay open
+    const CONNECTION_TIMEOUT: Duration = Duration::from_secs(30);
+
+    /// Serve `metrics` at `GET /metrics` on `addr` for Prometheus to scrape
+    ///
+    /// Returns the bound address (useful with port 0) and the server task;
+    /// abort the task to stop serving.
+    pub async fn spawn_prometheus_exporter(
+        addr: impl ToSocketAddrs,
+        metrics: Arc<PrometheusMetrics>,
+    ) -> Result<(SocketAddr, JoinHandle<()>)> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let server = tokio::spawn(async move {
+            loop {
+                match listener.accept().await {
+                    Ok((stream, _)) => {
+                        let metrics = metrics.clone();
+                        let service = service_fn(move |request| {
+                            let metrics = metrics.clone();
+                            async move { Ok::<_, std::convert::Infallible>(scrape(&request, &metrics)) }
+                        });
+                        let connection = http1::Builder::new()
+                            .timer(TokioTimer::new())
+                            .header_read_timeout(HEADER_READ_TIMEOUT)
+                            .max_buf_size(MAX_REQUEST_BYTES)
+                            .keep_alive(false)
+                            .serve_connection(TokioIo::new(stream), service);
+                        tokio::spawn(async move {
+                            match tokio::time::timeout(CONNECTION_TIMEOUT, connection).await {
+                                Ok(Ok(())) => {}
+                                Ok(Err(e)) => tracing::debug!("Metrics scrape failed: {}", e),
+                                Err(_) => tracing::debug!("Metrics scrape timed out"),
+                            }
+                        });
+                    }
+                    Err(e) => tracing::warn!("Metrics exporter failed to accept a connection: {}", e),
+                }
+            }
+        });
+        Ok((local_addr, server))
+    }
+
+    /// Answer `GET /metrics` with the rendered registry, anything else with 404
+    fn scrape(request: &Request<Incoming>, metrics: &PrometheusMetrics) -> Response<Full<Bytes>> {
+        let (status, content_type, body) = match (request.method(), request.uri().path()) {
+            (&Method::GET, "/metrics") => {
+                (StatusCode::OK, "text/plain; version=0.0.4; charset=utf-8", metrics.render())
+            }
+            _ => (StatusCode::NOT_FOUND, "text/plain; charset=utf-8", "not found\n".to_string()),
+        };
+        let mut response = Response::new(Full::new(Bytes::from(body)));
+        *response.status_mut() = status;
+        response
+            .headers_mut()
+            .insert(CONTENT_TYPE, hyper::header::HeaderValue::from_static(content_type));
+        response
+    }
+
+    fn format_labels(labels: &Labels, le: Option<&str>) -> String {
+        let mut parts: Vec<String> = labels
+            .iter()
+            .map(|(k, v)| format!("{}=\"{}\"", k, escape(v)))
+            .collect();
+        if let Some(le) = le {
+            parts.push(format!("le=\"{}\"", le));
+        }
+        if parts.is_empty() {
+            String::new()
+        } else {
+            format!("{{{}}}", parts.join(","))
+        }
+    }
+
+    fn escape(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    }
+
+    fn status(success: bool) -> String {
+        if success { "ok" } else { "error" }.to_string()
+    }
+
+    impl Metrics for PrometheusMetrics {
+        fn record_llm_request(&self, model: &str, latency: Duration, success: bool) {
+            let labels = vec![("model", model.to_string()), ("status", status(success))];
+            self.inc("llm_requests_total", "LLM completion requests", labels, 1.0);
+            self.observe(
+                "llm_request_duration_seconds",
+                "LLM completion latency",
+                vec![("model", model.to_string())],
+                latency.as_secs_f64(),
+            );
+        }
+
+        fn record_tokens(&self, model: &str, usage: &TokenUsage) {
+            let help = "Tokens consumed by LLM completions";
+            self.inc(
+                "llm_tokens_total",
+                help,
+                vec![("model", model.to_string()), ("direction", "in".to_string())],
+                usage.prompt_tokens as f64,
+            );
+            self.inc(
+                "llm_tokens_total",
+                help,
+                vec![("model", model.to_string()), ("direction", "out".to_string())],
+                usage.completion_tokens as f64,
+            );
+        }
+
+        fn record_tool_call(&self, tool_id: &str, latency: Duration, success: bool) {
+            let labels = vec![("tool", tool_id.to_string()), ("status", status(success))];
+            self.inc("tool_calls_total", "Tool invocations", labels, 1.0);
+            self.observe(
+                "tool_call_duration_seconds",
+                "Tool execution latency",
+                vec![("tool", tool_id.to_string())],
+                latency.as_secs_f64(),
+            );
+        }
+
+        fn record_guardrail_block(&self, guardrail_id: &str) {
+            let labels = vec![("guardrail", guardrail_id.to_string())];
+            self.inc("guardrail_blocks_total", "Guardrail violations", labels, 1.0);
+        }
+
+        fn record_handoff(&self, from: &str, to: &str) {
+            let labels = vec![("from", from.to_string()), ("to", to.to_string())];
+            self.inc("handoffs_total", "Agent handoffs", labels, 1.0);
+        }
+
+        fn record_agent_run(&self, agent: &str, loops: usize, latency: Duration, success: bool) {
+            let labels = vec![("agent", agent.to_string()), ("status", status(success))];
+            self.inc("agent_runs_total", "Completed ReAct loops", labels, 1.0);
+            self.inc(
+                "agent_iterations_total",
+                "ReAct iterations executed",
+                vec![("agent", agent.to_string())],
+                loops as f64,
+            );
+            self.observe(
+                "agent_run_duration_seconds",
+                "ReAct loop latency",
+                vec![("agent", agent.to_string())],
+                latency.as_secs_f64(),
+            );
+        }
+
+        fn record_background_run(&self, agent: &str, total_events: usize, success: bool) {
+            let labels = vec![("agent", agent.to_string()), ("status", status(success))];
+            self.inc("background_runs_total", "Finished background runs", labels, 1.0);
+            self.inc(
+                "background_run_events_total",
+                "Events emitted by background runs",
+                vec![("agent", agent.to_string())],
+                total_events as f64,
+            );
+        }
+
+        fn record_orchestration(
+            &self,
+            pattern: &str,
+            agent_count: usize,
+            handoff_count: usize,
+            latency: Duration,
+            success: bool,
+        ) {
+            let labels = vec![("pattern", pattern.to_string()), ("status", status(success))];
+            self.inc("orchestrations_total", "Orchestrator executions", labels, 1.0);
+            self.inc(
+                "orchestration_agents_total",
+                "Agents participating in orchestrator executions",
+                vec![("pattern", pattern.to_string())],
+                agent_count as f64,
+            );
+            self.inc(
+                "orchestration_handoffs_total",
+                "Handoffs performed inside orchestrator executions",
+                vec![("pattern", pattern.to_string())],
+                handoff_count as f64,
+            );
+            self.observe(
+                "orchestration_duration_seconds",
+                "Orchestrator execution latency",
+                vec![("pattern", pattern.to_string())],
+                latency.as_secs_f64(),
+            );
+        }
+    }
+}
+
+#[cfg(all(test, feature = "prometheus"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prometheus_counters_and_render() {
+        let metrics = PrometheusMetrics::new();
+        metrics.record_llm_request("gpt", Duration::from_millis(120), true);
+        metrics.record_llm_request("gpt", Duration::from_millis(80), true);
+        metrics.record_tokens("gpt", &TokenUsage::new(10, 5));
+        metrics.record_guardrail_block("pii");
+
+        assert_eq!(
+            metrics.counter_value("llm_requests_total", &[("model", "gpt"), ("status", "ok")]),
+            Some(2.0)
+        );
+        assert_eq!(
+            metrics.counter_value("llm_tokens_total", &[("model", "gpt"), ("direction", "in")]),
+            Some(10.0)
+        );
+
+        let text = metrics.render();
+        assert!(text.contains("# TYPE spai_llm_request_duration_seconds histogram"));
+        assert!(text.contains("spai_llm_request_duration_seconds_bucket{model=\"gpt\",le=\"0.1\"} 1"));
+        assert!(text.contains("spai_llm_request_duration_seconds_count{model=\"gpt\"} 2"));
+        assert!(text.contains("spai_guardrail_blocks_total{guardrail=\"pii\"} 1"));
+    }
+
+    #[tokio::test]
+    async fn test_prometheus_exporter_serves_metrics() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        async fn get(addr: std::net::SocketAddr, path: &str) -> String {
+            let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+            let request = format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", path);
+            stream.write_all(request.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        }
+
+        let metrics = Arc::new(PrometheusMetrics::new());
+        metrics.record_tool_call("lynis", Duration::from_millis(300), true);
+        let (addr, server) = spawn_prometheus_exporter("127.0.0.1:0", metrics.clone()).await.unwrap();
+
+        let scrape = get(addr, "/metrics").await;
+        assert!(scrape.starts_with("HTTP/1.1 200 OK"));
+        assert!(scrape.contains("spai_tool_calls_total{tool=\"lynis\",status=\"ok\"} 1"));
+        assert!(get(addr, "/").await.starts_with("HTTP/1.1 404"));
+        server.abort();
+    }
+}
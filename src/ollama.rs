@@ -0,0 +1,590 @@
+//! Ollama client for running agents against locally pulled models
+//!
+//! Talks to Ollama's native `/api/chat` endpoint, translating
+//! [`CompletionRequest`]s (including tool definitions, tool calls and inline
+//! images) to Ollama's format and its replies back into
+//! [`CompletionResponse`]s, so agents and orchestrators run unchanged.
+//!
+//! ```rust,ignore
+//! // ollama pull llama3.1
+//! let client = OllamaClient::new(OllamaConfig::new("http://localhost:11434"))?;
+//! let agent = Agent::builder()
+//!     .model("llama3.1")
+//!     .client(Arc::new(client))
+//!     .build()?;
+//! ```
+
+use crate::error::{Error, Result};
+use crate::llm_client::LlmClient;
+use crate::openrouter::{
+    Choice, CompletionRequest, CompletionResponse, CompletionStream, ContentPart, Delta, FunctionCall, Message,
+    ResponseFormat, Role, StreamChoice, StreamChunk, ToolCall, Usage,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::stream::{Stream, StreamExt};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+/// Ollama client configuration
+#[derive(Debug, Clone)]
+pub struct OllamaConfig {
+    /// Base URL of the Ollama server (e.g., "http://localhost:11434")
+    pub base_url: String,
+    /// Request timeout
+    pub timeout: Duration,
+    /// How long Ollama keeps the model loaded after a request (e.g. "5m")
+    pub keep_alive: Option<String>,
+}
+
+impl OllamaConfig {
+    /// Create a new Ollama configuration
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            timeout: Duration::from_secs(300), // local models can be slow to load
+            keep_alive: None,
+        }
+    }
+
+    /// Create configuration from `OLLAMA_HOST`, defaulting to `http://localhost:11434`
+    pub fn from_env() -> Self {
+        Self::from_env_with(|key| std::env::var(key).ok())
+    }
+
+    /// Like [`from_env`](Self::from_env), reading variables through `var`
+    pub fn from_env_with(var: impl Fn(&str) -> Option<String>) -> Self {
+        let host = var("OLLAMA_HOST").unwrap_or_else(|| "localhost:11434".to_string());
+        // OLLAMA_HOST is usually given without a scheme, as the Ollama CLI accepts it
+        if host.contains("://") {
+            Self::new(host)
+        } else {
+            Self::new(format!("http://{}", host))
+        }
+    }
+
+    /// Set the timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set how long the model stays loaded after each request
+    pub fn with_keep_alive(mut self, keep_alive: impl Into<String>) -> Self {
+        self.keep_alive = Some(keep_alive.into());
+        self
+    }
+}
+
+impl Default for OllamaConfig {
+    fn default() -> Self {
+        Self::new("http://localhost:11434")
+    }
+}
+
+/// Ollama client for local model inference
+pub struct OllamaClient {
+    /// HTTP client
+    client: Client,
+    /// Configuration
+    config: OllamaConfig,
+}
+
+impl OllamaClient {
+    /// Create a new Ollama client from `OLLAMA_HOST`
+    pub fn from_env() -> Result<Self> {
+        Self::new(OllamaConfig::from_env())
+    }
+
+    /// Create a new Ollama client with the given configuration
+    pub fn new(config: OllamaConfig) -> Result<Self> {
+        let client = Client::builder().timeout(config.timeout).build()?;
+        Ok(Self { client, config })
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &OllamaConfig {
+        &self.config
+    }
+
+    /// Models pulled on the server
+    pub async fn list_models(&self) -> Result<Vec<OllamaModel>> {
+        let url = format!("{}/api/tags", self.config.base_url);
+        let response = self.client.get(&url).send().await?;
+        if !response.status().is_success() {
+            return Err(Error::config(format!("Failed to list Ollama models: {}", response.status())));
+        }
+        Ok(response.json::<TagsResponse>().await?.models)
+    }
+
+    /// Send a chat request, returning the raw response once the status is checked
+    async fn post_chat(&self, request: &CompletionRequest, stream: bool) -> Result<reqwest::Response> {
+        let url = format!("{}/api/chat", self.config.base_url);
+        let body = chat_request(request, stream, self.config.keep_alive.as_deref())?;
+        let response = self.client.post(&url).json(&body).send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(Error::openrouter(format!(
+                "Ollama request failed with status {}: {}",
+                status, error_text
+            )));
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl LlmClient for OllamaClient {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        let response = self.post_chat(&request, false).await?;
+        let chat: ChatResponse = response.json().await?;
+        Ok(chat.into_completion())
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        let response = self.post_chat(&request, true).await?;
+        Ok(CompletionStream::new(ndjson_to_sse(response.bytes_stream())))
+    }
+
+    fn client_type(&self) -> &str {
+        "ollama"
+    }
+
+    fn endpoint(&self) -> &str {
+        &self.config.base_url
+    }
+
+    async fn model_ids(&self) -> Result<Vec<String>> {
+        Ok(self.list_models().await?.into_iter().map(|m| m.name).collect())
+    }
+}
+
+/// A model pulled on an Ollama server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaModel {
+    /// Model name with tag (e.g. "llama3.1:latest")
+    pub name: String,
+    /// Size on disk in bytes
+    #[serde(default)]
+    pub size: u64,
+}
+
+/// Response from `/api/tags`
+#[derive(Debug, Deserialize)]
+struct TagsResponse {
+    models: Vec<OllamaModel>,
+}
+
+/// One reply from `/api/chat`, or one line of a streamed reply
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    model: String,
+    #[serde(default)]
+    created_at: String,
+    #[serde(default)]
+    message: Option<ChatMessage>,
+    #[serde(default)]
+    done: bool,
+    #[serde(default)]
+    done_reason: Option<String>,
+    #[serde(default)]
+    prompt_eval_count: u64,
+    #[serde(default)]
+    eval_count: u64,
+}
+
+/// Assistant message in an Ollama reply
+#[derive(Debug, Default, Deserialize)]
+struct ChatMessage {
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    thinking: Option<String>,
+    #[serde(default)]
+    tool_calls: Vec<ChatToolCall>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatToolCall {
+    function: ChatFunction,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatFunction {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+impl ChatResponse {
+    fn usage(&self) -> Usage {
+        Usage {
+            prompt_tokens: self.prompt_eval_count,
+            completion_tokens: self.eval_count,
+            total_tokens: self.prompt_eval_count + self.eval_count,
+        }
+    }
+
+    /// Ollama does not report tool use as a finish reason; infer it like OpenAI does
+    fn finish_reason(&self, has_tool_calls: bool) -> Option<String> {
+        if has_tool_calls {
+            Some("tool_calls".to_string())
+        } else {
+            self.done_reason.clone()
+        }
+    }
+
+    fn into_completion(mut self) -> CompletionResponse {
+        let usage = self.usage();
+        let message = self.message.take().unwrap_or_default();
+        let tool_calls = tool_calls(message.tool_calls);
+        let finish_reason = self.finish_reason(tool_calls.is_some());
+
+        let mut reply = Message::assistant(message.content);
+        reply.tool_calls = tool_calls;
+        reply.reasoning = message.thinking.filter(|t| !t.is_empty());
+        CompletionResponse {
+            id: format!("ollama-{}", self.created_at),
+            model: self.model,
+            choices: vec![Choice {
+                index: 0,
+                message: reply,
+                finish_reason,
+            }],
+            usage,
+            retries: 0,
+            rate_limit: None,
+        }
+    }
+
+    fn into_chunk(mut self) -> StreamChunk {
+        let usage = self.done.then(|| self.usage());
+        let message = self.message.take().unwrap_or_default();
+        let tool_calls = tool_calls(message.tool_calls);
+        let finish_reason = if self.done {
+            self.finish_reason(tool_calls.is_some())
+        } else {
+            None
+        };
+
+        StreamChunk {
+            id: format!("ollama-{}", self.created_at),
+            model: self.model,
+            choices: vec![StreamChoice {
+                index: 0,
+                delta: Delta {
+                    role: None,
+                    content: Some(message.content).filter(|c| !c.is_empty()),
+                    tool_calls,
+                    reasoning: message.thinking.filter(|t| !t.is_empty()),
+                },
+                finish_reason,
+            }],
+            usage,
+        }
+    }
+}
+
+/// Convert Ollama tool calls, which carry no IDs and take arguments as an object
+fn tool_calls(calls: Vec<ChatToolCall>) -> Option<Vec<ToolCall>> {
+    if calls.is_empty() {
+        return None;
+    }
+    Some(
+        calls
+            .into_iter()
+            .enumerate()
+            .map(|(i, call)| ToolCall {
+                id: format!("call_{}", i),
+                tool_type: "function".to_string(),
+                function: FunctionCall {
+                    name: call.function.name,
+                    arguments: match call.function.arguments {
+                        Value::String(arguments) => arguments,
+                        arguments => arguments.to_string(),
+                    },
+                },
+            })
+            .collect(),
+    )
+}
+
+/// Re-frame Ollama's one-JSON-object-per-line stream as server-sent events
+///
+/// Lines may be split across reads, so partial lines are buffered until
+/// their newline arrives.
+fn ndjson_to_sse(
+    body: impl Stream<Item = reqwest::Result<Bytes>> + Send + 'static,
+) -> impl Stream<Item = reqwest::Result<Bytes>> + Send + 'static {
+    let mut buffer = Vec::new();
+    body.map(move |bytes| {
+        buffer.extend_from_slice(&bytes?);
+        let mut events = Vec::new();
+        while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            if !line.trim().is_empty() {
+                events.extend_from_slice(sse_event(line.trim()).as_bytes());
+            }
+        }
+        Ok(Bytes::from(events))
+    })
+}
+
+/// Re-frame one streamed Ollama line as a `data:` event, ending the stream when it is done
+///
+/// Lines that are not valid replies are passed through so the stream reports the parse error.
+fn sse_event(line: &str) -> String {
+    match serde_json::from_str::<ChatResponse>(line) {
+        Ok(response) => {
+            let done = response.done;
+            let chunk = serde_json::to_string(&response.into_chunk()).unwrap_or_default();
+            if done {
+                format!("data: {}\n\ndata: [DONE]\n\n", chunk)
+            } else {
+                format!("data: {}\n\n", chunk)
+            }
+        }
+        Err(_) => format!("data: {}\n\n", line),
+    }
+}
+
+/// Build the `/api/chat` body for a request
+fn chat_request(request: &CompletionRequest, stream: bool, keep_alive: Option<&str>) -> Result<Value> {
+    let messages = request.messages.iter().map(chat_message).collect::<Result<Vec<_>>>()?;
+
+    let mut options = serde_json::Map::new();
+    if let Some(temperature) = request.temperature {
+        options.insert("temperature".to_string(), json!(temperature));
+    }
+    if let Some(max_tokens) = request.max_tokens {
+        options.insert("num_predict".to_string(), json!(max_tokens));
+    }
+    if let Some(top_p) = request.top_p {
+        options.insert("top_p".to_string(), json!(top_p));
+    }
+    if let Some(penalty) = request.frequency_penalty {
+        options.insert("frequency_penalty".to_string(), json!(penalty));
+    }
+    if let Some(penalty) = request.presence_penalty {
+        options.insert("presence_penalty".to_string(), json!(penalty));
+    }
+
+    let mut body = json!({
+        "model": request.model,
+        "messages": messages,
+        "stream": stream,
+    });
+    if !options.is_empty() {
+        body["options"] = Value::Object(options);
+    }
+    if let Some(tools) = &request.tools {
+        body["tools"] = serde_json::to_value(tools)?;
+    }
+    match &request.response_format {
+        Some(ResponseFormat::JsonObject) => body["format"] = json!("json"),
+        Some(ResponseFormat::JsonSchema { json_schema }) => body["format"] = json_schema.schema.clone(),
+        Some(ResponseFormat::Text) | None => {}
+    }
+    if let Some(include) = request.include_reasoning {
+        body["think"] = json!(include);
+    }
+    if let Some(keep_alive) = keep_alive {
+        body["keep_alive"] = json!(keep_alive);
+    }
+    // Ollama serves whatever is pulled locally: no fallbacks, and tool_choice is not supported
+    Ok(body)
+}
+
+/// Convert a message, moving inline images into Ollama's base64 `images` list
+fn chat_message(message: &Message) -> Result<Value> {
+    let mut content = message.content.clone();
+    let mut images = Vec::new();
+    for part in &message.parts {
+        match part {
+            ContentPart::Text { text } => {
+                if !content.is_empty() {
+                    content.push('\n');
+                }
+                content.push_str(text);
+            }
+            ContentPart::ImageUrl { image_url } => {
+                let data = image_url
+                    .url
+                    .strip_prefix("data:")
+                    .and_then(|url| url.split_once(";base64,"))
+                    .map(|(_, data)| data)
+                    .ok_or_else(|| {
+                        Error::InvalidInput("Ollama only accepts images inlined as base64 data: URLs".to_string())
+                    })?;
+                images.push(data.to_string());
+            }
+        }
+    }
+
+    let mut value = json!({
+        "role": message.role.as_str(),
+        "content": content,
+    });
+    if !images.is_empty() {
+        value["images"] = json!(images);
+    }
+    if let Some(calls) = &message.tool_calls {
+        let calls: Vec<Value> = calls
+            .iter()
+            .map(|call| {
+                let arguments = serde_json::from_str::<Value>(&call.function.arguments)
+                    .unwrap_or_else(|_| Value::String(call.function.arguments.clone()));
+                json!({ "function": { "name": call.function.name, "arguments": arguments } })
+            })
+            .collect();
+        value["tool_calls"] = json!(calls);
+    }
+    if let (Role::Tool, Some(name)) = (&message.role, &message.name) {
+        value["tool_name"] = json!(name);
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openrouter::{FunctionDefinition, ImageUrl, ToolDefinition};
+
+    #[test]
+    fn test_ollama_config_from_env() {
+        let host = |value: &'static str| move |key: &str| (key == "OLLAMA_HOST").then(|| value.to_string());
+        assert_eq!(OllamaConfig::from_env_with(host("127.0.0.1:11500")).base_url, "http://127.0.0.1:11500");
+        assert_eq!(OllamaConfig::from_env_with(host("https://gpu-box:11434/")).base_url, "https://gpu-box:11434");
+        assert_eq!(OllamaConfig::from_env_with(|_| None).base_url, "http://localhost:11434");
+    }
+
+    #[test]
+    fn test_chat_request_translation() {
+        let mut assistant = Message::assistant("");
+        assistant.tool_calls = Some(vec![ToolCall {
+            id: "call_0".to_string(),
+            tool_type: "function".to_string(),
+            function: FunctionCall {
+                name: "scan".to_string(),
+                arguments: r#"{"host": "10.0.0.5"}"#.to_string(),
+            },
+        }]);
+        let mut result = Message::tool("port 22 open", "call_0");
+        result.name = Some("scan".to_string());
+        let mut image = Message::user("What is this?");
+        image.parts.push(ContentPart::ImageUrl {
+            image_url: ImageUrl {
+                url: "data:image/png;base64,iVBORw0KGgo=".to_string(),
+                detail: None,
+            },
+        });
+
+        let request = CompletionRequest::new("llama3.1", vec![Message::system("Be brief."), image, assistant, result])
+            .with_temperature(0.2)
+            .with_max_tokens(128)
+            .with_response_format(ResponseFormat::JsonObject)
+            .with_tools(vec![ToolDefinition {
+                tool_type: "function".to_string(),
+                function: FunctionDefinition {
+                    name: "scan".to_string(),
+                    description: "Scan a host".to_string(),
+                    parameters: json!({"type": "object"}),
+                },
+            }]);
+        let body = chat_request(&request, false, Some("10m")).unwrap();
+
+        assert_eq!(body["model"], "llama3.1");
+        assert_eq!(body["stream"], false);
+        assert_eq!(body["options"]["num_predict"], 128);
+        assert_eq!(body["format"], "json");
+        assert_eq!(body["keep_alive"], "10m");
+        assert_eq!(body["tools"][0]["function"]["name"], "scan");
+        assert_eq!(body["messages"][1]["images"][0], "iVBORw0KGgo=");
+        assert_eq!(body["messages"][2]["tool_calls"][0]["function"]["arguments"]["host"], "10.0.0.5");
+        assert_eq!(body["messages"][3]["role"], "tool");
+        assert_eq!(body["messages"][3]["tool_name"], "scan");
+
+        let mut remote = Message::user("What is this?");
+        remote.parts.push(ContentPart::ImageUrl {
+            image_url: ImageUrl {
+                url: "https://example.com/cat.png".to_string(),
+                detail: None,
+            },
+        });
+        assert!(chat_request(&CompletionRequest::new("llama3.1", vec![remote]), false, None).is_err());
+    }
+
+    #[test]
+    fn test_chat_response_translation() {
+        let body = r#"{"model":"llama3.1","created_at":"2024-07-22T20:33:28Z","message":{"role":"assistant","content":"","tool_calls":[{"function":{"name":"scan","arguments":{"host":"10.0.0.5"}}}]},"done":true,"done_reason":"stop","prompt_eval_count":26,"eval_count":12}"#;
+        let response = serde_json::from_str::<ChatResponse>(body).unwrap().into_completion();
+
+        assert_eq!(response.model, "llama3.1");
+        assert_eq!(response.usage.total_tokens, 38);
+        let choice = &response.choices[0];
+        assert_eq!(choice.finish_reason.as_deref(), Some("tool_calls"));
+        let call = &choice.message.tool_calls.as_ref().unwrap()[0];
+        assert_eq!(call.function.name, "scan");
+        assert_eq!(call.function.arguments, r#"{"host":"10.0.0.5"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_stream_reframes_ndjson() {
+        let lines = [
+            r#"{"model":"llama3.1","created_at":"t","message":{"role":"assistant","content":"Hel"},"done":false}"#,
+            r#"{"model":"llama3.1","created_at":"t","message":{"role":"assistant","content":"lo"},"done":false}"#,
+            r#"{"model":"llama3.1","created_at":"t","message":{"role":"assistant","content":""},"done":true,"done_reason":"stop","prompt_eval_count":3,"eval_count":2}"#,
+        ];
+        let body = lines.join("\n") + "\n";
+        // Split mid-line to check lines are reassembled across reads
+        let (head, tail) = body.split_at(40);
+        let body = futures::stream::iter(vec![Ok(Bytes::from(head.to_string())), Ok(Bytes::from(tail.to_string()))]);
+        let mut stream = CompletionStream::new(ndjson_to_sse(body));
+
+        let mut text = String::new();
+        let mut usage = None;
+        while let Some(chunk) = stream.next_chunk().await {
+            let chunk = chunk.unwrap();
+            text.extend(chunk.choices[0].delta.content.clone());
+            usage = chunk.usage.or(usage);
+        }
+        assert_eq!(text, "Hello");
+        assert_eq!(usage.unwrap().total_tokens, 5);
+    }
+
+    /// Runs against a real server when one is reachable, e.g.
+    /// `OLLAMA_TEST_MODEL=llama3.2 cargo test --features ollama ollama_server`
+    #[tokio::test]
+    async fn test_ollama_server_round_trip() {
+        let Ok(model) = std::env::var("OLLAMA_TEST_MODEL") else {
+            return;
+        };
+        let client = OllamaClient::from_env().unwrap();
+        if client.list_models().await.is_err() {
+            eprintln!("skipping: no Ollama server at {}", client.endpoint());
+            return;
+        }
+
+        let request = CompletionRequest::new(&model, vec![Message::user("Reply with the single word: pong")])
+            .with_temperature(0.0)
+            .with_max_tokens(16);
+        let response = client.complete(request.clone()).await.unwrap();
+        assert!(!response.choices[0].message.content.is_empty());
+        assert!(response.usage.completion_tokens > 0);
+
+        let mut stream = client.stream(request).await.unwrap();
+        let mut text = String::new();
+        while let Some(chunk) = stream.next_chunk().await {
+            text.extend(chunk.unwrap().choices[0].delta.content.clone());
+        }
+        assert!(!text.is_empty());
+    }
+}
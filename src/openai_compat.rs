@@ -0,0 +1,270 @@
+//! Client for any OpenAI-compatible chat completions endpoint
+//!
+//! [`GenericOpenAiClient`] speaks the plain `/v1/chat/completions` schema
+//! (streaming and tool calls included) without OpenRouter's routing fields
+//! or headers, for self-hosted gateways such as LiteLLM, vLLM or an internal
+//! proxy.
+//!
+//! ```rust,ignore
+//! let client = GenericOpenAiClient::new("https://llm.internal.example/v1", std::env::var("GATEWAY_KEY")?)?;
+//! let agent = Agent::builder()
+//!     .model("gpt-4o")
+//!     .client(Arc::new(client))
+//!     .build()?;
+//! ```
+
+use crate::error::{Error, Result};
+use crate::llm_client::LlmClient;
+use crate::openrouter::{CompletionRequest, CompletionResponse, CompletionStream, RateLimitInfo};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use std::time::Duration;
+
+/// `LlmClient` for an OpenAI-compatible gateway
+pub struct GenericOpenAiClient {
+    /// HTTP client
+    client: Client,
+    /// Base URL up to and including the API version (e.g. "http://localhost:4000/v1")
+    base_url: String,
+    /// Bearer token; no `Authorization` header is sent when empty
+    api_key: String,
+    /// Extra headers sent with every request
+    headers: Vec<(String, String)>,
+}
+
+impl std::fmt::Debug for GenericOpenAiClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("GenericOpenAiClient")
+            .field("base_url", &self.base_url)
+            .field("api_key", &if self.api_key.is_empty() { "" } else { "***" })
+            .field("headers", &self.headers.iter().map(|(name, _)| name).collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl GenericOpenAiClient {
+    /// Create a client for `base_url`, authenticating with `api_key` (empty for none)
+    ///
+    /// `base_url` may be given with or without the trailing `/v1`.
+    pub fn new(base_url: impl Into<String>, api_key: impl Into<String>) -> Result<Self> {
+        let base_url = base_url.into();
+        let base_url = base_url.trim_end_matches('/');
+        let base_url = if base_url.ends_with("/v1") {
+            base_url.to_string()
+        } else {
+            format!("{}/v1", base_url)
+        };
+
+        Ok(Self {
+            client: Client::builder().timeout(Duration::from_secs(300)).build()?,
+            base_url,
+            api_key: api_key.into(),
+            headers: Vec::new(),
+        })
+    }
+
+    /// Set the request timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Result<Self> {
+        self.client = Client::builder().timeout(timeout).build()?;
+        Ok(self)
+    }
+
+    /// Send an extra header with every request (e.g. a gateway tenant ID)
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// URL of an API path under the base URL
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path)
+    }
+
+    /// Attach authentication and extra headers
+    fn authorize(&self, mut request: reqwest::RequestBuilder) -> reqwest::RequestBuilder {
+        if !self.api_key.is_empty() {
+            request = request.bearer_auth(&self.api_key);
+        }
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        request
+    }
+
+    /// Post a chat completion, returning the response once the status is checked
+    async fn post_chat(&self, mut request: CompletionRequest, stream: bool) -> Result<reqwest::Response> {
+        // OpenRouter-only fields that strict gateways reject
+        request.fallback_models.clear();
+        request.include_reasoning = None;
+        request.provider = None;
+        request.stream = stream;
+
+        let response = self
+            .authorize(self.client.post(self.url("chat/completions")))
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(Error::openrouter(format!(
+                "Gateway request to {} failed with status {}: {}",
+                self.base_url, status, error_text
+            )));
+        }
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl LlmClient for GenericOpenAiClient {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        let response = self.post_chat(request, false).await?;
+        let rate_limit = RateLimitInfo::from_headers(response.headers());
+        let mut completion: CompletionResponse = response.json().await?;
+        completion.rate_limit = rate_limit;
+        Ok(completion)
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        let response = self.post_chat(request, true).await?;
+        Ok(CompletionStream::new(response.bytes_stream()))
+    }
+
+    fn client_type(&self) -> &str {
+        "openai_compatible"
+    }
+
+    fn endpoint(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn model_ids(&self) -> Result<Vec<String>> {
+        /// `/v1/models` listing; gateways disagree on every field but `id`
+        #[derive(Deserialize)]
+        struct ModelList {
+            data: Vec<ModelEntry>,
+        }
+        #[derive(Deserialize)]
+        struct ModelEntry {
+            id: String,
+        }
+
+        let response = self.authorize(self.client.get(self.url("models"))).send().await?;
+        if !response.status().is_success() {
+            return Err(Error::config(format!("Failed to list models: {}", response.status())));
+        }
+        let list: ModelList = response.json().await?;
+        Ok(list.data.into_iter().map(|m| m.id).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openrouter::Message;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Answer one request with `body`, returning the server URL and the raw request received
+    async fn serve_once(content_type: &'static str, body: &'static str) -> (String, tokio::task::JoinHandle<String>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = socket.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let length = text[..end]
+                        .lines()
+                        .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().to_string()))
+                        .and_then(|v| v.parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if request.len() >= end + 4 + length || n == 0 {
+                        break;
+                    }
+                }
+            }
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                content_type,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            socket.shutdown().await.ok();
+            String::from_utf8_lossy(&request).into_owned()
+        });
+        (url, handle)
+    }
+
+    #[test]
+    fn test_base_url_normalized() {
+        let client = GenericOpenAiClient::new("http://localhost:4000/", "").unwrap();
+        assert_eq!(client.endpoint(), "http://localhost:4000/v1");
+        let client = GenericOpenAiClient::new("http://localhost:4000/v1", "sk-test").unwrap();
+        assert_eq!(client.url("chat/completions"), "http://localhost:4000/v1/chat/completions");
+        assert!(!format!("{:?}", client).contains("sk-test"));
+    }
+
+    #[tokio::test]
+    async fn test_complete_sends_plain_openai_request() {
+        let body = r#"{"id":"c1","model":"llama-70b","choices":[{"index":0,"message":{"role":"assistant","content":"","tool_calls":[{"id":"call_1","type":"function","function":{"name":"scan","arguments":"{\"host\":\"10.0.0.5\"}"}}]},"finish_reason":"tool_calls"}],"usage":{"prompt_tokens":5,"completion_tokens":7,"total_tokens":12}}"#;
+        let (url, request) = serve_once("application/json", body).await;
+        let client = GenericOpenAiClient::new(url, "sk-test").unwrap().with_header("X-Tenant", "red-team");
+
+        let response = client
+            .complete(
+                CompletionRequest::new("llama-70b", vec![Message::user("scan 10.0.0.5")])
+                    .with_fallback_models(vec!["other".to_string()])
+                    .with_include_reasoning(true),
+            )
+            .await
+            .unwrap();
+        let call = &response.choices[0].message.tool_calls.as_ref().unwrap()[0];
+        assert_eq!(call.function.name, "scan");
+        assert_eq!(response.usage.total_tokens, 12);
+
+        let request = request.await.unwrap();
+        assert!(request.starts_with("POST /v1/chat/completions "));
+        let lower = request.to_lowercase();
+        assert!(lower.contains("authorization: bearer sk-test"));
+        assert!(lower.contains("x-tenant: red-team"));
+        assert!(!lower.contains("x-title"));
+        let json: serde_json::Value = serde_json::from_str(&request[request.find("\r\n\r\n").unwrap() + 4..]).unwrap();
+        assert_eq!(json["stream"], false);
+        assert!(json.get("models").is_none());
+        assert!(json.get("include_reasoning").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_parses_sse() {
+        let body = "data: {\"id\":\"c1\",\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"po\"},\"finish_reason\":null}]}\n\n\
+                    data: {\"id\":\"c1\",\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"ng\"},\"finish_reason\":\"stop\"}]}\n\n\
+                    data: [DONE]\n\n";
+        let (url, request) = serve_once("text/event-stream", body).await;
+        let client = GenericOpenAiClient::new(url, "").unwrap();
+
+        let mut stream = client
+            .stream(CompletionRequest::new("m", vec![Message::user("ping")]))
+            .await
+            .unwrap();
+        let mut text = String::new();
+        while let Some(chunk) = stream.next_chunk().await {
+            text.extend(chunk.unwrap().choices[0].delta.content.clone());
+        }
+        assert_eq!(text, "pong");
+
+        let request = request.await.unwrap();
+        assert!(!request.to_lowercase().contains("authorization"));
+        assert!(request.contains("\"stream\":true"));
+    }
+}
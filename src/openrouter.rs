@@ -1,16 +1,22 @@
 //! OpenRouter API client implementation with streaming support
 
-use crate::config::OpenRouterConfig;
+use crate::config::{OpenRouterConfig, ProviderPreferences, RetryConfig};
 use crate::error::{Error, Result};
+use crate::guardrails::SecretRedactor;
 use crate::llm_client::LlmClient;
 use crate::types::TokenUsage;
 use async_trait::async_trait;
 use bytes::Bytes;
+use chrono::{DateTime, TimeDelta, Utc};
 use futures::stream::Stream;
+use regex::Regex;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 use std::pin::Pin;
+use std::sync::OnceLock;
 use std::task::{Context, Poll};
+use std::time::Duration;
+use url::Url;
 
 /// OpenRouter API client
 pub struct OpenRouterClient {
@@ -18,9 +24,16 @@ pub struct OpenRouterClient {
     client: Client,
     /// Configuration
     config: OpenRouterConfig,
+    /// Model list, fetched once per client
+    models: tokio::sync::OnceCell<Vec<OpenRouterModel>>,
 }
 
 impl OpenRouterClient {
+    /// Create a builder for configuring a client explicitly
+    pub fn builder() -> OpenRouterClientBuilder {
+        OpenRouterClientBuilder::new()
+    }
+
     /// Create a new OpenRouter client from environment variables
     pub fn from_env() -> Result<Self> {
         let config = OpenRouterConfig::from_env()?;
@@ -33,49 +46,102 @@ impl OpenRouterClient {
             .timeout(config.timeout)
             .build()?;
 
-        Ok(Self { client, config })
+        Ok(Self {
+            client,
+            config,
+            models: tokio::sync::OnceCell::new(),
+        })
+    }
+
+    /// List the models available through this endpoint
+    ///
+    /// Fetched from `/models` on first use and cached for the client's lifetime.
+    pub async fn list_models(&self) -> Result<&[OpenRouterModel]> {
+        let models = self
+            .models
+            .get_or_try_init(|| async {
+                let url = self.config.endpoint_url("models");
+                let (response, _) = self.send_with_retry(|| self.client.get(&url)).await?;
+
+                if !response.status().is_success() {
+                    let status = response.status();
+                    let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+                    return Err(Error::openrouter(format!(
+                        "Model list request failed with status {}: {}",
+                        status, error_text
+                    )));
+                }
+
+                let list: ModelList = response.json().await?;
+                Ok(list.data)
+            })
+            .await?;
+        Ok(models)
+    }
+
+    /// Fetch usage and credit limits for the configured API key from `/key`
+    ///
+    /// OpenRouter does not report credits in completion response headers, so
+    /// callers that budget spend poll this instead.
+    pub async fn key_info(&self) -> Result<KeyInfo> {
+        let url = self.config.endpoint_url("key");
+        let (response, _) = self.send_with_retry(|| self.client.get(&url)).await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(Error::openrouter(format!(
+                "Key info request failed with status {}: {}",
+                status, error_text
+            )));
+        }
+
+        let envelope: KeyInfoEnvelope = response.json().await?;
+        Ok(envelope.data)
     }
 
     /// Send a completion request
+    ///
+    /// With fallback models set, OpenRouter may answer from one of them; the
+    /// response's `model` names the model that actually served the request.
     pub async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
-        let url = format!("{}/chat/completions", self.config.base_url);
-
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.config.api_key()))
-            .header("X-Title", &self.config.app_name)
-            .json(&request)
-            .send()
+        let url = self.config.endpoint_url("chat/completions");
+        let request = request.with_routing(&self.config.provider_preferences);
+        self.log_body("request", &url, || serde_json::to_string(&request).unwrap_or_default());
+
+        let (response, retries) = self
+            .send_with_retry(|| self.client.post(&url).json(&request))
             .await?;
 
         if !response.status().is_success() {
             let status = response.status();
             let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            self.log_body("response", &url, || error_text.clone());
             return Err(Error::openrouter(format!(
                 "Request failed with status {}: {}",
                 status, error_text
             )));
         }
 
-        let completion: CompletionResponse = response.json().await?;
+        let rate_limit = RateLimitInfo::from_headers(response.headers());
+        let body = response.text().await?;
+        self.log_body("response", &url, || body.clone());
+        let mut completion: CompletionResponse = serde_json::from_str(&body)?;
+        completion.retries = retries;
+        completion.rate_limit = rate_limit;
         Ok(completion)
     }
 
     /// Stream a completion request
     pub async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
-        let url = format!("{}/chat/completions", self.config.base_url);
+        let url = self.config.endpoint_url("chat/completions");
 
-        let mut request_with_stream = request;
+        let mut request_with_stream = request.with_routing(&self.config.provider_preferences);
         request_with_stream.stream = true;
+        self.log_body("request", &url, || serde_json::to_string(&request_with_stream).unwrap_or_default());
 
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.config.api_key()))
-            .header("X-Title", &self.config.app_name)
-            .json(&request_with_stream)
-            .send()
+        let (response, _) = self
+            .send_with_retry(|| self.client.post(&url).json(&request_with_stream))
             .await?;
 
         if !response.status().is_success() {
@@ -87,13 +153,258 @@ impl OpenRouterClient {
             )));
         }
 
-        Ok(CompletionStream::new(response.bytes_stream()))
+        if !self.wire_logging() {
+            return Ok(CompletionStream::new(response.bytes_stream()));
+        }
+        let config = self.config.clone();
+        let body = futures::StreamExt::inspect(response.bytes_stream(), move |chunk| {
+            if let Ok(chunk) = chunk {
+                let chunk = String::from_utf8_lossy(chunk);
+                let body = wire_log_body(&chunk, config.log_body_limit, config.log_redactor.as_ref());
+                tracing::debug!(target: "spai::wire", url = %url, body = %body, "stream chunk");
+            }
+        });
+        Ok(CompletionStream::new(body))
+    }
+
+    /// Whether bodies are logged and a subscriber would record them
+    fn wire_logging(&self) -> bool {
+        self.config.log_bodies && tracing::enabled!(target: "spai::wire", tracing::Level::DEBUG)
+    }
+
+    /// Log a request or response body, building it only when it will be recorded
+    fn log_body(&self, direction: &str, url: &str, body: impl FnOnce() -> String) {
+        if !self.wire_logging() {
+            return;
+        }
+        let body = wire_log_body(&body(), self.config.log_body_limit, self.config.log_redactor.as_ref());
+        tracing::debug!(target: "spai::wire", url, body = %body, "{}", direction);
+    }
+
+    /// Embed texts with an embedding model, one vector per input in order
+    pub async fn embed(&self, model: &str, input: &[String]) -> Result<Vec<Vec<f32>>> {
+        let url = self.config.endpoint_url("embeddings");
+        let request = serde_json::json!({ "model": model, "input": input });
+
+        let (response, _) = self
+            .send_with_retry(|| self.client.post(&url).json(&request))
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(Error::openrouter(format!(
+                "Embedding request failed with status {}: {}",
+                status, error_text
+            )));
+        }
+
+        let mut list: EmbeddingList = response.json().await?;
+        if list.data.len() != input.len() {
+            return Err(Error::openrouter(format!(
+                "Expected {} embeddings, got {}",
+                input.len(),
+                list.data.len()
+            )));
+        }
+        list.data.sort_by_key(|item| item.index);
+        Ok(list.data.into_iter().map(|item| item.embedding).collect())
     }
 
     /// Get the configuration
     pub fn config(&self) -> &OpenRouterConfig {
         &self.config
     }
+
+    /// Send a request, retrying transient failures per the retry policy
+    ///
+    /// Returns the final response (which may still be an error status) and
+    /// the number of retries performed.
+    async fn send_with_retry(
+        &self,
+        request: impl Fn() -> reqwest::RequestBuilder,
+    ) -> Result<(reqwest::Response, u32)> {
+        let retry = &self.config.retry;
+        let mut attempt = 0;
+        loop {
+            let result = request()
+                .header("Authorization", format!("Bearer {}", self.config.api_key()))
+                .header("X-Title", &self.config.app_name)
+                .send()
+                .await;
+
+            let (reason, retry_after) = match &result {
+                Ok(response) if is_retryable_status(response.status()) => {
+                    let max = Duration::from_millis(retry.max_backoff_ms);
+                    (response.status().to_string(), retry_after(response.headers(), max))
+                }
+                Err(e) if e.is_connect() || e.is_timeout() => (e.to_string(), None),
+                _ => return Ok((result?, attempt)),
+            };
+            if attempt >= retry.max_retries {
+                return Ok((result?, attempt));
+            }
+
+            let delay = retry_after.unwrap_or_else(|| retry.backoff(attempt));
+            attempt += 1;
+            tracing::warn!(
+                attempt,
+                max_retries = retry.max_retries,
+                delay_ms = delay.as_millis() as u64,
+                "OpenRouter request failed ({}), retrying",
+                reason
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+/// Prepare a body for the wire log: redact it, then cap it at `limit` bytes
+pub(crate) fn wire_log_body(body: &str, limit: usize, redactor: Option<&SecretRedactor>) -> String {
+    let mut body = match redactor {
+        Some(redactor) => redactor.redact(body).0,
+        None => body.to_string(),
+    };
+    if body.len() > limit {
+        let mut end = limit;
+        while !body.is_char_boundary(end) {
+            end -= 1;
+        }
+        let dropped = body.len() - end;
+        body.truncate(end);
+        body.push_str(&format!("... [{} more bytes]", dropped));
+    }
+    body
+}
+
+/// Builder for [`OpenRouterClient`]
+///
+/// Useful when running behind a corporate LLM gateway or pointing at a local
+/// OpenAI-compatible server (vLLM, LiteLLM). Unset fields fall back to the
+/// `OPENROUTER_API_KEY` / `OPENROUTER_BASE_URL` environment variables.
+#[derive(Debug, Default)]
+pub struct OpenRouterClientBuilder {
+    api_key: Option<String>,
+    base_url: Option<String>,
+    default_model: Option<String>,
+    provider_preferences: Option<ProviderPreferences>,
+    timeout: Option<Duration>,
+    retry: Option<RetryConfig>,
+    app_name: Option<String>,
+    log_bodies: bool,
+    log_redactor: Option<SecretRedactor>,
+}
+
+impl OpenRouterClientBuilder {
+    /// Create a new builder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the API key
+    pub fn api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+
+    /// Set the base URL (e.g. "http://localhost:8000/v1")
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Set the default model
+    pub fn default_model(mut self, model: impl Into<String>) -> Self {
+        self.default_model = Some(model.into());
+        self
+    }
+
+    /// Set provider preferences
+    pub fn provider_preferences(mut self, preferences: ProviderPreferences) -> Self {
+        self.provider_preferences = Some(preferences);
+        self
+    }
+
+    /// Set the request timeout
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Set the retry policy for transient failures
+    pub fn retry(mut self, retry: RetryConfig) -> Self {
+        self.retry = Some(retry);
+        self
+    }
+
+    /// Set the app name sent in the `X-Title` header
+    pub fn app_name(mut self, app_name: impl Into<String>) -> Self {
+        self.app_name = Some(app_name.into());
+        self
+    }
+
+    /// Log request and response bodies at debug level (see [`OpenRouterConfig::with_log_bodies`])
+    pub fn log_bodies(mut self, log_bodies: bool) -> Self {
+        self.log_bodies = log_bodies;
+        self
+    }
+
+    /// Redact logged bodies with `redactor`
+    pub fn log_redactor(mut self, redactor: SecretRedactor) -> Self {
+        self.log_redactor = Some(redactor);
+        self
+    }
+
+    /// Build the configuration without creating an HTTP client
+    pub fn build_config(self) -> Result<OpenRouterConfig> {
+        let _ = dotenvy::dotenv();
+
+        let api_key = match self.api_key {
+            Some(key) => key,
+            None => std::env::var("OPENROUTER_API_KEY").map_err(|_| {
+                Error::config("API key not set (call .api_key() or set OPENROUTER_API_KEY)")
+            })?,
+        };
+
+        let mut config = OpenRouterConfig::new(api_key);
+
+        let base_url = self
+            .base_url
+            .or_else(|| std::env::var("OPENROUTER_BASE_URL").ok())
+            .filter(|url| !url.trim().is_empty());
+        if let Some(url) = base_url {
+            let parsed = Url::parse(url.trim())
+                .map_err(|e| Error::config(format!("Invalid base URL '{}': {}", url, e)))?;
+            config = config.with_base_url(parsed);
+        }
+
+        if let Some(model) = self.default_model {
+            config = config.with_default_model(model);
+        }
+        if let Some(preferences) = self.provider_preferences {
+            config = config.with_provider_preferences(preferences);
+        }
+        if let Some(timeout) = self.timeout {
+            config = config.with_timeout(timeout);
+        }
+        if let Some(retry) = self.retry {
+            config = config.with_retry(retry);
+        }
+        if let Some(app_name) = self.app_name {
+            config = config.with_app_name(app_name);
+        }
+        config = config.with_log_bodies(self.log_bodies);
+        if let Some(redactor) = self.log_redactor {
+            config = config.with_log_redactor(redactor);
+        }
+
+        Ok(config)
+    }
+
+    /// Build the client
+    pub fn build(self) -> Result<OpenRouterClient> {
+        OpenRouterClient::new(self.build_config()?)
+    }
 }
 
 /// Completion request
@@ -127,6 +438,18 @@ pub struct CompletionRequest {
     /// Tool choice behavior
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<ToolChoice>,
+    /// Structured output format
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    /// Models to fall back to, in order, if `model` is unavailable (OpenRouter `models`)
+    #[serde(rename = "models", default, skip_serializing_if = "Vec::is_empty")]
+    pub fallback_models: Vec<String>,
+    /// Ask reasoning models to return their reasoning tokens in [`Message::reasoning`]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_reasoning: Option<bool>,
+    /// Provider routing preferences, overriding the client's defaults
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<ProviderPreferences>,
 }
 
 impl CompletionRequest {
@@ -143,6 +466,10 @@ impl CompletionRequest {
             stream: false,
             tools: None,
             tool_choice: None,
+            response_format: None,
+            fallback_models: Vec::new(),
+            include_reasoning: None,
+            provider: None,
         }
     }
 
@@ -175,24 +502,234 @@ impl CompletionRequest {
         self.tool_choice = Some(tool_choice);
         self
     }
+
+    /// Set the structured output format
+    pub fn with_response_format(mut self, response_format: ResponseFormat) -> Self {
+        self.response_format = Some(response_format);
+        self
+    }
+
+    /// Set the models to fall back to, in order
+    pub fn with_fallback_models(mut self, models: Vec<String>) -> Self {
+        self.fallback_models = models;
+        self
+    }
+
+    /// Request the model's native reasoning tokens
+    pub fn with_include_reasoning(mut self, include: bool) -> Self {
+        self.include_reasoning = Some(include);
+        self
+    }
+
+    /// Set the provider routing preferences
+    pub fn with_provider(mut self, preferences: ProviderPreferences) -> Self {
+        self.provider = Some(preferences);
+        self
+    }
+
+    /// Put the primary model at the head of the fallback list and fill in default provider preferences
+    ///
+    /// OpenRouter tries the `models` array in order, so the primary model has
+    /// to be listed there too once fallbacks are given.
+    fn with_routing(mut self, defaults: &ProviderPreferences) -> Self {
+        if !self.fallback_models.is_empty() && !self.fallback_models.contains(&self.model) {
+            self.fallback_models.insert(0, self.model.clone());
+        }
+        if self.provider.is_none() && !defaults.is_empty() {
+            self.provider = Some(defaults.clone());
+        }
+        self
+    }
+}
+
+/// Structured output format for a completion
+///
+/// Serializes to the OpenAI / OpenRouter `response_format` object, e.g.
+/// `{"type": "json_schema", "json_schema": {"name": ..., "strict": true, "schema": ...}}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Plain text
+    Text,
+    /// Any JSON object
+    JsonObject,
+    /// JSON matching a schema
+    JsonSchema {
+        /// Schema the response must match
+        json_schema: JsonSchemaFormat,
+    },
+}
+
+impl ResponseFormat {
+    /// Require a strict match against `schema`
+    pub fn json_schema(name: impl Into<String>, schema: serde_json::Value) -> Self {
+        Self::JsonSchema {
+            json_schema: JsonSchemaFormat {
+                name: name.into(),
+                strict: true,
+                schema,
+            },
+        }
+    }
+}
+
+/// Named JSON schema for [`ResponseFormat::JsonSchema`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonSchemaFormat {
+    /// Schema name (letters, digits, `_` and `-`)
+    pub name: String,
+    /// Whether the provider should enforce the schema exactly
+    #[serde(default)]
+    pub strict: bool,
+    /// The JSON schema
+    pub schema: serde_json::Value,
 }
 
 /// Message in a conversation
+///
+/// A message with [`parts`](Message::parts) is sent in the content-array
+/// format, its text first; otherwise `content` is sent as a plain string.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(into = "WireMessage", from = "WireMessage")]
 pub struct Message {
     /// Role of the message sender
     pub role: Role,
-    /// Content of the message
+    /// Text content of the message
     pub content: String,
+    /// Non-text content such as images, sent after the text
+    pub parts: Vec<ContentPart>,
     /// Optional name of the sender
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
     /// Optional tool calls (for assistant messages)
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
     /// Optional tool call ID (for tool messages)
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_call_id: Option<String>,
+    /// Native reasoning tokens returned by reasoning models; never sent back
+    pub reasoning: Option<String>,
+}
+
+/// Typed part of a multimodal message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    /// Plain text
+    Text {
+        /// The text
+        text: String,
+    },
+    /// Image given by URL (including `data:` URLs)
+    ImageUrl {
+        /// Image location
+        image_url: ImageUrl,
+    },
+}
+
+impl ContentPart {
+    /// Text part
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text { text: text.into() }
+    }
+
+    /// Image fetched by the provider from `url`
+    pub fn image_url(url: impl Into<String>) -> Self {
+        Self::ImageUrl {
+            image_url: ImageUrl {
+                url: url.into(),
+                detail: None,
+            },
+        }
+    }
+
+    /// Inline image from base64 `data` of the given media type (e.g. `image/png`)
+    pub fn image_base64(media_type: &str, data: &str) -> Self {
+        Self::image_url(format!("data:{};base64,{}", media_type, data))
+    }
+}
+
+/// Image reference inside a [`ContentPart::ImageUrl`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageUrl {
+    /// HTTP(S) or `data:` URL of the image
+    pub url: String,
+    /// Resolution hint (`low`, `high` or `auto`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Message content as sent on the wire: a string or an array of parts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    /// Plain text
+    Text(String),
+    /// Multimodal content
+    Parts(Vec<ContentPart>),
+}
+
+/// Wire form of [`Message`]
+#[derive(Serialize, Deserialize)]
+struct WireMessage {
+    role: Role,
+    #[serde(default)]
+    content: Option<MessageContent>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Vec<ToolCall>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<String>,
+    #[serde(default, skip_serializing)]
+    reasoning: Option<String>,
+}
+
+impl From<Message> for WireMessage {
+    fn from(message: Message) -> Self {
+        let content = if message.parts.is_empty() {
+            MessageContent::Text(message.content)
+        } else {
+            let text = (!message.content.is_empty()).then(|| ContentPart::text(message.content));
+            MessageContent::Parts(text.into_iter().chain(message.parts).collect())
+        };
+        Self {
+            role: message.role,
+            content: Some(content),
+            name: message.name,
+            tool_calls: message.tool_calls,
+            tool_call_id: message.tool_call_id,
+            reasoning: None,
+        }
+    }
+}
+
+impl From<WireMessage> for Message {
+    fn from(wire: WireMessage) -> Self {
+        let (content, parts) = match wire.content {
+            None => (String::new(), Vec::new()),
+            Some(MessageContent::Text(text)) => (text, Vec::new()),
+            Some(MessageContent::Parts(all)) => {
+                let (text, parts): (Vec<_>, Vec<_>) =
+                    all.into_iter().partition(|part| matches!(part, ContentPart::Text { .. }));
+                let text = text
+                    .into_iter()
+                    .filter_map(|part| match part {
+                        ContentPart::Text { text } => Some(text),
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n");
+                (text, parts)
+            }
+        };
+        Self {
+            role: wire.role,
+            content,
+            parts,
+            name: wire.name,
+            tool_calls: wire.tool_calls,
+            tool_call_id: wire.tool_call_id,
+            reasoning: wire.reasoning.filter(|reasoning| !reasoning.trim().is_empty()),
+        }
+    }
 }
 
 impl Message {
@@ -201,9 +738,11 @@ impl Message {
         Self {
             role: Role::System,
             content: content.into(),
+            parts: Vec::new(),
             name: None,
             tool_calls: None,
             tool_call_id: None,
+            reasoning: None,
         }
     }
 
@@ -212,9 +751,11 @@ impl Message {
         Self {
             role: Role::User,
             content: content.into(),
+            parts: Vec::new(),
             name: None,
             tool_calls: None,
             tool_call_id: None,
+            reasoning: None,
         }
     }
 
@@ -223,9 +764,11 @@ impl Message {
         Self {
             role: Role::Assistant,
             content: content.into(),
+            parts: Vec::new(),
             name: None,
             tool_calls: None,
             tool_call_id: None,
+            reasoning: None,
         }
     }
 
@@ -234,16 +777,26 @@ impl Message {
         Self {
             role: Role::Tool,
             content: content.into(),
+            parts: Vec::new(),
             name: None,
             tool_calls: None,
             tool_call_id: Some(tool_call_id.into()),
+            reasoning: None,
         }
     }
+
+    /// Add an image or other content part to the message
+    pub fn with_part(mut self, part: ContentPart) -> Self {
+        self.parts.push(part);
+        self
+    }
 }
 
 /// Role of a message sender
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
+///
+/// Serialized as the lowercase wire string. Unrecognized strings are kept as
+/// [`Role::Other`] so newer provider roles and old stored messages still load.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Role {
     /// System message
     System,
@@ -253,6 +806,136 @@ pub enum Role {
     Assistant,
     /// Tool message
     Tool,
+    /// Any other role, verbatim
+    Other(String),
+}
+
+impl Role {
+    /// Wire string for this role
+    pub fn as_str(&self) -> &str {
+        match self {
+            Role::System => "system",
+            Role::User => "user",
+            Role::Assistant => "assistant",
+            Role::Tool => "tool",
+            Role::Other(role) => role,
+        }
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<&str> for Role {
+    /// Parse a role string, ignoring case and surrounding whitespace for the known roles
+    fn from(role: &str) -> Self {
+        match role.trim().to_ascii_lowercase().as_str() {
+            "system" => Role::System,
+            "user" => Role::User,
+            "assistant" => Role::Assistant,
+            "tool" => Role::Tool,
+            _ => Role::Other(role.to_string()),
+        }
+    }
+}
+
+impl From<String> for Role {
+    fn from(role: String) -> Self {
+        Role::from(role.as_str())
+    }
+}
+
+impl Serialize for Role {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for Role {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Role::from)
+    }
+}
+
+/// Whether a response status is worth retrying
+fn is_retryable_status(status: reqwest::StatusCode) -> bool {
+    matches!(status.as_u16(), 429 | 500 | 502 | 503 | 504)
+}
+
+/// Delay requested by a `Retry-After` header (seconds form only), capped at `max`
+fn retry_after(headers: &reqwest::header::HeaderMap, max: Duration) -> Option<Duration> {
+    let secs = headers
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|secs| *secs >= 0.0)?;
+    // Clamp before converting: a huge header value would overflow `Duration`
+    Duration::try_from_secs_f64(secs.min(max.as_secs_f64())).ok()
+}
+
+/// A model listed by the `/models` endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OpenRouterModel {
+    /// Model identifier (e.g., "anthropic/claude-sonnet-4.5")
+    pub id: String,
+    /// Display name
+    #[serde(default)]
+    pub name: String,
+    /// Context window in tokens
+    #[serde(default)]
+    pub context_length: Option<u64>,
+}
+
+/// Usage and limits of an API key, from the `/key` endpoint
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeyInfo {
+    /// Key label
+    #[serde(default)]
+    pub label: String,
+    /// Credits used so far
+    #[serde(default)]
+    pub usage: f64,
+    /// Credit limit, `None` when unlimited
+    #[serde(default)]
+    pub limit: Option<f64>,
+    /// Credits left under the limit, `None` when unlimited
+    #[serde(default)]
+    pub limit_remaining: Option<f64>,
+    /// Whether the key is on the free tier
+    #[serde(default)]
+    pub is_free_tier: bool,
+}
+
+/// `/key` response body
+#[derive(Debug, Deserialize)]
+struct KeyInfoEnvelope {
+    data: KeyInfo,
+}
+
+/// `/models` response body
+#[derive(Debug, Deserialize)]
+struct ModelList {
+    data: Vec<OpenRouterModel>,
+}
+
+/// `/embeddings` response body
+#[derive(Debug, Deserialize)]
+struct EmbeddingList {
+    data: Vec<EmbeddingItem>,
+}
+
+/// One vector in an `/embeddings` response
+#[derive(Debug, Deserialize)]
+struct EmbeddingItem {
+    #[serde(default)]
+    index: usize,
+    embedding: Vec<f32>,
 }
 
 /// Tool definition for function calling
@@ -277,8 +960,10 @@ pub struct FunctionDefinition {
 }
 
 /// Tool choice behavior
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(untagged)]
+///
+/// Serializes to the OpenAI wire format: `"auto"`, `"none"`, `"required"`, or
+/// `{"type": "function", "function": {"name": ...}}` for a named tool.
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum ToolChoice {
     /// Auto (model decides)
     Auto,
@@ -286,8 +971,60 @@ pub enum ToolChoice {
     None,
     /// Required (must call a tool)
     Required,
-    /// Specific function
-    Function { function: FunctionChoice },
+    /// Must call the named tool
+    Named(String),
+}
+
+impl ToolChoice {
+    /// Force a call to the named tool
+    pub fn named(name: impl Into<String>) -> Self {
+        Self::Named(name.into())
+    }
+}
+
+/// Wire representation of [`ToolChoice`]
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ToolChoiceRepr {
+    Mode(String),
+    Function {
+        #[serde(rename = "type", default = "function_type")]
+        tool_type: String,
+        function: FunctionChoice,
+    },
+}
+
+fn function_type() -> String {
+    "function".to_string()
+}
+
+impl Serialize for ToolChoice {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let repr = match self {
+            Self::Auto => ToolChoiceRepr::Mode("auto".to_string()),
+            Self::None => ToolChoiceRepr::Mode("none".to_string()),
+            Self::Required => ToolChoiceRepr::Mode("required".to_string()),
+            Self::Named(name) => ToolChoiceRepr::Function {
+                tool_type: function_type(),
+                function: FunctionChoice { name: name.clone() },
+            },
+        };
+        repr.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ToolChoice {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        match ToolChoiceRepr::deserialize(deserializer)? {
+            ToolChoiceRepr::Mode(mode) => match mode.as_str() {
+                "auto" => Ok(Self::Auto),
+                "none" => Ok(Self::None),
+                "required" => Ok(Self::Required),
+                other => Err(serde::de::Error::unknown_variant(other, &["auto", "none", "required"])),
+            },
+            ToolChoiceRepr::Function { function, .. } => Ok(Self::Named(function.name)),
+        }
+    }
 }
 
 /// Specific function choice
@@ -329,6 +1066,105 @@ pub struct CompletionResponse {
     pub choices: Vec<Choice>,
     /// Token usage
     pub usage: Usage,
+    /// Retries performed before this response arrived
+    #[serde(skip)]
+    pub retries: u32,
+    /// Rate-limit state reported in the response headers, if the provider sent any
+    #[serde(skip)]
+    pub rate_limit: Option<RateLimitInfo>,
+}
+
+/// Rate-limit state from `x-ratelimit-*` response headers
+///
+/// Understands OpenRouter's `x-ratelimit-{limit,remaining,reset}` as well as
+/// the OpenAI-style `-requests` / `-tokens` variants that gateways send.
+/// Remaining credits are not sent as headers; see [`OpenRouterClient::key_info`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RateLimitInfo {
+    /// Requests allowed in the current window
+    pub limit_requests: Option<u64>,
+    /// Requests left in the current window
+    pub remaining_requests: Option<u64>,
+    /// Tokens left in the current window
+    pub remaining_tokens: Option<u64>,
+    /// When the request window resets
+    pub reset_at: Option<DateTime<Utc>>,
+}
+
+impl RateLimitInfo {
+    /// Parse rate-limit headers, returning `None` when there are none
+    pub fn from_headers(headers: &reqwest::header::HeaderMap) -> Option<Self> {
+        let header = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| headers.get(*name).and_then(|v| v.to_str().ok()).map(str::trim))
+        };
+        let number = |names: &[&str]| header(names).and_then(|v| v.parse::<f64>().ok()).map(|v| v as u64);
+
+        let info = Self {
+            limit_requests: number(&["x-ratelimit-limit", "x-ratelimit-limit-requests"]),
+            remaining_requests: number(&["x-ratelimit-remaining", "x-ratelimit-remaining-requests"]),
+            remaining_tokens: number(&["x-ratelimit-remaining-tokens"]),
+            reset_at: header(&["x-ratelimit-reset", "x-ratelimit-reset-requests"]).and_then(parse_reset),
+        };
+        (info != Self::default()).then_some(info)
+    }
+
+    /// Whether the request allowance is used up
+    pub fn is_exhausted(&self) -> bool {
+        self.remaining_requests == Some(0)
+    }
+
+    /// How long to wait before the next request to stay under the limit
+    ///
+    /// `None` while requests remain or when the reset time is unknown.
+    pub fn backoff(&self) -> Option<Duration> {
+        if !self.is_exhausted() {
+            return None;
+        }
+        let wait = self.reset_at? - Utc::now();
+        Some(wait.to_std().unwrap_or(Duration::ZERO))
+    }
+}
+
+/// Parse a reset header: epoch milliseconds or seconds, or a duration such as `6m0s` or `250ms`
+///
+/// Integers too small to be an epoch timestamp are seconds from now. The
+/// value comes from the server, so negative or out-of-range values are
+/// rejected instead of panicking.
+fn parse_reset(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(number) = value.parse::<i64>() {
+        return if number < 0 {
+            None
+        } else if number > 100_000_000_000 {
+            DateTime::from_timestamp_millis(number)
+        } else if number >= 1_000_000_000 {
+            DateTime::from_timestamp(number, 0)
+        } else {
+            Utc::now().checked_add_signed(TimeDelta::try_seconds(number)?)
+        };
+    }
+
+    static DURATION: OnceLock<Regex> = OnceLock::new();
+    let part = DURATION.get_or_init(|| Regex::new(r"(\d+(?:\.\d+)?)(ms|h|m|s)").unwrap());
+    let mut total = 0.0;
+    let mut matched = 0;
+    for caps in part.captures_iter(value) {
+        let amount: f64 = caps[1].parse().ok()?;
+        total += amount
+            * match &caps[2] {
+                "h" => 3600.0,
+                "m" => 60.0,
+                "s" => 1.0,
+                _ => 0.001,
+            };
+        matched += caps[0].len();
+    }
+    if matched != value.len() {
+        return None;
+    }
+    // `as i64` saturates, so anything past the representable range fails below
+    Utc::now().checked_add_signed(TimeDelta::try_milliseconds((total * 1000.0) as i64)?)
 }
 
 /// Choice in completion response
@@ -343,7 +1179,7 @@ pub struct Choice {
 }
 
 /// Token usage information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Usage {
     /// Prompt tokens
     pub prompt_tokens: u64,
@@ -368,6 +1204,9 @@ pub struct StreamChunk {
     pub model: String,
     /// Choices
     pub choices: Vec<StreamChoice>,
+    /// Token usage (usually only on the last chunk)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
 }
 
 /// Choice in stream chunk
@@ -393,25 +1232,53 @@ pub struct Delta {
     /// Tool calls delta
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_calls: Option<Vec<ToolCall>>,
+    /// Reasoning tokens delta
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
 }
 
 /// Streaming completion response
+///
+/// Parses the server-sent event body of a streaming request into
+/// [`StreamChunk`]s, ending at `data: [DONE]` or when the body ends.
 pub struct CompletionStream {
     inner: Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>,
-    buffer: String,
+    buffer: Vec<u8>,
+    done: bool,
 }
 
 impl CompletionStream {
     pub(crate) fn new(stream: impl Stream<Item = reqwest::Result<Bytes>> + Send + 'static) -> Self {
         Self {
             inner: Box::pin(stream),
-            buffer: String::new(),
+            buffer: Vec::new(),
+            done: false,
         }
     }
 
     /// Get the next chunk from the stream
     pub async fn next_chunk(&mut self) -> Option<Result<StreamChunk>> {
-        // Simplified streaming implementation - in production would need proper SSE parsing
+        futures::StreamExt::next(self).await
+    }
+
+    /// Parse the next complete `data:` line out of the buffer
+    fn next_event(&mut self) -> Option<Result<StreamChunk>> {
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            // Blank separators, `event:` lines and `: keep-alive` comments carry no chunk
+            let Some(data) = line.trim().strip_prefix("data:").map(str::trim) else {
+                continue;
+            };
+            if data == "[DONE]" {
+                self.done = true;
+                self.buffer.clear();
+                return None;
+            }
+            if !data.is_empty() {
+                return Some(serde_json::from_str(data).map_err(Error::from));
+            }
+        }
         None
     }
 }
@@ -419,9 +1286,31 @@ impl CompletionStream {
 impl Stream for CompletionStream {
     type Item = Result<StreamChunk>;
 
-    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        // Simplified implementation - in production would parse SSE events
-        Poll::Ready(None)
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            if let Some(chunk) = this.next_event() {
+                return Poll::Ready(Some(chunk));
+            }
+            if this.done {
+                return Poll::Ready(None);
+            }
+            match this.inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(bytes))) => this.buffer.extend_from_slice(&bytes),
+                Poll::Ready(Some(Err(e))) => {
+                    this.done = true;
+                    return Poll::Ready(Some(Err(e.into())));
+                }
+                Poll::Ready(None) => {
+                    // Flush a final line that arrived without a trailing newline
+                    this.done = true;
+                    if !this.buffer.is_empty() {
+                        this.buffer.push(b'\n');
+                    }
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
     }
 }
 
@@ -444,4 +1333,476 @@ impl LlmClient for OpenRouterClient {
     fn endpoint(&self) -> &str {
         self.config.base_url.as_str()
     }
+
+    async fn model_ids(&self) -> Result<Vec<String>> {
+        Ok(self.list_models().await?.iter().map(|m| m.id.clone()).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_builder_explicit_base_url() {
+        let client = OpenRouterClient::builder()
+            .api_key("test-key")
+            .base_url("http://localhost:4000/v1/")
+            .build()
+            .unwrap();
+
+        assert_eq!(client.config().api_key(), "test-key");
+        assert_eq!(
+            client.config().endpoint_url("chat/completions"),
+            "http://localhost:4000/v1/chat/completions"
+        );
+        assert_eq!(LlmClient::endpoint(&client), "http://localhost:4000/v1/");
+    }
+
+    #[test]
+    fn test_builder_rejects_invalid_base_url() {
+        let result = OpenRouterClient::builder()
+            .api_key("test-key")
+            .base_url("not a url")
+            .build();
+
+        assert!(matches!(result, Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn test_multimodal_content_wire_format() {
+        let plain = serde_json::to_value(Message::user("hi")).unwrap();
+        assert_eq!(plain["content"], "hi");
+
+        let message = Message::user("What is on this screen?")
+            .with_part(ContentPart::image_base64("image/png", "iVBORw0KGgo="));
+        let json = serde_json::to_value(&message).unwrap();
+        assert_eq!(
+            json["content"],
+            serde_json::json!([
+                {"type": "text", "text": "What is on this screen?"},
+                {"type": "image_url", "image_url": {"url": "data:image/png;base64,iVBORw0KGgo="}}
+            ])
+        );
+
+        let parsed: Message = serde_json::from_value(json).unwrap();
+        assert_eq!(parsed.content, "What is on this screen?");
+        assert_eq!(parsed.parts, message.parts);
+        let empty: Message = serde_json::from_str(r#"{"role": "assistant", "content": null}"#).unwrap();
+        assert_eq!(empty.content, "");
+    }
+
+    #[test]
+    fn test_native_reasoning_wire_format() {
+        let request = CompletionRequest::new("deepseek/deepseek-r1", vec![]);
+        assert!(serde_json::to_value(&request).unwrap().get("include_reasoning").is_none());
+        let request = request.with_include_reasoning(true);
+        assert_eq!(serde_json::to_value(&request).unwrap()["include_reasoning"], true);
+
+        let message: Message = serde_json::from_str(
+            r#"{"role": "assistant", "content": "Final answer: 4", "reasoning": "2 + 2 = 4"}"#,
+        )
+        .unwrap();
+        assert_eq!(message.reasoning.as_deref(), Some("2 + 2 = 4"));
+        assert!(serde_json::to_value(&message).unwrap().get("reasoning").is_none());
+
+        let message: Message = serde_json::from_str(r#"{"role": "assistant", "content": "4", "reasoning": ""}"#).unwrap();
+        assert!(message.reasoning.is_none());
+    }
+
+    #[test]
+    fn test_role_wire_strings() {
+        assert_eq!(serde_json::to_value(Message::assistant("hi")).unwrap()["role"], "assistant");
+        let roles: Vec<Role> = serde_json::from_str(r#"["system", "User ", "tool", "developer"]"#).unwrap();
+        assert_eq!(
+            roles,
+            vec![Role::System, Role::User, Role::Tool, Role::Other("developer".to_string())]
+        );
+        assert_eq!(serde_json::to_value(&roles[3]).unwrap(), "developer");
+    }
+
+    #[test]
+    fn test_tool_choice_wire_format() {
+        let request = CompletionRequest::new("test-model", vec![Message::user("hi")])
+            .with_tool_choice(ToolChoice::Required);
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["tool_choice"], "required");
+
+        let named = serde_json::to_value(ToolChoice::named("scan")).unwrap();
+        assert_eq!(named, serde_json::json!({"type": "function", "function": {"name": "scan"}}));
+        assert_eq!(serde_json::from_value::<ToolChoice>(named).unwrap(), ToolChoice::named("scan"));
+        assert_eq!(serde_json::from_str::<ToolChoice>("\"none\"").unwrap(), ToolChoice::None);
+        assert!(serde_json::from_str::<ToolChoice>("\"sometimes\"").is_err());
+    }
+
+    /// Serve canned HTTP responses, one per connection, counting requests
+    async fn serve(responses: Vec<String>) -> (String, Arc<AtomicUsize>) {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let hits = Arc::new(AtomicUsize::new(0));
+        let counter = hits.clone();
+        tokio::spawn(async move {
+            for response in responses {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+
+                // Read headers and body before answering
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                loop {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request);
+                    if let Some(end) = text.find("\r\n\r\n") {
+                        let length = text[..end]
+                            .lines()
+                            .find_map(|l| l.to_lowercase().strip_prefix("content-length:").map(|v| v.trim().to_string()))
+                            .and_then(|v| v.parse::<usize>().ok())
+                            .unwrap_or(0);
+                        if request.len() >= end + 4 + length {
+                            break;
+                        }
+                    }
+                    if n == 0 {
+                        break;
+                    }
+                }
+
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.ok();
+            }
+        });
+        (url, hits)
+    }
+
+    fn http_response(status: &str, headers: &str, body: &str) -> String {
+        format!(
+            "HTTP/1.1 {}\r\n{}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            headers,
+            body.len(),
+            body
+        )
+    }
+
+    fn test_client(url: &str) -> OpenRouterClient {
+        OpenRouterClient::builder()
+            .api_key("test-key")
+            .base_url(url)
+            .retry(RetryConfig {
+                max_retries: 3,
+                initial_backoff_ms: 1,
+                max_backoff_ms: 10,
+                jitter: false,
+            })
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_complete_retries_rate_limits() {
+        let body = r#"{"id":"r1","model":"test-model","choices":[{"index":0,"message":{"role":"assistant","content":"hi"},"finish_reason":"stop"}],"usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2}}"#;
+        let (url, hits) = serve(vec![
+            http_response("429 Too Many Requests", "Retry-After: 0\r\n", ""),
+            http_response("429 Too Many Requests", "", ""),
+            http_response("200 OK", "", body),
+        ])
+        .await;
+
+        let response = test_client(&url)
+            .complete(CompletionRequest::new("test-model", vec![Message::user("hi")]))
+            .await
+            .unwrap();
+        assert_eq!(response.choices[0].message.content, "hi");
+        assert_eq!(response.retries, 2);
+        assert_eq!(hits.load(Ordering::SeqCst), 3);
+    }
+
+    #[test]
+    fn test_wire_log_body_redacts_and_truncates() {
+        let redactor = SecretRedactor::empty().with_pattern(r"sk-[a-z0-9]+").unwrap();
+        let body = wire_log_body("key sk-abc123 then ééé", 18, Some(&redactor));
+        assert!(!body.contains("sk-abc123"));
+        assert!(body.starts_with("key [REDACTED]"));
+        // Cut back to a char boundary rather than splitting 'é'
+        assert!(body.ends_with("more bytes]"));
+        assert_eq!(wire_log_body("short", 100, None), "short");
+    }
+
+    /// Collects everything a fmt subscriber writes
+    #[derive(Clone, Default)]
+    struct CapturedLogs(Arc<parking_lot::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for CapturedLogs {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_complete_logs_bodies_without_api_key() {
+        let body = r#"{"id":"r1","model":"test-model","choices":[{"index":0,"message":{"role":"assistant","content":"the pin is 4242"},"finish_reason":"stop"}],"usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2}}"#;
+        let (url, _) = serve(vec![http_response("200 OK", "", body)]).await;
+        let client = OpenRouterClient::builder()
+            .api_key("test-key")
+            .base_url(&url)
+            .log_bodies(true)
+            .log_redactor(SecretRedactor::empty().with_pattern(r"pin is (\d+)").unwrap())
+            .build()
+            .unwrap();
+
+        let logs = CapturedLogs::default();
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let response = client
+            .complete(CompletionRequest::new("test-model", vec![Message::user("what is the pin?")]))
+            .await
+            .unwrap();
+        assert_eq!(response.choices[0].message.content, "the pin is 4242");
+
+        let logs = String::from_utf8(logs.0.lock().clone()).unwrap();
+        assert!(logs.contains("spai::wire"));
+        assert!(logs.contains("what is the pin?"));
+        assert!(logs.contains("the pin is [REDACTED]"));
+        assert!(!logs.contains("4242"));
+        assert!(!logs.contains("test-key"));
+    }
+
+    #[tokio::test]
+    async fn test_complete_fails_fast_on_client_error() {
+        let (url, hits) = serve(vec![
+            http_response("400 Bad Request", "", r#"{"error":"bad model"}"#),
+            http_response("200 OK", "", "{}"),
+        ])
+        .await;
+
+        let err = test_client(&url)
+            .complete(CompletionRequest::new("test-model", vec![Message::user("hi")]))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("400"));
+        assert_eq!(hits.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_retry_backoff() {
+        let retry = RetryConfig {
+            max_retries: 5,
+            initial_backoff_ms: 100,
+            max_backoff_ms: 1_000,
+            jitter: false,
+        };
+        assert_eq!(retry.backoff(0), Duration::from_millis(100));
+        assert_eq!(retry.backoff(2), Duration::from_millis(400));
+        assert_eq!(retry.backoff(10), Duration::from_millis(1_000));
+
+        let jittered = RetryConfig { jitter: true, ..retry }.backoff(1);
+        assert!(jittered >= Duration::from_millis(100) && jittered <= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn test_retry_after_is_capped() {
+        let max = Duration::from_secs(30);
+        let header = |value: &str| {
+            let mut headers = reqwest::header::HeaderMap::new();
+            headers.insert(reqwest::header::RETRY_AFTER, value.parse().unwrap());
+            retry_after(&headers, max)
+        };
+        assert_eq!(header("1.5"), Some(Duration::from_millis(1500)));
+        // Oversized values are clamped rather than overflowing `Duration`
+        assert_eq!(header("1e20"), Some(max));
+        assert_eq!(header("inf"), Some(max));
+        assert_eq!(header("-1"), None);
+        assert_eq!(header("NaN"), None);
+        assert_eq!(header("Wed, 21 Oct 2026 07:28:00 GMT"), None);
+    }
+
+    #[test]
+    fn test_default_endpoint_url() {
+        let config = OpenRouterConfig::new("test-key");
+        assert_eq!(
+            config.endpoint_url("/chat/completions"),
+            "https://openrouter.ai/api/v1/chat/completions"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_completion_stream_parses_sse() {
+        let body = [
+            ": OPENROUTER PROCESSING\n\ndata: {\"id\":\"c1\",\"model\":\"m\",\"choices\":[{\"index\":0,",
+            "\"delta\":{\"content\":\"Hel\"},\"finish_reason\":null}]}\n\n",
+            "data: {\"id\":\"c1\",\"model\":\"m\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"lo\"},",
+            "\"finish_reason\":\"stop\"}],\"usage\":{\"prompt_tokens\":3,\"completion_tokens\":2,\"total_tokens\":5}}\n\n",
+            "data: [DONE]\n\ndata: ignored\n\n",
+        ];
+        let mut stream = CompletionStream::new(futures::stream::iter(
+            body.into_iter().map(|part| Ok(Bytes::from(part))),
+        ));
+
+        let mut content = String::new();
+        let mut usage = None;
+        while let Some(chunk) = stream.next_chunk().await {
+            let chunk = chunk.unwrap();
+            content.push_str(chunk.choices[0].delta.content.as_deref().unwrap_or(""));
+            usage = chunk.usage.or(usage);
+        }
+        assert_eq!(content, "Hello");
+        assert_eq!(usage.unwrap().total_tokens, 5);
+    }
+
+    #[tokio::test]
+    async fn test_fallback_models() {
+        let request = CompletionRequest::new("anthropic/claude-sonnet-4", vec![Message::user("hi")])
+            .with_fallback_models(vec!["openai/gpt-4o".to_string()]);
+        let wire = serde_json::to_value(request.clone().with_routing(&ProviderPreferences::default())).unwrap();
+        assert_eq!(wire["model"], "anthropic/claude-sonnet-4");
+        assert_eq!(wire["models"], serde_json::json!(["anthropic/claude-sonnet-4", "openai/gpt-4o"]));
+        let plain = serde_json::to_value(CompletionRequest::new("m", vec![]).with_routing(&ProviderPreferences::default())).unwrap();
+        assert!(plain.get("models").is_none());
+
+        // The primary was rate-limited upstream; OpenRouter answered from the fallback
+        let body = r#"{"id":"r1","model":"openai/gpt-4o","choices":[{"index":0,"message":{"role":"assistant","content":"hi"},"finish_reason":"stop"}],"usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2}}"#;
+        let (url, _) = serve(vec![http_response("200 OK", "", body)]).await;
+        let response = test_client(&url).complete(request).await.unwrap();
+        assert_eq!(response.model, "openai/gpt-4o");
+        assert!(response.rate_limit.is_none());
+    }
+
+    #[test]
+    fn test_provider_preferences_round_trip() {
+        use crate::config::{DataCollection, OptimizationTarget};
+
+        let preferences = ProviderPreferences::new()
+            .with_order(vec!["anthropic".to_string(), "openai".to_string()])
+            .with_ignore(vec!["deepinfra".to_string()])
+            .with_allow_fallbacks(false)
+            .with_require_parameters(true)
+            .with_data_collection(DataCollection::Deny)
+            .with_quantizations(vec!["fp8".to_string()])
+            .with_optimization(OptimizationTarget::LowerCost);
+        let request = CompletionRequest::new("m", vec![]).with_provider(preferences.clone());
+        let wire = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            wire["provider"],
+            serde_json::json!({
+                "order": ["anthropic", "openai"],
+                "ignore": ["deepinfra"],
+                "allow_fallbacks": false,
+                "require_parameters": true,
+                "data_collection": "deny",
+                "quantizations": ["fp8"],
+                "sort": "price"
+            })
+        );
+        let parsed: CompletionRequest = serde_json::from_value(wire).unwrap();
+        assert_eq!(parsed.provider, Some(preferences));
+
+        // Only set fields are sent; the older field names still parse
+        let only = serde_json::to_value(ProviderPreferences::new().with_only(vec!["azure".to_string()])).unwrap();
+        assert_eq!(only, serde_json::json!({"only": ["azure"]}));
+        let legacy: ProviderPreferences =
+            serde_json::from_str(r#"{"preferred": ["openai"], "excluded": [], "optimization": "performance"}"#).unwrap();
+        assert_eq!(legacy.preferred, vec!["openai"]);
+        assert_eq!(legacy.optimization, OptimizationTarget::Performance);
+        assert!(ProviderPreferences::default().is_empty());
+    }
+
+    #[test]
+    fn test_client_provider_defaults() {
+        let defaults = ProviderPreferences::new().with_data_collection(crate::config::DataCollection::Deny);
+        let plain = CompletionRequest::new("m", vec![]).with_routing(&ProviderPreferences::default());
+        assert!(serde_json::to_value(&plain).unwrap().get("provider").is_none());
+
+        let filled = CompletionRequest::new("m", vec![]).with_routing(&defaults);
+        assert_eq!(filled.provider, Some(defaults.clone()));
+
+        let own = ProviderPreferences::new().with_only(vec!["azure".to_string()]);
+        let kept = CompletionRequest::new("m", vec![]).with_provider(own.clone()).with_routing(&defaults);
+        assert_eq!(kept.provider, Some(own));
+    }
+
+    #[test]
+    fn test_rate_limit_reset_formats() {
+        let now = Utc::now();
+        assert_eq!(parse_reset("1760000000000").unwrap().timestamp(), 1_760_000_000);
+        assert_eq!(parse_reset("1760000000").unwrap().timestamp(), 1_760_000_000);
+        let in_six_minutes = (parse_reset("6m0s").unwrap() - now).num_seconds();
+        assert!((359..=361).contains(&in_six_minutes), "{}", in_six_minutes);
+        let in_a_moment = (parse_reset("1.5s").unwrap() - now).num_milliseconds();
+        assert!((1400..=1600).contains(&in_a_moment), "{}", in_a_moment);
+        assert!(parse_reset("250ms").is_some());
+        assert!(parse_reset("soon").is_none());
+        assert!(parse_reset("5s later").is_none());
+    }
+
+    #[test]
+    fn test_rate_limit_reset_relative_seconds() {
+        let now = Utc::now();
+        let in_a_minute = (parse_reset("60").unwrap() - now).num_seconds();
+        assert!((59..=61).contains(&in_a_minute), "{}", in_a_minute);
+        assert!((parse_reset("0").unwrap() - now).num_seconds() <= 1);
+        assert_eq!(parse_reset("1000000000").unwrap().timestamp(), 1_000_000_000);
+    }
+
+    #[test]
+    fn test_rate_limit_reset_rejects_hostile_values() {
+        assert!(parse_reset("-1").is_none());
+        assert!(parse_reset(&i64::MIN.to_string()).is_none());
+        assert!(parse_reset(&i64::MAX.to_string()).is_none());
+        assert!(parse_reset("99999999999999h").is_none());
+        assert!(parse_reset("99999999999999999999999999s").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_headers_on_response() {
+        let body = r#"{"id":"r1","model":"m","choices":[{"index":0,"message":{"role":"assistant","content":"hi"},"finish_reason":"stop"}],"usage":{"prompt_tokens":1,"completion_tokens":1,"total_tokens":2}}"#;
+        let reset = Utc::now().timestamp_millis() + 30_000;
+        let headers = format!(
+            "X-RateLimit-Limit: 200\r\nX-RateLimit-Remaining: 0\r\nX-RateLimit-Reset: {}\r\nx-ratelimit-remaining-tokens: 9000\r\n",
+            reset
+        );
+        let (url, _) = serve(vec![http_response("200 OK", &headers, body)]).await;
+        let response = test_client(&url)
+            .complete(CompletionRequest::new("m", vec![Message::user("hi")]))
+            .await
+            .unwrap();
+
+        let limit = response.rate_limit.unwrap();
+        assert_eq!(limit.limit_requests, Some(200));
+        assert_eq!(limit.remaining_requests, Some(0));
+        assert_eq!(limit.remaining_tokens, Some(9000));
+        assert_eq!(limit.reset_at.unwrap().timestamp_millis(), reset);
+        assert!(limit.is_exhausted());
+        let backoff = limit.backoff().unwrap();
+        assert!(backoff > Duration::from_secs(25) && backoff <= Duration::from_secs(30));
+
+        let plenty = RateLimitInfo { remaining_requests: Some(5), ..limit };
+        assert_eq!(plenty.backoff(), None);
+    }
+
+    #[tokio::test]
+    async fn test_key_info() {
+        let body = r#"{"data":{"label":"sk-or-v1-abc...","usage":12.5,"limit":20,"limit_remaining":7.5,"is_free_tier":false,"rate_limit":{"requests":10,"interval":"10s"}}}"#;
+        let (url, _) = serve(vec![http_response("200 OK", "", body)]).await;
+        let info = test_client(&url).key_info().await.unwrap();
+        assert_eq!(info.usage, 12.5);
+        assert_eq!(info.limit, Some(20.0));
+        assert_eq!(info.limit_remaining, Some(7.5));
+        assert!(!info.is_free_tier);
+    }
 }
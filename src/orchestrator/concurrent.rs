@@ -5,16 +5,31 @@
 
 use crate::error::Result;
 use crate::Agent;
+use crate::tracing_ext::sub_agent_span;
 use crate::orchestrator::config::AggregationStrategy;
-use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorResult, AgentOutput};
+use crate::orchestrator::pattern::{
+    keep_partial, plan_runs, rank_by_confidence, render_synthesis_prompt, OrchestratorPattern, OrchestratorPlan, OrchestratorResult, AgentOutput,
+};
+use crate::types::AgentId;
 use async_trait::async_trait;
+use std::sync::Arc;
 use std::time::Instant;
-use futures::future::join_all;
+use futures::stream::{FuturesUnordered, StreamExt};
+use tokio::sync::{mpsc, Semaphore};
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+/// Synthesis prompt used when none is configured
+const DEFAULT_SYNTHESIS_PROMPT: &str =
+    "Original task: {question}\n\nAgent outputs:\n{inputs}\n\nSynthesize these into a single comprehensive answer:";
 
 /// Concurrent orchestrator - parallel execution with aggregation
 pub struct ConcurrentOrchestrator {
     agents: Vec<Agent>,
     aggregation: AggregationStrategy,
+    synthesizer: Option<Agent>,
+    synthesis_prompt: Option<String>,
+    limit: Option<Arc<Semaphore>>,
 }
 
 impl ConcurrentOrchestrator {
@@ -23,6 +38,9 @@ impl ConcurrentOrchestrator {
         Self {
             agents,
             aggregation: AggregationStrategy::Concatenate,
+            synthesizer: None,
+            synthesis_prompt: None,
+            limit: None,
         }
     }
 
@@ -32,6 +50,45 @@ impl ConcurrentOrchestrator {
         self
     }
 
+    /// Agent that combines the outputs under [`AggregationStrategy::Synthesize`]
+    ///
+    /// Without one, synthesis falls back to joining the outputs.
+    pub fn with_synthesizer(mut self, synthesizer: Agent) -> Self {
+        self.synthesizer = Some(synthesizer);
+        self
+    }
+
+    /// Set the synthesizer's prompt template
+    ///
+    /// `{inputs}` receives the agent outputs and `{question}` the original input.
+    pub fn with_synthesis_prompt(mut self, template: impl Into<String>) -> Self {
+        self.synthesis_prompt = Some(template.into());
+        self
+    }
+
+    /// Run at most `max` agents at once; the rest wait for a free slot
+    pub fn with_max_concurrency(self, max: usize) -> Self {
+        self.with_concurrency_limit(Arc::new(Semaphore::new(max.max(1))))
+    }
+
+    /// Take a permit from `limit` for every agent run, including the synthesizer
+    ///
+    /// Share one semaphore between orchestrators and a
+    /// [`BackgroundExecutor`](crate::BackgroundExecutor) to cap model calls
+    /// across all of them.
+    pub fn with_concurrency_limit(mut self, limit: Arc<Semaphore>) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Wait for a concurrency permit, if a limit is set
+    async fn permit(&self) -> Option<tokio::sync::SemaphorePermit<'_>> {
+        match &self.limit {
+            Some(limit) => limit.acquire().await.ok(),
+            None => None,
+        }
+    }
+
     /// Aggregate outputs based on strategy
     fn aggregate(&self, outputs: &[AgentOutput]) -> String {
         match &self.aggregation {
@@ -41,6 +98,13 @@ impl ConcurrentOrchestrator {
                     .collect::<Vec<_>>()
                     .join("\n\n---\n\n")
             }
+            AggregationStrategy::RankByConfidence => {
+                rank_by_confidence(outputs)
+                    .iter()
+                    .map(|o| format!("## {}\n\n{}", o.agent_name, o.content))
+                    .collect::<Vec<_>>()
+                    .join("\n\n---\n\n")
+            }
             AggregationStrategy::First => {
                 outputs.first().map(|o| o.content.clone()).unwrap_or_default()
             }
@@ -71,56 +135,137 @@ impl ConcurrentOrchestrator {
     }
 }
 
-#[async_trait]
-impl OrchestratorPattern for ConcurrentOrchestrator {
-    async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+impl ConcurrentOrchestrator {
+    /// Execute, sending each agent's output as soon as it finishes
+    ///
+    /// Outputs arrive on `tx` in completion order, tagged with the agent's ID;
+    /// agents that fail are logged and skipped. The returned result aggregates
+    /// every output in agent order, as [`execute`](OrchestratorPattern::execute) does.
+    #[tracing::instrument(name = "orchestration", skip_all, fields(pattern = "concurrent"))]
+    pub async fn execute_streaming(
+        &self,
+        input: &str,
+        tx: mpsc::Sender<(AgentId, AgentOutput)>,
+    ) -> Result<OrchestratorResult> {
+        self.run(input, CancellationToken::new(), Some(&tx)).await
+    }
+
+    async fn run(
+        &self,
+        input: &str,
+        token: CancellationToken,
+        tx: Option<&mpsc::Sender<(AgentId, AgentOutput)>>,
+    ) -> Result<OrchestratorResult> {
         let start = Instant::now();
-        
-        // Create futures for all agents
-        let futures: Vec<_> = self.agents.iter()
-            .map(|agent| {
-                let input = input.to_string();
+
+        // Run all agents in parallel, taking outputs as they finish
+        let mut pending: FuturesUnordered<_> = self.agents.iter()
+            .enumerate()
+            .map(|(index, agent)| {
+                let token = token.clone();
                 async move {
+                    let _permit = self.permit().await;
                     let agent_start = Instant::now();
-                    let result = agent.react_loop(&input).await;
-                    (agent.name.clone(), result, agent_start.elapsed().as_millis() as u64)
+                    let result = agent
+                        .react_loop_with_cancel(input, token)
+                        .instrument(sub_agent_span("worker", &agent.name))
+                        .await;
+                    (index, agent, result, agent_start.elapsed().as_millis() as u64)
                 }
             })
             .collect();
 
-        // Execute all in parallel
-        let results = join_all(futures).await;
-
-        // Collect outputs
-        let mut agent_outputs = Vec::new();
-        let mut result = OrchestratorResult::new("", "concurrent");
-
-        for (name, output_result, time_ms) in results {
-            match output_result {
+        let mut finished = Vec::new();
+        while let Some((index, agent, output_result, time_ms)) = pending.next().await {
+            // Cancelled agents contribute what they produced so far
+            match keep_partial(output_result) {
                 Ok(output) => {
                     let agent_output = AgentOutput {
-                        agent_name: name,
+                        agent_name: agent.name.clone(),
                         content: output.content,
                         loops_executed: output.trace.iteration_count(),
                         execution_time_ms: time_ms,
                     };
-                    agent_outputs.push(agent_output.clone());
-                    result = result.with_agent_output(agent_output);
+                    if let Some(tx) = tx {
+                        // A dropped receiver only stops the updates, not the run
+                        let _ = tx.send((agent.id, agent_output.clone())).await;
+                    }
+                    finished.push((index, agent_output));
                 }
                 Err(e) => {
-                    tracing::warn!("Agent {} failed: {}", name, e);
+                    tracing::warn!("Agent {} failed: {}", agent.name, e);
                 }
             }
         }
+        finished.sort_by_key(|(index, _)| *index);
+
+        // Collect outputs
+        let mut agent_outputs = Vec::new();
+        let mut result = OrchestratorResult::new("", "concurrent");
+        for (_, agent_output) in finished {
+            agent_outputs.push(agent_output.clone());
+            result = result.with_agent_output(agent_output);
+        }
 
         // Aggregate results
         result.content = self.aggregate(&agent_outputs);
+        if let (AggregationStrategy::Synthesize, Some(synthesizer)) = (&self.aggregation, &self.synthesizer) {
+            if !token.is_cancelled() && !agent_outputs.is_empty() {
+                let inputs = agent_outputs
+                    .iter()
+                    .map(|o| format!("### {}\n{}", o.agent_name, o.content))
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+                let template = self.synthesis_prompt.as_deref().unwrap_or(DEFAULT_SYNTHESIS_PROMPT);
+                let _permit = self.permit().await;
+                let synthesis_start = Instant::now();
+                match synthesizer
+                    .react_loop_with_cancel(&render_synthesis_prompt(template, &inputs, input), token.clone())
+                    .instrument(sub_agent_span("synthesizer", &synthesizer.name))
+                    .await
+                {
+                    Ok(output) => {
+                        result.content = output.content.clone();
+                        result = result.with_agent_output(AgentOutput {
+                            agent_name: format!("{} (synthesis)", synthesizer.name),
+                            content: output.content,
+                            loops_executed: output.trace.iteration_count(),
+                            execution_time_ms: synthesis_start.elapsed().as_millis() as u64,
+                        });
+                    }
+                    Err(e) => tracing::warn!("Synthesizer {} failed, joining outputs instead: {}", synthesizer.name, e),
+                }
+            }
+        }
         result = result
             .with_time(start.elapsed().as_millis() as u64)
             .with_extra("aggregation", serde_json::json!(format!("{:?}", self.aggregation)));
+        if token.is_cancelled() {
+            result = result.with_cancelled();
+        }
 
         Ok(result)
     }
+}
+
+#[async_trait]
+impl OrchestratorPattern for ConcurrentOrchestrator {
+    async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+        self.execute_with_cancel(input, CancellationToken::new()).await
+    }
+
+    #[tracing::instrument(name = "orchestration", skip_all, fields(pattern = "concurrent"))]
+    async fn execute_with_cancel(&self, input: &str, token: CancellationToken) -> Result<OrchestratorResult> {
+        self.run(input, token, None).await
+    }
+
+    async fn plan(&self, input: &str) -> OrchestratorPlan {
+        let synthesizer = self
+            .synthesizer
+            .iter()
+            .filter(|_| matches!(self.aggregation, AggregationStrategy::Synthesize));
+        plan_runs(self.agents.iter().chain(synthesizer).collect(), input).await
+    }
 
     fn pattern_type(&self) -> &str {
         "concurrent"
@@ -130,3 +275,100 @@ impl OrchestratorPattern for ConcurrentOrchestrator {
         self.agents.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockLlmClient;
+    use std::time::Duration;
+
+    /// Agent whose client answers after a delay, or fails when it has no reply
+    fn agent(name: &str, reply: Option<&str>, delay_ms: u64) -> Agent {
+        let client = MockLlmClient::new().with_latency(Duration::from_millis(delay_ms));
+        let client = match reply {
+            Some(reply) => client.with_fallback_reply(reply),
+            None => client.with_error("model unavailable"),
+        };
+        Agent::builder()
+            .name(name)
+            .system_prompt("You are a test agent.")
+            .client(Arc::new(client))
+            .build()
+            .unwrap()
+    }
+
+    /// Client that answers after 20ms, so overlapping requests show up in its peak
+    fn counting_client() -> Arc<MockLlmClient> {
+        Arc::new(
+            MockLlmClient::new()
+                .with_fallback_reply("Final answer: done")
+                .with_latency(Duration::from_millis(20)),
+        )
+    }
+
+    fn counted_agents(client: &Arc<MockLlmClient>, count: usize) -> Vec<Agent> {
+        (0..count)
+            .map(|i| {
+                Agent::builder()
+                    .name(format!("Agent {}", i))
+                    .system_prompt("You are a test agent.")
+                    .client(client.clone())
+                    .build()
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrency_caps_model_calls() {
+        let client = counting_client();
+        let orchestrator = ConcurrentOrchestrator::new(counted_agents(&client, 10)).with_max_concurrency(2);
+
+        let result = orchestrator.execute("go").await.unwrap();
+        assert_eq!(result.agent_outputs.len(), 10);
+        assert_eq!(client.request_count(), 10);
+        assert_eq!(client.peak_in_flight(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_concurrency_limit_shared_with_background_executor() {
+        let client = counting_client();
+        let limit = Arc::new(Semaphore::new(2));
+        let orchestrator = ConcurrentOrchestrator::new(counted_agents(&client, 4)).with_concurrency_limit(limit.clone());
+        let executor = crate::BackgroundExecutor::new().with_concurrency_limit(limit);
+
+        let batch = counted_agents(&client, 4)
+            .into_iter()
+            .map(|agent| (Arc::new(agent), "go".to_string()))
+            .collect();
+        let run_ids = executor.execute_batch(batch).await.unwrap();
+        let result = orchestrator.execute("go").await.unwrap();
+        let runs = executor.wait_for_all(run_ids).await;
+
+        assert_eq!(result.agent_outputs.len(), 4);
+        assert!(runs.iter().all(|r| r.is_ok()));
+        assert_eq!(client.request_count(), 8);
+        assert!(client.peak_in_flight() <= 2);
+    }
+
+    #[tokio::test]
+    async fn test_execute_streaming_emits_in_completion_order() {
+        let slow = agent("Slow", Some("Final answer: slow"), 150);
+        let fast = agent("Fast", Some("Final answer: fast"), 10);
+        let broken = agent("Broken", None, 0);
+        let fast_id = fast.id;
+        let orchestrator = ConcurrentOrchestrator::new(vec![slow, broken, fast]);
+
+        let (tx, mut rx) = mpsc::channel(8);
+        let result = orchestrator.execute_streaming("go", tx).await.unwrap();
+
+        let (first_id, first) = rx.recv().await.unwrap();
+        assert_eq!(first_id, fast_id);
+        assert_eq!(first.content, "fast");
+        assert_eq!(rx.recv().await.unwrap().1.content, "slow");
+        assert!(rx.recv().await.is_none());
+
+        assert_eq!(result.agent_outputs.len(), 2);
+        assert!(result.content.find("slow").unwrap() < result.content.find("fast").unwrap());
+    }
+}
@@ -4,6 +4,15 @@
 //! via YAML templates with dynamic agent instantiation.
 
 use crate::error::{Error, Result};
+use crate::handoffs::HandoffStrategy;
+use crate::llm_client::ClientRegistry;
+use crate::orchestrator::consensus::{ClusteringStrategy, TieBreak};
+use crate::orchestrator::debate::DebateOrchestrator;
+use crate::orchestrator::pattern::{validate_synthesis_template, OrchestratorPattern};
+use crate::orchestrator::{
+    ConcurrentOrchestrator, ConsensusOrchestrator, HierarchicalOrchestrator, PipelineOrchestrator,
+    RouterOrchestrator, SequentialOrchestrator,
+};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::Path;
@@ -11,7 +20,7 @@ use std::path::Path;
 /// Top-level orchestrator configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrchestratorConfig {
-    /// Pattern type (sequential, concurrent, hierarchical, debate, router, consensus)
+    /// Pattern type (sequential, concurrent, hierarchical, debate, router, consensus, pipeline)
     pub pattern: PatternType,
     /// Pattern-specific configuration
     #[serde(flatten)]
@@ -19,6 +28,14 @@ pub struct OrchestratorConfig {
     /// Optional tool tags to load
     #[serde(default)]
     pub tool_tags: Vec<String>,
+    /// How the sequential and hierarchical patterns pick the agent for each handoff
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handoff_strategy: Option<HandoffStrategy>,
+    /// Prompt for the synthesizing agent, with `{inputs}` and `{question}` placeholders
+    ///
+    /// Each pattern uses its built-in prompt when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub synthesis_prompt_template: Option<String>,
 }
 
 /// Supported pattern types
@@ -31,6 +48,8 @@ pub enum PatternType {
     Debate,
     Router,
     Consensus,
+    /// Patterns chained as stages
+    Pipeline,
 }
 
 /// Pattern-specific configuration variants
@@ -38,10 +57,18 @@ pub enum PatternType {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum PatternSpecificConfig {
+    /// Pipeline pattern whose stages are full pattern configurations
+    Pipeline {
+        /// Stages run in order, each with its own `pattern` block
+        stages: Vec<OrchestratorConfig>,
+    },
     /// Hierarchical pattern with lead and subagents
     Hierarchical {
         lead_agent: AgentConfig,
         subagents: SubagentConfig,
+        /// How subagent outputs are combined before the lead's synthesis
+        #[serde(default)]
+        aggregation: Option<AggregationStrategy>,
     },
     /// Debate pattern with pro/con and synthesizer
     Debate {
@@ -55,17 +82,41 @@ pub enum PatternSpecificConfig {
     Router {
         router_agent: AgentConfig,
         specialists: HashMap<String, AgentConfig>,
+        /// Optional agent for requests that match no specialist
+        #[serde(default)]
+        default_agent: Option<AgentConfig>,
     },
     /// Consensus pattern with agents and threshold (must come before AgentList!)
     Consensus {
         agents: Vec<AgentConfig>,
         threshold: f64,  // Required field to differentiate from AgentList
+        /// How equivalent answers are grouped before voting
+        #[serde(default)]
+        clustering: ClusteringStrategy,
+        /// Optional agent that groups answers instead of `clustering`
+        #[serde(default)]
+        judge: Option<AgentConfig>,
+        /// How ties between equally weighted positions are broken
+        #[serde(default)]
+        tie_break: TieBreak,
+        /// Agent that picks between tied positions (required for `tie_break: synthesizer`)
+        #[serde(default)]
+        synthesizer: Option<AgentConfig>,
     },
     /// Sequential or concurrent patterns with agent list (last - catch-all for agents array)
     AgentList {
         agents: Vec<AgentConfig>,
         #[serde(default)]
         aggregation: Option<AggregationStrategy>,
+        /// Agent that combines concurrent outputs under `aggregation: synthesize`
+        #[serde(default)]
+        synthesizer: Option<AgentConfig>,
+        /// End a sequential pipeline after the first output containing this keyword
+        #[serde(default)]
+        stop_keyword: Option<String>,
+        /// Run at most this many concurrent agents at once
+        #[serde(default)]
+        max_concurrency: Option<usize>,
     },
 }
 
@@ -90,19 +141,50 @@ pub struct AgentConfig {
     /// Optional tool tags to load for this agent
     #[serde(default)]
     pub tool_tags: Vec<String>,
+    /// Name of the registered LLM client to use (registry default if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    /// Capability tags advertised for routing and handoffs
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// Weight of this agent's vote in consensus patterns (1.0 if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vote_weight: Option<f32>,
+    /// Limit on each model call, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turn_timeout_secs: Option<u64>,
+    /// Limit on each tool call, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_timeout_secs: Option<u64>,
 }
 
 impl AgentConfig {
     /// Build an Agent from this configuration
     pub fn build(&self, client: std::sync::Arc<dyn crate::llm_client::LlmClient>) -> crate::error::Result<crate::Agent> {
-        crate::Agent::builder()
+        let capabilities: Vec<&str> = self.capabilities.iter().map(String::as_str).collect();
+        let mut builder = crate::Agent::builder()
             .name(&self.name)
             .model(&self.model)
             .system_prompt(&self.system_prompt)
             .max_loops(self.max_loops as u32)
             .temperature(self.temperature)
-            .client(client)
-            .build()
+            .capabilities(&capabilities)
+            .client(client);
+        if let Some(secs) = self.turn_timeout_secs {
+            builder = builder.turn_timeout(std::time::Duration::from_secs(secs));
+        }
+        if let Some(secs) = self.tool_timeout_secs {
+            builder = builder.tool_timeout(std::time::Duration::from_secs(secs));
+        }
+        builder.build()
+    }
+
+    /// Build an Agent, resolving its client by name from a registry
+    pub fn build_with_registry(&self, registry: &ClientRegistry) -> Result<crate::Agent> {
+        let client = registry.resolve(self.client.as_deref()).map_err(|e| {
+            Error::config(format!("Agent '{}': {}", self.name, e))
+        })?;
+        self.build(client)
     }
 }
 
@@ -127,6 +209,18 @@ pub struct SubagentConfig {
     /// Optional tool tags
     #[serde(default)]
     pub tool_tags: Vec<String>,
+    /// Name of the registered LLM client for all subagents
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client: Option<String>,
+    /// Capability tags advertised by all subagents
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// Limit on each subagent model call, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub turn_timeout_secs: Option<u64>,
+    /// Limit on each subagent tool call, in seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_timeout_secs: Option<u64>,
 }
 
 impl SubagentConfig {
@@ -140,12 +234,17 @@ impl SubagentConfig {
                 max_loops: self.max_loops,
                 temperature: self.temperature,
                 tool_tags: self.tool_tags.clone(),
+                client: self.client.clone(),
+                capabilities: self.capabilities.clone(),
+                vote_weight: None,
+                turn_timeout_secs: self.turn_timeout_secs,
+                tool_timeout_secs: self.tool_timeout_secs,
             })
             .collect()
     }
 }
 
-/// Aggregation strategy for concurrent patterns
+/// Aggregation strategy for the outputs of agents run in parallel
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum AggregationStrategy {
@@ -160,13 +259,30 @@ pub enum AggregationStrategy {
     Longest,
     /// Custom aggregation via synthesizer agent
     Synthesize,
+    /// Concatenate, most confident first, by each output's trailing `CONFIDENCE: 0.x` line
+    RankByConfidence,
 }
 
 impl OrchestratorConfig {
     /// Load configuration from YAML string
     pub fn from_yaml(yaml: &str) -> Result<Self> {
-        serde_yaml::from_str(yaml)
-            .map_err(|e| Error::Config(format!("Failed to parse YAML: {}", e)))
+        let config: Self = serde_yaml::from_str(yaml)
+            .map_err(|e| Error::Config(format!("Failed to parse YAML: {}", e)))?;
+        config.validate_templates()?;
+        Ok(config)
+    }
+
+    /// Check the synthesis templates of this config and any pipeline stages
+    fn validate_templates(&self) -> Result<()> {
+        if let Some(template) = &self.synthesis_prompt_template {
+            validate_synthesis_template(template)?;
+        }
+        if let PatternSpecificConfig::Pipeline { stages } = &self.pattern_config {
+            for stage in stages {
+                stage.validate_templates()?;
+            }
+        }
+        Ok(())
     }
 
     /// Load configuration from YAML file
@@ -180,6 +296,141 @@ impl OrchestratorConfig {
     pub fn pattern_type(&self) -> &PatternType {
         &self.pattern
     }
+
+    /// Build the orchestrator, resolving each agent's client from a registry
+    ///
+    /// Tool tags are not loaded here; wire tools manually with
+    /// [`AgentConfig::build_with_registry`] when agents need them.
+    pub fn build(&self, registry: &ClientRegistry) -> Result<Box<dyn OrchestratorPattern>> {
+        let build_all = |agents: &[AgentConfig]| -> Result<Vec<crate::Agent>> {
+            agents.iter().map(|a| a.build_with_registry(registry)).collect()
+        };
+
+        if let Some(template) = &self.synthesis_prompt_template {
+            validate_synthesis_template(template)?;
+        }
+        let template = self.synthesis_prompt_template.clone();
+
+        let orchestrator: Box<dyn OrchestratorPattern> = match (&self.pattern, &self.pattern_config) {
+            (PatternType::Sequential, PatternSpecificConfig::AgentList { agents, stop_keyword, .. }) => {
+                let mut sequential = SequentialOrchestrator::new(build_all(agents)?);
+                if let Some(keyword) = stop_keyword {
+                    sequential = sequential.with_stop_keyword(keyword.clone());
+                }
+                if let Some(template) = template {
+                    sequential = sequential.with_synthesis_prompt(template);
+                }
+                if let Some(strategy) = &self.handoff_strategy {
+                    sequential = sequential.with_handoff_strategy(strategy.clone());
+                }
+                Box::new(sequential)
+            }
+            (PatternType::Concurrent, PatternSpecificConfig::AgentList { agents, aggregation, synthesizer, max_concurrency, .. }) => {
+                let mut concurrent = ConcurrentOrchestrator::new(build_all(agents)?)
+                    .with_aggregation(aggregation.clone().unwrap_or_default());
+                if let Some(max) = max_concurrency {
+                    concurrent = concurrent.with_max_concurrency(*max);
+                }
+                if let Some(synthesizer) = synthesizer {
+                    concurrent = concurrent.with_synthesizer(synthesizer.build_with_registry(registry)?);
+                }
+                if let Some(template) = template {
+                    concurrent = concurrent.with_synthesis_prompt(template);
+                }
+                Box::new(concurrent)
+            }
+            (PatternType::Hierarchical, PatternSpecificConfig::Hierarchical { lead_agent, subagents, aggregation }) => {
+                let mut hierarchical = HierarchicalOrchestrator::new(
+                    lead_agent.build_with_registry(registry)?,
+                    build_all(&subagents.generate_agents())?,
+                )
+                .with_aggregation(aggregation.clone().unwrap_or_default());
+                if let Some(template) = template {
+                    hierarchical = hierarchical.with_synthesis_prompt(template);
+                }
+                if let Some(strategy) = &self.handoff_strategy {
+                    hierarchical = hierarchical.with_handoff_strategy(strategy.clone());
+                }
+                Box::new(hierarchical)
+            }
+            (PatternType::Debate, PatternSpecificConfig::Debate { pro_agent, con_agent, synthesizer, rounds }) => {
+                let mut debate = DebateOrchestrator::new(
+                    pro_agent.build_with_registry(registry)?,
+                    con_agent.build_with_registry(registry)?,
+                    synthesizer.build_with_registry(registry)?,
+                )
+                .with_rounds(*rounds);
+                if let Some(template) = template {
+                    debate = debate.with_synthesis_prompt(template);
+                }
+                Box::new(debate)
+            }
+            (PatternType::Router, PatternSpecificConfig::Router { router_agent, specialists, default_agent }) => {
+                // YAML mappings are unordered; register specialists by domain so routing is stable
+                let mut specialists = specialists
+                    .iter()
+                    .map(|(domain, cfg)| Ok((domain.clone(), cfg.build_with_registry(registry)?)))
+                    .collect::<Result<Vec<_>>>()?;
+                specialists.sort_by(|(a, _), (b, _)| a.cmp(b));
+                let mut router =
+                    RouterOrchestrator::new(router_agent.build_with_registry(registry)?).with_specialists(specialists);
+                if let Some(default_agent) = default_agent {
+                    router = router.with_default_agent(default_agent.build_with_registry(registry)?);
+                }
+                Box::new(router)
+            }
+            (
+                PatternType::Consensus,
+                PatternSpecificConfig::Consensus { agents, threshold, clustering, judge, tie_break, synthesizer },
+            ) => {
+                let weights = agents.iter().map(|a| a.vote_weight.unwrap_or(1.0) as f64).collect();
+                let mut consensus = ConsensusOrchestrator::new(build_all(agents)?)
+                    .with_threshold(*threshold)
+                    .with_clustering(*clustering)
+                    .with_weights(weights)
+                    .with_tie_break(*tie_break);
+                if let Some(judge) = judge {
+                    consensus = consensus.with_judge(judge.build_with_registry(registry)?);
+                }
+                match synthesizer {
+                    Some(synthesizer) => {
+                        consensus = consensus.with_synthesizer(synthesizer.build_with_registry(registry)?);
+                    }
+                    None if *tie_break == TieBreak::Synthesizer => {
+                        return Err(Error::config("Consensus tie_break 'synthesizer' requires a synthesizer agent"));
+                    }
+                    None => {}
+                }
+                if let Some(template) = template {
+                    consensus = consensus.with_synthesis_prompt(template);
+                }
+                Box::new(consensus)
+            }
+            (PatternType::Pipeline, PatternSpecificConfig::Pipeline { stages }) => {
+                if stages.is_empty() {
+                    return Err(Error::config("Pipeline needs at least one stage"));
+                }
+                let stages = stages
+                    .iter()
+                    .enumerate()
+                    .map(|(i, stage)| {
+                        stage
+                            .build(registry)
+                            .map_err(|e| Error::config(format!("Pipeline stage {}: {}", i + 1, e)))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Box::new(PipelineOrchestrator::new(stages))
+            }
+            (pattern, _) => {
+                return Err(Error::config(format!(
+                    "Pattern {:?} does not match the provided agent configuration",
+                    pattern
+                )))
+            }
+        };
+
+        Ok(orchestrator)
+    }
 }
 
 #[cfg(test)]
@@ -216,9 +467,223 @@ subagents:
   count: 3
   model: "anthropic/claude-haiku"
   system_prompt_template: "You are Analyst {index}."
+aggregation: rank_by_confidence
 "#;
         let config = OrchestratorConfig::from_yaml(yaml).unwrap();
         assert_eq!(config.pattern, PatternType::Hierarchical);
+        assert!(matches!(
+            config.pattern_config,
+            PatternSpecificConfig::Hierarchical {
+                aggregation: Some(AggregationStrategy::RankByConfidence),
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_parse_consensus_template() {
+        let config = OrchestratorConfig::from_yaml(include_str!("templates/consensus.yaml")).unwrap();
+        assert!(matches!(
+            config.pattern_config,
+            PatternSpecificConfig::Consensus {
+                clustering: ClusteringStrategy::Similarity { .. },
+                judge: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_consensus_vote_weights() {
+        let yaml = r#"
+pattern: consensus
+threshold: 0.5
+tie_break: synthesizer
+agents:
+  - name: "Expert"
+    model: "anthropic/claude-opus-4.5"
+    system_prompt: "Vote."
+    vote_weight: 2.5
+  - name: "Novice"
+    model: "anthropic/claude-haiku"
+    system_prompt: "Vote."
+"#;
+        let config = OrchestratorConfig::from_yaml(yaml).unwrap();
+        match &config.pattern_config {
+            PatternSpecificConfig::Consensus { agents, tie_break, .. } => {
+                assert_eq!(agents[0].vote_weight, Some(2.5));
+                assert_eq!(agents[1].vote_weight, None);
+                assert_eq!(*tie_break, TieBreak::Synthesizer);
+            }
+            other => panic!("unexpected config: {:?}", other),
+        }
+
+        // A synthesizer tie break without a synthesizer agent is rejected
+        let local: std::sync::Arc<dyn crate::llm_client::LlmClient> = std::sync::Arc::new(
+            crate::vllm::VllmClient::new(crate::vllm::VllmConfig::new("http://localhost:8000")).unwrap(),
+        );
+        let err = config.build(&ClientRegistry::new().with_client("local", local)).err().unwrap();
+        assert!(err.to_string().contains("synthesizer"));
+    }
+
+    #[test]
+    fn test_parse_handoff_strategy() {
+        let yaml = r#"
+pattern: sequential
+handoff_strategy:
+  type: by_capability
+  tag: "network"
+agents:
+  - name: "Scanner"
+    model: "anthropic/claude-sonnet-4"
+    system_prompt: "Scan the network."
+"#;
+        let config = OrchestratorConfig::from_yaml(yaml).unwrap();
+        assert!(matches!(
+            config.handoff_strategy,
+            Some(HandoffStrategy::ByCapability { ref tag }) if tag == "network"
+        ));
+        assert!(matches!(config.pattern_config, PatternSpecificConfig::AgentList { .. }));
+    }
+
+    #[test]
+    fn test_parse_stop_keyword() {
+        let yaml = r#"
+pattern: sequential
+stop_keyword: "RESOLVED"
+agents:
+  - name: "Triage"
+    model: "anthropic/claude-haiku"
+    system_prompt: "Triage the ticket."
+"#;
+        let config = OrchestratorConfig::from_yaml(yaml).unwrap();
+        assert!(matches!(
+            config.pattern_config,
+            PatternSpecificConfig::AgentList { stop_keyword: Some(ref keyword), .. } if keyword == "RESOLVED"
+        ));
+    }
+
+    #[test]
+    fn test_synthesis_prompt_template() {
+        let yaml = |template: &str| {
+            format!(
+                r#"
+pattern: concurrent
+aggregation: synthesize
+synthesis_prompt_template: "{}"
+agents:
+  - name: "Scanner"
+    model: "anthropic/claude-sonnet-4"
+    system_prompt: "Scan the network."
+synthesizer:
+  name: "Editor"
+  model: "anthropic/claude-sonnet-4"
+  system_prompt: "Merge reports."
+"#,
+                template
+            )
+        };
+
+        let config = OrchestratorConfig::from_yaml(&yaml("Q: {question}\\n{inputs}")).unwrap();
+        assert_eq!(config.synthesis_prompt_template.as_deref(), Some("Q: {question}\n{inputs}"));
+        assert!(matches!(
+            config.pattern_config,
+            PatternSpecificConfig::AgentList { synthesizer: Some(_), .. }
+        ));
+
+        let err = OrchestratorConfig::from_yaml(&yaml("Summarize {inputs}")).unwrap_err();
+        assert!(err.to_string().contains("{question}"));
+        assert!(OrchestratorConfig::from_yaml(&yaml("Summarize")).is_err());
+
+        assert_eq!(
+            crate::orchestrator::pattern::render_synthesis_prompt(
+                "{question} -> {inputs} {other}",
+                "a {question}",
+                "why?"
+            ),
+            "why? -> a {question} {other}"
+        );
+    }
+
+    #[test]
+    fn test_build_resolves_named_clients() {
+        use crate::vllm::{VllmClient, VllmConfig};
+
+        let yaml = r#"
+pattern: sequential
+agents:
+  - name: "Researcher"
+    model: "anthropic/claude-sonnet-4"
+    system_prompt: "Research the topic."
+    client: remote
+  - name: "Writer"
+    model: "qwen"
+    system_prompt: "Write based on research."
+    client: local
+    capabilities: [writing, Summaries]
+"#;
+        let config = OrchestratorConfig::from_yaml(yaml).unwrap();
+        match &config.pattern_config {
+            PatternSpecificConfig::AgentList { agents, .. } => {
+                assert_eq!(agents[1].client.as_deref(), Some("local"));
+            }
+            other => panic!("unexpected config: {:?}", other),
+        }
+
+        let local: std::sync::Arc<dyn crate::llm_client::LlmClient> =
+            std::sync::Arc::new(VllmClient::new(VllmConfig::new("http://localhost:8000")).unwrap());
+        let partial = ClientRegistry::new().with_client("local", local.clone());
+        let err = config.build(&partial).err().unwrap();
+        assert!(err.to_string().contains("remote"));
+
+        let registry = partial.with_client("remote", local);
+        let orchestrator = config.build(&registry).unwrap();
+        assert_eq!(orchestrator.pattern_type(), "sequential");
+        assert_eq!(orchestrator.agent_count(), 2);
+
+        let writer = match &config.pattern_config {
+            PatternSpecificConfig::AgentList { agents, .. } => agents[1].build_with_registry(&registry).unwrap(),
+            _ => unreachable!(),
+        };
+        assert!(writer.has_capability("summaries"));
+    }
+
+    #[test]
+    fn test_parse_pipeline_template() {
+        use crate::vllm::{VllmClient, VllmConfig};
+
+        let config = OrchestratorConfig::from_yaml(include_str!("templates/pipeline.yaml")).unwrap();
+        assert_eq!(config.pattern, PatternType::Pipeline);
+        let stages = match &config.pattern_config {
+            PatternSpecificConfig::Pipeline { stages } => stages,
+            other => panic!("unexpected config: {:?}", other),
+        };
+        assert_eq!(stages.len(), 3);
+        assert_eq!(stages[0].pattern, PatternType::Router);
+        assert_eq!(stages[1].pattern, PatternType::Debate);
+        assert!(matches!(
+            stages[2].pattern_config,
+            PatternSpecificConfig::AgentList { ref agents, .. } if agents.len() == 2
+        ));
+
+        let local: std::sync::Arc<dyn crate::llm_client::LlmClient> =
+            std::sync::Arc::new(VllmClient::new(VllmConfig::new("http://localhost:8000")).unwrap());
+        let orchestrator = config.build(&ClientRegistry::new().with_client("local", local)).unwrap();
+        assert_eq!(orchestrator.pattern_type(), "pipeline");
+        assert_eq!(orchestrator.agent_count(), 3 + 3 + 2);
+
+        // Stage templates are validated like top-level ones
+        let yaml = r#"
+pattern: pipeline
+stages:
+  - pattern: sequential
+    synthesis_prompt_template: "Summarize {inputs}"
+    agents:
+      - name: "Writer"
+        model: "qwen"
+        system_prompt: "Write."
+"#;
+        assert!(OrchestratorConfig::from_yaml(yaml).unwrap_err().to_string().contains("{question}"));
     }
 
     #[test]
@@ -230,11 +695,16 @@ subagents:
             max_loops: 2,
             temperature: 0.5,
             tool_tags: vec![],
+            client: Some("local".to_string()),
+            capabilities: vec!["analysis".to_string()],
+            turn_timeout_secs: Some(30),
+            tool_timeout_secs: None,
         };
         let agents = subconfig.generate_agents();
         assert_eq!(agents.len(), 3);
         assert_eq!(agents[0].system_prompt, "Agent 1 ready.");
         assert_eq!(agents[1].system_prompt, "Agent 2 ready.");
         assert_eq!(agents[2].system_prompt, "Agent 3 ready.");
+        assert_eq!(agents[0].turn_timeout_secs, Some(30));
     }
 }
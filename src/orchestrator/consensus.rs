@@ -1,20 +1,91 @@
 //! Consensus orchestrator pattern
 //!
-//! Multiple agents vote/respond independently, and a majority
-//! voting mechanism determines the final consensus.
+//! Multiple agents vote/respond independently, equivalent answers are
+//! clustered, and a (optionally weighted) majority vote over the clusters
+//! determines the final consensus.
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::Agent;
-use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorResult, AgentOutput};
+use crate::tracing_ext::sub_agent_span;
+use crate::orchestrator::pattern::{
+    plan_runs, render_synthesis_prompt, OrchestratorPattern, OrchestratorPlan, OrchestratorResult, AgentOutput,
+};
 use async_trait::async_trait;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::time::Instant;
 use futures::future::join_all;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 
-/// Consensus orchestrator - majority voting
+/// How free-text answers are grouped before votes are tallied
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClusteringStrategy {
+    /// Map answers to yes/no/uncertain by keyword, else to their first line
+    #[default]
+    Keywords,
+    /// Group answers whose key terms overlap by at least `threshold` (0.0 to 1.0)
+    Similarity {
+        /// Minimum overlap coefficient between two answers' key terms
+        threshold: f64,
+    },
+}
+
+/// How a tie between equally weighted clusters is resolved
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TieBreak {
+    /// The position that was answered first, in agent order
+    #[default]
+    First,
+    /// The position backed by the single highest-weighted agent
+    HighestWeight,
+    /// Ask the synthesizer agent to pick between the tied positions
+    Synthesizer,
+}
+
+/// A group of equivalent answers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AnswerCluster {
+    /// Representative answer for the cluster
+    pub label: String,
+    /// Names of the agents whose answers fell in this cluster
+    pub members: Vec<String>,
+    /// Fraction of the total vote weight in this cluster
+    pub share: f64,
+    /// Summed vote weight of the members
+    #[serde(default)]
+    pub weight: f64,
+}
+
+/// One agent's vote, as recorded in the result's vote breakdown
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Vote {
+    /// Agent name
+    pub agent: String,
+    /// Label of the cluster the agent's answer fell in
+    pub position: String,
+    /// Weight of the agent's vote
+    pub weight: f64,
+}
+
+/// Tie-break prompt used when none is configured
+const DEFAULT_SYNTHESIS_PROMPT: &str = "Question: {question}\n\n\
+     The following positions received equal support. Weigh the reasoning behind \
+     each and decide which one is best supported.\n\n{inputs}\n\n\
+     Reply with only the number of the position you choose.";
+
+/// Consensus orchestrator - majority voting over clustered answers
 pub struct ConsensusOrchestrator {
     agents: Vec<Agent>,
     threshold: f64,
+    clustering: ClusteringStrategy,
+    judge: Option<Agent>,
+    weights: Vec<f64>,
+    tie_break: TieBreak,
+    synthesizer: Option<Agent>,
+    synthesis_prompt: Option<String>,
 }
 
 impl ConsensusOrchestrator {
@@ -23,6 +94,12 @@ impl ConsensusOrchestrator {
         Self {
             agents,
             threshold: 0.66, // 2/3 majority by default
+            clustering: ClusteringStrategy::default(),
+            judge: None,
+            weights: Vec::new(),
+            tie_break: TieBreak::default(),
+            synthesizer: None,
+            synthesis_prompt: None,
         }
     }
 
@@ -32,70 +109,420 @@ impl ConsensusOrchestrator {
         self
     }
 
-    /// Majority vote handoff function
-    fn majority_vote(&self, responses: &[String]) -> (String, f64) {
-        if responses.is_empty() {
-            return (String::new(), 0.0);
+    /// Set how answers are clustered before voting
+    pub fn with_clustering(mut self, clustering: ClusteringStrategy) -> Self {
+        self.clustering = clustering;
+        self
+    }
+
+    /// Ask a judge agent to group equivalent answers
+    ///
+    /// Falls back to the clustering strategy if the judge fails or replies
+    /// with something that is not a valid grouping.
+    pub fn with_judge(mut self, judge: Agent) -> Self {
+        self.judge = Some(judge);
+        self
+    }
+
+    /// Set per-agent vote weights, in agent order
+    ///
+    /// Agents without a weight vote with weight 1.0; negative weights count as 0.
+    pub fn with_weights(mut self, weights: Vec<f64>) -> Self {
+        self.weights = weights.into_iter().map(|w| w.max(0.0)).collect();
+        self
+    }
+
+    /// Set how ties between equally weighted positions are broken
+    pub fn with_tie_break(mut self, tie_break: TieBreak) -> Self {
+        self.tie_break = tie_break;
+        self
+    }
+
+    /// Agent that picks between tied positions under [`TieBreak::Synthesizer`]
+    pub fn with_synthesizer(mut self, synthesizer: Agent) -> Self {
+        self.synthesizer = Some(synthesizer);
+        self
+    }
+
+    /// Set the synthesizer's tie-break prompt template
+    ///
+    /// `{inputs}` receives the numbered tied positions and `{question}` the
+    /// original question. The synthesizer must still reply with a position number.
+    pub fn with_synthesis_prompt(mut self, template: impl Into<String>) -> Self {
+        self.synthesis_prompt = Some(template.into());
+        self
+    }
+
+    /// Vote weight of the agent at `index`
+    fn weight(&self, index: usize) -> f64 {
+        self.weights.get(index).copied().unwrap_or(1.0)
+    }
+
+    /// Determine if consensus was reached
+    fn consensus_reached(&self, percentage: f64) -> bool {
+        percentage >= self.threshold
+    }
+
+    /// Group answers into clusters, heaviest first, with each cluster's answer indices
+    async fn cluster(&self, answers: &[(String, String)], weights: &[f64]) -> Vec<(AnswerCluster, Vec<usize>)> {
+        if answers.is_empty() {
+            return Vec::new();
+        }
+
+        let groups = match &self.judge {
+            Some(judge) => match self.judge_groups(judge, answers).await {
+                Some(groups) => groups,
+                None => {
+                    tracing::warn!("Consensus judge grouping failed, falling back to {:?}", self.clustering);
+                    cluster_answers(answers, self.clustering)
+                }
+            },
+            None => cluster_answers(answers, self.clustering),
+        };
+
+        tally(groups, answers, weights)
+    }
+
+    /// Move the winner of a tie for first place to the front
+    async fn break_tie(
+        &self,
+        clusters: &mut [(AnswerCluster, Vec<usize>)],
+        answers: &[(String, String)],
+        weights: &[f64],
+        question: &str,
+    ) -> Option<TieBreak> {
+        let top = clusters.first()?.0.weight;
+        let tied = clusters.iter().take_while(|(c, _)| (c.weight - top).abs() < WEIGHT_EPSILON).count();
+        if tied < 2 {
+            return None;
+        }
+
+        let winner = match self.tie_break {
+            TieBreak::First => earliest_cluster(&clusters[..tied]),
+            TieBreak::HighestWeight => heaviest_member_cluster(&clusters[..tied], weights),
+            TieBreak::Synthesizer => match &self.synthesizer {
+                Some(synthesizer) => match self.synthesizer_pick(synthesizer, &clusters[..tied], answers, question).await {
+                    Some(winner) => winner,
+                    None => {
+                        tracing::warn!("Consensus synthesizer failed to break the tie, using the first position");
+                        0
+                    }
+                },
+                None => {
+                    tracing::warn!("Consensus tie break is synthesizer but no synthesizer is set, using the first position");
+                    0
+                }
+            },
+        };
+        clusters[..=winner].rotate_right(1);
+        Some(self.tie_break)
+    }
+
+    /// Ask the synthesizer which of the tied positions is best supported
+    async fn synthesizer_pick(
+        &self,
+        synthesizer: &Agent,
+        tied: &[(AnswerCluster, Vec<usize>)],
+        answers: &[(String, String)],
+        question: &str,
+    ) -> Option<usize> {
+        let listing = tied
+            .iter()
+            .enumerate()
+            .map(|(i, (cluster, indices))| {
+                let support = indices
+                    .iter()
+                    .map(|&a| format!("{}:\n{}", answers[a].0, answers[a].1))
+                    .collect::<Vec<_>>()
+                    .join("\n\n");
+                format!("Position {}: {}\n\n{}", i + 1, cluster.label, support)
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let template = self.synthesis_prompt.as_deref().unwrap_or(DEFAULT_SYNTHESIS_PROMPT);
+        let prompt = render_synthesis_prompt(template, &listing, question);
+
+        let output = synthesizer
+            .react_loop(&prompt)
+            .instrument(sub_agent_span("synthesizer", &synthesizer.name))
+            .await
+            .ok()?;
+        parse_choice(&output.content, tied.len())
+    }
+
+    /// Ask the judge for `[{"label": ..., "members": [1, 3]}, ...]`
+    async fn judge_groups(
+        &self,
+        judge: &Agent,
+        answers: &[(String, String)],
+    ) -> Option<Vec<(String, Vec<usize>)>> {
+        let listing = answers
+            .iter()
+            .enumerate()
+            .map(|(i, (_, answer))| format!("Answer {}:\n{}", i + 1, answer))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+        let prompt = format!(
+            "Group the following answers by the position they take, treating answers that \
+             mean the same thing as one group even if worded differently.\n\n{}\n\n\
+             Reply with only a JSON array like \
+             [{{\"label\": \"short statement of the position\", \"members\": [1, 3]}}], \
+             listing every answer number exactly once.",
+            listing
+        );
+
+        let output = judge
+            .react_loop(&prompt)
+            .instrument(sub_agent_span("judge", &judge.name))
+            .await
+            .ok()?;
+        parse_judge_groups(&output.content, answers.len())
+    }
+}
+
+/// Tolerance when comparing summed vote weights
+const WEIGHT_EPSILON: f64 = 1e-9;
+
+/// Turn groups of answer indices into clusters, heaviest first
+///
+/// The sort is stable, so equally weighted clusters keep the order in which
+/// they were first answered.
+fn tally(
+    groups: Vec<(String, Vec<usize>)>,
+    answers: &[(String, String)],
+    weights: &[f64],
+) -> Vec<(AnswerCluster, Vec<usize>)> {
+    let total: f64 = weights.iter().sum();
+    let mut clusters: Vec<(AnswerCluster, Vec<usize>)> = groups
+        .into_iter()
+        .map(|(label, indices)| {
+            let weight: f64 = indices.iter().map(|&i| weights[i]).sum();
+            let cluster = AnswerCluster {
+                label,
+                members: indices.iter().map(|&i| answers[i].0.clone()).collect(),
+                share: if total > 0.0 { weight / total } else { 0.0 },
+                weight,
+            };
+            (cluster, indices)
+        })
+        .collect();
+    clusters.sort_by(|a, b| b.0.weight.total_cmp(&a.0.weight));
+    clusters
+}
+
+/// Index of the cluster holding the earliest answer
+fn earliest_cluster(clusters: &[(AnswerCluster, Vec<usize>)]) -> usize {
+    clusters
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, (_, indices))| indices.iter().min().copied().unwrap_or(usize::MAX))
+        .map(|(i, _)| i)
+        .unwrap_or(0)
+}
+
+/// Index of the cluster holding the single heaviest vote (the earliest on ties)
+fn heaviest_member_cluster(clusters: &[(AnswerCluster, Vec<usize>)], weights: &[f64]) -> usize {
+    let heaviest = |indices: &[usize]| indices.iter().map(|&i| weights[i]).fold(0.0, f64::max);
+    clusters
+        .iter()
+        .enumerate()
+        .fold((0, f64::NEG_INFINITY), |best, (i, (_, indices))| {
+            let weight = heaviest(indices);
+            if weight > best.1 + WEIGHT_EPSILON { (i, weight) } else { best }
+        })
+        .0
+}
+
+/// Parse a 1-based choice out of a reply, returning it 0-based
+fn parse_choice(reply: &str, count: usize) -> Option<usize> {
+    reply
+        .split(|c: char| !c.is_ascii_digit())
+        .find_map(|word| word.parse::<usize>().ok())
+        .filter(|choice| (1..=count).contains(choice))
+        .map(|choice| choice - 1)
+}
+
+/// Parse a judge's grouping, rejecting replies that miss or repeat answers
+fn parse_judge_groups(reply: &str, count: usize) -> Option<Vec<(String, Vec<usize>)>> {
+    #[derive(Deserialize)]
+    struct Group {
+        label: String,
+        members: Vec<usize>,
+    }
+
+    let start = reply.find('[')?;
+    let end = reply.rfind(']')?;
+    let groups: Vec<Group> = serde_json::from_str(reply.get(start..=end)?).ok()?;
+
+    let mut seen = HashSet::new();
+    let mut result = Vec::new();
+    for group in groups {
+        let mut indices = Vec::new();
+        for member in group.members {
+            if member == 0 || member > count || !seen.insert(member) {
+                return None;
+            }
+            indices.push(member - 1);
+        }
+        if !indices.is_empty() {
+            result.push((group.label, indices));
         }
+    }
+
+    (seen.len() == count).then_some(result)
+}
+
+/// Group `(agent, answer)` pairs, returning each group's label and answer indices
+fn cluster_answers(
+    answers: &[(String, String)],
+    strategy: ClusteringStrategy,
+) -> Vec<(String, Vec<usize>)> {
+    let mut groups: Vec<(String, Vec<usize>)> = Vec::new();
 
-        // Simple heuristic: Extract key decisions/answers
-        // Look for patterns like "Yes", "No", "Approve", "Reject", etc.
-        let mut vote_counts: HashMap<String, usize> = HashMap::new();
-        
-        let decision_keywords = [
-            ("yes", "yes"), ("approve", "yes"), ("agree", "yes"), ("support", "yes"),
-            ("no", "no"), ("reject", "no"), ("disagree", "no"), ("oppose", "no"),
-            ("uncertain", "uncertain"), ("maybe", "uncertain"),
-        ];
-
-        for response in responses {
-            let lower = response.to_lowercase();
-            let mut voted = false;
-            
-            for (keyword, vote) in &decision_keywords {
-                if lower.contains(keyword) {
-                    *vote_counts.entry(vote.to_string()).or_insert(0) += 1;
-                    voted = true;
-                    break;
+    match strategy {
+        ClusteringStrategy::Keywords => {
+            for (i, (_, answer)) in answers.iter().enumerate() {
+                let vote = keyword_vote(answer);
+                match groups.iter_mut().find(|(label, _)| *label == vote) {
+                    Some((_, members)) => members.push(i),
+                    None => groups.push((vote, vec![i])),
                 }
             }
-            
-            if !voted {
-                // Use first sentence or summary as the "vote"
-                let summary = response.lines().next().unwrap_or(response).to_string();
-                *vote_counts.entry(summary).or_insert(0) += 1;
+        }
+        ClusteringStrategy::Similarity { threshold } => {
+            // Each cluster is compared through its first (representative) answer;
+            // a negated answer never joins an affirmative one however many terms they share
+            let mut representatives: Vec<(HashSet<String>, bool)> = Vec::new();
+            for (i, (_, answer)) in answers.iter().enumerate() {
+                let line = answer_line(answer);
+                let (terms, negated) = (key_terms(&line), is_negated(&line));
+                let best = representatives
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, (_, rep_negated))| *rep_negated == negated)
+                    .map(|(g, (rep, _))| (g, overlap(&terms, rep)))
+                    .filter(|(_, score)| *score >= threshold)
+                    .max_by(|a, b| a.1.total_cmp(&b.1));
+                match best {
+                    Some((g, _)) => groups[g].1.push(i),
+                    None => {
+                        representatives.push((terms, negated));
+                        groups.push((answer_line(answer), vec![i]));
+                    }
+                }
             }
         }
+    }
 
-        // Find majority vote
-        let total = responses.len() as f64;
-        let (consensus, count) = vote_counts
-            .into_iter()
-            .max_by_key(|(_, count)| *count)
-            .unwrap_or((String::new(), 0));
+    groups
+}
+
+/// Map an answer to yes/no/uncertain by keyword, else to its first line
+fn keyword_vote(response: &str) -> String {
+    let decision_keywords = [
+        ("yes", "yes"), ("approve", "yes"), ("agree", "yes"), ("support", "yes"),
+        ("no", "no"), ("reject", "no"), ("disagree", "no"), ("oppose", "no"),
+        ("uncertain", "uncertain"), ("maybe", "uncertain"),
+    ];
 
-        let percentage = count as f64 / total;
-        (consensus, percentage)
+    let lower = response.to_lowercase();
+    for (keyword, vote) in &decision_keywords {
+        if lower.contains(keyword) {
+            return vote.to_string();
+        }
     }
 
-    /// Determine if consensus was reached
-    fn consensus_reached(&self, percentage: f64) -> bool {
-        percentage >= self.threshold
+    // Use first sentence or summary as the "vote"
+    response.lines().next().unwrap_or(response).to_string()
+}
+
+/// The line stating the answer: text after "Final Answer:", else the first non-empty line
+fn answer_line(response: &str) -> String {
+    let lower = response.to_lowercase();
+    let body = match lower.find("final answer:") {
+        Some(pos) => &response[pos + "final answer:".len()..],
+        None => response,
+    };
+    body.lines()
+        .map(|line| line.trim().trim_start_matches(['#', '*', '-', '>']).trim())
+        .find(|line| !line.is_empty())
+        .unwrap_or("")
+        .to_string()
+}
+
+/// Words that flip an answer's meaning (apostrophes removed)
+const NEGATIONS: &[&str] = &[
+    "not", "no", "never", "dont", "doesnt", "didnt", "shouldnt", "wont", "cant", "cannot", "isnt",
+    "arent", "avoid",
+];
+
+/// Lowercased words with apostrophes removed, so "don't" reads as "dont"
+fn words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .replace(['\'', '\u{2019}'], "")
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Whether an answer is negated, e.g. "Do not deploy" as opposed to "Deploy"
+fn is_negated(text: &str) -> bool {
+    words(text).iter().any(|word| NEGATIONS.contains(&word.as_str()))
+}
+
+/// Lowercased, crudely stemmed content words, leaving out negations
+fn key_terms(text: &str) -> HashSet<String> {
+    const STOPWORDS: &[&str] = &[
+        "a", "an", "the", "to", "of", "and", "or", "in", "on", "for", "is", "are", "be", "we",
+        "i", "it", "that", "this", "should", "would", "will", "choose", "pick", "go", "with",
+        "answer", "my", "our", "their", "they", "do", "does",
+    ];
+    const SUFFIXES: &[&str] = &["ations", "ation", "ings", "ing", "ate", "ion", "ed", "es", "ly", "s"];
+
+    words(text)
+        .into_iter()
+        .filter(|word| !STOPWORDS.contains(&word.as_str()) && !NEGATIONS.contains(&word.as_str()))
+        .map(|word| {
+            SUFFIXES
+                .iter()
+                .find_map(|suffix| word.strip_suffix(suffix).filter(|stem| stem.len() >= 3))
+                .unwrap_or(&word)
+                .to_string()
+        })
+        .collect()
+}
+
+/// Overlap coefficient: shared terms over the size of the smaller set
+fn overlap(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let smaller = a.len().min(b.len());
+    if smaller == 0 {
+        return if a.is_empty() && b.is_empty() { 1.0 } else { 0.0 };
     }
+    a.intersection(b).count() as f64 / smaller as f64
 }
 
 #[async_trait]
 impl OrchestratorPattern for ConsensusOrchestrator {
     async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+        self.execute_with_cancel(input, CancellationToken::new()).await
+    }
+
+    #[tracing::instrument(name = "orchestration", skip_all, fields(pattern = "consensus"))]
+    async fn execute_with_cancel(&self, input: &str, token: CancellationToken) -> Result<OrchestratorResult> {
         let start = Instant::now();
-        
+
         // All agents respond independently in parallel
         let futures: Vec<_> = self.agents.iter()
             .map(|agent| {
                 let input = input.to_string();
+                let token = token.clone();
                 async move {
                     let agent_start = Instant::now();
-                    let result = agent.react_loop(&input).await;
+                    let result = agent
+                        .react_loop_with_cancel(&input, token)
+                        .instrument(sub_agent_span("voter", &agent.name))
+                        .await;
                     (agent.name.clone(), result, agent_start.elapsed().as_millis() as u64)
                 }
             })
@@ -105,11 +532,13 @@ impl OrchestratorPattern for ConsensusOrchestrator {
 
         let mut result = OrchestratorResult::new("", "consensus");
         let mut responses = Vec::new();
+        let mut weights = Vec::new();
 
-        for (name, output_result, time_ms) in results {
+        for (index, (name, output_result, time_ms)) in results.into_iter().enumerate() {
             match output_result {
                 Ok(output) => {
-                    responses.push(output.content.clone());
+                    responses.push((name.clone(), output.content.clone()));
+                    weights.push(self.weight(index));
                     result = result.with_agent_output(AgentOutput {
                         agent_name: name,
                         content: output.content,
@@ -117,41 +546,91 @@ impl OrchestratorPattern for ConsensusOrchestrator {
                         execution_time_ms: time_ms,
                     });
                 }
+                // Unfinished answers are kept for the record but do not vote
+                Err(Error::Cancelled { partial }) => {
+                    result = result.with_agent_output(AgentOutput {
+                        agent_name: name,
+                        content: partial.content,
+                        loops_executed: partial.trace.iteration_count(),
+                        execution_time_ms: time_ms,
+                    });
+                }
                 Err(e) => {
                     tracing::warn!("Agent {} failed: {}", name, e);
                 }
             }
         }
 
-        // Perform majority vote
-        let (consensus, percentage) = self.majority_vote(&responses);
+        if token.is_cancelled() {
+            result.content = responses
+                .iter()
+                .map(|(name, r)| format!("### {}\n{}", name, r))
+                .collect::<Vec<_>>()
+                .join("\n\n");
+            return Ok(result
+                .with_time(start.elapsed().as_millis() as u64)
+                .with_extra("threshold", serde_json::json!(self.threshold))
+                .with_cancelled());
+        }
+
+        // Cluster equivalent answers, then take the heaviest cluster as the vote
+        let mut ranked = self.cluster(&responses, &weights).await;
+        let tie_broken_by = self.break_tie(&mut ranked, &responses, &weights, input).await;
+
+        let mut votes = Vec::new();
+        for (cluster, indices) in &ranked {
+            for &i in indices {
+                votes.push((i, Vote {
+                    agent: responses[i].0.clone(),
+                    position: cluster.label.clone(),
+                    weight: weights[i],
+                }));
+            }
+        }
+        votes.sort_by_key(|(i, _)| *i);
+        let votes: Vec<Vote> = votes.into_iter().map(|(_, vote)| vote).collect();
+
+        let clusters: Vec<AnswerCluster> = ranked.into_iter().map(|(cluster, _)| cluster).collect();
+        let (consensus, percentage) = clusters
+            .first()
+            .map(|c| (c.label.clone(), c.share))
+            .unwrap_or_default();
         let reached = self.consensus_reached(percentage);
 
+        let positions = clusters
+            .iter()
+            .map(|c| format!("- {} ({:.0}%): {}", c.label, c.share * 100.0, c.members.join(", ")))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let individual = responses
+            .iter()
+            .map(|(name, r)| format!("### {}\n{}", name, r))
+            .collect::<Vec<_>>()
+            .join("\n\n");
+
         // Format final output
         result.content = if reached {
             format!(
                 "# Consensus Reached ({:.0}% agreement)\n\n\
                  **Decision:** {}\n\n\
+                 ## Positions\n\n{}\n\n\
                  ## Individual Responses\n\n{}",
                 percentage * 100.0,
                 consensus,
-                responses.iter().enumerate()
-                    .map(|(i, r)| format!("### Agent {}\n{}", i + 1, r))
-                    .collect::<Vec<_>>()
-                    .join("\n\n")
+                positions,
+                individual
             )
         } else {
             format!(
                 "# No Consensus ({:.0}% < {:.0}% threshold)\n\n\
                  **Majority position:** {}\n\n\
+                 ## Positions\n\n{}\n\n\
                  ## Individual Responses\n\n{}",
                 percentage * 100.0,
                 self.threshold * 100.0,
                 consensus,
-                responses.iter().enumerate()
-                    .map(|(i, r)| format!("### Agent {}\n{}", i + 1, r))
-                    .collect::<Vec<_>>()
-                    .join("\n\n")
+                positions,
+                individual
             )
         };
 
@@ -160,11 +639,20 @@ impl OrchestratorPattern for ConsensusOrchestrator {
             .with_handoffs(0) // No handoffs in consensus pattern
             .with_extra("consensus_reached", serde_json::json!(reached))
             .with_extra("agreement_percentage", serde_json::json!(percentage))
-            .with_extra("threshold", serde_json::json!(self.threshold));
+            .with_extra("threshold", serde_json::json!(self.threshold))
+            .with_extra("clusters", serde_json::to_value(&clusters)?)
+            .with_extra("votes", serde_json::to_value(&votes)?)
+            .with_extra("tie_break", serde_json::to_value(tie_broken_by)?);
 
         Ok(result)
     }
 
+    async fn plan(&self, input: &str) -> OrchestratorPlan {
+        // The judge and synthesizer only run on some inputs; budget for them anyway
+        let runs = self.agents.iter().chain(&self.judge).chain(&self.synthesizer).collect();
+        plan_runs(runs, input).await
+    }
+
     fn pattern_type(&self) -> &str {
         "consensus"
     }
@@ -173,3 +661,130 @@ impl OrchestratorPattern for ConsensusOrchestrator {
         self.agents.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockLlmClient;
+    use std::sync::Arc;
+
+    fn agent(name: &str, reply: &'static str) -> Agent {
+        Agent::builder()
+            .name(name)
+            .system_prompt("You are a test agent.")
+            .client(Arc::new(MockLlmClient::new().with_fallback_reply(reply)))
+            .build()
+            .unwrap()
+    }
+
+    fn voters() -> Vec<Agent> {
+        vec![
+            agent("Small1", "Final answer: yes"),
+            agent("Small2", "Final answer: yes"),
+            agent("Large", "Final answer: no"),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_weighted_majority() {
+        let consensus = ConsensusOrchestrator::new(voters())
+            .with_clustering(ClusteringStrategy::Keywords)
+            .with_threshold(0.5)
+            .with_weights(vec![1.0, 1.0, 3.0]);
+
+        let result = consensus.execute("Ship it?").await.unwrap();
+        let extra = &result.metadata.extra;
+        assert!(result.content.contains("**Decision:** no"));
+        assert_eq!(extra["agreement_percentage"], 0.6);
+        assert!(extra["tie_break"].is_null());
+        assert_eq!(extra["votes"][2], serde_json::json!({"agent": "Large", "position": "no", "weight": 3.0}));
+        assert_eq!(extra["votes"][0]["position"], "yes");
+    }
+
+    #[tokio::test]
+    async fn test_tie_break_strategies() {
+        let tied = |tie_break| {
+            ConsensusOrchestrator::new(voters())
+                .with_clustering(ClusteringStrategy::Keywords)
+                .with_weights(vec![0.5, 1.0, 1.5])
+                .with_tie_break(tie_break)
+        };
+
+        let result = tied(TieBreak::First).execute("Ship it?").await.unwrap();
+        assert_eq!(result.metadata.extra["clusters"][0]["label"], "yes");
+        assert_eq!(result.metadata.extra["tie_break"], "first");
+
+        let result = tied(TieBreak::HighestWeight).execute("Ship it?").await.unwrap();
+        assert_eq!(result.metadata.extra["clusters"][0]["label"], "no");
+        assert_eq!(result.metadata.extra["tie_break"], "highest_weight");
+
+        let result = tied(TieBreak::Synthesizer)
+            .with_synthesizer(agent("Synth", "Final answer: Position 2"))
+            .execute("Ship it?")
+            .await
+            .unwrap();
+        assert!(result.content.contains("**Majority position:** no"));
+    }
+
+    #[test]
+    fn test_parse_choice() {
+        assert_eq!(parse_choice("I pick position 2.", 3), Some(1));
+        assert_eq!(parse_choice("4", 3), None);
+        assert_eq!(parse_choice("neither", 3), None);
+    }
+
+    fn answers(texts: &[&str]) -> Vec<(String, String)> {
+        texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| (format!("agent{}", i + 1), text.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_similarity_clusters_paraphrases() {
+        let answers = answers(&[
+            "Cooperate.\nMutual cooperation maximizes expected payoff.",
+            "Thought: weighing it up.\nFinal Answer: choose cooperation",
+            "Defect, since the other player cannot retaliate.",
+        ]);
+
+        let groups = cluster_answers(&answers, ClusteringStrategy::Similarity { threshold: 0.6 });
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0], ("Cooperate.".to_string(), vec![0, 1]));
+        assert_eq!(groups[1].1, vec![2]);
+
+        // Exact keyword voting keeps them apart
+        assert_eq!(cluster_answers(&answers, ClusteringStrategy::Keywords).len(), 3);
+    }
+
+    #[test]
+    fn test_similarity_keeps_negations_apart() {
+        let answers = answers(&["Do not deploy", "Deploy", "Don't deploy it", "Deploy it now"]);
+        let groups = cluster_answers(&answers, ClusteringStrategy::Similarity { threshold: 0.6 });
+        assert_eq!(groups, vec![
+            ("Do not deploy".to_string(), vec![0, 2]),
+            ("Deploy".to_string(), vec![1, 3]),
+        ]);
+    }
+
+    #[test]
+    fn test_default_clustering_is_keyword_voting() {
+        assert_eq!(ClusteringStrategy::default(), ClusteringStrategy::Keywords);
+        let consensus = ConsensusOrchestrator::new(voters());
+        assert_eq!(consensus.clustering, ClusteringStrategy::Keywords);
+    }
+
+    #[test]
+    fn test_parse_judge_groups() {
+        let reply = "Here you go:\n[{\"label\": \"cooperate\", \"members\": [1, 3]}, \
+                     {\"label\": \"defect\", \"members\": [2]}]";
+        let groups = parse_judge_groups(reply, 3).unwrap();
+        assert_eq!(groups, vec![("cooperate".to_string(), vec![0, 2]), ("defect".to_string(), vec![1])]);
+
+        // Missing, repeated or out-of-range answers are rejected
+        assert!(parse_judge_groups("[{\"label\": \"x\", \"members\": [1]}]", 2).is_none());
+        assert!(parse_judge_groups("[{\"label\": \"x\", \"members\": [1, 1]}]", 1).is_none());
+        assert!(parse_judge_groups("[{\"label\": \"x\", \"members\": [2]}]", 1).is_none());
+    }
+}
@@ -3,11 +3,107 @@
 //! Pro and con agents argue positions for multiple rounds,
 //! with a synthesizer agent producing the final balanced conclusion.
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::Agent;
-use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorResult, AgentOutput};
+use crate::tracing_ext::sub_agent_span;
+use crate::agent_file::CheckpointManager;
+use crate::orchestrator::pattern::{
+    finish_cancelled, plan_runs, record_progress, render_synthesis_prompt, OrchestratorPattern, OrchestratorPlan,
+    OrchestratorResult, OrchestratorSnapshot, AgentOutput,
+};
 use async_trait::async_trait;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+/// Who critiques whom in each round of a debate between several participants
+///
+/// [`DebateOrchestrator`] has only pro and con, who always answer each other;
+/// this is for debates with more participants, such as the provers in the
+/// `math_agg` example.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CritiqueTopology {
+    /// Each participant critiques the previous one
+    #[default]
+    Ring,
+    /// Each participant critiques every other participant
+    AllToAll,
+    /// The lead critiques everyone; everyone else critiques the lead
+    Star {
+        /// Index of the lead participant
+        lead: usize,
+    },
+    /// Each participant critiques one other participant chosen at random
+    Random,
+}
+
+impl CritiqueTopology {
+    /// Indices of the participants whose work `critic` should critique
+    pub fn targets(&self, critic: usize, participants: usize) -> Vec<usize> {
+        if participants < 2 || critic >= participants {
+            return Vec::new();
+        }
+
+        match self {
+            Self::Ring => vec![(critic + participants - 1) % participants],
+            Self::AllToAll => (0..participants).filter(|&i| i != critic).collect(),
+            Self::Star { lead } => {
+                let lead = (*lead).min(participants - 1);
+                if critic == lead {
+                    (0..participants).filter(|&i| i != lead).collect()
+                } else {
+                    vec![lead]
+                }
+            }
+            Self::Random => {
+                let offset = 1 + (OsRng.next_u64() % (participants as u64 - 1)) as usize;
+                vec![(critic + offset) % participants]
+            }
+        }
+    }
+}
+
+impl fmt::Display for CritiqueTopology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ring => write!(f, "ring"),
+            Self::AllToAll => write!(f, "all_to_all"),
+            Self::Star { lead } => write!(f, "star:{}", lead),
+            Self::Random => write!(f, "random"),
+        }
+    }
+}
+
+impl FromStr for CritiqueTopology {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        match s.as_str() {
+            "ring" => Ok(Self::Ring),
+            "all_to_all" | "all-to-all" | "all" => Ok(Self::AllToAll),
+            "star" => Ok(Self::Star { lead: 0 }),
+            "random" => Ok(Self::Random),
+            _ => s
+                .strip_prefix("star:")
+                .and_then(|lead| lead.parse().ok())
+                .map(|lead| Self::Star { lead })
+                .ok_or_else(|| Error::config(format!("Unknown critique topology: {}", s))),
+        }
+    }
+}
+
+/// Synthesis prompt used when none is configured
+const DEFAULT_SYNTHESIS_PROMPT: &str = "{inputs}\n\nProvide a balanced, nuanced synthesis of this debate. Consider:\n\
+     1. The strongest points from each side\n\
+     2. Where the positions might agree\n\
+     3. A reasoned conclusion\n\
+     Original question: {question}";
 
 /// Debate orchestrator - pro/con with synthesis
 pub struct DebateOrchestrator {
@@ -15,6 +111,11 @@ pub struct DebateOrchestrator {
     con_agent: Agent,
     synthesizer: Agent,
     rounds: usize,
+    blind_opening: bool,
+    synthesis_prompt: Option<String>,
+    checkpoints: Option<CheckpointManager>,
+    progress: parking_lot::Mutex<Option<OrchestratorSnapshot>>,
+    resume: parking_lot::Mutex<Option<OrchestratorSnapshot>>,
 }
 
 impl DebateOrchestrator {
@@ -25,6 +126,11 @@ impl DebateOrchestrator {
             con_agent,
             synthesizer,
             rounds: 2,
+            blind_opening: false,
+            synthesis_prompt: None,
+            checkpoints: None,
+            progress: parking_lot::Mutex::new(None),
+            resume: parking_lot::Mutex::new(None),
         }
     }
 
@@ -34,6 +140,49 @@ impl DebateOrchestrator {
         self
     }
 
+    /// Run the opening statements concurrently, each side blind to the other
+    ///
+    /// Counters anchoring on whoever speaks first; the opponent's statement is
+    /// first seen in the round-two rebuttals. By default con opens in answer to pro.
+    pub fn with_blind_opening(mut self, blind: bool) -> Self {
+        self.blind_opening = blind;
+        self
+    }
+
+    /// Set the synthesizer's prompt template
+    ///
+    /// `{inputs}` receives the debate summary and `{question}` the original
+    /// position; see [`validate_synthesis_template`](crate::orchestrator::validate_synthesis_template).
+    pub fn with_synthesis_prompt(mut self, template: impl Into<String>) -> Self {
+        self.synthesis_prompt = Some(template.into());
+        self
+    }
+
+    /// Save a snapshot after every completed round
+    pub fn with_checkpoints(mut self, manager: CheckpointManager) -> Self {
+        self.checkpoints = Some(manager);
+        self
+    }
+
+    /// Continue from `snapshot` on the next run instead of starting over
+    ///
+    /// The next `execute` must be given the snapshot's input; it skips the
+    /// rounds already argued and picks up with both sides' arguments so far.
+    pub fn resume_from(self, snapshot: OrchestratorSnapshot) -> Self {
+        *self.resume.lock() = Some(snapshot);
+        self
+    }
+
+    /// Record the debate after `rounds` completed rounds
+    fn checkpoint(&self, input: &str, rounds: usize, pro: &[String], con: &[String], result: &OrchestratorResult) {
+        let mut snapshot = OrchestratorSnapshot::new("debate", input);
+        snapshot.round = rounds;
+        snapshot.transcripts.insert("pro".to_string(), pro.to_vec());
+        snapshot.transcripts.insert("con".to_string(), con.to_vec());
+        snapshot.agent_outputs = result.agent_outputs.values().cloned().collect();
+        record_progress(&self.progress, self.checkpoints.as_ref(), snapshot);
+    }
+
     /// Debate synthesis handoff function
     fn debate_synthesis(&self, pro_args: &[String], con_args: &[String]) -> String {
         let mut synthesis = String::new();
@@ -56,12 +205,28 @@ impl DebateOrchestrator {
 #[async_trait]
 impl OrchestratorPattern for DebateOrchestrator {
     async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+        self.execute_with_cancel(input, CancellationToken::new()).await
+    }
+
+    #[tracing::instrument(name = "orchestration", skip_all, fields(pattern = "debate"))]
+    async fn execute_with_cancel(&self, input: &str, token: CancellationToken) -> Result<OrchestratorResult> {
         let start = Instant::now();
         let mut result = OrchestratorResult::new("", "debate");
         
         let mut pro_arguments = Vec::new();
         let mut con_arguments = Vec::new();
-        let mut all_outputs = Vec::new();
+        let mut first_round = 0;
+        *self.progress.lock() = None;
+        if let Some(snapshot) = self.resume.lock().take() {
+            snapshot.check("debate", input)?;
+            pro_arguments = snapshot.transcript("pro").to_vec();
+            con_arguments = snapshot.transcript("con").to_vec();
+            first_round = snapshot.round;
+            for output in snapshot.agent_outputs.iter().cloned() {
+                result = result.with_agent_output(output);
+            }
+            *self.progress.lock() = Some(snapshot);
+        }
 
         // Opening statements
         let pro_opening = format!(
@@ -74,7 +239,56 @@ impl OrchestratorPattern for DebateOrchestrator {
         );
 
         // Debate rounds
-        for round in 0..self.rounds {
+        for round in first_round..self.rounds {
+            if round == 0 && self.blind_opening {
+                let pro_name = format!("{} (Round 1)", self.pro_agent.name);
+                let con_name = format!("{} (Round 1)", self.con_agent.name);
+                let opening_start = Instant::now();
+                let pro = async {
+                    let output = self
+                        .pro_agent
+                        .react_loop_with_cancel(&pro_opening, token.clone())
+                        .instrument(sub_agent_span("pro", &self.pro_agent.name))
+                        .await;
+                    (output, opening_start.elapsed())
+                };
+                let con = async {
+                    let output = self
+                        .con_agent
+                        .react_loop_with_cancel(&con_opening, token.clone())
+                        .instrument(sub_agent_span("con", &self.con_agent.name))
+                        .await;
+                    (output, opening_start.elapsed())
+                };
+                let ((pro_output, pro_elapsed), (con_output, con_elapsed)) = tokio::join!(pro, con);
+                let pro_output = match pro_output {
+                    Ok(output) => output,
+                    Err(e) => return finish_cancelled(result, pro_name, opening_start, start, e),
+                };
+                let con_output = match con_output {
+                    Ok(output) => output,
+                    Err(e) => return finish_cancelled(result, con_name, opening_start, start, e),
+                };
+
+                pro_arguments.push(pro_output.content.clone());
+                con_arguments.push(con_output.content.clone());
+                result = result
+                    .with_agent_output(AgentOutput {
+                        agent_name: pro_name,
+                        content: pro_output.content,
+                        loops_executed: pro_output.trace.iteration_count(),
+                        execution_time_ms: pro_elapsed.as_millis() as u64,
+                    })
+                    .with_agent_output(AgentOutput {
+                        agent_name: con_name,
+                        content: con_output.content,
+                        loops_executed: con_output.trace.iteration_count(),
+                        execution_time_ms: con_elapsed.as_millis() as u64,
+                    });
+                self.checkpoint(input, 1, &pro_arguments, &con_arguments, &result);
+                continue;
+            }
+
             // Pro agent's turn
             let pro_prompt = if round == 0 {
                 pro_opening.clone()
@@ -87,11 +301,20 @@ impl OrchestratorPattern for DebateOrchestrator {
             };
 
             let pro_start = Instant::now();
-            let pro_output = self.pro_agent.react_loop(&pro_prompt).await?;
+            let pro_name = format!("{} (Round {})", self.pro_agent.name, round + 1);
+            let pro_output = match self
+                .pro_agent
+                .react_loop_with_cancel(&pro_prompt, token.clone())
+                .instrument(sub_agent_span("pro", &self.pro_agent.name))
+                .await
+            {
+                Ok(output) => output,
+                Err(e) => return finish_cancelled(result, pro_name, pro_start, start, e),
+            };
             pro_arguments.push(pro_output.content.clone());
             
-            all_outputs.push(AgentOutput {
-                agent_name: format!("{} (Round {})", self.pro_agent.name, round + 1),
+            result = result.with_agent_output(AgentOutput {
+                agent_name: pro_name,
                 content: pro_output.content.clone(),
                 loops_executed: pro_output.trace.iteration_count(),
                 execution_time_ms: pro_start.elapsed().as_millis() as u64,
@@ -113,37 +336,46 @@ impl OrchestratorPattern for DebateOrchestrator {
             };
 
             let con_start = Instant::now();
-            let con_output = self.con_agent.react_loop(&con_prompt).await?;
+            let con_name = format!("{} (Round {})", self.con_agent.name, round + 1);
+            let con_output = match self
+                .con_agent
+                .react_loop_with_cancel(&con_prompt, token.clone())
+                .instrument(sub_agent_span("con", &self.con_agent.name))
+                .await
+            {
+                Ok(output) => output,
+                Err(e) => return finish_cancelled(result, con_name, con_start, start, e),
+            };
             con_arguments.push(con_output.content.clone());
             
-            all_outputs.push(AgentOutput {
-                agent_name: format!("{} (Round {})", self.con_agent.name, round + 1),
+            result = result.with_agent_output(AgentOutput {
+                agent_name: con_name,
                 content: con_output.content.clone(),
                 loops_executed: con_output.trace.iteration_count(),
                 execution_time_ms: con_start.elapsed().as_millis() as u64,
             });
-        }
-
-        // Store all outputs
-        for output in all_outputs {
-            result = result.with_agent_output(output);
+            self.checkpoint(input, round + 1, &pro_arguments, &con_arguments, &result);
         }
 
         // Synthesizer produces final balanced conclusion
         let debate_summary = self.debate_synthesis(&pro_arguments, &con_arguments);
-        let synthesis_prompt = format!(
-            "{}\n\nProvide a balanced, nuanced synthesis of this debate. Consider:\n\
-             1. The strongest points from each side\n\
-             2. Where the positions might agree\n\
-             3. A reasoned conclusion\n\
-             Original question: {}",
-            debate_summary,
-            input
-        );
+        let template = self.synthesis_prompt.as_deref().unwrap_or(DEFAULT_SYNTHESIS_PROMPT);
+        let synthesis_prompt = render_synthesis_prompt(template, &debate_summary, input);
 
         let synth_start = Instant::now();
-        let synth_output = self.synthesizer.react_loop(&synthesis_prompt).await?;
-        
+        let synth_output = match self
+            .synthesizer
+            .react_loop_with_cancel(&synthesis_prompt, token.clone())
+            .instrument(sub_agent_span("synthesizer", &self.synthesizer.name))
+            .await
+        {
+            Ok(output) => output,
+            Err(e) => {
+                let name = format!("{} (Synthesis)", self.synthesizer.name);
+                return finish_cancelled(result, name, synth_start, start, e);
+            }
+        };
+
         result = result.with_agent_output(AgentOutput {
             agent_name: format!("{} (Synthesis)", self.synthesizer.name),
             content: synth_output.content.clone(),
@@ -155,11 +387,24 @@ impl OrchestratorPattern for DebateOrchestrator {
         result = result
             .with_time(start.elapsed().as_millis() as u64)
             .with_handoffs(self.rounds * 2) // Each round has pro->con handoff
-            .with_extra("rounds", serde_json::json!(self.rounds));
+            .with_extra("rounds", serde_json::json!(self.rounds))
+            .with_extra("blind_opening", serde_json::json!(self.blind_opening));
 
         Ok(result)
     }
 
+    fn snapshot(&self) -> Option<OrchestratorSnapshot> {
+        self.progress.lock().clone()
+    }
+
+    async fn plan(&self, input: &str) -> OrchestratorPlan {
+        let runs = std::iter::repeat_n([&self.pro_agent, &self.con_agent], self.rounds)
+            .flatten()
+            .chain(std::iter::once(&self.synthesizer))
+            .collect();
+        plan_runs(runs, input).await
+    }
+
     fn pattern_type(&self) -> &str {
         "debate"
     }
@@ -168,3 +413,127 @@ impl OrchestratorPattern for DebateOrchestrator {
         3 // pro, con, synthesizer
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockLlmClient;
+    use std::sync::Arc;
+
+    /// Agent that always answers with `reply`, and its client for reading prompts
+    fn agent(name: &str, reply: &str) -> (Agent, Arc<MockLlmClient>) {
+        let client = Arc::new(MockLlmClient::new().with_fallback_reply(reply));
+        let agent = Agent::builder()
+            .name(name)
+            .system_prompt("You are a test agent.")
+            .client(client.clone())
+            .build()
+            .unwrap();
+        (agent, client)
+    }
+
+    #[tokio::test]
+    async fn test_blind_opening_hides_pro_from_con() {
+        for blind in [false, true] {
+            let (pro, _) = agent("Pro", "Final answer: pro point");
+            let (con, con_client) = agent("Con", "Final answer: con point");
+            let (synthesizer, _) = agent("Synthesizer", "Final answer: balanced");
+            let result = DebateOrchestrator::new(pro, con, synthesizer)
+                .with_blind_opening(blind)
+                .execute("Tabs are better than spaces")
+                .await
+                .unwrap();
+
+            let prompts = con_client.prompts();
+            assert_eq!(prompts[0].contains("pro point"), !blind);
+            assert!(prompts[1].contains("pro point"));
+            assert_eq!(result.agent_outputs.len(), 5);
+            assert_eq!(result.metadata.extra["blind_opening"], serde_json::json!(blind));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_synthesis_prompt_template() {
+        let (pro, _) = agent("Pro", "Final answer: pro point");
+        let (con, _) = agent("Con", "Final answer: con point");
+        let (synthesizer, synth_client) = agent("Synthesizer", "Final answer: balanced");
+        DebateOrchestrator::new(pro, con, synthesizer)
+            .with_rounds(1)
+            .with_synthesis_prompt("Verdict on {question}?\n{inputs}")
+            .execute("Tabs are better than spaces")
+            .await
+            .unwrap();
+
+        let prompt = synth_client.prompts()[0].clone();
+        assert!(prompt.starts_with("Verdict on Tabs are better than spaces?\n# Debate Summary"));
+        assert!(prompt.contains("pro point") && prompt.contains("con point"));
+    }
+
+    #[tokio::test]
+    async fn test_resume_from_snapshot() {
+        let (pro, _) = agent("Pro", "Final answer: pro point");
+        let (con, _) = agent("Con", "Final answer: con point");
+        let (synthesizer, _) = agent("Synthesizer", "Final answer: balanced");
+        let first = DebateOrchestrator::new(pro, con, synthesizer).with_rounds(1);
+        first.execute("Tabs are better than spaces").await.unwrap();
+        let snapshot = first.snapshot().unwrap();
+        assert_eq!(snapshot.round, 1);
+        assert_eq!(snapshot.transcript("pro"), ["pro point"]);
+
+        let snapshot: OrchestratorSnapshot =
+            serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        let manager = CheckpointManager::new(dir.path().to_str().unwrap());
+        let (pro, pro_client) = agent("Pro", "Final answer: pro rebuttal");
+        let (con, _) = agent("Con", "Final answer: con rebuttal");
+        let (synthesizer, _) = agent("Synthesizer", "Final answer: balanced");
+        let resumed = DebateOrchestrator::new(pro, con, synthesizer)
+            .with_rounds(3)
+            .with_checkpoints(manager.clone())
+            .resume_from(snapshot.clone());
+        resumed.execute("Tabs are better than spaces").await.unwrap();
+
+        let prompts = pro_client.prompts().clone();
+        assert_eq!(prompts.len(), 2);
+        assert!(prompts[0].contains("con point"));
+        let latest = manager.latest_snapshot("debate").unwrap().unwrap();
+        assert_eq!(latest.round, 3);
+        assert_eq!(latest.transcript("pro"), ["pro point", "pro rebuttal", "pro rebuttal"]);
+
+        let (pro, _) = agent("Pro", "Final answer: pro point");
+        let (con, _) = agent("Con", "Final answer: con point");
+        let (synthesizer, _) = agent("Synthesizer", "Final answer: balanced");
+        let mismatched = DebateOrchestrator::new(pro, con, synthesizer).resume_from(snapshot);
+        assert!(mismatched.execute("Spaces are better than tabs").await.is_err());
+    }
+
+    #[test]
+    fn test_topology_targets() {
+        assert_eq!(CritiqueTopology::Ring.targets(0, 3), vec![2]);
+        assert_eq!(CritiqueTopology::Ring.targets(2, 3), vec![1]);
+        assert_eq!(CritiqueTopology::AllToAll.targets(1, 3), vec![0, 2]);
+        assert_eq!(CritiqueTopology::Star { lead: 1 }.targets(1, 3), vec![0, 2]);
+        assert_eq!(CritiqueTopology::Star { lead: 1 }.targets(2, 3), vec![1]);
+        assert!(CritiqueTopology::Ring.targets(0, 1).is_empty());
+
+        for _ in 0..20 {
+            let targets = CritiqueTopology::Random.targets(1, 4);
+            assert_eq!(targets.len(), 1);
+            assert_ne!(targets[0], 1);
+            assert!(targets[0] < 4);
+        }
+    }
+
+    #[test]
+    fn test_topology_parse_roundtrip() {
+        for topology in [
+            CritiqueTopology::Ring,
+            CritiqueTopology::AllToAll,
+            CritiqueTopology::Star { lead: 2 },
+            CritiqueTopology::Random,
+        ] {
+            assert_eq!(topology.to_string().parse::<CritiqueTopology>().unwrap(), topology);
+        }
+        assert!("mesh".parse::<CritiqueTopology>().is_err());
+    }
+}
@@ -5,23 +5,139 @@
 
 use crate::error::Result;
 use crate::Agent;
-use crate::handoffs::{Handoff, HandoffContext};
-use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorResult, AgentOutput};
+use crate::tracing_ext::sub_agent_span;
+use crate::handoffs::{Handoff, HandoffContext, HandoffStrategy};
+use crate::orchestrator::config::AggregationStrategy;
+use crate::orchestrator::pattern::{
+    finish_cancelled, keep_partial, plan_runs, rank_by_confidence, render_synthesis_prompt, OrchestratorPattern,
+    OrchestratorPlan, OrchestratorResult, AgentOutput,
+};
+use crate::react::Observation;
 use crate::types::AgentId;
 use async_trait::async_trait;
 use std::time::Instant;
 use futures::future::join_all;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+/// Synthesis prompt used when none is configured
+const DEFAULT_SYNTHESIS_PROMPT: &str =
+    "Original task: {question}\n\nSubagent outputs:\n{inputs}\n\nSynthesize these into a comprehensive final answer:";
 
 /// Hierarchical orchestrator - lead agent with subagent delegation
 pub struct HierarchicalOrchestrator {
     lead_agent: Agent,
     subagents: Vec<Agent>,
+    aggregation: AggregationStrategy,
+    synthesis_prompt: Option<String>,
+    handoff_strategy: Option<HandoffStrategy>,
 }
 
 impl HierarchicalOrchestrator {
     /// Create a new hierarchical orchestrator
     pub fn new(lead_agent: Agent, subagents: Vec<Agent>) -> Self {
-        Self { lead_agent, subagents }
+        Self {
+            lead_agent,
+            subagents,
+            aggregation: AggregationStrategy::Concatenate,
+            synthesis_prompt: None,
+            handoff_strategy: None,
+        }
+    }
+
+    /// Set how subagent outputs are combined before the lead synthesizes them
+    ///
+    /// With [`AggregationStrategy::RankByConfidence`], subagents should end their
+    /// answer with a `CONFIDENCE: 0.x` line; outputs without one go last.
+    pub fn with_aggregation(mut self, strategy: AggregationStrategy) -> Self {
+        self.aggregation = strategy;
+        self
+    }
+
+    /// Set the lead's synthesis prompt template
+    ///
+    /// `{inputs}` receives the aggregated subagent outputs and `{question}`
+    /// the original task.
+    pub fn with_synthesis_prompt(mut self, template: impl Into<String>) -> Self {
+        self.synthesis_prompt = Some(template.into());
+        self
+    }
+
+    /// Assign each subtask with `strategy` instead of pairing subagents and subtasks in order
+    ///
+    /// The strategy sees the subtask as the latest observation and the
+    /// subagents assigned so far as the handoff chain, so `RoundRobin` and
+    /// `LeastBusy` spread the work while `ByCapability` and `ByLlmDecision`
+    /// pick a specialist. Subtasks it assigns to no one go to subagents in order.
+    pub fn with_handoff_strategy(mut self, strategy: HandoffStrategy) -> Self {
+        self.handoff_strategy = Some(strategy);
+        self
+    }
+
+    /// Pair subtasks with the subagents that will run them
+    fn assign<'a>(&'a self, input: &str, subtasks: &'a [String]) -> Vec<(&'a Agent, &'a String)> {
+        let Some(strategy) = &self.handoff_strategy else {
+            // Cycle if fewer subtasks than subagents
+            return self.subagents.iter().zip(subtasks.iter().cycle()).collect();
+        };
+        if self.subagents.is_empty() {
+            return Vec::new();
+        }
+
+        let mut ctx = HandoffContext::new(input);
+        subtasks
+            .iter()
+            .enumerate()
+            .map(|(i, subtask)| {
+                ctx.observations.push(Observation::new(subtask.clone()));
+                let index = strategy
+                    .select_index(&ctx, &self.subagents)
+                    .unwrap_or(i % self.subagents.len());
+                ctx.chain.push(self.subagents[index].id);
+                (&self.subagents[index], subtask)
+            })
+            .collect()
+    }
+
+    /// Combine subagent outputs for the lead's synthesis prompt
+    fn aggregate(&self, outputs: &[AgentOutput]) -> String {
+        let section = |o: &AgentOutput| format!("### {}\n{}", o.agent_name, o.content);
+        match &self.aggregation {
+            AggregationStrategy::Concatenate | AggregationStrategy::Synthesize => {
+                outputs.iter().map(section).collect::<Vec<_>>().join("\n\n")
+            }
+            AggregationStrategy::RankByConfidence => rank_by_confidence(outputs)
+                .into_iter()
+                .map(section)
+                .collect::<Vec<_>>()
+                .join("\n\n"),
+            AggregationStrategy::First => outputs.first().map(section).unwrap_or_default(),
+            AggregationStrategy::Longest => outputs
+                .iter()
+                .max_by_key(|o| o.content.len())
+                .map(section)
+                .unwrap_or_default(),
+            AggregationStrategy::Merge => {
+                // Drop lines another subagent already reported, ignoring case and spacing
+                let mut seen = std::collections::HashSet::new();
+                outputs
+                    .iter()
+                    .filter_map(|o| {
+                        let lines: Vec<&str> = o
+                            .content
+                            .lines()
+                            .filter(|line| {
+                                let key = line.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+                                key.is_empty() || seen.insert(key)
+                            })
+                            .collect();
+                        let content = lines.join("\n");
+                        (!content.trim().is_empty()).then(|| format!("### {}\n{}", o.agent_name, content.trim()))
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n\n")
+            }
+        }
     }
 
     /// Create handoff to a subagent
@@ -62,6 +178,11 @@ impl HierarchicalOrchestrator {
 #[async_trait]
 impl OrchestratorPattern for HierarchicalOrchestrator {
     async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+        self.execute_with_cancel(input, CancellationToken::new()).await
+    }
+
+    #[tracing::instrument(name = "orchestration", skip_all, fields(pattern = "hierarchical"))]
+    async fn execute_with_cancel(&self, input: &str, token: CancellationToken) -> Result<OrchestratorResult> {
         let start = Instant::now();
         let mut result = OrchestratorResult::new("", "hierarchical");
         let mut handoff_count = 0;
@@ -74,8 +195,19 @@ impl OrchestratorPattern for HierarchicalOrchestrator {
         );
 
         let lead_start = Instant::now();
-        let lead_output = self.lead_agent.react_loop(&decomposition_prompt).await?;
-        
+        let lead_output = match self
+            .lead_agent
+            .react_loop_with_cancel(&decomposition_prompt, token.clone())
+            .instrument(sub_agent_span("lead", &self.lead_agent.name))
+            .await
+        {
+            Ok(output) => output,
+            Err(e) => {
+                let name = format!("{} (decomposition)", self.lead_agent.name);
+                return finish_cancelled(result, name, lead_start, start, e);
+            }
+        };
+
         result = result.with_agent_output(AgentOutput {
             agent_name: format!("{} (decomposition)", self.lead_agent.name),
             content: lead_output.content.clone(),
@@ -86,8 +218,8 @@ impl OrchestratorPattern for HierarchicalOrchestrator {
         // Phase 2: Parse subtasks and delegate to subagents
         let subtasks = self.parse_subtasks(&lead_output.content);
         
-        let futures: Vec<_> = self.subagents.iter()
-            .zip(subtasks.iter().cycle()) // Cycle if fewer subtasks than subagents
+        let futures: Vec<_> = self.assign(input, &subtasks)
+            .into_iter()
             .map(|(subagent, subtask)| {
                 let _handoff = self.create_handoff(subagent, subtask, input);
                 handoff_count += 1;
@@ -97,9 +229,13 @@ impl OrchestratorPattern for HierarchicalOrchestrator {
                     input, subtask
                 );
                 
+                let token = token.clone();
                 async move {
                     let agent_start = Instant::now();
-                    let result = subagent.react_loop(&subtask_prompt).await;
+                    let result = subagent
+                        .react_loop_with_cancel(&subtask_prompt, token)
+                        .instrument(sub_agent_span("subagent", &subagent.name))
+                        .await;
                     (subagent.name.clone(), result, agent_start.elapsed().as_millis() as u64)
                 }
             })
@@ -109,7 +245,7 @@ impl OrchestratorPattern for HierarchicalOrchestrator {
         
         let mut subagent_outputs = Vec::new();
         for (name, output_result, time_ms) in subagent_results {
-            if let Ok(output) = output_result {
+            if let Ok(output) = keep_partial(output_result) {
                 let agent_output = AgentOutput {
                     agent_name: name,
                     content: output.content.clone(),
@@ -121,19 +257,36 @@ impl OrchestratorPattern for HierarchicalOrchestrator {
             }
         }
 
+        let subagent_summary = self.aggregate(&subagent_outputs);
+        result = result
+            .with_handoffs(handoff_count)
+            .with_extra("subtasks", serde_json::json!(subtasks));
+
+        // Stop before synthesis if cancelled while the subagents ran
+        if token.is_cancelled() {
+            result.content = subagent_summary;
+            return Ok(result.with_time(start.elapsed().as_millis() as u64).with_cancelled());
+        }
+
         // Phase 3: Lead agent synthesizes results
-        let synthesis_prompt = format!(
-            "Original task: {}\n\nSubagent outputs:\n{}\n\nSynthesize these into a comprehensive final answer:",
-            input,
-            subagent_outputs.iter()
-                .map(|o| format!("### {}\n{}", o.agent_name, o.content))
-                .collect::<Vec<_>>()
-                .join("\n\n")
-        );
+        let template = self.synthesis_prompt.as_deref().unwrap_or(DEFAULT_SYNTHESIS_PROMPT);
+        let synthesis_prompt = render_synthesis_prompt(template, &subagent_summary, input);
 
         let synthesis_start = Instant::now();
-        let synthesis_output = self.lead_agent.react_loop(&synthesis_prompt).await?;
-        
+        let synthesis_output = match self
+            .lead_agent
+            .react_loop_with_cancel(&synthesis_prompt, token.clone())
+            .instrument(sub_agent_span("lead", &self.lead_agent.name))
+            .await
+        {
+            Ok(output) => output,
+            Err(e) => {
+                let name = format!("{} (synthesis)", self.lead_agent.name);
+                result.content = subagent_summary;
+                return finish_cancelled(result, name, synthesis_start, start, e);
+            }
+        };
+
         result = result.with_agent_output(AgentOutput {
             agent_name: format!("{} (synthesis)", self.lead_agent.name),
             content: synthesis_output.content.clone(),
@@ -142,14 +295,20 @@ impl OrchestratorPattern for HierarchicalOrchestrator {
         });
 
         result.content = synthesis_output.content;
-        result = result
-            .with_time(start.elapsed().as_millis() as u64)
-            .with_handoffs(handoff_count)
-            .with_extra("subtasks", serde_json::json!(subtasks));
+        result = result.with_time(start.elapsed().as_millis() as u64);
 
         Ok(result)
     }
 
+    async fn plan(&self, input: &str) -> OrchestratorPlan {
+        // The lead runs twice: once to decompose, once to synthesize
+        let runs = std::iter::once(&self.lead_agent)
+            .chain(&self.subagents)
+            .chain(std::iter::once(&self.lead_agent))
+            .collect();
+        plan_runs(runs, input).await
+    }
+
     fn pattern_type(&self) -> &str {
         "hierarchical"
     }
@@ -158,3 +317,85 @@ impl OrchestratorPattern for HierarchicalOrchestrator {
         1 + self.subagents.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockLlmClient;
+    use std::sync::Arc;
+
+    /// Agent that always answers with `reply`, and its client for reading prompts
+    fn agent(name: &str, reply: &str) -> (Agent, Arc<MockLlmClient>) {
+        let client = Arc::new(MockLlmClient::new().with_fallback_reply(reply));
+        let agent = Agent::builder()
+            .name(name)
+            .system_prompt("You are a test agent.")
+            .client(client.clone())
+            .build()
+            .unwrap();
+        (agent, client)
+    }
+
+    #[test]
+    fn test_parse_confidence() {
+        let output = |content: &str| AgentOutput {
+            agent_name: "a".to_string(),
+            content: content.to_string(),
+            loops_executed: 1,
+            execution_time_ms: 0,
+        };
+        assert_eq!(output("Port 22 is open.\nCONFIDENCE: 0.8\n").confidence(), Some(0.8));
+        assert_eq!(output("confidence:0.35").confidence(), Some(0.35));
+        assert_eq!(output("CONFIDENCE: 0.8\nMore text").confidence(), None);
+        assert_eq!(output("CONFIDENCE: high").confidence(), None);
+        assert_eq!(output("CONFIDENCE: 7").confidence(), None);
+    }
+
+    #[tokio::test]
+    async fn test_lead_receives_outputs_in_aggregation_order() {
+        for (strategy, order) in [
+            (AggregationStrategy::Concatenate, ["Low", "High", "Unscored"]),
+            (AggregationStrategy::RankByConfidence, ["High", "Low", "Unscored"]),
+        ] {
+            let (lead, lead_client) = agent("Lead", "Final answer: 1. ports\n2. users\n3. cron");
+            let subagents = vec![
+                agent("Low", "Final answer: maybe\nCONFIDENCE: 0.3").0,
+                agent("High", "Final answer: surely\nCONFIDENCE: 0.9").0,
+                agent("Unscored", "Final answer: no idea").0,
+            ];
+            HierarchicalOrchestrator::new(lead, subagents)
+                .with_aggregation(strategy)
+                .execute("Audit this host")
+                .await
+                .unwrap();
+
+            let synthesis = lead_client.prompts()[1].clone();
+            let positions: Vec<usize> = order
+                .iter()
+                .map(|name| synthesis.find(&format!("### {}\n", name)).unwrap())
+                .collect();
+            assert!(positions.windows(2).all(|pair| pair[0] < pair[1]), "{}", synthesis);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_handoff_strategy_assigns_subtasks() {
+        let (lead, _) = agent(
+            "Lead",
+            "Final answer: 1. Network: list open ports\n2. Disk: check usage\n3. Network: check DNS",
+        );
+        let (network, network_client) = agent("Network", "Final answer: ports 22 and 443");
+        let (disk, disk_client) = agent("Disk", "Final answer: 40% used");
+        let (idle, idle_client) = agent("Idle", "Final answer: nothing");
+        let result = HierarchicalOrchestrator::new(lead, vec![idle, network, disk])
+            .with_handoff_strategy(HandoffStrategy::ByLlmDecision)
+            .execute("Audit this host")
+            .await
+            .unwrap();
+
+        assert_eq!(network_client.request_count(), 2);
+        assert_eq!(disk_client.request_count(), 1);
+        assert_eq!(idle_client.request_count(), 0);
+        assert_eq!(result.metadata.handoff_count, 3);
+    }
+}
@@ -10,7 +10,8 @@
 //! - **Hierarchical**: Lead agent with subagent delegation
 //! - **Debate**: Pro/con with synthesis
 //! - **Router**: Triage to specialized agents
-//! - **Consensus**: Majority voting
+//! - **Consensus**: Majority voting over clustered answers
+//! - **Pipeline**: Whole patterns chained as stages, output feeding the next
 //!
 //! # Example
 //!
@@ -30,6 +31,7 @@ pub mod hierarchical;
 pub mod debate;
 pub mod router;
 pub mod consensus;
+pub mod pipeline;
 
 // Re-exports
 pub use config::{
@@ -46,10 +48,15 @@ pub use pattern::{
     AgentOutput,
     OrchestratorMetadata,
     OrchestratorBuilder,
+    OrchestratorPlan,
+    OrchestratorSnapshot,
+    validate_synthesis_template,
+    SYNTHESIS_PLACEHOLDERS,
 };
-pub use sequential::SequentialOrchestrator;
+pub use sequential::{SequentialOrchestrator, StopCondition};
 pub use concurrent::ConcurrentOrchestrator;
 pub use hierarchical::HierarchicalOrchestrator;
-pub use debate::DebateOrchestrator;
+pub use debate::{CritiqueTopology, DebateOrchestrator};
 pub use router::RouterOrchestrator;
-pub use consensus::ConsensusOrchestrator;
+pub use consensus::{AnswerCluster, ClusteringStrategy, ConsensusOrchestrator, TieBreak, Vote};
+pub use pipeline::PipelineOrchestrator;
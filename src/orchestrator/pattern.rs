@@ -1,10 +1,16 @@
 //! Orchestrator pattern trait and result types
 
-use crate::error::Result;
+use crate::agent::PlanEstimate;
+use crate::agent_file::CheckpointManager;
+use crate::error::{Error, Result};
+use crate::metrics::Metrics;
 use crate::Agent;
 use async_trait::async_trait;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
 
 /// Output from an orchestrator pattern execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,6 +36,73 @@ pub struct AgentOutput {
     pub execution_time_ms: u64,
 }
 
+impl AgentOutput {
+    /// Self-reported confidence from a trailing `CONFIDENCE: 0.x` line
+    pub fn confidence(&self) -> Option<f64> {
+        let last = self.content.lines().rev().find(|line| !line.trim().is_empty())?;
+        let (label, value) = last.split_once(':')?;
+        if !label.trim().eq_ignore_ascii_case("confidence") {
+            return None;
+        }
+        value.trim().parse::<f64>().ok().filter(|c| (0.0..=1.0).contains(c))
+    }
+}
+
+/// Progress of an orchestrator run, taken after its last completed round
+///
+/// Serializes to JSON so a run can be resumed after a restart; see
+/// [`CheckpointManager::save_snapshot`](crate::CheckpointManager::save_snapshot).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrchestratorSnapshot {
+    /// Pattern that took the snapshot
+    pub pattern_type: String,
+    /// Input the run was started with
+    pub input: String,
+    /// Rounds (or stages) completed; a resumed run starts at this index
+    pub round: usize,
+    /// Accumulated output per participant role, in order
+    #[serde(default)]
+    pub transcripts: BTreeMap<String, Vec<String>>,
+    /// Outputs recorded in the result so far, in order
+    #[serde(default)]
+    pub agent_outputs: Vec<AgentOutput>,
+    /// When the snapshot was taken
+    pub created_at: DateTime<Utc>,
+}
+
+impl OrchestratorSnapshot {
+    /// Create an empty snapshot for a run of `pattern_type` on `input`
+    pub fn new(pattern_type: impl Into<String>, input: impl Into<String>) -> Self {
+        Self {
+            pattern_type: pattern_type.into(),
+            input: input.into(),
+            round: 0,
+            transcripts: BTreeMap::new(),
+            agent_outputs: Vec::new(),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// Transcript recorded for a role (empty if none)
+    pub fn transcript(&self, role: &str) -> &[String] {
+        self.transcripts.get(role).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Check that the snapshot belongs to `pattern_type` and `input`
+    pub(crate) fn check(&self, pattern_type: &str, input: &str) -> Result<()> {
+        if self.pattern_type != pattern_type {
+            return Err(Error::config(format!(
+                "Cannot resume a {} orchestrator from a {} snapshot",
+                pattern_type, self.pattern_type
+            )));
+        }
+        if self.input != input {
+            return Err(Error::config("Snapshot was taken for a different input"));
+        }
+        Ok(())
+    }
+}
+
 /// Pattern execution metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrchestratorMetadata {
@@ -41,6 +114,9 @@ pub struct OrchestratorMetadata {
     pub agent_count: usize,
     /// Number of handoffs performed
     pub handoff_count: usize,
+    /// Whether the run was cut short by cancellation
+    #[serde(default)]
+    pub cancelled: bool,
     /// Pattern-specific data
     #[serde(default)]
     pub extra: HashMap<String, serde_json::Value>,
@@ -57,6 +133,7 @@ impl OrchestratorResult {
                 total_time_ms: 0,
                 agent_count: 0,
                 handoff_count: 0,
+                cancelled: false,
                 extra: HashMap::new(),
             },
         }
@@ -86,6 +163,160 @@ impl OrchestratorResult {
         self.metadata.extra.insert(key.into(), value);
         self
     }
+
+    /// Mark the result as cut short by cancellation
+    pub fn with_cancelled(mut self) -> Self {
+        self.metadata.cancelled = true;
+        self
+    }
+}
+
+/// Cost estimate for an orchestrator run, summed across its agent runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OrchestratorPlan {
+    /// One estimate per agent run the pattern may perform
+    pub runs: Vec<PlanEstimate>,
+    /// Total first-turn prompt tokens across runs
+    pub prompt_tokens: u64,
+    /// Total estimated USD across priced runs (None if no run is priced)
+    pub estimated_cost_usd: Option<f64>,
+    /// Agents left out of the cost because their model has no pricing
+    pub unpriced_agents: Vec<String>,
+}
+
+impl OrchestratorPlan {
+    /// Sum per-run estimates
+    pub fn from_runs(runs: Vec<PlanEstimate>) -> Self {
+        let mut plan = Self::default();
+        for run in &runs {
+            plan.prompt_tokens += run.prompt_tokens;
+            match run.estimated_cost_usd {
+                Some(cost) => *plan.estimated_cost_usd.get_or_insert(0.0) += cost,
+                None if !plan.unpriced_agents.contains(&run.agent_name) => {
+                    plan.unpriced_agents.push(run.agent_name.clone())
+                }
+                None => {}
+            }
+        }
+        plan.runs = runs;
+        plan
+    }
+}
+
+/// Plan one run of each agent on `input`
+pub(crate) async fn plan_runs(agents: Vec<&Agent>, input: &str) -> OrchestratorPlan {
+    let mut runs = Vec::new();
+    for agent in agents {
+        runs.push(agent.plan(input).await);
+    }
+    OrchestratorPlan::from_runs(runs)
+}
+
+/// Keep `snapshot` as the run's progress, saving it too when checkpoints are on
+pub(crate) fn record_progress(
+    progress: &parking_lot::Mutex<Option<OrchestratorSnapshot>>,
+    checkpoints: Option<&CheckpointManager>,
+    snapshot: OrchestratorSnapshot,
+) {
+    if let Some(manager) = checkpoints {
+        if let Err(e) = manager.save_snapshot(&snapshot) {
+            tracing::warn!("Failed to save {} snapshot: {}", snapshot.pattern_type, e);
+        }
+    }
+    *progress.lock() = Some(snapshot);
+}
+
+/// Placeholders every synthesis prompt template must contain
+///
+/// `{inputs}` is replaced by the collected agent outputs and `{question}` by
+/// the original task.
+pub const SYNTHESIS_PLACEHOLDERS: [&str; 2] = ["{inputs}", "{question}"];
+
+/// Check that a synthesis prompt template contains every placeholder
+pub fn validate_synthesis_template(template: &str) -> Result<()> {
+    let missing: Vec<&str> = SYNTHESIS_PLACEHOLDERS
+        .into_iter()
+        .filter(|placeholder| !template.contains(placeholder))
+        .collect();
+    if missing.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::config(format!(
+            "Synthesis prompt template is missing {}",
+            missing.join(" and ")
+        )))
+    }
+}
+
+/// Fill a synthesis prompt template in one pass, so placeholders inside
+/// agent outputs are left alone
+pub(crate) fn render_synthesis_prompt(template: &str, inputs: &str, question: &str) -> String {
+    let mut prompt = String::with_capacity(template.len() + inputs.len() + question.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        prompt.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("{inputs}") {
+            prompt.push_str(inputs);
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("{question}") {
+            prompt.push_str(question);
+            rest = after;
+        } else {
+            prompt.push('{');
+            rest = &rest[1..];
+        }
+    }
+    prompt.push_str(rest);
+    prompt
+}
+
+/// Outputs ordered most confident first; ties and unscored outputs keep their order
+pub(crate) fn rank_by_confidence(outputs: &[AgentOutput]) -> Vec<&AgentOutput> {
+    let mut ranked: Vec<&AgentOutput> = outputs.iter().collect();
+    ranked.sort_by(|a, b| {
+        let (a, b) = (a.confidence().unwrap_or(-1.0), b.confidence().unwrap_or(-1.0));
+        b.total_cmp(&a)
+    });
+    ranked
+}
+
+/// Treat a cancelled agent's partial output as its output
+pub(crate) fn keep_partial(result: Result<crate::agent::AgentOutput>) -> Result<crate::agent::AgentOutput> {
+    match result {
+        Err(Error::Cancelled { partial }) => Ok(*partial),
+        other => other,
+    }
+}
+
+/// Finish a run whose agent call failed with `err`
+///
+/// A cancelled agent's partial output is recorded under `agent_name` and the
+/// result returned marked cancelled (its content falls back to the partial
+/// output if nothing else was produced); any other error is passed through.
+pub(crate) fn finish_cancelled(
+    mut result: OrchestratorResult,
+    agent_name: String,
+    agent_start: Instant,
+    run_start: Instant,
+    err: Error,
+) -> Result<OrchestratorResult> {
+    let Error::Cancelled { partial } = err else {
+        return Err(err);
+    };
+
+    if result.content.is_empty() {
+        result.content = partial.content.clone();
+    }
+    Ok(result
+        .with_agent_output(AgentOutput {
+            agent_name,
+            content: partial.content,
+            loops_executed: partial.trace.iteration_count(),
+            execution_time_ms: agent_start.elapsed().as_millis() as u64,
+        })
+        .with_time(run_start.elapsed().as_millis() as u64)
+        .with_cancelled())
 }
 
 /// Trait for orchestrator patterns
@@ -94,11 +325,80 @@ pub trait OrchestratorPattern: Send + Sync {
     /// Execute the pattern with given input
     async fn execute(&self, input: &str) -> Result<OrchestratorResult>;
 
+    /// Execute the pattern, stopping early once `token` is cancelled
+    ///
+    /// Built-in patterns stop their agents between turns and return what was
+    /// produced so far with `metadata.cancelled` set. The default
+    /// implementation drops the in-flight `execute` and returns an empty
+    /// cancelled result.
+    async fn execute_with_cancel(&self, input: &str, token: CancellationToken) -> Result<OrchestratorResult> {
+        tokio::select! {
+            result = self.execute(input) => result,
+            _ = token.cancelled() => Ok(OrchestratorResult::new("", self.pattern_type()).with_cancelled()),
+        }
+    }
+
+    /// State after the last completed round of the current or latest run
+    ///
+    /// Patterns without checkpoint support return `None`.
+    fn snapshot(&self) -> Option<OrchestratorSnapshot> {
+        None
+    }
+
+    /// Estimate the cost of running `input` without calling any model
+    ///
+    /// Built-in patterns sum [`Agent::plan`] over every agent run they may
+    /// perform, each fed `input` (prompts built from earlier outputs are
+    /// longer, so this is a lower bound per turn). The default returns an
+    /// empty plan.
+    async fn plan(&self, _input: &str) -> OrchestratorPlan {
+        OrchestratorPlan::default()
+    }
+
     /// Get the pattern type name
     fn pattern_type(&self) -> &str;
 
     /// Get the number of agents in this pattern
     fn agent_count(&self) -> usize;
+
+    /// Execute the pattern synchronously on a fresh current-thread runtime
+    ///
+    /// Must not be called from within an existing Tokio runtime; see
+    /// [`crate::blocking`].
+    fn execute_blocking(&self, input: &str) -> Result<OrchestratorResult> {
+        crate::blocking::block_on(self.execute(input))?
+    }
+
+    /// Execute the pattern synchronously on a caller-provided runtime
+    fn execute_blocking_on(
+        &self,
+        runtime: &tokio::runtime::Runtime,
+        input: &str,
+    ) -> Result<OrchestratorResult> {
+        crate::blocking::block_on_with(runtime, self.execute(input))?
+    }
+
+    /// Execute the pattern and report the run to a metrics sink
+    async fn execute_with_metrics(
+        &self,
+        input: &str,
+        metrics: &dyn Metrics,
+    ) -> Result<OrchestratorResult> {
+        let start = Instant::now();
+        let result = self.execute(input).await;
+        let handoffs = result
+            .as_ref()
+            .map(|r| r.metadata.handoff_count)
+            .unwrap_or(0);
+        metrics.record_orchestration(
+            self.pattern_type(),
+            self.agent_count(),
+            handoffs,
+            start.elapsed(),
+            result.is_ok(),
+        );
+        result
+    }
 }
 
 /// Builder for orchestrator patterns
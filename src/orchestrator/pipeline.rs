@@ -0,0 +1,192 @@
+//! Pipeline orchestrator pattern
+//!
+//! Chains whole orchestrators as stages: the final output of each stage
+//! becomes the input of the next, so patterns can be nested (for example a
+//! router, then a debate, then a sequential summary).
+
+use crate::error::{Error, Result};
+use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorPlan, OrchestratorResult};
+use async_trait::async_trait;
+use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+
+/// Pipeline orchestrator - orchestrators execute in order as stages
+///
+/// The result's `agent_outputs` holds every stage's outputs keyed
+/// `"<stage>.<agent>"` (stages numbered from 1), `agent_count` and
+/// `handoff_count` are summed over the stages, and `extra["stages"]` lists
+/// each stage's pattern type, timing, counts and own extra metadata.
+pub struct PipelineOrchestrator {
+    stages: Vec<Box<dyn OrchestratorPattern>>,
+}
+
+impl PipelineOrchestrator {
+    /// Create a pipeline running `stages` in order
+    pub fn new(stages: Vec<Box<dyn OrchestratorPattern>>) -> Self {
+        Self { stages }
+    }
+
+    /// Append a stage
+    pub fn with_stage(mut self, stage: impl OrchestratorPattern + 'static) -> Self {
+        self.stages.push(Box::new(stage));
+        self
+    }
+
+    /// Number of stages
+    pub fn stage_count(&self) -> usize {
+        self.stages.len()
+    }
+}
+
+#[async_trait]
+impl OrchestratorPattern for PipelineOrchestrator {
+    async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+        self.execute_with_cancel(input, CancellationToken::new()).await
+    }
+
+    #[tracing::instrument(name = "orchestration", skip_all, fields(pattern = "pipeline"))]
+    async fn execute_with_cancel(&self, input: &str, token: CancellationToken) -> Result<OrchestratorResult> {
+        if self.stages.is_empty() {
+            return Err(Error::config("Pipeline has no stages"));
+        }
+
+        let start = Instant::now();
+        let mut result = OrchestratorResult::new("", "pipeline");
+        let mut current_input = input.to_string();
+        let mut stage_metadata = Vec::new();
+
+        for (index, stage) in self.stages.iter().enumerate() {
+            let stage_result = stage
+                .execute_with_cancel(&current_input, token.clone())
+                .await
+                .map_err(|e| Error::agent(format!("Pipeline stage {} ({}) failed: {}", index + 1, stage.pattern_type(), e)))?;
+
+            let metadata = &stage_result.metadata;
+            result.metadata.agent_count += metadata.agent_count;
+            result.metadata.handoff_count += metadata.handoff_count;
+            stage_metadata.push(serde_json::json!({
+                "stage": index + 1,
+                "pattern_type": metadata.pattern_type,
+                "total_time_ms": metadata.total_time_ms,
+                "agent_count": metadata.agent_count,
+                "handoff_count": metadata.handoff_count,
+                "cancelled": metadata.cancelled,
+                "extra": metadata.extra,
+            }));
+
+            for (name, output) in stage_result.agent_outputs {
+                result.agent_outputs.insert(format!("{}.{}", index + 1, name), output);
+            }
+            result.content = stage_result.content.clone();
+            current_input = stage_result.content;
+
+            if stage_result.metadata.cancelled {
+                result = result.with_cancelled();
+                break;
+            }
+        }
+
+        let stages_run = stage_metadata.len();
+        Ok(result
+            .with_time(start.elapsed().as_millis() as u64)
+            .with_extra("stages", serde_json::Value::Array(stage_metadata))
+            .with_extra("stages_run", serde_json::json!(stages_run))
+            .with_extra("stages_configured", serde_json::json!(self.stages.len())))
+    }
+
+    async fn plan(&self, input: &str) -> OrchestratorPlan {
+        let mut runs = Vec::new();
+        for stage in &self.stages {
+            runs.extend(stage.plan(input).await.runs);
+        }
+        OrchestratorPlan::from_runs(runs)
+    }
+
+    fn pattern_type(&self) -> &str {
+        "pipeline"
+    }
+
+    fn agent_count(&self) -> usize {
+        self.stages.iter().map(|stage| stage.agent_count()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::orchestrator::{AggregationStrategy, ConcurrentOrchestrator, SequentialOrchestrator};
+    use crate::testing::MockLlmClient;
+    use crate::Agent;
+    use std::sync::Arc;
+
+    fn agent(name: &str, reply: &str) -> (Agent, Arc<MockLlmClient>) {
+        let client = Arc::new(MockLlmClient::new().with_fallback_reply(format!("Final answer: {}", reply)));
+        let agent = Agent::builder()
+            .name(name)
+            .system_prompt("You are a test agent.")
+            .client(client.clone())
+            .build()
+            .unwrap();
+        (agent, client)
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_stage_feeds_sequential_stage() {
+        let (ports, _) = agent("Ports", "22 and 443 open");
+        let (vulns, _) = agent("Vulns", "OpenSSH 7.2 is outdated");
+        let (writer, writer_client) = agent("Writer", "summary written");
+        let (editor, editor_client) = agent("Editor", "final report");
+
+        let pipeline = PipelineOrchestrator::new(vec![Box::new(
+            ConcurrentOrchestrator::new(vec![ports, vulns]).with_aggregation(AggregationStrategy::Concatenate),
+        )])
+        .with_stage(SequentialOrchestrator::new(vec![writer, editor]));
+        assert_eq!(pipeline.stage_count(), 2);
+        assert_eq!(pipeline.agent_count(), 4);
+
+        let result = pipeline.execute("Assess 10.0.0.5").await.unwrap();
+        assert_eq!(result.content, "final report");
+
+        // The sequential stage received the concurrent stage's aggregate
+        let writer_input = writer_client.requests()[0].messages.last().unwrap().content.clone();
+        assert!(writer_input.contains("22 and 443 open"), "{}", writer_input);
+        assert!(writer_input.contains("OpenSSH 7.2 is outdated"), "{}", writer_input);
+        assert_eq!(editor_client.requests()[0].messages.last().unwrap().content, "summary written");
+
+        assert_eq!(result.metadata.pattern_type, "pipeline");
+        assert_eq!(result.metadata.agent_count, 4);
+        assert!(!result.metadata.cancelled);
+        let mut keys: Vec<_> = result.agent_outputs.keys().cloned().collect();
+        keys.sort();
+        assert_eq!(keys, ["1.Ports", "1.Vulns", "2.Editor", "2.Writer"]);
+
+        let stages = result.metadata.extra["stages"].as_array().unwrap();
+        assert_eq!(stages.len(), 2);
+        assert_eq!(stages[0]["pattern_type"], "concurrent");
+        assert_eq!(stages[0]["agent_count"], 2);
+        assert_eq!(stages[1]["pattern_type"], "sequential");
+        assert_eq!(stages[1]["extra"]["stages_run"], 2);
+        assert_eq!(result.metadata.extra["stages_run"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_failed_stage_names_the_stage() {
+        let (scanner, _) = agent("Scanner", "done");
+        let broken = Agent::builder()
+            .name("Broken")
+            .system_prompt("You are a test agent.")
+            .client(Arc::new(MockLlmClient::new().with_error("provider down")))
+            .build()
+            .unwrap();
+
+        let err = PipelineOrchestrator::new(Vec::new())
+            .with_stage(SequentialOrchestrator::single(scanner))
+            .with_stage(SequentialOrchestrator::single(broken))
+            .execute("go")
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("stage 2 (sequential)"), "{}", err);
+
+        assert!(PipelineOrchestrator::new(Vec::new()).execute("go").await.is_err());
+    }
+}
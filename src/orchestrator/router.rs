@@ -2,46 +2,76 @@
 //!
 //! A router agent triages requests and routes them
 //! to specialized agents based on domain expertise.
+//! Requests that match no specialist go to an optional default agent.
 
 use crate::error::Result;
 use crate::Agent;
+use crate::tracing_ext::sub_agent_span;
 use crate::handoffs::{Handoff, HandoffContext};
-use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorResult, AgentOutput};
+use crate::agent::PlanEstimate;
+use crate::orchestrator::pattern::{
+    finish_cancelled, OrchestratorPattern, OrchestratorPlan, OrchestratorResult, AgentOutput,
+};
 use crate::types::AgentId;
 use async_trait::async_trait;
-use std::collections::HashMap;
 use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
 
 /// Router orchestrator - triage and route to specialists
+///
+/// Specialists are tried in the order they were added, so when a router's
+/// answer matches several of them the first one registered wins.
 pub struct RouterOrchestrator {
     router_agent: Agent,
-    specialists: HashMap<String, Agent>,
+    specialists: Vec<(String, Agent)>,
+    default_agent: Option<Agent>,
 }
 
+/// Route name reported when the default agent handles a request
+pub const DEFAULT_ROUTE: &str = "default";
+
 impl RouterOrchestrator {
     /// Create a new router orchestrator
     pub fn new(router_agent: Agent) -> Self {
         Self {
             router_agent,
-            specialists: HashMap::new(),
+            specialists: Vec::new(),
+            default_agent: None,
         }
     }
 
-    /// Add a specialist agent
-    pub fn with_specialist(mut self, domain: impl Into<String>, agent: Agent) -> Self {
-        self.specialists.insert(domain.into(), agent);
+    /// Handle requests that match no specialist with this agent
+    pub fn with_default_agent(mut self, agent: Agent) -> Self {
+        self.default_agent = Some(agent);
         self
     }
 
-    /// Add multiple specialists
-    pub fn with_specialists(mut self, specialists: HashMap<String, Agent>) -> Self {
-        self.specialists.extend(specialists);
+    /// Add a specialist agent, replacing any earlier one for the same domain
+    pub fn with_specialist(mut self, domain: impl Into<String>, agent: Agent) -> Self {
+        let domain = domain.into();
+        match self.specialists.iter_mut().find(|(d, _)| *d == domain) {
+            Some((_, existing)) => *existing = agent,
+            None => self.specialists.push((domain, agent)),
+        }
         self
     }
 
+    /// Add multiple specialists, in iteration order
+    pub fn with_specialists(self, specialists: impl IntoIterator<Item = (String, Agent)>) -> Self {
+        specialists
+            .into_iter()
+            .fold(self, |router, (domain, agent)| router.with_specialist(domain, agent))
+    }
+
+    /// Specialist registered for `domain`
+    fn specialist(&self, domain: &str) -> Option<&Agent> {
+        self.specialists.iter().find(|(d, _)| d == domain).map(|(_, agent)| agent)
+    }
+
     /// Route to specialist handoff function
     fn route_to_specialist(&self, domain: &str, query: &str) -> Option<Handoff> {
-        self.specialists.get(domain).map(|specialist| {
+        self.specialist(domain).map(|specialist| {
             Handoff::new(
                 AgentId::new(),
                 AgentId::new(),
@@ -56,7 +86,7 @@ impl RouterOrchestrator {
         let output_lower = router_output.to_lowercase();
         
         // Look for explicit routing mentions
-        for domain in self.specialists.keys() {
+        for (domain, _) in &self.specialists {
             let domain_lower = domain.to_lowercase();
             if output_lower.contains(&format!("route to {}", domain_lower)) ||
                output_lower.contains(&format!("routing to {}", domain_lower)) ||
@@ -67,11 +97,18 @@ impl RouterOrchestrator {
         }
         
         // Try to find any specialist domain mentioned
-        for domain in self.specialists.keys() {
+        for (domain, _) in &self.specialists {
             if output_lower.contains(&domain.to_lowercase()) {
                 return Some(domain.clone());
             }
         }
+
+        // Fall back to a specialist advertising a mentioned capability
+        for (domain, specialist) in &self.specialists {
+            if specialist.capabilities().iter().any(|c| output_lower.contains(c.as_str())) {
+                return Some(domain.clone());
+            }
+        }
         
         None
     }
@@ -80,11 +117,16 @@ impl RouterOrchestrator {
 #[async_trait]
 impl OrchestratorPattern for RouterOrchestrator {
     async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+        self.execute_with_cancel(input, CancellationToken::new()).await
+    }
+
+    #[tracing::instrument(name = "orchestration", skip_all, fields(pattern = "router"))]
+    async fn execute_with_cancel(&self, input: &str, token: CancellationToken) -> Result<OrchestratorResult> {
         let start = Instant::now();
         let mut result = OrchestratorResult::new("", "router");
 
         // Build routing prompt with available specialists
-        let specialist_list: Vec<_> = self.specialists.keys().collect();
+        let specialist_list: Vec<_> = self.specialists.iter().map(|(domain, _)| domain).collect();
         let routing_prompt = format!(
             "You are a routing agent. Analyze this request and determine which specialist should handle it.\n\n\
              Available specialists: {:?}\n\n\
@@ -96,8 +138,19 @@ impl OrchestratorPattern for RouterOrchestrator {
 
         // Router agent makes decision
         let router_start = Instant::now();
-        let router_output = self.router_agent.react_loop(&routing_prompt).await?;
-        
+        let router_output = match self
+            .router_agent
+            .react_loop_with_cancel(&routing_prompt, token.clone())
+            .instrument(sub_agent_span("router", &self.router_agent.name))
+            .await
+        {
+            Ok(output) => output,
+            Err(e) => {
+                let name = format!("{} (Routing)", self.router_agent.name);
+                return finish_cancelled(result, name, router_start, start, e);
+            }
+        };
+
         result = result.with_agent_output(AgentOutput {
             agent_name: format!("{} (Routing)", self.router_agent.name),
             content: router_output.content.clone(),
@@ -107,9 +160,10 @@ impl OrchestratorPattern for RouterOrchestrator {
 
         // Parse routing decision
         let routed_domain = self.parse_routing(&router_output.content);
+        let mut route = routed_domain.clone();
 
         if let Some(domain) = &routed_domain {
-            if let Some(specialist) = self.specialists.get(domain) {
+            if let Some(specialist) = self.specialist(domain) {
                 // Create handoff
                 let _handoff = self.route_to_specialist(domain, input);
 
@@ -119,8 +173,19 @@ impl OrchestratorPattern for RouterOrchestrator {
                 );
 
                 let spec_start = Instant::now();
-                let spec_output = specialist.react_loop(&specialist_prompt).await?;
-                
+                let spec_output = match specialist
+                    .react_loop_with_cancel(&specialist_prompt, token.clone())
+                    .instrument(sub_agent_span("specialist", &specialist.name))
+                    .await
+                {
+                    Ok(output) => output,
+                    Err(e) => {
+                        let name = format!("{} ({})", specialist.name, domain);
+                        let result = result.with_handoffs(1).with_extra("route", serde_json::json!(domain));
+                        return finish_cancelled(result, name, spec_start, start, e);
+                    }
+                };
+
                 result = result.with_agent_output(AgentOutput {
                     agent_name: format!("{} ({})", specialist.name, domain),
                     content: spec_output.content.clone(),
@@ -131,6 +196,32 @@ impl OrchestratorPattern for RouterOrchestrator {
                 result.content = spec_output.content;
                 result = result.with_handoffs(1);
             }
+        } else if let Some(default_agent) = &self.default_agent {
+            // No specialist matched; the default agent takes the request as-is
+            let default_start = Instant::now();
+            let default_output = match default_agent
+                .react_loop_with_cancel(input, token.clone())
+                .instrument(sub_agent_span("default", &default_agent.name))
+                .await
+            {
+                Ok(output) => output,
+                Err(e) => {
+                    let name = format!("{} ({})", default_agent.name, DEFAULT_ROUTE);
+                    let result = result.with_handoffs(1).with_extra("route", serde_json::json!(DEFAULT_ROUTE));
+                    return finish_cancelled(result, name, default_start, start, e);
+                }
+            };
+
+            result = result.with_agent_output(AgentOutput {
+                agent_name: format!("{} ({})", default_agent.name, DEFAULT_ROUTE),
+                content: default_output.content.clone(),
+                loops_executed: default_output.trace.iteration_count(),
+                execution_time_ms: default_start.elapsed().as_millis() as u64,
+            });
+
+            result.content = default_output.content;
+            result = result.with_handoffs(1);
+            route = Some(DEFAULT_ROUTE.to_string());
         } else {
             // No specialist found, router handles directly
             result.content = format!(
@@ -141,16 +232,136 @@ impl OrchestratorPattern for RouterOrchestrator {
 
         result = result
             .with_time(start.elapsed().as_millis() as u64)
-            .with_extra("routed_to", serde_json::json!(routed_domain));
+            .with_extra("routed_to", serde_json::json!(routed_domain))
+            .with_extra("route", serde_json::json!(route));
 
         Ok(result)
     }
 
+    async fn plan(&self, input: &str) -> OrchestratorPlan {
+        // Only one route runs; budget for the costliest
+        let mut costliest: Option<PlanEstimate> = None;
+        for agent in self.specialists.iter().map(|(_, agent)| agent).chain(&self.default_agent) {
+            let run = agent.plan(input).await;
+            if costliest.as_ref().is_none_or(|c| {
+                (run.estimated_cost_usd, run.prompt_tokens) > (c.estimated_cost_usd, c.prompt_tokens)
+            }) {
+                costliest = Some(run);
+            }
+        }
+
+        let mut runs = vec![self.router_agent.plan(input).await];
+        runs.extend(costliest);
+        OrchestratorPlan::from_runs(runs)
+    }
+
     fn pattern_type(&self) -> &str {
         "router"
     }
 
     fn agent_count(&self) -> usize {
-        1 + self.specialists.len()
+        1 + self.specialists.len() + usize::from(self.default_agent.is_some())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockLlmClient;
+    use std::sync::Arc;
+
+    fn agent(name: &str, reply: &'static str) -> Agent {
+        Agent::builder()
+            .name(name)
+            .system_prompt("You are a test agent.")
+            .client(Arc::new(MockLlmClient::new().with_fallback_reply(reply)))
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_unmatched_request_goes_to_default_agent() {
+        let router = RouterOrchestrator::new(agent("Triage", "Final answer: this could be anything, hard to say"))
+            .with_specialist("security", agent("Security", "Final answer: audited"))
+            .with_specialist("development", agent("Dev", "Final answer: refactored"))
+            .with_default_agent(agent("Generalist", "Final answer: handled generally"));
+        assert_eq!(router.agent_count(), 4);
+
+        let result = router.execute("Can you help me with my thing?").await.unwrap();
+        assert_eq!(result.content, "handled generally");
+        assert_eq!(result.metadata.extra["route"], DEFAULT_ROUTE);
+        assert!(result.metadata.extra["routed_to"].is_null());
+
+        let routed = RouterOrchestrator::new(agent("Triage", "Final answer: Route to security"))
+            .with_specialist("security", agent("Security", "Final answer: audited"))
+            .with_default_agent(agent("Generalist", "Final answer: handled generally"));
+        let result = routed.execute("Audit the server").await.unwrap();
+        assert_eq!(result.content, "audited");
+        assert_eq!(result.metadata.extra["route"], "security");
+    }
+
+    #[test]
+    fn test_capability_fallback_prefers_first_registered() {
+        let capable = |name: &str| {
+            Agent::builder()
+                .name(name)
+                .system_prompt("You are a test agent.")
+                .capabilities(&["malware"])
+                .client(Arc::new(MockLlmClient::new()))
+                .build()
+                .unwrap()
+        };
+        let answer = "this looks like malware";
+
+        let router = RouterOrchestrator::new(agent("Triage", "Final answer: ok"))
+            .with_specialist("rootkits", capable("Rootkits"))
+            .with_specialist("forensics", capable("Forensics"));
+        assert_eq!(router.parse_routing(answer).as_deref(), Some("rootkits"));
+
+        let router = RouterOrchestrator::new(agent("Triage", "Final answer: ok"))
+            .with_specialist("forensics", capable("Forensics"))
+            .with_specialist("rootkits", capable("Rootkits"));
+        assert_eq!(router.parse_routing(answer).as_deref(), Some("forensics"));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_run_returns_partial_result() {
+        let router = RouterOrchestrator::new(agent("Triage", "Final answer: Route to security"))
+            .with_specialist("security", agent("Security", "Final answer: audited"));
+
+        let token = CancellationToken::new();
+        token.cancel();
+        let result = router.execute_with_cancel("Audit the server", token).await.unwrap();
+        assert!(result.metadata.cancelled);
+        assert!(result.agent_outputs.contains_key("Triage (Routing)"));
+        assert!(!result.agent_outputs.contains_key("Security (security)"));
+
+        let result = router.execute("Audit the server").await.unwrap();
+        assert!(!result.metadata.cancelled);
+        assert_eq!(result.content, "audited");
+    }
+
+    #[tokio::test]
+    async fn test_plan_budgets_for_costliest_route() {
+        let priced = |name: &str, prompt: &str, price: f64| {
+            Agent::builder()
+                .name(name)
+                .system_prompt(prompt)
+                .pricing(price, price)
+                .client(Arc::new(MockLlmClient::new().with_fallback_reply("Final answer: ok")))
+                .build()
+                .unwrap()
+        };
+        let router = RouterOrchestrator::new(priced("Triage", "Route requests.", 1.0))
+            .with_specialist("security", priced("Security", "Audit servers.", 10.0))
+            .with_specialist("network", priced("Network", "Inspect traffic.", 2.0))
+            .with_default_agent(agent("Generalist", "Final answer: ok"));
+
+        let plan = router.plan("Audit the server").await;
+        let names: Vec<&str> = plan.runs.iter().map(|r| r.agent_name.as_str()).collect();
+        assert_eq!(names, ["Triage", "Security"]);
+        let total: f64 = plan.runs.iter().filter_map(|r| r.estimated_cost_usd).sum();
+        assert!((plan.estimated_cost_usd.unwrap() - total).abs() < 1e-12);
+        assert!(plan.unpriced_agents.is_empty());
     }
 }
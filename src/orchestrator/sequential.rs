@@ -3,41 +3,171 @@
 //! Agents execute in order, with the output of each agent becoming
 //! the input for the next agent in the sequence.
 
+use crate::agent_file::CheckpointManager;
 use crate::error::Result;
+use crate::handoffs::{HandoffContext, HandoffStrategy};
+use crate::react::Observation;
 use crate::Agent;
-use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorResult, AgentOutput};
+use crate::tracing_ext::sub_agent_span;
+use crate::orchestrator::pattern::{
+    finish_cancelled, plan_runs, record_progress, render_synthesis_prompt, OrchestratorPattern, OrchestratorPlan,
+    OrchestratorResult, OrchestratorSnapshot, AgentOutput,
+};
 use async_trait::async_trait;
 use std::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+
+/// Predicate on a stage's output that ends the pipeline early
+pub type StopCondition = Box<dyn Fn(&AgentOutput) -> bool + Send + Sync>;
 
 /// Sequential orchestrator - agents execute in order
 pub struct SequentialOrchestrator {
     agents: Vec<Agent>,
+    synthesis_prompt: Option<String>,
+    stop_condition: Option<StopCondition>,
+    handoff_strategy: Option<HandoffStrategy>,
+    checkpoints: Option<CheckpointManager>,
+    progress: parking_lot::Mutex<Option<OrchestratorSnapshot>>,
+    resume: parking_lot::Mutex<Option<OrchestratorSnapshot>>,
 }
 
 impl SequentialOrchestrator {
     /// Create a new sequential orchestrator with given agents
     pub fn new(agents: Vec<Agent>) -> Self {
-        Self { agents }
+        Self {
+            agents,
+            synthesis_prompt: None,
+            stop_condition: None,
+            handoff_strategy: None,
+            checkpoints: None,
+            progress: parking_lot::Mutex::new(None),
+            resume: parking_lot::Mutex::new(None),
+        }
     }
 
     /// Create from a single agent (for simple chains)
     pub fn single(agent: Agent) -> Self {
-        Self { agents: vec![agent] }
+        Self::new(vec![agent])
+    }
+
+    /// Have the last agent synthesize every earlier stage's output
+    ///
+    /// `{inputs}` receives the earlier outputs and `{question}` the original
+    /// input. Without a template the last agent, like every other, sees only
+    /// the previous stage's output.
+    pub fn with_synthesis_prompt(mut self, template: impl Into<String>) -> Self {
+        self.synthesis_prompt = Some(template.into());
+        self
+    }
+
+    /// Stop after the first stage whose output satisfies `condition`
+    ///
+    /// The remaining agents are skipped and that stage's output is the result.
+    pub fn with_stop_condition(mut self, condition: impl Fn(&AgentOutput) -> bool + Send + Sync + 'static) -> Self {
+        self.stop_condition = Some(Box::new(condition));
+        self
+    }
+
+    /// Stop after the first stage whose output contains `keyword`, ignoring case
+    pub fn with_stop_keyword(self, keyword: impl Into<String>) -> Self {
+        let keyword = keyword.into().to_lowercase();
+        self.with_stop_condition(move |output| output.content.to_lowercase().contains(&keyword))
+    }
+
+    /// Pick each stage's agent with `strategy` instead of taking them in order
+    ///
+    /// The strategy sees earlier stage outputs as observations and the agents
+    /// that produced them as the handoff chain. A stage where it picks no one,
+    /// as with the mode-only strategies such as `Direct`, uses the agent at
+    /// that position. There is still one stage per configured agent.
+    pub fn with_handoff_strategy(mut self, strategy: HandoffStrategy) -> Self {
+        self.handoff_strategy = Some(strategy);
+        self
+    }
+
+    /// Agent that runs `stage`, given the outputs of the stages before it
+    fn stage_agent(&self, stage: usize, input: &str, stages: &[AgentOutput]) -> &Agent {
+        let Some(strategy) = &self.handoff_strategy else {
+            return &self.agents[stage];
+        };
+
+        let mut ctx = HandoffContext::new(input);
+        for output in stages {
+            ctx.observations.push(Observation::new(output.content.clone()));
+            if let Some(agent) = self.agents.iter().find(|a| a.name == output.agent_name) {
+                ctx.chain.push(agent.id);
+            }
+        }
+        let index = strategy.select_index(&ctx, &self.agents).unwrap_or(stage);
+        &self.agents[index]
+    }
+
+    /// Save a snapshot after every completed stage
+    pub fn with_checkpoints(mut self, manager: CheckpointManager) -> Self {
+        self.checkpoints = Some(manager);
+        self
+    }
+
+    /// Continue from `snapshot` on the next run instead of starting over
+    ///
+    /// The next `execute` must be given the snapshot's input; stages already
+    /// completed are skipped and the next one receives the last stage's output.
+    pub fn resume_from(self, snapshot: OrchestratorSnapshot) -> Self {
+        *self.resume.lock() = Some(snapshot);
+        self
     }
 }
 
 #[async_trait]
 impl OrchestratorPattern for SequentialOrchestrator {
     async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+        self.execute_with_cancel(input, CancellationToken::new()).await
+    }
+
+    #[tracing::instrument(name = "orchestration", skip_all, fields(pattern = "sequential"))]
+    async fn execute_with_cancel(&self, input: &str, token: CancellationToken) -> Result<OrchestratorResult> {
         let start = Instant::now();
         let mut result = OrchestratorResult::new("", "sequential");
         let mut current_input = input.to_string();
+        let mut stages: Vec<AgentOutput> = Vec::new();
+        *self.progress.lock() = None;
+        if let Some(snapshot) = self.resume.lock().take() {
+            snapshot.check("sequential", input)?;
+            stages = snapshot.agent_outputs.clone();
+            for output in stages.iter().cloned() {
+                result = result.with_agent_output(output);
+            }
+            if let Some(last) = stages.last() {
+                result.content = last.content.clone();
+                current_input = last.content.clone();
+            }
+            *self.progress.lock() = Some(snapshot);
+        }
 
-        for agent in &self.agents {
+        for stage in stages.len()..self.agents.len() {
+            let agent = self.stage_agent(stage, input, &stages);
             let agent_start = Instant::now();
+            if let Some(template) = &self.synthesis_prompt {
+                if stage > 0 && stage + 1 == self.agents.len() {
+                    let inputs = stages
+                        .iter()
+                        .map(|o| format!("### {}\n{}", o.agent_name, o.content))
+                        .collect::<Vec<_>>()
+                        .join("\n\n");
+                    current_input = render_synthesis_prompt(template, &inputs, input);
+                }
+            }
             
-            let output = agent.react_loop(&current_input).await?;
-            
+            let output = match agent
+                .react_loop_with_cancel(&current_input, token.clone())
+                .instrument(sub_agent_span("stage", &agent.name))
+                .await
+            {
+                Ok(output) => output,
+                Err(e) => return finish_cancelled(result, agent.name.clone(), agent_start, start, e),
+            };
+
             let agent_output = AgentOutput {
                 agent_name: agent.name.clone(),
                 content: output.content.clone(),
@@ -45,16 +175,40 @@ impl OrchestratorPattern for SequentialOrchestrator {
                 execution_time_ms: agent_start.elapsed().as_millis() as u64,
             };
             
+            let stop = self.stop_condition.as_ref().is_some_and(|condition| condition(&agent_output));
+            stages.push(agent_output.clone());
             result = result.with_agent_output(agent_output);
+            result.content = output.content.clone();
             current_input = output.content;
+
+            let mut snapshot = OrchestratorSnapshot::new("sequential", input);
+            snapshot.round = stages.len();
+            snapshot
+                .transcripts
+                .insert("stages".to_string(), stages.iter().map(|o| o.content.clone()).collect());
+            snapshot.agent_outputs = stages.clone();
+            record_progress(&self.progress, self.checkpoints.as_ref(), snapshot);
+            if stop {
+                break;
+            }
         }
 
-        result.content = current_input;
-        result = result.with_time(start.elapsed().as_millis() as u64);
+        result = result
+            .with_time(start.elapsed().as_millis() as u64)
+            .with_extra("stages_run", serde_json::json!(stages.len()))
+            .with_extra("stages_configured", serde_json::json!(self.agents.len()));
         
         Ok(result)
     }
 
+    fn snapshot(&self) -> Option<OrchestratorSnapshot> {
+        self.progress.lock().clone()
+    }
+
+    async fn plan(&self, input: &str) -> OrchestratorPlan {
+        plan_runs(self.agents.iter().collect(), input).await
+    }
+
     fn pattern_type(&self) -> &str {
         "sequential"
     }
@@ -63,3 +217,88 @@ impl OrchestratorPattern for SequentialOrchestrator {
         self.agents.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockLlmClient;
+    use std::sync::Arc;
+
+    /// Agent that always answers with `reply`, and its client for counting calls
+    fn agent(name: &str, reply: &str) -> (Agent, Arc<MockLlmClient>) {
+        let client = Arc::new(MockLlmClient::new().with_fallback_reply(reply));
+        let agent = Agent::builder()
+            .name(name)
+            .system_prompt("You are a test agent.")
+            .client(client.clone())
+            .build()
+            .unwrap();
+        (agent, client)
+    }
+
+    #[tokio::test]
+    async fn test_stop_condition_skips_remaining_stages() {
+        let (triage, _) = agent("Triage", "Final answer: known issue, RESOLVED by restarting");
+        let (escalation, escalation_client) = agent("Escalation", "Final answer: escalated");
+        let result = SequentialOrchestrator::new(vec![triage, escalation])
+            .with_stop_keyword("resolved")
+            .execute("The VPN is down")
+            .await
+            .unwrap();
+
+        assert_eq!(result.content, "known issue, RESOLVED by restarting");
+        assert_eq!(escalation_client.request_count(), 0);
+        assert_eq!(result.metadata.extra["stages_run"], 1);
+        assert_eq!(result.metadata.extra["stages_configured"], 2);
+
+        let (triage, _) = agent("Triage", "Final answer: needs a human");
+        let (escalation, escalation_client) = agent("Escalation", "Final answer: escalated");
+        let result = SequentialOrchestrator::new(vec![triage, escalation])
+            .with_stop_condition(|output| output.content.starts_with("RESOLVED"))
+            .execute("The VPN is down")
+            .await
+            .unwrap();
+        assert_eq!(result.content, "escalated");
+        assert_eq!(escalation_client.request_count(), 1);
+        assert_eq!(result.metadata.extra["stages_run"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_resume_skips_completed_stages() {
+        let (triage, _) = agent("Triage", "Final answer: needs a human");
+        let first = SequentialOrchestrator::single(triage);
+        first.execute("The VPN is down").await.unwrap();
+        let snapshot = first.snapshot().unwrap();
+        assert_eq!(snapshot.round, 1);
+        assert_eq!(snapshot.transcript("stages"), ["needs a human"]);
+
+        let (triage, triage_client) = agent("Triage", "Final answer: needs a human");
+        let (escalation, escalation_client) = agent("Escalation", "Final answer: escalated");
+        let resumed = SequentialOrchestrator::new(vec![triage, escalation]).resume_from(snapshot);
+        let result = resumed.execute("The VPN is down").await.unwrap();
+
+        assert_eq!(result.content, "escalated");
+        assert_eq!(triage_client.request_count(), 0);
+        assert_eq!(escalation_client.request_count(), 1);
+        assert_eq!(result.agent_outputs.len(), 2);
+        assert_eq!(result.metadata.extra["stages_run"], 2);
+        assert_eq!(resumed.snapshot().unwrap().round, 2);
+    }
+
+    #[tokio::test]
+    async fn test_handoff_strategy_picks_stage_agents() {
+        let (triage, _) = agent("Triage", "Final answer: DNS looks fine, hand over to Escalation");
+        let (network, network_client) = agent("Network", "Final answer: route fixed");
+        let (escalation, _) = agent("Escalation", "Final answer: paging Network");
+        let orchestrator = SequentialOrchestrator::new(vec![triage, network, escalation])
+            .with_handoff_strategy(HandoffStrategy::ByLlmDecision);
+        let result = orchestrator.execute("The VPN is down").await.unwrap();
+
+        let snapshot = orchestrator.snapshot().unwrap();
+        let order: Vec<&str> = snapshot.agent_outputs.iter().map(|o| o.agent_name.as_str()).collect();
+        assert_eq!(order, ["Triage", "Escalation", "Network"]);
+        assert_eq!(result.content, "route fixed");
+        // Network only saw Escalation's output
+        assert!(network_client.prompts()[0].contains("paging Network"));
+    }
+}
@@ -0,0 +1,198 @@
+//! Output post-processing
+//!
+//! An agent's final answer often needs cleaning before use: reasoning tags
+//! stripped, a code block or JSON object extracted, whitespace normalized.
+//! [`OutputTransform`]s registered with
+//! [`AgentBuilder::post_process`](crate::agent::AgentBuilder::post_process)
+//! run in order on the final answer; the untouched answer is kept in
+//! [`AgentOutput::raw_content`](crate::agent::AgentOutput::raw_content).
+//!
+//! Transforms that find nothing to extract return their input unchanged.
+
+use crate::react::{split_reasoning, ReasoningTags};
+
+/// A step in an agent's output post-processing pipeline
+pub trait OutputTransform: Send + Sync {
+    /// Name for tracing and debugging
+    fn name(&self) -> &str;
+
+    /// Transform the content
+    fn apply(&self, content: &str) -> String;
+}
+
+/// Run `content` through each transform in order
+pub fn apply_all(transforms: &[Box<dyn OutputTransform>], content: &str) -> String {
+    transforms
+        .iter()
+        .fold(content.to_string(), |content, transform| transform.apply(&content))
+}
+
+/// Extract the body of the first fenced code block in the given language
+///
+/// An empty language matches the first code block of any language.
+#[derive(Debug, Clone, Default)]
+pub struct ExtractCodeBlock(pub String);
+
+impl ExtractCodeBlock {
+    /// Extract the first block tagged with `lang` (e.g. `lean`, `rust`)
+    pub fn new(lang: impl Into<String>) -> Self {
+        Self(lang.into())
+    }
+
+    /// Extract the first block of any language
+    pub fn any() -> Self {
+        Self::default()
+    }
+}
+
+impl OutputTransform for ExtractCodeBlock {
+    fn name(&self) -> &str {
+        "extract_code_block"
+    }
+
+    fn apply(&self, content: &str) -> String {
+        let mut lines = content.lines();
+        while let Some(line) = lines.next() {
+            let Some(tag) = line.trim_start().strip_prefix("```") else {
+                continue;
+            };
+            let lang = tag.split_whitespace().next().unwrap_or("");
+            if !self.0.is_empty() && !lang.eq_ignore_ascii_case(&self.0) {
+                continue;
+            }
+
+            let body: Vec<&str> = lines
+                .by_ref()
+                .take_while(|line| line.trim_end() != "```")
+                .collect();
+            return body.join("\n");
+        }
+        content.to_string()
+    }
+}
+
+/// Remove `<think>` / `<thinking>` / `<reasoning>` blocks
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StripThinkTags;
+
+impl OutputTransform for StripThinkTags {
+    fn name(&self) -> &str {
+        "strip_think_tags"
+    }
+
+    fn apply(&self, content: &str) -> String {
+        split_reasoning(content, ReasoningTags::DEFAULT_TAGS).0
+    }
+}
+
+/// Trim surrounding whitespace, trailing spaces and runs of blank lines
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TrimWhitespace;
+
+impl OutputTransform for TrimWhitespace {
+    fn name(&self) -> &str {
+        "trim_whitespace"
+    }
+
+    fn apply(&self, content: &str) -> String {
+        let mut result = String::new();
+        let mut blank_run = 0;
+        for line in content.trim().lines() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                blank_run += 1;
+                if blank_run > 1 {
+                    continue;
+                }
+            } else {
+                blank_run = 0;
+            }
+            if !result.is_empty() {
+                result.push('\n');
+            }
+            result.push_str(line);
+        }
+        result
+    }
+}
+
+/// Extract the first complete JSON object in the content
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ExtractJsonObject;
+
+impl OutputTransform for ExtractJsonObject {
+    fn name(&self) -> &str {
+        "extract_json_object"
+    }
+
+    fn apply(&self, content: &str) -> String {
+        for (start, _) in content.match_indices('{') {
+            if let Some(end) = matching_brace(&content[start..]) {
+                let candidate = &content[start..start + end];
+                if serde_json::from_str::<serde_json::Value>(candidate).is_ok() {
+                    return candidate.to_string();
+                }
+            }
+        }
+        content.to_string()
+    }
+}
+
+/// Byte length of the balanced `{...}` at the start of `text`, skipping braces in strings
+fn matching_brace(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => {
+                depth = depth.checked_sub(1)?;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_code_block() {
+        let content = "Here:\n```rust\nfn a() {}\n```\n```lean\ntheorem t : True := trivial\n```";
+        assert_eq!(ExtractCodeBlock::new("lean").apply(content), "theorem t : True := trivial");
+        assert_eq!(ExtractCodeBlock::any().apply(content), "fn a() {}");
+        assert_eq!(ExtractCodeBlock::new("python").apply(content), content);
+    }
+
+    #[test]
+    fn test_extract_json_object() {
+        let content = "Result {not json} then {\"a\": {\"b\": \"}\"}, \"c\": 1} trailing";
+        assert_eq!(ExtractJsonObject.apply(content), "{\"a\": {\"b\": \"}\"}, \"c\": 1}");
+        assert_eq!(ExtractJsonObject.apply("none here"), "none here");
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let transforms: Vec<Box<dyn OutputTransform>> =
+            vec![Box::new(StripThinkTags), Box::new(TrimWhitespace)];
+        let content = "<think>\nplan it\n</think>\n\n  Answer:  \n\n\n\nDone.  \n";
+        assert_eq!(apply_all(&transforms, content), "Answer:\n\nDone.");
+        assert_eq!(StripThinkTags.apply("ok <thinking>truncated"), "ok");
+    }
+}
@@ -0,0 +1,341 @@
+//! Native port listing tool
+//!
+//! [`PortListTool`] reads sockets from `ss -tunap` with the parser the
+//! procinfo and tshark MCP servers use (`tool_common::ss`), and flags ports
+//! commonly used by malware or remote access. It replaces shelling out to the
+//! `portlist` script and returns one structured entry per socket.
+
+use crate::error::Result;
+use crate::tools::{JsonSchema, Tool, ToolContext, ToolOutput};
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::process::Command;
+
+pub use tool_common::parse::ParseError;
+pub use tool_common::ss::{parse_endpoint, parse_ss_line, SocketEndpoint, SocketEntry, SocketProcess};
+
+/// Ports flagged by default, with why they are suspicious
+///
+/// Matches the list in `tools/portlist`.
+pub const DEFAULT_SUSPICIOUS_PORTS: &[(u16, &str)] = &[
+    (1337, "Common backdoor"),
+    (3389, "RDP (Windows)"),
+    (4444, "Metasploit default"),
+    (5900, "VNC"),
+    (6667, "IRC"),
+    (6668, "IRC"),
+    (6669, "IRC"),
+    (8080, "HTTP Proxy (check if expected)"),
+    (9999, "Common backdoor"),
+    (12345, "NetBus"),
+    (31337, "Back Orifice"),
+    (54321, "Back Orifice"),
+];
+
+/// One socket as reported by [`PortListTool`]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PortEntry {
+    /// `tcp` or `udp`
+    pub protocol: String,
+    /// Owning process ID, when ss could see it
+    pub pid: Option<u32>,
+    /// Owning process name, when ss could see it
+    pub process: Option<String>,
+    /// Local address, e.g. `0.0.0.0:22` or `[::1]:631`
+    pub local: String,
+    /// Remote address, `*` ports for listening sockets
+    pub remote: String,
+    /// Socket state as ss prints it
+    pub state: String,
+    /// Whether either port is on the suspicious list
+    pub suspicious: bool,
+    /// Which port matched and why, for suspicious sockets
+    pub reason: Option<String>,
+}
+
+/// Which sockets to report
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StateFilter {
+    Listening,
+    Established,
+    All,
+}
+
+impl StateFilter {
+    fn matches(self, state: &str) -> bool {
+        match self {
+            Self::Listening => matches!(state, "LISTEN" | "UNCONN"),
+            Self::Established => state == "ESTAB",
+            Self::All => true,
+        }
+    }
+}
+
+/// Tool that lists sockets and flags suspicious ports
+///
+/// Takes `{ "states": "listening" | "established" | "all", "suspicious_only": bool }`,
+/// both optional. `data` carries `sockets` (an array of [`PortEntry`]),
+/// `suspicious_count` and `skipped` (lines ss printed that could not be
+/// parsed). Run as root to see the owners of other users' sockets.
+#[derive(Debug, Clone)]
+pub struct PortListTool {
+    ss_path: PathBuf,
+    suspicious_ports: BTreeMap<u16, String>,
+    timeout: Duration,
+}
+
+impl Default for PortListTool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PortListTool {
+    /// Run `ss` from `PATH`, flagging [`DEFAULT_SUSPICIOUS_PORTS`]
+    pub fn new() -> Self {
+        Self {
+            ss_path: PathBuf::from("ss"),
+            suspicious_ports: DEFAULT_SUSPICIOUS_PORTS
+                .iter()
+                .map(|(port, reason)| (*port, reason.to_string()))
+                .collect(),
+            timeout: Duration::from_secs(30),
+        }
+    }
+
+    /// Set the `ss` executable to run
+    pub fn with_ss_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.ss_path = path.into();
+        self
+    }
+
+    /// Replace the suspicious port list
+    pub fn with_suspicious_ports(mut self, ports: impl IntoIterator<Item = (u16, String)>) -> Self {
+        self.suspicious_ports = ports.into_iter().collect();
+        self
+    }
+
+    /// Flag one more port, or change the reason given for it
+    pub fn with_suspicious_port(mut self, port: u16, reason: impl Into<String>) -> Self {
+        self.suspicious_ports.insert(port, reason.into());
+        self
+    }
+
+    /// Set how long `ss` may run
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Why `socket` is suspicious, if it is
+    fn suspicion(&self, socket: &SocketEntry) -> Option<String> {
+        [("local", &socket.local), ("remote", &socket.remote)]
+            .into_iter()
+            .find_map(|(side, endpoint)| {
+                let port = endpoint.port?;
+                let reason = self.suspicious_ports.get(&port)?;
+                Some(format!("{} port {}: {}", side, port, reason))
+            })
+    }
+
+    /// Turn `ss -tunap` output into port entries, returning them with the number of lines skipped
+    pub fn analyze(&self, ss_output: &str) -> (Vec<PortEntry>, usize) {
+        let mut entries = Vec::new();
+        let mut skipped = 0;
+        for line in ss_output.lines().filter(|l| !l.trim().is_empty() && !l.starts_with("Netid")) {
+            let socket = match parse_ss_line(line) {
+                Ok(socket) => socket,
+                Err(e) => {
+                    tracing::debug!("Skipping ss line '{}': {}", line, e);
+                    skipped += 1;
+                    continue;
+                }
+            };
+            let reason = self.suspicion(&socket);
+            let owner = socket.processes.first();
+            entries.push(PortEntry {
+                protocol: socket.protocol.clone(),
+                pid: owner.map(|p| p.pid),
+                process: owner.map(|p| p.name.clone()),
+                local: socket.local.to_string(),
+                remote: socket.remote.to_string(),
+                state: socket.state.clone(),
+                suspicious: reason.is_some(),
+                reason,
+            });
+        }
+        (entries, skipped)
+    }
+}
+
+#[async_trait]
+impl Tool for PortListTool {
+    fn id(&self) -> &str {
+        "portlist"
+    }
+
+    fn name(&self) -> &str {
+        "Port List"
+    }
+
+    fn description(&self) -> &str {
+        "Lists listening ports and established connections with their owning processes, \
+         flagging ports commonly used by malware or remote access"
+    }
+
+    fn input_schema(&self) -> JsonSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "states".to_string(),
+            serde_json::json!({
+                "type": "string",
+                "enum": ["listening", "established", "all"],
+                "description": "Which sockets to list (default: all)"
+            }),
+        );
+        properties.insert(
+            "suspicious_only".to_string(),
+            serde_json::json!({
+                "type": "boolean",
+                "description": "Only list sockets on suspicious ports"
+            }),
+        );
+        JsonSchema::object(properties)
+    }
+
+    async fn execute(&self, params: Value, ctx: &ToolContext) -> Result<ToolOutput> {
+        let states = match params.get("states").and_then(|v| v.as_str()) {
+            Some("listening") => StateFilter::Listening,
+            Some("established") => StateFilter::Established,
+            _ => StateFilter::All,
+        };
+        let suspicious_only = params.get("suspicious_only").and_then(|v| v.as_bool()).unwrap_or(false);
+
+        ctx.progress(0.0, "reading sockets");
+        let mut cmd = Command::new(&self.ss_path);
+        cmd.arg("-tunap").kill_on_drop(true);
+        let output = match tokio::time::timeout(self.timeout, cmd.output()).await {
+            Err(_) => return Ok(ToolOutput::failure(format!("ss timed out after {:?}", self.timeout))),
+            Ok(Err(e)) => return Ok(ToolOutput::failure(format!("Failed to run {}: {}", self.ss_path.display(), e))),
+            Ok(Ok(output)) if !output.status.success() => {
+                return Ok(ToolOutput::failure(format!(
+                    "ss exited with status {}: {}",
+                    output.status,
+                    String::from_utf8_lossy(&output.stderr).trim()
+                )))
+            }
+            Ok(Ok(output)) => output,
+        };
+        ctx.progress(100.0, "sockets read");
+
+        let (mut entries, skipped) = self.analyze(&String::from_utf8_lossy(&output.stdout));
+        entries.retain(|e| states.matches(&e.state) && (!suspicious_only || e.suspicious));
+        let suspicious_count = entries.iter().filter(|e| e.suspicious).count();
+
+        let mut content = format!("{} sockets, {} suspicious", entries.len(), suspicious_count);
+        for entry in &entries {
+            let owner = match (&entry.process, entry.pid) {
+                (Some(name), Some(pid)) => format!("{} ({})", name, pid),
+                _ => "-".to_string(),
+            };
+            content.push_str(&format!(
+                "\n{:<4} {:<7} {:<30} {:<30} {}",
+                entry.protocol, entry.state, entry.local, entry.remote, owner
+            ));
+            if let Some(reason) = &entry.reason {
+                content.push_str(&format!(" [SUSPICIOUS: {}]", reason));
+            }
+        }
+
+        Ok(ToolOutput::success_with_data(
+            content,
+            serde_json::json!({
+                "sockets": entries,
+                "suspicious_count": suspicious_count,
+                "skipped": skipped,
+            }),
+        ))
+    }
+
+    fn cacheable(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SS_TUNAP: &str = "\
+Netid State   Recv-Q Send-Q                         Local Address:Port              Peer Address:Port Process
+tcp   LISTEN  0      128                                  0.0.0.0:22                     0.0.0.0:*     users:((\"sshd\",pid=812,fd=3))
+tcp   LISTEN  0      128                                    [::1]:631                       [::]:*     users:((\"cupsd\",pid=903,fd=7))
+tcp   LISTEN  0      5                                       [::]:4444                      [::]:*     users:((\"nc\",pid=6101,fd=3))
+tcp   ESTAB   0      0                     [::ffff:10.0.0.5]:51544          [::ffff:203.0.113.9]:31337 users:((\"Web Content\",pid=2231,fd=91))
+udp   UNCONN  0      0         [fe80::1c2a:3bff:fe4d:5e6f]%eth0:546                       [::]:*     users:((\"dhclient\",pid=700,fd=5))
+udp   UNCONN  0      0                                  127.0.0.53%lo:53                     0.0.0.0:*
+tcp   ESTAB   0      0                                10.0.0.5:22                     10.0.0.9:60212 users:((\"sshd\",pid=x9,fd=4))";
+
+    #[test]
+    fn test_analyze_flags_suspicious_ports() {
+        let (entries, skipped) = PortListTool::new().analyze(SS_TUNAP);
+        assert_eq!(entries.len(), 6);
+        assert_eq!(skipped, 1);
+
+        let sshd = &entries[0];
+        assert_eq!((sshd.pid, sshd.process.as_deref()), (Some(812), Some("sshd")));
+        assert_eq!((sshd.local.as_str(), sshd.remote.as_str()), ("0.0.0.0:22", "0.0.0.0:*"));
+        assert!(!sshd.suspicious);
+
+        let listener = &entries[2];
+        assert_eq!(listener.local, "[::]:4444");
+        assert_eq!(listener.reason.as_deref(), Some("local port 4444: Metasploit default"));
+
+        let outbound = &entries[3];
+        assert_eq!(outbound.state, "ESTAB");
+        assert_eq!(outbound.reason.as_deref(), Some("remote port 31337: Back Orifice"));
+
+        assert_eq!(entries[5].pid, None);
+        assert_eq!(entries.iter().filter(|e| e.suspicious).count(), 2);
+
+        let custom = PortListTool::new()
+            .with_suspicious_ports([(22, "SSH exposed".to_string())])
+            .analyze(SS_TUNAP)
+            .0;
+        assert!(custom[0].suspicious);
+        assert!(!custom[2].suspicious);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_execute_filters_and_reports_structured_sockets() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let fixture = dir.path().join("ss.txt");
+        std::fs::write(&fixture, SS_TUNAP).unwrap();
+        let ss = dir.path().join("ss");
+        std::fs::write(&ss, format!("#!/bin/sh\ncat '{}'\n", fixture.display())).unwrap();
+        std::fs::set_permissions(&ss, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let tool = PortListTool::new().with_ss_path(&ss);
+        let ctx = ToolContext::new(crate::types::AgentId::new());
+        let output = tool
+            .execute(serde_json::json!({"states": "listening", "suspicious_only": true}), &ctx)
+            .await
+            .unwrap();
+        assert!(output.success);
+        assert!(output.content.contains("[SUSPICIOUS: local port 4444: Metasploit default]"));
+        let data = output.data.unwrap();
+        assert_eq!(data["suspicious_count"], 1);
+        assert_eq!(data["sockets"][0]["pid"], 6101);
+        assert_eq!(data["sockets"][0]["process"], "nc");
+
+        let missing = PortListTool::new().with_ss_path("/nonexistent/ss");
+        assert!(!missing.execute(serde_json::json!({}), &ctx).await.unwrap().success);
+    }
+}
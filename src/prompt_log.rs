@@ -0,0 +1,185 @@
+//! Prompt audit log
+//!
+//! Records the exact [`CompletionRequest`] sent to the model on every turn,
+//! keyed by run and turn, together with the token usage it produced. This
+//! makes it possible to reconstruct (and replay) what the model actually saw.
+
+use crate::error::{Error, Result};
+use crate::guardrails::SecretRedactor;
+use crate::openrouter::CompletionRequest;
+use crate::types::{AgentId, TokenUsage, TraceId};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::io::AsyncWriteExt;
+
+/// One logged model request
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptRecord {
+    /// Run (one ReAct loop) this request belongs to
+    pub run_id: TraceId,
+    /// Turn within the run, starting at 0
+    pub turn: u32,
+    /// Agent that sent the request
+    pub agent_id: AgentId,
+    /// Agent name
+    pub agent_name: String,
+    /// When the request was sent
+    pub timestamp: DateTime<Utc>,
+    /// The exact request sent to the model
+    pub request: CompletionRequest,
+    /// Token usage reported for the request, if it succeeded
+    pub usage: Option<TokenUsage>,
+    /// Error message, if the request failed
+    pub error: Option<String>,
+}
+
+impl PromptRecord {
+    /// Redact secrets from message contents and tool-call arguments
+    pub fn redact(&mut self, redactor: &SecretRedactor) {
+        for message in &mut self.request.messages {
+            message.content = redactor.redact(&message.content).0;
+            for call in message.tool_calls.iter_mut().flatten() {
+                call.function.arguments = redactor.redact(&call.function.arguments).0;
+            }
+        }
+    }
+}
+
+/// Sink for prompt audit records
+#[async_trait]
+pub trait PromptLog: Send + Sync {
+    /// Persist a record
+    async fn record(&self, record: &PromptRecord) -> Result<()>;
+}
+
+/// In-memory prompt log, mainly for tests and short-lived processes
+#[derive(Debug, Default)]
+pub struct InMemoryPromptLog {
+    records: Mutex<Vec<PromptRecord>>,
+}
+
+impl InMemoryPromptLog {
+    /// Create an empty log
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// All records, in the order they were logged
+    pub fn records(&self) -> Vec<PromptRecord> {
+        self.records.lock().clone()
+    }
+
+    /// Records for one run, ordered by turn
+    pub fn run(&self, run_id: TraceId) -> Vec<PromptRecord> {
+        let mut records: Vec<PromptRecord> = self
+            .records
+            .lock()
+            .iter()
+            .filter(|r| r.run_id == run_id)
+            .cloned()
+            .collect();
+        records.sort_by_key(|r| r.turn);
+        records
+    }
+}
+
+#[async_trait]
+impl PromptLog for InMemoryPromptLog {
+    async fn record(&self, record: &PromptRecord) -> Result<()> {
+        self.records.lock().push(record.clone());
+        Ok(())
+    }
+}
+
+/// Append-only JSON Lines prompt log on disk
+#[derive(Debug)]
+pub struct JsonlPromptLog {
+    path: PathBuf,
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl JsonlPromptLog {
+    /// Log to the given file, creating it on first write
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Path of the log file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load all records from a log file for inspection or replay
+    pub fn load(path: impl AsRef<Path>) -> Result<Vec<PromptRecord>> {
+        let content = std::fs::read_to_string(path)?;
+        content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(Error::from))
+            .collect()
+    }
+}
+
+#[async_trait]
+impl PromptLog for JsonlPromptLog {
+    async fn record(&self, record: &PromptRecord) -> Result<()> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+        let _guard = self.lock.lock().await;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openrouter::Message;
+
+    fn record(run_id: TraceId, turn: u32, content: &str) -> PromptRecord {
+        PromptRecord {
+            run_id,
+            turn,
+            agent_id: AgentId::new(),
+            agent_name: "auditor".to_string(),
+            timestamp: Utc::now(),
+            request: CompletionRequest::new("test-model", vec![Message::user(content)]),
+            usage: Some(TokenUsage::new(10, 5)),
+            error: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_jsonl_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = JsonlPromptLog::new(dir.path().join("prompts.jsonl"));
+        let run_id = TraceId::new();
+
+        log.record(&record(run_id, 0, "first")).await.unwrap();
+        log.record(&record(run_id, 1, "second")).await.unwrap();
+
+        let loaded = JsonlPromptLog::load(log.path()).unwrap();
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[1].turn, 1);
+        assert_eq!(loaded[1].request.messages[0].content, "second");
+    }
+
+    #[test]
+    fn test_redact_record() {
+        let mut record = record(TraceId::new(), 0, "DB_PASSWORD=hunter2");
+        record.redact(&SecretRedactor::new());
+        assert_eq!(record.request.messages[0].content, "DB_PASSWORD=[REDACTED]");
+    }
+}
@@ -2,7 +2,10 @@
 
 use crate::types::{SpanId, TokenUsage};
 use chrono::{DateTime, Utc};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::OnceLock;
 
 /// Configuration for ReAct agent behavior
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -38,6 +41,203 @@ pub enum ReasoningFormat {
     XmlThinking,
     /// JSON structured reasoning
     JsonStructured,
+    /// Reasoning tokens returned by the model in a dedicated `reasoning` field
+    ///
+    /// Requests `include_reasoning`; for models that ignore it, reasoning is
+    /// stripped from the content by [`ReasoningTags`], with
+    /// [`ReasoningTags::Auto`] treating every model as a reasoning model.
+    NativeReasoning,
+}
+
+/// Reasoning delimiters stripped from model responses
+///
+/// Reasoning models wrap chain-of-thought in tags such as `<think>...</think>`.
+/// Stripped text is kept in [`AgentOutput::reasoning`](crate::agent::AgentOutput::reasoning)
+/// instead of leaking into the answer and into later prompts.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReasoningTags {
+    /// Strip [`ReasoningTags::DEFAULT_TAGS`] for known reasoning models only
+    #[default]
+    Auto,
+    /// Strip the given tag names (without angle brackets) for every model
+    Custom(Vec<String>),
+    /// Leave responses untouched
+    Disabled,
+}
+
+impl ReasoningTags {
+    /// Tags stripped by [`ReasoningTags::Auto`]
+    pub const DEFAULT_TAGS: &'static [&'static str] = &["think", "thinking", "reasoning"];
+
+    /// Model name fragments identifying reasoning models
+    const REASONING_MODELS: &'static [&'static str] =
+        &["deepseek-r1", "deepseek-reasoner", "qwq", "magistral", "-thinking"];
+
+    /// Whether `model` is a known reasoning model
+    pub fn is_reasoning_model(model: &str) -> bool {
+        let model = model.to_lowercase();
+        Self::REASONING_MODELS.iter().any(|name| model.contains(name))
+    }
+
+    /// Tags to strip for the given model (empty = none)
+    pub fn tags_for(&self, model: &str) -> Vec<String> {
+        match self {
+            Self::Auto if Self::is_reasoning_model(model) => {
+                Self::DEFAULT_TAGS.iter().map(|t| t.to_string()).collect()
+            }
+            Self::Custom(tags) => tags.clone(),
+            Self::Auto | Self::Disabled => Vec::new(),
+        }
+    }
+}
+
+/// Split `<tag>...</tag>` blocks out of `content`
+///
+/// Returns the remaining content and the joined block bodies, if any. An
+/// unclosed tag (e.g. a truncated response) runs to the end of the content.
+pub fn split_reasoning<S: AsRef<str>>(content: &str, tags: &[S]) -> (String, Option<String>) {
+    if tags.is_empty() {
+        return (content.to_string(), None);
+    }
+
+    let re = reasoning_pattern(tags);
+    let reasoning: Vec<&str> = re
+        .captures_iter(content)
+        .filter_map(|caps| caps.get(1))
+        .map(|body| body.as_str().trim())
+        .filter(|body| !body.is_empty())
+        .collect();
+    let reasoning = (!reasoning.is_empty()).then(|| reasoning.join("\n\n"));
+
+    (re.replace_all(content, "").trim().to_string(), reasoning)
+}
+
+/// Pattern matching blocks of any of `tags`, compiled once per tag set
+fn reasoning_pattern<S: AsRef<str>>(tags: &[S]) -> Regex {
+    static PATTERNS: OnceLock<parking_lot::Mutex<HashMap<String, Regex>>> = OnceLock::new();
+    let names = tags
+        .iter()
+        .map(|t| regex::escape(t.as_ref()))
+        .collect::<Vec<_>>()
+        .join("|");
+    PATTERNS
+        .get_or_init(Default::default)
+        .lock()
+        .entry(names)
+        .or_insert_with_key(|names| {
+            Regex::new(&format!(r"(?is)<(?:{names})>(.*?)(?:</(?:{names})>|\z)"))
+                .expect("escaped reasoning tags form a valid pattern")
+        })
+        .clone()
+}
+
+/// Parse tool-call arguments, repairing common model mistakes
+///
+/// Tries the text as-is, then strips markdown code fences, keeps the first
+/// balanced `{...}` object (dropping surrounding prose), removes trailing
+/// commas and converts single-quoted strings. Empty input is `{}`. When no
+/// repair helps, returns the error from parsing the original text.
+pub fn parse_action_input(raw: &str) -> serde_json::Result<serde_json::Value> {
+    let raw = raw.trim();
+    if raw.is_empty() {
+        return Ok(serde_json::json!({}));
+    }
+    let error = match serde_json::from_str(raw) {
+        Ok(value) => return Ok(value),
+        Err(error) => error,
+    };
+
+    let unfenced = strip_code_fences(raw);
+    let object = first_balanced_object(unfenced).unwrap_or(unfenced);
+    let without_commas = strip_trailing_commas(object);
+    let double_quoted = single_to_double_quotes(&without_commas);
+    let repaired = [unfenced, object, &without_commas, &double_quoted]
+        .into_iter()
+        .find_map(|candidate| serde_json::from_str(candidate).ok());
+    repaired.ok_or(error)
+}
+
+/// Body of the first ```` ``` ```` block, or the text itself if there is none
+fn strip_code_fences(text: &str) -> &str {
+    let Some(start) = text.find("```") else {
+        return text;
+    };
+    // Skip the info string (e.g. `json`) on the opening fence
+    let body = text[start + 3..].trim_start_matches(|c: char| c.is_ascii_alphanumeric());
+    body.find("```").map_or(body, |end| &body[..end]).trim()
+}
+
+/// The first balanced `{...}` in `text`, skipping braces inside quoted strings
+fn first_balanced_object(text: &str) -> Option<&str> {
+    let start = text.find('{')?;
+    let mut depth = 0usize;
+    let mut quote = None;
+    let mut escaped = false;
+    for (i, c) in text[start..].char_indices() {
+        if let Some(q) = quote {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                _ if c == q => quote = None,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' | '\'' => quote = Some(c),
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&text[start..start + i + 1]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Drop commas directly before a closing `}` or `]`
+fn strip_trailing_commas(text: &str) -> String {
+    static TRAILING_COMMA_RE: OnceLock<Regex> = OnceLock::new();
+    TRAILING_COMMA_RE
+        .get_or_init(|| Regex::new(r",(\s*[}\]])").expect("trailing comma pattern is valid"))
+        .replace_all(text, "$1")
+        .into_owned()
+}
+
+/// Rewrite `'single quoted'` strings as `"double quoted"` ones
+fn single_to_double_quotes(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut quote = None;
+    let mut escaped = false;
+    for c in text.chars() {
+        match quote {
+            Some(q) if escaped => {
+                escaped = false;
+                // `\'` needs no escape inside a double-quoted string
+                if !(q == '\'' && c == '\'') {
+                    out.push('\\');
+                }
+                out.push(c);
+            }
+            Some(_) if c == '\\' => escaped = true,
+            Some(q) if c == q => {
+                quote = None;
+                out.push('"');
+            }
+            Some('\'') if c == '"' => out.push_str("\\\""),
+            Some(_) => out.push(c),
+            None if c == '"' || c == '\'' => {
+                quote = Some(c);
+                out.push('"');
+            }
+            None => out.push(c),
+        }
+    }
+    out
 }
 
 /// A trace of ReAct loop execution
@@ -137,6 +337,9 @@ pub struct Thought {
     pub span_id: Option<SpanId>,
     /// Token usage for generating this thought
     pub tokens: TokenUsage,
+    /// Reasoning behind this thought, kept out of `content`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning: Option<String>,
 }
 
 impl Thought {
@@ -147,6 +350,7 @@ impl Thought {
             timestamp: Utc::now(),
             span_id: None,
             tokens: TokenUsage::default(),
+            reasoning: None,
         }
     }
 
@@ -161,6 +365,12 @@ impl Thought {
         self.tokens = tokens;
         self
     }
+
+    /// Set the reasoning
+    pub fn with_reasoning(mut self, reasoning: impl Into<String>) -> Self {
+        self.reasoning = Some(reasoning.into());
+        self
+    }
 }
 
 /// An action in the ReAct loop
@@ -248,6 +458,9 @@ pub struct Observation {
     pub is_error: bool,
     /// Span ID for tracing
     pub span_id: Option<SpanId>,
+    /// Whether the tool result was replayed from a tool cache
+    #[serde(default)]
+    pub from_cache: bool,
 }
 
 impl Observation {
@@ -258,6 +471,7 @@ impl Observation {
             timestamp: Utc::now(),
             is_error: false,
             span_id: None,
+            from_cache: false,
         }
     }
 
@@ -268,6 +482,7 @@ impl Observation {
             timestamp: Utc::now(),
             is_error: true,
             span_id: None,
+            from_cache: false,
         }
     }
 
@@ -277,3 +492,35 @@ impl Observation {
         self
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_action_input_recovers_malformed_json() {
+        let expected = json!({"path": "/tmp", "recursive": true});
+        for raw in [
+            r#"{"path": "/tmp", "recursive": true}"#,
+            "```json\n{\"path\": \"/tmp\", \"recursive\": true}\n```",
+            "```{\"path\": \"/tmp\", \"recursive\": true}```",
+            r#"{"path": "/tmp", "recursive": true} -- this lists the directory"#,
+            r#"Here are the arguments: {"path": "/tmp", "recursive": true}. Let me know."#,
+            r#"{"path": "/tmp", "recursive": true,}"#,
+            "{'path': '/tmp', 'recursive': true}",
+            "```python\n{'path': '/tmp', 'recursive': true,}\n```\nThat should do it.",
+        ] {
+            assert_eq!(parse_action_input(raw).unwrap(), expected, "{}", raw);
+        }
+
+        assert_eq!(parse_action_input("  ").unwrap(), json!({}));
+        assert_eq!(
+            parse_action_input(r#"{'query': 'it\'s "quoted"', 'tags': ['a', 'b',],}"#).unwrap(),
+            json!({"query": "it's \"quoted\"", "tags": ["a", "b"]})
+        );
+        assert_eq!(parse_action_input(r#"{"note": "a } brace"} extra"#).unwrap(), json!({"note": "a } brace"}));
+        assert!(parse_action_input(r#"{"path": /tmp}"#).is_err());
+        assert!(parse_action_input("no json here").is_err());
+    }
+}
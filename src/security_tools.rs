@@ -2,17 +2,21 @@
 //!
 //! This module provides dynamic discovery and execution of security tools
 //! from a tools directory. Tools can optionally have a `tool.json` metadata
-//! file for richer descriptions.
+//! file for richer descriptions. The registry can be rescanned with
+//! [`SecurityToolRegistry::reload`] or kept current with
+//! [`SecurityToolRegistry::watch`].
 
 use crate::error::Result;
 use crate::tools::{JsonSchema, Tool, ToolContext, ToolOutput};
 use async_trait::async_trait;
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 use tokio::sync::Semaphore;
 
 /// Category of security tool
@@ -97,7 +101,57 @@ pub struct SecurityTool {
     pub args: Vec<ToolArg>,
 }
 
+/// How the registry runs tools
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecutionPolicy {
+    /// Log the command instead of running it
+    pub dry_run: bool,
+    /// Prefix tools that require root with `sudo`
+    pub use_sudo: bool,
+}
+
+impl Default for ExecutionPolicy {
+    fn default() -> Self {
+        Self {
+            dry_run: false,
+            use_sudo: true,
+        }
+    }
+}
+
 impl SecurityTool {
+    /// The command line this tool would run with the given arguments
+    pub fn command_line(&self, args: &[String], use_sudo: bool) -> Vec<String> {
+        let mut line = Vec::new();
+        if self.requires_sudo && use_sudo {
+            line.push("sudo".to_string());
+        }
+        if let Some(timeout) = self.timeout_secs {
+            line.push("timeout".to_string());
+            line.push(timeout.to_string());
+        }
+        line.push(self.command_path.display().to_string());
+        line.extend(args.iter().cloned());
+        line
+    }
+
+    /// Execute this tool under the given policy
+    pub fn execute_with(&self, args: &[String], policy: ExecutionPolicy) -> ToolOutput {
+        if policy.dry_run {
+            let line = self.command_line(args, policy.use_sudo).join(" ");
+            tracing::info!("[dry-run] would run: {}", line);
+            return ToolOutput::success(format!("[dry-run] would run: {}", line));
+        }
+
+        if !policy.use_sudo {
+            let mut line = self.command_line(args, false).into_iter();
+            let program = line.next().unwrap_or_default();
+            return Self::run(Command::new(program).args(line));
+        }
+
+        self.execute(args)
+    }
+
     /// Execute this tool with the given arguments
     pub fn execute(&self, args: &[String]) -> ToolOutput {
         let mut cmd = if self.requires_sudo {
@@ -120,7 +174,10 @@ impl SecurityTool {
         };
 
         cmd.args(args);
+        Self::run(&mut cmd)
+    }
 
+    fn run(cmd: &mut Command) -> ToolOutput {
         match cmd.output() {
             Ok(output) => {
                 let stdout = String::from_utf8_lossy(&output.stdout);
@@ -148,13 +205,135 @@ impl SecurityTool {
     }
 }
 
+/// How a [`TagFilter`]'s tags are matched against a tool's tags
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MatchMode {
+    /// The tool has at least one of the tags (`all` matches every tool)
+    #[default]
+    Any,
+    /// The tool has every one of the tags
+    All,
+    /// The tool has none of the tags
+    None,
+}
+
+/// Selects tools by tag, for [`TaggedSecurityTools`] and the registry
+///
+/// Tags compare case-insensitively. Exclusions apply after the match, so
+/// "scanning but not sudo" is
+/// `TagFilter::any(&["scanning"]).excluding_sudo()`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TagFilter {
+    tags: Vec<String>,
+    mode: MatchMode,
+    excluded: Vec<String>,
+    exclude_sudo: bool,
+}
+
+impl TagFilter {
+    /// Match tools having at least one of `tags`
+    pub fn any(tags: &[&str]) -> Self {
+        Self::new(tags, MatchMode::Any)
+    }
+
+    /// Match tools having all of `tags`
+    pub fn all(tags: &[&str]) -> Self {
+        Self::new(tags, MatchMode::All)
+    }
+
+    /// Match tools having none of `tags`
+    pub fn none(tags: &[&str]) -> Self {
+        Self::new(tags, MatchMode::None)
+    }
+
+    /// Match `tags` with the given mode
+    pub fn new(tags: &[&str], mode: MatchMode) -> Self {
+        Self {
+            tags: tags.iter().map(|s| s.to_string()).collect(),
+            mode,
+            ..Self::default()
+        }
+    }
+
+    /// Also reject tools carrying any of `tags`
+    pub fn excluding(mut self, tags: &[&str]) -> Self {
+        self.excluded.extend(tags.iter().map(|s| s.to_string()));
+        self
+    }
+
+    /// Also reject tools that require sudo
+    pub fn excluding_sudo(mut self) -> Self {
+        self.exclude_sudo = true;
+        self
+    }
+
+    /// The tags being matched
+    pub fn tags(&self) -> &[String] {
+        &self.tags
+    }
+
+    /// How the tags are matched
+    pub fn mode(&self) -> MatchMode {
+        self.mode
+    }
+
+    /// Whether `tool` passes the filter
+    pub fn matches(&self, tool: &SecurityTool) -> bool {
+        let has = |tag: &String| tool.tags.iter().any(|t| t.eq_ignore_ascii_case(tag));
+        let matched = match self.mode {
+            MatchMode::Any => self.tags.iter().any(|t| t.eq_ignore_ascii_case("all")) || self.tags.iter().any(has),
+            MatchMode::All => self.tags.iter().all(has),
+            MatchMode::None => !self.tags.iter().any(has),
+        };
+        matched && !self.excluded.iter().any(has) && !(self.exclude_sudo && tool.requires_sudo)
+    }
+}
+
+impl std::fmt::Display for TagFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mode = match self.mode {
+            MatchMode::Any => "any of",
+            MatchMode::All => "all of",
+            MatchMode::None => "none of",
+        };
+        write!(f, "{} {:?}", mode, self.tags)?;
+        if !self.excluded.is_empty() {
+            write!(f, ", excluding {:?}", self.excluded)?;
+        }
+        if self.exclude_sudo {
+            write!(f, ", without sudo")?;
+        }
+        Ok(())
+    }
+}
+
+/// Tools found by one scan of the tools directory
+type ToolMap = HashMap<String, SecurityTool>;
+
+/// What changed in a [`SecurityToolRegistry::reload`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReloadSummary {
+    /// IDs of tools that were not registered before
+    pub added: Vec<String>,
+    /// IDs of tools that are no longer present
+    pub removed: Vec<String>,
+    /// IDs of tools whose `tool.json` could not be parsed; a previously
+    /// loaded version of the tool is kept
+    pub invalid: Vec<String>,
+}
+
 /// Registry of discovered security tools
+///
+/// Clones share the same tool set, so a reload is seen by every handle.
 #[derive(Debug, Clone)]
 pub struct SecurityToolRegistry {
     tools_dir: PathBuf,
-    tools: HashMap<String, SecurityTool>,
+    /// Swapped wholesale on reload; readers work on a snapshot
+    tools: Arc<RwLock<Arc<ToolMap>>>,
     /// Semaphore for controlling parallel execution (None = sequential)
     parallel_semaphore: Option<Arc<Semaphore>>,
+    /// How tools are run
+    policy: ExecutionPolicy,
 }
 
 impl SecurityToolRegistry {
@@ -164,38 +343,161 @@ impl SecurityToolRegistry {
     /// - Executable files (scripts, binaries)
     /// - Optional `tool.json` metadata files
     /// - MCP tool directories (with Cargo.toml)
+    ///
+    /// A tool whose `tool.json` is present but invalid is skipped with a warning.
     pub fn discover(tools_dir: impl AsRef<Path>) -> Self {
         let tools_dir = tools_dir.as_ref().to_path_buf();
+        let (tools, invalid) = Self::scan(&tools_dir);
+        for id in &invalid {
+            tracing::warn!("Skipping security tool '{}': invalid tool.json", id);
+        }
+
+        tracing::info!("Discovered {} security tools from {:?}", tools.len(), tools_dir);
+
+        Self {
+            tools_dir,
+            tools: Arc::new(RwLock::new(Arc::new(tools))),
+            parallel_semaphore: None, // Sequential by default
+            policy: ExecutionPolicy::default(),
+        }
+    }
+
+    /// Scan `tools_dir`, returning the tools found and the IDs of tools with invalid metadata
+    fn scan(tools_dir: &Path) -> (ToolMap, Vec<String>) {
         let mut tools = HashMap::new();
+        let mut invalid = Vec::new();
 
-        if let Ok(entries) = std::fs::read_dir(&tools_dir) {
+        if let Ok(entries) = std::fs::read_dir(tools_dir) {
             for entry in entries.flatten() {
                 let path = entry.path();
 
                 // Handle directories (potential MCP tools)
-                if path.is_dir() {
-                    if let Some(tool) = Self::discover_mcp_tool(&path) {
+                let discovered = if path.is_dir() {
+                    Self::discover_mcp_tool(&path)
+                } else if Self::is_executable(&path) {
+                    Self::discover_shell_tool(&path)
+                } else {
+                    None
+                };
+
+                match discovered {
+                    Some(Ok(tool)) => {
                         tools.insert(tool.id.clone(), tool);
                     }
-                    continue;
+                    Some(Err(id)) => invalid.push(id),
+                    None => {}
                 }
+            }
+        }
 
-                // Handle executable files
-                if Self::is_executable(&path) {
-                    if let Some(tool) = Self::discover_shell_tool(&path) {
-                        tools.insert(tool.id.clone(), tool);
-                    }
+        invalid.sort();
+        (tools, invalid)
+    }
+
+    /// Rescan the tools directory and swap in the new tool set
+    ///
+    /// Tools whose `tool.json` is mid-write or otherwise invalid keep their
+    /// previously loaded definition rather than disappearing.
+    pub fn reload(&self) -> ReloadSummary {
+        let (mut tools, invalid) = Self::scan(&self.tools_dir);
+        let mut current = self.tools.write();
+
+        for id in &invalid {
+            match current.get(id) {
+                Some(previous) => {
+                    tracing::warn!("Invalid tool.json for '{}', keeping the loaded version", id);
+                    tools.insert(id.clone(), previous.clone());
                 }
+                None => tracing::warn!("Skipping security tool '{}': invalid tool.json", id),
             }
         }
 
-        tracing::info!("Discovered {} security tools from {:?}", tools.len(), tools_dir);
+        let mut summary = ReloadSummary {
+            added: tools.keys().filter(|id| !current.contains_key(*id)).cloned().collect(),
+            removed: current.keys().filter(|id| !tools.contains_key(*id)).cloned().collect(),
+            invalid,
+        };
+        summary.added.sort();
+        summary.removed.sort();
+
+        if !summary.added.is_empty() || !summary.removed.is_empty() {
+            tracing::info!(
+                "Reloaded security tools from {:?}: +{:?} -{:?}",
+                self.tools_dir,
+                summary.added,
+                summary.removed
+            );
+        }
+        *current = Arc::new(tools);
+        summary
+    }
 
-        Self {
-            tools_dir,
-            tools,
-            parallel_semaphore: None, // Sequential by default
+    /// Reload whenever the tools directory changes, checking every `poll_interval`
+    ///
+    /// Changes are detected from the modification times of the directory's
+    /// entries, their `tool.json` files and built MCP binaries. The returned
+    /// task runs until aborted. Must be called within a Tokio runtime.
+    pub fn watch(&self, poll_interval: Duration) -> tokio::task::JoinHandle<()> {
+        let registry = self.clone();
+        let mut last = Self::fingerprint(&registry.tools_dir);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(poll_interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let dir = registry.tools_dir.clone();
+                let current = match tokio::task::spawn_blocking(move || Self::fingerprint(&dir)).await {
+                    Ok(fingerprint) => fingerprint,
+                    Err(_) => continue,
+                };
+                if current != last {
+                    last = current;
+                    let registry = registry.clone();
+                    let _ = tokio::task::spawn_blocking(move || registry.reload()).await;
+                }
+            }
+        })
+    }
+
+    /// Modification times of everything discovery looks at
+    fn fingerprint(tools_dir: &Path) -> Vec<(Option<SystemTime>, PathBuf)> {
+        let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut paths = vec![tools_dir.to_path_buf()];
+        if let Ok(entries) = std::fs::read_dir(tools_dir) {
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    for nested in ["tool.json", "Cargo.toml", "target/release", "target/debug"] {
+                        paths.push(path.join(nested));
+                    }
+                }
+                paths.push(path);
+            }
         }
+        paths.sort();
+        paths.into_iter().map(|path| (modified(&path), path)).collect()
+    }
+
+    /// Current tool set
+    fn snapshot(&self) -> Arc<ToolMap> {
+        self.tools.read().clone()
+    }
+
+    /// Log commands instead of running them
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.policy.dry_run = dry_run;
+        self
+    }
+
+    /// Control whether tools marked `requires_sudo` are run through sudo
+    pub fn with_sudo(mut self, use_sudo: bool) -> Self {
+        self.policy.use_sudo = use_sudo;
+        self
+    }
+
+    /// Current execution policy
+    pub fn policy(&self) -> ExecutionPolicy {
+        self.policy
     }
 
     /// Enable parallel execution with a maximum concurrency limit
@@ -215,20 +517,25 @@ impl SecurityToolRegistry {
     }
 
     /// Discover an MCP tool from a directory
-    fn discover_mcp_tool(dir: &Path) -> Option<SecurityTool> {
+    ///
+    /// `Some(Err(id))` means the directory is a tool but its `tool.json` is invalid.
+    fn discover_mcp_tool(dir: &Path) -> Option<std::result::Result<SecurityTool, String>> {
         // Check for Cargo.toml (Rust MCP tool)
         let cargo_path = dir.join("Cargo.toml");
         if !cargo_path.exists() {
             return None;
         }
 
-        // Check for tool.json metadata
-        let metadata_path = dir.join("tool.json");
-        let metadata = Self::read_metadata(&metadata_path);
-
         let dir_name = dir.file_name()?.to_str()?;
         let id = dir_name.trim_end_matches("-mcp").to_string();
 
+        // Check for tool.json metadata
+        let metadata_path = dir.join("tool.json");
+        let metadata = match Self::read_metadata(&metadata_path) {
+            Ok(metadata) => metadata,
+            Err(()) => return Some(Err(id)),
+        };
+
         // Try to find the built binary
         let binary_path = dir.join("target/release").join(&id);
         let debug_binary_path = dir.join("target/debug").join(&id);
@@ -242,7 +549,7 @@ impl SecurityToolRegistry {
             dir.to_path_buf()
         };
 
-        Some(SecurityTool {
+        Some(Ok(SecurityTool {
             id: id.clone(),
             name: metadata.as_ref().map(|m| m.name.clone()).unwrap_or_else(|| {
                 id.replace('-', " ").replace('_', " ")
@@ -256,11 +563,11 @@ impl SecurityToolRegistry {
             requires_sudo: metadata.as_ref().map(|m| m.requires_sudo).unwrap_or(false),
             timeout_secs: metadata.as_ref().and_then(|m| m.timeout_secs),
             args: metadata.map(|m| m.args).unwrap_or_default(),
-        })
+        }))
     }
 
     /// Discover a shell tool (script or binary)
-    fn discover_shell_tool(path: &Path) -> Option<SecurityTool> {
+    fn discover_shell_tool(path: &Path) -> Option<std::result::Result<SecurityTool, String>> {
         let file_name = path.file_name()?.to_str()?;
         
         // Skip known non-tool files
@@ -271,13 +578,16 @@ impl SecurityToolRegistry {
             return None;
         }
 
+        let id = path.file_stem()?.to_str()?.to_string();
+
         // Check for adjacent tool.json
         let metadata_path = path.with_extension("json");
-        let metadata = Self::read_metadata(&metadata_path);
-
-        let id = path.file_stem()?.to_str()?.to_string();
+        let metadata = match Self::read_metadata(&metadata_path) {
+            Ok(metadata) => metadata,
+            Err(()) => return Some(Err(id)),
+        };
 
-        Some(SecurityTool {
+        Some(Ok(SecurityTool {
             id: id.clone(),
             name: metadata.as_ref().map(|m| m.name.clone()).unwrap_or_else(|| {
                 id.replace('-', " ").replace('_', " ")
@@ -291,17 +601,19 @@ impl SecurityToolRegistry {
             requires_sudo: metadata.as_ref().map(|m| m.requires_sudo).unwrap_or(false),
             timeout_secs: metadata.as_ref().and_then(|m| m.timeout_secs),
             args: metadata.map(|m| m.args).unwrap_or_default(),
-        })
+        }))
     }
 
     /// Read tool.json metadata file
-    fn read_metadata(path: &Path) -> Option<ToolMetadata> {
+    ///
+    /// `Ok(None)` when there is no file, `Err(())` when it cannot be read or parsed.
+    fn read_metadata(path: &Path) -> std::result::Result<Option<ToolMetadata>, ()> {
         if !path.exists() {
-            return None;
+            return Ok(None);
         }
 
-        let content = std::fs::read_to_string(path).ok()?;
-        serde_json::from_str(&content).ok()
+        let content = std::fs::read_to_string(path).map_err(|_| ())?;
+        serde_json::from_str(&content).map(Some).map_err(|_| ())
     }
 
     /// Check if a file is executable
@@ -324,42 +636,36 @@ impl SecurityToolRegistry {
     }
 
     /// Get all discovered tools
-    pub fn tools(&self) -> impl Iterator<Item = &SecurityTool> {
-        self.tools.values()
+    pub fn tools(&self) -> Vec<SecurityTool> {
+        self.snapshot().values().cloned().collect()
     }
 
     /// Get a tool by ID
-    pub fn get(&self, id: &str) -> Option<&SecurityTool> {
-        self.tools.get(id)
+    pub fn get(&self, id: &str) -> Option<SecurityTool> {
+        self.snapshot().get(id).cloned()
     }
 
     /// Get tools by category
-    pub fn by_category(&self, category: SecurityCategory) -> Vec<&SecurityTool> {
-        self.tools.values().filter(|t| t.category == category).collect()
+    pub fn by_category(&self, category: SecurityCategory) -> Vec<SecurityTool> {
+        self.snapshot().values().filter(|t| t.category == category).cloned().collect()
     }
 
     /// Get tools matching any of the specified tags.
     /// 
     /// If tags contains "all", returns all tools.
     /// Otherwise, returns tools that have at least one matching tag.
-    pub fn by_tags(&self, tags: &[&str]) -> Vec<&SecurityTool> {
-        // "all" tag means return everything
-        if tags.iter().any(|t| t.eq_ignore_ascii_case("all")) {
-            return self.tools.values().collect();
-        }
+    pub fn by_tags(&self, tags: &[&str]) -> Vec<SecurityTool> {
+        self.filter(&TagFilter::any(tags))
+    }
 
-        self.tools.values()
-            .filter(|tool| {
-                tool.tags.iter().any(|tool_tag| {
-                    tags.iter().any(|filter_tag| tool_tag.eq_ignore_ascii_case(filter_tag))
-                })
-            })
-            .collect()
+    /// Get tools passing `filter`
+    pub fn filter(&self, filter: &TagFilter) -> Vec<SecurityTool> {
+        self.snapshot().values().filter(|tool| filter.matches(tool)).cloned().collect()
     }
 
     /// Get all unique tags across all tools
     pub fn all_tags(&self) -> Vec<String> {
-        let mut tags: Vec<String> = self.tools.values()
+        let mut tags: Vec<String> = self.snapshot().values()
             .flat_map(|t| t.tags.iter().cloned())
             .collect();
         tags.sort();
@@ -367,9 +673,18 @@ impl SecurityToolRegistry {
         tags
     }
 
+    /// Number of tools carrying each tag
+    pub fn tag_counts(&self) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for tag in self.snapshot().values().flat_map(|t| t.tags.iter()) {
+            *counts.entry(tag.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+
     /// Check if a tool has a specific tag
     pub fn has_tag(&self, tool_id: &str, tag: &str) -> bool {
-        self.tools.get(tool_id)
+        self.snapshot().get(tool_id)
             .map(|t| t.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
             .unwrap_or(false)
     }
@@ -379,8 +694,9 @@ impl SecurityToolRegistry {
         let mut descriptions = Vec::new();
         
         // Group by category
+        let tools = self.snapshot();
         let mut by_category: HashMap<SecurityCategory, Vec<&SecurityTool>> = HashMap::new();
-        for tool in self.tools.values() {
+        for tool in tools.values() {
             by_category.entry(tool.category.clone()).or_default().push(tool);
         }
 
@@ -412,15 +728,16 @@ impl SecurityToolRegistry {
 
     /// Execute a tool by ID with arguments
     pub fn execute(&self, tool_id: &str, args: &[String]) -> Result<ToolOutput> {
-        let tool = self.tools.get(tool_id)
+        let tools = self.snapshot();
+        let tool = tools.get(tool_id)
             .ok_or_else(|| crate::error::Error::tool_execution(
                 tool_id,
                 format!("Tool '{}' not found. Available tools: {:?}", 
                     tool_id, 
-                    self.tools.keys().collect::<Vec<_>>())
+                    tools.keys().collect::<Vec<_>>())
             ))?;
 
-        Ok(tool.execute(args))
+        Ok(tool.execute_with(args, self.policy))
     }
 
     /// Get the tools directory path
@@ -430,12 +747,12 @@ impl SecurityToolRegistry {
 
     /// Get the number of discovered tools
     pub fn len(&self) -> usize {
-        self.tools.len()
+        self.tools.read().len()
     }
 
     /// Check if the registry is empty
     pub fn is_empty(&self) -> bool {
-        self.tools.is_empty()
+        self.tools.read().is_empty()
     }
 }
 
@@ -492,10 +809,10 @@ impl Tool for ListSecurityTools {
                 _ => None,
             });
 
-        let tools: Vec<&SecurityTool> = if let Some(cat) = category_filter {
+        let tools: Vec<SecurityTool> = if let Some(cat) = category_filter {
             self.registry.by_category(cat)
         } else {
-            self.registry.tools().collect()
+            self.registry.tools()
         };
 
         if tools.is_empty() {
@@ -560,7 +877,7 @@ impl Tool for RunSecurityTool {
         JsonSchema::object(properties).with_required(vec!["tool_id".to_string()])
     }
 
-    async fn execute(&self, params: Value, _ctx: &ToolContext) -> Result<ToolOutput> {
+    async fn execute(&self, params: Value, ctx: &ToolContext) -> Result<ToolOutput> {
         let tool_id = params
             .get("tool_id")
             .and_then(|v| v.as_str())
@@ -578,7 +895,10 @@ impl Tool for RunSecurityTool {
 
         tracing::info!("Executing security tool '{}' with args: {:?}", tool_id, args);
 
-        self.registry.execute(tool_id, &args)
+        ctx.progress(0.0, format!("running {}", tool_id));
+        let output = self.registry.execute(tool_id, &args);
+        ctx.progress(100.0, format!("{} finished", tool_id));
+        output
     }
 }
 
@@ -588,7 +908,7 @@ impl Tool for RunSecurityTool {
 /// expose tools matching the specified tags.
 pub struct TaggedSecurityTools {
     registry: Arc<SecurityToolRegistry>,
-    tags: Vec<String>,
+    filter: TagFilter,
 }
 
 impl TaggedSecurityTools {
@@ -605,49 +925,87 @@ impl TaggedSecurityTools {
     /// let tools = security_tools.create_tools();
     /// ```
     pub fn new(registry: Arc<SecurityToolRegistry>, tags: &[&str]) -> Self {
-        Self {
-            registry,
-            tags: tags.iter().map(|s| s.to_string()).collect(),
-        }
+        Self::with_filter(registry, TagFilter::any(tags))
+    }
+
+    /// Create a helper exposing the tools that pass `filter`
+    ///
+    /// # Example
+    /// ```ignore
+    /// // Network tools that are also read-only, and never need sudo
+    /// let filter = TagFilter::all(&["network", "readonly"]).excluding_sudo();
+    /// let security_tools = TaggedSecurityTools::with_filter(registry.clone(), filter);
+    /// ```
+    pub fn with_filter(registry: Arc<SecurityToolRegistry>, filter: TagFilter) -> Self {
+        Self { registry, filter }
+    }
+
+    /// Match the tags with `mode` instead of [`MatchMode::Any`]
+    pub fn match_mode(mut self, mode: MatchMode) -> Self {
+        self.filter.mode = mode;
+        self
+    }
+
+    /// Leave out tools carrying any of `tags`
+    pub fn excluding(mut self, tags: &[&str]) -> Self {
+        self.filter = self.filter.excluding(tags);
+        self
+    }
+
+    /// Leave out tools that require sudo
+    pub fn excluding_sudo(mut self) -> Self {
+        self.filter = self.filter.excluding_sudo();
+        self
     }
 
-    /// Get the filtered tools from the registry
-    pub fn filtered_tools(&self) -> Vec<&SecurityTool> {
-        let tag_refs: Vec<&str> = self.tags.iter().map(|s| s.as_str()).collect();
-        self.registry.by_tags(&tag_refs)
+    /// Get the filtered tools from the registry's current tool set
+    pub fn filtered_tools(&self) -> Vec<SecurityTool> {
+        self.registry.filter(&self.filter)
     }
 
     /// Create ListSecurityTools and RunSecurityTool for agents.
     /// Returns a vector of Arc<dyn Tool> ready to add to an agent.
+    /// The tools look up the registry on every call, so they follow reloads.
     pub fn create_tools(&self) -> Vec<Arc<dyn Tool>> {
         vec![
-            Arc::new(TaggedListSecurityTools::new(
+            Arc::new(TaggedListSecurityTools::with_filter(
                 self.registry.clone(),
-                self.tags.clone(),
+                self.filter.clone(),
             )) as Arc<dyn Tool>,
-            Arc::new(TaggedRunSecurityTool::new(
+            Arc::new(TaggedRunSecurityTool::with_filter(
                 self.registry.clone(),
-                self.tags.clone(),
+                self.filter.clone(),
             )) as Arc<dyn Tool>,
         ]
     }
 
     /// Get the tags this helper filters by
     pub fn tags(&self) -> &[String] {
-        &self.tags
+        self.filter.tags()
+    }
+
+    /// Get the full filter
+    pub fn filter(&self) -> &TagFilter {
+        &self.filter
     }
 }
 
 /// List security tools filtered by tags
 pub struct TaggedListSecurityTools {
     registry: Arc<SecurityToolRegistry>,
-    tags: Vec<String>,
+    filter: TagFilter,
 }
 
 impl TaggedListSecurityTools {
     /// Create a new tagged list tools
     pub fn new(registry: Arc<SecurityToolRegistry>, tags: Vec<String>) -> Self {
-        Self { registry, tags }
+        let tag_refs: Vec<&str> = tags.iter().map(|s| s.as_str()).collect();
+        Self::with_filter(registry, TagFilter::any(&tag_refs))
+    }
+
+    /// List only the tools that pass `filter`
+    pub fn with_filter(registry: Arc<SecurityToolRegistry>, filter: TagFilter) -> Self {
+        Self { registry, filter }
     }
 }
 
@@ -694,8 +1052,7 @@ impl Tool for TaggedListSecurityTools {
             });
 
         // First filter by tags
-        let tag_refs: Vec<&str> = self.tags.iter().map(|s| s.as_str()).collect();
-        let mut tools: Vec<&SecurityTool> = self.registry.by_tags(&tag_refs);
+        let mut tools: Vec<SecurityTool> = self.registry.filter(&self.filter);
 
         // Then filter by category if specified
         if let Some(cat) = category_filter {
@@ -704,8 +1061,8 @@ impl Tool for TaggedListSecurityTools {
 
         if tools.is_empty() {
             return Ok(ToolOutput::success(format!(
-                "No security tools found matching tags: {:?}",
-                self.tags
+                "No security tools found matching tags: {}",
+                self.filter
             )));
         }
 
@@ -729,13 +1086,19 @@ impl Tool for TaggedListSecurityTools {
 /// Run security tool filtered by tags
 pub struct TaggedRunSecurityTool {
     registry: Arc<SecurityToolRegistry>,
-    tags: Vec<String>,
+    filter: TagFilter,
 }
 
 impl TaggedRunSecurityTool {
     /// Create a new tagged run tool
     pub fn new(registry: Arc<SecurityToolRegistry>, tags: Vec<String>) -> Self {
-        Self { registry, tags }
+        let tag_refs: Vec<&str> = tags.iter().map(|s| s.as_str()).collect();
+        Self::with_filter(registry, TagFilter::any(&tag_refs))
+    }
+
+    /// Run only the tools that pass `filter`
+    pub fn with_filter(registry: Arc<SecurityToolRegistry>, filter: TagFilter) -> Self {
+        Self { registry, filter }
     }
 }
 
@@ -774,21 +1137,19 @@ impl Tool for TaggedRunSecurityTool {
         JsonSchema::object(properties).with_required(vec!["tool_id".to_string()])
     }
 
-    async fn execute(&self, params: Value, _ctx: &ToolContext) -> Result<ToolOutput> {
+    async fn execute(&self, params: Value, ctx: &ToolContext) -> Result<ToolOutput> {
         let tool_id = params
             .get("tool_id")
             .and_then(|v| v.as_str())
             .ok_or_else(|| crate::error::Error::InvalidInput("Missing 'tool_id' parameter".into()))?;
 
         // Check if the tool is allowed by tags
-        let tag_refs: Vec<&str> = self.tags.iter().map(|s| s.as_str()).collect();
-        let allowed_tools = self.registry.by_tags(&tag_refs);
-        
-        if !allowed_tools.iter().any(|t| t.id == tool_id) {
+        let allowed = self.registry.get(tool_id).is_some_and(|tool| self.filter.matches(&tool));
+        if !allowed {
             return Ok(ToolOutput::failure(format!(
-                "Tool '{}' is not available with current tags: {:?}. \
+                "Tool '{}' is not available with current tags: {}. \
                  Use list_security_tools to see available tools.",
-                tool_id, self.tags
+                tool_id, self.filter
             )));
         }
 
@@ -804,7 +1165,10 @@ impl Tool for TaggedRunSecurityTool {
 
         tracing::info!("Executing security tool '{}' with args: {:?}", tool_id, args);
 
-        self.registry.execute(tool_id, &args)
+        ctx.progress(0.0, format!("running {}", tool_id));
+        let output = self.registry.execute(tool_id, &args);
+        ctx.progress(100.0, format!("{} finished", tool_id));
+        output
     }
 }
 
@@ -850,10 +1214,147 @@ mod tests {
         assert_eq!(metadata.tags.len(), 0);
     }
 
+    #[test]
+    fn test_dry_run_reports_command() {
+        let tool = SecurityTool {
+            id: "chkrootkit".to_string(),
+            name: "chkrootkit".to_string(),
+            description: String::new(),
+            category: SecurityCategory::Rootkit,
+            tags: vec![],
+            command_path: PathBuf::from("/usr/sbin/chkrootkit"),
+            requires_sudo: true,
+            timeout_secs: Some(30),
+            args: vec![],
+        };
+        let args = vec!["-q".to_string()];
+
+        let policy = ExecutionPolicy { dry_run: true, use_sudo: true };
+        let output = tool.execute_with(&args, policy);
+        assert!(output.success);
+        assert_eq!(output.content, "[dry-run] would run: sudo timeout 30 /usr/sbin/chkrootkit -q");
+
+        let policy = ExecutionPolicy { dry_run: true, use_sudo: false };
+        let output = tool.execute_with(&args, policy);
+        assert_eq!(output.content, "[dry-run] would run: timeout 30 /usr/sbin/chkrootkit -q");
+    }
+
     #[test]
     fn test_registry_empty_dir() {
         let registry = SecurityToolRegistry::discover("/nonexistent/path");
         assert!(registry.is_empty());
     }
+
+    #[cfg(unix)]
+    fn write_tool(dir: &Path, id: &str, manifest: Option<&str>) {
+        use std::os::unix::fs::PermissionsExt;
+        let script = dir.join(format!("{}.sh", id));
+        std::fs::write(&script, "#!/bin/sh\necho ok\n").unwrap();
+        std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        if let Some(manifest) = manifest {
+            std::fs::write(dir.join(format!("{}.json", id)), manifest).unwrap();
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_reload_swaps_tool_set_and_keeps_tools_with_bad_manifests() {
+        let dir = tempfile::tempdir().unwrap();
+        write_tool(dir.path(), "portlist", Some(r#"{"name": "Port List", "description": "Lists ports", "tags": ["net"]}"#));
+        write_tool(dir.path(), "broken", Some("{\"name\": "));
+        let registry = Arc::new(SecurityToolRegistry::discover(dir.path()));
+        assert_eq!(registry.len(), 1);
+
+        let list = TaggedSecurityTools::new(registry.clone(), &["net"]).create_tools().remove(0);
+        let ctx = ToolContext::new(crate::types::AgentId::new());
+        assert!(!list.execute(serde_json::json!({}), &ctx).await.unwrap().content.contains("scanner"));
+
+        // A half-written manifest keeps the loaded tool; a new tool is picked up
+        std::fs::write(dir.path().join("portlist.json"), "{\"name\": \"Port").unwrap();
+        write_tool(dir.path(), "scanner", Some(r#"{"name": "scanner", "description": "Scans", "tags": ["net"]}"#));
+        let summary = registry.reload();
+        assert_eq!(summary.added, vec!["scanner"]);
+        assert!(summary.removed.is_empty());
+        assert_eq!(summary.invalid, vec!["broken", "portlist"]);
+        assert_eq!(registry.get("portlist").unwrap().name, "Port List");
+        assert!(registry.get("broken").is_none());
+        assert!(list.execute(serde_json::json!({}), &ctx).await.unwrap().content.contains("scanner"));
+
+        std::fs::remove_file(dir.path().join("portlist.sh")).unwrap();
+        std::fs::remove_file(dir.path().join("portlist.json")).unwrap();
+        let summary = registry.reload();
+        assert_eq!(summary.removed, vec!["portlist"]);
+        assert_eq!(registry.len(), 1);
+    }
+
+    /// Registry of four tools: portlist (network, readonly), nmap (network, scanning, sudo),
+    /// chkrootkit (rootkit, scanning, sudo) and lynis (hardening, readonly)
+    #[cfg(unix)]
+    fn fixture_registry(dir: &Path) -> Arc<SecurityToolRegistry> {
+        for (id, tags, sudo) in [
+            ("portlist", r#"["network", "readonly"]"#, false),
+            ("nmap", r#"["network", "scanning"]"#, true),
+            ("chkrootkit", r#"["rootkit", "Scanning"]"#, true),
+            ("lynis", r#"["hardening", "readonly"]"#, false),
+        ] {
+            let manifest = format!(
+                r#"{{"name": "{}", "description": "", "tags": {}, "requires_sudo": {}}}"#,
+                id, tags, sudo
+            );
+            write_tool(dir, id, Some(&manifest));
+        }
+        Arc::new(SecurityToolRegistry::discover(dir))
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_tag_filter_modes() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = fixture_registry(dir.path());
+        let ids = |filter: TagFilter| {
+            let mut ids: Vec<String> = registry.filter(&filter).into_iter().map(|t| t.id).collect();
+            ids.sort();
+            ids
+        };
+
+        assert_eq!(ids(TagFilter::any(&["readonly", "rootkit"])), vec!["chkrootkit", "lynis", "portlist"]);
+        assert_eq!(ids(TagFilter::any(&["ALL"])).len(), 4);
+        assert_eq!(ids(TagFilter::all(&["network", "readonly"])), vec!["portlist"]);
+        assert_eq!(ids(TagFilter::all(&["scanning"])), vec!["chkrootkit", "nmap"]);
+        assert_eq!(ids(TagFilter::none(&["network", "rootkit"])), vec!["lynis"]);
+        assert_eq!(ids(TagFilter::any(&["scanning"]).excluding_sudo()), Vec::<String>::new());
+        assert_eq!(ids(TagFilter::any(&["network"]).excluding(&["scanning"])), vec!["portlist"]);
+
+        let counts = registry.tag_counts();
+        assert_eq!(counts["readonly"], 2);
+        assert_eq!(counts["network"], 2);
+        assert_eq!(counts["Scanning"], 1);
+
+        let helper = TaggedSecurityTools::new(registry.clone(), &["network"]).match_mode(MatchMode::All).excluding_sudo();
+        assert_eq!(helper.filtered_tools().len(), 1);
+        assert_eq!(helper.filter().to_string(), r#"all of ["network"], without sudo"#);
+
+        let run = helper.create_tools().remove(1);
+        let ctx = ToolContext::new(crate::types::AgentId::new());
+        let refused = run.execute(serde_json::json!({"tool_id": "nmap"}), &ctx).await.unwrap();
+        assert!(!refused.success);
+        assert!(refused.error.unwrap().contains("not available"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_watch_picks_up_new_tools() {
+        let dir = tempfile::tempdir().unwrap();
+        let registry = SecurityToolRegistry::discover(dir.path());
+        let watcher = registry.watch(Duration::from_millis(20));
+
+        write_tool(dir.path(), "chkrootkit", None);
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while registry.get("chkrootkit").is_none() {
+            assert!(std::time::Instant::now() < deadline, "watcher did not reload");
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        watcher.abort();
+    }
 }
 
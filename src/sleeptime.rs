@@ -8,8 +8,11 @@
 //! - Pattern detection across conversation history
 
 use crate::error::{Error, Result};
+use crate::llm_client::LlmClient;
 use crate::memory::{AgentMemory, MemoryBlock};
+use crate::openrouter::{CompletionRequest, Message, Role};
 use crate::types::AgentId;
+use chrono::{DateTime, Utc};
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{watch, RwLock};
@@ -32,6 +35,9 @@ pub struct SleepTimeConfig {
 
     /// Enable pattern detection
     pub enable_pattern_detection: bool,
+
+    /// Labels of the memory blocks the LLM rewrites from new messages
+    pub consolidated_blocks: Vec<String>,
 }
 
 impl Default for SleepTimeConfig {
@@ -42,6 +48,7 @@ impl Default for SleepTimeConfig {
             context_warning_threshold: 6000, // 75% of default 8K context
             enable_summarization: true,
             enable_pattern_detection: true,
+            consolidated_blocks: vec!["persona".to_string(), "scenario".to_string()],
         }
     }
 }
@@ -66,6 +73,12 @@ pub struct SleepTimeAgent {
 
     /// Optional handle to the background task
     task_handle: Arc<RwLock<Option<tokio::task::JoinHandle<()>>>>,
+
+    /// Client and model that rewrite memory blocks; without one only the heuristics run
+    consolidator: Option<(Arc<dyn LlmClient>, String)>,
+
+    /// Start of the last run that rewrote blocks
+    last_run: Arc<parking_lot::Mutex<Option<DateTime<Utc>>>>,
 }
 
 impl SleepTimeAgent {
@@ -85,6 +98,32 @@ impl SleepTimeAgent {
             shutdown_tx,
             shutdown_rx,
             task_handle: Arc::new(RwLock::new(None)),
+            consolidator: None,
+            last_run: Arc::new(parking_lot::Mutex::new(None)),
+        }
+    }
+
+    /// Rewrite the configured memory blocks with `client` on every tick
+    pub fn with_client(mut self, client: Arc<dyn LlmClient>, model: impl Into<String>) -> Self {
+        self.consolidator = Some((client, model.into()));
+        self
+    }
+
+    /// When the last consolidation that saw new messages started
+    pub fn last_run(&self) -> Option<DateTime<Utc>> {
+        *self.last_run.lock()
+    }
+
+    /// Rewrite the configured memory blocks now instead of waiting for a tick
+    ///
+    /// Returns how many blocks changed. Does nothing without a client or
+    /// when no messages arrived since the last run.
+    pub async fn consolidate_now(&self) -> Result<usize> {
+        match &self.consolidator {
+            Some((client, model)) => {
+                Self::rewrite_blocks(&self.shared_memory, &self.config, client.as_ref(), model, &self.last_run).await
+            }
+            None => Ok(0),
         }
     }
 
@@ -106,6 +145,8 @@ impl SleepTimeAgent {
         let running_flag = self.running.clone();
         let mut shutdown_rx = self.shutdown_rx.clone();
         let agent_id = self.primary_agent_id;
+        let consolidator = self.consolidator.clone();
+        let last_run = self.last_run.clone();
 
         let handle = tokio::spawn(async move {
             let mut interval = time::interval(config.consolidation_interval);
@@ -128,6 +169,11 @@ impl SleepTimeAgent {
                         if let Err(e) = Self::consolidate_memory(&memory, &config, agent_id).await {
                             eprintln!("Sleep-time agent error during consolidation: {}", e);
                         }
+                        if let Some((client, model)) = &consolidator {
+                            if let Err(e) = Self::rewrite_blocks(&memory, &config, client.as_ref(), model, &last_run).await {
+                                eprintln!("Sleep-time agent error while rewriting memory blocks: {}", e);
+                            }
+                        }
                     }
                 }
             }
@@ -187,6 +233,83 @@ impl SleepTimeAgent {
         Ok(())
     }
 
+    /// Ask the LLM to fold messages since the last run into each configured block
+    async fn rewrite_blocks(
+        memory: &Arc<AgentMemory>,
+        config: &SleepTimeConfig,
+        client: &dyn LlmClient,
+        model: &str,
+        last_run: &parking_lot::Mutex<Option<DateTime<Utc>>>,
+    ) -> Result<usize> {
+        let started = Utc::now();
+        let since = *last_run.lock();
+        let new_messages: Vec<_> = memory
+            .get_recent_messages(1000)
+            .await
+            .into_iter()
+            .filter(|m| since.is_none_or(|since| m.timestamp >= since) && m.timestamp < started)
+            .collect();
+        if new_messages.is_empty() {
+            return Ok(0);
+        }
+
+        let transcript = new_messages
+            .iter()
+            .map(|m| format!("{}: {}", m.role, m.content))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let mut blocks = memory.in_context_blocks().await;
+        blocks.extend(memory.out_of_context_blocks().await);
+
+        let mut rewritten = 0;
+        for block in blocks.iter().filter(|b| config.consolidated_blocks.contains(&b.label)) {
+            let limit = block
+                .max_size
+                .map(|max| format!(" Keep it under {} characters.", max))
+                .unwrap_or_default();
+            let request = CompletionRequest::new(
+                model,
+                vec![
+                    Message::system(format!(
+                        "You maintain the '{}' memory block ({}). Rewrite it to include anything in the new \
+                         messages that belongs there, keeping what is still true.{} Reply with the new block \
+                         content only.",
+                        block.label, block.description, limit
+                    )),
+                    Message::user(format!(
+                        "Current block:\n{}\n\nNew messages:\n{}",
+                        block.value, transcript
+                    )),
+                ],
+            )
+            .with_temperature(0.0);
+            let response = client.complete(request).await?;
+            let mut value = response
+                .choices
+                .first()
+                .map(|choice| choice.message.content.trim().to_string())
+                .unwrap_or_default();
+            if let Some(max) = block.max_size {
+                if value.len() > max {
+                    let mut end = max;
+                    while !value.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    value.truncate(end);
+                }
+            }
+            if value.is_empty() || value == block.value {
+                continue;
+            }
+
+            memory.update_block(block.id, value).await?;
+            rewritten += 1;
+        }
+
+        *last_run.lock() = Some(started);
+        Ok(rewritten)
+    }
+
     /// Archive old or low-priority memory blocks
     async fn perform_archival(memory: &Arc<AgentMemory>) -> Result<()> {
         let in_context = memory.in_context_blocks().await;
@@ -226,10 +349,10 @@ impl SleepTimeAgent {
         summary.push_str("Summary of recent conversation:\n");
 
         // Count message types
-        let user_msgs = to_summarize.iter().filter(|m| m.role == "user").count();
+        let user_msgs = to_summarize.iter().filter(|m| m.role == Role::User).count();
         let assistant_msgs = to_summarize
             .iter()
-            .filter(|m| m.role == "assistant")
+            .filter(|m| m.role == Role::Assistant)
             .count();
 
         summary.push_str(&format!(
@@ -288,7 +411,7 @@ impl SleepTimeAgent {
         let mut question_patterns: std::collections::HashMap<String, usize> =
             std::collections::HashMap::new();
 
-        for msg in messages.iter().filter(|m| m.role == "user") {
+        for msg in messages.iter().filter(|m| m.role == Role::User) {
             // Extract first 50 chars as pattern key
             let pattern_key = msg
                 .content
@@ -353,6 +476,7 @@ impl Drop for SleepTimeAgent {
 mod tests {
     use super::*;
     use crate::memory::MemoryConfig;
+    use crate::testing::MockLlmClient;
     use crate::types::AgentId;
 
     #[tokio::test]
@@ -393,6 +517,42 @@ mod tests {
             .unwrap();
     }
 
+    #[tokio::test]
+    async fn test_consolidate_now_rewrites_blocks_once() {
+        let agent_id = AgentId::new();
+        let memory = Arc::new(AgentMemory::new(agent_id, MemoryConfig::default()));
+        let mut persona = MemoryBlock::new("persona", "The user is Ana");
+        persona.max_size = Some(30);
+        let persona_id = memory.add_block(persona).await.unwrap();
+        let notes_id = memory.add_block(MemoryBlock::new("notes", "unchanged")).await.unwrap();
+
+        // Always proposes the same block content
+        let client = Arc::new(MockLlmClient::new().with_fallback_reply("The user is Ana, a marine biologist who likes tea"));
+        let sleeptime = SleepTimeAgent::new(agent_id, memory.clone(), SleepTimeConfig::default())
+            .with_client(client.clone(), "cheap-model");
+
+        assert_eq!(sleeptime.consolidate_now().await.unwrap(), 0);
+        assert_eq!(client.request_count(), 0);
+        assert!(sleeptime.last_run().is_none());
+
+        memory.add_message(Role::User, "I'm a marine biologist".to_string()).await;
+        memory.add_message(Role::User, "I like tea".to_string()).await;
+        assert_eq!(sleeptime.consolidate_now().await.unwrap(), 1);
+        assert_eq!(client.request_count(), 1);
+        assert_eq!(memory.get_block(persona_id).await.unwrap().value, "The user is Ana, a marine biol");
+        assert_eq!(memory.get_block(notes_id).await.unwrap().value, "unchanged");
+        let last_run = sleeptime.last_run().unwrap();
+
+        assert_eq!(sleeptime.consolidate_now().await.unwrap(), 0);
+        assert_eq!(client.request_count(), 1);
+        assert_eq!(sleeptime.last_run(), Some(last_run));
+
+        memory.add_message(Role::User, "Still Ana".to_string()).await;
+        assert_eq!(sleeptime.consolidate_now().await.unwrap(), 0);
+        assert_eq!(client.request_count(), 2);
+        assert!(sleeptime.last_run().unwrap() > last_run);
+    }
+
     #[tokio::test]
     async fn test_archival() {
         let agent_id = AgentId::new();
@@ -9,9 +9,16 @@ use anyhow::Context;
 use chrono::{DateTime, Duration, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
 use std::sync::{Arc, RwLock};
 use url::Url;
 
+/// Callback invoked with the issuer and the new token after each refresh
+pub type TokenRefreshedCallback = Arc<dyn Fn(&str, &DPoPBoundToken) + Send + Sync>;
+
+/// How long before expiry a token is refreshed by default
+pub const DEFAULT_REFRESH_SKEW: Duration = Duration::minutes(5);
+
 /// DPoP-bound access token
 #[derive(Debug, Clone)]
 pub struct DPoPBoundToken {
@@ -31,7 +38,91 @@ impl DPoPBoundToken {
     /// Check if token is expired or about to expire
     pub fn is_expired(&self) -> bool {
         // Consider expired if less than 5 minutes remaining
-        Utc::now() + Duration::minutes(5) >= self.expires_at
+        self.expires_within(DEFAULT_REFRESH_SKEW)
+    }
+
+    /// Check if token expires within `skew` from now
+    pub fn expires_within(&self, skew: Duration) -> bool {
+        Utc::now() + skew >= self.expires_at
+    }
+}
+
+/// Token cache keyed by OIDC issuer, renewing at most one token at a time
+struct TokenStore {
+    /// Cached tokens
+    tokens: RwLock<HashMap<String, DPoPBoundToken>>,
+    /// Held while logging in or refreshing, so concurrent callers share one result
+    refresh_lock: tokio::sync::Mutex<()>,
+    /// How long before expiry tokens are refreshed
+    refresh_skew: Duration,
+    /// Called after each successful refresh
+    on_token_refreshed: Option<TokenRefreshedCallback>,
+}
+
+impl TokenStore {
+    fn new() -> Self {
+        Self {
+            tokens: RwLock::new(HashMap::new()),
+            refresh_lock: tokio::sync::Mutex::new(()),
+            refresh_skew: DEFAULT_REFRESH_SKEW,
+            on_token_refreshed: None,
+        }
+    }
+
+    /// Cached token for `issuer` that is not within the refresh skew of expiry
+    fn fresh(&self, issuer: &str) -> Option<DPoPBoundToken> {
+        let tokens = self.tokens.read().unwrap();
+        tokens
+            .get(issuer)
+            .filter(|token| !token.expires_within(self.refresh_skew))
+            .cloned()
+    }
+
+    /// Fresh token for `issuer`, renewing it first if needed
+    ///
+    /// A stale token with a refresh token is passed to `refresh`; if there is
+    /// none or the refresh fails, `login` runs instead. Callers arriving while
+    /// a renewal is in flight wait for it and reuse its token.
+    async fn get_or_renew<R, RF, L, LF>(&self, issuer: &str, refresh: R, login: L) -> Result<DPoPBoundToken>
+    where
+        R: FnOnce(String) -> RF,
+        RF: Future<Output = Result<DPoPBoundToken>>,
+        L: FnOnce() -> LF,
+        LF: Future<Output = Result<DPoPBoundToken>>,
+    {
+        if let Some(token) = self.fresh(issuer) {
+            return Ok(token);
+        }
+
+        // Another caller may have renewed while we waited for the lock
+        let _refreshing = self.refresh_lock.lock().await;
+        if let Some(token) = self.fresh(issuer) {
+            return Ok(token);
+        }
+
+        let stale = self.tokens.read().unwrap().get(issuer).cloned();
+        let refreshed = match stale.and_then(|t| t.refresh_token) {
+            Some(refresh_token) => match refresh(refresh_token).await {
+                Ok(token) => Some(token),
+                Err(e) => {
+                    tracing::warn!("Token refresh for {} failed, logging in again: {}", issuer, e);
+                    None
+                }
+            },
+            None => None,
+        };
+        let token = match refreshed {
+            Some(token) => {
+                if let Some(callback) = &self.on_token_refreshed {
+                    callback(issuer, &token);
+                }
+                token
+            }
+            None => login().await?,
+        };
+
+        self.tokens.write().unwrap().insert(issuer.to_string(), token.clone());
+        Ok(token)
     }
 }
 
@@ -41,8 +132,10 @@ pub struct SolidOidcClient {
     identity_client: Arc<SolidIdentityClient>,
     /// DPoP manager for proof generation
     dpop_manager: Arc<DPoPManager>,
-    /// Token cache (keyed by OIDC issuer)
-    token_cache: Arc<RwLock<HashMap<String, DPoPBoundToken>>>,
+    /// Token cache and refresh coordination
+    tokens: TokenStore,
+    /// Token endpoints from OIDC discovery (keyed by issuer)
+    token_endpoints: RwLock<HashMap<String, String>>,
 }
 
 impl SolidOidcClient {
@@ -54,17 +147,44 @@ impl SolidOidcClient {
         Self {
             identity_client,
             dpop_manager,
-            token_cache: Arc::new(RwLock::new(HashMap::new())),
+            tokens: TokenStore::new(),
+            token_endpoints: RwLock::new(HashMap::new()),
         }
     }
 
+    /// Refresh tokens this long before they expire (default 5 minutes)
+    pub fn with_refresh_skew(mut self, skew: std::time::Duration) -> Self {
+        self.tokens.refresh_skew = Duration::from_std(skew).unwrap_or(DEFAULT_REFRESH_SKEW);
+        self
+    }
+
+    /// Call `callback` with the issuer and new token after each refresh
+    ///
+    /// Use this to persist refreshed tokens between runs.
+    pub fn on_token_refreshed<F>(mut self, callback: F) -> Self
+    where
+        F: Fn(&str, &DPoPBoundToken) + Send + Sync + 'static,
+    {
+        self.tokens.on_token_refreshed = Some(Arc::new(callback));
+        self
+    }
+
+    /// Seed the cache with a previously persisted token for `issuer`
+    pub fn with_token(self, issuer: &Url, token: DPoPBoundToken) -> Self {
+        self.tokens.tokens.write().unwrap().insert(issuer.as_str().to_string(), token);
+        self
+    }
+
     /// Authenticate to a resource server
     ///
     /// This method:
     /// 1. Discovers the OIDC issuer from the resource server
     /// 2. Checks cache for valid token
-    /// 3. If needed, performs OIDC flow via TypeScript bridge
+    /// 3. If needed, refreshes the cached token or performs OIDC flow via TypeScript bridge
     /// 4. Returns DPoP-bound access token
+    ///
+    /// Tokens within the refresh skew of expiry are refreshed first. Only one
+    /// caller refreshes at a time; the others wait and reuse its token.
     pub async fn authenticate(&self, resource_url: &Url) -> Result<DPoPBoundToken> {
         // 1. Get WebID profile to find OIDC issuer
         let profile = self.identity_client.fetch_webid_profile()
@@ -73,35 +193,34 @@ impl SolidOidcClient {
         let issuer_url = profile.require_oidc_issuer()
             .context("WebID profile must specify OIDC issuer")?;
 
-        let issuer_str = issuer_url.as_str().to_string();
-
-        // 2. Check cache for valid token
-        {
-            let cache = self.token_cache.read().unwrap();
-            if let Some(token) = cache.get(&issuer_str) {
-                if !token.is_expired() {
-                    return Ok(token.clone());
-                }
-            }
-        }
-
-        // 3. Perform OIDC authentication flow
-        let token = self.perform_oidc_flow(&issuer_url).await?;
+        // 2-4. Use the cached token, or refresh it / log in and cache the result
+        let issuer = &issuer_url;
+        self.tokens
+            .get_or_renew(
+                issuer.as_str(),
+                |refresh_token| async move { self.refresh(issuer, &refresh_token).await },
+                || self.perform_oidc_flow(issuer),
+            )
+            .await
+    }
 
-        // 4. Cache token
-        {
-            let mut cache = self.token_cache.write().unwrap();
-            cache.insert(issuer_str, token.clone());
+    /// Token endpoint of `issuer`, discovered once and then cached
+    async fn token_endpoint(&self, issuer: &Url) -> Result<String> {
+        if let Some(endpoint) = self.token_endpoints.read().unwrap().get(issuer.as_str()) {
+            return Ok(endpoint.clone());
         }
-
-        Ok(token)
+        let endpoint = discover_token_endpoint(&reqwest::Client::new(), issuer).await?;
+        self.token_endpoints
+            .write()
+            .unwrap()
+            .insert(issuer.as_str().to_string(), endpoint.clone());
+        Ok(endpoint)
     }
 
     /// Perform OIDC authentication flow
     async fn perform_oidc_flow(&self, issuer: &Url) -> Result<DPoPBoundToken> {
         // Generate DPoP proof for token endpoint
-        // Note: We don't know the token endpoint yet, so we use issuer + /token
-        let token_endpoint = format!("{}/token", issuer.as_str().trim_end_matches('/'));
+        let token_endpoint = self.token_endpoint(issuer).await?;
 
         let dpop_proof = self.dpop_manager.create_proof(
             "POST",
@@ -126,16 +245,35 @@ impl SolidOidcClient {
         let auth_response: AuthResponse = serde_json::from_value(response)
             .context("Failed to parse authentication response")?;
 
-        // Calculate expiration time
-        let expires_at = Utc::now() + Duration::seconds(3600); // Default 1 hour
+        Ok(auth_response.into_token(None))
+    }
 
-        Ok(DPoPBoundToken {
-            access_token: auth_response.access_token.unwrap_or_default(),
-            refresh_token: auth_response.refresh_token,
-            id_token: auth_response.id_token.unwrap_or_default(),
-            expires_at,
-            dpop_bound: true,
-        })
+    /// Exchange a refresh token for a new DPoP-bound token via the bridge
+    async fn refresh(&self, issuer: &Url, refresh_token: &str) -> Result<DPoPBoundToken> {
+        let token_endpoint = self.token_endpoint(issuer).await?;
+        let dpop_proof = self.dpop_manager.create_proof("POST", &token_endpoint, None)
+            .context("Failed to create DPoP proof")?;
+
+        let params = serde_json::json!({
+            "issuer": issuer.as_str(),
+            "tokenEndpoint": token_endpoint,
+            "clientId": self.identity_client.client_id().as_str(),
+            "refreshToken": refresh_token,
+            "dpopProof": dpop_proof,
+        });
+
+        let ipc = &self.identity_client.ipc;
+        let mut ipc_guard = ipc.lock().unwrap();
+        let response = ipc_guard.request("refreshToken", params)
+            .context("Token refresh failed")?;
+
+        let auth_response: AuthResponse = serde_json::from_value(response)
+            .context("Failed to parse refresh response")?;
+        if auth_response.access_token.is_none() {
+            return Err(anyhow::anyhow!("Refresh response did not include an access token"));
+        }
+
+        Ok(auth_response.into_token(Some(refresh_token)))
     }
 
     /// Make authenticated HTTP request with DPoP
@@ -147,37 +285,102 @@ impl SolidOidcClient {
         // Get access token
         let token = self.authenticate(url).await?;
 
-        // Create DPoP proof for this specific request
-        let ath = DPoPManager::compute_ath(&token.access_token);
-        let dpop_proof = self.dpop_manager.create_proof(
+        send_dpop_request(
+            &reqwest::Client::new(),
+            &self.dpop_manager,
             method,
-            url.as_str(),
-            Some(&ath),
-        ).context("Failed to create DPoP proof")?;
-
-        // Make HTTP request with DPoP headers
-        let client = reqwest::Client::new();
-        let response = client
-            .request(
-                method.parse().context("Invalid HTTP method")?,
-                url.clone(),
-            )
-            .header("Authorization", format!("DPoP {}", token.access_token))
-            .header("DPoP", dpop_proof)
-            .send()
-            .await
-            .context("HTTP request failed")?;
+            url,
+            &token.access_token,
+            |request| request,
+        )
+        .await
+    }
 
-        Ok(response)
+    /// DPoP manager used for this client's proofs
+    pub fn dpop_manager(&self) -> &Arc<DPoPManager> {
+        &self.dpop_manager
     }
 
     /// Clear token cache (force re-authentication)
     pub fn clear_cache(&self) {
-        let mut cache = self.token_cache.write().unwrap();
+        let mut cache = self.tokens.tokens.write().unwrap();
         cache.clear();
     }
 }
 
+/// Look up `issuer`'s token endpoint in its OpenID configuration
+///
+/// Servers differ in where they put it (Community Solid Server uses
+/// `/.oidc/token`), so it is never guessed from the issuer URL.
+pub async fn discover_token_endpoint(client: &reqwest::Client, issuer: &Url) -> Result<String> {
+    let config_url = format!(
+        "{}/.well-known/openid-configuration",
+        issuer.as_str().trim_end_matches('/')
+    );
+    let config: serde_json::Value = client
+        .get(&config_url)
+        .send()
+        .await
+        .context("OIDC discovery request failed")?
+        .error_for_status()
+        .context("OIDC discovery failed")?
+        .json()
+        .await
+        .context("Failed to parse OpenID configuration")?;
+
+    config["token_endpoint"]
+        .as_str()
+        .map(String::from)
+        .ok_or_else(|| anyhow::anyhow!("OpenID configuration of {} has no token_endpoint", issuer))
+}
+
+/// Send a DPoP-bound request, answering one `use_dpop_nonce` challenge
+///
+/// Any `DPoP-Nonce` the server returns is cached on `dpop` for later proofs.
+/// When the server rejects the proof with a 401 `use_dpop_nonce` challenge,
+/// the request is rebuilt with a fresh proof carrying the new nonce and sent
+/// once more. `build` adds the body and any extra headers, and runs for each
+/// attempt.
+pub async fn send_dpop_request<F>(
+    client: &reqwest::Client,
+    dpop: &DPoPManager,
+    method: &str,
+    url: &Url,
+    access_token: &str,
+    build: F,
+) -> Result<reqwest::Response>
+where
+    F: Fn(reqwest::RequestBuilder) -> reqwest::RequestBuilder,
+{
+    let method: reqwest::Method = method.parse().context("Invalid HTTP method")?;
+    let ath = DPoPManager::compute_ath(access_token);
+
+    let mut retried = false;
+    loop {
+        // Create DPoP proof for this specific request
+        let dpop_proof = dpop
+            .create_proof(method.as_str(), url.as_str(), Some(&ath))
+            .context("Failed to create DPoP proof")?;
+
+        // Make HTTP request with DPoP headers
+        let request = client
+            .request(method.clone(), url.clone())
+            .header("Authorization", format!("DPoP {}", access_token))
+            .header("DPoP", dpop_proof);
+        let response = build(request)
+            .send()
+            .await
+            .context("HTTP request failed")?;
+
+        if dpop.observe_response(url.as_str(), &response)? && !retried {
+            tracing::debug!("Retrying {} {} with server-provided DPoP nonce", method, url);
+            retried = true;
+            continue;
+        }
+        return Ok(response);
+    }
+}
+
 /// Authentication response from TypeScript bridge
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -188,12 +391,187 @@ struct AuthResponse {
     access_token: Option<String>,
     refresh_token: Option<String>,
     id_token: Option<String>,
+    /// Expiry in milliseconds since the Unix epoch
     expires_at: Option<i64>,
 }
 
+impl AuthResponse {
+    /// Build a token, keeping `previous_refresh` if the server did not rotate it
+    fn into_token(self, previous_refresh: Option<&str>) -> DPoPBoundToken {
+        let expires_at = self
+            .expires_at
+            .and_then(DateTime::from_timestamp_millis)
+            .unwrap_or_else(|| Utc::now() + Duration::seconds(3600)); // Default 1 hour
+
+        DPoPBoundToken {
+            access_token: self.access_token.unwrap_or_default(),
+            refresh_token: self.refresh_token.or_else(|| previous_refresh.map(String::from)),
+            id_token: self.id_token.unwrap_or_default(),
+            expires_at,
+            dpop_bound: true,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use base64::Engine;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Mutex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn token(access: &str, refresh: Option<&str>, expires_in: Duration) -> DPoPBoundToken {
+        DPoPBoundToken {
+            access_token: access.to_string(),
+            refresh_token: refresh.map(String::from),
+            id_token: String::new(),
+            expires_at: Utc::now() + expires_in,
+            dpop_bound: true,
+        }
+    }
+
+    /// Nonce claim of the proof in a raw HTTP request
+    fn proof_nonce(request: &str) -> Option<String> {
+        let proof = request
+            .lines()
+            .find_map(|l| l.strip_prefix("dpop: "))
+            .unwrap();
+        let payload = URL_SAFE_NO_PAD.decode(proof.split('.').nth(1).unwrap()).unwrap();
+        let claims: serde_json::Value = serde_json::from_slice(&payload).unwrap();
+        claims["nonce"].as_str().map(String::from)
+    }
+
+    #[tokio::test]
+    async fn test_nonce_challenge_is_retried() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = Url::parse(&format!("http://{}/alice/notes", listener.local_addr().unwrap())).unwrap();
+        let server = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for response in [
+                "HTTP/1.1 401 Unauthorized\r\nWWW-Authenticate: DPoP error=\"use_dpop_nonce\"\r\nDPoP-Nonce: n-1\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                "HTTP/1.1 200 OK\r\nDPoP-Nonce: n-2\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok",
+            ] {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = vec![0u8; 8192];
+                let n = socket.read(&mut buf).await.unwrap();
+                requests.push(String::from_utf8_lossy(&buf[..n]).into_owned());
+                socket.write_all(response.as_bytes()).await.unwrap();
+                socket.shutdown().await.ok();
+            }
+            requests
+        });
+
+        let dpop = DPoPManager::generate().unwrap();
+        let response = send_dpop_request(&reqwest::Client::new(), &dpop, "GET", &url, "token", |r| r)
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 200);
+
+        let requests = server.await.unwrap();
+        assert_eq!(proof_nonce(&requests[0]), None);
+        assert_eq!(proof_nonce(&requests[1]).as_deref(), Some("n-1"));
+        assert_eq!(dpop.nonce_for(url.as_str()).as_deref(), Some("n-2"));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_callers_share_one_refresh() {
+        const ISSUER: &str = "https://idp.example/";
+        let refreshed = Arc::new(Mutex::new(Vec::new()));
+        let mut store = TokenStore::new();
+        store.on_token_refreshed = Some({
+            let refreshed = refreshed.clone();
+            Arc::new(move |issuer: &str, token: &DPoPBoundToken| {
+                refreshed.lock().unwrap().push((issuer.to_string(), token.access_token.clone()));
+            })
+        });
+        store.tokens.write().unwrap().insert(
+            ISSUER.to_string(),
+            token("old", Some("r-1"), Duration::minutes(1)),
+        );
+        let store = Arc::new(store);
+
+        let refreshes = Arc::new(AtomicUsize::new(0));
+        let callers = (0..8).map(|_| {
+            let store = store.clone();
+            let refreshes = refreshes.clone();
+            tokio::spawn(async move {
+                store
+                    .get_or_renew(
+                        ISSUER,
+                        |refresh_token| async move {
+                            assert_eq!(refresh_token, "r-1");
+                            refreshes.fetch_add(1, Ordering::SeqCst);
+                            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                            Ok(token("new", None, Duration::hours(1)))
+                        },
+                        || async { panic!("should refresh, not log in") },
+                    )
+                    .await
+                    .unwrap()
+            })
+        });
+        for caller in callers.collect::<Vec<_>>() {
+            assert_eq!(caller.await.unwrap().access_token, "new");
+        }
+
+        assert_eq!(refreshes.load(Ordering::SeqCst), 1);
+        assert_eq!(*refreshed.lock().unwrap(), [(ISSUER.to_string(), "new".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn test_failed_refresh_logs_in_without_callback() {
+        let called = Arc::new(AtomicUsize::new(0));
+        let mut store = TokenStore::new();
+        store.on_token_refreshed = Some({
+            let called = called.clone();
+            Arc::new(move |_: &str, _: &DPoPBoundToken| {
+                called.fetch_add(1, Ordering::SeqCst);
+            })
+        });
+        store
+            .tokens
+            .write()
+            .unwrap()
+            .insert("i".to_string(), token("old", Some("r-1"), Duration::minutes(-1)));
+
+        let token = store
+            .get_or_renew(
+                "i",
+                |_| async { Err(anyhow::anyhow!("invalid_grant")) },
+                || async { Ok(token("login", None, Duration::hours(1))) },
+            )
+            .await
+            .unwrap();
+        assert_eq!(token.access_token, "login");
+        assert_eq!(called.load(Ordering::SeqCst), 0);
+        assert_eq!(store.fresh("i").unwrap().access_token, "login");
+    }
+
+    #[tokio::test]
+    async fn test_token_endpoint_is_discovered() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let issuer = Url::parse(&format!("http://{}/", listener.local_addr().unwrap())).unwrap();
+        let token_endpoint = format!("{}.oidc/token", issuer);
+        let body = serde_json::json!({ "issuer": issuer.as_str(), "token_endpoint": token_endpoint }).to_string();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = vec![0u8; 8192];
+            let n = socket.read(&mut buf).await.unwrap();
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&buf[..n]).into_owned()
+        });
+
+        let endpoint = discover_token_endpoint(&reqwest::Client::new(), &issuer).await.unwrap();
+        assert_eq!(endpoint, token_endpoint);
+        assert!(server.await.unwrap().starts_with("GET /.well-known/openid-configuration "));
+    }
 
     #[test]
     fn test_token_expiration() {
@@ -220,4 +598,46 @@ mod tests {
 
         assert!(!token.is_expired());
     }
+
+    #[test]
+    fn test_refresh_skew() {
+        let token = DPoPBoundToken {
+            access_token: "test".to_string(),
+            refresh_token: Some("r".to_string()),
+            id_token: "test".to_string(),
+            expires_at: Utc::now() + Duration::minutes(10),
+            dpop_bound: true,
+        };
+
+        assert!(!token.expires_within(Duration::minutes(5)));
+        assert!(token.expires_within(Duration::minutes(15)));
+    }
+
+    #[test]
+    fn test_refresh_response_keeps_refresh_token() {
+        let expires_at = Utc::now() + Duration::minutes(30);
+        let response: AuthResponse = serde_json::from_value(serde_json::json!({
+            "isLoggedIn": true,
+            "sessionId": "s1",
+            "accessToken": "new-access",
+            "expiresAt": expires_at.timestamp_millis(),
+        }))
+        .unwrap();
+
+        let token = response.into_token(Some("r-1"));
+        assert_eq!(token.access_token, "new-access");
+        assert_eq!(token.refresh_token.as_deref(), Some("r-1"));
+        assert_eq!(token.expires_at.timestamp_millis(), expires_at.timestamp_millis());
+
+        let rotated: AuthResponse = serde_json::from_value(serde_json::json!({
+            "isLoggedIn": true,
+            "sessionId": "s1",
+            "accessToken": "newer",
+            "refreshToken": "r-2",
+        }))
+        .unwrap();
+        let token = rotated.into_token(Some("r-1"));
+        assert_eq!(token.refresh_token.as_deref(), Some("r-2"));
+        assert!(!token.is_expired());
+    }
 }
@@ -9,7 +9,9 @@ use anyhow::Context;
 use oxigraph::model::{NamedNode, Term};
 use oxigraph::sparql::{Query, QueryResults};
 use oxigraph::store::Store;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 use url::Url;
@@ -87,6 +89,171 @@ impl DomainConsentPolicy {
     }
 }
 
+/// Operation an agent performs on a Pod resource
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PodOperation {
+    /// Fetch a resource
+    Read,
+    /// Create or replace a resource
+    Write,
+    /// Run a SPARQL query against a resource
+    Query,
+    /// List an LDP container
+    List,
+}
+
+impl PodOperation {
+    /// Parse an operation name as used by `SolidPodTool`
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "read" => Some(Self::Read),
+            "write" => Some(Self::Write),
+            "query" => Some(Self::Query),
+            "list" => Some(Self::List),
+            _ => None,
+        }
+    }
+
+    /// Lowercase operation name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Read => "read",
+            Self::Write => "write",
+            Self::Query => "query",
+            Self::List => "list",
+        }
+    }
+}
+
+impl fmt::Display for PodOperation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Operations allowed on Pod resources whose URL matches a pattern
+///
+/// Patterns starting with a scheme (`https://pod.example/public/*`) match the
+/// whole URL without query or fragment; other patterns (`/public/*`) match
+/// the path only. `*` matches any run of characters, including `/`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResourceRule {
+    /// URL or path pattern
+    pub pattern: String,
+    /// Operations permitted on matching resources; empty denies everything
+    pub allowed_operations: Vec<PodOperation>,
+    /// Compiled pattern
+    #[serde(skip)]
+    regex: Option<Regex>,
+}
+
+impl ResourceRule {
+    /// Allow `allowed_operations` on resources matching `pattern`
+    pub fn new(pattern: impl Into<String>, allowed_operations: Vec<PodOperation>) -> Self {
+        let pattern = pattern.into();
+        let regex = Self::compile(&pattern);
+        Self {
+            pattern,
+            allowed_operations,
+            regex: Some(regex),
+        }
+    }
+
+    /// Translate a `*` glob into an anchored regex
+    fn compile(pattern: &str) -> Regex {
+        let body = pattern
+            .split('*')
+            .map(regex::escape)
+            .collect::<Vec<_>>()
+            .join(".*");
+        Regex::new(&format!("^{}$", body)).expect("escaped glob is a valid regex")
+    }
+
+    /// Whether the pattern names a full URL rather than a path
+    fn is_absolute(&self) -> bool {
+        self.pattern.contains("://")
+    }
+
+    /// Check if `url` falls under this rule
+    pub fn matches(&self, url: &Url) -> bool {
+        let target = if self.is_absolute() {
+            &url[..url::Position::AfterPath]
+        } else {
+            url.path()
+        };
+        match &self.regex {
+            Some(regex) => regex.is_match(target),
+            None => Self::compile(&self.pattern).is_match(target),
+        }
+    }
+
+    /// Check if the rule permits `operation`
+    pub fn allows(&self, operation: PodOperation) -> bool {
+        self.allowed_operations.contains(&operation)
+    }
+
+    /// Ordering key for precedence: more literal characters, then fewer wildcards
+    fn specificity(&self) -> (usize, std::cmp::Reverse<usize>) {
+        let wildcards = self.pattern.matches('*').count();
+        (self.pattern.len() - wildcards, std::cmp::Reverse(wildcards))
+    }
+}
+
+impl fmt::Display for ResourceRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ops: Vec<_> = self.allowed_operations.iter().map(|op| op.as_str()).collect();
+        if ops.is_empty() {
+            write!(f, "{} (no operations)", self.pattern)
+        } else {
+            write!(f, "{} ({})", self.pattern, ops.join(", "))
+        }
+    }
+}
+
+/// Per-resource rules of a consent manifest
+///
+/// When several rules match a URL the most specific wins: the one with the
+/// most literal (non-`*`) characters, then the fewest wildcards, then the one
+/// added first. URLs no rule matches are not restricted; add a `*` rule with
+/// no operations to deny by default.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResourcePolicy {
+    /// Rules in the order they were added
+    pub rules: Vec<ResourceRule>,
+}
+
+impl ResourcePolicy {
+    /// Create an empty policy
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a rule
+    pub fn with_rule(mut self, rule: ResourceRule) -> Self {
+        self.rules.push(rule);
+        self
+    }
+
+    /// Most specific rule matching `url`
+    pub fn rule_for(&self, url: &Url) -> Option<&ResourceRule> {
+        self.rules
+            .iter()
+            .filter(|rule| rule.matches(url))
+            // max_by_key keeps the last maximum, so search in reverse to prefer earlier rules
+            .rev()
+            .max_by_key(|rule| rule.specificity())
+    }
+
+    /// Check `operation` on `url`, returning the violated rule if it is denied
+    pub fn check(&self, url: &Url, operation: PodOperation) -> std::result::Result<(), &ResourceRule> {
+        match self.rule_for(url) {
+            Some(rule) if !rule.allows(operation) => Err(rule),
+            _ => Ok(()),
+        }
+    }
+}
+
 /// Consent manifest manager
 pub struct ConsentManifest {
     /// User's Pod IRI
@@ -101,6 +268,8 @@ pub struct ConsentManifest {
     cache_duration: Duration,
     /// Default policy
     default_policy: DomainConsentPolicy,
+    /// Per-resource operation rules
+    resource_policy: ResourcePolicy,
 }
 
 impl ConsentManifest {
@@ -149,9 +318,26 @@ impl ConsentManifest {
             last_fetched: RwLock::new(Some(Instant::now())),
             cache_duration: Duration::from_secs(300), // 5 minutes
             default_policy,
+            resource_policy: ResourcePolicy::new(),
         })
     }
 
+    /// Restrict operations on resources matching `rule.pattern`
+    pub fn with_resource_rule(mut self, rule: ResourceRule) -> Self {
+        self.resource_policy.rules.push(rule);
+        self
+    }
+
+    /// Add a resource rule to a loaded manifest
+    pub fn add_resource_rule(&mut self, rule: ResourceRule) {
+        self.resource_policy.rules.push(rule);
+    }
+
+    /// Per-resource operation rules
+    pub fn resource_policy(&self) -> &ResourcePolicy {
+        &self.resource_policy
+    }
+
     /// Get consent policy for a specific domain
     pub fn policy_for_domain(&self, domain: &str) -> Result<DomainConsentPolicy> {
         // Check if cache needs refresh
@@ -286,4 +472,59 @@ mod tests {
         assert!(policy.requires_user_approval());
         assert_eq!(policy.unconfigured_categories(), vec!["analytics"]);
     }
+
+    fn url(s: &str) -> Url {
+        Url::parse(s).unwrap()
+    }
+
+    #[test]
+    fn test_resource_rule_matching() {
+        let public = ResourceRule::new("/public/*", vec![PodOperation::Read, PodOperation::List]);
+        assert!(public.matches(&url("https://pod.example/public/notes.ttl")));
+        assert!(public.matches(&url("https://pod.example/public/a/b.ttl?x=1")));
+        assert!(!public.matches(&url("https://pod.example/publicity.ttl")));
+        assert!(public.allows(PodOperation::Read));
+        assert!(!public.allows(PodOperation::Write));
+
+        let absolute = ResourceRule::new("https://pod.example/private/*", vec![]);
+        assert!(absolute.matches(&url("https://pod.example/private/diary.ttl#it")));
+        assert!(!absolute.matches(&url("https://other.example/private/diary.ttl")));
+        assert_eq!(absolute.to_string(), "https://pod.example/private/* (no operations)");
+    }
+
+    #[test]
+    fn test_resource_policy_most_specific_wins() {
+        let policy = ResourcePolicy::new()
+            .with_rule(ResourceRule::new("*", vec![]))
+            .with_rule(ResourceRule::new("/public/*", vec![PodOperation::Read, PodOperation::List]))
+            .with_rule(ResourceRule::new("/private/*", vec![PodOperation::Read]))
+            .with_rule(ResourceRule::new("/private/shared/*", vec![PodOperation::Read, PodOperation::Write]))
+            .with_rule(ResourceRule::new("/private/shared/readme.ttl", vec![PodOperation::Read]))
+            .with_rule(ResourceRule::new("/private/shared/readme.*", vec![]));
+
+        let read = PodOperation::Read;
+        let write = PodOperation::Write;
+        assert!(policy.check(&url("https://pod.example/public/a.ttl"), read).is_ok());
+        assert_eq!(policy.check(&url("https://pod.example/public/a.ttl"), write).unwrap_err().pattern, "/public/*");
+        assert_eq!(policy.check(&url("https://pod.example/private/a.ttl"), write).unwrap_err().pattern, "/private/*");
+        assert!(policy.check(&url("https://pod.example/private/shared/a.ttl"), write).is_ok());
+
+        // Exact path beats a wildcard with the same literal prefix
+        let readme = url("https://pod.example/private/shared/readme.ttl");
+        assert_eq!(policy.rule_for(&readme).unwrap().pattern, "/private/shared/readme.ttl");
+        assert!(policy.check(&readme, read).is_ok());
+        assert!(policy.check(&readme, write).is_err());
+
+        // Catch-all denies everything else
+        assert_eq!(policy.check(&url("https://pod.example/inbox/1"), read).unwrap_err().pattern, "*");
+    }
+
+    #[test]
+    fn test_resource_policy_ties_prefer_first_rule() {
+        let policy = ResourcePolicy::new()
+            .with_rule(ResourceRule::new("/a/*/x", vec![PodOperation::Read]))
+            .with_rule(ResourceRule::new("/a/b/*", vec![]));
+        assert!(policy.check(&url("https://pod.example/a/b/x"), PodOperation::Read).is_ok());
+        assert!(ResourcePolicy::new().check(&url("https://pod.example/"), PodOperation::Write).is_ok());
+    }
 }
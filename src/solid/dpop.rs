@@ -1,6 +1,10 @@
 ///! DPoP (Demonstrating Proof of Possession) Implementation
 ///!
 ///! Implements RFC 9449 for cryptographically binding access tokens to agent key pairs.
+///!
+///! Server-provided nonces (`DPoP-Nonce`) are cached per origin and embedded in
+///! later proofs, and recently seen `jti` values are remembered so a leaked
+///! proof cannot be replayed through this process.
 
 use crate::Result;
 use anyhow::Context;
@@ -12,9 +16,72 @@ use p256::ecdsa::{SigningKey, VerifyingKey};
 use rand_core::OsRng;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+use url::Url;
 use uuid::Uuid;
 
+/// How long a `jti` is remembered by default
+///
+/// Resource servers only accept proofs issued within a few minutes, so a
+/// replay older than this would be rejected for its `iat` anyway.
+pub const DEFAULT_JTI_TTL: Duration = Duration::from_secs(300);
+
+/// Short-lived record of proof IDs, used to detect replays
+#[derive(Debug)]
+pub struct JtiCache {
+    /// How long each entry is kept
+    ttl: Duration,
+    /// `jti` to the time it was first seen
+    seen: Mutex<HashMap<String, Instant>>,
+}
+
+impl JtiCache {
+    /// Create a cache that forgets entries after `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Record `jti`, returning false if it was already seen within the TTL
+    pub fn insert(&self, jti: &str) -> bool {
+        let now = Instant::now();
+        let mut seen = self.seen.lock().unwrap();
+        seen.retain(|_, at| now.duration_since(*at) < self.ttl);
+        if seen.contains_key(jti) {
+            return false;
+        }
+        seen.insert(jti.to_string(), now);
+        true
+    }
+
+    /// Number of unexpired entries
+    pub fn len(&self) -> usize {
+        let now = Instant::now();
+        self.seen
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|at| now.duration_since(**at) < self.ttl)
+            .count()
+    }
+
+    /// Whether no unexpired entries remain
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Origin (`scheme://host:port`) that a nonce applies to
+fn origin_of(url: &str) -> Result<String> {
+    let url = Url::parse(url).context("Invalid DPoP target URI")?;
+    Ok(url.origin().ascii_serialization())
+}
+
 /// DPoP Manager handles key generation and proof creation
 pub struct DPoPManager {
     /// ECDSA P-256 signing key
@@ -23,6 +90,12 @@ pub struct DPoPManager {
     verifying_key: VerifyingKey,
     /// Key ID for rotation tracking
     kid: String,
+    /// Latest `DPoP-Nonce` per origin
+    nonces: RwLock<HashMap<String, String>>,
+    /// IDs of proofs this manager issued
+    issued: JtiCache,
+    /// IDs of proofs passed to [`Self::check_replay`]
+    observed: JtiCache,
 }
 
 impl DPoPManager {
@@ -32,15 +105,93 @@ impl DPoPManager {
         let verifying_key = VerifyingKey::from(&signing_key);
         let kid = Uuid::new_v4().to_string();
 
-        Ok(Self {
+        Ok(Self::from_parts(signing_key, verifying_key, kid))
+    }
+
+    /// Assemble a manager with empty nonce and jti caches
+    fn from_parts(signing_key: SigningKey, verifying_key: VerifyingKey, kid: String) -> Self {
+        Self {
             signing_key,
             verifying_key,
             kid,
-        })
+            nonces: RwLock::new(HashMap::new()),
+            issued: JtiCache::new(DEFAULT_JTI_TTL),
+            observed: JtiCache::new(DEFAULT_JTI_TTL),
+        }
+    }
+
+    /// Start with a known server nonce for `origin`
+    ///
+    /// `origin` may be any URL on the server; only its origin is kept.
+    pub fn with_nonce(self, origin: &str, nonce: impl Into<String>) -> Result<Self> {
+        self.set_nonce(origin, nonce)?;
+        Ok(self)
+    }
+
+    /// Set how long proof IDs are remembered for replay detection
+    pub fn with_jti_ttl(mut self, ttl: Duration) -> Self {
+        self.issued = JtiCache::new(ttl);
+        self.observed = JtiCache::new(ttl);
+        self
+    }
+
+    /// Cache the server nonce for the origin of `url`, replacing any previous one
+    pub fn set_nonce(&self, url: &str, nonce: impl Into<String>) -> Result<()> {
+        let origin = origin_of(url)?;
+        self.nonces.write().unwrap().insert(origin, nonce.into());
+        Ok(())
+    }
+
+    /// Cached server nonce for the origin of `url`
+    pub fn nonce_for(&self, url: &str) -> Option<String> {
+        let origin = origin_of(url).ok()?;
+        self.nonces.read().unwrap().get(&origin).cloned()
+    }
+
+    /// Cache the `DPoP-Nonce` header of a response from `url`
+    ///
+    /// Returns true when the response is a `use_dpop_nonce` challenge that
+    /// supplied a nonce, meaning the request should be retried with a new proof.
+    pub fn observe_response(&self, url: &str, response: &reqwest::Response) -> Result<bool> {
+        let headers = response.headers();
+        let Some(nonce) = headers.get("DPoP-Nonce").and_then(|v| v.to_str().ok()) else {
+            return Ok(false);
+        };
+        self.set_nonce(url, nonce)?;
+
+        let challenged = headers
+            .get_all(reqwest::header::WWW_AUTHENTICATE)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .any(|v| v.contains("use_dpop_nonce"));
+        let status = response.status();
+        Ok(challenged
+            && (status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::BAD_REQUEST))
+    }
+
+    /// Reject a proof whose `jti` was already seen within the TTL
+    ///
+    /// Only the claims are inspected; the signature is not verified.
+    pub fn check_replay(&self, proof: &str) -> Result<()> {
+        let payload = proof
+            .split('.')
+            .nth(1)
+            .context("DPoP proof is not a JWT")?;
+        let claims: DPoPClaims = serde_json::from_slice(
+            &URL_SAFE_NO_PAD.decode(payload).context("Invalid DPoP proof encoding")?,
+        )
+        .context("Invalid DPoP proof claims")?;
+
+        if !self.observed.insert(&claims.jti) {
+            return Err(anyhow::anyhow!("DPoP proof {} was replayed", claims.jti));
+        }
+        Ok(())
     }
 
     /// Create DPoP proof JWT for a specific HTTP request
     ///
+    /// Includes the cached server nonce for the origin of `htu`, if any.
+    ///
     /// # Arguments
     /// * `htm` - HTTP method (e.g., "GET", "POST")
     /// * `htu` - HTTP URI being accessed
@@ -62,13 +213,22 @@ impl DPoPManager {
             "jwk": jwk
         });
 
+        // Never reuse a jti, even across a (vanishingly unlikely) UUID collision
+        let jti = loop {
+            let jti = Uuid::new_v4().to_string();
+            if self.issued.insert(&jti) {
+                break jti;
+            }
+        };
+
         // Create claims
         let claims = DPoPClaims {
-            jti: Uuid::new_v4().to_string(),
+            jti,
             htm: htm.to_string(),
             htu: htu.to_string(),
             iat: Utc::now().timestamp(),
             ath: ath.map(String::from),
+            nonce: self.nonce_for(htu),
         };
 
         // Sign with private key
@@ -148,11 +308,7 @@ impl DPoPManager {
         // Generate new kid on load
         let kid = Uuid::new_v4().to_string();
 
-        Ok(Self {
-            signing_key,
-            verifying_key,
-            kid,
-        })
+        Ok(Self::from_parts(signing_key, verifying_key, kid))
     }
 }
 
@@ -170,6 +326,9 @@ struct DPoPClaims {
     /// Access token hash (for resource server requests)
     #[serde(skip_serializing_if = "Option::is_none")]
     ath: Option<String>,
+    /// Server-provided nonce
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nonce: Option<String>,
 }
 
 #[cfg(test)]
@@ -190,6 +349,58 @@ mod tests {
         assert!(proof.split('.').count() == 3);
     }
 
+    /// Decode the claims of a proof without verifying it
+    fn claims(proof: &str) -> DPoPClaims {
+        let payload = URL_SAFE_NO_PAD.decode(proof.split('.').nth(1).unwrap()).unwrap();
+        serde_json::from_slice(&payload).unwrap()
+    }
+
+    #[test]
+    fn test_nonce_round_trip() {
+        let manager = DPoPManager::generate()
+            .unwrap()
+            .with_nonce("https://pod.example/", "n-1")
+            .unwrap();
+
+        let proof = manager.create_proof("GET", "https://pod.example/alice/notes", None).unwrap();
+        assert_eq!(claims(&proof).nonce.as_deref(), Some("n-1"));
+
+        // Nonces are per origin
+        let other = manager.create_proof("GET", "https://pod.example:8443/alice", None).unwrap();
+        assert_eq!(claims(&other).nonce, None);
+
+        manager.set_nonce("https://pod.example/alice/notes", "n-2").unwrap();
+        let proof = manager.create_proof("PUT", "https://pod.example/bob", None).unwrap();
+        assert_eq!(claims(&proof).nonce.as_deref(), Some("n-2"));
+        assert!(manager.set_nonce("not a url", "n-3").is_err());
+    }
+
+    #[test]
+    fn test_jti_uniqueness_and_replay() {
+        let manager = DPoPManager::generate().unwrap();
+        let proofs: Vec<_> = (0..50)
+            .map(|_| manager.create_proof("GET", "https://pod.example/", None).unwrap())
+            .collect();
+        let jtis: std::collections::HashSet<_> = proofs.iter().map(|p| claims(p).jti).collect();
+        assert_eq!(jtis.len(), 50);
+        assert_eq!(manager.issued.len(), 50);
+
+        assert!(manager.check_replay(&proofs[0]).is_ok());
+        assert!(manager.check_replay(&proofs[0]).is_err());
+        assert!(manager.check_replay(&proofs[1]).is_ok());
+        assert!(manager.check_replay("garbage").is_err());
+    }
+
+    #[test]
+    fn test_jti_cache_expiry() {
+        let cache = JtiCache::new(Duration::from_millis(20));
+        assert!(cache.insert("a"));
+        assert!(!cache.insert("a"));
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(cache.is_empty());
+        assert!(cache.insert("a"));
+    }
+
     #[test]
     fn test_ath_computation() {
         let token = "test_access_token";
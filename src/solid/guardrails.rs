@@ -1,14 +1,16 @@
 ///! Consent Enforcement Guardrail
 ///!
-///! Enforces user consent policies before allowing agent navigation to domains.
+///! Enforces user consent policies before allowing agent navigation to domains,
+///! and the manifest's per-resource rules before Pod operations.
 
 use crate::guardrails::{GuardrailContext, GuardrailResult, InputGuardrail};
 use crate::hitl::{ActionType, ApprovalContext, ApprovalRequest, Priority};
 use crate::Result;
-use crate::solid::consent::{ConsentManifest, ConsentValue};
+use crate::solid::consent::{ConsentManifest, ConsentValue, PodOperation, ResourcePolicy};
 use anyhow::Context;
 use async_trait::async_trait;
 use regex::Regex;
+use serde_json::Value;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use url::Url;
@@ -17,6 +19,13 @@ use url::Url;
 ///
 /// Checks user consent manifest before allowing agent to navigate to domains.
 /// Blocks navigation if domain is denied, triggers HITL if approval needed.
+///
+/// As an input guardrail it only sees the domains named in the input. The
+/// manifest's resource rules are opt-in per tool: a [`SolidPodTool`] checks
+/// them only when given this guardrail through
+/// [`with_consent_guardrail`](SolidPodTool::with_consent_guardrail).
+///
+/// [`SolidPodTool`]: crate::solid::tools::SolidPodTool
 pub struct ConsentEnforcementGuardrail {
     /// Consent manifest
     manifest: Arc<RwLock<ConsentManifest>>,
@@ -36,7 +45,7 @@ impl ConsentEnforcementGuardrail {
     /// Extract URLs from input text
     fn extract_urls(text: &str) -> Vec<Url> {
         let url_pattern = Regex::new(
-            r"https?://[^\s<>\"'\)]+|www\.[^\s<>\"'\)]+"
+            r#"https?://[^\s<>"'\)]+|www\.[^\s<>"'\)]+"#
         ).unwrap();
 
         let mut urls = Vec::new();
@@ -63,6 +72,43 @@ impl ConsentEnforcementGuardrail {
     fn extract_domain(url: &Url) -> Option<String> {
         url.domain().map(|d| d.to_string())
     }
+
+    /// Check an operation on a Pod resource against the manifest's resource rules
+    pub async fn check_resource_access(&self, url: &Url, operation: PodOperation) -> GuardrailResult {
+        let manifest = self.manifest.read().await;
+        Self::evaluate_resource(manifest.resource_policy(), url, operation)
+    }
+
+    /// Check a `SolidPodTool` call from its `operation` and `resourceUri` parameters
+    pub async fn check_tool_call(&self, params: &Value) -> GuardrailResult {
+        let operation = params["operation"].as_str().unwrap_or_default();
+        let Some(operation) = PodOperation::parse(operation) else {
+            return GuardrailResult::fail(format!("Unknown Pod operation '{}'", operation));
+        };
+        let url = params["resourceUri"].as_str().unwrap_or_default();
+        let Ok(url) = Url::parse(url) else {
+            return GuardrailResult::fail(format!("Invalid resource URI '{}'", url));
+        };
+        self.check_resource_access(&url, operation).await
+    }
+
+    /// Decide `operation` on `url` under `policy`, naming the rule that denies it
+    fn evaluate_resource(policy: &ResourcePolicy, url: &Url, operation: PodOperation) -> GuardrailResult {
+        match policy.check(url, operation) {
+            Ok(()) => GuardrailResult::pass(format!(
+                "'{}' on '{}' is permitted by user consent policy",
+                operation, url
+            )),
+            Err(rule) => GuardrailResult::fail(format!(
+                "'{}' on '{}' is denied by consent rule '{}'",
+                operation, url, rule
+            ))
+            .with_suggestion(format!(
+                "Use one of the permitted operations or ask the user to update the rule for '{}'.",
+                rule.pattern
+            )),
+        }
+    }
 }
 
 #[async_trait]
@@ -112,6 +158,8 @@ impl InputGuardrail for ConsentEnforcementGuardrail {
                         domain
                     )),
                     confidence: 1.0,
+                    redactions: 0,
+                    guardrail: String::new(),
                 });
             }
 
@@ -139,6 +187,7 @@ impl InputGuardrail for ConsentEnforcementGuardrail {
                     priority: Priority::Normal,
                     deadline: None,
                     suggested_approvers: vec![],
+                    timeout: None,
                 };
 
                 // In a real implementation, we'd trigger HITL approval here
@@ -157,6 +206,8 @@ impl InputGuardrail for ConsentEnforcementGuardrail {
                         domain
                     )),
                     confidence: 1.0,
+                    redactions: 0,
+                    guardrail: String::new(),
                 });
             }
 
@@ -204,6 +255,31 @@ mod tests {
         assert_eq!(domain, Some("subdomain.example.com".to_string()));
     }
 
+    #[test]
+    fn test_resource_rules_name_the_violated_rule() {
+        use crate::solid::consent::ResourceRule;
+
+        let policy = ResourcePolicy::new()
+            .with_rule(ResourceRule::new("/public/*", vec![PodOperation::Read]))
+            .with_rule(ResourceRule::new("/private/*", vec![]))
+            .with_rule(ResourceRule::new("/private/drafts/*", vec![PodOperation::Read, PodOperation::Write]));
+
+        let public = Url::parse("https://pod.example/public/notes.ttl").unwrap();
+        assert!(ConsentEnforcementGuardrail::evaluate_resource(&policy, &public, PodOperation::Read).passed);
+
+        let denied = ConsentEnforcementGuardrail::evaluate_resource(&policy, &public, PodOperation::Write);
+        assert!(!denied.passed);
+        assert!(denied.reasoning.contains("consent rule '/public/* (read)'"));
+
+        let private = Url::parse("https://pod.example/private/diary.ttl").unwrap();
+        let denied = ConsentEnforcementGuardrail::evaluate_resource(&policy, &private, PodOperation::Read);
+        assert!(denied.reasoning.contains("/private/* (no operations)"));
+        assert!(denied.suggested_modification.unwrap().contains("'/private/*'"));
+
+        let draft = Url::parse("https://pod.example/private/drafts/post.ttl").unwrap();
+        assert!(ConsentEnforcementGuardrail::evaluate_resource(&policy, &draft, PodOperation::Write).passed);
+    }
+
     #[test]
     fn test_url_extraction_complex() {
         let text = r#"
@@ -33,11 +33,16 @@ impl SolidIdentityClient {
         let ipc = IpcChannel::spawn(bridge_path)
             .context("Failed to spawn Solid identity bridge")?;
 
-        Ok(Self {
+        Ok(Self::with_channel(webid, client_id, ipc))
+    }
+
+    /// Create an identity client over an already-open bridge channel
+    pub(crate) fn with_channel(webid: Url, client_id: Url, ipc: IpcChannel) -> Self {
+        Self {
             webid,
             client_id,
             ipc: Arc::new(Mutex::new(ipc)),
-        })
+        }
     }
 
     /// Fetch WebID profile document
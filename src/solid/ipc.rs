@@ -37,8 +37,14 @@ impl IpcChannel {
         }
 
         // Spawn Node.js process
-        let mut child = Command::new("node")
-            .arg(&bridge_path)
+        let mut command = Command::new("node");
+        command.arg(&bridge_path);
+        Self::spawn_command(command)
+    }
+
+    /// Speak JSON-RPC over the stdio of `command`, which stands in for the bridge
+    pub(crate) fn spawn_command(mut command: Command) -> Result<Self> {
+        let mut child = command
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::inherit()) // Log errors to parent's stderr
@@ -30,10 +30,10 @@ pub mod guardrails;
 // Re-export key types when feature is enabled
 #[cfg(feature = "solid-integration")]
 pub use self::{
-    dpop::DPoPManager,
+    dpop::{DPoPManager, JtiCache},
     identity::SolidIdentityClient,
     auth::SolidOidcClient,
-    consent::ConsentManifest,
+    consent::{ConsentManifest, PodOperation, ResourcePolicy, ResourceRule},
     tools::SolidPodTool,
     guardrails::ConsentEnforcementGuardrail,
 };
@@ -4,7 +4,8 @@
 
 use crate::tools::{Tool, ToolContext, ToolOutput};
 use crate::Result;
-use crate::solid::auth::SolidOidcClient;
+use crate::solid::auth::{send_dpop_request, SolidOidcClient};
+use crate::solid::guardrails::ConsentEnforcementGuardrail;
 use anyhow::Context;
 use async_trait::async_trait;
 use serde_json::{json, Value};
@@ -17,6 +18,8 @@ pub struct SolidPodTool {
     oidc_client: Arc<SolidOidcClient>,
     /// Tool ID
     tool_id: String,
+    /// Resource rules checked before each operation
+    consent: Option<Arc<ConsentEnforcementGuardrail>>,
 }
 
 impl SolidPodTool {
@@ -25,8 +28,18 @@ impl SolidPodTool {
         Self {
             oidc_client,
             tool_id: "solid_pod".to_string(),
+            consent: None,
         }
     }
+
+    /// Refuse operations the consent manifest's resource rules do not allow
+    ///
+    /// Without this the tool does not consult the manifest at all; attaching the
+    /// guardrail to the agent only checks the domains named in its input.
+    pub fn with_consent_guardrail(mut self, guardrail: Arc<ConsentEnforcementGuardrail>) -> Self {
+        self.consent = Some(guardrail);
+        self
+    }
 }
 
 #[async_trait]
@@ -88,6 +101,13 @@ impl Tool for SolidPodTool {
         let resource_url = Url::parse(resource_uri)
             .context("Invalid resource URI")?;
 
+        if let Some(consent) = &self.consent {
+            let result = consent.check_tool_call(&params).await;
+            if !result.passed {
+                return Ok(ToolOutput::failure(result.reasoning));
+            }
+        }
+
         match operation {
             "read" => self.read_resource(&resource_url).await,
             "write" => {
@@ -144,24 +164,18 @@ impl SolidPodTool {
     async fn write_resource(&self, url: &Url, data: &str) -> Result<ToolOutput> {
         let client = reqwest::Client::new();
 
-        // Get access token and create DPoP proof
+        // Get access token; proofs are created per attempt
         let token = self.oidc_client.authenticate(url).await?;
-        let ath = crate::solid::dpop::DPoPManager::compute_ath(&token.access_token);
-        let dpop_proof = self.oidc_client.dpop_manager.create_proof(
+        let response = send_dpop_request(
+            &client,
+            self.oidc_client.dpop_manager(),
             "PUT",
-            url.as_str(),
-            Some(&ath),
-        )?;
-
-        let response = client
-            .put(url.clone())
-            .header("Authorization", format!("DPoP {}", token.access_token))
-            .header("DPoP", dpop_proof)
-            .header("Content-Type", "text/turtle")
-            .body(data.to_string())
-            .send()
-            .await
-            .context("Failed to write resource")?;
+            url,
+            &token.access_token,
+            |request| request.header("Content-Type", "text/turtle").body(data.to_string()),
+        )
+        .await
+        .context("Failed to write resource")?;
 
         let status = response.status();
         if !status.is_success() {
@@ -230,10 +244,52 @@ impl SolidPodTool {
 mod tests {
     use super::*;
 
-    #[test]
-    fn test_tool_metadata() {
-        // Create mock OIDC client for testing
-        // In production tests, we'd use actual mock
-        // For now, skip runtime test
+    use crate::solid::consent::{ConsentManifest, PodOperation, ResourceRule};
+    use crate::solid::dpop::DPoPManager;
+    use crate::solid::identity::SolidIdentityClient;
+    use crate::solid::ipc::IpcChannel;
+    use crate::types::AgentId;
+    use tokio::sync::RwLock;
+
+    /// Identity client whose bridge answers every request with an empty document
+    fn stub_identity() -> Arc<SolidIdentityClient> {
+        let mut bridge = std::process::Command::new("sh");
+        bridge.arg("-c").arg(
+            r#"while read -r line; do echo '{"jsonrpc":"2.0","id":0,"result":{"content":""}}'; done"#,
+        );
+        Arc::new(SolidIdentityClient::with_channel(
+            Url::parse("https://agent.example/profile#me").unwrap(),
+            Url::parse("https://agent.example/client.json").unwrap(),
+            IpcChannel::spawn_command(bridge).unwrap(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn test_consent_rules_apply_only_when_attached() {
+        let identity = stub_identity();
+        let manifest = ConsentManifest::load(Url::parse("https://pod.example/").unwrap(), identity.clone())
+            .await
+            .unwrap()
+            .with_resource_rule(ResourceRule::new("/private/*", vec![]))
+            .with_resource_rule(ResourceRule::new("/public/*", vec![PodOperation::Read]));
+        let guardrail = Arc::new(ConsentEnforcementGuardrail::new(Arc::new(RwLock::new(manifest))));
+        let oidc = Arc::new(SolidOidcClient::new(identity, Arc::new(DPoPManager::generate().unwrap())));
+        let tool = SolidPodTool::new(oidc).with_consent_guardrail(guardrail);
+        let ctx = ToolContext::new(AgentId::new());
+
+        // Denied before any request reaches the Pod
+        let write = json!({
+            "operation": "write",
+            "resourceUri": "https://pod.example/public/notes.ttl",
+            "data": "<#a> <#b> <#c> ."
+        });
+        let output = tool.execute(write, &ctx).await.unwrap();
+        assert!(!output.success);
+        assert!(output.error.unwrap().contains("denied by consent rule '/public/* (read)'"));
+
+        let read = json!({ "operation": "read", "resourceUri": "https://pod.example/private/diary.ttl" });
+        let output = tool.execute(read, &ctx).await.unwrap();
+        assert!(!output.success);
+        assert!(output.error.unwrap().contains("/private/* (no operations)"));
     }
 }
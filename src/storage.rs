@@ -6,12 +6,19 @@
 //! - PostgreSQL backend for distributed deployments
 //! - Automatic migrations
 //! - Memory block and message history persistence
+//! - Background run persistence ([`RunStorage`](crate::background::RunStorage))
 
+#[cfg(feature = "storage")]
+use crate::background::{RunEvent, RunId, RunMetadata, RunStorage, SeqId};
+#[cfg(feature = "storage")]
+use crate::embedding::{cosine_similarity, Embedder};
 #[cfg(feature = "storage")]
 use crate::error::{Error, Result};
 #[cfg(feature = "storage")]
 use crate::memory::{MemoryBlock, MemoryBlockId, MessageEntry};
 #[cfg(feature = "storage")]
+use crate::openrouter::Role;
+#[cfg(feature = "storage")]
 use crate::types::AgentId;
 #[cfg(feature = "storage")]
 use async_trait::async_trait;
@@ -50,10 +57,51 @@ pub trait MemoryStorage: Send + Sync {
     async fn delete_agent_data(&self, agent_id: AgentId) -> Result<()>;
 }
 
+/// Memory storage that can recall messages by embedding similarity
+#[cfg(feature = "storage")]
+#[async_trait]
+pub trait SemanticMemoryStorage: MemoryStorage {
+    /// Store (or replace) the embedding of a saved message
+    async fn save_embedding(&self, agent_id: AgentId, message_id: uuid::Uuid, embedding: &[f32]) -> Result<()>;
+
+    /// The `k` messages nearest to `query_embedding` by cosine similarity, best first
+    async fn search_similar(
+        &self,
+        agent_id: AgentId,
+        query_embedding: &[f32],
+        k: usize,
+    ) -> Result<Vec<(MessageEntry, f32)>>;
+
+    /// Save a message along with its embedding
+    async fn save_message_embedded(
+        &self,
+        agent_id: AgentId,
+        message: &MessageEntry,
+        embedder: &dyn Embedder,
+    ) -> Result<()> {
+        let embedding = embedder.embed_one(&message.content).await?;
+        self.save_message(agent_id, message).await?;
+        self.save_embedding(agent_id, message.id, &embedding).await
+    }
+
+    /// Embed `query` and return the `k` most similar messages
+    async fn search_similar_text(
+        &self,
+        agent_id: AgentId,
+        query: &str,
+        embedder: &dyn Embedder,
+        k: usize,
+    ) -> Result<Vec<(MessageEntry, f32)>> {
+        let embedding = embedder.embed_one(query).await?;
+        self.search_similar(agent_id, &embedding, k).await
+    }
+}
+
 /// SQLite storage backend
 #[cfg(feature = "storage")]
 pub struct SqliteStorage {
     pool: Pool<Sqlite>,
+    fts: bool,
 }
 
 #[cfg(feature = "storage")]
@@ -64,12 +112,108 @@ impl SqliteStorage {
             .await
             .map_err(|e| Error::config(format!("Failed to connect to SQLite: {}", e)))?;
 
-        let storage = Self { pool };
+        let mut storage = Self { pool, fts: false };
         storage.run_migrations().await?;
+        storage.fts = storage.create_message_index().await;
 
         Ok(storage)
     }
 
+    /// Whether message search uses the FTS5 index (false if SQLite lacks FTS5)
+    pub fn has_full_text_search(&self) -> bool {
+        self.fts
+    }
+
+    /// Create the FTS5 index over message content, returning whether it is available
+    ///
+    /// The porter tokenizer lets a search for "decide" find "decided".
+    async fn create_message_index(&self) -> bool {
+        let existed = sqlx::query("SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'messages_fts'")
+            .fetch_optional(&self.pool)
+            .await
+            .map(|row| row.is_some())
+            .unwrap_or(false);
+
+        let statements = [
+            "CREATE VIRTUAL TABLE IF NOT EXISTS messages_fts USING fts5(content, content='messages', content_rowid='rowid', tokenize='porter unicode61')",
+            r#"
+            CREATE TRIGGER IF NOT EXISTS messages_fts_insert AFTER INSERT ON messages BEGIN
+                INSERT INTO messages_fts(rowid, content) VALUES (new.rowid, new.content);
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS messages_fts_delete AFTER DELETE ON messages BEGIN
+                INSERT INTO messages_fts(messages_fts, rowid, content) VALUES ('delete', old.rowid, old.content);
+            END
+            "#,
+            r#"
+            CREATE TRIGGER IF NOT EXISTS messages_fts_update AFTER UPDATE ON messages BEGIN
+                INSERT INTO messages_fts(messages_fts, rowid, content) VALUES ('delete', old.rowid, old.content);
+                INSERT INTO messages_fts(rowid, content) VALUES (new.rowid, new.content);
+            END
+            "#,
+        ];
+        for statement in statements {
+            if let Err(e) = sqlx::query(statement).execute(&self.pool).await {
+                tracing::warn!("SQLite FTS5 unavailable, message search falls back to LIKE: {}", e);
+                return false;
+            }
+        }
+
+        // Index messages stored before the FTS table existed
+        if !existed {
+            if let Err(e) = sqlx::query("INSERT INTO messages_fts(messages_fts) VALUES ('rebuild')")
+                .execute(&self.pool)
+                .await
+            {
+                tracing::warn!("Failed to build message search index: {}", e);
+                return false;
+            }
+        }
+
+        true
+    }
+
+    /// Parse a `id, timestamp, role, content, tool_calls, metadata` row
+    fn message_from_row(row: &sqlx::sqlite::SqliteRow) -> Result<MessageEntry> {
+        let id_str: String = row.get(0);
+        let timestamp_str: String = row.get(1);
+        let tool_calls_json: Option<String> = row.get(4);
+        let metadata_json: String = row.get(5);
+
+        Ok(MessageEntry {
+            id: uuid::Uuid::parse_str(&id_str)
+                .map_err(|e| Error::config(format!("Invalid message ID: {}", e)))?,
+            timestamp: DateTime::parse_from_rfc3339(&timestamp_str)
+                .map_err(|e| Error::config(format!("Invalid timestamp: {}", e)))?
+                .with_timezone(&Utc),
+            role: Role::from(row.get::<String, _>(2)),
+            content: row.get(3),
+            tool_calls: tool_calls_json
+                .map(|json| serde_json::from_str(&json))
+                .transpose()
+                .map_err(|e| Error::config(format!("Invalid tool_calls JSON: {}", e)))?,
+            metadata: serde_json::from_str(&metadata_json)
+                .map_err(|e| Error::config(format!("Invalid metadata JSON: {}", e)))?,
+        })
+    }
+
+    /// Search message content with a plain substring match, newest first
+    async fn search_messages_like(&self, agent_id: AgentId, query: &str) -> Result<Vec<sqlx::sqlite::SqliteRow>> {
+        sqlx::query(
+            r#"
+            SELECT id, timestamp, role, content, tool_calls, metadata
+            FROM messages WHERE agent_id = ? AND content LIKE ?
+            ORDER BY timestamp DESC
+            "#,
+        )
+        .bind(agent_id.to_string())
+        .bind(format!("%{}%", query))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to search messages: {}", e)))
+    }
+
     /// Run database migrations
     async fn run_migrations(&self) -> Result<()> {
         // Create memory_blocks table
@@ -122,6 +266,206 @@ impl SqliteStorage {
             .await
             .map_err(|e| Error::config(format!("Failed to create index: {}", e)))?;
 
+        // Create message embeddings table (little-endian f32 blobs)
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS message_embeddings (
+                message_id TEXT PRIMARY KEY,
+                agent_id TEXT NOT NULL,
+                embedding BLOB NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to create message_embeddings table: {}", e)))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_message_embeddings_agent ON message_embeddings(agent_id)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::config(format!("Failed to create index: {}", e)))?;
+
+        // Create background run tables
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS background_runs (
+                run_id TEXT PRIMARY KEY,
+                agent_name TEXT NOT NULL,
+                input TEXT NOT NULL,
+                status TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                started_at TEXT,
+                completed_at TEXT,
+                total_events INTEGER NOT NULL,
+                last_seq_id INTEGER NOT NULL,
+                metadata TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to create background_runs table: {}", e)))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS run_events (
+                run_id TEXT NOT NULL,
+                seq_id INTEGER NOT NULL,
+                timestamp TEXT NOT NULL,
+                event_type TEXT NOT NULL,
+                data TEXT NOT NULL,
+                PRIMARY KEY (run_id, seq_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to create run_events table: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+/// Parse an RFC 3339 timestamp stored as text
+#[cfg(feature = "storage")]
+fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+    Ok(DateTime::parse_from_rfc3339(value)
+        .map_err(|e| Error::config(format!("Invalid timestamp: {}", e)))?
+        .with_timezone(&Utc))
+}
+
+#[cfg(feature = "storage")]
+#[async_trait]
+impl RunStorage for SqliteStorage {
+    async fn save_run(&self, metadata: &RunMetadata) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO background_runs
+            (run_id, agent_name, input, status, created_at, started_at, completed_at, total_events, last_seq_id, metadata)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(metadata.run_id.to_string())
+        .bind(&metadata.agent_name)
+        .bind(&metadata.input)
+        .bind(serde_json::to_string(&metadata.status)?)
+        .bind(metadata.created_at.to_rfc3339())
+        .bind(metadata.started_at.map(|t| t.to_rfc3339()))
+        .bind(metadata.completed_at.map(|t| t.to_rfc3339()))
+        .bind(metadata.total_events as i64)
+        .bind(metadata.last_seq_id.value() as i64)
+        .bind(serde_json::to_string(&metadata.metadata)?)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to save run: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn save_run_event(&self, run_id: RunId, event: &RunEvent) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO run_events (run_id, seq_id, timestamp, event_type, data)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(run_id.to_string())
+        .bind(event.seq_id.value() as i64)
+        .bind(event.timestamp.to_rfc3339())
+        .bind(serde_json::to_string(&event.event_type)?)
+        .bind(serde_json::to_string(&event.data)?)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to save run event: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn load_runs(&self) -> Result<Vec<RunMetadata>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT run_id, agent_name, input, status, created_at, started_at, completed_at,
+                   total_events, last_seq_id, metadata
+            FROM background_runs
+            ORDER BY created_at
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to load runs: {}", e)))?;
+
+        let mut runs = Vec::new();
+        for row in rows {
+            let run_id: String = row.get(0);
+            let status: String = row.get(3);
+            let created_at: String = row.get(4);
+            let started_at: Option<String> = row.get(5);
+            let completed_at: Option<String> = row.get(6);
+            let total_events: i64 = row.get(7);
+            let last_seq_id: i64 = row.get(8);
+            let metadata: String = row.get(9);
+
+            runs.push(RunMetadata {
+                run_id: run_id.parse()?,
+                agent_name: row.get(1),
+                input: row.get(2),
+                status: serde_json::from_str(&status)?,
+                created_at: parse_timestamp(&created_at)?,
+                started_at: started_at.as_deref().map(parse_timestamp).transpose()?,
+                completed_at: completed_at.as_deref().map(parse_timestamp).transpose()?,
+                total_events: total_events as usize,
+                last_seq_id: SeqId::new(last_seq_id as u64),
+                metadata: serde_json::from_str(&metadata)?,
+            });
+        }
+
+        Ok(runs)
+    }
+
+    async fn load_run_events(&self, run_id: RunId) -> Result<Vec<RunEvent>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT seq_id, timestamp, event_type, data
+            FROM run_events WHERE run_id = ?
+            ORDER BY seq_id
+            "#,
+        )
+        .bind(run_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to load run events: {}", e)))?;
+
+        let mut events = Vec::new();
+        for row in rows {
+            let seq_id: i64 = row.get(0);
+            let timestamp: String = row.get(1);
+            let event_type: String = row.get(2);
+            let data: String = row.get(3);
+
+            events.push(RunEvent {
+                seq_id: SeqId::new(seq_id as u64),
+                timestamp: parse_timestamp(&timestamp)?,
+                event_type: serde_json::from_str(&event_type)?,
+                data: serde_json::from_str(&data)?,
+            });
+        }
+
+        Ok(events)
+    }
+
+    async fn delete_run(&self, run_id: RunId) -> Result<()> {
+        sqlx::query("DELETE FROM run_events WHERE run_id = ?")
+            .bind(run_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::config(format!("Failed to delete run events: {}", e)))?;
+
+        sqlx::query("DELETE FROM background_runs WHERE run_id = ?")
+            .bind(run_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::config(format!("Failed to delete run: {}", e)))?;
+
         Ok(())
     }
 }
@@ -270,15 +614,22 @@ impl MemoryStorage for SqliteStorage {
 
         sqlx::query(
             r#"
-            INSERT OR REPLACE INTO messages
+            INSERT INTO messages
             (id, agent_id, timestamp, role, content, tool_calls, metadata)
             VALUES (?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(id) DO UPDATE SET
+                agent_id = excluded.agent_id,
+                timestamp = excluded.timestamp,
+                role = excluded.role,
+                content = excluded.content,
+                tool_calls = excluded.tool_calls,
+                metadata = excluded.metadata
             "#,
         )
         .bind(message.id.to_string())
         .bind(agent_id.to_string())
         .bind(message.timestamp.to_rfc3339())
-        .bind(&message.role)
+        .bind(message.role.as_str())
         .bind(&message.content)
         .bind(tool_calls_json)
         .bind(metadata_json)
@@ -304,73 +655,43 @@ impl MemoryStorage for SqliteStorage {
         .await
         .map_err(|e| Error::config(format!("Failed to load messages: {}", e)))?;
 
-        let mut messages = Vec::new();
-        for row in rows {
-            let id_str: String = row.get(0);
-            let timestamp_str: String = row.get(1);
-            let tool_calls_json: Option<String> = row.get(4);
-            let metadata_json: String = row.get(5);
-
-            messages.push(MessageEntry {
-                id: uuid::Uuid::parse_str(&id_str)
-                    .map_err(|e| Error::config(format!("Invalid message ID: {}", e)))?,
-                timestamp: DateTime::parse_from_rfc3339(&timestamp_str)
-                    .map_err(|e| Error::config(format!("Invalid timestamp: {}", e)))?
-                    .with_timezone(&Utc),
-                role: row.get(2),
-                content: row.get(3),
-                tool_calls: tool_calls_json
-                    .map(|json| serde_json::from_str(&json))
-                    .transpose()
-                    .map_err(|e| Error::config(format!("Invalid tool_calls JSON: {}", e)))?,
-                metadata: serde_json::from_str(&metadata_json)
-                    .map_err(|e| Error::config(format!("Invalid metadata JSON: {}", e)))?,
-            });
-        }
+        let mut messages = rows
+            .iter()
+            .map(Self::message_from_row)
+            .collect::<Result<Vec<_>>>()?;
 
         messages.reverse(); // Return in chronological order
         Ok(messages)
     }
 
     async fn search_messages(&self, agent_id: AgentId, query: &str) -> Result<Vec<MessageEntry>> {
-        let rows = sqlx::query(
-            r#"
-            SELECT id, timestamp, role, content, tool_calls, metadata
-            FROM messages WHERE agent_id = ? AND content LIKE ?
-            ORDER BY timestamp DESC
-            "#,
-        )
-        .bind(agent_id.to_string())
-        .bind(format!("%{}%", query))
-        .fetch_all(&self.pool)
-        .await
-        .map_err(|e| Error::config(format!("Failed to search messages: {}", e)))?;
-
-        let mut messages = Vec::new();
-        for row in rows {
-            let id_str: String = row.get(0);
-            let timestamp_str: String = row.get(1);
-            let tool_calls_json: Option<String> = row.get(4);
-            let metadata_json: String = row.get(5);
-
-            messages.push(MessageEntry {
-                id: uuid::Uuid::parse_str(&id_str)
-                    .map_err(|e| Error::config(format!("Invalid message ID: {}", e)))?,
-                timestamp: DateTime::parse_from_rfc3339(&timestamp_str)
-                    .map_err(|e| Error::config(format!("Invalid timestamp: {}", e)))?
-                    .with_timezone(&Utc),
-                role: row.get(2),
-                content: row.get(3),
-                tool_calls: tool_calls_json
-                    .map(|json| serde_json::from_str(&json))
-                    .transpose()
-                    .map_err(|e| Error::config(format!("Invalid tool_calls JSON: {}", e)))?,
-                metadata: serde_json::from_str(&metadata_json)
-                    .map_err(|e| Error::config(format!("Invalid metadata JSON: {}", e)))?,
-            });
-        }
+        let rows = if self.fts {
+            let ranked = sqlx::query(
+                r#"
+                SELECT m.id, m.timestamp, m.role, m.content, m.tool_calls, m.metadata
+                FROM messages_fts JOIN messages m ON m.rowid = messages_fts.rowid
+                WHERE messages_fts MATCH ? AND m.agent_id = ?
+                ORDER BY bm25(messages_fts)
+                "#,
+            )
+            .bind(query)
+            .bind(agent_id.to_string())
+            .fetch_all(&self.pool)
+            .await;
+
+            match ranked {
+                Ok(rows) => rows,
+                // Queries that are not valid FTS5 syntax still get a substring match
+                Err(e) => {
+                    tracing::debug!("FTS5 query {:?} failed, using LIKE: {}", query, e);
+                    self.search_messages_like(agent_id, query).await?
+                }
+            }
+        } else {
+            self.search_messages_like(agent_id, query).await?
+        };
 
-        Ok(messages)
+        rows.iter().map(Self::message_from_row).collect()
     }
 
     async fn delete_agent_data(&self, agent_id: AgentId) -> Result<()> {
@@ -386,14 +707,79 @@ impl MemoryStorage for SqliteStorage {
             .await
             .map_err(|e| Error::config(format!("Failed to delete agent messages: {}", e)))?;
 
+        sqlx::query("DELETE FROM message_embeddings WHERE agent_id = ?")
+            .bind(agent_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::config(format!("Failed to delete agent embeddings: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+/// Brute-force cosine search over the agent's stored embeddings
+#[cfg(feature = "storage")]
+#[async_trait]
+impl SemanticMemoryStorage for SqliteStorage {
+    async fn save_embedding(&self, agent_id: AgentId, message_id: uuid::Uuid, embedding: &[f32]) -> Result<()> {
+        let bytes: Vec<u8> = embedding.iter().flat_map(|x| x.to_le_bytes()).collect();
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO message_embeddings (message_id, agent_id, embedding)
+            VALUES (?, ?, ?)
+            "#,
+        )
+        .bind(message_id.to_string())
+        .bind(agent_id.to_string())
+        .bind(bytes)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to save embedding: {}", e)))?;
+
         Ok(())
     }
+
+    async fn search_similar(
+        &self,
+        agent_id: AgentId,
+        query_embedding: &[f32],
+        k: usize,
+    ) -> Result<Vec<(MessageEntry, f32)>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT m.id, m.timestamp, m.role, m.content, m.tool_calls, m.metadata, e.embedding
+            FROM message_embeddings e JOIN messages m ON m.id = e.message_id
+            WHERE e.agent_id = ?
+            "#,
+        )
+        .bind(agent_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to search embeddings: {}", e)))?;
+
+        let mut scored = Vec::with_capacity(rows.len());
+        for row in &rows {
+            let bytes: Vec<u8> = row.get(6);
+            let embedding: Vec<f32> = bytes
+                .chunks_exact(4)
+                .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+                .collect();
+            let score = cosine_similarity(query_embedding, &embedding);
+            scored.push((Self::message_from_row(row)?, score));
+        }
+
+        scored.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        scored.truncate(k);
+        Ok(scored)
+    }
 }
 
 /// PostgreSQL storage backend
 #[cfg(feature = "storage")]
 pub struct PostgresStorage {
     pool: Pool<Postgres>,
+    pgvector: bool,
 }
 
 #[cfg(feature = "storage")]
@@ -404,12 +790,48 @@ impl PostgresStorage {
             .await
             .map_err(|e| Error::config(format!("Failed to connect to PostgreSQL: {}", e)))?;
 
-        let storage = Self { pool };
+        let mut storage = Self { pool, pgvector: false };
         storage.run_migrations().await?;
+        storage.pgvector = storage.create_embedding_table().await;
 
         Ok(storage)
     }
 
+    /// Whether the pgvector extension is available for semantic search
+    pub fn has_pgvector(&self) -> bool {
+        self.pgvector
+    }
+
+    /// Create the pgvector-backed embeddings table, returning whether it is available
+    async fn create_embedding_table(&self) -> bool {
+        let statements = [
+            "CREATE EXTENSION IF NOT EXISTS vector",
+            r#"
+            CREATE TABLE IF NOT EXISTS message_embeddings (
+                message_id UUID PRIMARY KEY REFERENCES messages(id) ON DELETE CASCADE,
+                agent_id TEXT NOT NULL,
+                embedding vector NOT NULL
+            )
+            "#,
+            "CREATE INDEX IF NOT EXISTS idx_message_embeddings_agent ON message_embeddings(agent_id)",
+        ];
+        for statement in statements {
+            if let Err(e) = sqlx::query(statement).execute(&self.pool).await {
+                tracing::warn!("pgvector unavailable, semantic message search disabled: {}", e);
+                return false;
+            }
+        }
+        true
+    }
+
+    fn require_pgvector(&self) -> Result<()> {
+        if self.pgvector {
+            Ok(())
+        } else {
+            Err(Error::config("Semantic search requires the pgvector extension"))
+        }
+    }
+
     /// Run database migrations
     async fn run_migrations(&self) -> Result<()> {
         // Create memory_blocks table
@@ -467,6 +889,176 @@ impl PostgresStorage {
             .await
             .ok(); // Ignore error if GIN extension not available
 
+        // Create background run tables
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS background_runs (
+                run_id UUID PRIMARY KEY,
+                agent_name TEXT NOT NULL,
+                input TEXT NOT NULL,
+                status JSONB NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL,
+                started_at TIMESTAMPTZ,
+                completed_at TIMESTAMPTZ,
+                total_events BIGINT NOT NULL,
+                last_seq_id BIGINT NOT NULL,
+                metadata JSONB NOT NULL DEFAULT '{}'
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to create background_runs table: {}", e)))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS run_events (
+                run_id UUID NOT NULL,
+                seq_id BIGINT NOT NULL,
+                timestamp TIMESTAMPTZ NOT NULL,
+                event_type JSONB NOT NULL,
+                data JSONB NOT NULL,
+                PRIMARY KEY (run_id, seq_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to create run_events table: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "storage")]
+#[async_trait]
+impl RunStorage for PostgresStorage {
+    async fn save_run(&self, metadata: &RunMetadata) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO background_runs
+            (run_id, agent_name, input, status, created_at, started_at, completed_at, total_events, last_seq_id, metadata)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (run_id) DO UPDATE SET
+                status = EXCLUDED.status,
+                started_at = EXCLUDED.started_at,
+                completed_at = EXCLUDED.completed_at,
+                total_events = EXCLUDED.total_events,
+                last_seq_id = EXCLUDED.last_seq_id,
+                metadata = EXCLUDED.metadata
+            "#,
+        )
+        .bind(metadata.run_id.as_uuid())
+        .bind(&metadata.agent_name)
+        .bind(&metadata.input)
+        .bind(serde_json::to_value(&metadata.status)?)
+        .bind(metadata.created_at)
+        .bind(metadata.started_at)
+        .bind(metadata.completed_at)
+        .bind(metadata.total_events as i64)
+        .bind(metadata.last_seq_id.value() as i64)
+        .bind(serde_json::to_value(&metadata.metadata)?)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to save run: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn save_run_event(&self, run_id: RunId, event: &RunEvent) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO run_events (run_id, seq_id, timestamp, event_type, data)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (run_id, seq_id) DO NOTHING
+            "#,
+        )
+        .bind(run_id.as_uuid())
+        .bind(event.seq_id.value() as i64)
+        .bind(event.timestamp)
+        .bind(serde_json::to_value(&event.event_type)?)
+        .bind(&event.data)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to save run event: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn load_runs(&self) -> Result<Vec<RunMetadata>> {
+        #[allow(clippy::type_complexity)]
+        let rows = sqlx::query_as::<_, (uuid::Uuid, String, String, serde_json::Value, DateTime<Utc>, Option<DateTime<Utc>>, Option<DateTime<Utc>>, i64, i64, serde_json::Value)>(
+            r#"
+            SELECT run_id, agent_name, input, status, created_at, started_at, completed_at,
+                   total_events, last_seq_id, metadata
+            FROM background_runs
+            ORDER BY created_at
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to load runs: {}", e)))?;
+
+        let mut runs = Vec::new();
+        for (run_id, agent_name, input, status, created_at, started_at, completed_at, total_events, last_seq_id, metadata) in rows {
+            runs.push(RunMetadata {
+                run_id: RunId::from(run_id),
+                agent_name,
+                input,
+                status: serde_json::from_value(status)?,
+                created_at,
+                started_at,
+                completed_at,
+                total_events: total_events as usize,
+                last_seq_id: SeqId::new(last_seq_id as u64),
+                metadata: serde_json::from_value(metadata)?,
+            });
+        }
+
+        Ok(runs)
+    }
+
+    async fn load_run_events(&self, run_id: RunId) -> Result<Vec<RunEvent>> {
+        let rows = sqlx::query_as::<_, (i64, DateTime<Utc>, serde_json::Value, serde_json::Value)>(
+            r#"
+            SELECT seq_id, timestamp, event_type, data
+            FROM run_events WHERE run_id = $1
+            ORDER BY seq_id
+            "#,
+        )
+        .bind(run_id.as_uuid())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to load run events: {}", e)))?;
+
+        let mut events = Vec::new();
+        for (seq_id, timestamp, event_type, data) in rows {
+            events.push(RunEvent {
+                seq_id: SeqId::new(seq_id as u64),
+                timestamp,
+                event_type: serde_json::from_value(event_type)?,
+                data,
+            });
+        }
+
+        Ok(events)
+    }
+
+    async fn delete_run(&self, run_id: RunId) -> Result<()> {
+        let run_id = run_id.as_uuid();
+
+        sqlx::query("DELETE FROM run_events WHERE run_id = $1")
+            .bind(run_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::config(format!("Failed to delete run events: {}", e)))?;
+
+        sqlx::query("DELETE FROM background_runs WHERE run_id = $1")
+            .bind(run_id)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::config(format!("Failed to delete run: {}", e)))?;
+
         Ok(())
     }
 }
@@ -604,7 +1196,7 @@ impl MemoryStorage for PostgresStorage {
         .bind(message.id)
         .bind(agent_id.to_string())
         .bind(message.timestamp)
-        .bind(&message.role)
+        .bind(message.role.as_str())
         .bind(&message.content)
         .bind(tool_calls_json)
         .bind(serde_json::to_value(&message.metadata).unwrap())
@@ -635,7 +1227,7 @@ impl MemoryStorage for PostgresStorage {
             messages.push(MessageEntry {
                 id,
                 timestamp,
-                role,
+                role: Role::from(role),
                 content,
                 tool_calls: tool_calls_json
                     .map(|json| serde_json::from_value(json))
@@ -669,7 +1261,7 @@ impl MemoryStorage for PostgresStorage {
             messages.push(MessageEntry {
                 id,
                 timestamp,
-                role,
+                role: Role::from(role),
                 content,
                 tool_calls: tool_calls_json
                     .map(|json| serde_json::from_value(json))
@@ -700,6 +1292,83 @@ impl MemoryStorage for PostgresStorage {
     }
 }
 
+/// Nearest-neighbour search with pgvector's cosine distance operator
+#[cfg(feature = "storage")]
+#[async_trait]
+impl SemanticMemoryStorage for PostgresStorage {
+    async fn save_embedding(&self, agent_id: AgentId, message_id: uuid::Uuid, embedding: &[f32]) -> Result<()> {
+        self.require_pgvector()?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO message_embeddings (message_id, agent_id, embedding)
+            VALUES ($1, $2, $3::vector)
+            ON CONFLICT (message_id) DO UPDATE SET embedding = EXCLUDED.embedding
+            "#,
+        )
+        .bind(message_id)
+        .bind(agent_id.to_string())
+        .bind(vector_literal(embedding))
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to save embedding: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn search_similar(
+        &self,
+        agent_id: AgentId,
+        query_embedding: &[f32],
+        k: usize,
+    ) -> Result<Vec<(MessageEntry, f32)>> {
+        self.require_pgvector()?;
+
+        let rows = sqlx::query_as::<_, (uuid::Uuid, DateTime<Utc>, String, String, Option<serde_json::Value>, serde_json::Value, f64)>(
+            r#"
+            SELECT m.id, m.timestamp, m.role, m.content, m.tool_calls, m.metadata,
+                   1 - (e.embedding <=> $2::vector) AS score
+            FROM message_embeddings e JOIN messages m ON m.id = e.message_id
+            WHERE e.agent_id = $1
+            ORDER BY e.embedding <=> $2::vector
+            LIMIT $3
+            "#,
+        )
+        .bind(agent_id.to_string())
+        .bind(vector_literal(query_embedding))
+        .bind(k as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to search embeddings: {}", e)))?;
+
+        let mut scored = Vec::new();
+        for (id, timestamp, role, content, tool_calls_json, metadata, score) in rows {
+            let message = MessageEntry {
+                id,
+                timestamp,
+                role: Role::from(role),
+                content,
+                tool_calls: tool_calls_json
+                    .map(serde_json::from_value)
+                    .transpose()
+                    .map_err(|e| Error::config(format!("Invalid tool_calls: {}", e)))?,
+                metadata: serde_json::from_value(metadata)
+                    .map_err(|e| Error::config(format!("Invalid metadata: {}", e)))?,
+            };
+            scored.push((message, score as f32));
+        }
+
+        Ok(scored)
+    }
+}
+
+/// pgvector text form of an embedding, e.g. `[0.1,0.2]`
+#[cfg(feature = "storage")]
+fn vector_literal(embedding: &[f32]) -> String {
+    let values: Vec<String> = embedding.iter().map(|x| x.to_string()).collect();
+    format!("[{}]", values.join(","))
+}
+
 #[cfg(test)]
 #[cfg(feature = "storage")]
 mod tests {
@@ -730,4 +1399,146 @@ mod tests {
         assert_eq!(loaded.label, "test");
         assert_eq!(loaded.value, "test value");
     }
+
+    #[tokio::test]
+    async fn test_sqlite_search_ranking() {
+        let storage = SqliteStorage::new("sqlite::memory:")
+            .await
+            .expect("Failed to create SQLite storage");
+        assert!(storage.has_full_text_search());
+
+        let agent_id = AgentId::new();
+        let message = |content: &str| MessageEntry {
+            id: uuid::Uuid::new_v4(),
+            timestamp: Utc::now(),
+            role: Role::User,
+            content: content.to_string(),
+            tool_calls: None,
+            metadata: Default::default(),
+        };
+
+        let passing = message("the deploy went fine and we moved on to reviewing the quarterly roadmap");
+        let focused = message("deploy failed: deploy script could not reach the deploy host");
+        let unrelated = message("lunch plans for friday");
+        let mut edited = message("nothing yet");
+        for entry in [&passing, &focused, &unrelated, &edited] {
+            storage.save_message(agent_id, entry).await.unwrap();
+        }
+        storage.save_message(AgentId::new(), &message("deploy deploy deploy")).await.unwrap();
+
+        // Upserting a message reindexes its new content
+        edited.content = "rolled back the deploy".to_string();
+        storage.save_message(agent_id, &edited).await.unwrap();
+
+        let results = storage.search_messages(agent_id, "deploy").await.unwrap();
+        let ids: Vec<uuid::Uuid> = results.iter().map(|m| m.id).collect();
+        assert_eq!(ids, vec![focused.id, edited.id, passing.id]);
+
+        assert!(storage.search_messages(agent_id, "yet").await.unwrap().is_empty());
+        assert_eq!(storage.search_messages(agent_id, "\"reach the\"").await.unwrap().len(), 1);
+        // Invalid FTS syntax falls back to a substring match
+        assert_eq!(storage.search_messages(agent_id, "failed:").await.unwrap().len(), 1);
+    }
+
+    /// Embeds text as counts of a few fixed keywords
+    struct KeywordEmbedder;
+
+    #[async_trait]
+    impl Embedder for KeywordEmbedder {
+        async fn embed(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    ["cat", "dog", "car"]
+                        .iter()
+                        .map(|word| text.matches(word).count() as f32)
+                        .collect()
+                })
+                .collect())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_semantic_search() {
+        let storage = SqliteStorage::new("sqlite::memory:")
+            .await
+            .expect("Failed to create SQLite storage");
+
+        let agent_id = AgentId::new();
+        let message = |content: &str| MessageEntry {
+            id: uuid::Uuid::new_v4(),
+            timestamp: Utc::now(),
+            role: Role::User,
+            content: content.to_string(),
+            tool_calls: None,
+            metadata: Default::default(),
+        };
+
+        let pets = message("the cat chased the dog");
+        let cats = message("cat cat cat");
+        let cars = message("a car in the garage");
+        for entry in [&pets, &cats, &cars] {
+            storage.save_message_embedded(agent_id, entry, &KeywordEmbedder).await.unwrap();
+        }
+        storage
+            .save_message_embedded(AgentId::new(), &message("cat"), &KeywordEmbedder)
+            .await
+            .unwrap();
+
+        let results = storage
+            .search_similar_text(agent_id, "my cat", &KeywordEmbedder, 2)
+            .await
+            .unwrap();
+        let ids: Vec<uuid::Uuid> = results.iter().map(|(m, _)| m.id).collect();
+        assert_eq!(ids, vec![cats.id, pets.id]);
+        assert!((results[0].1 - 1.0).abs() < 1e-6);
+
+        storage.delete_agent_data(agent_id).await.unwrap();
+        assert!(storage.search_similar(agent_id, &[1.0, 0.0, 0.0], 5).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_run_storage() {
+        use crate::background::{RunEventType, RunStatus};
+
+        let storage = SqliteStorage::new("sqlite::memory:")
+            .await
+            .expect("Failed to create SQLite storage");
+
+        let run_id = RunId::new();
+        let mut metadata = RunMetadata {
+            run_id,
+            agent_name: "worker".to_string(),
+            input: "scan".to_string(),
+            status: RunStatus::Running,
+            created_at: Utc::now(),
+            started_at: Some(Utc::now()),
+            completed_at: None,
+            total_events: 1,
+            last_seq_id: SeqId::new(1),
+            metadata: Default::default(),
+        };
+        let event = RunEvent {
+            seq_id: SeqId::new(0),
+            timestamp: Utc::now(),
+            event_type: RunEventType::Started,
+            data: serde_json::json!({ "agent": "worker" }),
+        };
+        storage.save_run(&metadata).await.unwrap();
+        storage.save_run_event(run_id, &event).await.unwrap();
+
+        metadata.status = RunStatus::Failed { error: "boom".to_string() };
+        storage.save_run(&metadata).await.unwrap();
+
+        let runs = storage.load_runs().await.unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].run_id, run_id);
+        assert_eq!(runs[0].status, metadata.status);
+        let events = storage.load_run_events(run_id).await.unwrap();
+        assert_eq!(events[0].event_type, RunEventType::Started);
+        assert_eq!(events[0].data["agent"], "worker");
+
+        storage.delete_run(run_id).await.unwrap();
+        assert!(storage.load_runs().await.unwrap().is_empty());
+    }
 }
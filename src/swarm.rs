@@ -0,0 +1,518 @@
+//! Security swarm workflow
+//!
+//! A data collector agent runs security tools discovered from a tools
+//! directory, four specialist agents analyze the collected data, and a
+//! coordinator synthesizes the findings. Filesystem output and command
+//! execution are controlled through [`SwarmOptions`] so the flow can be
+//! embedded in other applications or exercised in tests.
+
+use crate::agent::Agent;
+use crate::error::Result;
+use crate::guardrails::{ClaimKind, GroundingGuardrail};
+use crate::handoffs::{HandoffContext, LlmSummarizer, ObservationSummarizer, TruncatingSummarizer};
+use crate::llm_client::LlmClient;
+use crate::react::Observation;
+use crate::security_tools::{SecurityToolRegistry, TaggedSecurityTools};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Options for [`run_security_swarm`]
+#[derive(Debug, Clone)]
+pub struct SwarmOptions {
+    /// Directory to write per-phase reports to (None = don't write files)
+    pub output_dir: Option<PathBuf>,
+    /// Log tool commands and file writes instead of performing them
+    pub dry_run: bool,
+    /// Run tools marked `requires_sudo` through sudo
+    pub use_sudo: bool,
+    /// Directory to discover security tools from
+    pub tools_dir: PathBuf,
+    /// Tags selecting which tools the collector may run
+    pub tool_tags: Vec<String>,
+    /// Model used by every agent in the swarm
+    pub model: String,
+    /// Observations kept in the shared handoff context before older ones are summarized
+    pub handoff_compaction_threshold: usize,
+    /// Cheap model used to summarize handoff observations (None = truncate without a model call)
+    pub summary_model: Option<String>,
+}
+
+impl Default for SwarmOptions {
+    fn default() -> Self {
+        Self {
+            output_dir: None,
+            dry_run: false,
+            use_sudo: true,
+            tools_dir: PathBuf::from("tools"),
+            tool_tags: vec!["security_tools".to_string()],
+            model: "anthropic/claude-sonnet-4".to_string(),
+            handoff_compaction_threshold: 3,
+            summary_model: None,
+        }
+    }
+}
+
+impl SwarmOptions {
+    /// Write reports to the given directory
+    pub fn with_output_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.output_dir = Some(dir.into());
+        self
+    }
+
+    /// Enable or disable dry-run mode
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Enable or disable sudo for privileged tools
+    pub fn with_sudo(mut self, use_sudo: bool) -> Self {
+        self.use_sudo = use_sudo;
+        self
+    }
+
+    /// Set the tools directory
+    pub fn with_tools_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.tools_dir = dir.into();
+        self
+    }
+
+    /// Set the tool tags
+    pub fn with_tool_tags(mut self, tags: Vec<String>) -> Self {
+        self.tool_tags = tags;
+        self
+    }
+
+    /// Set the model
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = model.into();
+        self
+    }
+
+    /// Set the handoff context compaction threshold
+    pub fn with_handoff_compaction_threshold(mut self, threshold: usize) -> Self {
+        self.handoff_compaction_threshold = threshold;
+        self
+    }
+
+    /// Summarize handoff observations with the given model
+    pub fn with_summary_model(mut self, model: impl Into<String>) -> Self {
+        self.summary_model = Some(model.into());
+        self
+    }
+}
+
+/// Security findings collected and analyzed by the swarm
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SecurityFindings {
+    /// Raw output gathered by the data collector
+    pub collected_data: String,
+    /// Network monitor analysis
+    pub network_analysis: String,
+    /// Process analyzer analysis
+    pub process_analysis: String,
+    /// Rootkit hunter analysis
+    pub rootkit_analysis: String,
+    /// Hardening auditor analysis
+    pub hardening_analysis: String,
+    /// Coordinator's final assessment
+    pub final_assessment: String,
+}
+
+impl SecurityFindings {
+    /// Render a plain-text summary report
+    pub fn summary(&self, tools_dir: &Path) -> String {
+        format!(
+            "═══════════════════════════════════════════════════════════════\n\
+             SPAI SWARM SECURITY ASSESSMENT SUMMARY\n\
+             Generated: {}\n\
+             Tools Directory: {:?}\n\
+             ═══════════════════════════════════════════════════════════════\n\n\
+             {}\n\n\
+             ═══════════════════════════════════════════════════════════════\n\
+             AGENT ANALYSES\n\
+             ═══════════════════════════════════════════════════════════════\n\n\
+             --- Network Agent ---\n{}\n\n\
+             --- Process Agent ---\n{}\n\n\
+             --- Rootkit Agent ---\n{}\n\n\
+             --- Hardening Agent ---\n{}\n\n\
+             ═══════════════════════════════════════════════════════════════\n\
+             QUICK VERIFICATION COMMANDS\n\
+             ═══════════════════════════════════════════════════════════════\n\n\
+             # Check listening ports\n\
+             ss -tulnp | grep LISTEN\n\n\
+             # Check high CPU processes\n\
+             ps aux --sort=-%cpu | head -10\n\n\
+             # Check for suspicious network connections\n\
+             lsof -i -n -P | grep ESTABLISHED\n\n\
+             # Run quick rootkit check\n\
+             sudo chkrootkit | grep -i infected\n",
+            Utc::now().format("%Y-%m-%d %H:%M:%S UTC"),
+            tools_dir,
+            self.final_assessment,
+            truncate_str(&self.network_analysis, 1000),
+            truncate_str(&self.process_analysis, 1000),
+            truncate_str(&self.rootkit_analysis, 1000),
+            truncate_str(&self.hardening_analysis, 1000)
+        )
+    }
+}
+
+/// Writes reports according to the swarm options
+struct ReportWriter<'a> {
+    options: &'a SwarmOptions,
+}
+
+impl ReportWriter<'_> {
+    fn write(&self, file_name: &str, content: &str) -> Result<()> {
+        let Some(dir) = &self.options.output_dir else {
+            return Ok(());
+        };
+        let path = dir.join(file_name);
+
+        if self.options.dry_run {
+            tracing::info!("[dry-run] would write {} bytes to {}", content.len(), path.display());
+            return Ok(());
+        }
+
+        std::fs::create_dir_all(dir)?;
+        std::fs::write(&path, content)?;
+        tracing::info!("Saved {}", path.display());
+        Ok(())
+    }
+}
+
+/// Run the security swarm end to end and return its findings
+///
+/// Individual agent failures are recorded in the corresponding findings
+/// field rather than aborting the run.
+pub async fn run_security_swarm(
+    client: Arc<dyn LlmClient>,
+    options: SwarmOptions,
+) -> Result<SecurityFindings> {
+    let writer = ReportWriter { options: &options };
+
+    let registry = Arc::new(
+        SecurityToolRegistry::discover(&options.tools_dir)
+            .with_dry_run(options.dry_run)
+            .with_sudo(options.use_sudo),
+    );
+    let tags: Vec<&str> = options.tool_tags.iter().map(String::as_str).collect();
+    let security_tools = TaggedSecurityTools::new(registry.clone(), &tags).create_tools();
+    tracing::info!("Discovered {} security tools", registry.len());
+
+    let model = options.model.as_str();
+    let mut findings = SecurityFindings::default();
+    let mut handoff_context = HandoffContext::new("Comprehensive security assessment")
+        .with_compaction_threshold(options.handoff_compaction_threshold);
+    let summarizer: Box<dyn ObservationSummarizer> = match &options.summary_model {
+        Some(summary_model) => Box::new(LlmSummarizer::new(client.clone(), summary_model)),
+        None => Box::new(TruncatingSummarizer::default()),
+    };
+
+    // Phase 1: agent-driven data collection
+    let collector_agent = Agent::builder()
+        .name("Security Data Collector")
+        .model(model)
+        .system_prompt(
+            "You are a security data collection agent. Your job is to gather comprehensive \
+             security data from the system using the available tools.\n\n\
+             WORKFLOW:\n\
+             1. First, call list_security_tools to see what tools are available\n\
+             2. Run tools from each category to collect comprehensive data:\n\
+                - Network: Check listening ports, established connections\n\
+                - Process: Identify running processes, resource usage\n\
+                - Rootkit: Scan for rootkits and suspicious modifications\n\
+                - Hardening: Audit system security configuration\n\n\
+             For each tool, use appropriate arguments. For example:\n\
+             - portlist: use args [\"-a\", \"-s\"] for all connections with suspicious highlighting\n\
+             - chkrootkit: no special args needed\n\n\
+             Run at least one tool from each category. Collect all the output.\n\
+             When done, provide a summary of what data you collected.",
+        )
+        .tools(security_tools)
+        .max_loops(10)
+        .temperature(0.1)
+        .client(client.clone())
+        .build()?;
+
+    let collection_prompt = "Perform a comprehensive security data collection. \
+         First list available tools, then run tools from network, process, rootkit, \
+         and hardening categories. Collect their output for analysis.";
+
+    match collector_agent.react_loop(collection_prompt).await {
+        Ok(output) => {
+            findings.collected_data = output.content.clone();
+            handoff_context = handoff_context.with_observation(Observation::new(format!(
+                "[collector] Collected security data from {} tool executions",
+                output.trace.observations.len()
+            )));
+            writer.write("01_collected_data.txt", &findings.collected_data)?;
+        }
+        Err(e) => {
+            tracing::warn!("Data collection failed: {}", e);
+            findings.collected_data = format!("Collection failed: {}", e);
+        }
+    }
+
+    // Phase 2: specialists analyze the collected data
+    let network_agent = Agent::builder()
+        .name("Network Monitor")
+        .model(model)
+        .system_prompt(
+            "You are a network security analyst. You will receive security data collected \
+             from the system.\n\n\
+             CRITICAL: Analyze ONLY the data provided. Do NOT invent any findings.\n\n\
+             Look for:\n\
+             - Suspicious listening ports (4444, 31337, 6667, 1337, etc.)\n\
+             - Unusual established connections to unknown IPs\n\
+             - Processes with unexpected network activity\n\
+             - Any ports flagged as SUSPICIOUS\n\n\
+             Provide a brief analysis and list any suspicious PIDs.\n\
+             End with: SUSPICIOUS_PIDS: [list] or SUSPICIOUS_PIDS: NONE",
+        )
+        // Reject any PID that does not appear in the collected data
+        .output_guardrail(Arc::new(
+            GroundingGuardrail::new(&findings.collected_data).with_kinds(vec![ClaimKind::Pid]),
+        ))
+        .temperature(0.1)
+        .client(client.clone())
+        .build()?;
+
+    let network_prompt = format!(
+        "Analyze the NETWORK-related data from this security collection:\n\n{}\n\n\
+         Focus on port scans, network connections, and related findings.",
+        truncate_str(&findings.collected_data, 8000)
+    );
+    findings.network_analysis = analyze(
+        &network_agent,
+        &network_prompt,
+        "network",
+        &mut handoff_context,
+        summarizer.as_ref(),
+    )
+    .await;
+    writer.write("02_network_analysis.txt", &findings.network_analysis)?;
+
+    let process_agent = Agent::builder()
+        .name("Process Analyzer")
+        .model(model)
+        .system_prompt(
+            "You are a process analyst. Analyze ONLY the data provided.\n\n\
+             Look for:\n\
+             - Suspicious process names or paths\n\
+             - Processes running from /tmp or /dev/shm\n\
+             - Abnormally high CPU or memory usage\n\
+             - Unusual parent-child relationships\n\n\
+             End with: SEVERITY: [CLEAN|LOW|MEDIUM|HIGH|CRITICAL] - [reason]",
+        )
+        .output_guardrail(Arc::new(
+            GroundingGuardrail::new(&findings.collected_data).with_kinds(vec![ClaimKind::Pid]),
+        ))
+        .temperature(0.1)
+        .client(client.clone())
+        .build()?;
+
+    let process_prompt = format!(
+        "Analyze the PROCESS-related data from this security collection:\n\n{}\n\n\
+         Previous network analysis found:\n{}",
+        truncate_str(&findings.collected_data, 6000),
+        truncate_str(&findings.network_analysis, 1000)
+    );
+    findings.process_analysis = analyze(
+        &process_agent,
+        &process_prompt,
+        "process",
+        &mut handoff_context,
+        summarizer.as_ref(),
+    )
+    .await;
+    writer.write("03_process_analysis.txt", &findings.process_analysis)?;
+
+    let rootkit_agent = Agent::builder()
+        .name("Rootkit Hunter")
+        .model(model)
+        .system_prompt(
+            "You are a rootkit analyst. Analyze ONLY the data provided.\n\n\
+             Look for:\n\
+             - Any 'INFECTED' or 'WARNING' messages\n\
+             - Hidden files or processes detected\n\
+             - Modified system binaries\n\
+             - Suspicious kernel modules\n\n\
+             End with: ROOTKIT_STATUS: [CLEAN|WARNING|INFECTED] - [reason]",
+        )
+        .temperature(0.1)
+        .client(client.clone())
+        .build()?;
+
+    let rootkit_prompt = format!(
+        "Analyze the ROOTKIT scan data from this security collection:\n\n{}",
+        truncate_str(&findings.collected_data, 8000)
+    );
+    findings.rootkit_analysis = analyze(
+        &rootkit_agent,
+        &rootkit_prompt,
+        "rootkit",
+        &mut handoff_context,
+        summarizer.as_ref(),
+    )
+    .await;
+    writer.write("04_rootkit_analysis.txt", &findings.rootkit_analysis)?;
+
+    let hardening_agent = Agent::builder()
+        .name("Hardening Auditor")
+        .model(model)
+        .system_prompt(
+            "You are a system hardening auditor. Analyze ONLY the data provided.\n\n\
+             Extract:\n\
+             - The actual hardening index score from any lynis output\n\
+             - Top 5 security warnings or suggestions\n\n\
+             End with: HARDENING_SCORE: [score from output] - [top recommendation]",
+        )
+        .temperature(0.1)
+        .client(client.clone())
+        .build()?;
+
+    let hardening_prompt = format!(
+        "Analyze the HARDENING/LYNIS data from this security collection:\n\n{}",
+        truncate_str(&findings.collected_data, 8000)
+    );
+    findings.hardening_analysis = analyze(
+        &hardening_agent,
+        &hardening_prompt,
+        "hardening",
+        &mut handoff_context,
+        summarizer.as_ref(),
+    )
+    .await;
+    writer.write("05_hardening_analysis.txt", &findings.hardening_analysis)?;
+
+    // Phase 3: coordinator synthesis
+    let coordinator_agent = Agent::builder()
+        .name("Security Coordinator")
+        .model(model)
+        .system_prompt(
+            "You are the security coordinator. Synthesize the analyses from all agents.\n\n\
+             CRITICAL: Base your assessment ONLY on the agent analyses provided.\n\n\
+             Provide:\n\
+             1. EXECUTIVE SUMMARY (2-3 sentences)\n\
+             2. SECURITY POSTURE: [SECURE|WARNING|COMPROMISED]\n\
+             3. HIGH SEVERITY FINDINGS (if any, with specific PIDs/IPs/ports)\n\
+             4. VERIFICATION COMMANDS - bash commands to verify the highest severity findings\n\n\
+             Format verification commands as:\n\
+             ```bash\n\
+             # Description of what this verifies\n\
+             command here\n\
+             ```",
+        )
+        .temperature(0.1)
+        .client(client)
+        .build()?;
+
+    let coordinator_prompt = format!(
+        "Synthesize these security findings from our agent swarm:\n\n\
+         === NETWORK ANALYSIS ===\n{}\n\n\
+         === PROCESS ANALYSIS ===\n{}\n\n\
+         === ROOTKIT ANALYSIS ===\n{}\n\n\
+         === HARDENING ANALYSIS ===\n{}\n\n\
+         Provide executive summary, security posture, and verification bash commands.",
+        truncate_str(&findings.network_analysis, 1500),
+        truncate_str(&findings.process_analysis, 1500),
+        truncate_str(&findings.rootkit_analysis, 1500),
+        truncate_str(&findings.hardening_analysis, 1500)
+    );
+
+    findings.final_assessment = match coordinator_agent.react_loop(&coordinator_prompt).await {
+        Ok(output) => output.content,
+        Err(e) => format!("Coordinator failed: {}", e),
+    };
+
+    writer.write("summary.txt", &findings.summary(&options.tools_dir))?;
+
+    Ok(findings)
+}
+
+/// Run one specialist and record its result in the shared handoff context
+async fn analyze(
+    agent: &Agent,
+    prompt: &str,
+    label: &str,
+    handoff_context: &mut HandoffContext,
+    summarizer: &dyn ObservationSummarizer,
+) -> String {
+    match agent.react_loop(prompt).await {
+        Ok(output) => {
+            let observation = Observation::new(format!(
+                "[{}] {}",
+                label,
+                truncate_str(&output.content, 500)
+            ));
+            if let Err(e) = handoff_context.record(observation, summarizer).await {
+                tracing::warn!("Failed to record {} findings in handoff context: {}", label, e);
+            }
+            output.content
+        }
+        Err(e) => {
+            tracing::warn!("{} analysis failed: {}", label, e);
+            format!("Analysis failed: {}", e)
+        }
+    }
+}
+
+/// Truncate to at most `max_len` bytes without splitting a character
+fn truncate_str(s: &str, max_len: usize) -> &str {
+    if s.len() <= max_len {
+        return s;
+    }
+    let mut end = max_len;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockLlmClient;
+
+    fn fixed() -> Arc<MockLlmClient> {
+        Arc::new(MockLlmClient::new().with_fallback_reply("Final answer: nothing suspicious"))
+    }
+
+    #[tokio::test]
+    async fn test_dry_run_writes_nothing() {
+        let dir = tempfile::tempdir().unwrap();
+        let output_dir = dir.path().join("reports");
+        let options = SwarmOptions::default()
+            .with_tools_dir(dir.path().join("no-tools"))
+            .with_output_dir(&output_dir)
+            .with_dry_run(true)
+            .with_sudo(false);
+
+        let findings = run_security_swarm(fixed(), options).await.unwrap();
+        assert_eq!(findings.network_analysis, "nothing suspicious");
+        assert_eq!(findings.final_assessment, "nothing suspicious");
+        assert!(!output_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn test_writes_reports_to_output_dir() {
+        let dir = tempfile::tempdir().unwrap();
+        let options = SwarmOptions::default()
+            .with_tools_dir(dir.path().join("no-tools"))
+            .with_output_dir(dir.path());
+
+        run_security_swarm(fixed(), options).await.unwrap();
+        assert!(dir.path().join("02_network_analysis.txt").exists());
+        assert!(dir.path().join("summary.txt").exists());
+    }
+
+    #[test]
+    fn test_truncate_respects_char_boundaries() {
+        assert_eq!(truncate_str("héllo", 2), "h");
+        assert_eq!(truncate_str("abc", 10), "abc");
+    }
+}
@@ -0,0 +1,427 @@
+//! Test doubles for exercising agents without a model server
+//!
+//! Enable the `testing` feature in `dev-dependencies` to use
+//! [`MockLlmClient`] in your own tests:
+//!
+//! ```rust,ignore
+//! let client = Arc::new(
+//!     MockLlmClient::new()
+//!         .with_tool_call("lookup", json!({"host": "10.0.0.5"}))
+//!         .with_reply("Final answer: 10.0.0.5 runs nginx"),
+//! );
+//! let agent = Agent::builder().tool(Arc::new(LookupTool)).client(client.clone()).build()?;
+//!
+//! let output = agent.react_loop("What runs on 10.0.0.5?").await?;
+//! assert_eq!(output.content, "10.0.0.5 runs nginx");
+//! assert_eq!(client.request_count(), 2);
+//! ```
+
+use crate::error::{Error, Result};
+use crate::llm_client::LlmClient;
+use crate::openrouter::{
+    Choice, CompletionRequest, CompletionResponse, CompletionStream, Delta, FunctionCall, Message, RateLimitInfo,
+    StreamChoice, StreamChunk, ToolCall, Usage,
+};
+use async_trait::async_trait;
+use bytes::Bytes;
+use parking_lot::Mutex;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// One scripted step of a [`MockLlmClient`]
+#[derive(Debug, Clone)]
+pub enum MockResponse {
+    /// Answer with this assistant text
+    Reply(String),
+    /// Call a tool, in whichever tool protocol the request uses
+    ToolCall {
+        /// Tool ID
+        name: String,
+        /// Tool arguments
+        arguments: Value,
+    },
+    /// Fail the request with a provider error carrying this message
+    Error(String),
+    /// Return this response verbatim
+    Response(CompletionResponse),
+    /// Never answer, for exercising timeouts and cancellation
+    Stall,
+}
+
+/// Deterministic [`LlmClient`] that replays scripted responses in order
+///
+/// Every request is recorded for later assertions. Once the script runs
+/// out, requests fail unless a [fallback reply](Self::with_fallback_reply)
+/// is set. Streaming requests replay the same script, as one chunk unless
+/// [`with_stream_deltas`](Self::with_stream_deltas) splits it.
+#[derive(Debug, Default)]
+pub struct MockLlmClient {
+    script: Mutex<VecDeque<MockResponse>>,
+    fallback: Option<MockResponse>,
+    requests: Mutex<Vec<CompletionRequest>>,
+    latency: Option<Duration>,
+    usage: Usage,
+    rate_limit: Option<RateLimitInfo>,
+    model_ids: Option<Vec<String>>,
+    stream_deltas: usize,
+    no_streaming: bool,
+    in_flight: AtomicUsize,
+    peak_in_flight: AtomicUsize,
+}
+
+/// Counts a request as in flight until dropped
+struct InFlight<'a>(&'a AtomicUsize);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl MockLlmClient {
+    /// Create a client with an empty script
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue an arbitrary scripted step
+    pub fn with_response(self, response: MockResponse) -> Self {
+        self.script.lock().push_back(response);
+        self
+    }
+
+    /// Queue a plain text reply
+    pub fn with_reply(self, content: impl Into<String>) -> Self {
+        self.with_response(MockResponse::Reply(content.into()))
+    }
+
+    /// Queue a call to the tool `name` with `arguments`
+    pub fn with_tool_call(self, name: impl Into<String>, arguments: Value) -> Self {
+        self.with_response(MockResponse::ToolCall {
+            name: name.into(),
+            arguments,
+        })
+    }
+
+    /// Queue a failed request
+    pub fn with_error(self, message: impl Into<String>) -> Self {
+        self.with_response(MockResponse::Error(message.into()))
+    }
+
+    /// Reply with `content` whenever the script is exhausted
+    pub fn with_fallback_reply(mut self, content: impl Into<String>) -> Self {
+        self.fallback = Some(MockResponse::Reply(content.into()));
+        self
+    }
+
+    /// Wait this long before answering each request
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Report this token usage on every response
+    pub fn with_usage(mut self, prompt_tokens: u64, completion_tokens: u64) -> Self {
+        self.usage = Usage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+        };
+        self
+    }
+
+    /// Report this rate-limit state on every response
+    pub fn with_rate_limit(mut self, rate_limit: RateLimitInfo) -> Self {
+        self.rate_limit = Some(rate_limit);
+        self
+    }
+
+    /// Answer `model_ids` with this list instead of the default error
+    pub fn with_model_ids<S: Into<String>>(mut self, ids: impl IntoIterator<Item = S>) -> Self {
+        self.model_ids = Some(ids.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Stream each response's text as `deltas` chunks of similar length
+    pub fn with_stream_deltas(mut self, deltas: usize) -> Self {
+        self.stream_deltas = deltas;
+        self
+    }
+
+    /// Fail every streaming request, so callers fall back to `complete`
+    pub fn without_streaming(mut self) -> Self {
+        self.no_streaming = true;
+        self
+    }
+
+    /// Every request received so far, oldest first
+    pub fn requests(&self) -> Vec<CompletionRequest> {
+        self.requests.lock().clone()
+    }
+
+    /// The most recent request, if any
+    pub fn last_request(&self) -> Option<CompletionRequest> {
+        self.requests.lock().last().cloned()
+    }
+
+    /// Content of the last message of every request, oldest first
+    pub fn prompts(&self) -> Vec<String> {
+        self.requests
+            .lock()
+            .iter()
+            .map(|request| request.messages.last().map(|m| m.content.clone()).unwrap_or_default())
+            .collect()
+    }
+
+    /// Most requests that were in flight at the same time
+    pub fn peak_in_flight(&self) -> usize {
+        self.peak_in_flight.load(Ordering::SeqCst)
+    }
+
+    /// How many requests have been received
+    pub fn request_count(&self) -> usize {
+        self.requests.lock().len()
+    }
+
+    /// Scripted steps not yet consumed
+    pub fn remaining(&self) -> usize {
+        self.script.lock().len()
+    }
+
+    /// Record the request, wait out the latency and produce the next response
+    async fn next_response(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        let step = self.script.lock().pop_front().or_else(|| self.fallback.clone());
+        let message = match &step {
+            Some(MockResponse::ToolCall { name, arguments }) => Some(tool_call_message(&request, name, arguments)),
+            _ => None,
+        };
+        self.requests.lock().push(request);
+        let now = self.in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        let _in_flight = InFlight(&self.in_flight);
+        self.peak_in_flight.fetch_max(now, Ordering::SeqCst);
+        if let Some(latency) = self.latency {
+            tokio::time::sleep(latency).await;
+        }
+
+        let (message, finish_reason) = match step {
+            None => return Err(Error::config("MockLlmClient has no scripted responses left")),
+            Some(MockResponse::Stall) => std::future::pending().await,
+            Some(MockResponse::Error(message)) => return Err(Error::openrouter(message)),
+            Some(MockResponse::Response(response)) => return Ok(response),
+            Some(MockResponse::Reply(content)) => (Message::assistant(content), "stop"),
+            Some(MockResponse::ToolCall { .. }) => (message.unwrap_or_else(|| Message::assistant("")), "tool_calls"),
+        };
+        Ok(CompletionResponse {
+            id: "mock".to_string(),
+            model: "mock".to_string(),
+            choices: vec![Choice {
+                index: 0,
+                message,
+                finish_reason: Some(finish_reason.to_string()),
+            }],
+            usage: self.usage.clone(),
+            retries: 0,
+            rate_limit: self.rate_limit.clone(),
+        })
+    }
+}
+
+/// Express a tool call in the protocol the request was built for
+///
+/// Native tool calls when the request carries tool definitions, a
+/// `<tool_use>` block when the prompt describes that format, and
+/// `Action:` / `Action Input:` text otherwise.
+fn tool_call_message(request: &CompletionRequest, name: &str, arguments: &Value) -> Message {
+    if request.tools.is_some() {
+        let mut message = Message::assistant("");
+        message.tool_calls = Some(vec![ToolCall {
+            id: format!("call_{}", name),
+            tool_type: "function".to_string(),
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        }]);
+        return message;
+    }
+
+    let uses_xml = request
+        .messages
+        .iter()
+        .any(|m| m.role == crate::openrouter::Role::System && m.content.contains("<tool_use>"));
+    if uses_xml {
+        Message::assistant(format!(
+            "<tool_use><name>{}</name><input>{}</input></tool_use>",
+            name, arguments
+        ))
+    } else {
+        Message::assistant(format!("Action: {}\nAction Input: {}", name, arguments))
+    }
+}
+
+#[async_trait]
+impl LlmClient for MockLlmClient {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        self.next_response(request).await
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        if self.no_streaming {
+            return Err(Error::config("Streaming disabled in MockLlmClient"));
+        }
+        let response = self.next_response(request).await?;
+        let mut events = Vec::new();
+        for choice in response.choices {
+            let pieces = split_text(&choice.message.content, self.stream_deltas.max(1));
+            let last = pieces.len() - 1;
+            for (i, piece) in pieces.into_iter().enumerate() {
+                let first = i == 0;
+                let chunk = StreamChunk {
+                    id: response.id.clone(),
+                    model: response.model.clone(),
+                    choices: vec![StreamChoice {
+                        index: choice.index,
+                        delta: Delta {
+                            role: first.then(|| choice.message.role.clone()),
+                            content: Some(piece),
+                            tool_calls: if first { choice.message.tool_calls.clone() } else { None },
+                            reasoning: if first { choice.message.reasoning.clone() } else { None },
+                        },
+                        finish_reason: if i == last { choice.finish_reason.clone() } else { None },
+                    }],
+                    usage: (i == last).then(|| response.usage.clone()),
+                };
+                events.push(Ok(Bytes::from(format!("data: {}\n\n", serde_json::to_string(&chunk)?))));
+            }
+        }
+        events.push(Ok(Bytes::from("data: [DONE]\n\n")));
+        Ok(CompletionStream::new(futures::stream::iter(events)))
+    }
+
+    fn client_type(&self) -> &str {
+        "mock"
+    }
+
+    fn endpoint(&self) -> &str {
+        "mock://"
+    }
+
+    async fn model_ids(&self) -> Result<Vec<String>> {
+        self.model_ids
+            .clone()
+            .ok_or_else(|| Error::config("MockLlmClient has no model list"))
+    }
+}
+
+/// Split `text` into at most `parts` pieces of similar length, on character boundaries
+fn split_text(text: &str, parts: usize) -> Vec<String> {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.is_empty() {
+        return vec![String::new()];
+    }
+    let size = chars.len().div_ceil(parts);
+    chars.chunks(size).map(|piece| piece.iter().collect()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::Agent;
+    use crate::tool_protocol::ToolProtocol;
+    use crate::tools::EchoTool;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    fn agent(client: Arc<MockLlmClient>, protocol: ToolProtocol) -> Agent {
+        Agent::builder()
+            .name("Mocked")
+            .system_prompt("You are a test agent.")
+            .tool(Arc::new(EchoTool))
+            .tool_protocol(protocol)
+            .client(client)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_scripted_tool_call_in_every_protocol() {
+        for protocol in [ToolProtocol::ReActText, ToolProtocol::OpenAiJson, ToolProtocol::Anthropic] {
+            let client = Arc::new(
+                MockLlmClient::new()
+                    .with_tool_call("echo", json!({"message": "ping"}))
+                    .with_reply("Final answer: pong")
+                    .with_usage(10, 5),
+            );
+            let output = agent(client.clone(), protocol).react_loop("ping?").await.unwrap();
+
+            assert_eq!(output.content, "pong", "{:?}", protocol);
+            assert_eq!(output.trace.observations.len(), 1, "{:?}", protocol);
+            assert!(output.trace.observations[0].content.contains("ping"), "{:?}", protocol);
+            assert_eq!(output.trace.total_tokens.total_tokens, 30);
+            assert_eq!(client.request_count(), 2);
+            assert_eq!(client.remaining(), 0);
+            assert_eq!(client.requests()[0].messages.last().unwrap().content, "ping?");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_errors_latency_and_exhaustion() {
+        let client = MockLlmClient::new()
+            .with_error("rate limited")
+            .with_reply("ok")
+            .with_latency(Duration::from_millis(20));
+        let request = CompletionRequest::new("m", vec![Message::user("hi")]);
+
+        let start = std::time::Instant::now();
+        let err = client.complete(request.clone()).await.unwrap_err();
+        assert!(err.to_string().contains("rate limited"));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        let mut stream = client.stream(request.clone()).await.unwrap();
+        let chunk = stream.next_chunk().await.unwrap().unwrap();
+        assert_eq!(chunk.choices[0].delta.content.as_deref(), Some("ok"));
+        assert!(stream.next_chunk().await.is_none());
+
+        assert!(client.complete(request.clone()).await.is_err());
+        assert_eq!(client.request_count(), 3);
+
+        let fallback = MockLlmClient::new().with_fallback_reply("again");
+        for _ in 0..3 {
+            let response = fallback.complete(request.clone()).await.unwrap();
+            assert_eq!(response.choices[0].message.content, "again");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_deltas_stall_and_peak_in_flight() {
+        let client = Arc::new(
+            MockLlmClient::new()
+                .with_reply("Final answer: pong")
+                .with_response(MockResponse::Stall)
+                .with_fallback_reply("ok")
+                .with_latency(Duration::from_millis(20))
+                .with_stream_deltas(3)
+                .with_model_ids(["openai/gpt-4o"]),
+        );
+        let request = CompletionRequest::new("m", vec![Message::user("hi")]);
+
+        let mut stream = client.stream(request.clone()).await.unwrap();
+        let mut deltas = Vec::new();
+        while let Some(chunk) = stream.next_chunk().await {
+            deltas.push(chunk.unwrap().choices[0].delta.content.clone().unwrap());
+        }
+        assert_eq!(deltas, ["Final ", "answer", ": pong"]);
+
+        let stalled = tokio::time::timeout(Duration::from_millis(100), client.complete(request.clone())).await;
+        assert!(stalled.is_err());
+
+        let calls = (0..3).map(|_| client.complete(request.clone()));
+        assert!(futures::future::join_all(calls).await.iter().all(|r| r.is_ok()));
+        assert_eq!(client.peak_in_flight(), 3);
+        assert_eq!(client.prompts(), ["hi"; 5]);
+        assert_eq!(client.model_ids().await.unwrap(), ["openai/gpt-4o"]);
+        assert!(MockLlmClient::new().model_ids().await.is_err());
+    }
+}
@@ -0,0 +1,309 @@
+//! Tool result caching
+//!
+//! Slow, deterministic tools (e.g. `lynis`, `rkhunter` over a short window)
+//! can have their results memoized. Tools opt in with
+//! [`Tool::cacheable`](crate::tools::Tool::cacheable); results are stored in
+//! the [`ToolCache`] attached to the [`ToolContext`](crate::tools::ToolContext),
+//! keyed by [`cache_key`] and kept for [`Tool::cache_ttl`](crate::tools::Tool::cache_ttl).
+//! Replayed results carry [`ToolOutput::from_cache`].
+
+use crate::error::Result;
+use crate::tools::{idempotency_key, Tool, ToolContext, ToolOutput};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Default number of entries kept by [`InMemoryToolCache`]
+pub const DEFAULT_CACHE_CAPACITY: usize = 256;
+
+/// Cache key for a call: the tool ID plus its arguments as canonical JSON
+pub fn cache_key(tool_id: &str, params: &Value) -> String {
+    idempotency_key(tool_id, params)
+}
+
+/// Execute `tool`, answering from the context's cache when the tool is cacheable
+///
+/// Only successful outputs are stored. A cache that fails to store a result
+/// is logged and otherwise ignored.
+pub async fn execute_cached(tool: &dyn Tool, params: Value, ctx: &ToolContext) -> Result<ToolOutput> {
+    let cache = match ctx.cache() {
+        Some(cache) if tool.cacheable() => cache,
+        _ => return tool.execute(params, ctx).await,
+    };
+
+    let key = cache_key(tool.id(), &params);
+    if let Some(mut output) = cache.get(&key).await {
+        output.from_cache = true;
+        return Ok(output);
+    }
+
+    let output = tool.execute(params, ctx).await?;
+    if output.success {
+        if let Err(e) = cache.put(&key, &output, tool.cache_ttl()).await {
+            tracing::warn!(tool_id = tool.id(), "Failed to cache tool result: {}", e);
+        }
+    }
+    Ok(output)
+}
+
+/// Store for memoized tool results
+#[async_trait]
+pub trait ToolCache: Send + Sync {
+    /// Cached output for `key`, if present and not expired
+    async fn get(&self, key: &str) -> Option<ToolOutput>;
+
+    /// Store `output` under `key` for `ttl`
+    async fn put(&self, key: &str, output: &ToolOutput, ttl: Duration) -> Result<()>;
+}
+
+struct MemoryEntry {
+    output: ToolOutput,
+    expires_at: Instant,
+    last_used: u64,
+}
+
+struct MemoryState {
+    entries: HashMap<String, MemoryEntry>,
+    clock: u64,
+}
+
+/// In-memory cache evicting the least recently used entry when full
+pub struct InMemoryToolCache {
+    capacity: usize,
+    state: Mutex<MemoryState>,
+}
+
+impl InMemoryToolCache {
+    /// Create a cache holding at most `capacity` entries
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            state: Mutex::new(MemoryState {
+                entries: HashMap::new(),
+                clock: 0,
+            }),
+        }
+    }
+
+    /// Number of entries currently stored (including expired ones not yet evicted)
+    pub fn len(&self) -> usize {
+        self.state.lock().entries.len()
+    }
+
+    /// Whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Default for InMemoryToolCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_CAPACITY)
+    }
+}
+
+#[async_trait]
+impl ToolCache for InMemoryToolCache {
+    async fn get(&self, key: &str) -> Option<ToolOutput> {
+        let mut state = self.state.lock();
+        state.clock += 1;
+        let now = state.clock;
+        let entry = state.entries.get_mut(key)?;
+        if entry.expires_at <= Instant::now() {
+            state.entries.remove(key);
+            return None;
+        }
+        entry.last_used = now;
+        Some(entry.output.clone())
+    }
+
+    async fn put(&self, key: &str, output: &ToolOutput, ttl: Duration) -> Result<()> {
+        let mut state = self.state.lock();
+        state.clock += 1;
+        let now = Instant::now();
+        if !state.entries.contains_key(key) && state.entries.len() >= self.capacity {
+            state.entries.retain(|_, entry| entry.expires_at > now);
+            if state.entries.len() >= self.capacity {
+                let oldest = state
+                    .entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.last_used)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    state.entries.remove(&oldest);
+                }
+            }
+        }
+
+        let last_used = state.clock;
+        state.entries.insert(
+            key.to_string(),
+            MemoryEntry {
+                output: output.clone(),
+                expires_at: now + ttl,
+                last_used,
+            },
+        );
+        Ok(())
+    }
+}
+
+/// One cached result on disk
+#[derive(Serialize, Deserialize)]
+struct DiskEntry {
+    key: String,
+    expires_at: DateTime<Utc>,
+    output: ToolOutput,
+}
+
+/// Cache persisting results as JSON files in a directory, surviving restarts
+#[derive(Debug, Clone)]
+pub struct DiskToolCache {
+    dir: PathBuf,
+}
+
+impl DiskToolCache {
+    /// Cache in `dir`, creating it on first write
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    /// Directory holding the cache files
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        // FNV-1a; the full key is stored in the file to rule out collisions
+        let hash = key.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        self.dir.join(format!("{:016x}.json", hash))
+    }
+}
+
+#[async_trait]
+impl ToolCache for DiskToolCache {
+    async fn get(&self, key: &str) -> Option<ToolOutput> {
+        let path = self.path(key);
+        let content = tokio::fs::read_to_string(&path).await.ok()?;
+        let entry: DiskEntry = serde_json::from_str(&content).ok()?;
+        if entry.key != key {
+            return None;
+        }
+        if entry.expires_at <= Utc::now() {
+            let _ = tokio::fs::remove_file(&path).await;
+            return None;
+        }
+        Some(entry.output)
+    }
+
+    async fn put(&self, key: &str, output: &ToolOutput, ttl: Duration) -> Result<()> {
+        let ttl = chrono::Duration::from_std(ttl).unwrap_or(chrono::Duration::MAX);
+        let entry = DiskEntry {
+            key: key.to_string(),
+            expires_at: Utc::now().checked_add_signed(ttl).unwrap_or(DateTime::<Utc>::MAX_UTC),
+            output: output.clone(),
+        };
+        tokio::fs::create_dir_all(&self.dir).await?;
+        tokio::fs::write(self.path(key), serde_json::to_vec(&entry)?).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AgentId;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    struct CountingTool {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Tool for CountingTool {
+        fn id(&self) -> &str {
+            "count"
+        }
+
+        fn name(&self) -> &str {
+            "Count"
+        }
+
+        fn description(&self) -> &str {
+            "Counts its own invocations"
+        }
+
+        async fn execute(&self, _params: Value, _ctx: &ToolContext) -> Result<ToolOutput> {
+            let n = self.calls.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(ToolOutput::success(n.to_string()))
+        }
+
+        fn cacheable(&self) -> bool {
+            true
+        }
+    }
+
+    #[tokio::test]
+    async fn test_execute_cached() {
+        let tool = CountingTool { calls: AtomicUsize::new(0) };
+        let uncached = ToolContext::new(AgentId::new());
+        let ctx = uncached.clone().with_cache(Arc::new(InMemoryToolCache::default()));
+        let params = serde_json::json!({"path": "/etc"});
+
+        let first = execute_cached(&tool, params.clone(), &ctx).await.unwrap();
+        assert!(!first.from_cache);
+        let second = execute_cached(&tool, params.clone(), &ctx).await.unwrap();
+        assert!(second.from_cache);
+        assert_eq!(second.content, "1");
+
+        let other = execute_cached(&tool, serde_json::json!({"path": "/var"}), &ctx).await.unwrap();
+        assert_eq!(other.content, "2");
+        let bypassed = execute_cached(&tool, params, &uncached).await.unwrap();
+        assert_eq!(bypassed.content, "3");
+    }
+
+    #[tokio::test]
+    async fn test_in_memory_lru_and_ttl() {
+        let cache = InMemoryToolCache::new(2);
+        let ttl = Duration::from_secs(60);
+        cache.put("a", &ToolOutput::success("A"), ttl).await.unwrap();
+        cache.put("b", &ToolOutput::success("B"), ttl).await.unwrap();
+
+        // Touch "a" so "b" is the least recently used
+        assert_eq!(cache.get("a").await.unwrap().content, "A");
+        cache.put("c", &ToolOutput::success("C"), ttl).await.unwrap();
+        assert!(cache.get("b").await.is_none());
+        assert!(cache.get("a").await.is_some());
+        assert_eq!(cache.len(), 2);
+
+        cache.put("d", &ToolOutput::success("D"), Duration::ZERO).await.unwrap();
+        assert!(cache.get("d").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_disk_cache_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let key = cache_key("lynis", &serde_json::json!({"mode": "quick", "audit": true}));
+        assert_eq!(key, cache_key("lynis", &serde_json::json!({"audit": true, "mode": "quick"})));
+
+        let cache = DiskToolCache::new(dir.path().join("tools"));
+        cache
+            .put(&key, &ToolOutput::success("hardening index: 71"), Duration::from_secs(60))
+            .await
+            .unwrap();
+        let reopened = DiskToolCache::new(cache.dir());
+        assert_eq!(reopened.get(&key).await.unwrap().content, "hardening index: 71");
+        assert!(reopened.get("lynis:{}").await.is_none());
+
+        cache.put(&key, &ToolOutput::success("stale"), Duration::ZERO).await.unwrap();
+        assert!(cache.get(&key).await.is_none());
+    }
+}
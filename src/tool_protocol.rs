@@ -0,0 +1,395 @@
+//! Tool-call wire formats
+//!
+//! Providers and models differ in how they expect tool calls to appear in
+//! the conversation. A [`ToolProtocol`] controls how the ReAct loop
+//! advertises tools, serializes calls and results into messages, and parses
+//! calls back out of model responses.
+
+use crate::openrouter::{FunctionDefinition, Message, ToolDefinition};
+use crate::react::{parse_action_input, Observation};
+use crate::tools::Tool;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
+
+/// How tool calls and results are exchanged with the model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolProtocol {
+    /// Native OpenAI-style `tools` / `tool_calls` fields
+    OpenAiJson,
+    /// XML `<tool_use>` / `<tool_result>` blocks in message text
+    Anthropic,
+    /// `Action:` / `Action Input:` / `Observation:` lines in message text
+    #[default]
+    ReActText,
+}
+
+/// A tool call parsed from a model response
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedToolCall {
+    /// Provider-assigned call ID, if any
+    pub id: Option<String>,
+    /// Tool identifier
+    pub tool_id: String,
+    /// Tool parameters
+    pub params: serde_json::Value,
+}
+
+/// A tool call whose arguments could not be parsed as JSON
+#[derive(Debug, Clone, PartialEq)]
+pub struct MalformedToolCall {
+    /// Provider-assigned call ID, if any
+    pub id: Option<String>,
+    /// Tool identifier
+    pub tool_id: String,
+    /// Arguments as the model wrote them
+    pub arguments: String,
+    /// Why the arguments failed to parse
+    pub error: String,
+}
+
+impl ToolProtocol {
+    /// Tool definitions to send with the request (native protocols only)
+    pub fn tool_definitions(&self, tools: &[Arc<dyn Tool>]) -> Option<Vec<ToolDefinition>> {
+        if *self != Self::OpenAiJson || tools.is_empty() {
+            return None;
+        }
+
+        Some(
+            tools
+                .iter()
+                .map(|tool| ToolDefinition {
+                    tool_type: "function".to_string(),
+                    function: FunctionDefinition {
+                        name: tool.id().to_string(),
+                        description: tool.description().to_string(),
+                        parameters: serde_json::to_value(tool.input_schema())
+                            .unwrap_or_else(|_| serde_json::json!({ "type": "object" })),
+                    },
+                })
+                .collect(),
+        )
+    }
+
+    /// System prompt section describing the tools (text protocols only)
+    pub fn instructions(&self, tools: &[Arc<dyn Tool>]) -> Option<String> {
+        if *self == Self::OpenAiJson || tools.is_empty() {
+            return None;
+        }
+
+        let mut text = String::from("You can use these tools:\n");
+        for tool in tools {
+            let schema = serde_json::to_string(&tool.input_schema()).unwrap_or_default();
+            text.push_str(&format!("- {}: {} Parameters: {}\n", tool.id(), tool.description(), schema));
+        }
+
+        text.push_str(match self {
+            Self::Anthropic => {
+                "\nTo call a tool, reply with exactly one block:\n\
+                 <tool_use><name>TOOL_ID</name><input>{\"param\": \"value\"}</input></tool_use>\n\
+                 Results arrive in <tool_result> blocks. When done, reply with 'Final Answer: ...'."
+            }
+            _ => {
+                "\nTo call a tool, reply with:\n\
+                 Action: TOOL_ID\n\
+                 Action Input: {\"param\": \"value\"}\n\
+                 Results arrive as 'Observation: ...'. When done, reply with 'Final Answer: ...'."
+            }
+        });
+
+        Some(text)
+    }
+
+    /// Parse a tool call out of a model response
+    pub fn parse_tool_call(&self, response: &Message) -> Option<ParsedToolCall> {
+        self.raw_tool_calls(response).into_iter().next()?.parse().ok()
+    }
+
+    /// Parse every tool call out of a model response, in order
+    ///
+    /// Calls whose arguments cannot be recovered as JSON are left out; see
+    /// [`ToolProtocol::malformed_tool_calls`].
+    pub fn parse_tool_calls(&self, response: &Message) -> Vec<ParsedToolCall> {
+        self.raw_tool_calls(response)
+            .into_iter()
+            .filter_map(|call| call.parse().ok())
+            .collect()
+    }
+
+    /// Tool calls whose arguments are not valid JSON even after repair
+    pub fn malformed_tool_calls(&self, response: &Message) -> Vec<MalformedToolCall> {
+        self.raw_tool_calls(response)
+            .into_iter()
+            .filter_map(|call| call.parse().err())
+            .collect()
+    }
+
+    /// Tool calls in a response with their arguments still unparsed
+    fn raw_tool_calls(&self, response: &Message) -> Vec<RawToolCall> {
+        match self {
+            Self::OpenAiJson => response
+                .tool_calls
+                .iter()
+                .flatten()
+                .map(|call| RawToolCall {
+                    id: Some(call.id.clone()),
+                    tool_id: call.function.name.clone(),
+                    arguments: call.function.arguments.clone(),
+                })
+                .collect(),
+            // Split before each `<tool_use>` / `Action:` and parse the pieces alone
+            Self::Anthropic | Self::ReActText => {
+                static TOOL_USE_START_RE: OnceLock<Regex> = OnceLock::new();
+                static ACTION_START_RE: OnceLock<Regex> = OnceLock::new();
+                let re = match self {
+                    Self::Anthropic => TOOL_USE_START_RE
+                        .get_or_init(|| Regex::new(r"<tool_use>").expect("tool_use start pattern is valid")),
+                    _ => ACTION_START_RE
+                        .get_or_init(|| Regex::new(r"(?i)\baction:").expect("action start pattern is valid")),
+                };
+                let content = &response.content;
+                let starts: Vec<usize> = re.find_iter(content).map(|m| m.start()).collect();
+                starts
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, &start)| {
+                        let end = starts.get(i + 1).copied().unwrap_or(content.len());
+                        self.raw_text_call(&content[start..end])
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    /// Tool call at the start of one piece of a text-protocol response
+    fn raw_text_call(&self, text: &str) -> Option<RawToolCall> {
+        match self {
+            Self::Anthropic => {
+                static TOOL_USE_RE: OnceLock<Regex> = OnceLock::new();
+                let re = TOOL_USE_RE.get_or_init(|| {
+                    Regex::new(r"(?s)<tool_use>\s*<name>\s*(.*?)\s*</name>\s*<input>\s*(.*?)\s*</input>\s*</tool_use>")
+                        .expect("tool_use pattern is valid")
+                });
+                let caps = re.captures(text)?;
+                Some(RawToolCall {
+                    id: None,
+                    tool_id: caps[1].to_string(),
+                    arguments: caps[2].to_string(),
+                })
+            }
+            _ => {
+                static ACTION_RE: OnceLock<Regex> = OnceLock::new();
+                let re = ACTION_RE.get_or_init(|| {
+                    Regex::new(r"(?is)\baction:\s*`?([A-Za-z0-9_.-]+)`?\s*(?:action input:(.*))?")
+                        .expect("action pattern is valid")
+                });
+                let caps = re.captures(text)?;
+                // An input without any object is treated as no input
+                let arguments = caps
+                    .get(2)
+                    .map(|input| input.as_str())
+                    .filter(|input| input.contains('{'))
+                    .unwrap_or_default();
+                Some(RawToolCall {
+                    id: None,
+                    tool_id: caps[1].to_string(),
+                    arguments: arguments.to_string(),
+                })
+            }
+        }
+    }
+
+    /// Assistant message recording a tool call in the conversation
+    pub fn call_message(&self, response: &Message) -> Message {
+        match self {
+            Self::OpenAiJson => {
+                let mut message = Message::assistant(&response.content);
+                message.tool_calls = response.tool_calls.clone();
+                message
+            }
+            _ => Message::assistant(&response.content),
+        }
+    }
+
+    /// Assistant message recording only `calls` out of a response
+    ///
+    /// Native providers reject a conversation in which an echoed call ID has
+    /// no tool reply, so calls that will not be answered are left out.
+    pub fn calls_message(&self, response: &Message, calls: &[ParsedToolCall]) -> Message {
+        let mut message = self.call_message(response);
+        if let Some(tool_calls) = message.tool_calls.as_mut() {
+            tool_calls.retain(|echoed| calls.iter().any(|call| call.id.as_deref() == Some(echoed.id.as_str())));
+            if tool_calls.is_empty() {
+                message.tool_calls = None;
+            }
+        }
+        message
+    }
+
+    /// Message carrying the result of one specific call back to the model
+    pub fn call_result_message(&self, call: &ParsedToolCall, observation: &Observation) -> Message {
+        match (self, &call.id) {
+            (Self::OpenAiJson, Some(id)) => Message::tool(&observation.content, id),
+            _ => self.result_message(&Message::assistant(""), &call.tool_id, observation),
+        }
+    }
+
+    /// Message carrying a tool result back to the model
+    pub fn result_message(&self, response: &Message, tool_id: &str, observation: &Observation) -> Message {
+        match self {
+            Self::OpenAiJson => {
+                // Answer the call to this tool, not whichever call came first
+                let calls = response.tool_calls.as_deref().unwrap_or_default();
+                let call_id = calls
+                    .iter()
+                    .find(|call| call.function.name == tool_id)
+                    .or_else(|| calls.first())
+                    .map(|call| call.id.clone())
+                    .unwrap_or_else(|| tool_id.to_string());
+                Message::tool(&observation.content, call_id)
+            }
+            Self::Anthropic => Message::user(format!(
+                "<tool_result name=\"{}\">\n{}\n</tool_result>",
+                tool_id, observation.content
+            )),
+            Self::ReActText => Message::user(format!("Observation: {}", observation.content)),
+        }
+    }
+}
+
+/// A tool call whose arguments are still text
+struct RawToolCall {
+    id: Option<String>,
+    tool_id: String,
+    arguments: String,
+}
+
+impl RawToolCall {
+    fn parse(self) -> Result<ParsedToolCall, MalformedToolCall> {
+        match parse_action_input(&self.arguments) {
+            Ok(params) => Ok(ParsedToolCall {
+                id: self.id,
+                tool_id: self.tool_id,
+                params,
+            }),
+            Err(error) => Err(MalformedToolCall {
+                id: self.id,
+                tool_id: self.tool_id,
+                arguments: self.arguments,
+                error: error.to_string(),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openrouter::{FunctionCall, ToolCall};
+    use crate::tools::EchoTool;
+
+    #[test]
+    fn test_parse_text_protocols() {
+        let react = Message::assistant(
+            "Thought: I should echo.\nAction: echo\nAction Input: {\"message\": \"hi\"}",
+        );
+        let call = ToolProtocol::ReActText.parse_tool_call(&react).unwrap();
+        assert_eq!(call.tool_id, "echo");
+        assert_eq!(call.params["message"], "hi");
+
+        let xml = Message::assistant(
+            "Let me check.\n<tool_use><name>echo</name><input>{\"message\": \"hi\"}</input></tool_use>",
+        );
+        let call = ToolProtocol::Anthropic.parse_tool_call(&xml).unwrap();
+        assert_eq!(call.tool_id, "echo");
+        assert!(ToolProtocol::ReActText.parse_tool_call(&xml).is_none());
+        assert!(ToolProtocol::Anthropic.parse_tool_call(&react).is_none());
+    }
+
+    #[test]
+    fn test_parse_multiple_calls() {
+        let react = Message::assistant(
+            "Action: echo\nAction Input: {\"message\": \"a\"}\n\
+             Action: echo\nAction Input: {\"message\": \"b\"}",
+        );
+        let calls = ToolProtocol::ReActText.parse_tool_calls(&react);
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].params["message"], "a");
+        assert_eq!(calls[1].params["message"], "b");
+
+        let xml = Message::assistant(
+            "<tool_use><name>echo</name><input>{}</input></tool_use>\n\
+             <tool_use><name>calculator</name><input>{\"a\": 1}</input></tool_use>",
+        );
+        let calls = ToolProtocol::Anthropic.parse_tool_calls(&xml);
+        let ids: Vec<&str> = calls.iter().map(|c| c.tool_id.as_str()).collect();
+        assert_eq!(ids, ["echo", "calculator"]);
+    }
+
+    #[test]
+    fn test_malformed_arguments() {
+        let react = Message::assistant(
+            "Action: echo\nAction Input: ```json\n{'message': 'hi',}\n```\nI'll wait for the result.",
+        );
+        let call = ToolProtocol::ReActText.parse_tool_call(&react).unwrap();
+        assert_eq!(call.params, serde_json::json!({"message": "hi"}));
+        assert!(ToolProtocol::ReActText.malformed_tool_calls(&react).is_empty());
+
+        let broken = Message::assistant("Action: echo\nAction Input: {\"message\": hi}");
+        assert!(ToolProtocol::ReActText.parse_tool_calls(&broken).is_empty());
+        let malformed = ToolProtocol::ReActText.malformed_tool_calls(&broken);
+        assert_eq!(malformed[0].tool_id, "echo");
+        assert_eq!(malformed[0].arguments.trim(), "{\"message\": hi}");
+
+        let bare = Message::assistant("Action: echo");
+        assert_eq!(ToolProtocol::ReActText.parse_tool_call(&bare).unwrap().params, serde_json::json!({}));
+    }
+
+    #[test]
+    fn test_openai_json_round_trip() {
+        let mut response = Message::assistant("");
+        response.tool_calls = Some(vec![ToolCall {
+            id: "call_1".to_string(),
+            tool_type: "function".to_string(),
+            function: FunctionCall {
+                name: "echo".to_string(),
+                arguments: "{\"message\": \"hi\"}".to_string(),
+            },
+        }]);
+
+        let protocol = ToolProtocol::OpenAiJson;
+        let call = protocol.parse_tool_call(&response).unwrap();
+        assert_eq!(call.id.as_deref(), Some("call_1"));
+        assert!(protocol.call_message(&response).tool_calls.is_some());
+
+        let result = protocol.result_message(&response, "echo", &Observation::new("hi"));
+        assert_eq!(result.tool_call_id.as_deref(), Some("call_1"));
+
+        let mut two = response.clone();
+        two.tool_calls.as_mut().unwrap().insert(
+            0,
+            ToolCall {
+                id: "call_0".to_string(),
+                tool_type: "function".to_string(),
+                function: FunctionCall {
+                    name: "lookup".to_string(),
+                    arguments: "{}".to_string(),
+                },
+            },
+        );
+        let result = protocol.result_message(&two, "echo", &Observation::new("hi"));
+        assert_eq!(result.tool_call_id.as_deref(), Some("call_1"));
+
+        let echoed = protocol.calls_message(&response, &[]);
+        assert!(echoed.tool_calls.is_none());
+        let echoed = protocol.calls_message(&response, &[call]);
+        assert_eq!(echoed.tool_calls.unwrap()[0].id, "call_1");
+
+        let tools: Vec<Arc<dyn Tool>> = vec![Arc::new(EchoTool)];
+        assert_eq!(protocol.tool_definitions(&tools).unwrap()[0].function.name, "echo");
+        assert!(protocol.instructions(&tools).is_none());
+        assert!(ToolProtocol::ReActText.tool_definitions(&tools).is_none());
+        assert!(ToolProtocol::ReActText.instructions(&tools).unwrap().contains("Action Input"));
+    }
+}
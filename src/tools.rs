@@ -1,33 +1,70 @@
 //! Tool trait and implementations
 
 use crate::error::Result;
+use crate::memory::AgentMemory;
+use crate::tool_cache::ToolCache;
 use crate::types::AgentId;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::process::Stdio;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::sync::mpsc::UnboundedSender;
 
 #[cfg(feature = "mcp-tools")]
 use rmcp::{
     model::{
-        CallToolRequestParam, CallToolResult, PaginatedRequestParam, RawContent,
+        CallToolRequest, CallToolRequestParam, CallToolResult, ClientRequest, Meta, NumberOrString,
+        PaginatedRequestParam, ProgressNotificationParam, ProgressToken, RawContent, ServerResult,
     },
-    service::ServiceExt,
+    service::{NotificationContext, PeerRequestOptions, RoleClient, RunningService, ServiceError, ServiceExt},
     transport::child_process::TokioChildProcess,
 };
-#[cfg(feature = "mcp-tools")]
 use tokio::process::Command;
 
+/// Progress update reported by a running tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolProgress {
+    /// Tool reporting progress
+    pub tool_id: String,
+    /// Completion percentage (0-100)
+    pub percent: f32,
+    /// Human-readable status, e.g. "capturing 45s/60s"
+    pub message: String,
+}
+
+/// Channel receiving progress updates from tools during a run
+pub type ProgressSender = UnboundedSender<ToolProgress>;
+
 /// Context provided to tools during execution
-#[derive(Debug, Clone)]
+#[derive(Clone)]
 pub struct ToolContext {
     /// ID of the agent executing the tool
     pub agent_id: AgentId,
     /// Additional context data
     pub data: HashMap<String, Value>,
+    /// Where progress updates go, tagged with the running tool's ID
+    progress: Option<(String, ProgressSender)>,
+    /// Where results of cacheable tools are memoized
+    cache: Option<Arc<dyn ToolCache>>,
+    /// Memory of the calling agent, for tools that edit it
+    memory: Option<AgentMemory>,
+}
+
+impl std::fmt::Debug for ToolContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolContext")
+            .field("agent_id", &self.agent_id)
+            .field("data", &self.data)
+            .field("progress", &self.progress)
+            .field("cache", &self.cache.is_some())
+            .field("memory", &self.memory.is_some())
+            .finish()
+    }
 }
 
 impl ToolContext {
@@ -36,9 +73,51 @@ impl ToolContext {
         Self {
             agent_id,
             data: HashMap::new(),
+            progress: None,
+            cache: None,
+            memory: None,
         }
     }
 
+    /// Forward progress reported by `tool_id` to the given channel
+    pub fn with_progress(mut self, tool_id: impl Into<String>, sender: ProgressSender) -> Self {
+        self.progress = Some((tool_id.into(), sender));
+        self
+    }
+
+    /// Report progress; a no-op when nobody is listening
+    pub fn progress(&self, percent: f32, message: impl Into<String>) {
+        if let Some((tool_id, sender)) = &self.progress {
+            let _ = sender.send(ToolProgress {
+                tool_id: tool_id.clone(),
+                percent: percent.clamp(0.0, 100.0),
+                message: message.into(),
+            });
+        }
+    }
+
+    /// Memoize results of [cacheable](Tool::cacheable) tools in `cache`
+    pub fn with_cache(mut self, cache: Arc<dyn ToolCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Tool result cache, if one is attached
+    pub fn cache(&self) -> Option<&Arc<dyn ToolCache>> {
+        self.cache.as_ref()
+    }
+
+    /// Give tools access to the calling agent's memory
+    pub fn with_memory(mut self, memory: AgentMemory) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    /// Memory of the calling agent, if it has one
+    pub fn memory(&self) -> Option<&AgentMemory> {
+        self.memory.as_ref()
+    }
+
     /// Add data to the context
     pub fn with_data(mut self, key: impl Into<String>, value: Value) -> Self {
         self.data.insert(key.into(), value);
@@ -64,6 +143,9 @@ pub struct ToolOutput {
     /// Optional error message
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// Whether this output was replayed from a [`ToolCache`]
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub from_cache: bool,
 }
 
 impl ToolOutput {
@@ -74,6 +156,7 @@ impl ToolOutput {
             content: content.into(),
             data: None,
             error: None,
+            from_cache: false,
         }
     }
 
@@ -84,6 +167,7 @@ impl ToolOutput {
             content: content.into(),
             data: Some(data),
             error: None,
+            from_cache: false,
         }
     }
 
@@ -94,6 +178,7 @@ impl ToolOutput {
             content: String::new(),
             data: None,
             error: Some(error.into()),
+            from_cache: false,
         }
     }
 
@@ -104,6 +189,7 @@ impl ToolOutput {
             content: content.into(),
             data: None,
             error: Some(error.into()),
+            from_cache: false,
         }
     }
 }
@@ -166,7 +252,12 @@ pub trait Tool: Send + Sync {
     fn description(&self) -> &str;
 
     /// JSON Schema for input parameters
-    fn input_schema(&self) -> JsonSchema;
+    ///
+    /// Arguments are checked against it before [`execute`](Self::execute) is
+    /// called (see [`validate_params`]). Defaults to an object with no constraints.
+    fn input_schema(&self) -> JsonSchema {
+        JsonSchema::empty()
+    }
 
     /// Execute the tool with given parameters
     async fn execute(&self, params: Value, ctx: &ToolContext) -> Result<ToolOutput>;
@@ -180,6 +271,98 @@ pub trait Tool: Send + Sync {
     fn estimated_duration(&self) -> Duration {
         Duration::from_secs(1)
     }
+
+    /// Optional: Key identifying a side-effecting call
+    ///
+    /// Within one agent run, a call whose key matches an earlier successful
+    /// call is not executed again; the earlier result is replayed instead.
+    /// Read-only tools return `None` (the default). See [`idempotency_key`].
+    fn idempotency_key(&self, _params: &Value) -> Option<String> {
+        None
+    }
+
+    /// Optional: Whether successful results may be memoized
+    ///
+    /// Only for deterministic, read-only tools. When the [`ToolContext`] has a
+    /// [`ToolCache`], a repeated call with the same arguments is answered from
+    /// the cache for [`cache_ttl`](Self::cache_ttl). Defaults to `false`.
+    fn cacheable(&self) -> bool {
+        false
+    }
+
+    /// Optional: How long a cached result stays valid
+    fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(300)
+    }
+}
+
+/// Compile a tool's input schema for [`validate_params_with`]
+pub fn compile_input_schema(tool: &dyn Tool) -> Result<jsonschema::Validator> {
+    let schema = serde_json::to_value(tool.input_schema())?;
+    jsonschema::validator_for(&schema)
+        .map_err(|e| crate::error::Error::config(format!("Invalid input schema for tool {}: {}", tool.id(), e)))
+}
+
+/// Check tool arguments against the tool's input schema and [`Tool::validate`]
+///
+/// Compiles the schema on every call; agents compile each tool's schema once
+/// when built and use [`validate_params_with`]. A schema that does not
+/// compile is logged and not enforced.
+pub fn validate_params(tool: &dyn Tool, params: &Value) -> std::result::Result<(), ToolOutput> {
+    let validator = compile_input_schema(tool)
+        .map_err(|e| tracing::warn!("{}; only Tool::validate is checked", e))
+        .ok();
+    validate_params_with(tool, validator.as_ref(), params)
+}
+
+/// Check tool arguments against a compiled input schema and [`Tool::validate`]
+///
+/// On failure returns a failed [`ToolOutput`] whose `data` lists each
+/// violation by JSON pointer, so the model can correct the call instead of
+/// the tool tripping over a missing or mistyped argument. Without a
+/// validator only [`Tool::validate`] is checked.
+pub fn validate_params_with(
+    tool: &dyn Tool,
+    validator: Option<&jsonschema::Validator>,
+    params: &Value,
+) -> std::result::Result<(), ToolOutput> {
+    let mut violations: Vec<Value> = match validator.map(|v| v.validate(params)) {
+        Some(Err(errors)) => errors
+            .map(|e| serde_json::json!({ "path": e.instance_path.to_string(), "message": e.to_string() }))
+            .collect(),
+        Some(Ok(())) | None => Vec::new(),
+    };
+    if violations.is_empty() {
+        if let Err(e) = tool.validate(params) {
+            violations.push(serde_json::json!({ "path": "", "message": e.to_string() }));
+        }
+    }
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    let summary = violations
+        .iter()
+        .map(|v| match v["path"].as_str() {
+            Some(path) if !path.is_empty() => format!("{}: {}", path, v["message"].as_str().unwrap_or_default()),
+            _ => v["message"].as_str().unwrap_or_default().to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("; ");
+    Err(ToolOutput {
+        success: false,
+        content: String::new(),
+        data: Some(serde_json::json!({ "validation_errors": violations })),
+        error: Some(format!("Invalid parameters for {}: {}", tool.id(), summary)),
+        from_cache: false,
+    })
+}
+
+/// Idempotency key derived from a tool ID and its arguments
+///
+/// Object keys serialize in sorted order, so argument order does not matter.
+pub fn idempotency_key(tool_id: &str, params: &Value) -> String {
+    format!("{}:{}", tool_id, params)
 }
 
 /// A simple echo tool for testing
@@ -319,6 +502,280 @@ pub fn calculator_tool() -> Arc<dyn Tool> {
     Arc::new(CalculatorTool)
 }
 
+/// Tool that runs one binary with allow-listed flags
+///
+/// Takes `{ "args": ["-t", "-n"] }`. Every flag must be on the allow-list;
+/// positional arguments are rejected unless enabled. `--flag=value` matches
+/// `--flag` and its value is passed through unchecked, even with positional
+/// arguments disabled, so only allow-list flags whose values are safe to take
+/// from the model. No shell is involved, the environment is cleared except for
+/// `PATH` and variables passed through explicitly, and stdout and stderr are
+/// each read only up to the maximum output size; a process that writes more
+/// is killed. The output `data` carries `stdout`, `stderr`, `exit_code` and
+/// `truncated`.
+#[derive(Debug, Clone)]
+pub struct ShellTool {
+    id: String,
+    name: String,
+    description: String,
+    program: PathBuf,
+    allowed_flags: Vec<String>,
+    allow_positional: bool,
+    max_output_bytes: usize,
+    timeout: Duration,
+    env: HashMap<String, String>,
+}
+
+impl ShellTool {
+    /// Wrap `program` under `id`, with no flags allowed yet
+    pub fn new(id: impl Into<String>, program: impl Into<PathBuf>) -> Self {
+        let id = id.into();
+        let program = program.into();
+        let mut env = HashMap::new();
+        env.insert("PATH".to_string(), "/usr/sbin:/usr/bin:/sbin:/bin".to_string());
+        Self {
+            name: id.clone(),
+            description: format!("Runs {} with a restricted set of flags", program.display()),
+            id,
+            program,
+            allowed_flags: Vec::new(),
+            allow_positional: false,
+            max_output_bytes: 64 * 1024,
+            timeout: Duration::from_secs(30),
+            env,
+        }
+    }
+
+    /// Set the human-readable name
+    pub fn with_name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Set the description shown to the model
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = description.into();
+        self
+    }
+
+    /// Permit these flags, e.g. `["-t", "-u", "-l", "-n", "-p"]`
+    ///
+    /// Any value given as `--flag=value` is passed through unchecked.
+    pub fn with_allowed_flags<I, S>(mut self, flags: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowed_flags.extend(flags.into_iter().map(Into::into));
+        self
+    }
+
+    /// Permit arguments that are not flags, such as file paths
+    pub fn with_positional_args(mut self, allow: bool) -> Self {
+        self.allow_positional = allow;
+        self
+    }
+
+    /// Read at most this many bytes of stdout and of stderr (default 64 KiB)
+    pub fn with_max_output(mut self, bytes: usize) -> Self {
+        self.max_output_bytes = bytes;
+        self
+    }
+
+    /// Kill the process after this long (default 30 seconds)
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set an environment variable for the process, replacing the default `PATH` if named so
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    /// Pass a variable through from this process's environment, if set
+    pub fn with_inherited_env(mut self, key: impl Into<String>) -> Self {
+        let key = key.into();
+        if let Ok(value) = std::env::var(&key) {
+            self.env.insert(key, value);
+        }
+        self
+    }
+
+    /// Arguments from the call, rejecting any not on the allow-list
+    fn check_args(&self, params: &Value) -> Result<Vec<String>> {
+        let args = match params.get("args") {
+            None | Some(Value::Null) => return Ok(Vec::new()),
+            Some(Value::Array(args)) => args,
+            Some(_) => return Err(crate::error::Error::InvalidInput("'args' must be an array of strings".to_string())),
+        };
+
+        let mut checked = Vec::with_capacity(args.len());
+        for arg in args {
+            let arg = arg
+                .as_str()
+                .ok_or_else(|| crate::error::Error::InvalidInput("'args' must be an array of strings".to_string()))?;
+            if arg.contains('\0') {
+                return Err(crate::error::Error::InvalidInput("Arguments may not contain NUL bytes".to_string()));
+            }
+            if arg.starts_with('-') && arg.len() > 1 {
+                let flag = arg.split_once('=').map_or(arg, |(flag, _)| flag);
+                if !self.allowed_flags.iter().any(|allowed| allowed == flag) {
+                    return Err(crate::error::Error::InvalidInput(format!(
+                        "Flag '{}' is not allowed for {}; allowed: {}",
+                        flag,
+                        self.id,
+                        self.allowed_flags.join(" ")
+                    )));
+                }
+            } else if !self.allow_positional {
+                return Err(crate::error::Error::InvalidInput(format!(
+                    "{} does not accept positional argument '{}'",
+                    self.id, arg
+                )));
+            }
+            checked.push(arg.to_string());
+        }
+        Ok(checked)
+    }
+
+    /// Decode one output stream read with [`read_capped`], reporting whether it was cut
+    fn capture(&self, bytes: &[u8]) -> (String, bool) {
+        if bytes.len() <= self.max_output_bytes {
+            return (String::from_utf8_lossy(bytes).into_owned(), false);
+        }
+        // Back up to a character boundary so the cut does not leave a replacement character
+        let mut end = self.max_output_bytes;
+        while end > 0 && bytes[end] & 0xC0 == 0x80 {
+            end -= 1;
+        }
+        (
+            format!(
+                "{}\n[truncated at {} bytes]",
+                String::from_utf8_lossy(&bytes[..end]),
+                self.max_output_bytes
+            ),
+            true,
+        )
+    }
+}
+
+#[async_trait]
+impl Tool for ShellTool {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn input_schema(&self) -> JsonSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "args".to_string(),
+            serde_json::json!({
+                "type": "array",
+                "items": { "type": "string" },
+                "description": format!("Arguments to pass; allowed flags: {}", self.allowed_flags.join(" "))
+            }),
+        );
+
+        JsonSchema::object(properties)
+    }
+
+    fn validate(&self, params: &Value) -> Result<()> {
+        self.check_args(params).map(|_| ())
+    }
+
+    fn estimated_duration(&self) -> Duration {
+        self.timeout
+    }
+
+    async fn execute(&self, params: Value, _ctx: &ToolContext) -> Result<ToolOutput> {
+        let args = match self.check_args(&params) {
+            Ok(args) => args,
+            Err(e) => return Ok(ToolOutput::failure(e.to_string())),
+        };
+
+        let mut cmd = Command::new(&self.program);
+        cmd.args(&args)
+            .env_clear()
+            .envs(&self.env)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+        let mut child = match cmd.spawn() {
+            Ok(child) => child,
+            Err(e) => return Ok(ToolOutput::failure(format!("Failed to execute {}: {}", self.program.display(), e))),
+        };
+        let stdout = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+
+        // One byte past the cap tells a full stream from a cut one
+        let limit = self.max_output_bytes as u64 + 1;
+        let run = async {
+            let (stdout, stderr) = tokio::try_join!(read_capped(stdout, limit), read_capped(stderr, limit))?;
+            let cut = stdout.len() as u64 == limit || stderr.len() as u64 == limit;
+            // The closed pipes usually end it; stop it rather than wait for output nobody will read
+            if cut && tokio::time::timeout(KILL_GRACE, child.wait()).await.is_err() {
+                let _ = child.start_kill();
+            }
+            Ok::<_, std::io::Error>((child.wait().await?, cut, stdout, stderr))
+        };
+        let (status, cut, stdout, stderr) = match tokio::time::timeout(self.timeout, run).await {
+            Err(_) => return Ok(ToolOutput::failure(format!("{} timed out after {:?}", self.id, self.timeout))),
+            Ok(Err(e)) => return Ok(ToolOutput::failure(format!("Failed to execute {}: {}", self.program.display(), e))),
+            Ok(Ok(output)) => output,
+        };
+
+        let (stdout, stdout_truncated) = self.capture(&stdout);
+        let (stderr, stderr_truncated) = self.capture(&stderr);
+        let content = if stdout.is_empty() && !stderr.is_empty() {
+            format!("STDERR:\n{}", stderr)
+        } else if !stderr.is_empty() {
+            format!("{}\n\nSTDERR:\n{}", stdout, stderr)
+        } else {
+            stdout.clone()
+        };
+        let data = serde_json::json!({
+            "stdout": stdout,
+            "stderr": stderr,
+            "exit_code": status.code(),
+            "truncated": stdout_truncated || stderr_truncated,
+        });
+
+        // Ended by a signal after its output was cut is our doing, not a failure of the program
+        if status.success() || (cut && status.code().is_none()) {
+            Ok(ToolOutput::success_with_data(content, data))
+        } else {
+            Ok(ToolOutput {
+                data: Some(data),
+                ..ToolOutput::failure_with_content(content, format!("{} exited with status: {}", self.id, status))
+            })
+        }
+    }
+}
+
+/// How long a process whose output was cut gets to exit before it is killed
+const KILL_GRACE: Duration = Duration::from_millis(200);
+
+/// Read at most `limit` bytes from a child's output stream
+///
+/// The pipe is closed on return, so a process still writing gets `EPIPE`
+/// instead of blocking on a full pipe.
+async fn read_capped(stream: impl tokio::io::AsyncRead + Unpin, limit: u64) -> std::io::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    stream.take(limit).read_to_end(&mut buf).await?;
+    Ok(buf)
+}
+
 /// MCP tool wrapper that launches an MCP server over stdio as a subprocess.
 /// Requires the `mcp-tools` feature.
 #[cfg(feature = "mcp-tools")]
@@ -330,6 +787,7 @@ pub struct McpSubprocessTool {
     command: PathBuf,
     args: Vec<String>,
     mcp_tool_name: String,
+    side_effects: bool,
 }
 
 #[cfg(feature = "mcp-tools")]
@@ -350,9 +808,16 @@ impl McpSubprocessTool {
             command: command.into(),
             args: Vec::new(),
             mcp_tool_name: mcp_tool_name.into(),
+            side_effects: false,
         }
     }
 
+    /// Mark the tool as side-effecting, so identical calls run once per agent run.
+    pub fn with_side_effects(mut self) -> Self {
+        self.side_effects = true;
+        self
+    }
+
     /// Set CLI arguments for launching the subprocess (e.g., `["--foo", "bar"]`).
     pub fn with_args(mut self, args: Vec<String>) -> Self {
         self.args = args;
@@ -385,7 +850,11 @@ impl Tool for McpSubprocessTool {
         self.input_schema.clone()
     }
 
-    async fn execute(&self, params: Value, _ctx: &ToolContext) -> Result<ToolOutput> {
+    fn idempotency_key(&self, params: &Value) -> Option<String> {
+        self.side_effects.then(|| idempotency_key(&self.id, params))
+    }
+
+    async fn execute(&self, params: Value, ctx: &ToolContext) -> Result<ToolOutput> {
         let command = self.command.clone();
         let args = self.args.clone();
         let command_str = command
@@ -408,7 +877,7 @@ impl Tool for McpSubprocessTool {
         let transport = TokioChildProcess::new(cmd)
             .map_err(|e| crate::error::Error::tool_execution(self.id(), e.to_string()))?;
 
-        let service = ()
+        let service = ProgressRelay::default()
             .serve(transport)
             .await
             .map_err(|e| crate::error::Error::tool_execution(self.id(), e.to_string()))?;
@@ -419,21 +888,310 @@ impl Tool for McpSubprocessTool {
             .await
             .map_err(|e| crate::error::Error::tool_execution(self.id(), e.to_string()));
 
-        let call_result = service
-            .call_tool(CallToolRequestParam {
-                name: self.mcp_tool_name.clone().into(),
-                arguments: Some(args_map),
-            })
+        ctx.progress(0.0, format!("calling {}", self.mcp_tool_name));
+        let call_result = call_with_progress(&service, &self.mcp_tool_name, args_map, ctx)
             .await
             .map_err(|e| crate::error::Error::tool_execution(self.id(), e.to_string()))?;
 
         // Best-effort shutdown
         let _ = service.cancel().await;
+        ctx.progress(100.0, format!("{} finished", self.mcp_tool_name));
 
         Ok(convert_mcp_result(call_result))
     }
 }
 
+/// Connection to an MCP server, exposing its tools as SPAI [`Tool`]s
+///
+/// Unlike [`McpSubprocessTool`], which launches the server for every call,
+/// the client keeps one session open and discovers tool names, descriptions
+/// and schemas from the server itself. Requires the `mcp-tools` feature.
+///
+/// ```rust,ignore
+/// let procinfo = McpToolClient::spawn("tools/procinfo-mcp/target/release/procinfo-mcp", Vec::new()).await?;
+/// let agent = Agent::builder()
+///     .tools(procinfo.tools().await?)
+///     .build()?;
+/// ```
+#[cfg(feature = "mcp-tools")]
+#[derive(Clone)]
+pub struct McpToolClient {
+    server: String,
+    service: Arc<RunningService<RoleClient, ProgressRelay>>,
+}
+
+#[cfg(feature = "mcp-tools")]
+impl std::fmt::Debug for McpToolClient {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("McpToolClient").field("server", &self.server).finish()
+    }
+}
+
+#[cfg(feature = "mcp-tools")]
+impl McpToolClient {
+    /// Launch an MCP server binary and talk to it over stdio
+    ///
+    /// The server is stopped when the last clone of the client, and of the
+    /// tools it created, is dropped.
+    pub async fn spawn(command: impl Into<PathBuf>, args: Vec<String>) -> Result<Self> {
+        let command = command.into();
+        let server = command.display().to_string();
+        let mut cmd = Command::new(&command);
+        cmd.args(&args);
+        let transport =
+            TokioChildProcess::new(cmd).map_err(|e| crate::error::Error::tool_execution(&server, e.to_string()))?;
+        Self::serve(server, transport).await
+    }
+
+    /// Connect to an MCP server listening on a TCP socket
+    pub async fn connect_tcp(addr: impl tokio::net::ToSocketAddrs + std::fmt::Display) -> Result<Self> {
+        let server = addr.to_string();
+        let stream = tokio::net::TcpStream::connect(addr)
+            .await
+            .map_err(|e| crate::error::Error::tool_execution(&server, e.to_string()))?;
+        Self::connect(server, stream).await
+    }
+
+    /// Connect to an MCP server listening on a Unix socket
+    #[cfg(unix)]
+    pub async fn connect_unix(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let server = path.as_ref().display().to_string();
+        let stream = tokio::net::UnixStream::connect(path.as_ref())
+            .await
+            .map_err(|e| crate::error::Error::tool_execution(&server, e.to_string()))?;
+        Self::connect(server, stream).await
+    }
+
+    /// Speak MCP over an already-open byte stream, naming the server `server` in errors
+    pub async fn connect<S>(server: impl Into<String>, stream: S) -> Result<Self>
+    where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin + 'static,
+    {
+        Self::serve(server.into(), stream).await
+    }
+
+    async fn serve<T, E, A>(server: String, transport: T) -> Result<Self>
+    where
+        T: rmcp::transport::IntoTransport<RoleClient, E, A>,
+        E: std::error::Error + Send + Sync + 'static,
+    {
+        let service = ProgressRelay::default()
+            .serve(transport)
+            .await
+            .map_err(|e| crate::error::Error::tool_execution(&server, e.to_string()))?;
+        Ok(Self {
+            server,
+            service: Arc::new(service),
+        })
+    }
+
+    /// Name the server is reported under in errors
+    pub fn server(&self) -> &str {
+        &self.server
+    }
+
+    /// Every tool the server offers, following pagination
+    pub async fn list_tools(&self) -> Result<Vec<rmcp::model::Tool>> {
+        self.service
+            .list_all_tools()
+            .await
+            .map_err(|e| crate::error::Error::tool_execution(&self.server, e.to_string()))
+    }
+
+    /// Wrap every tool the server offers as a SPAI [`Tool`]
+    pub async fn tools(&self) -> Result<Vec<Arc<dyn Tool>>> {
+        Ok(self
+            .list_tools()
+            .await?
+            .into_iter()
+            .map(|tool| Arc::new(McpRemoteTool::new(self.clone(), tool)) as Arc<dyn Tool>)
+            .collect())
+    }
+
+    /// Call the server's tool `name` with object `arguments`
+    pub async fn call_tool(&self, name: &str, arguments: serde_json::Map<String, Value>) -> Result<ToolOutput> {
+        let result = self
+            .service
+            .call_tool(CallToolRequestParam {
+                name: name.to_string().into(),
+                arguments: Some(arguments),
+            })
+            .await
+            .map_err(|e| crate::error::Error::tool_execution(name, e.to_string()))?;
+        Ok(convert_mcp_result(result))
+    }
+
+    /// [`call_tool`](Self::call_tool), forwarding the server's progress to `ctx`
+    pub async fn call_tool_with_progress(
+        &self,
+        name: &str,
+        arguments: serde_json::Map<String, Value>,
+        ctx: &ToolContext,
+    ) -> Result<ToolOutput> {
+        let result = call_with_progress(&self.service, name, arguments, ctx)
+            .await
+            .map_err(|e| crate::error::Error::tool_execution(name, e.to_string()))?;
+        Ok(convert_mcp_result(result))
+    }
+}
+
+/// Client side of an MCP session, relaying `notifications/progress`
+///
+/// Each call made through [`call_with_progress`] registers its
+/// [`ToolContext`] under a fresh progress token for as long as it runs.
+/// Progress arriving after the call returned is dropped.
+#[cfg(feature = "mcp-tools")]
+#[derive(Default)]
+struct ProgressRelay {
+    calls: std::sync::Mutex<HashMap<ProgressToken, ToolContext>>,
+}
+
+#[cfg(feature = "mcp-tools")]
+impl rmcp::ClientHandler for ProgressRelay {
+    async fn on_progress(&self, params: ProgressNotificationParam, _context: NotificationContext<RoleClient>) {
+        let ctx = self.calls.lock().unwrap().get(&params.progress_token).cloned();
+        if let Some(ctx) = ctx {
+            // Without a total, progress is taken to be a percentage
+            let (percent, status) = match params.total.filter(|total| *total > 0.0) {
+                Some(total) => (params.progress / total * 100.0, format!("{}/{}", params.progress, total)),
+                None => (params.progress, format!("{}%", params.progress)),
+            };
+            ctx.progress(percent as f32, params.message.unwrap_or(status));
+        }
+    }
+}
+
+/// Unregisters a call from its [`ProgressRelay`] when dropped
+#[cfg(feature = "mcp-tools")]
+struct RelayedCall<'a> {
+    relay: &'a ProgressRelay,
+    token: ProgressToken,
+}
+
+#[cfg(feature = "mcp-tools")]
+impl Drop for RelayedCall<'_> {
+    fn drop(&mut self) {
+        self.relay.calls.lock().unwrap().remove(&self.token);
+    }
+}
+
+/// Call an MCP tool, forwarding the server's progress notifications to `ctx`
+#[cfg(feature = "mcp-tools")]
+async fn call_with_progress(
+    service: &RunningService<RoleClient, ProgressRelay>,
+    name: &str,
+    arguments: serde_json::Map<String, Value>,
+    ctx: &ToolContext,
+) -> std::result::Result<CallToolResult, ServiceError> {
+    let token = ProgressToken(NumberOrString::String(uuid::Uuid::new_v4().to_string().into()));
+    let relay = service.service();
+    relay.calls.lock().unwrap().insert(token.clone(), ctx.clone());
+    let _relayed = RelayedCall {
+        relay,
+        token: token.clone(),
+    };
+
+    let mut meta = Meta::new();
+    meta.set_progress_token(token);
+    let request = ClientRequest::CallToolRequest(CallToolRequest {
+        method: Default::default(),
+        params: CallToolRequestParam {
+            name: name.to_string().into(),
+            arguments: Some(arguments),
+        },
+        extensions: Default::default(),
+    });
+    let options = PeerRequestOptions {
+        meta: Some(meta),
+        ..Default::default()
+    };
+    match service.send_request_with_option(request, options).await?.await_response().await? {
+        ServerResult::CallToolResult(result) => Ok(result),
+        _ => Err(ServiceError::UnexpectedResponse),
+    }
+}
+
+/// A tool offered by an MCP server, called through an [`McpToolClient`]
+///
+/// Takes its ID, description and input schema from the server's tool
+/// listing. Tools annotated read-only are cacheable; destructive ones are
+/// run once per agent run for identical arguments.
+#[cfg(feature = "mcp-tools")]
+#[derive(Debug, Clone)]
+pub struct McpRemoteTool {
+    client: McpToolClient,
+    id: String,
+    name: String,
+    description: String,
+    input_schema: JsonSchema,
+    read_only: bool,
+    destructive: bool,
+}
+
+#[cfg(feature = "mcp-tools")]
+impl McpRemoteTool {
+    /// Wrap `tool` as listed by the server behind `client`
+    pub fn new(client: McpToolClient, tool: rmcp::model::Tool) -> Self {
+        let input_schema = serde_json::from_value(Value::Object((*tool.input_schema).clone()))
+            .unwrap_or_else(|_| JsonSchema::empty());
+        let annotations = tool.annotations.unwrap_or_default();
+        Self {
+            client,
+            id: tool.name.to_string(),
+            name: tool.title.unwrap_or_else(|| tool.name.to_string()),
+            description: tool.description.map(|d| d.to_string()).unwrap_or_default(),
+            input_schema,
+            read_only: annotations.read_only_hint == Some(true),
+            destructive: annotations.destructive_hint == Some(true),
+        }
+    }
+}
+
+#[cfg(feature = "mcp-tools")]
+#[async_trait]
+impl Tool for McpRemoteTool {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn input_schema(&self) -> JsonSchema {
+        self.input_schema.clone()
+    }
+
+    fn idempotency_key(&self, params: &Value) -> Option<String> {
+        self.destructive.then(|| idempotency_key(&self.id, params))
+    }
+
+    fn cacheable(&self) -> bool {
+        self.read_only
+    }
+
+    async fn execute(&self, params: Value, ctx: &ToolContext) -> Result<ToolOutput> {
+        let arguments = match params {
+            Value::Object(map) => map,
+            Value::Null => serde_json::Map::new(),
+            _ => {
+                return Err(crate::error::Error::InvalidInput(
+                    "MCP tool expects an object payload".to_string(),
+                ))
+            }
+        };
+
+        ctx.progress(0.0, format!("calling {} on {}", self.id, self.client.server()));
+        let output = self.client.call_tool_with_progress(&self.id, arguments, ctx).await;
+        ctx.progress(100.0, format!("{} finished", self.id));
+        output
+    }
+}
+
 /// Convert an MCP tool call result into the framework's `ToolOutput`.
 #[cfg(feature = "mcp-tools")]
 fn convert_mcp_result(result: CallToolResult) -> ToolOutput {
@@ -472,6 +1230,204 @@ fn convert_mcp_result(result: CallToolResult) -> ToolOutput {
             content,
             data,
             error: None,
+            from_cache: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ctx() -> ToolContext {
+        ToolContext::new(AgentId::new())
+    }
+
+    #[tokio::test]
+    async fn test_shell_tool_rejects_unlisted_args() {
+        let tool = ShellTool::new("echo", "echo").with_allowed_flags(["-n"]);
+
+        assert!(tool.validate(&serde_json::json!({ "args": ["-n"] })).is_ok());
+        assert!(tool.validate(&serde_json::json!({ "args": ["-e"] })).is_err());
+        assert!(tool.validate(&serde_json::json!({ "args": ["hello"] })).is_err());
+        let rejected = validate_params(&tool, &serde_json::json!({ "args": ["--help"] })).unwrap_err();
+        assert!(rejected.error.unwrap().contains("'--help' is not allowed"));
+
+        let output = tool.execute(serde_json::json!({ "args": ["-e"] }), &ctx()).await.unwrap();
+        assert!(!output.success);
+    }
+
+    #[tokio::test]
+    async fn test_shell_tool_captures_output() {
+        let tool = ShellTool::new("echo", "echo")
+            .with_allowed_flags(["-n"])
+            .with_positional_args(true)
+            .with_max_output(5);
+        let output = tool
+            .execute(serde_json::json!({ "args": ["-n", "hello world"] }), &ctx())
+            .await
+            .unwrap();
+
+        assert!(output.success);
+        let data = output.data.unwrap();
+        assert_eq!(data["stdout"], "hello\n[truncated at 5 bytes]");
+        assert_eq!(data["exit_code"], 0);
+        assert_eq!(data["truncated"], true);
+
+        let env = ShellTool::new("env", "env").execute(serde_json::json!({}), &ctx()).await.unwrap();
+        assert_eq!(env.content.trim(), "PATH=/usr/sbin:/usr/bin:/sbin:/bin");
+    }
+
+    #[tokio::test]
+    async fn test_shell_tool_stops_endless_output() {
+        // `yes` never exits on its own; only the bounded read lets this finish
+        let tool = ShellTool::new("yes", "yes").with_max_output(1024).with_timeout(Duration::from_secs(10));
+        let output = tool.execute(serde_json::json!({}), &ctx()).await.unwrap();
+
+        assert!(output.success);
+        let data = output.data.unwrap();
+        assert_eq!(data["truncated"], true);
+        assert!(data["stdout"].as_str().unwrap().starts_with("y\ny\n"));
+        assert!(data["stdout"].as_str().unwrap().ends_with("[truncated at 1024 bytes]"));
+    }
+
+    #[tokio::test]
+    async fn test_shell_tool_flag_values_pass_through() {
+        let tool = ShellTool::new("echo", "echo").with_allowed_flags(["--flag"]);
+        assert!(tool.validate(&serde_json::json!({ "args": ["--flag=anything at all"] })).is_ok());
+        assert!(tool.validate(&serde_json::json!({ "args": ["--flag", "value"] })).is_err());
+        assert!(tool.validate(&serde_json::json!({ "args": ["--other=value"] })).is_err());
+    }
+
+    /// In-process MCP server with an `add` tool, an `explode` tool that reports
+    /// an error and a `scan` tool that reports progress halfway through
+    #[cfg(feature = "mcp-tools")]
+    struct Calculator;
+
+    #[cfg(feature = "mcp-tools")]
+    impl rmcp::ServerHandler for Calculator {
+        fn get_info(&self) -> rmcp::model::ServerInfo {
+            rmcp::model::ServerInfo {
+                capabilities: rmcp::model::ServerCapabilities::builder().enable_tools().build(),
+                ..Default::default()
+            }
+        }
+
+        async fn list_tools(
+            &self,
+            _request: Option<PaginatedRequestParam>,
+            _context: rmcp::service::RequestContext<rmcp::RoleServer>,
+        ) -> std::result::Result<rmcp::model::ListToolsResult, rmcp::ErrorData> {
+            let schema = serde_json::json!({
+                "type": "object",
+                "properties": {"a": {"type": "integer"}, "b": {"type": "integer"}},
+                "required": ["a", "b"]
+            });
+            let mut add = rmcp::model::Tool::new("add", "Adds two integers", schema.as_object().unwrap().clone());
+            add.annotations = Some(rmcp::model::ToolAnnotations::new().read_only(true));
+            let explode = rmcp::model::Tool::new("explode", "Always fails", serde_json::Map::new());
+            let scan = rmcp::model::Tool::new("scan", "Reports progress", serde_json::Map::new());
+            Ok(rmcp::model::ListToolsResult::with_all_items(vec![add, explode, scan]))
+        }
+
+        async fn call_tool(
+            &self,
+            request: CallToolRequestParam,
+            context: rmcp::service::RequestContext<rmcp::RoleServer>,
+        ) -> std::result::Result<CallToolResult, rmcp::ErrorData> {
+            let args = request.arguments.unwrap_or_default();
+            let content = match request.name.as_ref() {
+                "scan" => {
+                    let progress_token = context.meta.get_progress_token().expect("client sends a progress token");
+                    let _ = context
+                        .peer
+                        .notify_progress(ProgressNotificationParam {
+                            progress_token,
+                            progress: 1.0,
+                            total: Some(2.0),
+                            message: Some("halfway".to_string()),
+                        })
+                        .await;
+                    // Notifications are handled on their own task; let it run before replying
+                    tokio::time::sleep(Duration::from_millis(50)).await;
+                    return Ok(CallToolResult::success(vec![rmcp::model::Content::text("clean")]));
+                }
+                "add" => {
+                    let sum = args["a"].as_i64().unwrap_or(0) + args["b"].as_i64().unwrap_or(0);
+                    return Ok(CallToolResult::success(vec![rmcp::model::Content::text(sum.to_string())]));
+                }
+                _ => rmcp::model::Content::text("boom"),
+            };
+            Ok(CallToolResult::error(vec![content]))
+        }
+    }
+
+    #[cfg(feature = "mcp-tools")]
+    #[tokio::test]
+    async fn test_mcp_tool_client_forwards_calls() {
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move {
+            let server = Calculator.serve(server_io).await.unwrap();
+            let _ = server.waiting().await;
+        });
+        let client = McpToolClient::connect("calculator", client_io).await.unwrap();
+
+        let tools = client.tools().await.unwrap();
+        assert_eq!(tools.len(), 3);
+        let add = tools.iter().find(|t| t.id() == "add").unwrap();
+        assert_eq!(add.description(), "Adds two integers");
+        assert_eq!(add.input_schema().required, Some(vec!["a".to_string(), "b".to_string()]));
+        assert!(add.cacheable());
+        assert!(validate_params(add.as_ref(), &serde_json::json!({"a": 1})).is_err());
+
+        let output = add.execute(serde_json::json!({"a": 2, "b": 3}), &ctx()).await.unwrap();
+        assert!(output.success);
+        assert_eq!(output.content, "5");
+
+        let explode = tools.iter().find(|t| t.id() == "explode").unwrap();
+        let output = explode.execute(Value::Null, &ctx()).await.unwrap();
+        assert!(!output.success);
+        assert_eq!(output.content, "boom");
+
+        // The same session drives an agent's ReAct loop
+        let llm = Arc::new(
+            crate::testing::MockLlmClient::new()
+                .with_tool_call("add", serde_json::json!({"a": 20, "b": 22}))
+                .with_reply("Final answer: 42"),
+        );
+        let agent = crate::Agent::builder()
+            .name("Calc")
+            .system_prompt("You are a test agent.")
+            .tools(tools)
+            .client(llm)
+            .build()
+            .unwrap();
+        let run = agent.react_loop("What is 20 + 22?").await.unwrap();
+        assert_eq!(run.trace.observations[0].content, "42");
+    }
+
+    #[cfg(feature = "mcp-tools")]
+    #[tokio::test]
+    async fn test_mcp_tool_forwards_server_progress() {
+        let (client_io, server_io) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move {
+            let server = Calculator.serve(server_io).await.unwrap();
+            let _ = server.waiting().await;
+        });
+        let client = McpToolClient::connect("calculator", client_io).await.unwrap();
+        let tools = client.tools().await.unwrap();
+        let scan = tools.iter().find(|t| t.id() == "scan").unwrap();
+
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let output = scan.execute(Value::Null, &ctx().with_progress("scan", tx)).await.unwrap();
+        assert_eq!(output.content, "clean");
+
+        let mut updates = Vec::new();
+        while let Ok(update) = rx.try_recv() {
+            updates.push((update.percent, update.message));
         }
+        assert_eq!(updates.len(), 3);
+        assert_eq!(updates[1], (50.0, "halfway".to_string()));
+        assert_eq!(updates[2], (100.0, "scan finished".to_string()));
     }
 }
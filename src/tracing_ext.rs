@@ -1,4 +1,11 @@
 //! Tracing and observability infrastructure
+//!
+//! Agents emit `tracing` spans as they run: a `react_loop` span per run, a
+//! `turn` span per ReAct iteration and a `tool_call` span per tool call, with
+//! model, token usage, tool name and success recorded as fields. Orchestrators
+//! wrap each run in an `orchestration` span and each agent in a
+//! [`sub_agent_span`]. With the `otel` feature, [`init_otlp`] exports them to
+//! an OTLP collector such as Jaeger.
 
 use crate::types::{SpanId, TokenUsage, TraceId};
 use chrono::{DateTime, Utc};
@@ -111,3 +118,68 @@ pub enum FailureMode {
         backoff: Duration,
     },
 }
+
+/// Span wrapping one agent's run inside an orchestrator
+pub fn sub_agent_span(role: &str, agent: &str) -> tracing::Span {
+    tracing::info_span!("sub_agent", role, agent)
+}
+
+#[cfg(feature = "otel")]
+pub use otel::{init_otlp, otlp_layer, otlp_provider};
+
+#[cfg(feature = "otel")]
+mod otel {
+    use crate::error::{Error, Result};
+    use opentelemetry::trace::TracerProvider as _;
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::trace::{Tracer, TracerProvider};
+    use opentelemetry_sdk::{runtime, Resource};
+    use tracing_opentelemetry::OpenTelemetryLayer;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::registry::LookupSpan;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    /// Tracer provider batching spans to an OTLP/gRPC `endpoint` (e.g. `http://localhost:4317`)
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn otlp_provider(service_name: &str, endpoint: &str) -> Result<TracerProvider> {
+        let exporter = opentelemetry_otlp::SpanExporter::builder()
+            .with_tonic()
+            .with_endpoint(endpoint)
+            .build()
+            .map_err(|e| Error::Tracing(e.to_string()))?;
+        Ok(TracerProvider::builder()
+            .with_batch_exporter(exporter, runtime::Tokio)
+            .with_resource(Resource::new(vec![KeyValue::new(
+                "service.name",
+                service_name.to_string(),
+            )]))
+            .build())
+    }
+
+    /// Layer turning `tracing` spans into OpenTelemetry spans from `provider`
+    pub fn otlp_layer<S>(provider: &TracerProvider) -> OpenTelemetryLayer<S, Tracer>
+    where
+        S: tracing::Subscriber + for<'span> LookupSpan<'span>,
+    {
+        tracing_opentelemetry::layer().with_tracer(provider.tracer("spai"))
+    }
+
+    /// Install a global subscriber that logs to stderr and exports spans over OTLP
+    ///
+    /// Logging honours `RUST_LOG` (default `info`). Call `shutdown()` on the
+    /// returned provider before exiting to flush pending spans.
+    pub fn init_otlp(service_name: &str, endpoint: &str) -> Result<TracerProvider> {
+        let provider = otlp_provider(service_name, endpoint)?;
+        let filter = tracing_subscriber::EnvFilter::try_from_default_env()
+            .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new("info"));
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(tracing_subscriber::fmt::layer())
+            .with(otlp_layer(&provider))
+            .try_init()
+            .map_err(|e| Error::Tracing(e.to_string()))?;
+        Ok(provider)
+    }
+}
@@ -1,10 +1,11 @@
 //! Turn and session management
 
 use crate::agent::AgentOutput;
-use crate::error::Result;
+use crate::error::{Error, Result};
 use crate::react::ReActTrace;
 use crate::types::{AgentId, SessionId, TokenUsage, TurnId, UserId};
 use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -39,14 +40,60 @@ pub struct Session {
     pub user_id: Option<UserId>,
     /// Current active agent
     pub current_agent: AgentId,
-    /// All turns in this session
-    pub turns: Vec<Turn>,
+    /// All turns in this session, shared with forks until either side adds a turn
+    pub turns: Arc<Vec<Turn>>,
     /// Session-level metadata
     pub metadata: SessionMetadata,
     /// Session state
     pub state: SessionState,
 }
 
+impl Session {
+    /// Create an active session with no turns
+    pub fn new(config: SessionConfig) -> Self {
+        let now = Utc::now();
+        Self {
+            id: SessionId::new(),
+            user_id: config.user_id,
+            current_agent: config.agent_id,
+            turns: Arc::new(Vec::new()),
+            metadata: SessionMetadata {
+                created_at: now,
+                updated_at: now,
+                custom: config.metadata,
+            },
+            state: SessionState::Active,
+        }
+    }
+
+    /// Append a turn, copying the history first if it is shared with a fork
+    pub fn push_turn(&mut self, turn: Turn) {
+        Arc::make_mut(&mut self.turns).push(turn);
+        self.metadata.updated_at = Utc::now();
+    }
+
+    /// Branch this session under a fresh ID, sharing the history so far
+    ///
+    /// The fork records its origin in `metadata.custom["forked_from"]`.
+    pub fn fork(&self) -> Self {
+        let now = Utc::now();
+        let mut custom = self.metadata.custom.clone();
+        custom.insert("forked_from".to_string(), serde_json::json!(self.id));
+        Self {
+            id: SessionId::new(),
+            user_id: self.user_id.clone(),
+            current_agent: self.current_agent,
+            turns: self.turns.clone(),
+            metadata: SessionMetadata {
+                created_at: now,
+                updated_at: now,
+                custom,
+            },
+            state: SessionState::Active,
+        }
+    }
+}
+
 /// Session metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SessionMetadata {
@@ -80,6 +127,8 @@ pub struct TurnManager {
     compaction_strategy: CompactionStrategy,
     /// Persistent storage backend
     storage: Option<Arc<dyn TurnStorage>>,
+    /// Live sessions
+    sessions: RwLock<HashMap<SessionId, Session>>,
 }
 
 impl TurnManager {
@@ -89,6 +138,7 @@ impl TurnManager {
             max_context_tokens,
             compaction_strategy: CompactionStrategy::SlidingWindow { keep_recent: 10 },
             storage: None,
+            sessions: RwLock::new(HashMap::new()),
         }
     }
 
@@ -105,8 +155,57 @@ impl TurnManager {
     }
 
     /// Create a new session
-    pub async fn create_session(&self, _config: SessionConfig) -> Result<Session> {
-        todo!("Implement session creation")
+    pub async fn create_session(&self, config: SessionConfig) -> Result<Session> {
+        let session = Session::new(config);
+        if let Some(storage) = &self.storage {
+            storage.store_session(&session)?;
+        }
+        self.sessions.write().insert(session.id, session.clone());
+        Ok(session)
+    }
+
+    /// Snapshot of a live session
+    pub fn session(&self, id: SessionId) -> Option<Session> {
+        self.sessions.read().get(&id).cloned()
+    }
+
+    /// Append a turn to a live session
+    pub fn record_turn(&self, session_id: SessionId, turn: Turn) -> Result<()> {
+        let mut sessions = self.sessions.write();
+        let session = sessions
+            .get_mut(&session_id)
+            .ok_or_else(|| Error::SessionNotFound(session_id.to_string()))?;
+        if let Some(storage) = &self.storage {
+            storage.store_turn(&turn)?;
+        }
+        session.push_turn(turn);
+        Ok(())
+    }
+
+    /// Branch a session so two different next turns can run from the same history
+    ///
+    /// The fork shares the existing turns with the original; each copies the
+    /// history only when it records its own next turn. Sessions that are not
+    /// live are loaded from storage.
+    pub fn fork_session(&self, session_id: SessionId) -> Result<SessionId> {
+        let original = match self.session(session_id) {
+            Some(session) => session,
+            None => self
+                .storage
+                .as_ref()
+                .map(|storage| storage.load_session(session_id))
+                .transpose()?
+                .flatten()
+                .ok_or_else(|| Error::SessionNotFound(session_id.to_string()))?,
+        };
+
+        let fork = original.fork();
+        if let Some(storage) = &self.storage {
+            storage.store_session(&fork)?;
+        }
+        let id = fork.id;
+        self.sessions.write().insert(id, fork);
+        Ok(id)
     }
 
     /// Process a new turn within a session
@@ -173,3 +272,53 @@ pub trait TurnStorage: Send + Sync {
     /// Load turns for a session
     fn load_turns(&self, session_id: SessionId) -> Result<Vec<Turn>>;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(session: &Session, input: &str) -> Turn {
+        Turn {
+            id: TurnId::new(),
+            session_id: session.id,
+            agent_id: session.current_agent,
+            input: input.to_string(),
+            output: AgentOutput::new(session.current_agent, format!("re: {}", input), ReActTrace::new()),
+            timestamp: Utc::now(),
+            token_usage: TokenUsage::default(),
+            trace: ReActTrace::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_fork_session_is_independent() {
+        let manager = TurnManager::new(8_000);
+        let session = manager
+            .create_session(SessionConfig {
+                agent_id: AgentId::new(),
+                user_id: None,
+                metadata: HashMap::new(),
+            })
+            .await
+            .unwrap();
+        manager.record_turn(session.id, turn(&session, "hello")).unwrap();
+        manager.record_turn(session.id, turn(&session, "scan /tmp")).unwrap();
+
+        let fork_id = manager.fork_session(session.id).unwrap();
+        assert_ne!(fork_id, session.id);
+        let original = manager.session(session.id).unwrap();
+        let fork = manager.session(fork_id).unwrap();
+        assert!(Arc::ptr_eq(&original.turns, &fork.turns));
+        assert_eq!(fork.metadata.custom["forked_from"], serde_json::json!(session.id));
+
+        manager.record_turn(fork_id, turn(&fork, "prompt B")).unwrap();
+        let original = manager.session(session.id).unwrap();
+        let fork = manager.session(fork_id).unwrap();
+        assert_eq!(original.turns.len(), 2);
+        assert_eq!(fork.turns.len(), 3);
+        assert_eq!(fork.turns[1].input, "scan /tmp");
+        assert_eq!(fork.turns[2].input, "prompt B");
+
+        assert!(manager.fork_session(SessionId::new()).is_err());
+    }
+}
@@ -0,0 +1,372 @@
+//! Typed tools without hand-written JSON schemas
+//!
+//! [`tool_params!`](crate::tool_params) declares a parameter struct whose
+//! field doc comments and types become the tool's JSON schema, and
+//! [`TypedTool`] receives those parameters already deserialized. Every
+//! `TypedTool` is also a [`Tool`], so it can be handed to an agent directly.
+//!
+//! ```rust
+//! use async_trait::async_trait;
+//! use spai::tools::{ToolContext, ToolOutput};
+//! use spai::typed_tool::TypedTool;
+//!
+//! spai::tool_params! {
+//!     /// Parameters for [`Greet`]
+//!     pub struct GreetParams {
+//!         /// Who to greet
+//!         pub name: String,
+//!         /// How many times to repeat the greeting
+//!         pub times: Option<u32>,
+//!     }
+//! }
+//!
+//! struct Greet;
+//!
+//! #[async_trait]
+//! impl TypedTool for Greet {
+//!     type Params = GreetParams;
+//!
+//!     fn id(&self) -> &str {
+//!         "greet"
+//!     }
+//!
+//!     fn description(&self) -> &str {
+//!         "Greet someone by name"
+//!     }
+//!
+//!     async fn run(&self, params: GreetParams, _ctx: &ToolContext) -> spai::Result<ToolOutput> {
+//!         let greeting = format!("Hello, {}!", params.name);
+//!         Ok(ToolOutput::success(vec![greeting; params.times.unwrap_or(1) as usize].join(" ")))
+//!     }
+//! }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::tools::{JsonSchema, Tool, ToolContext, ToolOutput};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+
+/// Maps a Rust type to its JSON schema
+pub trait SchemaType {
+    /// JSON schema for values of this type
+    fn schema() -> Value;
+
+    /// Whether a field of this type must be present
+    fn required() -> bool {
+        true
+    }
+}
+
+macro_rules! impl_schema_type {
+    ($json_type:literal => $($ty:ty),+) => {
+        $(
+            impl SchemaType for $ty {
+                fn schema() -> Value {
+                    json!({ "type": $json_type })
+                }
+            }
+        )+
+    };
+}
+
+impl_schema_type!("string" => String, char, std::path::PathBuf);
+impl_schema_type!("boolean" => bool);
+impl_schema_type!("integer" => i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+impl_schema_type!("number" => f32, f64);
+
+impl<T: SchemaType> SchemaType for Option<T> {
+    fn schema() -> Value {
+        T::schema()
+    }
+
+    fn required() -> bool {
+        false
+    }
+}
+
+impl<T: SchemaType> SchemaType for Vec<T> {
+    fn schema() -> Value {
+        json!({ "type": "array", "items": T::schema() })
+    }
+}
+
+impl<T: SchemaType> SchemaType for HashMap<String, T> {
+    fn schema() -> Value {
+        json!({ "type": "object", "additionalProperties": T::schema() })
+    }
+}
+
+impl SchemaType for Value {
+    fn schema() -> Value {
+        json!({})
+    }
+}
+
+/// Tool parameters that can describe themselves as a JSON schema
+///
+/// Usually implemented through [`tool_params!`](crate::tool_params).
+pub trait ToolParams: Sized + Send {
+    /// Object schema for the parameters
+    fn schema() -> JsonSchema;
+
+    /// Deserialize the parameters from a tool call's JSON arguments
+    fn from_value(params: Value) -> std::result::Result<Self, String>;
+}
+
+/// Build the schema for one field, using its doc comment as the description
+#[doc(hidden)]
+pub fn field_schema<T: SchemaType>(doc: &[&str]) -> (Value, bool) {
+    let mut schema = T::schema();
+    let description = doc
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if !description.is_empty() {
+        if let Some(object) = schema.as_object_mut() {
+            object.insert("description".to_string(), json!(description));
+        }
+    }
+    (schema, T::required())
+}
+
+/// Deserialize one field out of the parameter object
+#[doc(hidden)]
+pub fn take_field<T: SchemaType + DeserializeOwned>(
+    object: &mut Map<String, Value>,
+    name: &str,
+) -> std::result::Result<T, String> {
+    match object.remove(name) {
+        Some(value) => serde_json::from_value(value).map_err(|e| format!("field `{}`: {}", name, e)),
+        None if T::required() => Err(format!("missing field `{}`", name)),
+        None => serde_json::from_value(Value::Null).map_err(|e| format!("field `{}`: {}", name, e)),
+    }
+}
+
+/// Declare a tool parameter struct and derive its JSON schema
+///
+/// Field doc comments become property descriptions; `Option` fields are
+/// optional and everything else is required. Field attributes other than
+/// doc comments are not supported.
+#[macro_export]
+macro_rules! tool_params {
+    (
+        $(#[$meta:meta])*
+        $vis:vis struct $name:ident {
+            $(
+                $(#[doc = $doc:literal])*
+                $field_vis:vis $field:ident : $ty:ty
+            ),* $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone)]
+        $vis struct $name {
+            $(
+                $(#[doc = $doc])*
+                $field_vis $field: $ty,
+            )*
+        }
+
+        impl $crate::typed_tool::ToolParams for $name {
+            fn schema() -> $crate::tools::JsonSchema {
+                let mut properties = ::std::collections::HashMap::new();
+                let mut required = ::std::vec::Vec::new();
+                $(
+                    let (schema, is_required) =
+                        $crate::typed_tool::field_schema::<$ty>(&[$($doc),*]);
+                    properties.insert(stringify!($field).to_string(), schema);
+                    if is_required {
+                        required.push(stringify!($field).to_string());
+                    }
+                )*
+                $crate::tools::JsonSchema::object(properties).with_required(required)
+            }
+
+            fn from_value(
+                params: $crate::__private::serde_json::Value,
+            ) -> ::std::result::Result<Self, ::std::string::String> {
+                let mut object = match params {
+                    $crate::__private::serde_json::Value::Object(object) => object,
+                    $crate::__private::serde_json::Value::Null => ::std::default::Default::default(),
+                    other => {
+                        return ::std::result::Result::Err(::std::format!(
+                            "expected an object, got {}",
+                            other
+                        ))
+                    }
+                };
+                ::std::result::Result::Ok(Self {
+                    $(
+                        $field: $crate::typed_tool::take_field::<$ty>(&mut object, stringify!($field))?,
+                    )*
+                })
+            }
+        }
+    };
+}
+
+/// A tool that receives typed, already-validated parameters
+///
+/// Implementors get a [`Tool`] implementation for free: the schema comes from
+/// [`TypedTool::Params`] and bad parameters are rejected before `run` is called.
+#[async_trait]
+pub trait TypedTool: Send + Sync {
+    /// Parameter type, usually declared with [`tool_params!`](crate::tool_params)
+    type Params: ToolParams;
+
+    /// Unique identifier for this tool
+    fn id(&self) -> &str;
+
+    /// Human-readable name (defaults to the ID)
+    fn name(&self) -> &str {
+        self.id()
+    }
+
+    /// Description for LLM function calling
+    fn description(&self) -> &str;
+
+    /// Run the tool
+    async fn run(&self, params: Self::Params, ctx: &ToolContext) -> Result<ToolOutput>;
+
+    /// Key identifying a side-effecting call (see [`Tool::idempotency_key`])
+    fn idempotency_key(&self, _params: &Value) -> Option<String> {
+        None
+    }
+
+    /// Whether results may be memoized (see [`Tool::cacheable`])
+    fn cacheable(&self) -> bool {
+        false
+    }
+
+    /// How long a cached result stays valid (see [`Tool::cache_ttl`])
+    fn cache_ttl(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(300)
+    }
+}
+
+#[async_trait]
+impl<T: TypedTool> Tool for T {
+    fn id(&self) -> &str {
+        TypedTool::id(self)
+    }
+
+    fn name(&self) -> &str {
+        TypedTool::name(self)
+    }
+
+    fn description(&self) -> &str {
+        TypedTool::description(self)
+    }
+
+    fn input_schema(&self) -> JsonSchema {
+        T::Params::schema()
+    }
+
+    async fn execute(&self, params: Value, ctx: &ToolContext) -> Result<ToolOutput> {
+        let params = parse_params::<T::Params>(TypedTool::id(self), params)?;
+        self.run(params, ctx).await
+    }
+
+    fn validate(&self, params: &Value) -> Result<()> {
+        parse_params::<T::Params>(TypedTool::id(self), params.clone()).map(|_| ())
+    }
+
+    fn idempotency_key(&self, params: &Value) -> Option<String> {
+        TypedTool::idempotency_key(self, params)
+    }
+
+    fn cacheable(&self) -> bool {
+        TypedTool::cacheable(self)
+    }
+
+    fn cache_ttl(&self) -> std::time::Duration {
+        TypedTool::cache_ttl(self)
+    }
+}
+
+fn parse_params<P: ToolParams>(tool_id: &str, params: Value) -> Result<P> {
+    P::from_value(params).map_err(|e| Error::tool_execution(tool_id, format!("Invalid parameters: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::AgentId;
+
+    crate::tool_params! {
+        struct LookupParams {
+            /// Host to resolve
+            host: String,
+            /// Record types to query
+            /// (defaults to A)
+            record_types: Option<Vec<String>>,
+            /// Timeout in seconds
+            timeout: u32,
+        }
+    }
+
+    struct Lookup;
+
+    #[async_trait]
+    impl TypedTool for Lookup {
+        type Params = LookupParams;
+
+        fn id(&self) -> &str {
+            "lookup"
+        }
+
+        fn description(&self) -> &str {
+            "Resolve a host"
+        }
+
+        async fn run(&self, params: LookupParams, _ctx: &ToolContext) -> Result<ToolOutput> {
+            let types = params.record_types.unwrap_or_else(|| vec!["A".to_string()]);
+            Ok(ToolOutput::success(format!(
+                "{} {} ({}s)",
+                params.host,
+                types.join(","),
+                params.timeout
+            )))
+        }
+    }
+
+    #[test]
+    fn test_schema_from_params() {
+        let schema = LookupParams::schema();
+        let properties = schema.properties.unwrap();
+        assert_eq!(properties["host"], json!({"type": "string", "description": "Host to resolve"}));
+        assert_eq!(
+            properties["record_types"],
+            json!({
+                "type": "array",
+                "items": {"type": "string"},
+                "description": "Record types to query (defaults to A)"
+            })
+        );
+        assert_eq!(properties["timeout"]["type"], "integer");
+
+        let mut required = schema.required.unwrap();
+        required.sort();
+        assert_eq!(required, vec!["host", "timeout"]);
+    }
+
+    #[tokio::test]
+    async fn test_typed_tool_execute() {
+        let tool: std::sync::Arc<dyn Tool> = std::sync::Arc::new(Lookup);
+        let ctx = ToolContext::new(AgentId::new());
+
+        let output = tool
+            .execute(json!({"host": "example.com", "timeout": 5}), &ctx)
+            .await
+            .unwrap();
+        assert_eq!(output.content, "example.com A (5s)");
+
+        let err = tool.execute(json!({"host": "example.com"}), &ctx).await.unwrap_err();
+        assert!(err.to_string().contains("timeout"));
+        assert!(tool.validate(&json!({"host": 1, "timeout": 5})).is_err());
+    }
+}
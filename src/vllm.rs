@@ -152,10 +152,22 @@ impl VllmClient {
     }
 }
 
+/// Strip the OpenRouter-only routing fields from a request
+///
+/// A vLLM server hosts a single model, so there is nothing to fall back to
+/// and no upstream provider to prefer.
+fn chat_request(mut request: CompletionRequest, stream: bool) -> CompletionRequest {
+    request.fallback_models.clear();
+    request.provider = None;
+    request.stream = stream;
+    request
+}
+
 #[async_trait]
 impl LlmClient for VllmClient {
     async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
         let url = format!("{}/v1/chat/completions", self.config.base_url);
+        let request = chat_request(request, false);
 
         let mut http_request = self.client.post(&url).json(&request);
 
@@ -184,8 +196,7 @@ impl LlmClient for VllmClient {
     async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
         let url = format!("{}/v1/chat/completions", self.config.base_url);
 
-        let mut request_with_stream = request;
-        request_with_stream.stream = true;
+        let request_with_stream = chat_request(request, true);
 
         let mut http_request = self.client.post(&url).json(&request_with_stream);
 
@@ -217,6 +228,10 @@ impl LlmClient for VllmClient {
     fn endpoint(&self) -> &str {
         &self.config.base_url
     }
+
+    async fn model_ids(&self) -> Result<Vec<String>> {
+        Ok(self.get_models().await?.data.into_iter().map(|m| m.id).collect())
+    }
 }
 
 /// vLLM health check response
@@ -251,6 +266,8 @@ pub struct ModelInfo {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::config::ProviderPreferences;
+    use crate::openrouter::Message;
 
     #[test]
     fn test_vllm_config_from_env() {
@@ -269,4 +286,19 @@ mod tests {
         assert_eq!(config.timeout, Duration::from_secs(60));
         assert_eq!(config.api_key, Some("test-key".to_string()));
     }
+
+    #[test]
+    fn test_chat_request_drops_openrouter_routing() {
+        let request = CompletionRequest::new("qwen", vec![Message::user("hi")])
+            .with_fallback_models(vec!["other".to_string()])
+            .with_provider(ProviderPreferences {
+                preferred: vec!["together".to_string()],
+                ..Default::default()
+            });
+
+        let body = serde_json::to_value(chat_request(request, true)).unwrap();
+        assert!(body.get("provider").is_none(), "{}", body);
+        assert!(body.get("models").is_none(), "{}", body);
+        assert_eq!(body["stream"], true);
+    }
 }
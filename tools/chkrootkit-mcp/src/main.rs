@@ -7,9 +7,9 @@ use rmcp::{
 };
 use rmcp::serde_json;
 use rmcp::model::ErrorData;
-use std::process::Command;
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use tool_common::sudo::{check_passwordless_sudo, scan_command, sudo_required_message};
 use tracing::info;
 
 #[derive(Clone)]
@@ -27,7 +27,7 @@ impl ChkrootkitServer {
         }
     }
 
-    #[tool(description = "Run chkrootkit with sudo -x and summarize any findings")]
+    #[tool(description = "Run chkrootkit -x with non-interactive sudo (set use_sudo=false when running as root) and summarize any findings")]
     async fn chkrootkit_scan(
         &self,
         params: serde_json::Map<String, serde_json::Value>,
@@ -47,17 +47,27 @@ impl ChkrootkitServer {
             })
             .unwrap_or_else(|| vec!["-x".to_string()]);
 
-        // Run chkrootkit with sudo
-        let mut cmd = Command::new("sudo");
-        cmd.arg("chkrootkit");
-        cmd.args(&flags);
+        let use_sudo = params
+            .get("use_sudo")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        // Fail fast instead of hanging on a sudo password prompt
+        if use_sudo {
+            if let Err(reason) = check_passwordless_sudo("chkrootkit") {
+                return Ok(CallToolResult::error(vec![Content::text(
+                    sudo_required_message("chkrootkit", &reason),
+                )]));
+            }
+        }
 
+        let mut cmd = scan_command("chkrootkit", &flags, use_sudo);
         let output = cmd.output();
         let output = match output {
             Ok(out) => out,
             Err(err) => {
                 return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Failed to execute 'sudo chkrootkit': {}. Ensure:\n\
+                    "Failed to execute chkrootkit: {}. Ensure:\n\
                      1. chkrootkit is installed (apt-get install chkrootkit)\n\
                      2. sudo is available\n\
                      3. User has passwordless sudo access for chkrootkit OR run this as root",
@@ -106,8 +116,10 @@ impl ServerHandler for ChkrootkitServer {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             instructions: Some(
-                "Run 'sudo chkrootkit -x' and return a concise summary of any flagged lines. \
-                 Requires passwordless sudo or root access.".into(),
+                "Run 'chkrootkit -x' and return a concise summary of any flagged lines. \
+                 Runs through 'sudo -n', which fails with setup steps instead of prompting \
+                 when passwordless sudo is not configured; when the server runs as root, \
+                 pass \"use_sudo\": false.".into(),
             ),
             capabilities: ServerCapabilities::builder().enable_tools().build(),
             ..Default::default()
@@ -13,8 +13,10 @@ use rmcp::{
 use rmcp::model::ErrorData;
 use rmcp::serde_json;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use sysinfo::{Pid, System};
+use sysinfo::{Pid, Process, System};
 use tokio::sync::Mutex;
 use tracing::info;
 
@@ -49,8 +51,76 @@ struct SystemStats {
     swap_usage_percent: f32,
     uptime_seconds: u64,
     process_count: usize,
+    sample_count: usize,
+    interval_ms: u64,
+    cpu: MetricSummary,
+    memory: MetricSummary,
+    per_core_cpu: Vec<MetricSummary>,
+    samples: Vec<StatsSample>,
 }
 
+/// One point in the sampled CPU/memory series
+#[derive(Debug, Serialize, Deserialize)]
+struct StatsSample {
+    elapsed_ms: u64,
+    cpu_usage: f32,
+    per_core_cpu: Vec<f32>,
+    memory_percent: f32,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+struct MetricSummary {
+    min: f32,
+    max: f32,
+    avg: f32,
+}
+
+/// How urgently a suspicious-process finding should be looked at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Severity {
+    Low,
+    Medium,
+    High,
+}
+
+/// One reason a process looks suspicious
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Finding {
+    severity: Severity,
+    reason: String,
+}
+
+impl Finding {
+    fn new(severity: Severity, reason: impl Into<String>) -> Self {
+        Self {
+            severity,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// What `/proc/<pid>/exe` says about a process's executable
+#[derive(Debug, Clone, PartialEq)]
+enum Executable {
+    /// Executable on disk
+    Path(PathBuf),
+    /// Executable was deleted after the process started
+    Deleted(PathBuf),
+    /// Process has no executable path (and is not a kernel thread)
+    Missing,
+    /// Could not be determined, e.g. another user's process without root
+    Unknown,
+}
+
+/// Directories that legitimate binaries are rarely run from
+const WRITABLE_EXE_DIRS: [&str; 3] = ["/tmp", "/dev/shm", "/run"];
+
+/// Upper bound on samples per call, to keep a single tool call short
+const MAX_SAMPLES: u64 = 120;
+/// Upper bound on the sampling interval
+const MAX_INTERVAL_MS: u64 = 10_000;
+
 #[tool_router]
 impl HtopServer {
     fn new() -> Self {
@@ -87,11 +157,7 @@ impl HtopServer {
             .iter()
             .map(|(pid, process)| {
                 let memory = process.memory();
-                let memory_percent = if total_memory > 0 {
-                    (memory as f32 / total_memory as f32) * 100.0
-                } else {
-                    0.0
-                };
+                let memory_percent = memory_percent(memory, total_memory);
 
                 ProcessInfo {
                     pid: pid.as_u32(),
@@ -180,11 +246,7 @@ impl HtopServer {
 
         let total_memory = sys.total_memory();
         let memory = process.memory();
-        let memory_percent = if total_memory > 0 {
-            (memory as f32 / total_memory as f32) * 100.0
-        } else {
-            0.0
-        };
+        let memory_percent = memory_percent(memory, total_memory);
 
         let info = ProcessInfo {
             pid: sysinfo_pid.as_u32(),
@@ -230,26 +292,66 @@ impl HtopServer {
         ]))
     }
 
-    #[tool(description = "Get overall system statistics including CPU, memory, swap usage, and process count")]
+    #[tool(description = "Get overall system statistics including CPU, memory, swap usage, and process count. Optional 'samples' and 'interval_ms' collect a time series with min/max/avg and per-core CPU.")]
     async fn get_system_stats(
         &self,
-        _params: serde_json::Map<String, serde_json::Value>,
+        params: serde_json::Map<String, serde_json::Value>,
     ) -> Result<CallToolResult, ErrorData> {
-        let _guard = self.inner.lock().await;
+        let samples = params
+            .get("samples")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(1)
+            .clamp(1, MAX_SAMPLES) as usize;
+        let min_interval_ms = sysinfo::MINIMUM_CPU_UPDATE_INTERVAL.as_millis() as u64;
+        let interval_ms = params
+            .get("interval_ms")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(min_interval_ms)
+            .clamp(min_interval_ms, MAX_INTERVAL_MS);
+
+        let mut sys = {
+            let _guard = self.inner.lock().await;
+            let mut sys = System::new_all();
+            sys.refresh_all();
+            sys
+        };
 
-        let mut sys = System::new_all();
-        sys.refresh_all();
+        // CPU usage is a delta between refreshes, so every sample waits one interval.
+        // The lock is only held while refreshing, so a long series doesn't stall other calls.
+        let start = std::time::Instant::now();
+        let mut series = Vec::with_capacity(samples);
+        for _ in 0..samples {
+            tokio::time::sleep(std::time::Duration::from_millis(interval_ms)).await;
+            let _guard = self.inner.lock().await;
+            sys.refresh_cpu_usage();
+            sys.refresh_memory();
+            series.push(StatsSample {
+                elapsed_ms: start.elapsed().as_millis() as u64,
+                cpu_usage: sys.global_cpu_usage(),
+                per_core_cpu: sys.cpus().iter().map(|cpu| cpu.cpu_usage()).collect(),
+                memory_percent: memory_percent(sys.used_memory(), sys.total_memory()),
+            });
+        }
+
+        let cpu = summarize(&series.iter().map(|s| s.cpu_usage).collect::<Vec<_>>());
+        let memory = summarize(&series.iter().map(|s| s.memory_percent).collect::<Vec<_>>());
+        let per_core_cpu: Vec<MetricSummary> = (0..sys.cpus().len())
+            .map(|core| {
+                summarize(
+                    &series
+                        .iter()
+                        .filter_map(|s| s.per_core_cpu.get(core).copied())
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect();
 
         let total_memory = sys.total_memory();
         let used_memory = sys.used_memory();
         let total_swap = sys.total_swap();
         let used_swap = sys.used_swap();
 
-        let memory_percent = if total_memory > 0 {
-            (used_memory as f32 / total_memory as f32) * 100.0
-        } else {
-            0.0
-        };
+        let memory_percent = memory_percent(used_memory, total_memory);
 
         let swap_percent = if total_swap > 0 {
             (used_swap as f32 / total_swap as f32) * 100.0
@@ -268,9 +370,15 @@ impl HtopServer {
             swap_usage_percent: swap_percent,
             uptime_seconds: System::uptime(),
             process_count: sys.processes().len(),
+            sample_count: series.len(),
+            interval_ms,
+            cpu,
+            memory,
+            per_core_cpu,
+            samples: series,
         };
 
-        let output = format!(
+        let mut output = format!(
             "System Statistics:\n\n\
              CPU:\n\
              - Total Usage:    {:.1}%\n\
@@ -297,6 +405,29 @@ impl HtopServer {
             stats.process_count
         );
 
+        if stats.sample_count > 1 {
+            output.push_str(&format!(
+                "\nWindow ({} samples every {} ms):\n\
+                 - CPU:    min {:.1}% / max {:.1}% / avg {:.1}%\n\
+                 - Memory: min {:.1}% / max {:.1}% / avg {:.1}%\n\
+                 - Per-core max: {}\n",
+                stats.sample_count,
+                stats.interval_ms,
+                stats.cpu.min,
+                stats.cpu.max,
+                stats.cpu.avg,
+                stats.memory.min,
+                stats.memory.max,
+                stats.memory.avg,
+                stats
+                    .per_core_cpu
+                    .iter()
+                    .map(|core| format!("{:.0}%", core.max))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            ));
+        }
+
         let json_data = serde_json::to_string_pretty(&stats)
             .unwrap_or_else(|_| "{}".to_string());
 
@@ -306,7 +437,7 @@ impl HtopServer {
         ]))
     }
 
-    #[tool(description = "Identify potentially suspicious processes based on heuristics (high CPU/memory usage, unusual names, hidden processes)")]
+    #[tool(description = "Identify potentially suspicious processes based on heuristics (high CPU/memory usage, unusual names, hidden processes, executables in /tmp, /dev/shm or /run, deleted or missing executables). Findings carry a low/medium/high severity and the JSON output is grouped by severity.")]
     async fn find_suspicious_processes(
         &self,
         params: serde_json::Map<String, serde_json::Value>,
@@ -333,34 +464,25 @@ impl HtopServer {
             let name = process.name().to_string_lossy().to_string();
             let cpu = process.cpu_usage();
             let memory = process.memory();
-            let mem_percent = if total_memory > 0 {
-                (memory as f32 / total_memory as f32) * 100.0
-            } else {
-                0.0
-            };
+            let mem_percent = memory_percent(memory, total_memory);
 
-            let mut reasons = Vec::new();
+            let mut findings = Vec::new();
 
-            // Heuristic checks
+            // Resource usage alone is common for legitimate workloads
             if cpu > high_cpu_threshold {
-                reasons.push(format!("High CPU usage: {:.1}%", cpu));
+                findings.push(Finding::new(Severity::Low, format!("High CPU usage: {:.1}%", cpu)));
             }
             if mem_percent > high_memory_threshold {
-                reasons.push(format!("High memory usage: {:.1}%", mem_percent));
-            }
-
-            // Check for suspicious naming patterns
-            if name.starts_with('.') && name.len() > 1 {
-                reasons.push("Hidden process (starts with '.')".to_string());
-            }
-            if name.contains("tmp") || name.contains("...") {
-                reasons.push(format!("Suspicious name pattern: '{}'", name));
-            }
-            if name.len() > 4 && name.chars().all(|c| c.is_ascii_hexdigit()) {
-                reasons.push("Process name is all hexadecimal characters".to_string());
+                findings.push(Finding::new(
+                    Severity::Low,
+                    format!("High memory usage: {:.1}%", mem_percent),
+                ));
             }
+            findings.extend(name_findings(&name));
+            findings.extend(executable_findings(&resolve_executable(*pid, process)));
 
-            if !reasons.is_empty() {
+            if !findings.is_empty() {
+                findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
                 suspicious_processes.push((
                     ProcessInfo {
                         pid: pid.as_u32(),
@@ -376,11 +498,19 @@ impl HtopServer {
                             .unwrap_or_else(|| "N/A".to_string()),
                         cmd: process.cmd().iter().map(|s| s.to_string_lossy().to_string()).collect(),
                     },
-                    reasons,
+                    findings,
                 ));
             }
         }
 
+        // Most severe first; findings are sorted, so the first is the highest
+        suspicious_processes.sort_by(|(a, a_findings), (b, b_findings)| {
+            b_findings[0]
+                .severity
+                .cmp(&a_findings[0].severity)
+                .then(a.pid.cmp(&b.pid))
+        });
+
         let mut output = format!(
             "Suspicious Process Detection (CPU>{:.0}%, MEM>{:.0}%):\n\n",
             high_cpu_threshold, high_memory_threshold
@@ -394,33 +524,23 @@ impl HtopServer {
                 suspicious_processes.len()
             ));
 
-            for (proc, reasons) in &suspicious_processes {
+            for (proc, findings) in &suspicious_processes {
                 output.push_str(&format!(
-                    "PID {}: {} (CPU: {:.1}%, MEM: {} MB / {:.1}%)\n",
-                    proc.pid, proc.name, proc.cpu_usage, proc.memory_mb, proc.memory_percent
+                    "[{:?}] PID {}: {} (CPU: {:.1}%, MEM: {} MB / {:.1}%)\n",
+                    findings[0].severity, proc.pid, proc.name, proc.cpu_usage, proc.memory_mb, proc.memory_percent
                 ));
                 output.push_str(&format!("  Executable: {}\n", proc.exe_path));
                 output.push_str(&format!("  Command: {}\n", proc.cmd.join(" ")));
                 output.push_str("  Reasons:\n");
-                for reason in reasons {
-                    output.push_str(&format!("    - {}\n", reason));
+                for finding in findings {
+                    output.push_str(&format!("    - [{:?}] {}\n", finding.severity, finding.reason));
                 }
                 output.push('\n');
             }
         }
 
-        let json_data = serde_json::to_string_pretty(
-            &suspicious_processes
-                .iter()
-                .map(|(p, r)| {
-                    serde_json::json!({
-                        "process": p,
-                        "reasons": r
-                    })
-                })
-                .collect::<Vec<_>>(),
-        )
-        .unwrap_or_else(|_| "[]".to_string());
+        let json_data = serde_json::to_string_pretty(&group_by_severity(&suspicious_processes))
+            .unwrap_or_else(|_| "{}".to_string());
 
         Ok(CallToolResult::success(vec![
             Content::text(output),
@@ -461,6 +581,145 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Findings based on the process name alone
+fn name_findings(name: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    if name.starts_with('.') && name.len() > 1 {
+        findings.push(Finding::new(Severity::Medium, "Hidden process (starts with '.')"));
+    }
+    if name.contains("tmp") || name.contains("...") {
+        findings.push(Finding::new(
+            Severity::Medium,
+            format!("Suspicious name pattern: '{}'", name),
+        ));
+    }
+    if name.len() > 4 && name.chars().all(|c| c.is_ascii_hexdigit()) {
+        findings.push(Finding::new(
+            Severity::Medium,
+            "Process name is all hexadecimal characters",
+        ));
+    }
+    findings
+}
+
+/// Findings based on where the process's executable lives
+fn executable_findings(exe: &Executable) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let path = match exe {
+        Executable::Path(path) => path,
+        Executable::Deleted(path) => {
+            findings.push(Finding::new(
+                Severity::High,
+                format!("Executable deleted after start: {}", path.display()),
+            ));
+            path
+        }
+        Executable::Missing => {
+            findings.push(Finding::new(Severity::Medium, "No executable path"));
+            return findings;
+        }
+        Executable::Unknown => return findings,
+    };
+
+    if let Some(dir) = WRITABLE_EXE_DIRS.iter().find(|dir| path.starts_with(dir)) {
+        findings.push(Finding::new(
+            Severity::High,
+            format!("Executable in world-writable location {}: {}", dir, path.display()),
+        ));
+    }
+    findings
+}
+
+/// Read the process's executable link, falling back to sysinfo off Linux
+fn resolve_executable(pid: Pid, process: &Process) -> Executable {
+    let proc_dir = PathBuf::from(format!("/proc/{}", pid.as_u32()));
+    if !Path::new("/proc/self/exe").exists() {
+        return match process.exe() {
+            Some(path) => parse_exe_link(path),
+            None => Executable::Unknown,
+        };
+    }
+
+    match std::fs::read_link(proc_dir.join("exe")) {
+        Ok(target) => parse_exe_link(&target),
+        Err(e) if e.kind() == std::io::ErrorKind::PermissionDenied => Executable::Unknown,
+        // Kernel threads have neither an executable nor a command line
+        Err(_) if process.cmd().is_empty() => Executable::Unknown,
+        // The process may have exited since the snapshot was taken
+        Err(_) if !proc_dir.exists() => Executable::Unknown,
+        Err(_) => Executable::Missing,
+    }
+}
+
+/// Classify an executable link, which the kernel suffixes with ` (deleted)`
+fn parse_exe_link(target: &Path) -> Executable {
+    let target = target.to_string_lossy();
+    match target.strip_suffix(" (deleted)") {
+        Some(path) => Executable::Deleted(PathBuf::from(path)),
+        None if target.is_empty() => Executable::Missing,
+        None => Executable::Path(PathBuf::from(target.as_ref())),
+    }
+}
+
+/// JSON output with each process listed under its most severe finding
+fn group_by_severity(processes: &[(ProcessInfo, Vec<Finding>)]) -> serde_json::Value {
+    let mut groups: BTreeMap<Severity, Vec<serde_json::Value>> = BTreeMap::new();
+    for severity in [Severity::High, Severity::Medium, Severity::Low] {
+        groups.insert(severity, Vec::new());
+    }
+    for (process, findings) in processes {
+        let Some(highest) = findings.iter().map(|f| f.severity).max() else {
+            continue;
+        };
+        groups.entry(highest).or_default().push(serde_json::json!({
+            "process": process,
+            "findings": findings,
+        }));
+    }
+
+    let counts: serde_json::Map<String, serde_json::Value> = groups
+        .iter()
+        .map(|(severity, entries)| (severity_key(*severity).to_string(), entries.len().into()))
+        .collect();
+    let mut output = serde_json::Map::new();
+    output.insert("counts".to_string(), counts.into());
+    for (severity, entries) in groups.into_iter().rev() {
+        output.insert(severity_key(severity).to_string(), entries.into());
+    }
+    output.into()
+}
+
+fn severity_key(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Low => "low",
+        Severity::Medium => "medium",
+        Severity::High => "high",
+    }
+}
+
+/// Min/max/avg of a sampled series (all zero when empty)
+fn summarize(values: &[f32]) -> MetricSummary {
+    if values.is_empty() {
+        return MetricSummary::default();
+    }
+
+    let min = values.iter().copied().fold(f32::INFINITY, f32::min);
+    let max = values.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let avg = values.iter().sum::<f32>() / values.len() as f32;
+    MetricSummary { min, max, avg }
+}
+
+/// Percentage of physical RAM used, clamped to [0, 100]
+///
+/// Both arguments are in bytes: sysinfo reports `Process::memory()` as the
+/// resident set size and `System::total_memory()` as total physical RAM.
+fn memory_percent(rss_bytes: u64, total_bytes: u64) -> f32 {
+    if total_bytes == 0 {
+        return 0.0;
+    }
+    ((rss_bytes as f64 / total_bytes as f64) * 100.0).clamp(0.0, 100.0) as f32
+}
+
 fn truncate_string(s: &str, max_len: usize) -> String {
     if s.len() <= max_len {
         s.to_string()
@@ -468,3 +727,78 @@ fn truncate_string(s: &str, max_len: usize) -> String {
         format!("{}...", &s[..max_len.saturating_sub(3)])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize() {
+        assert_eq!(summarize(&[]), MetricSummary::default());
+        assert_eq!(
+            summarize(&[10.0, 40.0, 25.0]),
+            MetricSummary { min: 10.0, max: 40.0, avg: 25.0 }
+        );
+    }
+
+    #[test]
+    fn test_executable_findings() {
+        assert_eq!(
+            parse_exe_link(Path::new("/tmp/.x/miner (deleted)")),
+            Executable::Deleted(PathBuf::from("/tmp/.x/miner"))
+        );
+        assert_eq!(
+            parse_exe_link(Path::new("/usr/bin/sshd")),
+            Executable::Path(PathBuf::from("/usr/bin/sshd"))
+        );
+
+        let deleted = executable_findings(&Executable::Deleted(PathBuf::from("/dev/shm/kworker")));
+        assert_eq!(deleted.len(), 2);
+        assert!(deleted.iter().all(|f| f.severity == Severity::High));
+
+        assert!(executable_findings(&Executable::Path(PathBuf::from("/usr/bin/sshd"))).is_empty());
+        assert!(executable_findings(&Executable::Path(PathBuf::from("/runner/bin/app"))).is_empty());
+        assert_eq!(executable_findings(&Executable::Path(PathBuf::from("/run/user/1000/x")))[0].severity, Severity::High);
+        assert_eq!(executable_findings(&Executable::Missing)[0].severity, Severity::Medium);
+        assert!(executable_findings(&Executable::Unknown).is_empty());
+    }
+
+    #[test]
+    fn test_group_by_severity() {
+        let process = |pid| ProcessInfo {
+            pid,
+            name: "x".to_string(),
+            cpu_usage: 0.0,
+            memory_mb: 0,
+            memory_percent: 0.0,
+            status: "Run".to_string(),
+            parent_pid: None,
+            exe_path: "N/A".to_string(),
+            cmd: Vec::new(),
+        };
+        let grouped = group_by_severity(&[
+            (process(1), vec![Finding::new(Severity::Low, "busy")]),
+            (
+                process(2),
+                vec![
+                    Finding::new(Severity::High, "deleted"),
+                    Finding::new(Severity::Low, "busy"),
+                ],
+            ),
+        ]);
+
+        assert_eq!(grouped["counts"], serde_json::json!({"high": 1, "medium": 0, "low": 1}));
+        assert_eq!(grouped["high"][0]["process"]["pid"], 2);
+        assert_eq!(grouped["high"][0]["findings"][1]["severity"], "low");
+        assert_eq!(grouped["low"][0]["process"]["pid"], 1);
+    }
+
+    #[test]
+    fn test_memory_percent() {
+        const GIB: u64 = 1024 * 1024 * 1024;
+        assert_eq!(memory_percent(GIB, 16 * GIB), 6.25);
+        assert_eq!(memory_percent(512 * 1024 * 1024, 2 * GIB), 25.0);
+        assert_eq!(memory_percent(GIB, 0), 0.0);
+        assert_eq!(memory_percent(32 * GIB, 16 * GIB), 100.0);
+    }
+}
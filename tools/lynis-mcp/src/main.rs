@@ -7,9 +7,9 @@ use rmcp::{
 };
 use rmcp::serde_json;
 use rmcp::model::ErrorData;
-use std::process::Command;
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use tool_common::sudo::{check_passwordless_sudo, scan_command, sudo_required_message};
 use tracing::info;
 
 #[derive(Clone)]
@@ -27,7 +27,7 @@ impl LynisServer {
         }
     }
 
-    #[tool(description = "Run lynis audit system with sudo and summarize findings")]
+    #[tool(description = "Run lynis audit system with non-interactive sudo (set use_sudo=false when running as root) and summarize findings. Returns a JSON report with the numeric hardening_index; set fail_below (0-100) to flag the result as a warning when the index is lower or missing")]
     async fn lynis_scan(
         &self,
         params: serde_json::Map<String, serde_json::Value>,
@@ -51,17 +51,32 @@ impl LynisServer {
                 "--quick".to_string(),
             ]);
 
-        // Run lynis with sudo
-        let mut cmd = Command::new("sudo");
-        cmd.arg("lynis");
-        cmd.args(&flags);
+        let use_sudo = params
+            .get("use_sudo")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
 
+        let fail_below = params
+            .get("fail_below")
+            .and_then(|v| v.as_u64())
+            .map(|v| v.min(100) as u32);
+
+        // Fail fast instead of hanging on a sudo password prompt
+        if use_sudo {
+            if let Err(reason) = check_passwordless_sudo("lynis") {
+                return Ok(CallToolResult::error(vec![Content::text(
+                    sudo_required_message("lynis", &reason),
+                )]));
+            }
+        }
+
+        let mut cmd = scan_command("lynis", &flags, use_sudo);
         let output = cmd.output();
         let output = match output {
             Ok(out) => out,
             Err(err) => {
                 return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Failed to execute 'sudo lynis': {}. Ensure:\n\
+                    "Failed to execute lynis: {}. Ensure:\n\
                      1. lynis is installed (apt-get install lynis)\n\
                      2. sudo is available\n\
                      3. User has passwordless sudo access for lynis OR run this as root",
@@ -73,17 +88,24 @@ impl LynisServer {
         let stdout = String::from_utf8_lossy(&output.stdout).to_string();
         let stderr = String::from_utf8_lossy(&output.stderr).to_string();
         let (summary, findings, suggestions, hardening_index) = summarize_lynis(&stdout);
+        let threshold = fail_below.map(|threshold| check_threshold(hardening_index, threshold));
 
-        let mut content = vec![Content::text(summary)];
+        let mut content = vec![Content::text(summary.clone())];
 
         // Add hardening index if found
         if let Some(index) = hardening_index {
             content.push(Content::text(format!(
-                "🛡️  System Hardening Index: {}",
+                "🛡️  System Hardening Index: {}/100",
                 index
             )));
         }
 
+        if let Some((passed, message)) = &threshold {
+            if !passed {
+                content.push(Content::text(format!("⚠️  {}", message)));
+            }
+        }
+
         if !findings.is_empty() {
             let bullet_list = findings.join("\n- ");
             content.push(Content::text(format!(
@@ -100,6 +122,24 @@ impl LynisServer {
             )));
         }
 
+        let report = serde_json::json!({
+            "status": match &threshold {
+                Some((false, _)) => "warning",
+                _ if output.status.success() => "ok",
+                _ => "error",
+            },
+            "summary": summary,
+            "hardening_index": hardening_index,
+            "fail_below": fail_below,
+            "threshold_passed": threshold.as_ref().map(|(passed, _)| *passed),
+            "warning_count": findings.len(),
+            "suggestion_count": suggestions.len(),
+            "findings": findings,
+            "suggestions": suggestions,
+        });
+        let report = serde_json::to_string_pretty(&report).unwrap_or_else(|_| "{}".to_string());
+        content.push(Content::text(format!("JSON report:\n{}", report)));
+
         if !stdout.trim().is_empty() {
             content.push(Content::text(format!(
                 "lynis stdout (truncated):\n{}",
@@ -126,8 +166,10 @@ impl ServerHandler for LynisServer {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             instructions: Some(
-                "Run 'sudo lynis audit system' and return a comprehensive security assessment. \
-                 Requires passwordless sudo or root access.".into(),
+                "Run 'lynis audit system' and return a comprehensive security assessment. \
+                 Runs through 'sudo -n', which fails with setup steps instead of prompting \
+                 when passwordless sudo is not configured; when the server runs as root, \
+                 pass \"use_sudo\": false.".into(),
             ),
             capabilities: ServerCapabilities::builder().enable_tools().build(),
             ..Default::default()
@@ -151,7 +193,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn summarize_lynis(stdout: &str) -> (String, Vec<String>, Vec<String>, Option<String>) {
+fn summarize_lynis(stdout: &str) -> (String, Vec<String>, Vec<String>, Option<u32>) {
     let mut findings = Vec::new();
     let mut suggestions = Vec::new();
     let mut warning_count = 0;
@@ -167,12 +209,8 @@ fn summarize_lynis(stdout: &str) -> (String, Vec<String>, Vec<String>, Option<St
         let normalized = trimmed.to_lowercase();
 
         // Extract hardening index
-        if normalized.contains("hardening index") && normalized.contains("[") {
-            if let Some(start) = trimmed.find('[') {
-                if let Some(end) = trimmed.find(']') {
-                    hardening_index = Some(trimmed[start..=end].to_string());
-                }
-            }
+        if let Some(index) = parse_hardening_index(&normalized) {
+            hardening_index = Some(index);
         }
 
         // Check for warnings and issues
@@ -211,6 +249,39 @@ fn summarize_lynis(stdout: &str) -> (String, Vec<String>, Vec<String>, Option<St
     (summary, findings, suggestions, hardening_index)
 }
 
+/// Parse the numeric hardening index from a lowercased lynis line
+///
+/// Handles both `hardening index : 67 [#####     ]` and `hardening index [67]`.
+fn parse_hardening_index(line: &str) -> Option<u32> {
+    let rest = &line[line.find("hardening index")? + "hardening index".len()..];
+    let digits: String = rest
+        .trim_start_matches(|c: char| !c.is_ascii_digit())
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok().filter(|index| *index <= 100)
+}
+
+/// Compare the hardening index against a `fail_below` threshold
+///
+/// A missing index fails the check, since the scan could not prove compliance.
+fn check_threshold(hardening_index: Option<u32>, fail_below: u32) -> (bool, String) {
+    match hardening_index {
+        Some(index) if index >= fail_below => (
+            true,
+            format!("Hardening index {} meets threshold {}", index, fail_below),
+        ),
+        Some(index) => (
+            false,
+            format!("Hardening index {} is below threshold {}", index, fail_below),
+        ),
+        None => (
+            false,
+            format!("Hardening index not found; cannot verify threshold {}", fail_below),
+        ),
+    }
+}
+
 fn truncate(input: &str, limit: usize) -> String {
     if input.len() <= limit {
         return input.to_string();
@@ -220,3 +291,37 @@ fn truncate(input: &str, limit: usize) -> String {
     truncated.push_str("\n...[truncated]...");
     truncated
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hardening_index() {
+        assert_eq!(parse_hardening_index("  hardening index : 67 [#############       ]"), Some(67));
+        assert_eq!(parse_hardening_index("hardening index [82]"), Some(82));
+        assert_eq!(parse_hardening_index("hardening index : 100 [####################]"), Some(100));
+        assert_eq!(parse_hardening_index("hardening index : 0 [                    ]"), Some(0));
+
+        // Out of range, missing or unrelated
+        assert_eq!(parse_hardening_index("hardening index : 140"), None);
+        assert_eq!(parse_hardening_index("hardening index : [ ]"), None);
+        assert_eq!(parse_hardening_index("tests performed : 251"), None);
+    }
+
+    #[test]
+    fn test_check_threshold() {
+        assert_eq!(
+            check_threshold(Some(70), 70),
+            (true, "Hardening index 70 meets threshold 70".to_string())
+        );
+        assert_eq!(
+            check_threshold(Some(69), 70),
+            (false, "Hardening index 69 is below threshold 70".to_string())
+        );
+        assert_eq!(
+            check_threshold(None, 0),
+            (false, "Hardening index not found; cannot verify threshold 0".to_string())
+        );
+    }
+}
@@ -16,12 +16,17 @@ use rmcp::{
 use rmcp::model::ErrorData;
 use rmcp::serde_json;
 use regex::Regex;
-use serde::{Deserialize, Serialize};
+use serde::Serialize;
 use std::collections::HashMap;
 use std::process::Command;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use tracing::info;
+use tracing::{info, warn};
+
+mod parse;
+
+use parse::{parse_lsof_line, parse_ps_aux_line, parse_ss_line, NetworkFile, ProcessInfo};
+use tool_common::process::run_bounded;
 
 #[derive(Clone)]
 pub struct ProcInfoServer {
@@ -29,34 +34,6 @@ pub struct ProcInfoServer {
     tool_router: ToolRouter<Self>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct ProcessInfo {
-    user: String,
-    pid: u32,
-    cpu_percent: f32,
-    mem_percent: f32,
-    vsz_kb: u64,
-    rss_kb: u64,
-    tty: String,
-    stat: String,
-    start: String,
-    time: String,
-    command: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-struct NetworkFile {
-    command: String,
-    pid: u32,
-    user: String,
-    fd: String,
-    file_type: String,
-    device: String,
-    size_off: String,
-    node: String,
-    name: String,
-}
-
 #[tool_router]
 impl ProcInfoServer {
     fn new() -> Self {
@@ -81,17 +58,21 @@ impl ProcInfoServer {
         let sort_by = params.get("sort_by").and_then(|v| v.as_str()).unwrap_or("cpu");
 
         // Run ps aux
-        let output = Command::new("ps")
-            .arg("aux")
-            .arg("--sort")
-            .arg(match sort_by {
-                "memory" | "mem" => "-%mem",
-                "cpu" => "-%cpu",
-                "pid" => "pid",
-                "user" => "user",
-                _ => "-%cpu",
-            })
-            .output();
+        let output = run_bounded(
+            Command::new("ps")
+                .env("LC_ALL", "C")
+                .arg("aux")
+                .arg("--sort")
+                .arg(match sort_by {
+                    "memory" | "mem" => "-%mem",
+                    "cpu" => "-%cpu",
+                    "pid" => "pid",
+                    "user" => "user",
+                    _ => "-%cpu",
+                }),
+            MAX_OUTPUT_LINES,
+            MAX_OUTPUT_BYTES,
+        );
 
         let output = match output {
             Ok(out) => out,
@@ -103,31 +84,22 @@ impl ProcInfoServer {
             }
         };
 
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        let stdout = output.stdout;
         let mut processes: Vec<ProcessInfo> = Vec::new();
 
         // Parse ps aux output
         for line in stdout.lines().skip(1) {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() < 11 {
-                continue;
-            }
-
-            let user = parts[0].to_string();
-            let pid: u32 = parts[1].parse().unwrap_or(0);
-            let cpu_percent: f32 = parts[2].parse().unwrap_or(0.0);
-            let mem_percent: f32 = parts[3].parse().unwrap_or(0.0);
-            let vsz_kb: u64 = parts[4].parse().unwrap_or(0);
-            let rss_kb: u64 = parts[5].parse().unwrap_or(0);
-            let tty = parts[6].to_string();
-            let stat = parts[7].to_string();
-            let start = parts[8].to_string();
-            let time = parts[9].to_string();
-            let command = parts[10..].join(" ");
+            let process = match parse_ps_aux_line(line) {
+                Ok(process) => process,
+                Err(e) => {
+                    warn!("Skipping ps aux line ({}): {}", e, line);
+                    continue;
+                }
+            };
 
             // Apply filters
             if let Some(user_f) = user_filter {
-                if !user.contains(user_f) {
+                if !process.user.contains(user_f) {
                     continue;
                 }
             }
@@ -135,31 +107,19 @@ impl ProcInfoServer {
             if let Some(cmd_f) = command_filter {
                 let re = Regex::new(cmd_f).ok();
                 if let Some(regex) = re {
-                    if !regex.is_match(&command) {
+                    if !regex.is_match(&process.command) {
                         continue;
                     }
-                } else if !command.contains(cmd_f) {
+                } else if !process.command.contains(cmd_f) {
                     continue;
                 }
             }
 
-            if cpu_percent < min_cpu || mem_percent < min_mem {
+            if process.cpu_percent < min_cpu || process.mem_percent < min_mem {
                 continue;
             }
 
-            processes.push(ProcessInfo {
-                user,
-                pid,
-                cpu_percent,
-                mem_percent,
-                vsz_kb,
-                rss_kb,
-                tty,
-                stat,
-                start,
-                time,
-                command,
-            });
+            processes.push(process);
         }
 
         // Build report
@@ -233,7 +193,7 @@ impl ProcInfoServer {
             cmd.arg(pid_val.to_string());
         }
 
-        let output = match cmd.output() {
+        let output = match run_bounded(&mut cmd, MAX_OUTPUT_LINES, PSTREE_MAX_BYTES) {
             Ok(out) => out,
             Err(err) => {
                 return Ok(CallToolResult::error(vec![Content::text(format!(
@@ -243,17 +203,12 @@ impl ProcInfoServer {
             }
         };
 
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-
         let report = format!(
             "🌳 Process Tree\n\
              ═══════════════════════════════════════\n\n\
-             {}\n",
-            if stdout.len() > 8000 {
-                truncate(&stdout, 8000)
-            } else {
-                stdout
-            }
+             {}{}\n",
+            output.stdout,
+            if output.truncated { "...[truncated]...\n" } else { "" }
         );
 
         Ok(CallToolResult::success(vec![Content::text(report)]))
@@ -270,6 +225,7 @@ impl ProcInfoServer {
         let protocol = params.get("protocol").and_then(|v| v.as_str());
 
         let mut cmd = Command::new("lsof");
+        cmd.env("LC_ALL", "C");
         cmd.arg("-i");  // Network files
         cmd.arg("-n");  // No hostname resolution
         cmd.arg("-P");  // No port name resolution
@@ -286,7 +242,7 @@ impl ProcInfoServer {
             }
         }
 
-        let output = match cmd.output() {
+        let output = match run_bounded(&mut cmd, MAX_OUTPUT_LINES, MAX_OUTPUT_BYTES) {
             Ok(out) => out,
             Err(err) => {
                 return Ok(CallToolResult::error(vec![Content::text(format!(
@@ -296,25 +252,17 @@ impl ProcInfoServer {
             }
         };
 
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        if output.stdout.is_empty() && !output.stderr.is_empty() {
+            warn!("lsof printed nothing: {}", output.stderr.trim());
+        }
+        let stdout = output.stdout;
         let mut network_files: Vec<NetworkFile> = Vec::new();
 
         // Parse lsof output
         for line in stdout.lines().skip(1) {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 9 {
-                let nf = NetworkFile {
-                    command: parts[0].to_string(),
-                    pid: parts[1].parse().unwrap_or(0),
-                    user: parts[2].to_string(),
-                    fd: parts[3].to_string(),
-                    file_type: parts[4].to_string(),
-                    device: parts[5].to_string(),
-                    size_off: parts[6].to_string(),
-                    node: parts[7].to_string(),
-                    name: parts[8..].join(" "),
-                };
-                network_files.push(nf);
+            match parse_lsof_line(line) {
+                Ok(nf) => network_files.push(nf),
+                Err(e) => warn!("Skipping lsof line ({}): {}", e, line),
             }
         }
 
@@ -357,7 +305,7 @@ impl ProcInfoServer {
         ]))
     }
 
-    #[tool(description = "Correlate PIDs with network connections using ss. Maps each PID to its active TCP/UDP connections.")]
+    #[tool(description = "Correlate PIDs with network connections using ss. Maps each PID to its active TCP/UDP connections and to its container (docker/containerd/CRI-O/podman ID and Kubernetes pod, from /proc/<pid>/cgroup; 'host' otherwise), grouped by container then process.")]
     async fn correlate_pid_packets(
         &self,
         params: serde_json::Map<String, serde_json::Value>,
@@ -367,9 +315,11 @@ impl ProcInfoServer {
         let target_pid = params.get("pid").and_then(|v| v.as_u64());
 
         // Use ss to get connections with process info
-        let output = Command::new("ss")
-            .arg("-tunap")
-            .output();
+        let output = run_bounded(
+            Command::new("ss").env("LC_ALL", "C").arg("-tunap"),
+            MAX_OUTPUT_LINES,
+            MAX_OUTPUT_BYTES,
+        );
 
         let output = match output {
             Ok(out) => out,
@@ -381,12 +331,16 @@ impl ProcInfoServer {
             }
         };
 
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
+        if output.stdout.is_empty() && !output.stderr.is_empty() {
+            warn!("ss printed nothing: {}", output.stderr.trim());
+        }
+        let stdout = output.stdout;
 
         #[derive(Debug, Serialize)]
         struct PidConnection {
             pid: u32,
             process: String,
+            container: String,
             protocol: String,
             state: String,
             local: String,
@@ -394,104 +348,115 @@ impl ProcInfoServer {
         }
 
         let mut connections: Vec<PidConnection> = Vec::new();
-        let pid_re = Regex::new(r"pid=(\d+)").ok();
-        let name_re = Regex::new(r#"\("([^"]+)""#).ok();
-
+        let mut containers: HashMap<u32, String> = HashMap::new();
         for line in stdout.lines().skip(1) {
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() < 6 {
-                continue;
-            }
-
-            let protocol = parts[0].to_string();
-            let state = parts[1].to_string();
-            let local = parts[4].to_string();
-            let remote = parts[5].to_string();
-
-            let users_part = parts.get(6).unwrap_or(&"");
-
-            let mut pid: u32 = 0;
-            let mut process = String::from("unknown");
-
-            if let Some(ref regex) = pid_re {
-                if let Some(caps) = regex.captures(users_part) {
-                    if let Some(pid_str) = caps.get(1) {
-                        pid = pid_str.as_str().parse().unwrap_or(0);
-                    }
-                }
-            }
-
-            if let Some(ref regex) = name_re {
-                if let Some(caps) = regex.captures(users_part) {
-                    if let Some(name) = caps.get(1) {
-                        process = name.as_str().to_string();
-                    }
+            let socket = match parse_ss_line(line) {
+                Ok(socket) => socket,
+                Err(e) => {
+                    warn!("Skipping ss line ({}): {}", e, line);
+                    continue;
                 }
-            }
+            };
 
-            // Filter by target PID if specified
-            if let Some(target) = target_pid {
-                if pid != target as u32 {
+            // A socket shared by several processes counts once for each
+            for owner in &socket.processes {
+                // Filter by target PID if specified
+                if target_pid.is_some_and(|target| u64::from(owner.pid) != target) {
                     continue;
                 }
-            }
 
-            if pid > 0 {
+                let container = containers
+                    .entry(owner.pid)
+                    .or_insert_with(|| pid_container(owner.pid))
+                    .clone();
                 connections.push(PidConnection {
-                    pid,
-                    process,
-                    protocol,
-                    state,
-                    local,
-                    remote,
+                    pid: owner.pid,
+                    process: owner.name.clone(),
+                    container,
+                    protocol: socket.protocol.clone(),
+                    state: socket.state.clone(),
+                    local: socket.local.to_string(),
+                    remote: socket.remote.to_string(),
                 });
             }
         }
 
-        // Group by PID
-        let mut by_pid: HashMap<u32, Vec<&PidConnection>> = HashMap::new();
+        // Group by container, then by PID
+        let mut by_container: HashMap<&str, HashMap<u32, Vec<&PidConnection>>> = HashMap::new();
         for conn in &connections {
-            by_pid.entry(conn.pid).or_default().push(conn);
+            by_container
+                .entry(conn.container.as_str())
+                .or_default()
+                .entry(conn.pid)
+                .or_default()
+                .push(conn);
         }
 
         let mut report = format!(
-            "🔗 PID-Network Correlation ({} connections, {} processes)\n\
+            "🔗 PID-Network Correlation ({} connections, {} processes, {} containers)\n\
              ═══════════════════════════════════════\n\n",
             connections.len(),
-            by_pid.len()
+            containers.len(),
+            by_container.keys().filter(|c| **c != HOST_CONTAINER).count()
         );
 
-        // Sort by connection count
-        let mut pid_counts: Vec<(u32, usize, String)> = by_pid
+        // Containers with the most connections first, host last
+        let mut container_order: Vec<(&str, usize)> = by_container
             .iter()
-            .map(|(pid, conns)| {
-                let name = conns.first().map(|c| c.process.clone()).unwrap_or_default();
-                (*pid, conns.len(), name)
-            })
+            .map(|(container, pids)| (*container, pids.values().map(Vec::len).sum()))
             .collect();
-        pid_counts.sort_by(|a, b| b.1.cmp(&a.1));
+        container_order.sort_by(|a, b| {
+            (a.0 == HOST_CONTAINER)
+                .cmp(&(b.0 == HOST_CONTAINER))
+                .then(b.1.cmp(&a.1))
+                .then(a.0.cmp(b.0))
+        });
+
+        for (container, total) in &container_order {
+            let by_pid = &by_container[container];
+            report.push_str(&format!(
+                "🐳 {} - {} processes, {} connections\n",
+                container,
+                by_pid.len(),
+                total
+            ));
 
-        for (pid, count, name) in pid_counts.iter().take(20) {
-            report.push_str(&format!("📦 {} (PID {}) - {} connections\n", name, pid, count));
+            // Sort by connection count
+            let mut pid_counts: Vec<(u32, usize, String)> = by_pid
+                .iter()
+                .map(|(pid, conns)| {
+                    let name = conns.first().map(|c| c.process.clone()).unwrap_or_default();
+                    (*pid, conns.len(), name)
+                })
+                .collect();
+            pid_counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
 
-            if let Some(conns) = by_pid.get(pid) {
-                for conn in conns.iter().take(5) {
-                    report.push_str(&format!(
-                        "   {} {} → {} [{}]\n",
-                        conn.protocol, conn.local, conn.remote, conn.state
-                    ));
-                }
-                if conns.len() > 5 {
-                    report.push_str(&format!("   ... and {} more\n", conns.len() - 5));
+            for (pid, count, name) in pid_counts.iter().take(20) {
+                report.push_str(&format!("   📦 {} (PID {}) - {} connections\n", name, pid, count));
+
+                if let Some(conns) = by_pid.get(pid) {
+                    for conn in conns.iter().take(5) {
+                        report.push_str(&format!(
+                            "      {} {} → {} [{}]\n",
+                            conn.protocol, conn.local, conn.remote, conn.state
+                        ));
+                    }
+                    if conns.len() > 5 {
+                        report.push_str(&format!("      ... and {} more\n", conns.len() - 5));
+                    }
                 }
             }
-            report.push('\n');
-        }
 
-        if pid_counts.len() > 20 {
-            report.push_str(&format!("\n... and {} more processes\n", pid_counts.len() - 20));
+            if pid_counts.len() > 20 {
+                report.push_str(&format!("   ... and {} more processes\n", pid_counts.len() - 20));
+            }
+            report.push('\n');
         }
 
+        connections.sort_by(|a, b| {
+            (a.container == HOST_CONTAINER, &a.container, a.pid)
+                .cmp(&(b.container == HOST_CONTAINER, &b.container, b.pid))
+        });
         let json_data = serde_json::to_string_pretty(&connections.iter().take(100).collect::<Vec<_>>())
             .unwrap_or_else(|_| "[]".to_string());
 
@@ -552,16 +517,22 @@ impl ProcInfoServer {
         }
 
         // Network connections for this PID
-        let ss_output = Command::new("ss")
-            .arg("-tunap")
-            .output();
+        let ss_output = run_bounded(
+            Command::new("ss").env("LC_ALL", "C").arg("-tunap"),
+            MAX_OUTPUT_LINES,
+            MAX_OUTPUT_BYTES,
+        );
 
         if let Ok(out) = ss_output {
-            let stdout = String::from_utf8_lossy(&out.stdout);
-            let pid_str = format!("pid={}", pid);
+            let stdout = out.stdout;
             let matching_lines: Vec<&str> = stdout
                 .lines()
-                .filter(|l| l.contains(&pid_str))
+                .skip(1)
+                .filter(|l| {
+                    parse_ss_line(l)
+                        .map(|socket| socket.processes.iter().any(|p| u64::from(p.pid) == pid))
+                        .unwrap_or(false)
+                })
                 .collect();
 
             if !matching_lines.is_empty() {
@@ -608,6 +579,87 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Container label for processes outside any container
+const HOST_CONTAINER: &str = "host";
+
+/// Container a PID belongs to, from `/proc/<pid>/cgroup`
+fn pid_container(pid: u32) -> String {
+    std::fs::read_to_string(format!("/proc/{}/cgroup", pid))
+        .ok()
+        .and_then(|cgroup| container_from_cgroup(&cgroup))
+        .unwrap_or_else(|| HOST_CONTAINER.to_string())
+}
+
+/// Extract a container label from the contents of a `/proc/<pid>/cgroup` file
+///
+/// Handles cgroup v1 (`/docker/<id>`, `/kubepods/<qos>/pod<uid>/<id>`) and v2
+/// systemd scopes (`docker-<id>.scope`, `cri-containerd-<id>.scope`,
+/// `crio-<id>.scope`, `libpod-<id>.scope`). The label is
+/// `<runtime>:<short id>`, prefixed with `pod:<uid>/` for Kubernetes pods.
+fn container_from_cgroup(cgroup: &str) -> Option<String> {
+    const RUNTIME_PREFIXES: [(&str, &str); 5] = [
+        ("docker-", "docker"),
+        ("cri-containerd-", "containerd"),
+        ("containerd-", "containerd"),
+        ("crio-", "crio"),
+        ("libpod-", "podman"),
+    ];
+    let is_id = |s: &str| s.len() >= 12 && s.chars().all(|c| c.is_ascii_hexdigit());
+
+    for line in cgroup.lines() {
+        let Some(path) = line.splitn(3, ':').nth(2) else {
+            continue;
+        };
+
+        let mut pod = None;
+        let mut container = None;
+        let mut parent = "";
+        let mut in_kubepods = false;
+        for segment in path.split('/').filter(|s| !s.is_empty()) {
+            let name = segment.trim_end_matches(".scope").trim_end_matches(".slice");
+
+            // `pod<uid>` (v1) or `kubepods-<qos>-pod<uid_with_underscores>` (v2)
+            in_kubepods |= name.starts_with("kubepods");
+            let uid = name
+                .strip_prefix("pod")
+                .or_else(|| name.rsplit_once("-pod").map(|(_, uid)| uid));
+            if let Some(uid) = uid.filter(|uid| in_kubepods && uid.len() >= 8) {
+                pod = Some(uid.replace('_', "-"));
+            }
+
+            if let Some((runtime, id)) = RUNTIME_PREFIXES
+                .iter()
+                .find_map(|(prefix, runtime)| name.strip_prefix(prefix).map(|id| (*runtime, id)))
+                .filter(|(_, id)| is_id(id))
+            {
+                container = Some(format!("{}:{}", runtime, &id[..12]));
+            } else if is_id(name) {
+                let runtime = match parent {
+                    "docker" => "docker:",
+                    _ => "",
+                };
+                container = Some(format!("{}{}", runtime, &name[..12]));
+            }
+            parent = name;
+        }
+
+        match (pod, container) {
+            (Some(pod), Some(container)) => return Some(format!("pod:{}/{}", pod, container)),
+            (Some(pod), None) => return Some(format!("pod:{}", pod)),
+            (None, Some(container)) => return Some(container),
+            (None, None) => {}
+        }
+    }
+    None
+}
+
+/// Line limit for streamed command output
+const MAX_OUTPUT_LINES: usize = 20_000;
+/// Byte limit for streamed command output
+const MAX_OUTPUT_BYTES: usize = 2 * 1024 * 1024;
+/// Byte limit for the pstree report
+const PSTREE_MAX_BYTES: usize = 8000;
+
 fn truncate_str(s: &str, max_len: usize) -> String {
     if s.len() <= max_len {
         s.to_string()
@@ -616,12 +668,49 @@ fn truncate_str(s: &str, max_len: usize) -> String {
     }
 }
 
-fn truncate(input: &str, limit: usize) -> String {
-    if input.len() <= limit {
-        return input.to_string();
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_container_from_cgroup() {
+        let id = "3f4e1c2b9a8d7e6f5a4b3c2d1e0f9a8b7c6d5e4f3a2b1c0d9e8f7a6b5c4d3e2f";
+
+        let v1 = format!("12:memory:/docker/{}\n11:cpu:/docker/{}\n", id, id);
+        assert_eq!(container_from_cgroup(&v1).as_deref(), Some("docker:3f4e1c2b9a8d"));
+
+        let v2 = format!("0::/system.slice/docker-{}.scope\n", id);
+        assert_eq!(container_from_cgroup(&v2).as_deref(), Some("docker:3f4e1c2b9a8d"));
+
+        let kube = format!(
+            "0::/kubepods.slice/kubepods-besteffort.slice/\
+             kubepods-besteffort-pod0c1d2e3f_4a5b_6c7d_8e9f_0a1b2c3d4e5f.slice/cri-containerd-{}.scope\n",
+            id
+        );
+        assert_eq!(
+            container_from_cgroup(&kube).as_deref(),
+            Some("pod:0c1d2e3f-4a5b-6c7d-8e9f-0a1b2c3d4e5f/containerd:3f4e1c2b9a8d")
+        );
+
+        let kube_v1 = format!("4:pids:/kubepods/burstable/pod0c1d2e3f-4a5b-6c7d-8e9f-0a1b2c3d4e5f/{}\n", id);
+        assert_eq!(
+            container_from_cgroup(&kube_v1).as_deref(),
+            Some("pod:0c1d2e3f-4a5b-6c7d-8e9f-0a1b2c3d4e5f/3f4e1c2b9a8d")
+        );
+
+        assert_eq!(container_from_cgroup("0::/user.slice/user-1000.slice/session-2.scope\n"), None);
+        assert_eq!(container_from_cgroup("0::/init.scope\n"), None);
     }
 
-    let mut truncated = input[..limit].to_string();
-    truncated.push_str("\n...[truncated]...");
-    truncated
+    #[test]
+    fn test_parse_ps_aux_uses_mem_column() {
+        let line = "postgres  1201  2.5 12.3 4194304 2015232 ?  Ss  09:14  1:02 postgres: writer process";
+        let process = parse_ps_aux_line(line).unwrap();
+        assert_eq!(process.pid, 1201);
+        assert_eq!(process.mem_percent, 12.3);
+        assert_eq!(process.rss_kb, 2015232);
+        assert_eq!(process.command, "postgres: writer process");
+
+        assert!(parse_ps_aux_line("USER PID %CPU").is_err());
+    }
 }
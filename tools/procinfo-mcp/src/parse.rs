@@ -0,0 +1,138 @@
+//! Parsers for `ps aux` and `lsof -i` output
+//!
+//! Each parser handles one data line and reports why a line was rejected
+//! instead of guessing, so callers can log and skip it. Free-text trailing
+//! columns (commands, file names) are taken verbatim from the rest of the
+//! line, so embedded spaces survive. The `ss -tunap` parser lives in
+//! `tool_common::ss`, shared with tshark-mcp and the native port tool.
+
+use serde::{Deserialize, Serialize};
+pub use tool_common::parse::ParseError;
+use tool_common::parse::{number, split_columns};
+pub use tool_common::ss::parse_ss_line;
+
+/// One process from `ps aux`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProcessInfo {
+    pub user: String,
+    pub pid: u32,
+    pub cpu_percent: f32,
+    pub mem_percent: f32,
+    pub vsz_kb: u64,
+    pub rss_kb: u64,
+    pub tty: String,
+    pub stat: String,
+    pub start: String,
+    pub time: String,
+    pub command: String,
+}
+
+/// One network file from `lsof -i`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NetworkFile {
+    pub command: String,
+    pub pid: u32,
+    pub user: String,
+    pub fd: String,
+    pub file_type: String,
+    pub device: String,
+    pub size_off: String,
+    pub node: String,
+    pub name: String,
+}
+
+/// Parse one data line of `ps aux` output
+///
+/// The `%MEM` column is ps's own RSS-over-physical-RAM figure and is used
+/// as-is rather than recomputed from the RSS column.
+pub fn parse_ps_aux_line(line: &str) -> Result<ProcessInfo, ParseError> {
+    let (columns, command) = split_columns(line, 10)?;
+    if command.is_empty() {
+        return Err(ParseError::MissingFields { expected: 11, found: 10 });
+    }
+
+    Ok(ProcessInfo {
+        user: columns[0].to_string(),
+        pid: number("pid", columns[1])?,
+        cpu_percent: number("%cpu", columns[2])?,
+        mem_percent: number("%mem", columns[3])?,
+        vsz_kb: number("vsz", columns[4])?,
+        rss_kb: number("rss", columns[5])?,
+        tty: columns[6].to_string(),
+        stat: columns[7].to_string(),
+        start: columns[8].to_string(),
+        time: columns[9].to_string(),
+        command: command.to_string(),
+    })
+}
+
+/// Parse one data line of `lsof -i -n -P` output
+pub fn parse_lsof_line(line: &str) -> Result<NetworkFile, ParseError> {
+    let (columns, name) = split_columns(line, 8)?;
+    if name.is_empty() {
+        return Err(ParseError::MissingFields { expected: 9, found: 8 });
+    }
+
+    Ok(NetworkFile {
+        command: columns[0].to_string(),
+        pid: number("pid", columns[1])?,
+        user: columns[2].to_string(),
+        fd: columns[3].to_string(),
+        file_type: columns[4].to_string(),
+        device: columns[5].to_string(),
+        size_off: columns[6].to_string(),
+        node: columns[7].to_string(),
+        name: name.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PS_AUX: &str = include_str!("../fixtures/ps_aux.txt");
+    const LSOF_I: &str = include_str!("../fixtures/lsof_i.txt");
+
+    #[test]
+    fn test_parse_ps_aux_fixture() {
+        let results: Vec<_> = PS_AUX.lines().skip(1).map(parse_ps_aux_line).collect();
+        assert_eq!(results.len(), 6);
+
+        let postgres = results[1].as_ref().unwrap();
+        assert_eq!(postgres.pid, 1201);
+        assert_eq!(postgres.mem_percent, 12.3);
+        assert_eq!(postgres.rss_kb, 2015232);
+        assert_eq!(postgres.command, "postgres: writer process");
+
+        let spaced = results[2].as_ref().unwrap();
+        assert_eq!(spaced.command, "/opt/My App/bin/my app --name \"two  spaces\"");
+
+        let comma_locale = results[3].as_ref().unwrap();
+        assert_eq!(comma_locale.cpu_percent, 1.5);
+
+        assert_eq!(
+            results[4].as_ref().unwrap_err(),
+            &ParseError::InvalidField { field: "pid", value: "12a4".to_string() }
+        );
+        assert_eq!(
+            results[5].as_ref().unwrap_err(),
+            &ParseError::MissingFields { expected: 11, found: 10 }
+        );
+        assert!(parse_ps_aux_line("USER PID %CPU").is_err());
+    }
+
+    #[test]
+    fn test_parse_lsof_fixture() {
+        let results: Vec<_> = LSOF_I.lines().skip(1).map(parse_lsof_line).collect();
+        assert_eq!(results.len(), 3);
+
+        let listen = results[0].as_ref().unwrap();
+        assert_eq!(listen.pid, 812);
+        assert_eq!(listen.name, "*:22 (LISTEN)");
+
+        let established = results[1].as_ref().unwrap();
+        assert_eq!(established.name, "[::1]:5432->[::1]:40122 (ESTABLISHED)");
+
+        assert!(matches!(results[2], Err(ParseError::InvalidField { field: "pid", .. })));
+    }
+}
@@ -7,9 +7,9 @@ use rmcp::{
 };
 use rmcp::serde_json;
 use rmcp::model::ErrorData;
-use std::process::Command;
 use std::sync::Arc;
 use tokio::sync::Mutex;
+use tool_common::sudo::{check_passwordless_sudo, scan_command, sudo_required_message};
 use tracing::info;
 
 #[derive(Clone)]
@@ -27,7 +27,7 @@ impl RkhunterServer {
         }
     }
 
-    #[tool(description = "Run rkhunter --checkall with sudo and summarize any findings")]
+    #[tool(description = "Run rkhunter --checkall with non-interactive sudo (set use_sudo=false when running as root) and summarize any findings")]
     async fn rkhunter_scan(
         &self,
         params: serde_json::Map<String, serde_json::Value>,
@@ -51,17 +51,27 @@ impl RkhunterServer {
                 "--report-warnings-only".to_string(),
             ]);
 
-        // Run rkhunter with sudo
-        let mut cmd = Command::new("sudo");
-        cmd.arg("rkhunter");
-        cmd.args(&flags);
+        let use_sudo = params
+            .get("use_sudo")
+            .and_then(|v| v.as_bool())
+            .unwrap_or(true);
+
+        // Fail fast instead of hanging on a sudo password prompt
+        if use_sudo {
+            if let Err(reason) = check_passwordless_sudo("rkhunter") {
+                return Ok(CallToolResult::error(vec![Content::text(
+                    sudo_required_message("rkhunter", &reason),
+                )]));
+            }
+        }
 
+        let mut cmd = scan_command("rkhunter", &flags, use_sudo);
         let output = cmd.output();
         let output = match output {
             Ok(out) => out,
             Err(err) => {
                 return Ok(CallToolResult::error(vec![Content::text(format!(
-                    "Failed to execute 'sudo rkhunter': {}. Ensure:\n\
+                    "Failed to execute rkhunter: {}. Ensure:\n\
                      1. rkhunter is installed (apt-get install rkhunter)\n\
                      2. sudo is available\n\
                      3. User has passwordless sudo access for rkhunter OR run this as root\n\
@@ -107,8 +117,10 @@ impl ServerHandler for RkhunterServer {
     fn get_info(&self) -> ServerInfo {
         ServerInfo {
             instructions: Some(
-                "Run 'sudo rkhunter --checkall' and return a concise summary of any warnings. \
-                 Requires passwordless sudo or root access.".into(),
+                "Run 'rkhunter --checkall' and return a concise summary of any warnings. \
+                 Runs through 'sudo -n', which fails with setup steps instead of prompting \
+                 when passwordless sudo is not configured; when the server runs as root, \
+                 pass \"use_sudo\": false.".into(),
             ),
             capabilities: ServerCapabilities::builder().enable_tools().build(),
             ..Default::default()
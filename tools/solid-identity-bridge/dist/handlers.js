@@ -20,6 +20,8 @@ async function handleRequest(request) {
             return await fetchWebIdProfile(params.webid);
         case 'authenticate':
             return await performOidcAuth(params);
+        case 'refreshToken':
+            return await refreshAccessToken(params);
         case 'fetchResource':
             return await fetchSolidResource(params.url, params.contentType);
         case 'updateResource':
@@ -81,6 +83,44 @@ async function performOidcAuth(params) {
         throw new Error(`Authentication failed: ${error.message}`);
     }
 }
+/**
+ * Exchange a refresh token at the issuer's token endpoint
+ *
+ * The DPoP proof is created in Rust for the discovered token endpoint, so it
+ * is sent as-is. Expiry is returned in milliseconds since the Unix epoch.
+ */
+async function refreshAccessToken(params) {
+    try {
+        const response = await fetch(params.tokenEndpoint, {
+            method: 'POST',
+            headers: {
+                'Content-Type': 'application/x-www-form-urlencoded',
+                DPoP: params.dpopProof,
+            },
+            body: new URLSearchParams({
+                grant_type: 'refresh_token',
+                refresh_token: params.refreshToken,
+                client_id: params.clientId,
+            }).toString(),
+        });
+        const body = await response.json();
+        if (!response.ok) {
+            throw new Error(body.error_description || body.error || `HTTP ${response.status}`);
+        }
+        return {
+            isLoggedIn: true,
+            webId: session.info.webId || null,
+            sessionId: session.info.sessionId,
+            accessToken: body.access_token,
+            refreshToken: body.refresh_token || null,
+            idToken: body.id_token || null,
+            expiresAt: Date.now() + (body.expires_in ?? 3600) * 1000,
+        };
+    }
+    catch (error) {
+        throw new Error(`Token refresh failed: ${error.message}`);
+    }
+}
 /**
  * Fetch a Solid resource
  */
@@ -34,6 +34,9 @@ export async function handleRequest(request: any): Promise<any> {
     case 'authenticate':
       return await performOidcAuth(params);
 
+    case 'refreshToken':
+      return await refreshAccessToken(params);
+
     case 'fetchResource':
       return await fetchSolidResource(params.url, params.contentType);
 
@@ -110,6 +113,52 @@ async function performOidcAuth(params: {
   }
 }
 
+/**
+ * Exchange a refresh token at the issuer's token endpoint
+ *
+ * The DPoP proof is created in Rust for the discovered token endpoint, so it
+ * is sent as-is. Expiry is returned in milliseconds since the Unix epoch.
+ */
+async function refreshAccessToken(params: {
+  issuer: string;
+  tokenEndpoint: string;
+  clientId: string;
+  refreshToken: string;
+  dpopProof: string;
+}): Promise<any> {
+  try {
+    const response = await fetch(params.tokenEndpoint, {
+      method: 'POST',
+      headers: {
+        'Content-Type': 'application/x-www-form-urlencoded',
+        DPoP: params.dpopProof,
+      },
+      body: new URLSearchParams({
+        grant_type: 'refresh_token',
+        refresh_token: params.refreshToken,
+        client_id: params.clientId,
+      }).toString(),
+    });
+
+    const body: any = await response.json();
+    if (!response.ok) {
+      throw new Error(body.error_description || body.error || `HTTP ${response.status}`);
+    }
+
+    return {
+      isLoggedIn: true,
+      webId: session.info.webId || null,
+      sessionId: session.info.sessionId,
+      accessToken: body.access_token,
+      refreshToken: body.refresh_token || null,
+      idToken: body.id_token || null,
+      expiresAt: Date.now() + (body.expires_in ?? 3600) * 1000,
+    };
+  } catch (error: any) {
+    throw new Error(`Token refresh failed: ${error.message}`);
+  }
+}
+
 /**
  * Fetch a Solid resource
  */
@@ -0,0 +1,114 @@
+/**
+ * Drives the built bridge (dist/) over stdio the way the Rust IpcChannel does
+ *
+ * The Inrupt packages are replaced by stubs on NODE_PATH when they are not
+ * installed, so this runs without network access. Run `npm run build` first.
+ */
+
+const { test } = require('node:test');
+const assert = require('node:assert');
+const { spawn } = require('node:child_process');
+const fs = require('node:fs');
+const http = require('node:http');
+const os = require('node:os');
+const path = require('node:path');
+const readline = require('node:readline');
+
+const STUBS = {
+  '@inrupt/solid-client': 'module.exports = {};',
+  '@inrupt/solid-client-authn-node':
+    "exports.Session = class { constructor() { this.info = { isLoggedIn: false, sessionId: 'stub' }; } };",
+  '@inrupt/vocab-common-rdf': 'exports.FOAF = {}; exports.VCARD = {};',
+};
+
+function stubModules() {
+  const dir = fs.mkdtempSync(path.join(os.tmpdir(), 'bridge-stubs-'));
+  for (const [name, source] of Object.entries(STUBS)) {
+    fs.mkdirSync(path.join(dir, name), { recursive: true });
+    fs.writeFileSync(path.join(dir, name, 'index.js'), source);
+  }
+  return dir;
+}
+
+/** Start the bridge and return a JSON-RPC `call(method, params)` */
+function startBridge(t) {
+  const bridge = spawn(process.execPath, [path.join(__dirname, '..', 'dist', 'index.js')], {
+    env: { ...process.env, NODE_PATH: stubModules() },
+    stdio: ['pipe', 'pipe', 'ignore'],
+  });
+  t.after(() => bridge.kill());
+
+  const pending = new Map();
+  readline.createInterface({ input: bridge.stdout }).on('line', (line) => {
+    const response = JSON.parse(line);
+    pending.get(response.id)?.(response);
+    pending.delete(response.id);
+  });
+
+  let nextId = 1;
+  return (method, params) =>
+    new Promise((resolve) => {
+      const id = nextId++;
+      pending.set(id, resolve);
+      bridge.stdin.write(JSON.stringify({ jsonrpc: '2.0', id, method, params }) + '\n');
+    });
+}
+
+/** Token endpoint answering every request with `status` and `body` */
+async function tokenEndpoint(t, status, body) {
+  const requests = [];
+  const server = http.createServer((req, res) => {
+    let form = '';
+    req.on('data', (chunk) => (form += chunk));
+    req.on('end', () => {
+      requests.push({ headers: req.headers, form: new URLSearchParams(form) });
+      res.writeHead(status, { 'Content-Type': 'application/json' });
+      res.end(JSON.stringify(body));
+    });
+  });
+  await new Promise((resolve) => server.listen(0, '127.0.0.1', resolve));
+  t.after(() => server.close());
+  return { url: `http://127.0.0.1:${server.address().port}/.oidc/token`, requests };
+}
+
+function refreshParams(tokenEndpoint) {
+  return {
+    issuer: 'https://issuer.example/',
+    tokenEndpoint,
+    clientId: 'spai-agent',
+    refreshToken: 'old-refresh',
+    dpopProof: 'proof.jwt',
+  };
+}
+
+test('refreshToken exchanges the refresh token at the token endpoint', async (t) => {
+  const endpoint = await tokenEndpoint(t, 200, {
+    access_token: 'new-access',
+    refresh_token: 'new-refresh',
+    expires_in: 60,
+  });
+  const call = startBridge(t);
+
+  const before = Date.now();
+  const response = await call('refreshToken', refreshParams(endpoint.url));
+
+  assert.strictEqual(response.error, undefined);
+  assert.strictEqual(response.result.accessToken, 'new-access');
+  assert.strictEqual(response.result.refreshToken, 'new-refresh');
+  assert.ok(response.result.expiresAt >= before + 60 * 1000);
+
+  const [request] = endpoint.requests;
+  assert.strictEqual(request.headers.dpop, 'proof.jwt');
+  assert.strictEqual(request.form.get('grant_type'), 'refresh_token');
+  assert.strictEqual(request.form.get('refresh_token'), 'old-refresh');
+  assert.strictEqual(request.form.get('client_id'), 'spai-agent');
+});
+
+test('refreshToken reports the token endpoint error', async (t) => {
+  const endpoint = await tokenEndpoint(t, 400, { error: 'invalid_grant' });
+  const call = startBridge(t);
+
+  const response = await call('refreshToken', refreshParams(endpoint.url));
+
+  assert.match(response.error.message, /Token refresh failed: invalid_grant/);
+});
@@ -0,0 +1,12 @@
+//! Parsers and helpers shared by the SPAI native tools and the MCP servers
+//! under `tools/`
+//!
+//! Kept free of MCP and agent dependencies so both the `spai` crate and the
+//! standalone servers can depend on it by path.
+
+#![warn(missing_docs)]
+
+pub mod parse;
+pub mod process;
+pub mod ss;
+pub mod sudo;
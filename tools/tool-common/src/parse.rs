@@ -0,0 +1,89 @@
+//! Building blocks for line-oriented parsers of command output
+//!
+//! Parsers handle one data line and report why a line was rejected instead
+//! of guessing, so callers can log and skip it.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Why a line of command output could not be parsed
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The line has fewer columns than the format requires
+    MissingFields {
+        /// Columns required
+        expected: usize,
+        /// Columns present
+        found: usize,
+    },
+    /// A column did not hold a value of the expected type
+    InvalidField {
+        /// Column name
+        field: &'static str,
+        /// Text found in the column
+        value: String,
+    },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingFields { expected, found } => {
+                write!(f, "expected at least {} columns, found {}", expected, found)
+            }
+            Self::InvalidField { field, value } => write!(f, "invalid {} '{}'", field, value),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Split off the first `count` whitespace-separated columns and return the rest of the line
+///
+/// The rest is taken verbatim, so free-text trailing columns keep their spaces.
+pub fn split_columns(line: &str, count: usize) -> Result<(Vec<&str>, &str), ParseError> {
+    let mut columns = Vec::with_capacity(count);
+    let mut rest = line.trim_start();
+    while columns.len() < count {
+        if rest.is_empty() {
+            return Err(ParseError::MissingFields { expected: count, found: columns.len() });
+        }
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        columns.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    Ok((columns, rest.trim_end()))
+}
+
+/// Parse a column, accepting a decimal comma as some locales print it
+pub fn number<T: FromStr>(field: &'static str, value: &str) -> Result<T, ParseError> {
+    value
+        .replace(',', ".")
+        .parse()
+        .map_err(|_| ParseError::InvalidField { field, value: value.to_string() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_columns_keeps_trailing_text() {
+        let (columns, rest) = split_columns("  root  1  /opt/My App/bin  --flag ", 2).unwrap();
+        assert_eq!(columns, ["root", "1"]);
+        assert_eq!(rest, "/opt/My App/bin  --flag");
+        assert_eq!(
+            split_columns("a b", 3).unwrap_err(),
+            ParseError::MissingFields { expected: 3, found: 2 }
+        );
+    }
+
+    #[test]
+    fn test_number_accepts_decimal_comma() {
+        assert_eq!(number::<f32>("%cpu", "1,5").unwrap(), 1.5);
+        assert_eq!(
+            number::<u32>("pid", "12a4").unwrap_err(),
+            ParseError::InvalidField { field: "pid", value: "12a4".to_string() }
+        );
+    }
+}
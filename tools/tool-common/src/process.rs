@@ -0,0 +1,144 @@
+//! Running commands without buffering unbounded output
+//!
+//! Used by the procinfo and tshark MCP servers, whose commands (`ss`, `lsof`,
+//! `ps`) can print far more than a report has room for.
+
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Command, Stdio};
+use std::thread;
+
+/// How much of the end of stderr [`run_bounded`] keeps
+pub const STDERR_TAIL_BYTES: usize = 4096;
+
+/// Output of a command read with [`run_bounded`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BoundedOutput {
+    /// Stdout, cut at the line or byte limit
+    pub stdout: String,
+    /// The last [`STDERR_TAIL_BYTES`] of stderr
+    pub stderr: String,
+    /// Whether stdout was cut short
+    pub truncated: bool,
+}
+
+/// Run a command, streaming stdout line by line
+///
+/// Stops once `max_lines` or `max_bytes` is reached and kills the child, so
+/// verbose output is never buffered in full only to be truncated. A line cut
+/// off by the byte limit is dropped rather than split. Stderr is drained
+/// alongside so the child cannot block on it, keeping only its tail.
+pub fn run_bounded(
+    cmd: &mut Command,
+    max_lines: usize,
+    max_bytes: usize,
+) -> std::io::Result<BoundedOutput> {
+    let mut child = cmd.stdout(Stdio::piped()).stderr(Stdio::piped()).spawn()?;
+    let stderr = child.stderr.take().expect("stderr is piped");
+    let stderr = thread::spawn(move || read_tail(stderr, STDERR_TAIL_BYTES));
+    let mut reader = BufReader::new(child.stdout.take().expect("stdout is piped"));
+    let mut buf = Vec::new();
+    let mut lines = 0;
+    let mut truncated = false;
+
+    loop {
+        if lines >= max_lines || buf.len() >= max_bytes {
+            truncated = !reader.fill_buf()?.is_empty();
+            break;
+        }
+
+        let line_start = buf.len();
+        let remaining = (max_bytes - buf.len()) as u64;
+        let read = (&mut reader).take(remaining).read_until(b'\n', &mut buf)?;
+        if read == 0 {
+            break;
+        }
+
+        if buf.last() != Some(&b'\n') && buf.len() >= max_bytes {
+            buf.truncate(line_start);
+            truncated = true;
+            break;
+        }
+        lines += 1;
+    }
+
+    if truncated {
+        let _ = child.kill();
+    }
+    let _ = child.wait();
+
+    Ok(BoundedOutput {
+        stdout: String::from_utf8_lossy(&buf).into_owned(),
+        stderr: stderr.join().unwrap_or_default(),
+        truncated,
+    })
+}
+
+/// Read a stream to the end, keeping only its last `limit` bytes
+fn read_tail(mut stream: impl Read, limit: usize) -> String {
+    let mut tail = Vec::new();
+    let mut chunk = [0u8; 4096];
+    while let Ok(n) = stream.read(&mut chunk) {
+        if n == 0 {
+            break;
+        }
+        tail.extend_from_slice(&chunk[..n]);
+        if tail.len() > limit {
+            tail.drain(..tail.len() - limit);
+        }
+    }
+    String::from_utf8_lossy(&tail).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `sh` printing the numbers from 1 to `n`, one per line
+    fn count_to(n: u32) -> Command {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c").arg(format!(
+            "i=1; while [ $i -le {} ]; do echo $i; i=$((i+1)); done",
+            n
+        ));
+        cmd
+    }
+
+    #[test]
+    fn test_run_bounded_stops_early() {
+        let output = run_bounded(&mut count_to(1_000_000), 10, 1024).unwrap();
+        assert!(output.truncated);
+        assert_eq!(output.stdout.lines().count(), 10);
+        assert_eq!(output.stdout.lines().last(), Some("10"));
+
+        let output = run_bounded(&mut count_to(1_000_000), 1000, 20).unwrap();
+        assert!(output.truncated);
+        assert_eq!(output.stdout, "1\n2\n3\n4\n5\n6\n7\n8\n9\n");
+
+        let output = run_bounded(&mut count_to(3), 3, 1024).unwrap();
+        assert!(!output.truncated);
+        assert_eq!(output.stdout, "1\n2\n3\n");
+    }
+
+    #[test]
+    fn test_run_bounded_keeps_stderr_tail() {
+        let output = run_bounded(
+            Command::new("sh")
+                .arg("-c")
+                .arg("echo listening; echo permission denied >&2"),
+            10,
+            1024,
+        )
+        .unwrap();
+        assert_eq!(output.stdout, "listening\n");
+        assert_eq!(output.stderr, "permission denied\n");
+
+        // Only the end of a long stderr is kept
+        let noisy = format!(
+            "i=0; while [ $i -lt {} ]; do echo warning >&2; i=$((i+1)); done; echo done >&2",
+            STDERR_TAIL_BYTES
+        );
+        let output = run_bounded(Command::new("sh").arg("-c").arg(noisy), 10, 1024).unwrap();
+        assert_eq!(output.stderr.len(), STDERR_TAIL_BYTES);
+        assert!(output.stderr.ends_with("warning\ndone\n"));
+    }
+}
@@ -0,0 +1,160 @@
+//! Parser for `ss -tunap` output
+//!
+//! Used by the native port listing tool and the procinfo and tshark MCP
+//! servers. The process column is read from the rest of the line, so names
+//! with spaces survive.
+
+use crate::parse::{number, split_columns, ParseError};
+use regex::Regex;
+use std::fmt;
+use std::sync::OnceLock;
+
+/// Address and port of one side of a socket
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SocketEndpoint {
+    /// Host without brackets, including any `%interface` scope
+    pub host: String,
+    /// Port, or `None` for the `*` wildcard
+    pub port: Option<u16>,
+}
+
+impl fmt::Display for SocketEndpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.host.contains(':') {
+            write!(f, "[{}]", self.host)?;
+        } else {
+            write!(f, "{}", self.host)?;
+        }
+        match self.port {
+            Some(port) => write!(f, ":{}", port),
+            None => write!(f, ":*"),
+        }
+    }
+}
+
+/// A process holding a socket, from the `users:((...))` column
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SocketProcess {
+    /// Process name
+    pub name: String,
+    /// Process ID
+    pub pid: u32,
+}
+
+/// One socket from `ss -tunap`
+#[derive(Debug, Clone, PartialEq)]
+pub struct SocketEntry {
+    /// `tcp` or `udp`
+    pub protocol: String,
+    /// Socket state as ss prints it (`LISTEN`, `ESTAB`, `UNCONN`, ...)
+    pub state: String,
+    /// Local side
+    pub local: SocketEndpoint,
+    /// Remote side
+    pub remote: SocketEndpoint,
+    /// Every process sharing the socket; empty when ss could not see the owner
+    pub processes: Vec<SocketProcess>,
+}
+
+/// Parse an ss address such as `10.0.0.5:443`, `[::1]:631`, `[fe80::1]%eth0:546` or `*:*`
+pub fn parse_endpoint(value: &str) -> Result<SocketEndpoint, ParseError> {
+    let invalid = || ParseError::InvalidField { field: "address", value: value.to_string() };
+    let (host, port) = match value.strip_prefix('[') {
+        Some(bracketed) => {
+            let (host, after) = bracketed.split_once(']').ok_or_else(invalid)?;
+            let (scope, port) = after.rsplit_once(':').ok_or_else(invalid)?;
+            (format!("{}{}", host, scope), port)
+        }
+        // Older ss prints IPv6 unbracketed (`:::22`); the port is still after the last colon
+        None => {
+            let (host, port) = value.rsplit_once(':').ok_or_else(invalid)?;
+            (host.to_string(), port)
+        }
+    };
+    if host.is_empty() {
+        return Err(invalid());
+    }
+
+    let port = match port {
+        "*" => None,
+        port => Some(number("port", port)?),
+    };
+    Ok(SocketEndpoint { host, port })
+}
+
+/// Parse one data line of `ss -tunap` output
+pub fn parse_ss_line(line: &str) -> Result<SocketEntry, ParseError> {
+    static PROCESS: OnceLock<Regex> = OnceLock::new();
+    let process = PROCESS.get_or_init(|| Regex::new(r#"\("(.*?)",pid=([^,)]*)"#).unwrap());
+
+    let (columns, users) = split_columns(line, 6)?;
+    let processes = process
+        .captures_iter(users)
+        .map(|caps| {
+            Ok(SocketProcess {
+                name: caps[1].to_string(),
+                pid: number("pid", &caps[2])?,
+            })
+        })
+        .collect::<Result<Vec<_>, ParseError>>()?;
+
+    Ok(SocketEntry {
+        protocol: columns[0].to_string(),
+        state: columns[1].to_string(),
+        local: parse_endpoint(columns[4])?,
+        remote: parse_endpoint(columns[5])?,
+        processes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SS_TUNAP: &str = include_str!("../fixtures/ss_tunap.txt");
+
+    #[test]
+    fn test_parse_ss_fixture() {
+        let results: Vec<_> = SS_TUNAP.lines().skip(1).map(parse_ss_line).collect();
+        assert_eq!(results.len(), 7);
+
+        let sshd = results[0].as_ref().unwrap();
+        assert_eq!((sshd.protocol.as_str(), sshd.state.as_str()), ("tcp", "LISTEN"));
+        assert_eq!(sshd.local, SocketEndpoint { host: "0.0.0.0".to_string(), port: Some(22) });
+        assert_eq!(sshd.remote.port, None);
+        assert_eq!(sshd.processes, vec![SocketProcess { name: "sshd".to_string(), pid: 812 }]);
+
+        let ipv6 = results[1].as_ref().unwrap();
+        assert_eq!(ipv6.local.host, "::1");
+        assert_eq!(ipv6.local.to_string(), "[::1]:631");
+        assert_eq!(ipv6.remote.to_string(), "[::]:*");
+
+        let mapped = results[2].as_ref().unwrap();
+        assert_eq!(mapped.remote.host, "::ffff:10.0.0.7");
+        assert_eq!(mapped.remote.port, Some(51544));
+        assert_eq!(mapped.processes[0].name, "Web Content");
+
+        let shared = results[3].as_ref().unwrap();
+        assert_eq!(shared.processes.iter().map(|p| p.pid).collect::<Vec<_>>(), vec![1500, 1501]);
+
+        let scoped = results[4].as_ref().unwrap();
+        assert_eq!(scoped.local.host, "fe80::1c2a:3bff:fe4d:5e6f%eth0");
+        assert_eq!(scoped.local.port, Some(546));
+
+        assert!(results[5].as_ref().unwrap().processes.is_empty());
+        assert_eq!(
+            results[6].as_ref().unwrap_err(),
+            &ParseError::InvalidField { field: "pid", value: "x9".to_string() }
+        );
+    }
+
+    #[test]
+    fn test_parse_endpoints() {
+        assert_eq!(parse_endpoint("[::1]:631").unwrap(), SocketEndpoint { host: "::1".to_string(), port: Some(631) });
+        assert_eq!(parse_endpoint(":::22").unwrap().host, "::");
+        let scoped = parse_endpoint("[fe80::1]%eth0:546").unwrap();
+        assert_eq!(scoped.to_string(), "[fe80::1%eth0]:546");
+        assert_eq!(parse_endpoint("*:*").unwrap().port, None);
+        assert!(parse_endpoint("nonsense").is_err());
+    }
+}
@@ -0,0 +1,100 @@
+//! Running scanners through non-interactive sudo
+//!
+//! Used by the chkrootkit, lynis and rkhunter MCP servers. Their stdin is the
+//! MCP stdio transport, so sudo must never stop to prompt for a password:
+//! every call goes through `sudo -n`, which fails instead.
+
+use std::process::{Command, Stdio};
+
+/// Build the command that runs `tool` with `flags`
+///
+/// With `use_sudo`, runs through `sudo -n` so sudo fails instead of prompting
+/// for a password on stdin. Stdin is detached either way so the tool itself
+/// can never block on it.
+pub fn scan_command(tool: &str, flags: &[String], use_sudo: bool) -> Command {
+    let mut cmd = if use_sudo {
+        let mut c = Command::new("sudo");
+        c.arg("-n").arg(tool);
+        c
+    } else {
+        Command::new(tool)
+    };
+    cmd.args(flags).stdin(Stdio::null());
+    cmd
+}
+
+/// Check that `tool` can be run through sudo without a password
+///
+/// The error is sudo's own explanation when it gives one.
+pub fn check_passwordless_sudo(tool: &str) -> Result<(), String> {
+    check_with("sudo", tool)
+}
+
+/// [`check_passwordless_sudo`] through the given sudo binary
+fn check_with(sudo: &str, tool: &str) -> Result<(), String> {
+    let output = Command::new(sudo)
+        .arg("-n")
+        .arg("-l")
+        .arg(tool)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| format!("sudo is not available: {}", err))?;
+
+    if output.status.success() {
+        Ok(())
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(if stderr.is_empty() {
+            format!("sudo does not allow running {} without a password", tool)
+        } else {
+            stderr
+        })
+    }
+}
+
+/// Explanation returned when passwordless sudo is unavailable
+pub fn sudo_required_message(tool: &str, reason: &str) -> String {
+    format!(
+        "Passwordless sudo is required to run {tool} ({reason}).\n\
+         Either:\n\
+         1. Allow it in sudoers, e.g. '<user> ALL=(root) NOPASSWD: /usr/sbin/{tool}', or\n\
+         2. Run this MCP server as root and pass \"use_sudo\": false"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(cmd: &Command) -> Vec<String> {
+        cmd.get_args().map(|arg| arg.to_string_lossy().into_owned()).collect()
+    }
+
+    #[test]
+    fn test_scan_command_uses_non_interactive_sudo() {
+        let flags = vec!["-x".to_string()];
+        let cmd = scan_command("chkrootkit", &flags, true);
+        assert_eq!(cmd.get_program(), "sudo");
+        assert_eq!(args(&cmd), ["-n", "chkrootkit", "-x"]);
+
+        let cmd = scan_command("chkrootkit", &flags, false);
+        assert_eq!(cmd.get_program(), "chkrootkit");
+        assert_eq!(args(&cmd), ["-x"]);
+    }
+
+    #[test]
+    fn test_sudo_check_fails_instead_of_prompting() {
+        // `true` and `false` stand in for a sudo that allows or refuses `sudo -n -l`
+        assert_eq!(check_with("true", "lynis"), Ok(()));
+        assert_eq!(
+            check_with("false", "lynis"),
+            Err("sudo does not allow running lynis without a password".to_string())
+        );
+        let missing = check_with("/nonexistent/sudo", "lynis").unwrap_err();
+        assert!(missing.starts_with("sudo is not available"), "{}", missing);
+
+        let message = sudo_required_message("lynis", "a password is required");
+        assert!(message.contains("NOPASSWD: /usr/sbin/lynis"));
+        assert!(message.contains("\"use_sudo\": false"));
+    }
+}
@@ -19,11 +19,17 @@ use rmcp::model::ErrorData;
 use rmcp::serde_json;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+use std::time::UNIX_EPOCH;
 use std::process::Command;
 use std::sync::Arc;
 use tokio::sync::Mutex;
-use tracing::info;
+use tracing::{info, warn};
+
+
+use tool_common::process::run_bounded;
+use tool_common::ss::parse_ss_line;
 
 #[derive(Clone)]
 pub struct TsharkServer {
@@ -36,8 +42,83 @@ struct PacketStats {
     total_packets: u64,
     protocols: HashMap<String, u64>,
     top_talkers: Vec<(String, u64)>,
-    suspicious_ports: Vec<u16>,
-    duration_seconds: u64,
+    suspicious_ports: Vec<SuspiciousPort>,
+    duration_seconds: f64,
+    packets_per_second: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    baseline: Option<BaselineComparison>,
+}
+
+/// Service side of a flow: the endpoint on the lower port, which is rarely ephemeral
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+struct Endpoint {
+    ip: String,
+    port: u16,
+}
+
+/// Endpoints seen in a known-good capture, cached next to the pcap
+#[derive(Debug, Serialize, Deserialize)]
+struct BaselineFingerprint {
+    source: String,
+    /// Size and mtime of the source pcap, to detect a replaced baseline
+    source_size: u64,
+    source_modified: u64,
+    endpoints: BTreeSet<Endpoint>,
+}
+
+/// An endpoint in the capture that the baseline never saw
+#[derive(Debug, Serialize, Deserialize)]
+struct NewEndpoint {
+    ip: String,
+    port: u16,
+    hit_count: u64,
+    sample_flows: Vec<(String, String)>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BaselineComparison {
+    baseline_file: String,
+    fingerprint_file: String,
+    baseline_endpoints: usize,
+    /// Packets whose endpoint is in the baseline and were left out of the analysis
+    known_packets: u64,
+    new_endpoints: Vec<NewEndpoint>,
+}
+
+/// Known suspicious ports
+const SUSPICIOUS_PORTS: &[u16] = &[
+    4444, 5555, 6666, 7777, 8888, 9999,  // Common malware ports
+    31337, 12345, 54321,                  // Backdoor ports
+    1337, 666,                            // Hacker culture ports
+    6667, 6668, 6669,                     // IRC (potential C2)
+];
+
+/// Maximum distinct (src, dst) flows kept per suspicious port
+const MAX_SAMPLE_FLOWS: usize = 5;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SuspiciousPort {
+    port: u16,
+    hit_count: u64,
+    sample_flows: Vec<(String, String)>,
+}
+
+impl SuspiciousPort {
+    fn new(port: u16) -> Self {
+        Self {
+            port,
+            hit_count: 0,
+            sample_flows: Vec::new(),
+        }
+    }
+
+    fn record(&mut self, src: &str, dst: &str) {
+        self.hit_count += 1;
+        let flow = (src.to_string(), dst.to_string());
+        if self.sample_flows.len() < MAX_SAMPLE_FLOWS && !self.sample_flows.contains(&flow) {
+            self.sample_flows.push(flow);
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -58,7 +139,7 @@ impl TsharkServer {
         }
     }
 
-    #[tool(description = "Capture network traffic for N seconds using tshark. Returns packet summary and saves pcap file.")]
+    #[tool(description = "Capture network traffic for N seconds using tshark. Returns packet summary and saves pcap file. Note: BPF capture filters ('filter') are unreliable on the Linux 'any' pseudo-interface (the default), so when a filter is set without an explicit interface the default route interface is used instead; if none can be found, the capture runs unfiltered and the filter should be applied later via analyze_packets' 'display_filter'.")]
     async fn capture_traffic(
         &self,
         params: serde_json::Map<String, serde_json::Value>,
@@ -70,11 +151,11 @@ impl TsharkServer {
             .get("duration_seconds")
             .and_then(|v| v.as_u64())
             .unwrap_or(60);
-        let interface = params
+        let requested_interface = params
             .get("interface")
             .and_then(|v| v.as_str())
             .unwrap_or("any");
-        let filter = params
+        let mut filter = params
             .get("filter")
             .and_then(|v| v.as_str())
             .unwrap_or("");
@@ -83,10 +164,44 @@ impl TsharkServer {
             .and_then(|v| v.as_str())
             .unwrap_or("/tmp/spai_capture.pcap");
 
+        // BPF filters on the `any` pseudo-interface can silently match nothing
+        let mut warnings = Vec::new();
+        let mut interface = requested_interface.to_string();
+        let explicit_interface = params.contains_key("interface");
+        if interface == "any" && !filter.is_empty() && explicit_interface {
+            warnings.push(
+                "Capture filters are unreliable on interface 'any' and may capture nothing; \
+                 if the capture is empty, name a specific interface or capture unfiltered and \
+                 use analyze_packets' 'display_filter'."
+                    .to_string(),
+            );
+        } else if interface == "any" && !filter.is_empty() {
+            match default_route_interface() {
+                Some(dev) => {
+                    warnings.push(format!(
+                        "Capture filters are unreliable on interface 'any'; capturing on default \
+                         route interface '{}' instead. Pass 'interface' explicitly to override.",
+                        dev
+                    ));
+                    interface = dev;
+                }
+                None => {
+                    warnings.push(format!(
+                        "Capture filters are unreliable on interface 'any' and no default route \
+                         interface was found, so the capture was NOT filtered. Apply the filter \
+                         afterwards with analyze_packets' 'display_filter' (display filter syntax, \
+                         e.g. 'tcp.port == 443' rather than '{}').",
+                        filter
+                    ));
+                    filter = "";
+                }
+            }
+        }
+
         // Build tshark command
         let mut cmd = Command::new("sudo");
         cmd.arg("tshark")
-            .arg("-i").arg(interface)
+            .arg("-i").arg(&interface)
             .arg("-a").arg(format!("duration:{}", duration))
             .arg("-w").arg(output_file);
 
@@ -130,6 +245,9 @@ impl TsharkServer {
         );
 
         let mut content = vec![Content::text(summary)];
+        for warning in &warnings {
+            content.push(Content::text(format!("⚠️ {}", warning)));
+        }
 
         if !stdout.is_empty() {
             content.push(Content::text(format!("stdout: {}", truncate(&stdout, 2000))));
@@ -156,7 +274,7 @@ impl TsharkServer {
         Ok(CallToolResult::success(content))
     }
 
-    #[tool(description = "Analyze a captured pcap file for suspicious patterns. Detects unusual ports, high-frequency connections, and maps to processes.")]
+    #[tool(description = "Analyze a captured pcap file for suspicious patterns. Detects unusual ports, high-frequency connections, and maps to processes. Optional 'display_filter' (tshark -Y syntax) restricts analysis to matching packets. Optional 'baseline_file' (a known-good pcap, or the .fingerprint.json cached next to one) switches to anomaly detection: only (remote IP, port) endpoints absent from the baseline are reported.")]
     async fn analyze_packets(
         &self,
         params: serde_json::Map<String, serde_json::Value>,
@@ -167,10 +285,32 @@ impl TsharkServer {
             .get("pcap_file")
             .and_then(|v| v.as_str())
             .unwrap_or("/tmp/spai_capture.pcap");
+        let display_filter = params
+            .get("display_filter")
+            .and_then(|v| v.as_str())
+            .filter(|f| !f.trim().is_empty());
+        let baseline_file = params
+            .get("baseline_file")
+            .and_then(|v| v.as_str())
+            .filter(|f| !f.trim().is_empty());
+
+        let baseline = match baseline_file.map(load_baseline).transpose() {
+            Ok(baseline) => baseline,
+            Err(err) => {
+                return Ok(CallToolResult::error(vec![Content::text(format!(
+                    "Failed to load baseline: {}",
+                    err
+                ))]));
+            }
+        };
 
         // Read the pcap file with tshark
-        let output = Command::new("tshark")
-            .arg("-r").arg(pcap_file)
+        let mut cmd = Command::new("tshark");
+        cmd.arg("-r").arg(pcap_file);
+        if let Some(display_filter) = display_filter {
+            cmd.arg("-Y").arg(display_filter);
+        }
+        let output = cmd
             .arg("-T").arg("fields")
             .arg("-e").arg("ip.src")
             .arg("-e").arg("ip.dst")
@@ -179,6 +319,7 @@ impl TsharkServer {
             .arg("-e").arg("udp.srcport")
             .arg("-e").arg("udp.dstport")
             .arg("-e").arg("frame.protocols")
+            .arg("-e").arg("frame.time_epoch")
             .arg("-E").arg("separator=|")
             .output();
 
@@ -197,25 +338,46 @@ impl TsharkServer {
         // Parse the output
         let mut protocols: HashMap<String, u64> = HashMap::new();
         let mut ip_counts: HashMap<String, u64> = HashMap::new();
-        let mut suspicious_ports: Vec<u16> = Vec::new();
+        let mut reported_lines: Vec<&str> = Vec::new();
+        let mut new_endpoints: HashMap<Endpoint, NewEndpoint> = HashMap::new();
+        let mut known_packets: u64 = 0;
         let mut total_packets: u64 = 0;
-
-        // Known suspicious ports
-        let suspicious_port_list: Vec<u16> = vec![
-            4444, 5555, 6666, 7777, 8888, 9999,  // Common malware ports
-            31337, 12345, 54321,                  // Backdoor ports
-            1337, 666,                            // Hacker culture ports
-            6667, 6668, 6669,                     // IRC (potential C2)
-        ];
+        let mut first_epoch: Option<f64> = None;
+        let mut last_epoch: Option<f64> = None;
 
         for line in stdout.lines() {
             if line.trim().is_empty() {
                 continue;
             }
 
-            total_packets += 1;
             let parts: Vec<&str> = line.split('|').collect();
 
+            // Baseline mode: traffic to endpoints seen in the known-good capture is not reported
+            if let Some((fingerprint, _)) = &baseline {
+                if let Some(endpoint) = packet_endpoint(&parts) {
+                    if fingerprint.endpoints.contains(&endpoint) {
+                        known_packets += 1;
+                        continue;
+                    }
+                    let src = parts.first().copied().unwrap_or("");
+                    let dst = parts.get(1).copied().unwrap_or("");
+                    let entry = new_endpoints.entry(endpoint.clone()).or_insert_with(|| NewEndpoint {
+                        ip: endpoint.ip,
+                        port: endpoint.port,
+                        hit_count: 0,
+                        sample_flows: Vec::new(),
+                    });
+                    entry.hit_count += 1;
+                    let flow = (src.to_string(), dst.to_string());
+                    if entry.sample_flows.len() < MAX_SAMPLE_FLOWS && !entry.sample_flows.contains(&flow) {
+                        entry.sample_flows.push(flow);
+                    }
+                }
+            }
+
+            total_packets += 1;
+            reported_lines.push(line);
+
             // Count source IPs
             if let Some(src_ip) = parts.first() {
                 if !src_ip.is_empty() {
@@ -223,25 +385,45 @@ impl TsharkServer {
                 }
             }
 
-            // Check for suspicious ports
-            for (idx, part) in parts.iter().enumerate() {
-                if idx >= 2 && idx <= 5 {
-                    if let Ok(port) = part.parse::<u16>() {
-                        if suspicious_port_list.contains(&port) && !suspicious_ports.contains(&port) {
-                            suspicious_ports.push(port);
-                        }
-                    }
-                }
-            }
-
             // Count protocols
-            if let Some(proto_str) = parts.last() {
+            if let Some(proto_str) = parts.get(6) {
                 for proto in proto_str.split(':') {
                     *protocols.entry(proto.to_string()).or_insert(0) += 1;
                 }
             }
+
+            // Track capture time span
+            if let Some(epoch) = parts.get(7).and_then(|t| t.trim().parse::<f64>().ok()) {
+                first_epoch = Some(first_epoch.map_or(epoch, |f| f.min(epoch)));
+                last_epoch = Some(last_epoch.map_or(epoch, |l| l.max(epoch)));
+            }
         }
 
+        // Prefer frame timestamps; fall back to capinfos if they were missing
+        let duration_seconds = match (first_epoch, last_epoch) {
+            (Some(first), Some(last)) => last - first,
+            _ => capinfos_duration(pcap_file).unwrap_or(0.0),
+        };
+        let packets_per_second = packets_per_second(total_packets, duration_seconds);
+
+        let suspicious_ports = suspicious_ports(reported_lines);
+
+        let baseline = baseline.map(|(fingerprint, fingerprint_file)| {
+            let mut new_endpoints: Vec<NewEndpoint> = new_endpoints.into_values().collect();
+            new_endpoints.sort_by(|a, b| {
+                b.hit_count
+                    .cmp(&a.hit_count)
+                    .then_with(|| (&a.ip, a.port).cmp(&(&b.ip, b.port)))
+            });
+            BaselineComparison {
+                baseline_file: fingerprint.source,
+                fingerprint_file,
+                baseline_endpoints: fingerprint.endpoints.len(),
+                known_packets,
+                new_endpoints,
+            }
+        });
+
         // Sort top talkers
         let mut top_talkers: Vec<(String, u64)> = ip_counts.into_iter().collect();
         top_talkers.sort_by(|a, b| b.1.cmp(&a.1));
@@ -251,9 +433,19 @@ impl TsharkServer {
         let mut report = format!(
             "🔍 Packet Analysis Report\n\
              ═══════════════════════════════════════\n\n\
-             📦 Total Packets: {}\n\n",
-            total_packets
+             📦 Total Packets: {}\n\
+             ⏱️ Duration: {:.2}s ({:.1} packets/s)\n\n",
+            total_packets, duration_seconds, packets_per_second
         );
+        if let Some(display_filter) = display_filter {
+            report.push_str(&format!("🔎 Display filter: {}\n\n", display_filter));
+        }
+        if let Some(comparison) = &baseline {
+            report.push_str(&format!(
+                "📐 Baseline: {} ({} known endpoints; {} matching packets excluded below)\n\n",
+                comparison.baseline_file, comparison.baseline_endpoints, comparison.known_packets
+            ));
+        }
 
         // Protocols
         report.push_str("📋 Protocol Distribution:\n");
@@ -270,11 +462,38 @@ impl TsharkServer {
             report.push_str(&format!("  • {}: {} packets\n", ip, count));
         }
 
+        // Endpoints absent from the baseline
+        if let Some(comparison) = &baseline {
+            if comparison.new_endpoints.is_empty() {
+                report.push_str("\n✅ No endpoints outside the baseline\n");
+            } else {
+                report.push_str(&format!(
+                    "\n🆕 {} ENDPOINT(S) NOT IN BASELINE:\n",
+                    comparison.new_endpoints.len()
+                ));
+                for endpoint in comparison.new_endpoints.iter().take(25) {
+                    report.push_str(&format!(
+                        "  🟠 {}:{}: {} packets\n",
+                        endpoint.ip, endpoint.port, endpoint.hit_count
+                    ));
+                    for (src, dst) in &endpoint.sample_flows {
+                        report.push_str(&format!("     {} → {}\n", src, dst));
+                    }
+                }
+            }
+        }
+
         // Suspicious findings
         if !suspicious_ports.is_empty() {
             report.push_str("\n⚠️ SUSPICIOUS PORTS DETECTED:\n");
-            for port in &suspicious_ports {
-                report.push_str(&format!("  🔴 Port {} (known malware/backdoor port)\n", port));
+            for hit in &suspicious_ports {
+                report.push_str(&format!(
+                    "  🔴 Port {} (known malware/backdoor port): {} packets\n",
+                    hit.port, hit.hit_count
+                ));
+                for (src, dst) in &hit.sample_flows {
+                    report.push_str(&format!("     {} → {}\n", src, dst));
+                }
             }
         } else {
             report.push_str("\n✅ No known suspicious ports detected\n");
@@ -297,7 +516,9 @@ impl TsharkServer {
             protocols,
             top_talkers,
             suspicious_ports,
-            duration_seconds: 0, // Would need to parse from pcap
+            duration_seconds,
+            packets_per_second,
+            baseline,
         };
 
         let json_data = serde_json::to_string_pretty(&stats)
@@ -321,8 +542,9 @@ impl TsharkServer {
             .and_then(|v| v.as_str())
             .unwrap_or("/tmp/spai_capture.pcap");
 
-        // Use capinfos for statistics
+        // Use capinfos for statistics (-M prints exact numbers without k/M suffixes)
         let capinfos_output = Command::new("capinfos")
+            .arg("-M")
             .arg(pcap_file)
             .output();
 
@@ -331,6 +553,15 @@ impl TsharkServer {
         match capinfos_output {
             Ok(out) => {
                 let stdout = String::from_utf8_lossy(&out.stdout).to_string();
+                if let Some(duration) = parse_capinfos_duration(&stdout) {
+                    let rate = parse_capinfos_packets(&stdout)
+                        .map(|packets| packets_per_second(packets, duration))
+                        .unwrap_or(0.0);
+                    report.push_str(&format!(
+                        "⏱️ Duration: {:.2}s ({:.1} packets/s)\n\n",
+                        duration, rate
+                    ));
+                }
                 report.push_str(&stdout);
             }
             Err(_) => {
@@ -431,17 +662,19 @@ impl TsharkServer {
         }
 
         // Also run lsof for more detail
-        let lsof_output = Command::new("lsof")
-            .arg("-i")
-            .arg("-n")
-            .arg("-P")
-            .output();
+        let lsof_output = run_bounded(
+            Command::new("lsof").env("LC_ALL", "C").arg("-i").arg("-n").arg("-P"),
+            MAX_OUTPUT_LINES,
+            LSOF_REPORT_BYTES,
+        );
 
         if let Ok(out) = lsof_output {
-            let stdout = String::from_utf8_lossy(&out.stdout).to_string();
-            if !stdout.is_empty() {
+            if !out.stdout.is_empty() {
                 report.push_str("\n📋 lsof Network Files:\n");
-                report.push_str(&truncate(&stdout, 4000));
+                report.push_str(&out.stdout);
+                if out.truncated {
+                    report.push_str("...[truncated]...\n");
+                }
             }
         }
 
@@ -486,6 +719,160 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Suspicious ports hit by `ip.src|ip.dst|tcp.srcport|tcp.dstport|udp.srcport|udp.dstport` lines
+///
+/// Each packet counts once per port, whichever side it is on. Ports are
+/// ranked by hit count, then by port number.
+fn suspicious_ports<'a>(lines: impl IntoIterator<Item = &'a str>) -> Vec<SuspiciousPort> {
+    let mut port_hits: HashMap<u16, SuspiciousPort> = HashMap::new();
+    for line in lines {
+        let parts: Vec<&str> = line.split('|').collect();
+        let src = parts.first().copied().unwrap_or("");
+        let dst = parts.get(1).copied().unwrap_or("");
+        let mut packet_ports: Vec<u16> = parts
+            .iter()
+            .take(6)
+            .skip(2)
+            .filter_map(|part| part.parse::<u16>().ok())
+            .filter(|port| SUSPICIOUS_PORTS.contains(port))
+            .collect();
+        packet_ports.sort_unstable();
+        packet_ports.dedup();
+        for port in packet_ports {
+            port_hits
+                .entry(port)
+                .or_insert_with(|| SuspiciousPort::new(port))
+                .record(src, dst);
+        }
+    }
+
+    let mut suspicious_ports: Vec<SuspiciousPort> = port_hits.into_values().collect();
+    suspicious_ports.sort_by(|a, b| b.hit_count.cmp(&a.hit_count).then(a.port.cmp(&b.port)));
+    suspicious_ports
+}
+
+/// Service endpoint of a packet from `ip.src|ip.dst|tcp.srcport|tcp.dstport|udp.srcport|udp.dstport`
+///
+/// The side on the lower port is taken as the service, so both directions
+/// of a flow map to the same endpoint; ties go to the destination.
+fn packet_endpoint(parts: &[&str]) -> Option<Endpoint> {
+    let src = parts.first().copied().filter(|ip| !ip.is_empty())?;
+    let dst = parts.get(1).copied().filter(|ip| !ip.is_empty())?;
+    let port = |i: usize| parts.get(i).and_then(|p| p.parse::<u16>().ok());
+    let (src_port, dst_port) = match (port(2), port(3)) {
+        (Some(src_port), Some(dst_port)) => (src_port, dst_port),
+        _ => (port(4)?, port(5)?),
+    };
+
+    let (ip, port) = if src_port < dst_port {
+        (src, src_port)
+    } else {
+        (dst, dst_port)
+    };
+    Some(Endpoint {
+        ip: ip.to_string(),
+        port,
+    })
+}
+
+/// Load a baseline fingerprint, building and caching it from a pcap if needed
+///
+/// Returns the fingerprint and the path of its JSON file. A cached
+/// fingerprint is reused while the pcap's size and mtime are unchanged.
+fn load_baseline(baseline_file: &str) -> Result<(BaselineFingerprint, String), String> {
+    if baseline_file.ends_with(".json") {
+        let fingerprint = read_fingerprint(baseline_file)?;
+        return Ok((fingerprint, baseline_file.to_string()));
+    }
+
+    let metadata = std::fs::metadata(baseline_file).map_err(|e| format!("{}: {}", baseline_file, e))?;
+    let source_size = metadata.len();
+    let source_modified = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+
+    let fingerprint_file = format!("{}.fingerprint.json", baseline_file);
+    if Path::new(&fingerprint_file).exists() {
+        if let Ok(fingerprint) = read_fingerprint(&fingerprint_file) {
+            if fingerprint.source_size == source_size && fingerprint.source_modified == source_modified {
+                return Ok((fingerprint, fingerprint_file));
+            }
+        }
+    }
+
+    let output = Command::new("tshark")
+        .arg("-r").arg(baseline_file)
+        .arg("-T").arg("fields")
+        .arg("-e").arg("ip.src")
+        .arg("-e").arg("ip.dst")
+        .arg("-e").arg("tcp.srcport")
+        .arg("-e").arg("tcp.dstport")
+        .arg("-e").arg("udp.srcport")
+        .arg("-e").arg("udp.dstport")
+        .arg("-E").arg("separator=|")
+        .output()
+        .map_err(|e| format!("failed to read {}: {}. Ensure tshark is installed.", baseline_file, e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "tshark could not read {}: {}",
+            baseline_file,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+
+    let endpoints = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| packet_endpoint(&line.split('|').collect::<Vec<_>>()))
+        .collect();
+    let fingerprint = BaselineFingerprint {
+        source: baseline_file.to_string(),
+        source_size,
+        source_modified,
+        endpoints,
+    };
+
+    // A read-only location only costs the cache, not the comparison
+    match serde_json::to_string_pretty(&fingerprint) {
+        Ok(json) => {
+            if let Err(e) = std::fs::write(&fingerprint_file, json) {
+                tracing::warn!("Failed to cache baseline fingerprint {}: {}", fingerprint_file, e);
+            }
+        }
+        Err(e) => tracing::warn!("Failed to serialize baseline fingerprint: {}", e),
+    }
+    Ok((fingerprint, fingerprint_file))
+}
+
+fn read_fingerprint(path: &str) -> Result<BaselineFingerprint, String> {
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    serde_json::from_str(&content).map_err(|e| format!("{} is not a baseline fingerprint: {}", path, e))
+}
+
+/// Interface carrying the default route, via `ip route get`
+fn default_route_interface() -> Option<String> {
+    let output = Command::new("ip")
+        .args(["route", "get", "1.1.1.1"])
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_route_dev(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// Extract the `dev <name>` field from `ip route get` output
+fn parse_route_dev(output: &str) -> Option<String> {
+    let mut fields = output.split_whitespace();
+    while let Some(field) = fields.next() {
+        if field == "dev" {
+            return fields.next().map(str::to_string);
+        }
+    }
+    None
+}
+
 fn extract_packet_count(stderr: &str) -> u64 {
     // tshark reports "X packets captured" in stderr
     let re = Regex::new(r"(\d+)\s+packets?\s+captured").ok();
@@ -499,59 +886,69 @@ fn extract_packet_count(stderr: &str) -> u64 {
     0
 }
 
+/// Capture duration in seconds as reported by `capinfos -u`
+fn capinfos_duration(pcap_file: &str) -> Option<f64> {
+    let output = Command::new("capinfos")
+        .arg("-u")
+        .arg("-M")
+        .arg(pcap_file)
+        .output()
+        .ok()?;
+    parse_capinfos_duration(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn parse_capinfos_duration(output: &str) -> Option<f64> {
+    // "Capture duration:    12.345678 seconds"
+    let re = Regex::new(r"Capture duration:\s+([\d.]+)\s+seconds").ok()?;
+    re.captures(output)?.get(1)?.as_str().parse().ok()
+}
+
+fn parse_capinfos_packets(output: &str) -> Option<u64> {
+    // "Number of packets:   1234" (may contain thousands separators without -M)
+    let re = Regex::new(r"Number of packets:\s+([\d,]+)").ok()?;
+    re.captures(output)?.get(1)?.as_str().replace(',', "").parse().ok()
+}
+
+fn packets_per_second(packets: u64, duration_seconds: f64) -> f64 {
+    if duration_seconds > 0.0 {
+        packets as f64 / duration_seconds
+    } else {
+        0.0
+    }
+}
+
 fn get_network_connections() -> Vec<ProcessConnection> {
     let mut connections = Vec::new();
 
     // Use ss to get connections with process info
-    let output = Command::new("ss")
-        .arg("-tunap")
-        .output();
+    let output = run_bounded(
+        Command::new("ss").env("LC_ALL", "C").arg("-tunap"),
+        MAX_OUTPUT_LINES,
+        MAX_OUTPUT_BYTES,
+    );
 
     if let Ok(out) = output {
-        let stdout = String::from_utf8_lossy(&out.stdout).to_string();
-
-        for line in stdout.lines().skip(1) {
-            // Parse ss output
-            let parts: Vec<&str> = line.split_whitespace().collect();
-            if parts.len() >= 6 {
-                let state = parts[1].to_string();
-                let local_addr = parts[4].to_string();
-                let remote_addr = parts[5].to_string();
-
-                // Extract PID from users:((...)pid=XXXX,...)
-                let mut pid: u32 = 0;
-                let mut process_name = String::from("unknown");
-
-                if let Some(users_part) = parts.get(6) {
-                    let pid_re = Regex::new(r"pid=(\d+)").ok();
-                    let name_re = Regex::new(r#"\("([^"]+)""#).ok();
-
-                    if let Some(regex) = pid_re {
-                        if let Some(caps) = regex.captures(users_part) {
-                            if let Some(pid_str) = caps.get(1) {
-                                pid = pid_str.as_str().parse().unwrap_or(0);
-                            }
-                        }
-                    }
-
-                    if let Some(regex) = name_re {
-                        if let Some(caps) = regex.captures(users_part) {
-                            if let Some(name) = caps.get(1) {
-                                process_name = name.as_str().to_string();
-                            }
-                        }
-                    }
-                }
-
-                if pid > 0 {
-                    connections.push(ProcessConnection {
-                        pid,
-                        process_name,
-                        local_addr,
-                        remote_addr,
-                        state,
-                    });
+        if out.stdout.is_empty() && !out.stderr.is_empty() {
+            warn!("ss printed nothing: {}", out.stderr.trim());
+        }
+        for line in out.stdout.lines().skip(1) {
+            let socket = match parse_ss_line(line) {
+                Ok(socket) => socket,
+                Err(e) => {
+                    warn!("Skipping ss line ({}): {}", e, line);
+                    continue;
                 }
+            };
+
+            // A socket shared by several processes counts once for each
+            for owner in socket.processes {
+                connections.push(ProcessConnection {
+                    pid: owner.pid,
+                    process_name: owner.name,
+                    local_addr: socket.local.to_string(),
+                    remote_addr: socket.remote.to_string(),
+                    state: socket.state.clone(),
+                });
             }
         }
     }
@@ -559,6 +956,13 @@ fn get_network_connections() -> Vec<ProcessConnection> {
     connections
 }
 
+/// Line limit for streamed command output
+const MAX_OUTPUT_LINES: usize = 20_000;
+/// Byte limit for streamed command output
+const MAX_OUTPUT_BYTES: usize = 2 * 1024 * 1024;
+/// Byte limit for the lsof section of the process report
+const LSOF_REPORT_BYTES: usize = 4000;
+
 fn truncate(input: &str, limit: usize) -> String {
     if input.len() <= limit {
         return input.to_string();
@@ -568,3 +972,197 @@ fn truncate(input: &str, limit: usize) -> String {
     truncated.push_str("\n...[truncated]...");
     truncated
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_route_dev() {
+        // Default route through a gateway
+        let via_gateway = "1.1.1.1 via 192.168.1.1 dev wlp3s0 src 192.168.1.23 uid 1000 \n    cache \n";
+        assert_eq!(parse_route_dev(via_gateway).as_deref(), Some("wlp3s0"));
+
+        // No route: ip prints nothing on stdout, or a truncated line
+        assert_eq!(parse_route_dev(""), None);
+        assert_eq!(parse_route_dev("1.1.1.1 via 10.0.0.1 dev"), None);
+
+        // Several routes: the first one is the one ip would use
+        let multiple = "1.1.1.1 via 10.8.0.1 dev tun0 table 51820 src 10.8.0.2 uid 0 \n    cache \n\
+                        1.1.1.1 via 192.168.1.1 dev eth0 src 192.168.1.23 uid 0 \n    cache \n";
+        assert_eq!(parse_route_dev(multiple).as_deref(), Some("tun0"));
+    }
+
+    /// `capinfos -M` output for a short capture
+    const CAPINFOS: &str = "\
+File name:           /tmp/capture.pcap
+File type:           Wireshark/tcpdump/... - pcap
+File encapsulation:  Ethernet
+File timestamp precision:  microseconds (6)
+Packet size limit:   file hdr: 262144 bytes
+Number of packets:   1234
+File size:           456789 bytes
+Data size:           437013 bytes
+Capture duration:    12.345678 seconds
+First packet time:   2024-05-01 10:00:00.000000
+Last packet time:    2024-05-01 10:00:12.345678
+Data byte rate:      35398.21 bytes/s
+Average packet rate: 99.95 packets/s
+";
+
+    #[test]
+    fn test_parse_capinfos() {
+        assert_eq!(parse_capinfos_packets(CAPINFOS), Some(1234));
+        assert_eq!(parse_capinfos_duration(CAPINFOS), Some(12.345678));
+        let rate = packets_per_second(1234, 12.345678);
+        assert!((rate - 99.95).abs() < 0.01, "{}", rate);
+
+        // `capinfos -u` prints only the duration
+        let duration_only = "File name:           /tmp/empty.pcap\nCapture duration:    0.000000 seconds\n";
+        assert_eq!(parse_capinfos_duration(duration_only), Some(0.0));
+        assert_eq!(parse_capinfos_packets(duration_only), None);
+        assert_eq!(packets_per_second(10, 0.0), 0.0);
+
+        // Without -M, large counts carry thousands separators
+        assert_eq!(parse_capinfos_packets("Number of packets:   1,048,576\n"), Some(1_048_576));
+
+        // Error output from a file capinfos can't read
+        let error = "capinfos: The file \"/tmp/x.pcap\" isn't a capture file in a format capinfos understands.";
+        assert_eq!(parse_capinfos_duration(error), None);
+        assert_eq!(parse_capinfos_packets(error), None);
+    }
+
+    #[test]
+    fn test_suspicious_ports() {
+        let lines = [
+            // 4444 on either side, over TCP and UDP, merged into one entry
+            "10.0.0.5|203.0.113.9|51000|4444||",
+            "203.0.113.9|10.0.0.5|4444|51000||",
+            "10.0.0.6|203.0.113.9|||52000|4444",
+            // Same port on both sides counts once
+            "10.0.0.7|203.0.113.9|4444|4444||",
+            // Two suspicious ports in one packet count for both
+            "10.0.0.5|203.0.113.10|31337|6667||",
+            // 1337 ties 31337 and 6667; 8888 has the fewest hits
+            "10.0.0.5|203.0.113.11|50000|1337||",
+            "10.0.0.5|203.0.113.12|50000|8888||",
+            // Unlisted ports, unparsable fields and blank lines are ignored
+            "10.0.0.5|198.51.100.1|50000|443||",
+            "10.0.0.5|198.51.100.1|not-a-port|||",
+            "",
+        ];
+        let ranked = suspicious_ports(lines);
+        let ports: Vec<(u16, u64)> = ranked.iter().map(|p| (p.port, p.hit_count)).collect();
+        assert_eq!(ports, [(4444, 4), (1337, 1), (6667, 1), (8888, 1), (31337, 1)]);
+        assert_eq!(
+            ranked[0].sample_flows,
+            [
+                ("10.0.0.5".to_string(), "203.0.113.9".to_string()),
+                ("203.0.113.9".to_string(), "10.0.0.5".to_string()),
+                ("10.0.0.6".to_string(), "203.0.113.9".to_string()),
+                ("10.0.0.7".to_string(), "203.0.113.9".to_string()),
+            ]
+        );
+
+        // Flows are deduplicated and capped; every packet is still counted
+        let flows: Vec<String> = (0..10)
+            .flat_map(|i| {
+                let line = format!("10.0.0.{}|203.0.113.9|5000{}|4444||", i, i);
+                [line.clone(), line]
+            })
+            .collect();
+        let ranked = suspicious_ports(flows.iter().map(String::as_str));
+        assert_eq!(ranked[0].hit_count, 20);
+        assert_eq!(ranked[0].sample_flows.len(), MAX_SAMPLE_FLOWS);
+        assert_eq!(ranked[0].sample_flows[1], ("10.0.0.1".to_string(), "203.0.113.9".to_string()));
+    }
+
+    fn endpoint(ip: &str, port: u16) -> Option<Endpoint> {
+        Some(Endpoint {
+            ip: ip.to_string(),
+            port,
+        })
+    }
+
+    #[test]
+    fn test_packet_endpoint() {
+        // Both directions of a TCP flow map to the service side
+        let request = ["10.0.0.5", "93.184.216.34", "51234", "443", "", ""];
+        let reply = ["93.184.216.34", "10.0.0.5", "443", "51234", "", ""];
+        assert_eq!(packet_endpoint(&request), endpoint("93.184.216.34", 443));
+        assert_eq!(packet_endpoint(&reply), endpoint("93.184.216.34", 443));
+
+        // Equal ports go to the destination
+        assert_eq!(packet_endpoint(&["10.0.0.1", "10.0.0.2", "123", "123", "", ""]), endpoint("10.0.0.2", 123));
+
+        // UDP-only packets use the UDP ports
+        assert_eq!(packet_endpoint(&["10.0.0.5", "1.1.1.1", "", "", "40000", "53"]), endpoint("1.1.1.1", 53));
+        assert_eq!(packet_endpoint(&["1.1.1.1", "10.0.0.5", "", "", "53", "40000"]), endpoint("1.1.1.1", 53));
+
+        // Missing addresses or ports
+        assert_eq!(packet_endpoint(&["", "1.1.1.1", "40000", "53", "", ""]), None);
+        assert_eq!(packet_endpoint(&["10.0.0.5", "", "40000", "53", "", ""]), None);
+        assert_eq!(packet_endpoint(&["10.0.0.5", "1.1.1.1", "", "", "", ""]), None);
+        assert_eq!(packet_endpoint(&["10.0.0.5", "1.1.1.1", "40000"]), None);
+        assert_eq!(packet_endpoint(&["10.0.0.5", "1.1.1.1"]), None);
+        assert_eq!(packet_endpoint(&[]), None);
+    }
+
+    fn fingerprint(source: &str, source_size: u64, source_modified: u64, ip: &str) -> BaselineFingerprint {
+        BaselineFingerprint {
+            source: source.to_string(),
+            source_size,
+            source_modified,
+            endpoints: endpoint(ip, 443).into_iter().collect(),
+        }
+    }
+
+    #[test]
+    fn test_load_baseline_reuses_fresh_fingerprint() {
+        let dir = std::env::temp_dir().join(format!("tshark-mcp-baseline-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let pcap = dir.join("baseline.pcap");
+        std::fs::write(&pcap, b"not really a pcap").unwrap();
+        let pcap = pcap.to_str().unwrap().to_string();
+        let fingerprint_file = format!("{}.fingerprint.json", pcap);
+
+        let metadata = std::fs::metadata(&pcap).unwrap();
+        let modified = metadata
+            .modified()
+            .unwrap()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let write = |fingerprint: &BaselineFingerprint| {
+            std::fs::write(&fingerprint_file, serde_json::to_string(fingerprint).unwrap()).unwrap();
+        };
+
+        // Matching size and mtime: the cached fingerprint is used without running tshark
+        write(&fingerprint(&pcap, metadata.len(), modified, "192.0.2.1"));
+        let (loaded, file) = load_baseline(&pcap).unwrap();
+        assert_eq!(file, fingerprint_file);
+        assert!(loaded.endpoints.contains(&endpoint("192.0.2.1", 443).unwrap()));
+
+        // A JSON path is read directly
+        let (loaded, file) = load_baseline(&fingerprint_file).unwrap();
+        assert_eq!(file, fingerprint_file);
+        assert_eq!(loaded.source, pcap);
+
+        // A replaced pcap (size or mtime changed) is fingerprinted again; the
+        // file is not a capture, so the rebuild fails instead of reusing the cache
+        for stale in [
+            fingerprint(&pcap, metadata.len() + 1, modified, "192.0.2.2"),
+            fingerprint(&pcap, metadata.len(), modified + 1, "192.0.2.3"),
+        ] {
+            write(&stale);
+            assert!(load_baseline(&pcap).is_err());
+        }
+
+        // So is an unreadable cache
+        std::fs::write(&fingerprint_file, "{").unwrap();
+        assert!(load_baseline(&pcap).is_err());
+        assert!(load_baseline(&fingerprint_file).unwrap_err().contains("is not a baseline fingerprint"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}